@@ -0,0 +1,146 @@
+# English messages for PiCode, also the fallback for every other locale.
+#
+# Translations live in `<locale>.ftl` files with the same keys, either next to
+# this one or in `~/.config/picode/locales` and `.picode/locales` of a
+# workspace. Placeholders are written `{ $name }`; a key missing from a
+# translation falls back to English.
+
+## Interactive mode
+
+interactive-title = PiCode Interactive Mode
+interactive-daemon-index = Daemon index: { $files } files, { $symbols } symbols
+interactive-commands = Available slash commands:
+interactive-palette-hint = Press Ctrl-P then Enter to search commands, key bindings and recent actions
+interactive-prompt = picode>{" "}
+interactive-help-title = PiCode Help:
+interactive-help-tagline = Interactive terminal workspace with AI assistance
+interactive-unknown-command = Unknown command: { $command }. Type /help for available commands.
+interactive-analyzing = Analyzing project structure...
+interactive-escape-handling = Output escape handling: { $policy }
+interactive-goodbye = Goodbye!
+interactive-error = { $what } error: { $error }
+interactive-context-stale = [stale context: { $count }]{" "}
+interactive-watch-polling = [polling { $seconds }s]{" "}
+interactive-watch-fallback = File change notifications are unavailable ({ $reason }); pinned files are polled instead.
+interactive-context-stale-notice = Pinned files changed on disk: { $files }. Press Ctrl-R then Enter to refresh them.
+interactive-context-refreshed = Refreshed { $count } pinned file(s); the changes are sent with the next prompt
+interactive-context-current = Pinned files are up to date
+interactive-broadcast = [broadcast → { $panes }]{" "}
+interactive-broadcast-on = Typed lines now run in every one of: { $panes }. /broadcast off returns to the chat.
+interactive-broadcast-off = Broadcast is off; typed lines go to the chat
+
+## Slash commands (`slash-<name>-summary` and `slash-<name>-help`)
+
+slash-help-summary = Show help information
+slash-help-help = Lists every slash command. With a command name, shows that command's full help.
+slash-palette-summary = Open the command palette (Ctrl-P)
+slash-palette-help =
+    Fuzzy search over slash commands, key bindings and recently used actions.
+    Enter runs the selection, Esc closes the palette.
+slash-analyze-summary = Analyze current project
+slash-analyze-help = Scans the workspace and prints lines per language, the test/source ratio and the frameworks in use.
+slash-todos-summary = List the TODO, FIXME and HACK comments
+slash-todos-help =
+    Rescans the workspace and lists the open items with their id, location and owner, as
+    `TODO(alice)` or `TODO @alice` names them. Items whose comment is gone are marked done;
+    `done <id> [note]` marks one done by hand and `all` includes finished items.
+slash-queue-summary = Show the follow-ups queued during a reply
+slash-queue-help =
+    Lines typed and submitted with Enter while a reply streams are queued and sent, in order,
+    once it finishes; unsubmitted text pre-fills the next prompt. Both are saved with the session.
+    `send` sends the queue now, e.g. after resuming a session, and `clear` drops it.
+slash-edit-summary = Open a file in the modal editor
+slash-edit-help =
+    Opens <path> (relative to the workspace root) in the modal editor.
+    :w saves, :q quits; saves never overwrite changes made on disk meanwhile.
+    With --chat, a chat about the file opens next to the editor: prompts include the file
+    (Ctrl-T: only the visible lines) and Ctrl-A applies the edits the reply proposes.
+slash-open-summary = Edit a file in your external editor
+slash-open-help =
+    Opens <path> at the given line in [editor] external, $VISUAL or $EDITOR and waits for the save;
+    the new content is added to the conversation and pinned copies are refreshed.
+slash-timeline-summary = Show every version of a file edited in this session
+slash-timeline-help =
+    Lists the versions of <file>: its initial content, each saved edit and the current content
+    when it changed since. `diff` compares two versions (the newest by default) and `restore`
+    writes an earlier version back as a new edit. Without a file, lists the edited files.
+slash-run-summary = Run a command and attach its output to the next prompt
+slash-run-help =
+    Runs <cmd> in a shell at the workspace root and shows the output in an output pane.
+    A summary of the output goes with your next prompt unless you pass --no-attach;
+    `clear` drops attachments that have not been sent yet.
+slash-broadcast-summary = Type into several terminal panes at once
+slash-broadcast-help =
+    Sends every line you type, instead of to the chat, to the selected terminal panes of the
+    session template, where it runs in each pane's directory at the same time; like tmux's
+    synchronize-panes. Without arguments, toggles broadcast to all terminal panes; name panes
+    by title to select only those. The prompt shows the panes while broadcast is on.
+slash-raw-summary = Toggle raw view of escape sequences in output
+slash-raw-help = Switches between sanitized output and the raw escape sequences programs emitted.
+slash-context-summary = Show what fills the context window and prune it
+slash-context-help =
+    `show` breaks the next request down into system prompt, pinned items, file chunks and
+    history with token counts and percentages; `pin` keeps a file in every request and `drop`
+    removes the numbered items. `pin <path>:<start>-<end>` pins only those lines and
+    `pin <path>#<symbol>` only a definition; large files can only be pinned this way.
+    Pinned files that change on disk mark the prompt stale; `refresh` (Ctrl-R) re-reads them
+    and sends only the changes with the next prompt.
+slash-pack-summary = Attach a named context pack
+slash-pack-help =
+    Packs bundle file globs, a description and notes under a name in [packs.<name>] of the
+    configuration or a workspace picode.toml. A name pins the pack's files and notes into the
+    conversation, replacing an earlier attach of it; `drop` removes them and `list` shows the
+    packs with the ones attached.
+slash-tag-summary = Tag this conversation
+slash-tag-help =
+    Adds tags to the conversation (`rm` removes them) and saves it with the session, so
+    `picode session history --tag <tag>` finds it later.
+slash-note-summary = Add a note to the latest exchange
+slash-note-help =
+    Attaches <text> to the latest message; `#words` in the note tag that exchange, which
+    `picode session export --tag` uses to pick exchanges.
+slash-retry-summary = Regenerate the last response and show what changed
+slash-retry-help =
+    Asks the last prompt again and replaces the response, printing a word-level diff against
+    the previous one. `picode session export --response-diffs` includes these diffs.
+slash-preset-summary = Show or switch the generation preset
+slash-preset-help =
+    Presets bundle temperature, top_p, seed and reply length under a name: precise (the
+    default), creative and deterministic are built in, and [presets.<name>] in the
+    configuration or a workspace picode.toml adds more. `list` shows them all; a name
+    switches the chat, /retry and /edit --chat to that preset.
+slash-image-summary = Show an image inline
+slash-image-help =
+    Draws the image in terminals with the kitty or iTerm2 image protocol and describes it
+    elsewhere. Mermaid and Graphviz diagrams in replies are drawn the same way.
+slash-bookmark-summary = Manage bookmarks
+slash-bookmark-help =
+    Bookmarks remember locations in the workspace. `add` stores one with an optional label,
+    `find` searches labels and paths, `rm` deletes one.
+slash-files-summary = Show the workspace file tree and its locks
+slash-files-help =
+    Lists the files under [dir], or the whole workspace, as a tree. Files another session or
+    agent run is editing are marked 🔒 with the lock holder and the time left on its lease.
+slash-model-summary = List provider models or switch this pane's model
+slash-model-help =
+    `list` shows the cached model catalog, `refresh` queries providers again, and a number
+    or provider/model switches the chat pane's model for the rest of the conversation.
+slash-health-summary = Show the health of every configured provider
+slash-health-help =
+    Lists each provider's last health check, error rate, average latency, rate-limit state and
+    spend so far today. `check` runs a fresh health check against every provider first.
+slash-system-summary = Inspect or override the system prompt
+slash-system-help =
+    `show` prints every system prompt layer and its source; `pane <text>` overrides the
+    prompt for this pane only and `pane clear` removes the override.
+slash-exit-summary = Exit interactive mode
+slash-exit-help = Ends the interactive session.
+
+## Command palette
+
+palette-title = Command palette
+palette-help = Help
+palette-count = { $shown } of { $total }
+palette-tag-recent = recent
+palette-tag-command = command
+palette-tag-key = key
@@ -36,6 +36,10 @@ pub struct Args {
     #[arg(long, global = true)]
     pub no_color: bool,
 
+    /// Configuration profile to use (e.g. work, personal, offline)
+    #[arg(long, global = true, env = "PICODE_PROFILE")]
+    pub profile: Option<String>,
+
     /// The subcommand to run
     #[command(subcommand)]
     pub command: Commands,
@@ -80,6 +84,10 @@ pub enum Commands {
         /// Session name for workspace isolation
         #[arg(short, long)]
         session: Option<String>,
+
+        /// Session template from .picode/sessions/templates to boot from
+        #[arg(short, long)]
+        template: Option<String>,
     },
 
     /// Execute a command with AI assistance
@@ -99,6 +107,25 @@ pub enum Commands {
         dry_run: bool,
     },
 
+    /// Answer a prompt and print the reply; piped stdin is attached as context
+    #[command(short_flag = 'p', long_flag = "print")]
+    Print {
+        /// The prompt; `{{stdin}}` is replaced with the piped input
+        prompt: String,
+
+        /// Attach piped input verbatim instead of summarizing it
+        #[arg(long)]
+        raw_stdin: bool,
+
+        /// Write the reply to this file instead of stdout
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+
+        /// Write the reply to the output file as it is generated, without holding it in memory
+        #[arg(long, requires = "output_file")]
+        stream: bool,
+    },
+
     /// Manage project configurations and settings
     Config {
         #[command(subcommand)]
@@ -128,6 +155,383 @@ pub enum Commands {
         #[command(subcommand)]
         action: DevAction,
     },
+
+    /// Hook management and community hook index
+    Hooks {
+        #[command(subcommand)]
+        action: HooksAction,
+    },
+
+    /// Agent run inspection
+    Agent {
+        #[command(subcommand)]
+        action: AgentAction,
+    },
+
+    /// Background indexing daemon
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonAction,
+    },
+
+    /// Stored session management
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+
+    /// Explain a file or line range (e.g. src/main.rs:10-40)
+    Explain {
+        /// File path, optionally followed by :LINE or :START-END
+        target: String,
+
+        /// How detailed the explanation should be
+        #[arg(short, long, value_enum, default_value_t = ExplainDepth::Standard)]
+        depth: ExplainDepth,
+    },
+
+    /// Open a file in your external editor and pick up the saved changes
+    Open {
+        /// File path, optionally followed by :LINE or :LINE:COL
+        target: String,
+    },
+
+    /// Work with diffs
+    Diff {
+        #[command(subcommand)]
+        action: DiffAction,
+    },
+
+    /// Run PiCode as an HTTP server (exposes /metrics and /health)
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        bind: std::net::SocketAddr,
+
+        /// Account store (defaults to ~/.picode/serve-users.json)
+        #[arg(long)]
+        users_file: Option<PathBuf>,
+
+        /// Manage serve-mode accounts instead of starting the server
+        #[command(subcommand)]
+        action: Option<ServeAction>,
+    },
+
+    /// Serve PiCode to editors over the Model Context Protocol (stdio by default)
+    Mcp {
+        /// Listen on this Unix socket (named pipe on Windows) and bridge every client to one session
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// Whether socket clients share one conversation or each get their own
+        #[arg(long, value_enum, default_value_t = McpIsolation::Shared)]
+        isolation: McpIsolation,
+    },
+
+    /// Benchmarking harnesses
+    Bench {
+        #[command(subcommand)]
+        action: BenchAction,
+    },
+
+    /// Named workspace tasks from the `[tasks]` section of picode.toml
+    Task {
+        #[command(subcommand)]
+        action: TaskAction,
+    },
+
+    /// Run golden prompt tests and fail when an assertion does not hold
+    Eval {
+        /// Eval suite file (YAML)
+        suite: PathBuf,
+        /// Providers to evaluate, as provider, provider/model or mock (defaults to the configured provider)
+        #[arg(short, long = "provider", value_delimiter = ',')]
+        providers: Vec<String>,
+        /// Only run cases whose name contains this
+        #[arg(long)]
+        filter: Option<String>,
+        /// Write a JUnit XML report to this file
+        #[arg(long)]
+        junit: Option<PathBuf>,
+    },
+
+    /// Sync sessions, bookmarks and prompt templates with the `[sync]` remote (encrypted)
+    Sync {
+        /// Show what would change without transferring anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Project statistics
+    Stats {
+        #[command(subcommand)]
+        action: StatsAction,
+    },
+
+    /// List the TODO, FIXME and HACK comments of the workspace
+    Todos {
+        /// Print the tracked items as JSON
+        #[arg(long)]
+        json: bool,
+        /// Include items already done
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Review edits an agent staged with `agent run --review`
+    Review {
+        #[command(subcommand)]
+        action: ReviewAction,
+    },
+
+    /// Decide whether workspaces may run hooks, tasks and PICODE.md instructions
+    Trust {
+        #[command(subcommand)]
+        action: TrustAction,
+    },
+
+    /// Release preparation: changelog entries and announcements
+    Release {
+        #[command(subcommand)]
+        action: ReleaseAction,
+    },
+
+    /// Security audits of the workspace
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+
+    /// Start on an issue: fetch it, create its branch and open a session seeded with it
+    WorkOn {
+        /// Issue URL, number (`42`, `#42`) or `owner/repo#42`
+        issue: String,
+        /// Session template to open (defaults to `[forge] template` when it exists)
+        #[arg(long)]
+        template: Option<String>,
+        /// Create the branch from this ref instead of the current HEAD
+        #[arg(long)]
+        base: Option<String>,
+    },
+}
+
+/// Release subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum ReleaseAction {
+    /// Draft a CHANGELOG section and a release announcement from the commits since a tag, staged for review
+    Notes {
+        /// Tag or revision of the previous release
+        #[arg(long)]
+        since: String,
+        /// Last revision of the release
+        #[arg(long, default_value = "HEAD")]
+        until: String,
+        /// Version of the release; the section is headed Unreleased without one
+        #[arg(long = "release-version")]
+        version: Option<String>,
+        /// Changelog the section is added to
+        #[arg(long, default_value = "CHANGELOG.md")]
+        changelog: PathBuf,
+        /// File the announcement is written to
+        #[arg(long, default_value = "RELEASE_NOTES.md")]
+        announcement: PathBuf,
+    },
+}
+
+/// Audit subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum AuditAction {
+    /// Look up the dependencies pinned by the workspace's lockfiles in the OSV advisory databases
+    Deps {
+        /// Print the report as JSON instead of markdown
+        #[arg(long)]
+        json: bool,
+        /// Write the report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Ask the model for an upgrade plan fixing the findings
+        #[arg(long)]
+        plan: bool,
+        /// Exit with an error when a finding is at least this severe
+        #[arg(long, value_enum)]
+        fail_on: Option<AuditSeverity>,
+    },
+}
+
+/// Advisory severities `--fail-on` accepts
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// Review subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum ReviewAction {
+    /// List the pending edits
+    List,
+    /// Write the pending edits as a git patch
+    Export {
+        /// Patch file to write (stdout if omitted)
+        #[arg(long)]
+        patch: Option<PathBuf>,
+    },
+    /// Replace the pending edits with a reviewed patch; files left out of it are dropped
+    Import {
+        /// Patch file to read
+        #[arg(long)]
+        patch: PathBuf,
+    },
+    /// Write every pending edit to the workspace
+    Apply,
+}
+
+/// Workspace trust subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum TrustAction {
+    /// Trust a workspace (the current directory if omitted)
+    Allow {
+        path: Option<PathBuf>,
+    },
+    /// Remember a workspace as untrusted without asking again
+    Deny {
+        path: Option<PathBuf>,
+    },
+    /// Forget the decision for a workspace so it is asked about again
+    Forget {
+        path: Option<PathBuf>,
+    },
+    /// List trust decisions
+    List,
+}
+
+/// Statistics subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum StatsAction {
+    /// Lines per language, test/source ratio and detected frameworks
+    Workspace {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Workspace task subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum TaskAction {
+    /// List the defined tasks
+    List,
+    /// Run a task by name
+    Run {
+        /// Task name (e.g. test, lint, build)
+        name: String,
+        /// Extra arguments appended to the task's command
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+}
+
+/// Benchmark subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum BenchAction {
+    /// Run a prompt suite against providers and compare latency, throughput and failures
+    Llm {
+        /// Providers to compare, as provider or provider/model (defaults to the configured provider)
+        #[arg(short, long = "provider", value_delimiter = ',')]
+        providers: Vec<String>,
+        /// Prompt suite file (YAML or JSON); a small built-in suite is used otherwise
+        #[arg(long)]
+        suite: Option<PathBuf>,
+        /// Times each prompt is sent to each provider
+        #[arg(short = 'n', long, default_value_t = 3)]
+        iterations: usize,
+        /// Score answers 0-10 with this judge model (provider or provider/model)
+        #[arg(long)]
+        judge: Option<String>,
+        /// Print the results as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Measure core operations and compare them with a saved baseline
+    Core {
+        /// Operations to measure (defaults to all of them)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        only: Vec<CoreBenchKind>,
+        /// Baseline to compare with, stored under .picode/bench/baselines
+        #[arg(long, default_value = "main")]
+        baseline: String,
+        /// Store this run as the baseline instead of failing on regressions
+        #[arg(long)]
+        save_baseline: bool,
+        /// Slowdown in percent that counts as a regression
+        #[arg(long, default_value_t = 10.0)]
+        threshold: f64,
+        /// Fewer samples and a shorter measurement, for a rough check
+        #[arg(long)]
+        quick: bool,
+    },
+}
+
+/// Operations measured by `picode bench core`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoreBenchKind {
+    WorkspaceScan,
+    EventPublish,
+    ContextBuild,
+    SessionPersist,
+}
+
+/// Diff subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum DiffAction {
+    /// Summarize a diff and call out risks (reads stdin unless --file or --range is given)
+    Explain {
+        /// Read the diff from a file
+        #[arg(long, conflicts_with = "range")]
+        file: Option<PathBuf>,
+        /// Diff a git range instead (e.g. main..HEAD)
+        #[arg(long)]
+        range: Option<String>,
+        /// Format the result as a pull request description
+        #[arg(long)]
+        pr: bool,
+    },
+}
+
+/// Serve-mode account management
+#[derive(Subcommand, Debug, Clone)]
+pub enum ServeAction {
+    /// Create an account and print its token
+    AddUser {
+        /// User name
+        name: String,
+        /// Grant access to the admin API
+        #[arg(long)]
+        admin: bool,
+        /// Maximum USD per request
+        #[arg(long)]
+        per_request_limit: Option<f64>,
+        /// Maximum USD per day
+        #[arg(long)]
+        daily_limit: Option<f64>,
+        /// Maximum USD per month
+        #[arg(long)]
+        monthly_limit: Option<f64>,
+    },
+    /// Issue an additional token for an account
+    Token {
+        /// User name
+        name: String,
+    },
+    /// Revoke every token of an account
+    Revoke {
+        /// User name
+        name: String,
+    },
+    /// List accounts with their spend
+    Users,
 }
 
 /// Configuration management subcommands
@@ -158,6 +562,59 @@ pub enum ConfigAction {
         #[arg(short, long)]
         confirm: bool,
     },
+    /// Manage named configuration profiles
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    /// Export or import a shareable bundle of configuration and hooks
+    Bundle {
+        #[command(subcommand)]
+        action: BundleAction,
+    },
+}
+
+/// Configuration bundle subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum BundleAction {
+    /// Write configuration (without secrets), hooks, prompt templates,
+    /// themes and policies to a single file
+    Export {
+        /// Bundle file to write
+        #[arg(short, long, default_value = "picode-bundle.json")]
+        output: PathBuf,
+    },
+    /// Validate a bundle and install it
+    Import {
+        /// Bundle file to read
+        path: PathBuf,
+        /// Overwrite files that differ from the bundle
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+/// Configuration profile subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum ProfileAction {
+    /// Create a new profile
+    Create {
+        /// Profile name
+        name: String,
+        /// Copy settings from an existing profile
+        #[arg(long)]
+        from: Option<String>,
+        /// Profile description
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// List configured profiles
+    List,
+    /// Switch the default profile
+    Switch {
+        /// Profile name
+        name: String,
+    },
 }
 
 /// Git integration subcommands
@@ -180,6 +637,11 @@ pub enum GitAction {
         /// Include all changes
         #[arg(short, long)]
         all: bool,
+        /// Files to commit; submodules and nested repositories are committed separately
+        paths: Vec<PathBuf>,
+        /// Commit even when a `pre_commit` hook vetoes it in its dry run
+        #[arg(long)]
+        override_hooks: bool,
     },
     /// Analyze repository health and suggest improvements
     Analyze {
@@ -190,6 +652,43 @@ pub enum GitAction {
         #[arg(short, long)]
         focus: Option<GitFocus>,
     },
+    /// Find the commit that introduced a bug with `git bisect`, judging each step
+    BisectAssist {
+        /// The bug being hunted
+        description: String,
+        /// A commit known to be free of the bug
+        #[arg(long)]
+        good: String,
+        /// A commit known to have the bug
+        #[arg(long, default_value = "HEAD")]
+        bad: String,
+        /// Check run at each step; exit 0 is good, 125 skips (defaults to the `test` task)
+        #[arg(long)]
+        check: Option<String>,
+        /// Let the model judge the check's output instead of its exit status
+        #[arg(long)]
+        judge: bool,
+    },
+    /// Split the working tree's changes into a series of logical commits
+    SplitCommit {
+        /// Let the model group the hunks instead of grouping them by file proximity and symbols
+        #[arg(long)]
+        judge: bool,
+        /// Show the proposed commits without committing
+        #[arg(long)]
+        dry_run: bool,
+        /// Make every proposed commit without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Walk through merge conflicts one by one and stage the resolved files
+    Resolve {
+        /// Conflicted files to resolve (defaults to every unmerged file)
+        paths: Vec<PathBuf>,
+        /// Do not ask the model for a suggested resolution
+        #[arg(long)]
+        no_ai: bool,
+    },
 }
 
 /// Git analysis focus areas
@@ -201,6 +700,26 @@ pub enum GitFocus {
     Dependencies,
 }
 
+/// How `picode mcp --socket` clients share conversations
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum McpIsolation {
+    /// Every client talks in the same conversation
+    Shared,
+    /// Every client gets a conversation of its own
+    PerClient,
+}
+
+/// Level of detail for `picode explain`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplainDepth {
+    /// A short summary of what the code does
+    Brief,
+    /// Purpose, control flow and notable details
+    Standard,
+    /// Line-by-line walkthrough including edge cases
+    Deep,
+}
+
 /// LLM provider management subcommands
 #[derive(Subcommand, Debug, Clone)]
 pub enum LlmAction {
@@ -240,7 +759,7 @@ pub enum LlmAction {
 }
 
 /// Supported LLM providers
-#[derive(ValueEnum, Debug, Clone, Serialize, Deserialize)]
+#[derive(ValueEnum, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LlmProvider {
     /// Anthropic Claude
     Anthropic,
@@ -294,6 +813,196 @@ pub enum PluginAction {
     },
 }
 
+/// Hook management subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum HooksAction {
+    /// List installed hooks
+    List,
+    /// Search the community hook index
+    Search {
+        /// Search term (matches name, description and triggers)
+        term: String,
+        /// Hook index URL (overrides configuration)
+        #[arg(long)]
+        index: Option<String>,
+    },
+    /// Install a hook from the index after checksum verification and review
+    Install {
+        /// Hook name
+        name: String,
+        /// Hook index URL (overrides configuration)
+        #[arg(long)]
+        index: Option<String>,
+        /// Install without the interactive review prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Remove an installed hook
+    Remove {
+        /// Hook name
+        name: String,
+    },
+    /// Run a hook
+    Run {
+        /// Hook name
+        name: String,
+        /// Arguments passed to the hook
+        args: Vec<String>,
+    },
+    /// List running and completed async hook jobs
+    Jobs {
+        /// Only show queued and running jobs
+        #[arg(long)]
+        running: bool,
+    },
+}
+
+/// Daemon subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum DaemonAction {
+    /// Start the daemon in the background
+    Start {
+        /// Run in the foreground instead of detaching
+        #[arg(long)]
+        foreground: bool,
+    },
+    /// Stop the running daemon
+    Stop,
+    /// Show registered workspaces and index state
+    Status,
+    /// Keep a workspace indexed by the daemon
+    Register {
+        /// Workspace root
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Stop indexing a workspace
+    Unregister {
+        /// Workspace root
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+}
+
+/// Session subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum SessionAction {
+    /// Remove sensitive strings from a stored conversation
+    Redact {
+        /// Session name
+        name: String,
+        /// Regular expression to redact (repeatable)
+        #[arg(long, required_unless_present = "secrets")]
+        pattern: Vec<String>,
+        /// Redact well-known credential formats (API keys, tokens, private keys)
+        #[arg(long)]
+        secrets: bool,
+    },
+    /// Search stored conversations by text and tags
+    History {
+        /// Text to look for in messages, notes and titles
+        query: Option<String>,
+        /// Only conversations carrying this tag (repeatable)
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Export a stored conversation as markdown
+    Export {
+        /// Session name
+        name: String,
+        /// Only exchanges carrying this tag (repeatable)
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Show how regenerated responses differ from the versions they replaced
+        #[arg(long)]
+        response_diffs: bool,
+    },
+    /// Render a stored conversation as a self-contained HTML page for sharing
+    Publish {
+        /// Session name
+        name: String,
+        /// Output file (default: <name>.html)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Leave out file contents, keeping only the conversation and diffs
+        #[arg(long)]
+        strip_contents: bool,
+    },
+    /// Move older messages of stored conversations into compressed segments
+    /// and report the space reclaimed
+    Compact,
+    /// Send the user turns of a stored conversation again, with their
+    /// recorded context, to another provider or model and compare the replies
+    Rerun {
+        /// Session name
+        name: String,
+        /// Provider to send the turns to
+        #[arg(long)]
+        provider: String,
+        /// Model to use instead of the provider's default
+        #[arg(long)]
+        model: Option<String>,
+        /// Write the comparison report to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Agent subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum AgentAction {
+    /// Run the agent on a task until it is done or its budget runs low
+    Run {
+        /// What the agent should do
+        task: String,
+        /// Token or cost ceiling, e.g. 50k-tokens or $2.50
+        #[arg(long)]
+        budget: Option<picode_core::agent::RunBudget>,
+        /// Turns before the agent is asked to wrap up
+        #[arg(long, default_value_t = 20)]
+        max_turns: usize,
+        /// Permission profile limiting the agent's tools, e.g. reader, editor, operator, network
+        #[arg(long)]
+        permissions: Option<String>,
+        /// Stage file writes for review (`picode review`) instead of making them
+        #[arg(long)]
+        review: bool,
+        /// Run the `[agent.verify]` checks and critic before finishing, even when not enabled
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Show the report of a previous agent run
+    Report {
+        /// Run id (or unambiguous prefix)
+        id: picode_core::IdPrefix<picode_core::id::AgentRunKind>,
+        /// Print the raw JSON report instead of markdown
+        #[arg(long)]
+        json: bool,
+    },
+    /// Inspect and restore files deleted by agent tools
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+}
+
+/// Agent trash subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum TrashAction {
+    /// List deleted files held in .picode/trash
+    List,
+    /// Restore a deleted file to its original path
+    Restore {
+        /// Trash entry id (or unambiguous prefix)
+        id: picode_core::IdPrefix<picode_core::id::TrashKind>,
+    },
+    /// Permanently delete everything in the trash
+    Empty,
+}
+
 /// Development utilities subcommands
 #[derive(Subcommand, Debug, Clone)]
 pub enum DevAction {
@@ -310,9 +1019,12 @@ pub enum DevAction {
         /// Check for pre-release versions
         #[arg(long)]
         pre_release: bool,
-        /// Automatically install updates
+        /// Download, verify and install the update
         #[arg(short, long)]
         auto: bool,
+        /// Install with only the checksum verified, without a release signature
+        #[arg(long)]
+        insecure_checksum_only: bool,
     },
     /// Export configuration
     Export {
@@ -331,6 +1043,11 @@ pub enum DevAction {
         #[arg(short, long)]
         merge: bool,
     },
+    /// Show which ignore pattern (if any) excludes a path from the workspace
+    WhyIgnored {
+        /// Path, relative to the workspace root
+        path: PathBuf,
+    },
 }
 
 /// Shell types for completion generation
@@ -408,6 +1125,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_workspace_template() {
+        let args = Args::try_parse_from(["picode", "workspace", "--template", "review-mode"]).unwrap();
+
+        match args.command {
+            Commands::Workspace { template, .. } => assert_eq!(template.as_deref(), Some("review-mode")),
+            _ => panic!("Expected Workspace command"),
+        }
+    }
+
+    #[test]
+    fn test_print_flag() {
+        let args = Args::try_parse_from(["picode", "-p", "why is this failing?"]).unwrap();
+        
+        match args.command {
+            Commands::Print { prompt, raw_stdin, .. } => {
+                assert_eq!(prompt, "why is this failing?");
+                assert!(!raw_stdin);
+            }
+            _ => panic!("Expected Print command"),
+        }
+        
+        let args = Args::try_parse_from(["picode", "--print", "{{stdin}}", "--raw-stdin"]).unwrap();
+        assert!(matches!(args.command, Commands::Print { raw_stdin: true, .. }));
+
+        let args = Args::try_parse_from(["picode", "-p", "write the guide", "--output-file", "out.md", "--stream"]).unwrap();
+        assert!(matches!(args.command, Commands::Print { stream: true, output_file: Some(_), .. }));
+        assert!(Args::try_parse_from(["picode", "-p", "write the guide", "--stream"]).is_err());
+    }
+
     #[test]
     fn test_config_set() {
         let args = Args::try_parse_from(["picode", "config", "set", "key", "value"]).unwrap();
@@ -421,6 +1168,465 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_profile_commands() {
+        let args = Args::try_parse_from(["picode", "--profile", "work", "config", "profile", "switch", "personal"]).unwrap();
+        
+        assert_eq!(args.profile, Some("work".to_string()));
+        match args.command {
+            Commands::Config { action: ConfigAction::Profile { action: ProfileAction::Switch { name } } } => {
+                assert_eq!(name, "personal");
+            }
+            _ => panic!("Expected Config Profile Switch command"),
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let args = Args::try_parse_from(["picode", "eval", "golden.yaml", "-p", "mock,openai/gpt-4o", "--junit", "eval.xml"]).unwrap();
+        match args.command {
+            Commands::Eval { suite, providers, filter, junit } => {
+                assert_eq!(suite, PathBuf::from("golden.yaml"));
+                assert_eq!(providers, vec!["mock", "openai/gpt-4o"]);
+                assert!(filter.is_none());
+                assert_eq!(junit, Some(PathBuf::from("eval.xml")));
+            }
+            _ => panic!("Expected Eval command"),
+        }
+    }
+
+    #[test]
+    fn test_sync() {
+        let args = Args::try_parse_from(["picode", "sync", "--dry-run"]).unwrap();
+        assert!(matches!(args.command, Commands::Sync { dry_run: true }));
+    }
+
+    #[test]
+    fn test_review_export() {
+        let args = Args::try_parse_from(["picode", "review", "export", "--patch", "out.patch"]).unwrap();
+        match args.command {
+            Commands::Review { action: ReviewAction::Export { patch } } => assert_eq!(patch, Some(PathBuf::from("out.patch"))),
+            _ => panic!("Expected Review Export command"),
+        }
+        assert!(Args::try_parse_from(["picode", "review", "import"]).is_err());
+    }
+
+    #[test]
+    fn test_release_notes() {
+        let args = Args::try_parse_from(["picode", "release", "notes", "--since", "v1.2.0", "--release-version", "1.3.0"]).unwrap();
+        match args.command {
+            Commands::Release { action: ReleaseAction::Notes { since, until, version, changelog, .. } } => {
+                assert_eq!((since.as_str(), until.as_str()), ("v1.2.0", "HEAD"));
+                assert_eq!(version.as_deref(), Some("1.3.0"));
+                assert_eq!(changelog, PathBuf::from("CHANGELOG.md"));
+            }
+            _ => panic!("Expected Release Notes command"),
+        }
+        assert!(Args::try_parse_from(["picode", "release", "notes"]).is_err());
+    }
+
+    #[test]
+    fn test_audit_deps() {
+        let args = Args::try_parse_from(["picode", "audit", "deps", "--plan", "--fail-on", "high", "-o", "audit.md"]).unwrap();
+        match args.command {
+            Commands::Audit { action: AuditAction::Deps { json, output, plan, fail_on } } => {
+                assert!(plan && !json);
+                assert_eq!(output, Some(PathBuf::from("audit.md")));
+                assert_eq!(fail_on, Some(AuditSeverity::High));
+            }
+            _ => panic!("Expected Audit Deps command"),
+        }
+        assert!(Args::try_parse_from(["picode", "audit", "deps", "--fail-on", "severe"]).is_err());
+    }
+
+    #[test]
+    fn test_trust_allow() {
+        let args = Args::try_parse_from(["picode", "trust", "allow", "/src/project"]).unwrap();
+        match args.command {
+            Commands::Trust { action: TrustAction::Allow { path } } => assert_eq!(path, Some(PathBuf::from("/src/project"))),
+            _ => panic!("Expected Trust Allow command"),
+        }
+        let args = Args::try_parse_from(["picode", "trust", "forget"]).unwrap();
+        assert!(matches!(args.command, Commands::Trust { action: TrustAction::Forget { path: None } }));
+    }
+
+    #[test]
+    fn test_work_on() {
+        let args = Args::try_parse_from(["picode", "work-on", "https://github.com/o/r/issues/42", "--base", "main"]).unwrap();
+        match args.command {
+            Commands::WorkOn { issue, template, base } => {
+                assert_eq!(issue, "https://github.com/o/r/issues/42");
+                assert_eq!(template, None);
+                assert_eq!(base.as_deref(), Some("main"));
+            }
+            _ => panic!("Expected WorkOn command"),
+        }
+    }
+
+    #[test]
+    fn test_stats_workspace() {
+        let args = Args::try_parse_from(["picode", "stats", "workspace", "--json"]).unwrap();
+        assert!(matches!(args.command, Commands::Stats { action: StatsAction::Workspace { json: true } }));
+    }
+
+    #[test]
+    fn test_todos() {
+        let args = Args::try_parse_from(["picode", "todos", "--json"]).unwrap();
+        assert!(matches!(args.command, Commands::Todos { json: true, all: false }));
+    }
+
+    #[test]
+    fn test_config_bundle() {
+        let args = Args::try_parse_from(["picode", "config", "bundle", "import", "team.json", "--force"]).unwrap();
+        match args.command {
+            Commands::Config { action: ConfigAction::Bundle { action: BundleAction::Import { path, force } } } => {
+                assert_eq!(path, PathBuf::from("team.json"));
+                assert!(force);
+            }
+            _ => panic!("Expected Config Bundle Import command"),
+        }
+    }
+
+    #[test]
+    fn test_hooks_search() {
+        let args = Args::try_parse_from(["picode", "hooks", "search", "fmt", "--index", "./index.json"]).unwrap();
+        
+        match args.command {
+            Commands::Hooks { action: HooksAction::Search { term, index } } => {
+                assert_eq!(term, "fmt");
+                assert_eq!(index, Some("./index.json".to_string()));
+            }
+            _ => panic!("Expected Hooks Search command"),
+        }
+    }
+
+    #[test]
+    fn test_hooks_jobs() {
+        let args = Args::try_parse_from(["picode", "hooks", "jobs", "--running"]).unwrap();
+
+        match args.command {
+            Commands::Hooks { action: HooksAction::Jobs { running } } => assert!(running),
+            _ => panic!("Expected Hooks Jobs command"),
+        }
+    }
+
+    #[test]
+    fn test_agent_trash_restore() {
+        let args = Args::try_parse_from(["picode", "agent", "trash", "restore", "3f2a"]).unwrap();
+
+        match args.command {
+            Commands::Agent { action: AgentAction::Trash { action: TrashAction::Restore { id } } } => {
+                assert_eq!(id.as_str(), "3F2A");
+            }
+            _ => panic!("Expected Agent Trash Restore command"),
+        }
+        assert!(Args::try_parse_from(["picode", "agent", "trash", "restore", "3f 2a"]).is_err());
+    }
+
+    #[test]
+    fn test_bench_llm() {
+        let args = Args::try_parse_from(["picode", "bench", "llm", "-p", "openai/gpt-4o,local", "-n", "5", "--json"]).unwrap();
+
+        match args.command {
+            Commands::Bench { action: BenchAction::Llm { providers, iterations, judge, json, .. } } => {
+                assert_eq!(providers, vec!["openai/gpt-4o", "local"]);
+                assert_eq!(iterations, 5);
+                assert!(judge.is_none());
+                assert!(json);
+            }
+            _ => panic!("Expected Bench Llm command"),
+        }
+    }
+
+    #[test]
+    fn test_bench_core() {
+        let args = Args::try_parse_from(["picode", "bench", "core", "--only", "workspace-scan,event-publish", "--quick"]).unwrap();
+        match args.command {
+            Commands::Bench { action: BenchAction::Core { only, baseline, save_baseline, threshold, quick } } => {
+                assert_eq!(only, vec![CoreBenchKind::WorkspaceScan, CoreBenchKind::EventPublish]);
+                assert_eq!(baseline, "main");
+                assert!(!save_baseline && quick);
+                assert_eq!(threshold, 10.0);
+            }
+            _ => panic!("Expected Bench Core command"),
+        }
+    }
+
+    #[test]
+    fn test_task_run() {
+        let args = Args::try_parse_from(["picode", "task", "run", "test", "--", "--nocapture"]).unwrap();
+
+        match args.command {
+            Commands::Task { action: TaskAction::Run { name, args } } => {
+                assert_eq!(name, "test");
+                assert_eq!(args, vec!["--nocapture"]);
+            }
+            _ => panic!("Expected Task Run command"),
+        }
+    }
+
+    #[test]
+    fn test_git_commit_paths() {
+        let args = Args::try_parse_from(["picode", "git", "commit", "-m", "Update", "src/main.rs", "vendor/lib/lib.rs"]).unwrap();
+
+        match args.command {
+            Commands::Git { action: GitAction::Commit { message, paths, all, .. } } => {
+                assert_eq!(message.as_deref(), Some("Update"));
+                assert_eq!(paths, vec![PathBuf::from("src/main.rs"), PathBuf::from("vendor/lib/lib.rs")]);
+                assert!(!all);
+            }
+            _ => panic!("Expected Git Commit command"),
+        }
+    }
+
+    #[test]
+    fn test_git_bisect_assist() {
+        let args = Args::try_parse_from([
+            "picode", "git", "bisect-assist", "login fails with 500", "--good", "v1.2.0", "--check", "cargo test login",
+        ])
+        .unwrap();
+        match args.command {
+            Commands::Git { action: GitAction::BisectAssist { description, good, bad, check, judge } } => {
+                assert_eq!(description, "login fails with 500");
+                assert_eq!(good, "v1.2.0");
+                assert_eq!(bad, "HEAD");
+                assert_eq!(check.as_deref(), Some("cargo test login"));
+                assert!(!judge);
+            }
+            _ => panic!("Expected Git BisectAssist command"),
+        }
+        assert!(Args::try_parse_from(["picode", "git", "bisect-assist", "bug"]).is_err());
+    }
+
+    #[test]
+    fn test_git_split_commit() {
+        let args = Args::try_parse_from(["picode", "git", "split-commit", "--judge", "--dry-run"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Commands::Git { action: GitAction::SplitCommit { judge: true, dry_run: true, yes: false } }
+        ));
+    }
+
+    #[test]
+    fn test_git_resolve() {
+        let args = Args::try_parse_from(["picode", "git", "resolve", "src/lib.rs", "--no-ai"]).unwrap();
+        match args.command {
+            Commands::Git { action: GitAction::Resolve { paths, no_ai } } => {
+                assert_eq!(paths, vec![PathBuf::from("src/lib.rs")]);
+                assert!(no_ai);
+            }
+            _ => panic!("Expected Git Resolve command"),
+        }
+    }
+
+    #[test]
+    fn test_serve_command() {
+        let args = Args::try_parse_from(["picode", "serve", "--bind", "0.0.0.0:9000"]).unwrap();
+        
+        match args.command {
+            Commands::Serve { bind, action, .. } => {
+                assert_eq!(bind.port(), 9000);
+                assert!(action.is_none());
+            }
+            _ => panic!("Expected Serve command"),
+        }
+    }
+
+    #[test]
+    fn test_diff_explain() {
+        let args = Args::try_parse_from(["picode", "diff", "explain", "--range", "main..HEAD", "--pr"]).unwrap();
+        
+        match args.command {
+            Commands::Diff { action: DiffAction::Explain { file, range, pr } } => {
+                assert!(file.is_none());
+                assert_eq!(range.as_deref(), Some("main..HEAD"));
+                assert!(pr);
+            }
+            _ => panic!("Expected Diff Explain command"),
+        }
+        
+        assert!(Args::try_parse_from(["picode", "diff", "explain", "--file", "a.diff", "--range", "HEAD~1"]).is_err());
+    }
+
+    #[test]
+    fn test_serve_add_user() {
+        let args = Args::try_parse_from(["picode", "serve", "add-user", "alice", "--daily-limit", "5"]).unwrap();
+        
+        match args.command {
+            Commands::Serve { action: Some(ServeAction::AddUser { name, admin, daily_limit, .. }), .. } => {
+                assert_eq!(name, "alice");
+                assert!(!admin);
+                assert_eq!(daily_limit, Some(5.0));
+            }
+            _ => panic!("Expected Serve AddUser command"),
+        }
+    }
+
+    #[test]
+    fn test_agent_report() {
+        let args = Args::try_parse_from(["picode", "agent", "report", "3f2a", "--json"]).unwrap();
+        
+        match args.command {
+            Commands::Agent { action: AgentAction::Report { id, json } } => {
+                assert_eq!(id.as_str(), "3F2A");
+                assert!(json);
+            }
+            _ => panic!("Expected Agent Report command"),
+        }
+    }
+
+    #[test]
+    fn test_daemon_commands() {
+        let args = Args::try_parse_from(["picode", "daemon", "start", "--foreground"]).unwrap();
+        assert!(matches!(args.command, Commands::Daemon { action: DaemonAction::Start { foreground: true } }));
+        
+        let args = Args::try_parse_from(["picode", "daemon", "register"]).unwrap();
+        match args.command {
+            Commands::Daemon { action: DaemonAction::Register { path } } => assert_eq!(path, PathBuf::from(".")),
+            _ => panic!("Expected Daemon Register command"),
+        }
+    }
+
+    #[test]
+    fn test_session_redact() {
+        let args = Args::try_parse_from(["picode", "session", "redact", "demo", "--pattern", "hunter2", "--secrets"]).unwrap();
+        
+        match args.command {
+            Commands::Session { action: SessionAction::Redact { name, pattern, secrets } } => {
+                assert_eq!(name, "demo");
+                assert_eq!(pattern, vec!["hunter2".to_string()]);
+                assert!(secrets);
+            }
+            _ => panic!("Expected Session Redact command"),
+        }
+        
+        assert!(Args::try_parse_from(["picode", "session", "redact", "demo"]).is_err());
+    }
+
+    #[test]
+    fn test_open_command() {
+        let args = Args::try_parse_from(["picode", "open", "src/lib.rs:10:4"]).unwrap();
+        
+        match args.command {
+            Commands::Open { target } => assert_eq!(target, "src/lib.rs:10:4"),
+            _ => panic!("Expected Open command"),
+        }
+    }
+
+    #[test]
+    fn test_session_history_and_export() {
+        let args = Args::try_parse_from(["picode", "session", "history", "timeout", "--tag", "bug-hunt"]).unwrap();
+        match args.command {
+            Commands::Session { action: SessionAction::History { query, tag } } => {
+                assert_eq!(query.as_deref(), Some("timeout"));
+                assert_eq!(tag, vec!["bug-hunt"]);
+            }
+            _ => panic!("Expected Session History command"),
+        }
+
+        let args = Args::try_parse_from([
+            "picode", "session", "export", "auth", "--tag", "a", "--tag", "b", "-o", "auth.md", "--response-diffs",
+        ])
+        .unwrap();
+        match args.command {
+            Commands::Session { action: SessionAction::Export { name, tag, output, response_diffs } } => {
+                assert_eq!(name, "auth");
+                assert_eq!(tag, vec!["a", "b"]);
+                assert_eq!(output, Some(PathBuf::from("auth.md")));
+                assert!(response_diffs);
+            }
+            _ => panic!("Expected Session Export command"),
+        }
+    }
+
+    #[test]
+    fn test_session_publish_command() {
+        let args = Args::try_parse_from(["picode", "session", "publish", "auth", "--strip-contents"]).unwrap();
+        match args.command {
+            Commands::Session { action: SessionAction::Publish { name, output, strip_contents } } => {
+                assert_eq!(name, "auth");
+                assert_eq!(output, None);
+                assert!(strip_contents);
+            }
+            _ => panic!("Expected Session Publish command"),
+        }
+    }
+
+    #[test]
+    fn test_session_compact_command() {
+        let args = Args::try_parse_from(["picode", "session", "compact"]).unwrap();
+        assert!(matches!(args.command, Commands::Session { action: SessionAction::Compact }));
+    }
+
+    #[test]
+    fn test_session_rerun_command() {
+        let args = Args::try_parse_from(["picode", "session", "rerun", "auth", "--provider", "anthropic", "-o", "rerun.md"]).unwrap();
+        match args.command {
+            Commands::Session { action: SessionAction::Rerun { name, provider, model, output } } => {
+                assert_eq!((name.as_str(), provider.as_str(), model), ("auth", "anthropic", None));
+                assert_eq!(output, Some(PathBuf::from("rerun.md")));
+            }
+            _ => panic!("Expected Session Rerun command"),
+        }
+        assert!(Args::try_parse_from(["picode", "session", "rerun", "auth"]).is_err());
+    }
+
+    #[test]
+    fn test_mcp_command() {
+        let args = Args::try_parse_from(["picode", "mcp"]).unwrap();
+        assert!(matches!(args.command, Commands::Mcp { socket: None, isolation: McpIsolation::Shared }));
+
+        let args = Args::try_parse_from(["picode", "mcp", "--socket", "/tmp/picode.sock", "--isolation", "per-client"]).unwrap();
+        match args.command {
+            Commands::Mcp { socket, isolation } => {
+                assert_eq!(socket, Some(PathBuf::from("/tmp/picode.sock")));
+                assert_eq!(isolation, McpIsolation::PerClient);
+            }
+            _ => panic!("Expected Mcp command"),
+        }
+    }
+
+    #[test]
+    fn test_dev_why_ignored_command() {
+        let args = Args::try_parse_from(["picode", "dev", "why-ignored", "src/target_utils.rs"]).unwrap();
+        match args.command {
+            Commands::Dev { action: DevAction::WhyIgnored { path } } => {
+                assert_eq!(path, PathBuf::from("src/target_utils.rs"));
+            }
+            _ => panic!("Expected Dev WhyIgnored command"),
+        }
+    }
+
+    #[test]
+    fn test_agent_run_command() {
+        let args = Args::try_parse_from(["picode", "agent", "run", "Fix the build", "--budget", "50k-tokens", "--permissions", "reader"]).unwrap();
+        match args.command {
+            Commands::Agent { action: AgentAction::Run { task, budget, max_turns, permissions, review, verify } } => {
+                assert_eq!(task, "Fix the build");
+                assert_eq!(budget.and_then(|b| b.max_tokens), Some(50_000));
+                assert_eq!(max_turns, 20);
+                assert_eq!(permissions.as_deref(), Some("reader"));
+                assert!(!review);
+                assert!(!verify);
+            }
+            _ => panic!("Expected Agent Run command"),
+        }
+        assert!(Args::try_parse_from(["picode", "agent", "run", "x", "--budget", "lots"]).is_err());
+    }
+
+    #[test]
+    fn test_explain_command() {
+        let args = Args::try_parse_from(["picode", "explain", "src/lib.rs:10-20", "--depth", "deep"]).unwrap();
+        
+        match args.command {
+            Commands::Explain { target, depth } => {
+                assert_eq!(target, "src/lib.rs:10-20");
+                assert_eq!(depth, ExplainDepth::Deep);
+            }
+            _ => panic!("Expected Explain command"),
+        }
+    }
+
     #[test]
     fn test_global_args() {
         let args = Args::try_parse_from(["picode", "-vv", "--debug", "init"]).unwrap();
@@ -13,12 +13,15 @@ pub async fn execute_command(args: &Args) -> Result<()> {
         Commands::Init { path, name, template, force } => {
             execute_init(path, name.as_deref(), template.as_deref(), *force).await
         },
-        Commands::Workspace { ai, provider, endpoint, session } => {
-            execute_workspace(*ai, provider.as_ref(), endpoint.as_deref(), session.as_deref()).await
+        Commands::Workspace { ai, provider, endpoint, session, template } => {
+            execute_workspace(*ai, provider.as_ref(), endpoint.as_deref(), session.as_deref(), template.as_deref()).await
         },
         Commands::Execute { command, args, suggest, dry_run } => {
             execute_run(command, args, *suggest, *dry_run).await
         },
+        Commands::Print { prompt, .. } => {
+            execute_print(prompt).await
+        },
         Commands::Config { action } => {
             execute_config(action).await
         },
@@ -34,6 +37,66 @@ pub async fn execute_command(args: &Args) -> Result<()> {
         Commands::Dev { action } => {
             execute_dev(action).await
         },
+        Commands::Hooks { action } => {
+            execute_hooks(action).await
+        },
+        Commands::Agent { action } => {
+            execute_agent(action).await
+        },
+        Commands::Daemon { action } => {
+            execute_daemon(action).await
+        },
+        Commands::Session { action } => {
+            execute_session(action).await
+        },
+        Commands::Explain { target, depth } => {
+            execute_explain(target, *depth).await
+        },
+        Commands::Open { target } => {
+            execute_open(target).await
+        },
+        Commands::Diff { action } => {
+            execute_diff(action).await
+        },
+        Commands::Serve { bind, .. } => {
+            execute_serve(bind).await
+        },
+        Commands::Mcp { .. } => {
+            execute_mcp().await
+        },
+        Commands::Bench { action } => {
+            execute_bench(action).await
+        },
+        Commands::Task { action } => {
+            execute_task(action).await
+        },
+        Commands::Eval { suite, .. } => {
+            execute_eval(suite).await
+        },
+        Commands::Sync { .. } => {
+            execute_sync().await
+        },
+        Commands::Stats { action } => {
+            execute_stats(action).await
+        },
+        Commands::Todos { .. } => {
+            execute_todos().await
+        },
+        Commands::Review { action } => {
+            execute_review(action).await
+        },
+        Commands::Trust { action } => {
+            execute_trust(action).await
+        },
+        Commands::Release { action } => {
+            execute_release(action).await
+        },
+        Commands::Audit { action } => {
+            execute_audit(action).await
+        },
+        Commands::WorkOn { issue, .. } => {
+            execute_work_on(issue).await
+        },
     }
 }
 
@@ -357,6 +420,7 @@ async fn execute_workspace(
     _provider: Option<&LlmProvider>,
     _endpoint: Option<&str>,
     _session: Option<&str>,
+    _template: Option<&str>,
 ) -> Result<()> {
     println!("🚀 Starting PiCode workspace...");
     // TODO: Implement workspace startup
@@ -374,6 +438,12 @@ async fn execute_run(
     Ok(())
 }
 
+async fn execute_print(_prompt: &str) -> Result<()> {
+    println!("💬 Print mode...");
+    // Print mode is run by the main binary
+    Ok(())
+}
+
 async fn execute_config(_action: &ConfigAction) -> Result<()> {
     println!("⚙️ Managing configuration...");
     // TODO: Implement config management
@@ -382,7 +452,7 @@ async fn execute_config(_action: &ConfigAction) -> Result<()> {
 
 async fn execute_git(_action: &GitAction) -> Result<()> {
     println!("📝 Git integration...");
-    // TODO: Implement Git integration
+    // Git is run by the main binary
     Ok(())
 }
 
@@ -404,6 +474,126 @@ async fn execute_dev(_action: &DevAction) -> Result<()> {
     Ok(())
 }
 
+async fn execute_hooks(_action: &HooksAction) -> Result<()> {
+    println!("🪝 Hook management...");
+    // Hook commands are dispatched to picode-hooks by the main binary
+    Ok(())
+}
+
+async fn execute_agent(_action: &AgentAction) -> Result<()> {
+    println!("🤖 Agent runs...");
+    // Agent commands are run by the main binary
+    Ok(())
+}
+
+async fn execute_daemon(_action: &DaemonAction) -> Result<()> {
+    println!("🛰️ Daemon...");
+    // The daemon is run by the main binary
+    Ok(())
+}
+
+async fn execute_session(_action: &SessionAction) -> Result<()> {
+    println!("📝 Sessions...");
+    // Session commands are run by the main binary
+    Ok(())
+}
+
+async fn execute_explain(_target: &str, _depth: ExplainDepth) -> Result<()> {
+    println!("📖 Explaining code...");
+    // Explanations are run by the main binary
+    Ok(())
+}
+
+async fn execute_open(_target: &str) -> Result<()> {
+    println!("📝 Opening in external editor...");
+    // Opening files is run by the main binary
+    Ok(())
+}
+
+async fn execute_mcp() -> Result<()> {
+    println!("🔌 Starting MCP server...");
+    // MCP server mode is run by the main binary
+    Ok(())
+}
+
+async fn execute_diff(_action: &DiffAction) -> Result<()> {
+    println!("🔍 Diff...");
+    // Diff commands are run by the main binary
+    Ok(())
+}
+
+async fn execute_serve(_bind: &std::net::SocketAddr) -> Result<()> {
+    println!("🌐 Serve mode...");
+    // Serve mode is run by the main binary
+    Ok(())
+}
+
+async fn execute_bench(_action: &BenchAction) -> Result<()> {
+    println!("⏱️ Benchmark...");
+    // Benchmarks are run by the main binary
+    Ok(())
+}
+
+async fn execute_eval(_suite: &PathBuf) -> Result<()> {
+    println!("🧪 Eval...");
+    // Evals are run by the main binary
+    Ok(())
+}
+
+async fn execute_sync() -> Result<()> {
+    println!("🔄 Sync...");
+    // Sync is run by the main binary
+    Ok(())
+}
+
+async fn execute_stats(_action: &StatsAction) -> Result<()> {
+    println!("📊 Stats...");
+    // Statistics are computed by the main binary
+    Ok(())
+}
+
+async fn execute_todos() -> Result<()> {
+    println!("📝 TODOs...");
+    // The TODO list is kept by the main binary
+    Ok(())
+}
+
+async fn execute_review(_action: &ReviewAction) -> Result<()> {
+    println!("🔍 Review...");
+    // Reviews are handled by the main binary
+    Ok(())
+}
+
+async fn execute_trust(_action: &TrustAction) -> Result<()> {
+    println!("🔒 Workspace trust...");
+    // Trust decisions are stored by the main binary
+    Ok(())
+}
+
+async fn execute_release(_action: &ReleaseAction) -> Result<()> {
+    println!("📝 Drafting release notes...");
+    // Commits are read and the drafts staged by the main binary
+    Ok(())
+}
+
+async fn execute_audit(_action: &AuditAction) -> Result<()> {
+    println!("🛡  Auditing dependencies...");
+    // Lockfiles are scanned and advisories looked up by the main binary
+    Ok(())
+}
+
+async fn execute_work_on(issue: &str) -> Result<()> {
+    println!("🎫 Working on {}...", issue);
+    // Issues are fetched and sessions opened by the main binary
+    Ok(())
+}
+
+async fn execute_task(_action: &TaskAction) -> Result<()> {
+    println!("🧰 Workspace task...");
+    // Tasks are run by the main binary
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
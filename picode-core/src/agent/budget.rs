@@ -0,0 +1,191 @@
+//! Per-run token and cost ceilings
+//!
+//! A [`RunBudget`] caps the tokens (or dollars) one agent run may spend.
+//! When a run reaches [`WRAP_UP_THRESHOLD`] of its budget it is told to stop
+//! starting new work and summarize its partial results; once the budget is
+//! spent the run stops. Consumption is recorded in the run report.
+
+use super::report::UsageTotals;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Share of the budget after which the agent is asked to wrap up
+pub const WRAP_UP_THRESHOLD: f64 = 0.8;
+
+/// Instruction sent with the final turn of a run nearing its budget
+pub const WRAP_UP_INSTRUCTIONS: &str = "This run is about to reach its budget. Do not start new \
+work. Reply with a summary of your partial results: what is done, what remains, and how to \
+continue from here.";
+
+/// Ceiling on what a run may spend; `None` disables a limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunBudget {
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid budget '{0}': expected e.g. 50k-tokens, 200000-tokens or $2.50")]
+pub struct BudgetParseError(pub String);
+
+impl FromStr for RunBudget {
+    type Err = BudgetParseError;
+
+    /// Parse `50k-tokens`, `1.5m-tokens`, `200000-tokens`, `$2.50` or `2.5usd`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BudgetParseError(s.to_string());
+        let text = s.trim().to_lowercase();
+
+        if let Some(amount) = text.strip_prefix('$').or_else(|| text.strip_suffix("usd")) {
+            let cost: f64 = amount.trim().parse().map_err(|_| invalid())?;
+            if !cost.is_finite() || cost <= 0.0 {
+                return Err(invalid());
+            }
+            return Ok(Self { max_tokens: None, max_cost_usd: Some(cost) });
+        }
+
+        let count = text
+            .strip_suffix("-tokens")
+            .or_else(|| text.strip_suffix("tokens"))
+            .unwrap_or(&text)
+            .trim();
+        let (number, scale) = match count.chars().last() {
+            Some('k') => (&count[..count.len() - 1], 1_000.0),
+            Some('m') => (&count[..count.len() - 1], 1_000_000.0),
+            _ => (count, 1.0),
+        };
+        let tokens = number.parse::<f64>().map_err(|_| invalid())? * scale;
+        if !tokens.is_finite() || tokens < 1.0 {
+            return Err(invalid());
+        }
+        Ok(Self { max_tokens: Some(tokens as u64), max_cost_usd: None })
+    }
+}
+
+impl fmt::Display for RunBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.max_tokens, self.max_cost_usd) {
+            (Some(tokens), Some(cost)) => write!(f, "{} tokens or ${:.2}", tokens, cost),
+            (Some(tokens), None) => write!(f, "{} tokens", tokens),
+            (None, Some(cost)) => write!(f, "${:.2}", cost),
+            (None, None) => write!(f, "unlimited"),
+        }
+    }
+}
+
+/// Where a run stands against its budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetStatus {
+    Within,
+    /// Past the wrap-up threshold: the next turn should be the last
+    WrapUp,
+    Exhausted,
+}
+
+/// Budget consumption recorded in the run report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetReport {
+    pub budget: RunBudget,
+    pub used: UsageTotals,
+    /// Largest share of a limit that was used, e.g. 0.93
+    pub fraction_used: f64,
+    /// The run was asked to wrap up before finishing on its own
+    pub wrapped_up: bool,
+}
+
+/// Spending of one run against its budget
+#[derive(Debug, Clone)]
+pub struct BudgetTracker {
+    budget: RunBudget,
+    used: UsageTotals,
+    wrapped_up: bool,
+}
+
+impl BudgetTracker {
+    pub fn new(budget: RunBudget) -> Self {
+        Self {
+            budget,
+            used: UsageTotals::default(),
+            wrapped_up: false,
+        }
+    }
+
+    pub fn record(&mut self, prompt_tokens: u64, completion_tokens: u64, cost_usd: f64) {
+        self.used.prompt_tokens += prompt_tokens;
+        self.used.completion_tokens += completion_tokens;
+        self.used.cost_usd += cost_usd;
+    }
+
+    pub fn used(&self) -> &UsageTotals {
+        &self.used
+    }
+
+    /// Largest share of any limit used so far
+    pub fn fraction_used(&self) -> f64 {
+        let tokens = self
+            .budget
+            .max_tokens
+            .map(|max| (self.used.prompt_tokens + self.used.completion_tokens) as f64 / max as f64);
+        let cost = self.budget.max_cost_usd.map(|max| self.used.cost_usd / max);
+        tokens.into_iter().chain(cost).fold(0.0, f64::max)
+    }
+
+    pub fn status(&self) -> BudgetStatus {
+        match self.fraction_used() {
+            used if used >= 1.0 => BudgetStatus::Exhausted,
+            used if used >= WRAP_UP_THRESHOLD => BudgetStatus::WrapUp,
+            _ => BudgetStatus::Within,
+        }
+    }
+
+    /// Note that the run was asked to wrap up
+    pub fn mark_wrapped_up(&mut self) {
+        self.wrapped_up = true;
+    }
+
+    pub fn report(&self) -> BudgetReport {
+        BudgetReport {
+            budget: self.budget,
+            used: self.used.clone(),
+            fraction_used: self.fraction_used(),
+            wrapped_up: self.wrapped_up,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_token_and_cost_budgets() {
+        assert_eq!("50k-tokens".parse::<RunBudget>().unwrap().max_tokens, Some(50_000));
+        assert_eq!("1.5M-tokens".parse::<RunBudget>().unwrap().max_tokens, Some(1_500_000));
+        assert_eq!("2000".parse::<RunBudget>().unwrap().max_tokens, Some(2000));
+        assert_eq!("$2.50".parse::<RunBudget>().unwrap().max_cost_usd, Some(2.5));
+        assert_eq!("3usd".parse::<RunBudget>().unwrap().to_string(), "$3.00");
+        assert!("lots".parse::<RunBudget>().is_err());
+        assert!("$0".parse::<RunBudget>().is_err());
+    }
+
+    #[test]
+    fn tracker_moves_from_within_to_wrap_up_to_exhausted() {
+        let mut tracker = BudgetTracker::new(RunBudget { max_tokens: Some(1000), max_cost_usd: Some(1.0) });
+        tracker.record(300, 100, 0.1);
+        assert_eq!(tracker.status(), BudgetStatus::Within);
+
+        // Cost is the tighter limit here
+        tracker.record(100, 100, 0.75);
+        assert_eq!(tracker.status(), BudgetStatus::WrapUp);
+        tracker.mark_wrapped_up();
+
+        tracker.record(300, 100, 0.05);
+        assert_eq!(tracker.status(), BudgetStatus::Exhausted);
+        let report = tracker.report();
+        assert!(report.wrapped_up);
+        assert_eq!(report.used.prompt_tokens + report.used.completion_tokens, 1000);
+    }
+}
@@ -0,0 +1,231 @@
+//! Clarifying questions before an agent acts
+//!
+//! Agents are asked to answer with a structured [`AgentPlan`] (see
+//! [`PLAN_INSTRUCTIONS`]). A plan that lists open questions is not executed:
+//! the questions are shown numbered, with quick answers, through a
+//! [`ClarificationPrompt`]. The answers are pinned into the conversation so
+//! every later request sees them, and [`resume_message`] tells the model to
+//! continue the plan with them instead of guessing.
+
+use crate::conversation::{ConversationLog, ConversationMessage, PinnedItem};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// Label of the pinned item collecting clarifications
+pub const CLARIFICATIONS_LABEL: &str = "clarifications";
+
+/// System prompt instructions asking for a structured plan
+pub const PLAN_INSTRUCTIONS: &str = "Before acting, reply with a JSON plan: \
+{\"steps\": [\"...\"], \"questions\": [{\"question\": \"...\", \"options\": [\"...\"], \"default\": \"...\"}]}. \
+List a question whenever information you need is missing or ambiguous instead of guessing; \
+leave `questions` empty when you can proceed.";
+
+/// A question the model needs answered before it can proceed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClarifyingQuestion {
+    pub question: String,
+    /// Quick answers offered to the user
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Answer used when the user just presses Enter
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+impl ClarifyingQuestion {
+    /// Question and lettered quick answers, for the numbered prompt
+    pub fn render(&self, number: usize) -> String {
+        let mut out = format!("{}. {}\n", number, self.question);
+        for (index, option) in self.options.iter().enumerate() {
+            let marker = if self.default.as_deref() == Some(option.as_str()) { " (default)" } else { "" };
+            let _ = writeln!(out, "   {}) {}{}", option_letter(index), option, marker);
+        }
+        out
+    }
+
+    /// Turn what the user typed into an answer: an option letter or number
+    /// picks a quick answer, empty input the default; anything else is the
+    /// answer itself. `None` when there is nothing to use.
+    pub fn interpret(&self, input: &str) -> Option<String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return self.default.clone();
+        }
+        let picked = if input.chars().count() == 1 {
+            let c = input.chars().next().unwrap_or_default().to_ascii_lowercase();
+            match c {
+                'a'..='z' => self.options.get(c as usize - 'a' as usize),
+                '1'..='9' => self.options.get(c as usize - '1' as usize),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        Some(picked.cloned().unwrap_or_else(|| input.to_string()))
+    }
+}
+
+fn option_letter(index: usize) -> char {
+    (b'a' + (index % 26) as u8) as char
+}
+
+/// A structured plan returned by the model
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentPlan {
+    #[serde(default)]
+    pub steps: Vec<String>,
+    #[serde(default)]
+    pub questions: Vec<ClarifyingQuestion>,
+}
+
+impl AgentPlan {
+    /// Read a plan from a reply: the whole reply as JSON, else the first
+    /// fenced block or `{...}` span that parses
+    pub fn parse(reply: &str) -> Option<Self> {
+        if let Ok(plan) = serde_json::from_str(reply.trim()) {
+            return Some(plan);
+        }
+        let fenced = reply.split("```").skip(1).step_by(2).map(|block| {
+            let body = block.split_once('\n').map_or(block, |(_, rest)| rest);
+            body.trim()
+        });
+        let braces = reply
+            .find('{')
+            .zip(reply.rfind('}'))
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| &reply[start..=end]);
+        fenced.chain(braces).find_map(|candidate| serde_json::from_str(candidate).ok())
+    }
+
+    pub fn needs_clarification(&self) -> bool {
+        !self.questions.is_empty()
+    }
+}
+
+/// A question with the user's answer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clarification {
+    pub question: String,
+    pub answer: String,
+}
+
+/// Asks the user one question; `None` means the user declined to answer
+pub trait ClarificationPrompt {
+    fn ask(&self, number: usize, question: &ClarifyingQuestion) -> Option<String>;
+}
+
+/// Asks on the terminal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalClarification;
+
+impl ClarificationPrompt for TerminalClarification {
+    fn ask(&self, number: usize, question: &ClarifyingQuestion) -> Option<String> {
+        print!("{}> ", question.render(number));
+        std::io::stdout().flush().ok()?;
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).ok()?;
+        question.interpret(&input)
+    }
+}
+
+/// Ask every open question of a plan; unanswered questions are recorded
+/// as left to the model's judgement
+pub fn clarify(plan: &AgentPlan, prompt: &dyn ClarificationPrompt) -> Vec<Clarification> {
+    plan.questions
+        .iter()
+        .enumerate()
+        .map(|(index, question)| Clarification {
+            question: question.question.clone(),
+            answer: prompt
+                .ask(index + 1, question)
+                .unwrap_or_else(|| "No answer; use your best judgement and say what you assumed.".to_string()),
+        })
+        .collect()
+}
+
+fn render_clarifications(answers: &[Clarification]) -> String {
+    answers
+        .iter()
+        .fold(String::new(), |mut out, answer| {
+            let _ = writeln!(out, "Q: {}\nA: {}", answer.question, answer.answer);
+            out
+        })
+}
+
+/// Pin the answers into the conversation (added to earlier ones) so every
+/// later request sees them
+pub fn remember(log: &mut ConversationLog, answers: &[Clarification]) {
+    if answers.is_empty() {
+        return;
+    }
+    let rendered = render_clarifications(answers);
+    match log.pinned.iter_mut().find(|item| item.label == CLARIFICATIONS_LABEL) {
+        Some(item) => item.content.push_str(&rendered),
+        None => log.pinned.push(PinnedItem::new(CLARIFICATIONS_LABEL, rendered)),
+    }
+}
+
+/// Message that hands the answers back and resumes the plan
+pub fn resume_message(answers: &[Clarification]) -> ConversationMessage {
+    ConversationMessage::new(
+        "user",
+        format!(
+            "Answers to your questions:\n{}\nContinue with the plan using these answers.",
+            render_clarifications(answers)
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionId;
+    use std::cell::RefCell;
+
+    struct Scripted(RefCell<Vec<&'static str>>);
+
+    impl ClarificationPrompt for Scripted {
+        fn ask(&self, _number: usize, question: &ClarifyingQuestion) -> Option<String> {
+            let input = self.0.borrow_mut().remove(0);
+            question.interpret(input)
+        }
+    }
+
+    const REPLY: &str = "I need to know a few things first.\n```json\n{\"steps\": [\"Add the endpoint\", \"Write tests\"], \
+\"questions\": [{\"question\": \"Which database?\", \"options\": [\"postgres\", \"sqlite\"], \"default\": \"sqlite\"}, \
+{\"question\": \"Route prefix?\"}]}\n```";
+
+    #[test]
+    fn plan_with_questions_is_parsed_and_rendered() {
+        let plan = AgentPlan::parse(REPLY).unwrap();
+        assert_eq!(plan.steps.len(), 2);
+        assert!(plan.needs_clarification());
+        assert_eq!(
+            plan.questions[0].render(1),
+            "1. Which database?\n   a) postgres\n   b) sqlite (default)\n"
+        );
+        assert!(!AgentPlan::parse("{\"steps\": [\"Go\"]}").unwrap().needs_clarification());
+        assert!(AgentPlan::parse("no plan here").is_none());
+    }
+
+    #[test]
+    fn answers_are_pinned_and_resume_the_plan() {
+        let plan = AgentPlan::parse(REPLY).unwrap();
+        assert_eq!(plan.questions[0].interpret("a").as_deref(), Some("postgres"));
+        assert_eq!(plan.questions[0].interpret("2").as_deref(), Some("sqlite"));
+        assert_eq!(plan.questions[1].interpret("").as_deref(), None);
+
+        let answers = clarify(&plan, &Scripted(RefCell::new(vec!["", "/api/v2"])));
+        assert_eq!(answers[0].answer, "sqlite");
+        assert_eq!(answers[1].answer, "/api/v2");
+
+        let mut log = ConversationLog::new(SessionId::new());
+        remember(&mut log, &answers);
+        remember(&mut log, &[Clarification { question: "Auth?".to_string(), answer: "none".to_string() }]);
+        assert_eq!(log.pinned.len(), 1);
+        assert!(log.pinned[0].content.contains("Q: Which database?\nA: sqlite\n"));
+        assert!(log.pinned[0].content.ends_with("Q: Auth?\nA: none\n"));
+        assert!(resume_message(&answers).content.contains("A: /api/v2"));
+    }
+}
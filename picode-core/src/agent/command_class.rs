@@ -0,0 +1,346 @@
+//! Classification of commands that reach outside the workspace
+//!
+//! Most commands an agent runs only read and write the workspace. Those
+//! that install packages (`npm install`, `pip install`, `cargo add`) or
+//! talk to the network (`curl`, `git fetch`, `ssh`) are classified here so
+//! the [`ToolRegistry`](super::ToolRegistry) can require the separate
+//! `network` tier of a [`PermissionProfile`](super::PermissionProfile), and
+//! the refusal can say what would have been fetched. Wrappers such as
+//! `env`, `nice` and `timeout` are looked through, and inline scripts
+//! (`sh -c`, `node -e`, `python -c`) are treated as able to do anything,
+//! network included. In lockfile-only mode,
+//! installs are rewritten to their frozen form (`npm ci`, `pip install
+//! --require-hashes`, `cargo build --locked`) so agent runs install exactly
+//! what the lockfiles pin; installs that would change a lockfile are refused.
+
+use super::permissions::PermissionError;
+use std::fmt;
+
+/// What a command reaches beyond the workspace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandClass {
+    Local,
+    /// Downloads and installs packages
+    PackageInstall,
+    /// Otherwise uses the network
+    Network,
+    /// Runs code given inline, whose reach cannot be told
+    Unknown,
+}
+
+impl CommandClass {
+    pub fn needs_network(self) -> bool {
+        self != Self::Local
+    }
+}
+
+impl fmt::Display for CommandClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Local => "local",
+            Self::PackageInstall => "package install",
+            Self::Network => "network",
+            Self::Unknown => "inline script",
+        })
+    }
+}
+
+/// A classified command and what it fetches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandAssessment {
+    pub class: CommandClass,
+    /// What the command fetches, e.g. "npm packages lodash, left-pad";
+    /// empty for local commands
+    pub fetches: String,
+}
+
+impl CommandAssessment {
+    /// Classify `command`, its program followed by its arguments
+    pub fn of(command: &[String]) -> Self {
+        let local = Self { class: CommandClass::Local, fetches: String::new() };
+        let command = &command[wrapper_len(command)..];
+        let Some(program) = command.first() else {
+            return local;
+        };
+        let args: Vec<&str> = command[1..].iter().map(String::as_str).collect();
+        let (manager, args) = match (program_name(program), args.as_slice()) {
+            ("python" | "python3", ["-m", "pip", rest @ ..]) => ("pip", rest),
+            ("uv", ["pip", rest @ ..]) => ("pip", rest),
+            ("git", args) => ("git", git_command(args)),
+            (name, args) => (name, args),
+        };
+        if runs_inline_code(manager, args) {
+            let fetches = format!("whatever its inline {} code fetches", manager);
+            return Self { class: CommandClass::Unknown, fetches };
+        }
+        let operands = operands(args);
+        let subcommand = operands.first().copied().unwrap_or("");
+        let packages = || {
+            operands
+                .iter()
+                .skip(1)
+                .copied()
+                .filter(|operand| !is_requirement_file(args, operand))
+                .collect::<Vec<_>>()
+        };
+
+        let install = match manager {
+            "npm" | "pnpm" | "yarn" | "bun" => match subcommand {
+                "" if manager == "yarn" => Some(Vec::new()),
+                "install" | "i" | "add" | "ci" | "update" | "upgrade" => Some(packages()),
+                _ => None,
+            },
+            "npx" | "pnpx" | "bunx" => Some(operands.iter().take(1).copied().collect()),
+            "pip" | "pip3" => match subcommand {
+                "install" | "download" => Some(packages()),
+                _ => None,
+            },
+            "cargo" => match subcommand {
+                "install" | "add" | "update" | "fetch" => Some(packages()),
+                _ => None,
+            },
+            "go" => match (subcommand, operands.get(1).copied()) {
+                ("get" | "install", _) => Some(packages()),
+                ("mod", Some("download")) => Some(Vec::new()),
+                _ => None,
+            },
+            "gem" | "brew" | "apt" | "apt-get" | "dnf" | "yum" | "apk" => match subcommand {
+                "install" | "add" | "upgrade" | "update" => Some(packages()),
+                _ => None,
+            },
+            "bundle" | "composer" | "poetry" => match subcommand {
+                "install" | "add" | "update" | "require" => Some(packages()),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(packages) = install {
+            let fetches = if packages.is_empty() {
+                format!("{} dependencies of the workspace", manager)
+            } else {
+                format!("{} packages {}", manager, packages.join(", "))
+            };
+            return Self { class: CommandClass::PackageInstall, fetches };
+        }
+
+        let remote = match manager {
+            "curl" | "wget" | "http" | "https" | "xh" | "aria2c" => true,
+            "ssh" | "scp" | "sftp" | "rsync" | "ftp" | "nc" | "telnet" => true,
+            "git" => matches!(subcommand, "clone" | "fetch" | "pull" | "push" | "ls-remote" | "submodule"),
+            _ => false,
+        };
+        if !remote {
+            return local;
+        }
+        let urls: Vec<&str> = args.iter().copied().filter(|arg| looks_remote(arg)).collect();
+        let fetches = match (manager, urls.is_empty()) {
+            ("git", true) => format!("git {} from the configured remote", subcommand),
+            (_, true) => format!("network access by {}", manager),
+            (_, false) => urls.join(", "),
+        };
+        Self { class: CommandClass::Network, fetches }
+    }
+}
+
+/// `command` rewritten to install only what the workspace's lockfiles pin;
+/// other commands are returned unchanged
+pub fn lockfile_only(command: &[String]) -> Result<Vec<String>, PermissionError> {
+    let refuse = || PermissionError::ChangesLockfile(command.join(" "));
+    let assessment = CommandAssessment::of(command);
+    let (wrapper, command) = command.split_at(wrapper_len(command));
+    let rewrapped = |rewritten: Vec<String>| [wrapper.to_vec(), rewritten].concat();
+    let Some(program) = command.first() else {
+        return Ok(wrapper.to_vec());
+    };
+    let args: Vec<&str> = command[1..].iter().map(String::as_str).collect();
+    let operands = operands(&args);
+    let subcommand = operands.first().copied().unwrap_or("");
+    let with = |extra: &str| {
+        let mut rewritten = command.to_vec();
+        if !args.contains(&extra) {
+            rewritten.push(extra.to_string());
+        }
+        rewritten
+    };
+
+    let rewritten = match program_name(program) {
+        // cargo builds resolve dependencies too; pin them to Cargo.lock
+        "cargo" if matches!(subcommand, "build" | "check" | "test" | "run" | "bench" | "fetch" | "doc") => {
+            Ok(with("--locked"))
+        }
+        _ if assessment.class != CommandClass::PackageInstall => Ok(command.to_vec()),
+        "npm" if matches!(subcommand, "install" | "i" | "ci") && operands.len() == 1 => {
+            Ok(vec![program.clone(), "ci".to_string()])
+        }
+        "pnpm" | "yarn" | "bun" if matches!(subcommand, "install" | "") && operands.len() <= 1 => {
+            Ok(with("--frozen-lockfile"))
+        }
+        "pip" | "pip3" | "python" | "python3" | "uv"
+            if args.contains(&"install") && args.iter().any(|arg| matches!(*arg, "-r" | "--requirement")) =>
+        {
+            // Only requirement files with hashes install; bare package names do not
+            let named = operands
+                .iter()
+                .skip_while(|operand| **operand != "install")
+                .skip(1)
+                .any(|operand| !is_requirement_file(&args, operand));
+            if named {
+                Err(refuse())
+            } else {
+                Ok(with("--require-hashes"))
+            }
+        }
+        "go" if subcommand == "mod" => Ok(command.to_vec()),
+        _ => Err(refuse()),
+    };
+    rewritten.map(rewrapped)
+}
+
+/// File name of a program given as a path
+fn program_name(program: &str) -> &str {
+    program.rsplit(['/', '\\']).next().unwrap_or(program)
+}
+
+/// Number of leading arguments that only wrap the command after them:
+/// `env` with its options and variables, `nice` and `timeout`
+fn wrapper_len(command: &[String]) -> usize {
+    let mut start = 0;
+    while let Some(program) = command.get(start) {
+        let rest = &command[start + 1..];
+        start += 1 + match program_name(program) {
+            "env" => leading_options(rest, &["-u", "--unset", "-C", "--chdir"], |arg| arg.contains('=')),
+            "nice" => leading_options(rest, &["-n", "--adjustment"], |_| false),
+            // The duration follows the options
+            "timeout" => leading_options(rest, &["-s", "--signal", "-k", "--kill-after"], |_| false) + 1,
+            _ => return start,
+        };
+    }
+    start.min(command.len())
+}
+
+/// Number of leading options in `args`, counting the values of those in
+/// `with_value` and arguments `also` accepts
+fn leading_options(args: &[String], with_value: &[&str], also: impl Fn(&str) -> bool) -> usize {
+    let mut count = 0;
+    while let Some(arg) = args.get(count) {
+        if arg == "--" {
+            return count + 1;
+        }
+        if with_value.contains(&arg.as_str()) {
+            count += 2;
+        } else if arg.starts_with('-') || also(arg) {
+            count += 1;
+        } else {
+            break;
+        }
+    }
+    count
+}
+
+/// git arguments from the subcommand on, past global options such as
+/// `-C <path>`, `-c <name>=<value>` and `--git-dir <path>`
+fn git_command<'a, 'b>(args: &'b [&'a str]) -> &'b [&'a str] {
+    let mut rest = args;
+    while let [option, tail @ ..] = rest {
+        if !option.starts_with('-') {
+            break;
+        }
+        let takes_value = matches!(*option, "-C" | "-c" | "--git-dir" | "--work-tree" | "--namespace" | "--config-env");
+        rest = if takes_value { tail.get(1..).unwrap_or_default() } else { tail };
+    }
+    rest
+}
+
+/// Whether `program` is an interpreter given code to run on its command
+/// line, e.g. `sh -c`, `node -e` or `python -c`
+fn runs_inline_code(program: &str, args: &[&str]) -> bool {
+    let options = args.iter().take_while(|arg| arg.starts_with('-'));
+    match program {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" => {
+            options.into_iter().any(|arg| *arg == "--command" || (!arg.starts_with("--") && arg.contains('c')))
+        }
+        "python" | "python3" => options.into_iter().any(|arg| !arg.starts_with("--") && arg.ends_with('c')),
+        "node" | "nodejs" => options.into_iter().any(|arg| {
+            matches!(*arg, "-e" | "--eval" | "-p" | "--print" | "-pe") || arg.starts_with("--eval=")
+        }),
+        "perl" | "ruby" => options.into_iter().any(|arg| matches!(*arg, "-e" | "-E")),
+        _ => false,
+    }
+}
+
+/// Arguments that are not options
+fn operands<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    args.iter().copied().filter(|arg| !arg.starts_with('-')).collect()
+}
+
+/// Whether `operand` is the value of a `-r`/`--requirement` option
+fn is_requirement_file(args: &[&str], operand: &str) -> bool {
+    args.windows(2)
+        .any(|pair| matches!(pair[0], "-r" | "--requirement") && pair[1] == operand)
+}
+
+fn looks_remote(arg: &str) -> bool {
+    arg.contains("://") || (arg.contains('@') && arg.contains(':') && !arg.starts_with('-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn installs_and_network_commands_are_classified() {
+        let npm = CommandAssessment::of(&command("npm install lodash left-pad --save"));
+        assert_eq!(npm.class, CommandClass::PackageInstall);
+        assert_eq!(npm.fetches, "npm packages lodash, left-pad");
+        assert_eq!(CommandAssessment::of(&command("python3 -m pip install -r requirements.txt")).class, CommandClass::PackageInstall);
+        assert_eq!(CommandAssessment::of(&command("yarn")).fetches, "yarn dependencies of the workspace");
+
+        let curl = CommandAssessment::of(&command("/usr/bin/curl -sSL https://example.com/install.sh"));
+        assert_eq!((curl.class, curl.fetches.as_str()), (CommandClass::Network, "https://example.com/install.sh"));
+        assert_eq!(CommandAssessment::of(&command("git pull")).class, CommandClass::Network);
+
+        for local in ["cargo test", "npm run build", "git status", "ls -la", ""] {
+            assert_eq!(CommandAssessment::of(&command(local)).class, CommandClass::Local, "{}", local);
+        }
+    }
+
+    #[test]
+    fn wrappers_and_inline_scripts_are_seen_through() {
+        for inline in ["sh -c make", "bash -lc make", "node -e 1", "python3 -c print(1)", "env A=1 perl -e 1"] {
+            let assessment = CommandAssessment::of(&command(inline));
+            assert_eq!(assessment.class, CommandClass::Unknown, "{}", inline);
+            assert!(assessment.class.needs_network());
+        }
+        assert_eq!(CommandAssessment::of(&command("bash scripts/build.sh -c")).class, CommandClass::Local);
+
+        let wrapped = CommandAssessment::of(&command("env -u HOME CI=1 npm install lodash"));
+        assert_eq!((wrapped.class, wrapped.fetches.as_str()), (CommandClass::PackageInstall, "npm packages lodash"));
+        for network in ["nice -n 10 timeout -s KILL 60 git fetch", "git -C ../other fetch", "git -c http.proxy=p --git-dir=.git pull"] {
+            assert_eq!(CommandAssessment::of(&command(network)).class, CommandClass::Network, "{}", network);
+        }
+        assert_eq!(CommandAssessment::of(&command("git -C fetch status")).class, CommandClass::Local);
+        assert_eq!(CommandAssessment::of(&command("timeout 60")).class, CommandClass::Local);
+    }
+
+    #[test]
+    fn lockfile_only_mode_freezes_installs() {
+        assert_eq!(lockfile_only(&command("npm install")).unwrap(), command("npm ci"));
+        assert_eq!(lockfile_only(&command("pnpm install")).unwrap(), command("pnpm install --frozen-lockfile"));
+        assert_eq!(lockfile_only(&command("cargo test --locked")).unwrap(), command("cargo test --locked"));
+        assert_eq!(lockfile_only(&command("cargo build")).unwrap(), command("cargo build --locked"));
+        assert_eq!(
+            lockfile_only(&command("pip install -r requirements.txt")).unwrap(),
+            command("pip install -r requirements.txt --require-hashes")
+        );
+        assert_eq!(lockfile_only(&command("curl https://example.com")).unwrap(), command("curl https://example.com"));
+        assert_eq!(lockfile_only(&command("env CI=1 npm install")).unwrap(), command("env CI=1 npm ci"));
+
+        for changing in ["npm install lodash", "pip install requests", "cargo add serde", "gem install rails"] {
+            assert!(matches!(lockfile_only(&command(changing)), Err(PermissionError::ChangesLockfile(_))), "{}", changing);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Per-run safety limits for agent loops
+//!
+//! A runaway agent can rewrite a whole tree or spin on shell commands. The
+//! [`Guardrails`] of a run count files modified, bytes of diff produced and
+//! commands executed. An action that would cross a configured limit pauses
+//! the run and asks a [`LimitConfirmation`] whether to continue; only an
+//! explicit yes raises the limit, anything else stops the run.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Limits applied to a single agent run; `None` disables a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunLimits {
+    /// Distinct files the run may modify
+    pub max_files_modified: Option<usize>,
+    /// Total size of the diffs the run may produce, in bytes
+    pub max_diff_bytes: Option<usize>,
+    /// Commands the run may execute
+    pub max_commands: Option<usize>,
+}
+
+impl Default for RunLimits {
+    fn default() -> Self {
+        Self {
+            max_files_modified: Some(50),
+            max_diff_bytes: Some(512 * 1024),
+            max_commands: Some(100),
+        }
+    }
+}
+
+/// Which limit was hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    FilesModified,
+    DiffBytes,
+    Commands,
+}
+
+impl std::fmt::Display for LimitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LimitKind::FilesModified => "files modified",
+            LimitKind::DiffBytes => "diff bytes",
+            LimitKind::Commands => "commands executed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// An action that would take the run past a limit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitExceeded {
+    pub kind: LimitKind,
+    /// Usage including the action that was paused
+    pub attempted: usize,
+    pub limit: usize,
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} would reach {} (limit {})", self.kind, self.attempted, self.limit)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GuardrailError {
+    #[error("Agent run stopped: {0}")]
+    Stopped(LimitExceeded),
+}
+
+/// Asked whether a paused run may go past a limit
+pub trait LimitConfirmation {
+    fn confirm(&self, exceeded: &LimitExceeded) -> bool;
+}
+
+/// Asks on the terminal, defaulting to no
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalConfirmation;
+
+impl LimitConfirmation for TerminalConfirmation {
+    fn confirm(&self, exceeded: &LimitExceeded) -> bool {
+        print!("⚠️  Agent run paused: {}. Continue? [y/N] ", exceeded);
+        if std::io::stdout().flush().is_err() {
+            return false;
+        }
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).is_ok()
+            && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    }
+}
+
+/// Usage counted against the limits of one run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub files_modified: BTreeSet<PathBuf>,
+    pub diff_bytes: usize,
+    pub commands: usize,
+    /// Limits the user agreed to go past
+    pub approvals: Vec<LimitExceeded>,
+}
+
+/// Limit enforcement for one agent run
+#[derive(Debug, Clone)]
+pub struct Guardrails {
+    limits: RunLimits,
+    usage: RunUsage,
+}
+
+impl Guardrails {
+    pub fn new(limits: RunLimits) -> Self {
+        Self {
+            limits,
+            usage: RunUsage::default(),
+        }
+    }
+
+    pub fn usage(&self) -> &RunUsage {
+        &self.usage
+    }
+
+    /// Check a file modification producing `diff_bytes` of diff; records it
+    /// when allowed, otherwise pauses for confirmation
+    pub fn file_change(
+        &mut self,
+        path: &Path,
+        diff_bytes: usize,
+        confirm: &dyn LimitConfirmation,
+    ) -> Result<(), GuardrailError> {
+        let new_file = !self.usage.files_modified.contains(path);
+        if new_file {
+            self.check(LimitKind::FilesModified, self.usage.files_modified.len() + 1, confirm)?;
+        }
+        self.check(LimitKind::DiffBytes, self.usage.diff_bytes + diff_bytes, confirm)?;
+
+        self.usage.files_modified.insert(path.to_path_buf());
+        self.usage.diff_bytes += diff_bytes;
+        Ok(())
+    }
+
+    /// Check a command execution; records it when allowed
+    pub fn command(&mut self, confirm: &dyn LimitConfirmation) -> Result<(), GuardrailError> {
+        self.check(LimitKind::Commands, self.usage.commands + 1, confirm)?;
+        self.usage.commands += 1;
+        Ok(())
+    }
+
+    /// Pause at a limit; an approval lifts that limit to what was attempted
+    /// plus its original allowance, so the next pause comes as late again
+    fn check(&mut self, kind: LimitKind, attempted: usize, confirm: &dyn LimitConfirmation) -> Result<(), GuardrailError> {
+        let limit = match kind {
+            LimitKind::FilesModified => &mut self.limits.max_files_modified,
+            LimitKind::DiffBytes => &mut self.limits.max_diff_bytes,
+            LimitKind::Commands => &mut self.limits.max_commands,
+        };
+        let Some(max) = *limit else {
+            return Ok(());
+        };
+        if attempted <= max {
+            return Ok(());
+        }
+
+        let exceeded = LimitExceeded { kind, attempted, limit: max };
+        if !confirm.confirm(&exceeded) {
+            return Err(GuardrailError::Stopped(exceeded));
+        }
+        let original = self.usage.approvals.iter().find(|a| a.kind == kind).map_or(max, |first| first.limit);
+        *limit = Some(attempted + original);
+        self.usage.approvals.push(exceeded);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Answers from a script and remembers what it was asked
+    struct Scripted {
+        answers: RefCell<Vec<bool>>,
+        asked: RefCell<Vec<LimitExceeded>>,
+    }
+
+    impl Scripted {
+        fn new(answers: &[bool]) -> Self {
+            Self {
+                answers: RefCell::new(answers.iter().rev().copied().collect()),
+                asked: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl LimitConfirmation for Scripted {
+        fn confirm(&self, exceeded: &LimitExceeded) -> bool {
+            self.asked.borrow_mut().push(exceeded.clone());
+            self.answers.borrow_mut().pop().unwrap_or(false)
+        }
+    }
+
+    #[test]
+    fn pauses_at_file_limit_and_continues_on_approval() {
+        let limits = RunLimits { max_files_modified: Some(2), max_diff_bytes: None, max_commands: None };
+        let mut guardrails = Guardrails::new(limits);
+        let confirm = Scripted::new(&[true]);
+
+        guardrails.file_change(Path::new("a.rs"), 10, &confirm).unwrap();
+        guardrails.file_change(Path::new("b.rs"), 10, &confirm).unwrap();
+        guardrails.file_change(Path::new("a.rs"), 10, &confirm).unwrap();
+        assert!(confirm.asked.borrow().is_empty());
+
+        guardrails.file_change(Path::new("c.rs"), 10, &confirm).unwrap();
+        assert_eq!(
+            confirm.asked.borrow()[0],
+            LimitExceeded { kind: LimitKind::FilesModified, attempted: 3, limit: 2 }
+        );
+        assert_eq!(guardrails.usage().files_modified.len(), 3);
+        assert_eq!(guardrails.usage().diff_bytes, 40);
+
+        // Approval extends the limit by the original allowance
+        guardrails.file_change(Path::new("d.rs"), 10, &confirm).unwrap();
+        guardrails.file_change(Path::new("e.rs"), 10, &confirm).unwrap();
+        assert_eq!(confirm.asked.borrow().len(), 1);
+    }
+
+    #[test]
+    fn refusal_stops_the_run_without_recording() {
+        let limits = RunLimits { max_files_modified: None, max_diff_bytes: Some(100), max_commands: Some(1) };
+        let mut guardrails = Guardrails::new(limits);
+        let confirm = Scripted::new(&[false, false]);
+
+        guardrails.command(&confirm).unwrap();
+        let err = guardrails.command(&confirm).unwrap_err();
+        assert_eq!(err, GuardrailError::Stopped(LimitExceeded { kind: LimitKind::Commands, attempted: 2, limit: 1 }));
+        assert_eq!(guardrails.usage().commands, 1);
+
+        assert!(guardrails.file_change(Path::new("big.rs"), 500, &confirm).is_err());
+        assert!(guardrails.usage().files_modified.is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+//! Agent run support for PiCode
+//!
+//! Shared types for agent loops: run identifiers, traces of tool usage and
+//! per-run helpers such as the token/cost budget, the tool result cache, the trash that makes
+//! agent file deletions recoverable, the guardrails that pause runaway
+//! runs, the clarification flow that asks instead of guessing, and the
+//! tool registry that enforces a run's permission profile, including the
+//! network tier for commands that install packages or fetch from the network,
+//! the project formatters and linters applied to agent edits, and the
+//! verification pass run before an agent may finish.
+
+pub mod budget;
+pub mod clarify;
+pub mod command_class;
+pub mod guardrails;
+pub mod permissions;
+pub mod project_tools;
+pub mod report;
+pub mod tool_cache;
+pub mod tools;
+pub mod trash;
+pub mod verify;
+
+pub use crate::id::{AgentRunId, TrashId};
+pub use budget::{BudgetReport, BudgetStatus, BudgetTracker, RunBudget, WRAP_UP_INSTRUCTIONS};
+pub use clarify::{
+    AgentPlan, Clarification, ClarificationPrompt, ClarifyingQuestion, TerminalClarification, PLAN_INSTRUCTIONS,
+};
+pub use command_class::{CommandAssessment, CommandClass};
+pub use guardrails::{
+    GuardrailError, Guardrails, LimitConfirmation, LimitExceeded, LimitKind, RunLimits, RunUsage,
+    TerminalConfirmation,
+};
+pub use permissions::{PermissionError, PermissionProfile, BUILTIN_PROFILES, DEFAULT_PROFILE};
+pub use project_tools::{LintFinding, ProjectTool, ProjectToolSettings, ProjectTooling};
+pub use report::{AgentRunReport, CommandRecord, FileChange, ReportError, RUNS_DIR};
+pub use tool_cache::{CachedToolResult, ToolCache, ToolCacheStats};
+pub use tools::{AgentTool, ToolContext, ToolError, ToolRegistry};
+pub use trash::{Trash, TrashEntry, TrashError, TrashMode, TRASH_DIR};
+pub use verify::{CheckOutcome, VerificationRound, VerifySettings, CRITIC_INSTRUCTIONS};
+
+use serde::{Deserialize, Serialize};
+
+/// A single tool invocation made during an agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    pub output_bytes: usize,
+    pub cached: bool,
+    pub duration: std::time::Duration,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Trace of everything an agent did during a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentTrace {
+    pub run_id: AgentRunId,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub tool_calls: Vec<ToolCallRecord>,
+    pub cache_stats: ToolCacheStats,
+    /// Files the run's tools changed, in order
+    #[serde(default)]
+    pub file_changes: Vec<FileChange>,
+    /// Shell commands the run's tools ran, in order
+    #[serde(default)]
+    pub commands: Vec<CommandRecord>,
+}
+
+impl AgentTrace {
+    pub fn new(run_id: AgentRunId) -> Self {
+        Self {
+            run_id,
+            started_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            cache_stats: ToolCacheStats::default(),
+            file_changes: Vec::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn record_tool_call(&mut self, record: ToolCallRecord) {
+        self.tool_calls.push(record);
+    }
+
+    pub fn record_file_change(&mut self, path: std::path::PathBuf, diff: String) {
+        self.file_changes.push(FileChange { path, diff });
+    }
+
+    pub fn record_command(&mut self, command: String, exit_code: Option<i32>, duration: std::time::Duration) {
+        self.commands.push(CommandRecord { command, exit_code, duration });
+    }
+
+    /// Copy the latest cache statistics into the trace
+    pub fn update_cache_stats(&mut self, cache: &ToolCache) {
+        self.cache_stats = cache.stats().clone();
+    }
+
+    pub fn tool_call_count(&self) -> usize {
+        self.tool_calls.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agent_run_id_generation() {
+        assert_ne!(AgentRunId::new(), AgentRunId::new());
+    }
+
+    #[test]
+    fn trace_records_cache_stats() {
+        let mut cache = ToolCache::new();
+        let args = serde_json::json!({ "path": "src/lib.rs" });
+        cache.insert("read_file", &args, "contents".to_string());
+        assert!(cache.get("read_file", &args).is_some());
+
+        let mut trace = AgentTrace::new(AgentRunId::new());
+        trace.update_cache_stats(&cache);
+
+        assert_eq!(trace.cache_stats.hits, 1);
+        assert_eq!(trace.cache_stats.entries, 1);
+    }
+}
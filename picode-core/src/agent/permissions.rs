@@ -0,0 +1,243 @@
+//! Tool permission profiles
+//!
+//! A [`PermissionProfile`] names the tools an agent may call and the
+//! workspace paths those calls may touch. Four profiles are built in:
+//! `reader` (read and list files), `editor` (also write files, outside
+//! `.git` and `.picode`), `operator` (every tool, including commands) and
+//! `network` (also commands that install packages or use the network).
+//! Profiles in the configuration override built-ins of the same name. The
+//! [`ToolRegistry`](super::ToolRegistry) checks every call against the
+//! profile of the run.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path};
+use thiserror::Error;
+
+/// Profile used when none is selected
+pub const DEFAULT_PROFILE: &str = "editor";
+
+/// Names of the built-in profiles
+pub const BUILTIN_PROFILES: &[&str] = &["reader", "editor", "operator", "network"];
+
+/// Permission errors
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PermissionError {
+    #[error("unknown permission profile '{0}' (built in: reader, editor, operator, network)")]
+    UnknownProfile(String),
+
+    #[error("tool '{tool}' is not allowed with '{profile}' permissions")]
+    ToolDenied { tool: String, profile: String },
+
+    #[error("'{path}' is outside the paths '{profile}' permissions allow")]
+    PathDenied { path: String, profile: String },
+
+    #[error("'{0}' is outside the workspace")]
+    OutsideWorkspace(String),
+
+    #[error("invalid pattern '{0}'")]
+    InvalidPattern(String),
+
+    #[error("'{command}' fetches {fetches}, which '{profile}' permissions do not allow")]
+    NetworkDenied { command: String, fetches: String, profile: String },
+
+    #[error("'{0}' would change the lockfiles, which lockfile-only runs refuse")]
+    ChangesLockfile(String),
+}
+
+/// Tools and workspace paths an agent may use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionProfile {
+    #[serde(default)]
+    pub description: String,
+
+    /// Tool names allowed; `*` matches any part of a name
+    pub tools: Vec<String>,
+
+    /// Workspace-relative globs tool paths must match (`**` crosses
+    /// directories); empty allows the whole workspace
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    /// Globs refused even when `paths` matches
+    #[serde(default)]
+    pub deny_paths: Vec<String>,
+
+    /// Whether commands may install packages or use the network
+    #[serde(default)]
+    pub network: bool,
+}
+
+impl PermissionProfile {
+    /// A built-in profile by name
+    pub fn builtin(name: &str) -> Option<Self> {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let profile = match name {
+            "reader" => Self {
+                description: "Read and list workspace files".to_string(),
+                tools: strings(&["read_file", "list_files", "list_todos"]),
+                paths: Vec::new(),
+                deny_paths: Vec::new(),
+                network: false,
+            },
+            "editor" => Self {
+                description: "Read and write workspace files, no commands".to_string(),
+                tools: strings(&["read_file", "list_files", "write_file", "list_todos", "update_todo"]),
+                paths: Vec::new(),
+                deny_paths: strings(&[".git", ".git/**", ".picode", ".picode/**"]),
+                network: false,
+            },
+            "operator" => Self {
+                description: "Every tool, including commands, without network access".to_string(),
+                tools: strings(&["*"]),
+                paths: Vec::new(),
+                deny_paths: Vec::new(),
+                network: false,
+            },
+            "network" => Self {
+                description: "Every tool, including package installs and network commands".to_string(),
+                tools: strings(&["*"]),
+                paths: Vec::new(),
+                deny_paths: Vec::new(),
+                network: true,
+            },
+            _ => return None,
+        };
+        Some(profile)
+    }
+
+    /// `name` from `configured`, falling back to the built-ins
+    pub fn resolve(name: &str, configured: &HashMap<String, PermissionProfile>) -> Result<Self, PermissionError> {
+        configured
+            .get(name)
+            .cloned()
+            .or_else(|| Self::builtin(name))
+            .ok_or_else(|| PermissionError::UnknownProfile(name.to_string()))
+    }
+
+    pub fn allows_tool(&self, tool: &str) -> bool {
+        self.tools.iter().any(|pattern| glob_match(pattern, tool))
+    }
+
+    /// Whether a workspace-relative, `/`-separated path may be touched
+    pub fn allows_path(&self, path: &str) -> bool {
+        let allowed = self.paths.is_empty() || self.paths.iter().any(|pattern| glob_match(pattern, path));
+        allowed && !self.deny_paths.iter().any(|pattern| glob_match(pattern, path))
+    }
+}
+
+/// `path` relative to `root` as a `/`-separated string; absolute paths must
+/// be inside `root` and `..` is refused. Where the workspace is on disk,
+/// symlinks are resolved first (through the nearest existing ancestor of a
+/// path yet to be created), so a link cannot lead out of the workspace or
+/// around a denied path.
+pub fn workspace_relative(root: &Path, path: &str) -> Result<String, PermissionError> {
+    let candidate = Path::new(path);
+    let relative = match crate::paths::strip_root(candidate, root) {
+        Some(relative) => relative,
+        None if candidate.has_root() => return Err(PermissionError::OutsideWorkspace(path.to_string())),
+        None => candidate,
+    };
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            _ => return Err(PermissionError::OutsideWorkspace(path.to_string())),
+        }
+    }
+    let relative = parts.join("/");
+    let Ok(real_root) = crate::paths::canonicalize(root) else {
+        return Ok(relative);
+    };
+    let real = crate::paths::canonicalize_existing(real_root.join(&relative))
+        .map_err(|_| PermissionError::OutsideWorkspace(path.to_string()))?;
+    match crate::paths::strip_root(&real, &real_root) {
+        Some(inside) => Ok(crate::paths::to_slash(inside)),
+        None => Err(PermissionError::OutsideWorkspace(path.to_string())),
+    }
+}
+
+/// Match `text` against a glob: `**` matches across `/`, `*` and `?` do not
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut regex = String::from("^");
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            regex.push_str("(?:.*/)?");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("**") {
+            regex.push_str(".*");
+            rest = after;
+        } else {
+            match c {
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    regex.push('$');
+    // Patterns are built from escaped text, so they always compile
+    Regex::new(&regex).is_ok_and(|regex| regex.is_match(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn builtin_profiles_limit_tools_and_paths() {
+        let reader = PermissionProfile::builtin("reader").unwrap();
+        assert!(reader.allows_tool("read_file"));
+        assert!(!reader.allows_tool("write_file"));
+
+        let editor = PermissionProfile::builtin("editor").unwrap();
+        assert!(editor.allows_path("src/lib.rs"));
+        assert!(!editor.allows_path(".git/config") && !editor.allows_path(".git"));
+        assert!(!editor.allows_tool("run_command"));
+        assert!(PermissionProfile::builtin("operator").unwrap().allows_tool("run_command"));
+
+        let docs = PermissionProfile {
+            description: String::new(),
+            tools: vec!["*_file".to_string()],
+            paths: vec!["docs/**".to_string(), "*.md".to_string()],
+            deny_paths: vec!["**/secret*".to_string()],
+            network: false,
+        };
+        assert!(docs.allows_tool("write_file") && !docs.allows_tool("run_command"));
+        assert!(docs.allows_path("docs/guide/intro.md") && docs.allows_path("README.md"));
+        assert!(!docs.allows_path("src/README.md") && !docs.allows_path("docs/secret.md"));
+
+        let configured = HashMap::from([("reader".to_string(), docs.clone())]);
+        assert_eq!(PermissionProfile::resolve("reader", &configured).unwrap(), docs);
+        assert!(PermissionProfile::resolve("root", &configured).is_err());
+    }
+
+    #[test]
+    fn paths_stay_inside_the_workspace() {
+        let root = PathBuf::from("/repo");
+        assert_eq!(workspace_relative(&root, "./src/lib.rs").unwrap(), "src/lib.rs");
+        assert_eq!(workspace_relative(&root, "/repo/src").unwrap(), "src");
+        assert!(workspace_relative(&root, "../etc/passwd").is_err());
+        assert!(workspace_relative(&root, "/etc/passwd").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_resolved_before_matching() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join(".git"), dir.path().join("notes")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("out")).unwrap();
+
+        assert_eq!(workspace_relative(dir.path(), "notes/hooks/pre-commit").unwrap(), ".git/hooks/pre-commit");
+        assert_eq!(workspace_relative(dir.path(), "notes").unwrap(), ".git");
+        assert!(workspace_relative(dir.path(), "out/new.txt").is_err());
+        assert_eq!(workspace_relative(dir.path(), "src/new.rs").unwrap(), "src/new.rs");
+    }
+}
@@ -0,0 +1,349 @@
+//! Project formatters and linters for agent edits
+//!
+//! [`ProjectTooling::detect`] looks at the manifests the workspace analyzer
+//! reads (`Cargo.toml`, `package.json`, `pyproject.toml`) and the tools'
+//! own config files to find what the project formats and lints with:
+//! rustfmt and clippy, prettier and eslint, black or ruff. Content an agent
+//! writes goes through the formatter before the write, so the formatting
+//! is part of the same edit rather than a second one. After a turn's
+//! edits, the linters run over the changed files and their findings are
+//! handed back to the agent.
+
+use crate::editor::Severity;
+use crate::io::{FileSystem, ProcessRunner};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Placeholder in tool arguments replaced with the workspace-relative path
+pub const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Findings reported back at most per turn
+const MAX_FINDINGS: usize = 50;
+
+/// `[agent.project_tools]` settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectToolSettings {
+    /// Run written content through the project's formatter
+    pub format: bool,
+    /// Lint the files a turn changed and report the findings to the agent
+    pub lint: bool,
+}
+
+impl Default for ProjectToolSettings {
+    fn default() -> Self {
+        Self { format: true, lint: true }
+    }
+}
+
+/// A formatter or linter of the project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectTool {
+    pub name: &'static str,
+    pub program: String,
+    /// `{file}` is replaced with the file's path; linters without it are
+    /// run once for the whole project, others get the changed files appended
+    pub args: Vec<String>,
+    /// File extensions the tool handles
+    pub extensions: &'static [&'static str],
+}
+
+impl ProjectTool {
+    fn new(name: &'static str, program: &str, args: &[&str], extensions: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            extensions,
+        }
+    }
+
+    pub fn handles(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| self.extensions.contains(&extension))
+    }
+}
+
+/// A lint finding in a changed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    /// Workspace-relative, `/`-separated path
+    pub path: String,
+    /// 1-based
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+    pub source: &'static str,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Hint => "hint",
+        };
+        write!(f, "{}:{}: {} ({}): {}", self.path, self.line, severity, self.source, self.message)
+    }
+}
+
+const RUST: &[&str] = &["rs"];
+const JS: &[&str] = &["js", "jsx", "ts", "tsx", "mjs", "cjs", "vue"];
+const PRETTIER: &[&str] = &["js", "jsx", "ts", "tsx", "mjs", "cjs", "vue", "json", "css", "scss", "md", "html", "yaml", "yml"];
+const PYTHON: &[&str] = &["py", "pyi"];
+
+/// Formatters and linters detected for a workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectTooling {
+    pub formatters: Vec<ProjectTool>,
+    pub linters: Vec<ProjectTool>,
+}
+
+impl ProjectTooling {
+    /// Detect the tools of the project at `root` from its manifests and
+    /// tool config files
+    pub async fn detect(fs: &dyn FileSystem, root: &Path) -> Self {
+        let mut tooling = Self::default();
+        let read = |name: &str| {
+            let path = root.join(name);
+            async move { fs.read_to_string(&path).await.ok() }
+        };
+        let exists = |name: &str| {
+            let path = root.join(name);
+            async move { fs.exists(&path).await }
+        };
+
+        if let Some(cargo) = read("Cargo.toml").await {
+            let edition = manifest_edition(&cargo).unwrap_or_else(|| "2021".to_string());
+            tooling.formatters.push(ProjectTool::new("rustfmt", "rustfmt", &["--edition", &edition], RUST));
+            tooling
+                .linters
+                .push(ProjectTool::new("clippy", "cargo", &["clippy", "--quiet", "--message-format", "short"], RUST));
+        }
+
+        if let Some(package) = read("package.json").await {
+            let mut prettier = package.contains("\"prettier\"");
+            for config in [".prettierrc", ".prettierrc.json", ".prettierrc.js", "prettier.config.js"] {
+                prettier |= exists(config).await;
+            }
+            if prettier {
+                tooling.formatters.push(ProjectTool::new(
+                    "prettier",
+                    "npx",
+                    &["--no-install", "prettier", "--stdin-filepath", FILE_PLACEHOLDER],
+                    PRETTIER,
+                ));
+            }
+            let mut eslint = package.contains("\"eslint\"");
+            for config in [".eslintrc", ".eslintrc.json", ".eslintrc.js", "eslint.config.js", "eslint.config.mjs"] {
+                eslint |= exists(config).await;
+            }
+            if eslint {
+                tooling.linters.push(ProjectTool::new("eslint", "npx", &["--no-install", "eslint", "--format", "unix"], JS));
+            }
+        }
+
+        let pyproject = read("pyproject.toml").await.unwrap_or_default();
+        let ruff = pyproject.contains("[tool.ruff") || exists("ruff.toml").await || exists(".ruff.toml").await;
+        if pyproject.contains("[tool.black") {
+            tooling.formatters.push(ProjectTool::new("black", "black", &["--quiet", "-"], PYTHON));
+        } else if ruff {
+            tooling
+                .formatters
+                .push(ProjectTool::new("ruff", "ruff", &["format", "--stdin-filename", FILE_PLACEHOLDER], PYTHON));
+        }
+        if ruff {
+            tooling.linters.push(ProjectTool::new("ruff", "ruff", &["check", "--output-format", "concise"], PYTHON));
+        }
+        tooling
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.formatters.is_empty() && self.linters.is_empty()
+    }
+
+    /// Names of the detected tools, for logs
+    pub fn names(&self) -> Vec<&'static str> {
+        self.formatters.iter().chain(&self.linters).map(|tool| tool.name).collect()
+    }
+
+    /// `content` as the project's formatter for `relative` writes it, with
+    /// the formatter's name when that changed anything. Content the
+    /// formatter rejects, e.g. because it does not parse, is kept as is.
+    pub async fn format(
+        &self,
+        runner: &dyn ProcessRunner,
+        root: &Path,
+        relative: &Path,
+        content: &str,
+    ) -> Option<(String, &'static str)> {
+        let formatter = self.formatters.iter().find(|tool| tool.handles(relative))?;
+        let file = crate::paths::to_slash(relative);
+        let args: Vec<String> = formatter.args.iter().map(|arg| arg.replace(FILE_PLACEHOLDER, &file)).collect();
+        match runner.run(&formatter.program, &args, Some(root), &[], Some(content.as_bytes())).await {
+            Ok(output) if output.success() => {
+                let formatted = String::from_utf8(output.stdout).ok()?;
+                (!formatted.is_empty() && formatted != content).then_some((formatted, formatter.name))
+            }
+            Ok(output) => {
+                tracing::debug!("{} left {} unformatted: {}", formatter.name, file, String::from_utf8_lossy(&output.stderr));
+                None
+            }
+            Err(e) => {
+                tracing::debug!("{} could not run: {}", formatter.name, e);
+                None
+            }
+        }
+    }
+
+    /// Run the linters over the changed workspace-relative `files`;
+    /// findings in other files are left out
+    pub async fn lint(&self, runner: &dyn ProcessRunner, root: &Path, files: &[PathBuf]) -> Vec<LintFinding> {
+        let mut findings = Vec::new();
+        for linter in &self.linters {
+            let changed: Vec<String> =
+                files.iter().filter(|file| linter.handles(file)).map(|file| crate::paths::to_slash(file)).collect();
+            if changed.is_empty() {
+                continue;
+            }
+            let mut args = linter.args.clone();
+            if linter.name != "clippy" {
+                args.extend(changed.iter().cloned());
+            }
+            let output = match runner.run(&linter.program, &args, Some(root), &[], None).await {
+                Ok(output) => output,
+                Err(e) => {
+                    tracing::debug!("{} could not run: {}", linter.name, e);
+                    continue;
+                }
+            };
+            // Linters exit non-zero when they find problems
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push('\n');
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            findings.extend(
+                text.lines()
+                    .filter_map(|line| parse_lint_line(line, root, linter.name))
+                    .filter(|finding| changed.contains(&finding.path)),
+            );
+        }
+        findings.dedup();
+        findings
+    }
+}
+
+/// Findings as one line each, for the agent transcript
+pub fn render_findings(findings: &[LintFinding]) -> String {
+    let mut out = String::new();
+    for finding in findings.iter().take(MAX_FINDINGS) {
+        let _ = writeln!(out, "{}", finding);
+    }
+    if findings.len() > MAX_FINDINGS {
+        let _ = writeln!(out, "... and {} more", findings.len() - MAX_FINDINGS);
+    }
+    out
+}
+
+/// `edition = "2021"` of a Cargo manifest's `[package]`
+fn manifest_edition(manifest: &str) -> Option<String> {
+    manifest.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "edition").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// `path:line[:column]: [severity:] message`, the shape shared by clippy's
+/// short format, ruff's concise format and eslint's unix format
+fn parse_lint_line(line: &str, root: &Path, source: &'static str) -> Option<LintFinding> {
+    let mut parts = line.splitn(3, ':');
+    let path = parts.next()?.trim();
+    let line_no: usize = parts.next()?.trim().parse().ok()?;
+    let mut rest = parts.next()?;
+    if let Some((column, after)) = rest.split_once(':') {
+        if column.trim().parse::<usize>().is_ok() {
+            rest = after;
+        }
+    }
+    let (severity, message) = match rest.split_once(':').and_then(|(severity, message)| Some((Severity::parse(severity)?, message))) {
+        Some(parsed) => parsed,
+        // eslint marks errors as `[Error/rule]`; other findings are warnings
+        None if rest.contains("[Error/") => (Severity::Error, rest),
+        None => (Severity::Warning, rest),
+    };
+    let path = Path::new(path);
+    let relative = crate::paths::strip_root(path, root).unwrap_or(path);
+    Some(LintFinding {
+        path: crate::paths::to_slash(relative),
+        line: line_no,
+        severity,
+        message: message.trim().to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{MemoryFileSystem, ProcessOutput};
+    use async_trait::async_trait;
+    use std::io;
+
+    /// Formats by trimming trailing spaces; lints with canned output
+    #[derive(Debug)]
+    struct Scripted;
+
+    #[async_trait]
+    impl ProcessRunner for Scripted {
+        async fn run(
+            &self,
+            program: &str,
+            _args: &[String],
+            _working_dir: Option<&Path>,
+            _env: &[(String, String)],
+            stdin: Option<&[u8]>,
+        ) -> io::Result<ProcessOutput> {
+            let (stdout, stderr) = match program {
+                "rustfmt" => {
+                    let input = String::from_utf8_lossy(stdin.unwrap_or_default()).into_owned();
+                    (input.lines().map(|line| format!("{}\n", line.trim_end())).collect::<String>(), String::new())
+                }
+                _ => (
+                    String::new(),
+                    "src/lib.rs:3:9: warning: unused variable: `x`\nsrc/other.rs:1:1: error: mismatched types\n".to_string(),
+                ),
+            };
+            Ok(ProcessOutput { exit_code: Some(0), stdout: stdout.into_bytes(), stderr: stderr.into_bytes() })
+        }
+    }
+
+    #[tokio::test]
+    async fn rust_projects_are_formatted_and_linted() {
+        let fs = MemoryFileSystem::new();
+        let root = Path::new("/repo");
+        fs.write(&root.join("Cargo.toml"), b"[package]\nname = \"demo\"\nedition = \"2018\"\n").await.unwrap();
+        let tooling = ProjectTooling::detect(&fs, root).await;
+        assert_eq!(tooling.names(), vec!["rustfmt", "clippy"]);
+        assert_eq!(tooling.formatters[0].args, vec!["--edition", "2018"]);
+
+        let formatted = tooling.format(&Scripted, root, Path::new("src/lib.rs"), "fn f() {}   \n").await;
+        assert_eq!(formatted, Some(("fn f() {}\n".to_string(), "rustfmt")));
+        assert_eq!(tooling.format(&Scripted, root, Path::new("src/lib.rs"), "fn f() {}\n").await, None);
+        assert_eq!(tooling.format(&Scripted, root, Path::new("notes.md"), "x  \n").await, None);
+
+        let findings = tooling.lint(&Scripted, root, &[PathBuf::from("src/lib.rs")]).await;
+        assert_eq!(render_findings(&findings), "src/lib.rs:3: warning (clippy): unused variable: `x`\n");
+    }
+
+    #[test]
+    fn lint_lines_of_each_tool_are_parsed() {
+        let root = Path::new("/repo");
+        let eslint = parse_lint_line("/repo/src/app.js:4:1: Unexpected var. [Error/no-var]", root, "eslint").unwrap();
+        assert_eq!((eslint.path.as_str(), eslint.line, eslint.severity), ("src/app.js", 4, Severity::Error));
+        let ruff = parse_lint_line("app/main.py:1:8: F401 [*] `os` imported but unused", root, "ruff").unwrap();
+        assert_eq!((ruff.severity, ruff.message.as_str()), (Severity::Warning, "F401 [*] `os` imported but unused"));
+        assert!(parse_lint_line("Checking demo v0.1.0", root, "clippy").is_none());
+    }
+}
@@ -0,0 +1,320 @@
+//! Exportable agent run reports
+//!
+//! After a run, a structured report (task, plan, tools used, file diffs,
+//! commands with exit codes, token and cost totals, duration) is written to
+//! `.picode/runs/<id>.json` alongside a markdown rendering for code review.
+
+use super::budget::BudgetReport;
+use super::verify::VerificationRound;
+use super::{AgentRunId, AgentTrace};
+use crate::id::{AgentRunKind, IdError, IdPrefix};
+use crate::io::FileSystem;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Directory (relative to the workspace root) where run reports are stored
+pub const RUNS_DIR: &str = ".picode/runs";
+
+/// A file changed during the run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// Unified diff of the change
+    pub diff: String,
+}
+
+/// A shell command run by the agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandRecord {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub duration: std::time::Duration,
+}
+
+/// Token and cost totals for the run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Structured report of a completed agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRunReport {
+    pub run_id: AgentRunId,
+    pub task: String,
+    pub plan: Vec<String>,
+    /// Tool name to number of invocations
+    pub tools_used: BTreeMap<String, usize>,
+    pub files_changed: Vec<FileChange>,
+    pub commands: Vec<CommandRecord>,
+    pub usage: UsageTotals,
+    /// Budget the run had and how much of it was consumed
+    #[serde(default)]
+    pub budget: Option<BudgetReport>,
+    /// Permission profile the run's tools were limited to
+    #[serde(default)]
+    pub permissions: Option<String>,
+    /// Verification passes run when the agent said it was done
+    #[serde(default)]
+    pub verification: Vec<VerificationRound>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl AgentRunReport {
+    /// Start a report from the run's trace, with the files and commands it
+    /// recorded
+    pub fn from_trace(task: String, trace: &AgentTrace) -> Self {
+        let mut tools_used = BTreeMap::new();
+        for call in &trace.tool_calls {
+            *tools_used.entry(call.tool.clone()).or_insert(0) += 1;
+        }
+
+        Self {
+            run_id: trace.run_id.clone(),
+            task,
+            plan: Vec::new(),
+            tools_used,
+            files_changed: trace.file_changes.clone(),
+            commands: trace.commands.clone(),
+            usage: UsageTotals::default(),
+            budget: None,
+            permissions: None,
+            verification: Vec::new(),
+            started_at: trace.started_at,
+            finished_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn with_plan(mut self, plan: Vec<String>) -> Self {
+        self.plan = plan;
+        self
+    }
+
+    /// Record the run's budget; its consumption becomes the usage totals
+    pub fn with_budget(mut self, budget: BudgetReport) -> Self {
+        self.usage = budget.used.clone();
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn with_permissions(mut self, profile: impl Into<String>) -> Self {
+        self.permissions = Some(profile.into());
+        self
+    }
+
+    pub fn with_verification(mut self, rounds: Vec<VerificationRound>) -> Self {
+        self.verification = rounds;
+        self
+    }
+
+    pub fn add_file_change(&mut self, path: PathBuf, diff: String) {
+        self.files_changed.push(FileChange { path, diff });
+    }
+
+    pub fn add_command(&mut self, command: String, exit_code: Option<i32>, duration: std::time::Duration) {
+        self.commands.push(CommandRecord {
+            command,
+            exit_code,
+            duration,
+        });
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        self.finished_at - self.started_at
+    }
+
+    /// Render the report as markdown
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# Agent run {}\n\n", self.run_id);
+        md.push_str(&format!("**Task:** {}\n\n", self.task));
+        md.push_str(&format!(
+            "**Started:** {}  \n**Duration:** {}s\n\n",
+            self.started_at.to_rfc3339(),
+            self.duration().num_seconds()
+        ));
+        if let Some(permissions) = &self.permissions {
+            md.push_str(&format!("**Permissions:** {}\n\n", permissions));
+        }
+
+        if !self.plan.is_empty() {
+            md.push_str("## Plan\n\n");
+            for (i, step) in self.plan.iter().enumerate() {
+                md.push_str(&format!("{}. {}\n", i + 1, step));
+            }
+            md.push('\n');
+        }
+
+        if !self.tools_used.is_empty() {
+            md.push_str("## Tools used\n\n| Tool | Calls |\n|------|-------|\n");
+            for (tool, count) in &self.tools_used {
+                md.push_str(&format!("| {} | {} |\n", tool, count));
+            }
+            md.push('\n');
+        }
+
+        if !self.commands.is_empty() {
+            md.push_str("## Commands\n\n| Command | Exit code | Duration |\n|---------|-----------|----------|\n");
+            for cmd in &self.commands {
+                let exit = cmd.exit_code.map_or("-".to_string(), |c| c.to_string());
+                md.push_str(&format!("| `{}` | {} | {:.1}s |\n", cmd.command, exit, cmd.duration.as_secs_f64()));
+            }
+            md.push('\n');
+        }
+
+        if !self.verification.is_empty() {
+            md.push_str("## Verification\n\n");
+            for (i, round) in self.verification.iter().enumerate() {
+                md.push_str(&format!("{}. {}\n", i + 1, round.summary()));
+            }
+            md.push('\n');
+        }
+
+        if !self.files_changed.is_empty() {
+            md.push_str("## Files changed\n\n");
+            for change in &self.files_changed {
+                md.push_str(&format!("### {}\n\n```diff\n{}\n```\n\n", change.path.display(), change.diff.trim_end()));
+            }
+        }
+
+        md.push_str(&format!(
+            "## Usage\n\n- Prompt tokens: {}\n- Completion tokens: {}\n- Cost: ${:.4}\n",
+            self.usage.prompt_tokens, self.usage.completion_tokens, self.usage.cost_usd
+        ));
+        if let Some(budget) = &self.budget {
+            md.push_str(&format!(
+                "- Budget: {} ({:.0}% used{})\n",
+                budget.budget,
+                budget.fraction_used * 100.0,
+                if budget.wrapped_up { ", asked to wrap up" } else { "" }
+            ));
+        }
+        md
+    }
+
+    /// Save the report as JSON and markdown under the runs directory
+    #[cfg(feature = "native")]
+    pub async fn save(&self, runs_dir: &Path) -> Result<PathBuf, ReportError> {
+        self.save_with(&crate::io::NativeFileSystem, runs_dir).await
+    }
+
+    /// Save the report through the given file system
+    pub async fn save_with(&self, fs: &dyn FileSystem, runs_dir: &Path) -> Result<PathBuf, ReportError> {
+        fs.create_dir_all(runs_dir).await?;
+
+        let json_path = runs_dir.join(format!("{}.json", self.run_id));
+        fs.write(&json_path, serde_json::to_string_pretty(self)?.as_bytes()).await?;
+        fs.write(&runs_dir.join(format!("{}.md", self.run_id)), self.to_markdown().as_bytes())
+            .await?;
+
+        Ok(json_path)
+    }
+
+    /// Load a report by full id or unambiguous id prefix
+    #[cfg(feature = "native")]
+    pub async fn load(runs_dir: &Path, id: &IdPrefix<AgentRunKind>) -> Result<Self, ReportError> {
+        Self::load_with(&crate::io::NativeFileSystem, runs_dir, id).await
+    }
+
+    /// Load a report through the given file system
+    pub async fn load_with(fs: &dyn FileSystem, runs_dir: &Path, id: &IdPrefix<AgentRunKind>) -> Result<Self, ReportError> {
+        // Reports of earlier versions are named by UUID, so match on the parsed ids
+        let reports: Vec<(AgentRunId, PathBuf)> = fs
+            .read_dir(runs_dir)
+            .await?
+            .into_iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| Some((path.file_stem()?.to_str()?.parse().ok()?, path)))
+            .collect();
+        let run_id = id.resolve(reports.iter().map(|(run_id, _)| run_id.clone()))?;
+        let (_, path) = reports.iter().find(|(candidate, _)| *candidate == run_id).expect("resolved among the reports");
+        let content = fs.read_to_string(path).await?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+/// Report-related errors
+#[derive(Error, Debug)]
+pub enum ReportError {
+    #[error(transparent)]
+    Id(#[from] IdError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ToolCallRecord;
+    use tempfile::tempdir;
+
+    fn report() -> AgentRunReport {
+        let mut trace = AgentTrace::new(AgentRunId::new());
+        for tool in ["read_file", "read_file", "grep"] {
+            trace.record_tool_call(ToolCallRecord {
+                tool: tool.to_string(),
+                arguments: serde_json::json!({}),
+                output_bytes: 0,
+                cached: false,
+                duration: std::time::Duration::from_millis(5),
+                timestamp: chrono::Utc::now(),
+            });
+        }
+        trace.record_command("cargo test".to_string(), Some(0), std::time::Duration::from_secs(2));
+        trace.record_file_change(PathBuf::from("src/lib.rs"), "-old\n+new\n".to_string());
+
+        AgentRunReport::from_trace("Fix the build".to_string(), &trace)
+            .with_plan(vec!["Reproduce".to_string(), "Patch".to_string()])
+    }
+
+    #[test]
+    fn report_aggregates_tools_and_renders_markdown() {
+        let report = report();
+        assert_eq!(report.tools_used.get("read_file"), Some(&2));
+        assert_eq!(report.tools_used.get("grep"), Some(&1));
+
+        let md = report.to_markdown();
+        assert!(md.contains("**Task:** Fix the build"));
+        assert!(md.contains("2. Patch"));
+        assert!(md.contains("| `cargo test` | 0 | 2.0s |"));
+        assert!(md.contains("### src/lib.rs"));
+    }
+
+    #[tokio::test]
+    async fn report_save_and_load_by_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let report = report();
+        report.save(temp_dir.path()).await.unwrap();
+
+        let id = report.run_id.to_string();
+        let loaded = AgentRunReport::load(temp_dir.path(), &id[..8].to_lowercase().parse().unwrap()).await.unwrap();
+        assert_eq!(loaded.task, "Fix the build");
+        assert!(temp_dir.path().join(format!("{}.md", id)).exists());
+
+        assert!(matches!(
+            AgentRunReport::load(temp_dir.path(), &"zzzz".parse().unwrap()).await,
+            Err(ReportError::Id(IdError::NotFound { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn report_roundtrip_through_memory_file_system() {
+        let fs = crate::io::MemoryFileSystem::new();
+        let runs_dir = Path::new("/ws").join(RUNS_DIR);
+        let report = report();
+
+        report.save_with(&fs, &runs_dir).await.unwrap();
+        let loaded = AgentRunReport::load_with(&fs, &runs_dir, &report.run_id.to_string().parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(loaded.plan.len(), 2);
+    }
+}
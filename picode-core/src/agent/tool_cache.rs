@@ -0,0 +1,215 @@
+//! Scoped memory of tool results within an agent run
+//!
+//! Models frequently repeat the same read-only tool call (the same
+//! `list_files`, the same `read_file`) while looping. The cache keys results by tool name
+//! and normalized arguments so repeats are answered without re-running the
+//! tool, and tracks statistics for the agent trace.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Marker prepended to outputs served from the cache
+pub const CACHE_MARKER: &str = "[cached result]";
+
+/// Tools whose results are safe to reuse within a run
+const DEFAULT_CACHEABLE_TOOLS: &[&str] = &["read_file", "list_files", "list_todos"];
+
+/// Cache statistics exposed in the agent trace
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub invalidations: u64,
+    /// Output bytes served from the cache instead of re-running tools
+    pub bytes_saved: u64,
+}
+
+impl ToolCacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// A tool result returned from the cache
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedToolResult {
+    pub output: String,
+    pub cached: bool,
+}
+
+impl CachedToolResult {
+    /// Output with the cache marker so the model knows the call was deduplicated
+    pub fn marked_output(&self) -> String {
+        if self.cached {
+            format!("{} {}", CACHE_MARKER, self.output)
+        } else {
+            self.output.clone()
+        }
+    }
+}
+
+/// Per-run cache of tool results keyed by normalized arguments
+#[derive(Debug, Clone)]
+pub struct ToolCache {
+    entries: HashMap<String, String>,
+    cacheable_tools: HashSet<String>,
+    stats: ToolCacheStats,
+}
+
+impl ToolCache {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            cacheable_tools: DEFAULT_CACHEABLE_TOOLS.iter().map(|t| t.to_string()).collect(),
+            stats: ToolCacheStats::default(),
+        }
+    }
+
+    /// Mark an additional tool as read-only and therefore cacheable
+    pub fn with_cacheable_tool(mut self, tool: impl Into<String>) -> Self {
+        self.cacheable_tools.insert(tool.into());
+        self
+    }
+
+    pub fn is_cacheable(&self, tool: &str) -> bool {
+        self.cacheable_tools.contains(tool)
+    }
+
+    /// Look up a previous result for the same tool call
+    pub fn get(&mut self, tool: &str, arguments: &serde_json::Value) -> Option<CachedToolResult> {
+        if !self.is_cacheable(tool) {
+            return None;
+        }
+
+        match self.entries.get(&cache_key(tool, arguments)) {
+            Some(output) => {
+                self.stats.hits += 1;
+                self.stats.bytes_saved += output.len() as u64;
+                Some(CachedToolResult {
+                    output: output.clone(),
+                    cached: true,
+                })
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store the result of a tool call
+    pub fn insert(&mut self, tool: &str, arguments: &serde_json::Value, output: String) {
+        if !self.is_cacheable(tool) {
+            return;
+        }
+
+        self.entries.insert(cache_key(tool, arguments), output);
+        self.stats.entries = self.entries.len();
+    }
+
+    /// Drop every cached result, e.g. after a tool modified the workspace
+    pub fn invalidate(&mut self) {
+        if !self.entries.is_empty() {
+            self.entries.clear();
+            self.stats.entries = 0;
+            self.stats.invalidations += 1;
+        }
+    }
+
+    /// Notify the cache that a tool ran; non-cacheable tools are assumed to
+    /// have side effects and invalidate previous results
+    pub fn observe_tool(&mut self, tool: &str) {
+        if !self.is_cacheable(tool) {
+            self.invalidate();
+        }
+    }
+
+    pub fn stats(&self) -> &ToolCacheStats {
+        &self.stats
+    }
+}
+
+impl Default for ToolCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn cache_key(tool: &str, arguments: &serde_json::Value) -> String {
+    format!("{}:{}", tool, normalize_arguments(arguments))
+}
+
+/// Canonical form of tool arguments: object keys sorted. Values are kept as
+/// they are, since tools may treat `"foo "` and `"foo"` differently.
+pub fn normalize_arguments(arguments: &serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    match arguments {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            let mut normalized = serde_json::Map::new();
+            for key in keys {
+                normalized.insert(key.clone(), normalize_arguments(&map[key]));
+            }
+            Value::Object(normalized)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(normalize_arguments).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn repeated_calls_hit_cache() {
+        let mut cache = ToolCache::new();
+        let args = json!({ "path": "src" });
+
+        assert!(cache.get("list_files", &args).is_none());
+        cache.insert("list_files", &args, "src/main.rs:1".to_string());
+
+        let result = cache.get("list_files", &args).unwrap();
+        assert!(result.cached);
+        assert_eq!(result.marked_output(), "[cached result] src/main.rs:1");
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.bytes_saved, 13);
+    }
+
+    #[test]
+    fn arguments_are_normalized() {
+        let mut cache = ToolCache::new();
+        cache.insert("read_file", &json!({ "path": "src/lib.rs", "start": 1, "end": 40 }), "x".to_string());
+
+        assert!(cache.get("read_file", &json!({ "end": 40, "start": 1, "path": "src/lib.rs" })).is_some());
+        assert!(cache.get("read_file", &json!({ "path": "src/lib.rs ", "start": 1, "end": 40 })).is_none());
+        assert!(cache.get("read_file", &json!({ "path": "src/lib.rs" })).is_none());
+    }
+
+    #[test]
+    fn side_effecting_tools_invalidate() {
+        let mut cache = ToolCache::new();
+        let args = json!({ "path": "a.txt" });
+        cache.insert("read_file", &args, "old".to_string());
+
+        cache.observe_tool("write_file");
+        assert!(cache.get("read_file", &args).is_none());
+        assert_eq!(cache.stats().invalidations, 1);
+
+        cache.insert("write_file", &args, "ok".to_string());
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -0,0 +1,699 @@
+//! Agent tools and the registry that gates them
+//!
+//! Every tool an agent can call is an [`AgentTool`] registered in a
+//! [`ToolRegistry`]. The registry is the single place calls are checked:
+//! a call goes through only when the run's [`PermissionProfile`] allows
+//! the tool and every workspace path the call names. Built-in tools read,
+//! list and write workspace files, the latter through the workspace's
+//! [`EditJournal`], and run commands; with review enabled, writes are
+//! staged in the [`ReviewQueue`] instead of made. With a
+//! [`FileLockService`], tools that write take the lock on every path they
+//! touch first, so concurrent sessions never interleave edits to a file.
+//! Commands that install packages or use the network also need the
+//! profile's `network` tier; in lockfile-only runs, installs are frozen to
+//! what the lockfiles pin. The workspace's tracked TODO items can be listed
+//! and marked done, so plans can refer to them. With [`ProjectTooling`],
+//! written content is run through the project's formatter first and the
+//! files written are linted once the turn's calls are done.
+
+use super::command_class::{lockfile_only, CommandAssessment};
+use super::permissions::{workspace_relative, PermissionError, PermissionProfile};
+use super::project_tools::{LintFinding, ProjectToolSettings, ProjectTooling};
+use crate::content_cache::{content_hash, ContentCache};
+use crate::editor::review::{read_text, ReviewQueue};
+use crate::editor::{EditJournal, FileEdit};
+use crate::file_locks::{FileLockService, LockError};
+use crate::io::{FileSystem, ProcessRunner};
+use crate::large_file::{LargeFileSettings, LineRange};
+use crate::todos::{TodoError, TodoList};
+use async_trait::async_trait;
+use serde_json::Value;
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Longest tool output handed back to the model
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Tool call errors
+#[derive(Error, Debug)]
+pub enum ToolError {
+    #[error(transparent)]
+    Denied(#[from] PermissionError),
+
+    #[error("unknown tool '{0}'")]
+    Unknown(String),
+
+    #[error("invalid arguments for '{tool}': {reason}")]
+    InvalidArguments { tool: String, reason: String },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Editor(#[from] crate::editor::EditorError),
+
+    #[error(transparent)]
+    Locked(#[from] LockError),
+
+    #[error(transparent)]
+    Todo(#[from] TodoError),
+}
+
+/// What a tool runs against
+#[derive(Debug, Clone)]
+pub struct ToolContext {
+    pub root: PathBuf,
+    pub fs: Arc<dyn FileSystem>,
+    pub processes: Arc<dyn ProcessRunner>,
+}
+
+/// A tool the agent can call
+#[async_trait]
+pub trait AgentTool: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// One line for the tool list given to the model, including arguments
+    fn description(&self) -> &str;
+
+    /// Workspace paths a call touches, checked against the profile
+    fn paths(&self, arguments: &Value) -> Vec<String> {
+        arguments.get("path").and_then(Value::as_str).map(|path| vec![path.to_string()]).unwrap_or_default()
+    }
+
+    /// Whether a call modifies the files in [`AgentTool::paths`], which are
+    /// then locked first
+    fn writes(&self) -> bool {
+        false
+    }
+
+    /// Whether a call's `content` argument is the new content of its
+    /// `path`, which the project's formatter then gets to format first
+    fn takes_content(&self) -> bool {
+        false
+    }
+
+    /// The command line a call runs, classified before it is allowed
+    fn command(&self, _arguments: &Value) -> Result<Option<Vec<String>>, ToolError> {
+        Ok(None)
+    }
+
+    async fn call(&self, context: &ToolContext, arguments: &Value) -> Result<String, ToolError>;
+}
+
+/// The tools of a run, filtered by its permission profile
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Arc<dyn AgentTool>>,
+    context: ToolContext,
+    profile_name: String,
+    profile: PermissionProfile,
+    locks: Option<Arc<FileLockService>>,
+    tooling: Option<(Arc<ProjectTooling>, ProjectToolSettings)>,
+    /// Files written since the last [`ToolRegistry::lint_written`]
+    written: Mutex<Vec<PathBuf>>,
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("tools", &self.tools.keys().collect::<Vec<_>>())
+            .field("profile", &self.profile_name)
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    /// An empty registry enforcing `profile`
+    pub fn new(context: ToolContext, profile_name: impl Into<String>, profile: PermissionProfile) -> Self {
+        Self {
+            tools: BTreeMap::new(),
+            context,
+            profile_name: profile_name.into(),
+            profile,
+            locks: None,
+            tooling: None,
+            written: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add `read_file`, `list_files`, `write_file`, `run_command`,
+    /// `list_todos` and `update_todo`
+    pub fn with_builtin_tools(mut self) -> Self {
+        self.register(Arc::new(ReadFile::default()));
+        self.register(Arc::new(ListFiles));
+        self.register(Arc::new(WriteFile));
+        self.register(Arc::new(RunCommand { lockfile_only: false }));
+        self.register(Arc::new(ListTodos));
+        self.register(Arc::new(UpdateTodo));
+        self
+    }
+
+    /// Read files over `settings.threshold` in windows of lines
+    pub fn with_large_files(mut self, settings: LargeFileSettings) -> Self {
+        self.register(Arc::new(ReadFile { large_files: settings }));
+        self
+    }
+
+    /// Run package installs only in their lockfile-pinned form
+    pub fn with_lockfile_only(mut self) -> Self {
+        self.register(Arc::new(RunCommand { lockfile_only: true }));
+        self
+    }
+
+    /// Stage `write_file` calls for review instead of writing
+    pub fn with_review(mut self) -> Self {
+        self.register(Arc::new(StageFile));
+        self
+    }
+
+    /// Lock files before tools write them
+    pub fn with_locks(mut self, locks: Arc<FileLockService>) -> Self {
+        self.locks = Some(locks);
+        self
+    }
+
+    /// Format written content and lint written files with the project's tools
+    pub fn with_project_tooling(mut self, tooling: ProjectTooling, settings: ProjectToolSettings) -> Self {
+        self.tooling = Some((Arc::new(tooling), settings));
+        self
+    }
+
+    pub fn locks(&self) -> Option<&Arc<FileLockService>> {
+        self.locks.as_ref()
+    }
+
+    /// Release the file locks taken by this registry's calls
+    pub async fn release_locks(&self) -> Result<usize, ToolError> {
+        match &self.locks {
+            Some(locks) => Ok(locks.release_all().await?),
+            None => Ok(0),
+        }
+    }
+
+    pub fn register(&mut self, tool: Arc<dyn AgentTool>) {
+        self.tools.insert(tool.name().to_string(), tool);
+    }
+
+    /// What the tools run against
+    pub fn context(&self) -> &ToolContext {
+        &self.context
+    }
+
+    pub fn profile_name(&self) -> &str {
+        &self.profile_name
+    }
+
+    /// Registered tools the profile allows
+    pub fn available(&self) -> Vec<&dyn AgentTool> {
+        self.tools
+            .values()
+            .filter(|tool| self.profile.allows_tool(tool.name()))
+            .map(|tool| tool.as_ref())
+            .collect()
+    }
+
+    /// Check a call against the profile without running it
+    pub fn authorize(&self, tool: &str, arguments: &Value) -> Result<(), ToolError> {
+        let registered = self.tools.get(tool).ok_or_else(|| ToolError::Unknown(tool.to_string()))?;
+        if !self.profile.allows_tool(tool) {
+            return Err(PermissionError::ToolDenied { tool: tool.to_string(), profile: self.profile_name.clone() }.into());
+        }
+        for path in registered.paths(arguments) {
+            let relative = workspace_relative(&self.context.root, &path)?;
+            if !self.profile.allows_path(&relative) {
+                return Err(PermissionError::PathDenied { path, profile: self.profile_name.clone() }.into());
+            }
+        }
+        if let Some(command) = registered.command(arguments)? {
+            let assessment = CommandAssessment::of(&command);
+            if assessment.class.needs_network() {
+                if !self.profile.network {
+                    return Err(PermissionError::NetworkDenied {
+                        command: command.join(" "),
+                        fetches: assessment.fetches,
+                        profile: self.profile_name.clone(),
+                    }
+                    .into());
+                }
+                tracing::info!("'{}' ({}) fetches {}", command.join(" "), assessment.class, assessment.fetches);
+            }
+        }
+        Ok(())
+    }
+
+    /// The command line a call would run
+    pub fn command(&self, tool: &str, arguments: &Value) -> Result<Option<Vec<String>>, ToolError> {
+        let registered = self.tools.get(tool).ok_or_else(|| ToolError::Unknown(tool.to_string()))?;
+        registered.command(arguments)
+    }
+
+    /// Unified diffs of the files a call would write, by workspace-relative
+    /// path; empty for calls that write nothing
+    pub async fn planned_changes(&self, tool: &str, arguments: &Value) -> Result<Vec<(PathBuf, String)>, ToolError> {
+        let registered = self.tools.get(tool).ok_or_else(|| ToolError::Unknown(tool.to_string()))?;
+        let content = match arguments.get("content").and_then(Value::as_str) {
+            Some(content) if registered.writes() && registered.takes_content() => content,
+            _ => return Ok(Vec::new()),
+        };
+        let mut changes = Vec::new();
+        for path in registered.paths(arguments) {
+            let relative = workspace_relative(&self.context.root, &path)?;
+            let current = read_text(self.context.fs.as_ref(), &self.context.root.join(&relative)).await?;
+            let diff = TextDiff::from_lines(current.as_deref().unwrap_or(""), content)
+                .unified_diff()
+                .header(&relative, &relative)
+                .to_string();
+            changes.push((PathBuf::from(relative), diff));
+        }
+        Ok(changes)
+    }
+
+    /// Run a tool if the profile allows the call
+    #[tracing::instrument(name = "agent.tool", skip(self, arguments), fields(profile = %self.profile_name), err)]
+    pub async fn call(&self, tool: &str, arguments: &Value) -> Result<String, ToolError> {
+        self.authorize(tool, arguments)?;
+        if let Some(locks) = self.locks.as_ref().filter(|_| self.tools[tool].writes()) {
+            for path in self.tools[tool].paths(arguments) {
+                locks.acquire(&workspace_relative(&self.context.root, &path)?).await?;
+            }
+        }
+        let registered = &self.tools[tool];
+        let formatted = match &self.tooling {
+            Some((tooling, settings)) if settings.format && registered.takes_content() => {
+                self.format_content(tooling, arguments).await?
+            }
+            _ => None,
+        };
+        let mut output = match &formatted {
+            Some((arguments, formatter)) => {
+                let output = registered.call(&self.context, arguments).await?;
+                format!("{} (formatted with {})", output, formatter)
+            }
+            None => registered.call(&self.context, arguments).await?,
+        };
+        if registered.writes() && self.tooling.as_ref().is_some_and(|(_, settings)| settings.lint) {
+            let written = registered
+                .paths(arguments)
+                .iter()
+                .map(|path| workspace_relative(&self.context.root, path).map(PathBuf::from))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.written.lock().unwrap_or_else(|e| e.into_inner()).extend(written);
+        }
+        if output.len() > MAX_OUTPUT_BYTES {
+            let mut end = MAX_OUTPUT_BYTES;
+            while !output.is_char_boundary(end) {
+                end -= 1;
+            }
+            output.truncate(end);
+            output.push_str("\n[output truncated]");
+        }
+        Ok(output)
+    }
+
+    /// `arguments` with their `content` formatted, and the formatter, when
+    /// formatting changed it
+    async fn format_content(
+        &self,
+        tooling: &ProjectTooling,
+        arguments: &Value,
+    ) -> Result<Option<(Value, &'static str)>, ToolError> {
+        let (Some(path), Some(content)) =
+            (arguments.get("path").and_then(Value::as_str), arguments.get("content").and_then(Value::as_str))
+        else {
+            return Ok(None);
+        };
+        let relative = workspace_relative(&self.context.root, path)?;
+        let Some((formatted, formatter)) =
+            tooling.format(self.context.processes.as_ref(), &self.context.root, Path::new(&relative), content).await
+        else {
+            return Ok(None);
+        };
+        let mut arguments = arguments.clone();
+        arguments["content"] = Value::String(formatted);
+        Ok(Some((arguments, formatter)))
+    }
+
+    /// Lint the files written since the last call; empty without project
+    /// linters
+    pub async fn lint_written(&self) -> Vec<LintFinding> {
+        let mut written = std::mem::take(&mut *self.written.lock().unwrap_or_else(|e| e.into_inner()));
+        let Some((tooling, _)) = &self.tooling else {
+            return Vec::new();
+        };
+        written.sort();
+        written.dedup();
+        tooling.lint(self.context.processes.as_ref(), &self.context.root, &written).await
+    }
+
+    /// Tool list for the system prompt
+    pub fn instructions(&self) -> String {
+        let mut out = format!("Tools available with '{}' permissions:\n", self.profile_name);
+        for tool in self.available() {
+            let _ = writeln!(out, "- {}: {}", tool.name(), tool.description());
+        }
+        out
+    }
+}
+
+fn string_argument<'a>(tool: &str, arguments: &'a Value, name: &str) -> Result<&'a str, ToolError> {
+    arguments.get(name).and_then(Value::as_str).ok_or_else(|| ToolError::InvalidArguments {
+        tool: tool.to_string(),
+        reason: format!("missing '{}' string", name),
+    })
+}
+
+fn resolve(context: &ToolContext, path: &str) -> Result<PathBuf, ToolError> {
+    Ok(context.root.join(workspace_relative(&context.root, path)?))
+}
+
+#[derive(Default)]
+struct ReadFile {
+    large_files: LargeFileSettings,
+}
+
+impl ReadFile {
+    fn line_argument(&self, arguments: &Value, name: &str) -> Result<Option<usize>, ToolError> {
+        match arguments.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => value.as_u64().filter(|line| *line > 0).map(|line| Some(line as usize)).ok_or_else(|| {
+                ToolError::InvalidArguments {
+                    tool: self.name().to_string(),
+                    reason: format!("'{}' must be a line number from 1", name),
+                }
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl AgentTool for ReadFile {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        r#"contents of a workspace file, {"path": "src/lib.rs"}; add "start" and "end" line numbers for part of it, which large files require"#
+    }
+
+    async fn call(&self, context: &ToolContext, arguments: &Value) -> Result<String, ToolError> {
+        let label = string_argument(self.name(), arguments, "path")?;
+        let path = resolve(context, label)?;
+        let start = self.line_argument(arguments, "start")?;
+        let end = self.line_argument(arguments, "end")?;
+        if start.is_none() && end.is_none() {
+            let size = context.fs.file_size(&path).await?;
+            if !self.large_files.is_large(size) {
+                return Ok(context.fs.read_to_string(&path).await?);
+            }
+            // A full read of a large file gets its first window and a warning
+            let window = context.fs.read_window(&path, self.large_files.window_at(1)).await?;
+            tracing::warn!("read_file asked for all of {} ({} bytes); sent lines {}", label, size, window.range);
+            return Ok(format!(
+                "Warning: {} is too large to read whole ({} bytes, {} lines). Showing lines {} only; call read_file with \"start\" and \"end\" line numbers for other parts.\n\n{}",
+                label, size, window.total_lines, window.range, window.text
+            ));
+        }
+        let start = start.unwrap_or(1);
+        let range = match end {
+            Some(end) => LineRange::new(start, end),
+            None => self.large_files.window_at(start),
+        };
+        let window = context.fs.read_window(&path, range).await?;
+        Ok(format!("Lines {} of {} in {}:\n{}", window.range, window.total_lines, label, window.text))
+    }
+}
+
+struct ListFiles;
+
+#[async_trait]
+impl AgentTool for ListFiles {
+    fn name(&self) -> &str {
+        "list_files"
+    }
+
+    fn description(&self) -> &str {
+        r#"entries of a workspace directory, {"path": "src"}"#
+    }
+
+    fn paths(&self, arguments: &Value) -> Vec<String> {
+        vec![arguments.get("path").and_then(Value::as_str).unwrap_or(".").to_string()]
+    }
+
+    async fn call(&self, context: &ToolContext, arguments: &Value) -> Result<String, ToolError> {
+        let dir = resolve(context, arguments.get("path").and_then(Value::as_str).unwrap_or("."))?;
+        let mut entries: Vec<String> = context
+            .fs
+            .read_dir(&dir)
+            .await?
+            .iter()
+            .map(|entry| crate::paths::to_slash(crate::paths::strip_root(entry, &context.root).unwrap_or(entry)))
+            .collect();
+        entries.sort();
+        Ok(entries.join("\n"))
+    }
+}
+
+struct WriteFile;
+
+#[async_trait]
+impl AgentTool for WriteFile {
+    fn name(&self) -> &str {
+        "write_file"
+    }
+
+    fn description(&self) -> &str {
+        r#"replace a workspace file's contents, {"path": "src/lib.rs", "content": "..."}"#
+    }
+
+    fn writes(&self) -> bool {
+        true
+    }
+
+    fn takes_content(&self) -> bool {
+        true
+    }
+
+    async fn call(&self, context: &ToolContext, arguments: &Value) -> Result<String, ToolError> {
+        let relative = string_argument(self.name(), arguments, "path")?;
+        let content = string_argument(self.name(), arguments, "content")?;
+        let path = resolve(context, relative)?;
+        // Through the workspace's journal, so a crash midway is recovered
+        let mut edit = FileEdit::new(&path, content);
+        if let Some(base) = read_text(context.fs.as_ref(), &path).await? {
+            edit = edit.based_on(content_hash(base.as_bytes()));
+        }
+        EditJournal::for_workspace(&context.root)
+            .apply(&[edit], context.fs.as_ref(), &ContentCache::global())
+            .await?;
+        Ok(format!("wrote {} bytes to {}", content.len(), relative))
+    }
+}
+
+/// `write_file` that adds to the review queue
+struct StageFile;
+
+#[async_trait]
+impl AgentTool for StageFile {
+    fn name(&self) -> &str {
+        "write_file"
+    }
+
+    fn description(&self) -> &str {
+        r#"replace a workspace file's contents (staged for the user's review), {"path": "src/lib.rs", "content": "..."}"#
+    }
+
+    fn takes_content(&self) -> bool {
+        true
+    }
+
+    async fn call(&self, context: &ToolContext, arguments: &Value) -> Result<String, ToolError> {
+        let relative = workspace_relative(&context.root, string_argument(self.name(), arguments, "path")?)?;
+        let content = string_argument(self.name(), arguments, "content")?;
+        let mut queue = ReviewQueue::load(context.fs.as_ref(), &context.root).await?;
+        let base = read_text(context.fs.as_ref(), &context.root.join(&relative)).await?;
+        queue.stage(&relative, base, content);
+        queue.save(context.fs.as_ref(), &context.root).await?;
+        Ok(format!("staged {} bytes for {} (pending review)", content.len(), relative))
+    }
+}
+
+struct RunCommand {
+    lockfile_only: bool,
+}
+
+#[async_trait]
+impl AgentTool for RunCommand {
+    fn name(&self) -> &str {
+        "run_command"
+    }
+
+    fn description(&self) -> &str {
+        r#"run a program in the workspace root, {"program": "cargo", "args": ["test"]}"#
+    }
+
+    fn command(&self, arguments: &Value) -> Result<Option<Vec<String>>, ToolError> {
+        let mut command = vec![string_argument(self.name(), arguments, "program")?.to_string()];
+        if let Some(args) = arguments.get("args").and_then(Value::as_array) {
+            command.extend(args.iter().filter_map(Value::as_str).map(str::to_string));
+        }
+        if self.lockfile_only {
+            command = lockfile_only(&command)?;
+        }
+        Ok(Some(command))
+    }
+
+    async fn call(&self, context: &ToolContext, arguments: &Value) -> Result<String, ToolError> {
+        let command = self.command(arguments)?.unwrap_or_default();
+        let (program, args) = command.split_first().ok_or_else(|| ToolError::InvalidArguments {
+            tool: self.name().to_string(),
+            reason: "empty command".to_string(),
+        })?;
+        let output = context.processes.run(program, args, Some(&context.root), &[], None).await?;
+        let exit = output.exit_code.map_or("signal".to_string(), |code| code.to_string());
+        Ok(format!(
+            "exit code {}\n{}{}",
+            exit,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+struct ListTodos;
+
+#[async_trait]
+impl AgentTool for ListTodos {
+    fn name(&self) -> &str {
+        "list_todos"
+    }
+
+    fn description(&self) -> &str {
+        r#"open TODO/FIXME/HACK comments as "id KIND file:line (owner) text", {}"#
+    }
+
+    async fn call(&self, context: &ToolContext, _arguments: &Value) -> Result<String, ToolError> {
+        let list = TodoList::load(context.fs.as_ref(), &context.root).await?;
+        let open = list.render_open();
+        Ok(if open.is_empty() { "no open TODO items".to_string() } else { open })
+    }
+}
+
+struct UpdateTodo;
+
+#[async_trait]
+impl AgentTool for UpdateTodo {
+    fn name(&self) -> &str {
+        "update_todo"
+    }
+
+    fn description(&self) -> &str {
+        r#"mark a TODO item done once your change settles it, {"id": "3f2a9c1e", "note": "retries added"}"#
+    }
+
+    async fn call(&self, context: &ToolContext, arguments: &Value) -> Result<String, ToolError> {
+        let id = string_argument(self.name(), arguments, "id")?;
+        let note = arguments.get("note").and_then(Value::as_str).unwrap_or("marked done by the agent");
+        let mut list = TodoList::load(context.fs.as_ref(), &context.root).await?;
+        let item = list.mark_done(id, note)?;
+        let done = format!("marked {} {} {} done", item.id, item.kind, item.location());
+        list.save(context.fs.as_ref()).await?;
+        Ok(done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{MemoryFileSystem, NoProcessRunner};
+    use serde_json::json;
+    use std::path::Path;
+
+    fn registry(profile: &str) -> (ToolRegistry, Arc<MemoryFileSystem>) {
+        let fs = Arc::new(MemoryFileSystem::new());
+        let context = ToolContext { root: PathBuf::from("/repo"), fs: fs.clone(), processes: Arc::new(NoProcessRunner) };
+        let registry = ToolRegistry::new(context, profile, PermissionProfile::builtin(profile).unwrap()).with_builtin_tools();
+        (registry, fs)
+    }
+
+    #[tokio::test]
+    async fn registry_enforces_the_profile() {
+        let (reader, fs) = registry("reader");
+        fs.write(Path::new("/repo/src/lib.rs"), b"pub fn f() {}").await.unwrap();
+
+        assert_eq!(reader.call("read_file", &json!({ "path": "src/lib.rs" })).await.unwrap(), "pub fn f() {}");
+        let denied = reader.call("write_file", &json!({ "path": "src/lib.rs", "content": "" })).await;
+        assert!(matches!(denied, Err(ToolError::Denied(PermissionError::ToolDenied { .. }))));
+        assert!(matches!(reader.call("read_file", &json!({ "path": "../secrets" })).await, Err(ToolError::Denied(_))));
+        assert!(!reader.instructions().contains("run_command"));
+        let part = reader.call("read_file", &json!({ "path": "src/lib.rs", "start": 1, "end": 3 })).await.unwrap();
+        assert_eq!(part, "Lines 1-1 of 1 in src/lib.rs:\npub fn f() {}\n");
+        let large = reader.with_large_files(LargeFileSettings { threshold: 8, window_lines: 20 });
+        let warned = large.call("read_file", &json!({ "path": "src/lib.rs" })).await.unwrap();
+        assert!(warned.starts_with("Warning: src/lib.rs is too large to read whole (13 bytes, 1 lines)"));
+
+        let (editor, fs) = registry("editor");
+        editor.call("write_file", &json!({ "path": "src/new.rs", "content": "x" })).await.unwrap();
+        assert!(fs.exists(Path::new("/repo/src/new.rs")).await);
+        let hidden = editor.call("write_file", &json!({ "path": ".git/config", "content": "" })).await;
+        assert!(matches!(hidden, Err(ToolError::Denied(PermissionError::PathDenied { .. }))));
+        assert!(matches!(editor.call("run_command", &json!({ "program": "ls" })).await, Err(ToolError::Denied(_))));
+    }
+
+    #[test]
+    fn installs_and_network_commands_need_the_network_tier() {
+        let install = json!({ "program": "npm", "args": ["install", "lodash"] });
+        let (operator, _) = registry("operator");
+        let denied = operator.authorize("run_command", &install);
+        assert!(matches!(denied, Err(ToolError::Denied(PermissionError::NetworkDenied { ref fetches, .. })) if fetches == "npm packages lodash"));
+        operator.authorize("run_command", &json!({ "program": "cargo", "args": ["test"] })).unwrap();
+
+        let (network, _) = registry("network");
+        network.authorize("run_command", &install).unwrap();
+        let network = network.with_lockfile_only();
+        let refused = network.authorize("run_command", &install);
+        assert!(matches!(refused, Err(ToolError::Denied(PermissionError::ChangesLockfile(_)))));
+        network.authorize("run_command", &json!({ "program": "npm", "args": ["install"] })).unwrap();
+    }
+
+    #[tokio::test]
+    async fn agents_list_and_settle_todos() {
+        let (editor, fs) = registry("editor");
+        let mut list = TodoList::load(fs.as_ref(), Path::new("/repo")).await.unwrap();
+        list.reconcile(crate::todos::extract(Path::new("src/net.rs"), "// FIXME(ana): retry on 429\n"));
+        list.save(fs.as_ref()).await.unwrap();
+        let id = list.items[0].id.clone();
+
+        let open = editor.call("list_todos", &json!({})).await.unwrap();
+        assert_eq!(open, format!("{} FIXME src/net.rs:1 (ana) retry on 429\n", id));
+        editor.call("update_todo", &json!({ "id": id, "note": "retries added" })).await.unwrap();
+        assert_eq!(editor.call("list_todos", &json!({})).await.unwrap(), "no open TODO items");
+        assert!(matches!(editor.call("update_todo", &json!({ "id": "nope" })).await, Err(ToolError::Todo(_))));
+
+        let (reader, _) = registry("reader");
+        assert!(matches!(reader.call("update_todo", &json!({ "id": id })).await, Err(ToolError::Denied(_))));
+    }
+
+    #[tokio::test]
+    async fn writes_take_file_locks() {
+        use crate::file_locks::{LockPolicy, LockSettings};
+        let (editor, fs) = registry("editor");
+        let settings = LockSettings { policy: LockPolicy::Fail, wait_secs: 0, lease_secs: 60 };
+        let other = FileLockService::new(PathBuf::from("/repo"), fs.clone(), "other session", settings);
+        let editor = editor.with_locks(Arc::new(FileLockService::new(PathBuf::from("/repo"), fs.clone(), "agent", settings)));
+
+        editor.call("write_file", &json!({ "path": "src/a.rs", "content": "a" })).await.unwrap();
+        assert_eq!(other.lock_of("src/a.rs").await.unwrap().unwrap().holder, "agent");
+        assert!(matches!(other.acquire("src/a.rs").await, Err(LockError::Held { .. })));
+
+        other.acquire("src/b.rs").await.unwrap();
+        let blocked = editor.call("write_file", &json!({ "path": "src/b.rs", "content": "b" })).await;
+        assert!(matches!(blocked, Err(ToolError::Locked(LockError::Held { .. }))));
+        assert!(!fs.exists(Path::new("/repo/src/b.rs")).await);
+
+        assert_eq!(editor.release_locks().await.unwrap(), 1);
+        other.acquire("src/a.rs").await.unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+//! Recoverable file deletion for agent tools
+//!
+//! Files the agent deletes are not removed outright. Depending on the
+//! [`TrashMode`] they are moved to the OS trash, or to a staging area in
+//! `.picode/trash` from which `picode agent trash restore` can bring them
+//! back. Strict environments (CI, shared containers) can opt into permanent
+//! deletion instead.
+
+use super::AgentRunId;
+use crate::id::{IdError, IdPrefix, TrashId, TrashKind};
+use crate::io::FileSystem;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Directory (relative to the workspace root) holding staged deletions
+pub const TRASH_DIR: &str = ".picode/trash";
+
+/// Where deleted files go
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashMode {
+    /// Move to `.picode/trash`, restorable with `picode agent trash restore`
+    #[default]
+    Staging,
+    /// Move to the operating system's trash / recycle bin
+    System,
+    /// Delete permanently
+    Off,
+}
+
+/// A file held in the staging area
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: TrashId,
+    pub original_path: PathBuf,
+    pub deleted_at: chrono::DateTime<chrono::Utc>,
+    pub size: u64,
+    /// Agent run that deleted the file
+    #[serde(default)]
+    pub run_id: Option<AgentRunId>,
+}
+
+impl TrashEntry {
+    fn data_file(&self, dir: &Path) -> PathBuf {
+        dir.join(format!("{}.data", self.id))
+    }
+}
+
+/// Deletes files according to a [`TrashMode`]
+#[derive(Debug, Clone)]
+pub struct Trash {
+    dir: PathBuf,
+    mode: TrashMode,
+}
+
+impl Trash {
+    /// Trash staging files in `dir` (usually `<workspace>/.picode/trash`)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: TrashMode::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: TrashMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn mode(&self) -> TrashMode {
+        self.mode
+    }
+
+    /// Delete a file; returns the staging entry when it can be restored
+    pub async fn delete(
+        &self,
+        fs: &dyn FileSystem,
+        path: &Path,
+        run_id: Option<&AgentRunId>,
+    ) -> Result<Option<TrashEntry>, TrashError> {
+        match self.mode {
+            TrashMode::Off => {
+                fs.remove_file(path).await?;
+                Ok(None)
+            }
+            TrashMode::System => {
+                move_to_system_trash(path).await?;
+                Ok(None)
+            }
+            TrashMode::Staging => {
+                let content = fs.read(path).await?;
+                let entry = TrashEntry {
+                    id: TrashId::new(),
+                    original_path: path.to_path_buf(),
+                    deleted_at: chrono::Utc::now(),
+                    size: content.len() as u64,
+                    run_id: run_id.cloned(),
+                };
+
+                fs.create_dir_all(&self.dir).await?;
+                fs.write(&entry.data_file(&self.dir), &content).await?;
+                fs.write(&self.meta_file(&entry.id), serde_json::to_string_pretty(&entry)?.as_bytes())
+                    .await?;
+                fs.remove_file(path).await?;
+                Ok(Some(entry))
+            }
+        }
+    }
+
+    /// Staged entries, most recently deleted first
+    pub async fn list(&self, fs: &dyn FileSystem) -> Result<Vec<TrashEntry>, TrashError> {
+        let paths = match fs.read_dir(&self.dir).await {
+            Ok(paths) => paths,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        for path in paths.iter().filter(|p| p.extension().is_some_and(|ext| ext == "json")) {
+            let entry = serde_json::from_str::<TrashEntry>(&fs.read_to_string(path).await?)?;
+            if path.file_stem().is_some_and(|stem| *stem != *entry.id.to_string()) {
+                self.migrate_files(fs, path, &entry).await?;
+            }
+            entries.push(entry);
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+        Ok(entries)
+    }
+
+    /// Put a staged file back where it was, refusing to overwrite
+    pub async fn restore(&self, fs: &dyn FileSystem, id: &IdPrefix<TrashKind>) -> Result<TrashEntry, TrashError> {
+        let entry = self.find(fs, id).await?;
+        if fs.exists(&entry.original_path).await {
+            return Err(TrashError::RestoreConflict(entry.original_path));
+        }
+
+        let content = fs.read(&entry.data_file(&self.dir)).await?;
+        if let Some(parent) = entry.original_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs.create_dir_all(parent).await?;
+        }
+        fs.write(&entry.original_path, &content).await?;
+        self.remove_entry(fs, &entry).await?;
+        Ok(entry)
+    }
+
+    /// Permanently delete every staged file; returns how many were removed
+    pub async fn empty(&self, fs: &dyn FileSystem) -> Result<usize, TrashError> {
+        let entries = self.list(fs).await?;
+        for entry in &entries {
+            self.remove_entry(fs, entry).await?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Find an entry by full id or unambiguous id prefix
+    async fn find(&self, fs: &dyn FileSystem, id: &IdPrefix<TrashKind>) -> Result<TrashEntry, TrashError> {
+        let entries = self.list(fs).await?;
+        let id = id.resolve(entries.iter().map(|entry| entry.id.clone()))?;
+        Ok(entries.into_iter().find(|entry| entry.id == id).expect("resolved among the entries"))
+    }
+
+    /// Rename the files of an entry stored under its UUID by an earlier
+    /// version to its id's current form
+    async fn migrate_files(&self, fs: &dyn FileSystem, legacy: &Path, entry: &TrashEntry) -> Result<(), TrashError> {
+        fs.rename(&legacy.with_extension("data"), &entry.data_file(&self.dir)).await?;
+        fs.write(&self.meta_file(&entry.id), serde_json::to_string_pretty(entry)?.as_bytes()).await?;
+        fs.remove_file(legacy).await?;
+        Ok(())
+    }
+
+    async fn remove_entry(&self, fs: &dyn FileSystem, entry: &TrashEntry) -> Result<(), TrashError> {
+        fs.remove_file(&entry.data_file(&self.dir)).await?;
+        fs.remove_file(&self.meta_file(&entry.id)).await?;
+        Ok(())
+    }
+
+    fn meta_file(&self, id: &TrashId) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+#[cfg(feature = "native")]
+async fn move_to_system_trash(path: &Path) -> Result<(), TrashError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || trash::delete(&path))
+        .await
+        .map_err(|e| TrashError::System(e.to_string()))?
+        .map_err(|e| TrashError::System(e.to_string()))
+}
+
+#[cfg(not(feature = "native"))]
+async fn move_to_system_trash(_path: &Path) -> Result<(), TrashError> {
+    Err(TrashError::System("no OS trash on this host".to_string()))
+}
+
+/// Trash-related errors
+#[derive(Error, Debug)]
+pub enum TrashError {
+    #[error(transparent)]
+    Id(#[from] IdError),
+
+    #[error("Cannot restore, {0} already exists")]
+    RestoreConflict(PathBuf),
+
+    #[error("OS trash error: {0}")]
+    System(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    #[tokio::test]
+    async fn staged_files_can_be_restored() {
+        let fs = MemoryFileSystem::new();
+        let path = Path::new("/ws/src/old.rs");
+        fs.insert(path, "fn old() {}");
+        let trash = Trash::new("/ws/.picode/trash");
+
+        let run = AgentRunId::new();
+        let entry = trash.delete(&fs, path, Some(&run)).await.unwrap().unwrap();
+        assert!(!fs.exists(path).await);
+        assert_eq!(entry.size, 11);
+        assert_eq!(trash.list(&fs).await.unwrap(), vec![entry.clone()]);
+
+        fs.insert(path, "fn new() {}");
+        let prefix: IdPrefix<TrashKind> = entry.id.to_string()[..8].parse().unwrap();
+        assert!(matches!(trash.restore(&fs, &prefix).await, Err(TrashError::RestoreConflict(_))));
+        fs.remove_file(path).await.unwrap();
+
+        let restored = trash.restore(&fs, &prefix).await.unwrap();
+        assert_eq!(restored.run_id, Some(run));
+        assert_eq!(fs.read_to_string(path).await.unwrap(), "fn old() {}");
+        assert!(trash.list(&fs).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn entries_stored_under_uuids_are_migrated() {
+        let fs = MemoryFileSystem::new();
+        let dir = Path::new("/ws/.picode/trash");
+        let legacy = "67e5504410b1426f9247bb680e5fe0c8";
+        let entry = serde_json::json!({
+            "id": legacy,
+            "original_path": "/ws/old.txt",
+            "deleted_at": "2026-01-01T00:00:00Z",
+            "size": 3,
+        });
+        fs.insert(dir.join(format!("{}.json", legacy)), entry.to_string());
+        fs.insert(dir.join(format!("{}.data", legacy)), "old");
+
+        let trash = Trash::new(dir);
+        let id: TrashId = legacy.parse().unwrap();
+        assert_eq!(trash.list(&fs).await.unwrap()[0].id, id);
+        let restored = trash.restore(&fs, &id.to_string().parse().unwrap()).await.unwrap();
+        assert_eq!(restored.original_path, Path::new("/ws/old.txt"));
+        assert_eq!(fs.paths(), vec![PathBuf::from("/ws/old.txt")]);
+    }
+
+    #[tokio::test]
+    async fn off_mode_deletes_permanently() {
+        let fs = MemoryFileSystem::new();
+        fs.insert("/ws/a.txt", "a");
+        fs.insert("/ws/b.txt", "b");
+        let staging = Trash::new("/ws/.picode/trash");
+        staging.delete(&fs, Path::new("/ws/a.txt"), None).await.unwrap();
+
+        let strict = staging.clone().with_mode(TrashMode::Off);
+        assert!(strict.delete(&fs, Path::new("/ws/b.txt"), None).await.unwrap().is_none());
+        assert_eq!(staging.list(&fs).await.unwrap().len(), 1);
+
+        assert_eq!(staging.empty(&fs).await.unwrap(), 1);
+        assert!(fs.paths().is_empty());
+    }
+}
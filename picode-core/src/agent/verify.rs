@@ -0,0 +1,239 @@
+//! Verification of an agent's work before its run finishes
+//!
+//! With `[agent.verify]` enabled, an agent saying it is done does not end
+//! the run right away: the configured checks (build, test and lint command
+//! lines) run in the workspace and, optionally, a critic model reviews the
+//! workspace diff against the task. Failed checks and the critic's
+//! objections are handed back to the agent for another iteration, at most
+//! `max_repairs` times; every pass is recorded in the run report.
+//!
+//! ```toml
+//! [agent.verify]
+//! enabled = true
+//! checks = ["cargo build", "cargo test", "cargo clippy -- -D warnings"]
+//! critic = true
+//! max_repairs = 2
+//! ```
+
+use crate::io::ProcessRunner;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Output kept per check, from its end where the errors usually are
+const MAX_CHECK_OUTPUT: usize = 8 * 1024;
+
+/// Longest diff shown to the critic
+const MAX_DIFF_BYTES: usize = 64 * 1024;
+
+/// Reply of a critic with no objections
+pub const APPROVED_MARKER: &str = "APPROVED";
+
+/// System prompt of the critic
+pub const CRITIC_INSTRUCTIONS: &str = "You review the work of an autonomous coding agent. Compare \
+the diff with the task it was given. If the change does the whole task correctly, reply with only \
+APPROVED. Otherwise list what is wrong or missing, one problem per line, without rewriting the code.";
+
+/// `[agent.verify]` settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerifySettings {
+    /// Verify before a run finishes (`picode agent run --verify` forces it)
+    pub enabled: bool,
+    /// Shell command lines that must succeed, run from the workspace root
+    pub checks: Vec<String>,
+    /// Have a critic model review the diff against the task
+    pub critic: bool,
+    /// Model of the critic; the run's own model when unset
+    pub critic_model: Option<String>,
+    /// Repair iterations after a failed verification before the run ends
+    pub max_repairs: usize,
+}
+
+impl Default for VerifySettings {
+    fn default() -> Self {
+        Self { enabled: false, checks: Vec::new(), critic: false, critic_model: None, max_repairs: 2 }
+    }
+}
+
+impl VerifySettings {
+    /// Whether verification is on and has anything to do
+    pub fn is_active(&self) -> bool {
+        self.enabled && (!self.checks.is_empty() || self.critic)
+    }
+}
+
+/// Result of one check command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckOutcome {
+    pub command: String,
+    /// `None` when the command could not be run or was killed
+    pub exit_code: Option<i32>,
+    /// End of its combined stdout and stderr
+    pub output: String,
+}
+
+impl CheckOutcome {
+    pub fn passed(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// One verification pass over the agent's work
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationRound {
+    pub checks: Vec<CheckOutcome>,
+    /// The critic's objections; `None` when it approved or did not run
+    #[serde(default)]
+    pub objections: Option<String>,
+}
+
+impl VerificationRound {
+    pub fn passed(&self) -> bool {
+        self.objections.is_none() && self.checks.iter().all(CheckOutcome::passed)
+    }
+
+    /// What failed, for the agent's next turn
+    pub fn render_failures(&self) -> String {
+        let mut out = String::new();
+        for check in self.checks.iter().filter(|check| !check.passed()) {
+            let exit = check.exit_code.map_or("did not finish".to_string(), |code| format!("exit code {}", code));
+            let _ = writeln!(out, "Check `{}` failed ({}):\n{}\n", check.command, exit, check.output.trim_end());
+        }
+        if let Some(objections) = &self.objections {
+            let _ = writeln!(out, "A reviewer compared your diff with the task and found:\n{}\n", objections.trim_end());
+        }
+        out
+    }
+
+    /// One line for the run report
+    pub fn summary(&self) -> String {
+        let failed: Vec<String> =
+            self.checks.iter().filter(|check| !check.passed()).map(|check| format!("`{}`", check.command)).collect();
+        match (failed.is_empty(), &self.objections) {
+            (true, None) => format!("passed ({} check(s))", self.checks.len()),
+            (false, None) => format!("failed: {}", failed.join(", ")),
+            (true, Some(_)) => "failed: reviewer objected".to_string(),
+            (false, Some(_)) => format!("failed: {}; reviewer objected", failed.join(", ")),
+        }
+    }
+}
+
+/// Run `checks` through the shell in `root`, in order; all of them run even
+/// after one fails so the agent sees every problem at once
+pub async fn run_checks(runner: &dyn ProcessRunner, root: &Path, checks: &[String]) -> Vec<CheckOutcome> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut outcomes = Vec::with_capacity(checks.len());
+    for command in checks {
+        let args = [flag.to_string(), command.clone()];
+        let (exit_code, output) = match runner.run(shell, &args, Some(root), &[], None).await {
+            Ok(output) => {
+                let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                text.push_str(&String::from_utf8_lossy(&output.stderr));
+                (output.exit_code, tail(&text, MAX_CHECK_OUTPUT).to_string())
+            }
+            Err(e) => (None, format!("could not run: {}", e)),
+        };
+        outcomes.push(CheckOutcome { command: command.clone(), exit_code, output });
+    }
+    outcomes
+}
+
+/// Uncommitted changes of the workspace, for the critic; empty outside a
+/// git repository
+pub async fn workspace_diff(runner: &dyn ProcessRunner, root: &Path) -> String {
+    let args = ["diff".to_string(), "HEAD".to_string()];
+    match runner.run("git", &args, Some(root), &[], None).await {
+        Ok(output) if output.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Ok(output) => {
+            tracing::debug!("git diff failed: {}", String::from_utf8_lossy(&output.stderr));
+            String::new()
+        }
+        Err(e) => {
+            tracing::debug!("git diff could not run: {}", e);
+            String::new()
+        }
+    }
+}
+
+/// The critic's request: the task, the agent's closing reply and the diff
+pub fn critic_prompt(task: &str, reply: &str, diff: &str) -> String {
+    let diff = if diff.trim().is_empty() { "(no changes)" } else { head(diff, MAX_DIFF_BYTES) };
+    format!(
+        "Task:\n{}\n\nThe agent's final reply:\n{}\n\nDiff of its changes:\n```diff\n{}\n```\n",
+        task.trim(),
+        reply.trim(),
+        diff.trim_end()
+    )
+}
+
+/// The critic's objections, or `None` when it approved
+pub fn parse_verdict(reply: &str) -> Option<String> {
+    let reply = reply.trim();
+    let approved = reply.lines().next().is_some_and(|line| line.trim().trim_matches('.') == APPROVED_MARKER);
+    (!approved && !reply.is_empty()).then(|| reply.to_string())
+}
+
+fn tail(text: &str, max: usize) -> &str {
+    let mut start = text.len().saturating_sub(max);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+fn head(text: &str, max: usize) -> &str {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::ProcessOutput;
+    use async_trait::async_trait;
+    use std::io;
+
+    /// Fails commands mentioning `test`, passes the others
+    #[derive(Debug)]
+    struct Scripted;
+
+    #[async_trait]
+    impl ProcessRunner for Scripted {
+        async fn run(
+            &self,
+            _program: &str,
+            args: &[String],
+            _working_dir: Option<&Path>,
+            _env: &[(String, String)],
+            _stdin: Option<&[u8]>,
+        ) -> io::Result<ProcessOutput> {
+            let failing = args.last().is_some_and(|command| command.contains("test"));
+            let (exit_code, stderr) = if failing { (101, "test parses_input ... FAILED\n") } else { (0, "") };
+            Ok(ProcessOutput { exit_code: Some(exit_code), stdout: b"Compiling demo\n".to_vec(), stderr: stderr.into() })
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_checks_and_objections_fail_the_round() {
+        let checks = ["cargo build".to_string(), "cargo test".to_string()];
+        let outcomes = run_checks(&Scripted, Path::new("/repo"), &checks).await;
+        assert!(outcomes[0].passed() && !outcomes[1].passed());
+
+        let round = VerificationRound { checks: outcomes, objections: parse_verdict("The flag is never parsed.") };
+        assert!(!round.passed());
+        assert_eq!(round.summary(), "failed: `cargo test`; reviewer objected");
+        let failures = round.render_failures();
+        assert!(failures.contains("Check `cargo test` failed (exit code 101):\nCompiling demo\ntest parses_input ... FAILED"));
+        assert!(failures.contains("found:\nThe flag is never parsed."));
+        assert!(!failures.contains("cargo build"));
+
+        assert_eq!(parse_verdict("APPROVED."), None);
+        let passing = VerificationRound { checks: run_checks(&Scripted, Path::new("/repo"), &checks[..1]).await, objections: None };
+        assert!(passing.passed());
+        assert_eq!(passing.summary(), "passed (1 check(s))");
+    }
+}
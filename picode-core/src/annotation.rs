@@ -0,0 +1,141 @@
+//! Tags and notes on conversations
+//!
+//! A conversation can carry tags (`/tag bug-hunt`) and notes on individual
+//! exchanges (`/note "this approach worked"`). Both are stored with the
+//! conversation log and make stored sessions searchable by topic. Words
+//! written as `#tag` inside a note also tag the exchange it is attached to.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+/// Errors from tagging conversations
+#[derive(Error, Debug)]
+pub enum AnnotationError {
+    #[error("Invalid tag '{0}': use letters, digits, '-', '_' or '/'")]
+    InvalidTag(String),
+
+    #[error("Nothing to annotate: the conversation has no messages yet")]
+    EmptyConversation,
+}
+
+/// Canonical form of a tag: lowercase, without a leading `#`
+pub fn normalize_tag(tag: &str) -> Result<String, AnnotationError> {
+    let normalized = tag.trim().trim_start_matches('#').to_lowercase();
+    let valid = !normalized.is_empty()
+        && normalized
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/'));
+    if valid {
+        Ok(normalized)
+    } else {
+        Err(AnnotationError::InvalidTag(tag.to_string()))
+    }
+}
+
+/// A note on one exchange of the conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    /// Index of the message the note is attached to
+    pub message: usize,
+    pub text: String,
+    /// Tags of the exchange, taken from `#words` in the text
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Note {
+    pub fn new(message: usize, text: impl Into<String>) -> Self {
+        let text = text.into();
+        let tags = text
+            .split_whitespace()
+            .filter(|word| word.starts_with('#'))
+            .filter_map(|word| normalize_tag(word.trim_end_matches(|c: char| c.is_ascii_punctuation())).ok())
+            .collect();
+        Self {
+            message,
+            text,
+            tags,
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Tags and notes of a conversation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotations {
+    /// Tags of the whole conversation
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default)]
+    pub notes: Vec<Note>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.notes.is_empty()
+    }
+
+    /// Tag the conversation; false when it already had the tag
+    pub fn tag(&mut self, tag: &str) -> Result<bool, AnnotationError> {
+        Ok(self.tags.insert(normalize_tag(tag)?))
+    }
+
+    /// Remove a conversation tag; false when it was not there
+    pub fn untag(&mut self, tag: &str) -> Result<bool, AnnotationError> {
+        Ok(self.tags.remove(&normalize_tag(tag)?))
+    }
+
+    /// Notes attached to a message
+    pub fn notes_for(&self, message: usize) -> impl Iterator<Item = &Note> {
+        self.notes.iter().filter(move |note| note.message == message)
+    }
+
+    /// Tags of one exchange: the conversation's plus those of its notes
+    pub fn exchange_tags(&self, message: usize) -> BTreeSet<String> {
+        let mut tags = self.tags.clone();
+        tags.extend(self.notes_for(message).flat_map(|note| note.tags.iter().cloned()));
+        tags
+    }
+
+    /// Every tag used anywhere in the conversation
+    pub fn all_tags(&self) -> BTreeSet<String> {
+        let mut tags = self.tags.clone();
+        tags.extend(self.notes.iter().flat_map(|note| note.tags.iter().cloned()));
+        tags
+    }
+
+    /// Whether the conversation carries all of `tags` (already normalized)
+    pub fn has_all(&self, tags: &[String]) -> bool {
+        let all = self.all_tags();
+        tags.iter().all(|tag| all.contains(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_normalized_and_validated() {
+        let mut annotations = Annotations::default();
+        assert!(annotations.tag("#Bug-Hunt").unwrap());
+        assert!(!annotations.tag("bug-hunt").unwrap());
+        assert!(matches!(annotations.tag("two words"), Err(AnnotationError::InvalidTag(_))));
+        assert!(annotations.untag("BUG-HUNT").unwrap());
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn notes_tag_their_exchange() {
+        let mut annotations = Annotations::default();
+        annotations.tag("deploy").unwrap();
+        annotations.notes.push(Note::new(3, "this approach worked, #retry-logic."));
+        assert_eq!(annotations.notes[0].tags, BTreeSet::from(["retry-logic".to_string()]));
+        assert_eq!(annotations.exchange_tags(3).len(), 2);
+        assert_eq!(annotations.exchange_tags(1), BTreeSet::from(["deploy".to_string()]));
+        assert!(annotations.has_all(&["deploy".to_string(), "retry-logic".to_string()]));
+        assert!(!annotations.has_all(&["perf".to_string()]));
+    }
+}
@@ -0,0 +1,167 @@
+//! ANSI escape sequence sanitization
+//!
+//! LLM and command output may contain control sequences that corrupt the TUI
+//! or abuse terminal features (window title changes, hyperlinks, clipboard
+//! writes via OSC 52). Output is sanitized before rendering: everything is
+//! stripped except, optionally, plain SGR styling. The raw view shows the
+//! original bytes with every control character escaped, so none of them
+//! reaches the terminal.
+
+use serde::{Deserialize, Serialize};
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{07}';
+const CSI_C1: char = '\u{9b}';
+
+/// How escape sequences in output are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnsiPolicy {
+    /// Remove every escape sequence and control character
+    Strip,
+    /// Keep SGR color/style sequences only
+    #[default]
+    Styling,
+    /// Show every escape sequence and control character as text (raw view)
+    Raw,
+}
+
+/// Sanitize output according to a policy
+pub fn sanitize(input: &str, policy: AnsiPolicy) -> String {
+    if policy == AnsiPolicy::Raw {
+        return escape_visible(input);
+    }
+
+    let keep_styling = policy == AnsiPolicy::Styling;
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ESC => match chars.peek().copied() {
+                Some('[') => {
+                    chars.next();
+                    let sequence = read_csi(&mut chars);
+                    if keep_styling && is_sgr(&sequence) {
+                        out.push(ESC);
+                        out.push('[');
+                        out.push_str(&sequence);
+                    }
+                }
+                Some(']') | Some('P') | Some('_') | Some('^') | Some('X') => {
+                    chars.next();
+                    skip_string_sequence(&mut chars);
+                }
+                Some(_) => {
+                    // Two-character escape (e.g. ESC c resets the terminal)
+                    chars.next();
+                }
+                None => {}
+            },
+            CSI_C1 => {
+                read_csi(&mut chars);
+            }
+            '\n' | '\t' => out.push(c),
+            '\r' => {
+                // Bare carriage returns can overwrite already rendered text
+                if chars.peek() == Some(&'\n') {
+                    out.push('\r');
+                }
+            }
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Render control characters visibly, e.g. `ESC[31m` becomes `\x1b[31m`
+pub fn escape_visible(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '\n' | '\t' => out.push(c),
+            c if c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Check whether text contains any escape or control sequences
+pub fn contains_escapes(input: &str) -> bool {
+    input
+        .chars()
+        .any(|c| c.is_control() && c != '\n' && c != '\t' && c != '\r')
+}
+
+/// Read CSI parameters up to and including the final byte
+fn read_csi(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut sequence = String::new();
+    for c in chars.by_ref() {
+        sequence.push(c);
+        if ('\u{40}'..='\u{7e}').contains(&c) {
+            break;
+        }
+    }
+    sequence
+}
+
+/// Skip an OSC/DCS style string terminated by BEL or ST (ESC \)
+fn skip_string_sequence(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while let Some(c) = chars.next() {
+        match c {
+            BEL => return,
+            ESC if chars.peek() == Some(&'\\') => {
+                chars.next();
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_sgr(sequence: &str) -> bool {
+    sequence.ends_with('m')
+        && sequence[..sequence.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ';')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styling_policy_keeps_colors_only() {
+        let input = "\x1b[31mred\x1b[0m \x1b[2Jcleared\x1b]0;evil title\x07 done";
+        assert_eq!(sanitize(input, AnsiPolicy::Styling), "\x1b[31mred\x1b[0m cleared done");
+        assert_eq!(sanitize(input, AnsiPolicy::Strip), "red cleared done");
+        assert_eq!(
+            sanitize(input, AnsiPolicy::Raw),
+            "\\x1b[31mred\\x1b[0m \\x1b[2Jcleared\\x1b]0;evil title\\x07 done"
+        );
+    }
+
+    #[test]
+    fn raw_view_never_passes_sequences_through() {
+        let input = "link\x1b]8;;https://example.com\x1b\\here\x1b]8;;\x1b\\ \x1b]52;c;ZXZpbA==\x07";
+        let raw = sanitize(input, AnsiPolicy::Raw);
+        assert!(!contains_escapes(&raw));
+        assert_eq!(raw, "link\\x1b]8;;https://example.com\\x1b\\here\\x1b]8;;\\x1b\\ \\x1b]52;c;ZXZpbA==\\x07");
+    }
+
+    #[test]
+    fn strips_osc52_and_control_characters() {
+        let input = "copy\x1b]52;c;ZXZpbA==\x1b\\ me\x08\x07\r\nnext\roverwrite";
+        assert_eq!(sanitize(input, AnsiPolicy::Styling), "copy me\r\nnextoverwrite");
+    }
+
+    #[test]
+    fn visible_escapes() {
+        assert_eq!(escape_visible("\x1b[1mbold\n"), "\\x1b[1mbold\n");
+        assert!(contains_escapes("\x1b[1m"));
+        assert!(!contains_escapes("plain\ttext\n"));
+    }
+}
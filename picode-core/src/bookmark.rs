@@ -0,0 +1,242 @@
+//! Workspace bookmarks for PiCode
+//!
+//! Bookmarks point at files or lines of interest, are persisted per workspace
+//! under `.picode/bookmarks.json`, can be fuzzy-searched and exported into LLM
+//! context as a "points of interest" section.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::io::FileSystem;
+
+/// File name of the bookmark store inside the workspace `.picode` directory
+pub const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+/// A bookmarked file location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub path: PathBuf,
+    pub line: Option<u32>,
+    pub label: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Bookmark {
+    pub fn new(path: PathBuf, line: Option<u32>, label: String) -> Self {
+        Self {
+            path,
+            line,
+            label,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    /// Parse a `path[:line]` location
+    pub fn parse_location(location: &str) -> Result<(PathBuf, Option<u32>), BookmarkError> {
+        if location.trim().is_empty() {
+            return Err(BookmarkError::InvalidLocation(location.to_string()));
+        }
+
+        match location.rsplit_once(':') {
+            Some((path, line)) if !path.is_empty() && line.chars().all(|c| c.is_ascii_digit()) => {
+                let line = line
+                    .parse()
+                    .map_err(|_| BookmarkError::InvalidLocation(location.to_string()))?;
+                Ok((PathBuf::from(path), Some(line)))
+            }
+            _ => Ok((PathBuf::from(location), None)),
+        }
+    }
+
+    /// Location in `path[:line]` form
+    pub fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.path.display(), line),
+            None => self.path.display().to_string(),
+        }
+    }
+}
+
+/// Per-workspace bookmark collection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BookmarkStore {
+    #[serde(skip)]
+    file_path: PathBuf,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl BookmarkStore {
+    /// Load bookmarks for a workspace, starting empty if none were saved
+    #[cfg(feature = "native")]
+    pub async fn load(workspace_root: &Path) -> Result<Self, BookmarkError> {
+        Self::load_with(&crate::io::NativeFileSystem, workspace_root).await
+    }
+
+    /// Load bookmarks through the given file system
+    pub async fn load_with(fs: &dyn FileSystem, workspace_root: &Path) -> Result<Self, BookmarkError> {
+        let file_path = workspace_root.join(".picode").join(BOOKMARKS_FILE);
+
+        let mut store = if fs.exists(&file_path).await {
+            let content = fs.read_to_string(&file_path).await?;
+            serde_json::from_str::<BookmarkStore>(&content)?
+        } else {
+            BookmarkStore::default()
+        };
+
+        store.file_path = file_path;
+        Ok(store)
+    }
+
+    #[cfg(feature = "native")]
+    pub async fn save(&self) -> Result<(), BookmarkError> {
+        self.save_with(&crate::io::NativeFileSystem).await
+    }
+
+    /// Save bookmarks through the given file system
+    pub async fn save_with(&self, fs: &dyn FileSystem) -> Result<(), BookmarkError> {
+        if let Some(parent) = self.file_path.parent() {
+            fs.create_dir_all(parent).await?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs.write(&self.file_path, content.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Add a bookmark, replacing any existing one at the same location
+    pub fn add(&mut self, bookmark: Bookmark) {
+        self.bookmarks
+            .retain(|b| !(b.path == bookmark.path && b.line == bookmark.line));
+        self.bookmarks.push(bookmark);
+    }
+
+    /// Remove a bookmark by location or label
+    pub fn remove(&mut self, key: &str) -> Result<Bookmark, BookmarkError> {
+        let index = self
+            .bookmarks
+            .iter()
+            .position(|b| b.location() == key || b.label == key)
+            .ok_or_else(|| BookmarkError::NotFound(key.to_string()))?;
+        Ok(self.bookmarks.remove(index))
+    }
+
+    pub fn list(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    /// Fuzzy search bookmarks by label and location, best matches first
+    pub fn search(&self, query: &str) -> Vec<&Bookmark> {
+        let mut scored: Vec<(i64, &Bookmark)> = self
+            .bookmarks
+            .iter()
+            .filter_map(|b| {
+                let haystack = format!("{} {}", b.label, b.location());
+                fuzzy_score(query, &haystack).map(|score| (score, b))
+            })
+            .collect();
+
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, b)| b).collect()
+    }
+
+    /// Render bookmarks as a "points of interest" section for LLM context
+    pub fn to_context(&self) -> String {
+        if self.bookmarks.is_empty() {
+            return String::new();
+        }
+
+        let mut context = String::from("## Points of interest\n");
+        for bookmark in &self.bookmarks {
+            context.push_str(&format!("- {} — {}\n", bookmark.location(), bookmark.label));
+        }
+        context
+    }
+}
+
+/// Subsequence fuzzy match; higher scores for consecutive and early matches
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query = query.to_lowercase();
+    let candidate = candidate.to_lowercase();
+
+    let mut score = 0i64;
+    let mut last_match: Option<usize> = None;
+    let mut chars = candidate.char_indices();
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let (index, _) = chars.by_ref().find(|(_, c)| *c == q)?;
+        score += match last_match {
+            Some(last) if index == last + 1 => 5,
+            _ => 1,
+        };
+        if index == 0 {
+            score += 3;
+        }
+        last_match = Some(index);
+    }
+
+    Some(score)
+}
+
+/// Bookmark-related errors
+#[derive(Error, Debug)]
+pub enum BookmarkError {
+    #[error("Invalid bookmark location: {0}")]
+    InvalidLocation(String),
+
+    #[error("Bookmark not found: {0}")]
+    NotFound(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn location_parsing() {
+        let (path, line) = Bookmark::parse_location("src/lib.rs:120").unwrap();
+        assert_eq!(path, PathBuf::from("src/lib.rs"));
+        assert_eq!(line, Some(120));
+
+        let (path, line) = Bookmark::parse_location("README.md").unwrap();
+        assert_eq!(path, PathBuf::from("README.md"));
+        assert_eq!(line, None);
+
+        assert!(Bookmark::parse_location("  ").is_err());
+    }
+
+    #[test]
+    fn fuzzy_search_orders_matches() {
+        let mut store = BookmarkStore::default();
+        store.add(Bookmark::new(PathBuf::from("src/lib.rs"), Some(120), "entry point".to_string()));
+        store.add(Bookmark::new(PathBuf::from("src/event.rs"), None, "event bus".to_string()));
+
+        let results = store.search("entry");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].label, "entry point");
+        assert_eq!(store.search("evbus")[0].label, "event bus");
+        assert!(store.search("zzz").is_empty());
+    }
+
+    #[tokio::test]
+    async fn persistence_and_context_export() {
+        let temp_dir = tempdir().unwrap();
+
+        let mut store = BookmarkStore::load(temp_dir.path()).await.unwrap();
+        store.add(Bookmark::new(PathBuf::from("src/lib.rs"), Some(120), "entry point".to_string()));
+        store.save().await.unwrap();
+
+        let mut loaded = BookmarkStore::load(temp_dir.path()).await.unwrap();
+        assert_eq!(loaded.list().len(), 1);
+        assert_eq!(loaded.to_context(), "## Points of interest\n- src/lib.rs:120 — entry point\n");
+
+        loaded.remove("src/lib.rs:120").unwrap();
+        assert!(loaded.list().is_empty());
+    }
+}
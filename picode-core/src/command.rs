@@ -2,26 +2,13 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+#[cfg(feature = "native")]
 use std::process::Stdio;
 use thiserror::Error;
+#[cfg(feature = "native")]
 use tokio::process::Command as TokioCommand;
-use uuid::Uuid;
 
-/// Unique identifier for a command
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct CommandId(pub Uuid);
-
-impl CommandId {
-    pub fn new() -> Self {
-        Self(Uuid::new_v4())
-    }
-}
-
-impl std::fmt::Display for CommandId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
+pub use crate::id::CommandId;
 
 /// Command to be executed
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +18,9 @@ pub struct Command {
     pub args: Vec<String>,
     pub working_dir: Option<std::path::PathBuf>,
     pub env: HashMap<String, String>,
+    /// Start from an empty environment rather than PiCode's own
+    #[serde(default)]
+    pub clear_env: bool,
     pub stdin_data: Option<String>,
     pub timeout: Option<std::time::Duration>,
     pub created_at: chrono::DateTime<chrono::Utc>,
@@ -44,6 +34,7 @@ impl Command {
             args: Vec::new(),
             working_dir: None,
             env: HashMap::new(),
+            clear_env: false,
             stdin_data: None,
             timeout: None,
             created_at: chrono::Utc::now(),
@@ -65,6 +56,13 @@ impl Command {
         self
     }
     
+    /// Run with exactly `env`, inheriting nothing from PiCode's environment
+    pub fn with_clean_env(mut self, env: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.clear_env = true;
+        self.env = env.into_iter().collect();
+        self
+    }
+    
     pub fn with_stdin(mut self, data: String) -> Self {
         self.stdin_data = Some(data);
         self
@@ -75,6 +73,8 @@ impl Command {
         self
     }
     
+    #[cfg(feature = "native")]
+    #[tracing::instrument(name = "command.run", skip(self), fields(program = %self.program, args = self.args.len()), err)]
     pub async fn execute(&self) -> Result<CommandResult, CommandError> {
         let mut cmd = TokioCommand::new(&self.program);
         cmd.args(&self.args);
@@ -83,6 +83,9 @@ impl Command {
             cmd.current_dir(working_dir);
         }
         
+        if self.clear_env {
+            cmd.env_clear();
+        }
         for (key, value) in &self.env {
             cmd.env(key, value);
         }
@@ -142,6 +145,29 @@ pub struct CommandResult {
     pub finished_at: chrono::DateTime<chrono::Utc>,
 }
 
+impl CommandResult {
+    /// Standard output sanitized for display in the terminal UI
+    pub fn rendered_stdout(&self, policy: crate::ansi::AnsiPolicy) -> String {
+        crate::ansi::sanitize(&self.stdout, policy)
+    }
+    
+    /// Standard error sanitized for display in the terminal UI
+    pub fn rendered_stderr(&self, policy: crate::ansi::AnsiPolicy) -> String {
+        crate::ansi::sanitize(&self.stderr, policy)
+    }
+    
+    /// Combined output reduced for inclusion in a model prompt
+    pub fn prompt_output(&self, summarizer: &crate::summarize::CommandOutputSummarizer) -> String {
+        let output = match (self.stdout.is_empty(), self.stderr.is_empty()) {
+            (false, false) => format!("{}\n{}", self.stdout.trim_end(), self.stderr),
+            (true, _) => self.stderr.clone(),
+            (false, true) => self.stdout.clone(),
+        };
+        let plain = crate::ansi::sanitize(&output, crate::ansi::AnsiPolicy::Strip);
+        summarizer.summarize(&plain).text
+    }
+}
+
 /// Command execution status
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CommandStatus {
@@ -252,8 +278,8 @@ mod tests {
 
     #[tokio::test]
     async fn command_execution_success() {
-        let cmd = Command::new("echo".to_string())
-            .with_args(vec!["test".to_string()]);
+        // `echo` is a shell builtin on Windows, so go through the shell
+        let cmd = CommandBuilder::shell("echo test");
         
         let result = cmd.execute().await.unwrap();
         assert!(result.status.is_success());
@@ -313,29 +339,38 @@ mod tests {
     #[tokio::test]
     async fn command_with_working_dir() {
         let temp_dir = std::env::temp_dir();
-        let cmd = Command::new("pwd".to_string())
+        // `cd` without arguments prints the working directory in cmd.exe
+        let cmd = CommandBuilder::shell(if cfg!(windows) { "cd" } else { "pwd" })
             .with_working_dir(temp_dir.clone());
         
-        // Skip this test on Windows as pwd doesn't exist
-        #[cfg(unix)]
-        {
-            let result = cmd.execute().await.unwrap();
-            assert!(result.status.is_success());
-            assert!(result.stdout.trim().contains(&temp_dir.to_string_lossy()));
-        }
+        let result = cmd.execute().await.unwrap();
+        assert!(result.status.is_success());
+        // Symlinked (macOS) or 8.3 short (Windows) temp paths differ until canonicalized
+        assert_eq!(
+            crate::paths::canonicalize(result.stdout.trim()).unwrap(),
+            crate::paths::canonicalize(&temp_dir).unwrap()
+        );
     }
 
     #[tokio::test]
     async fn command_with_env() {
-        let cmd = Command::new("env".to_string())
+        let cmd = CommandBuilder::shell(if cfg!(windows) { "set PICODE_TEST" } else { "env" })
             .with_env("PICODE_TEST".to_string(), "test_value".to_string());
         
-        // Skip this test on Windows as env command is different
-        #[cfg(unix)]
-        {
-            let result = cmd.execute().await.unwrap();
-            assert!(result.status.is_success());
-            assert!(result.stdout.contains("PICODE_TEST=test_value"));
-        }
+        let result = cmd.execute().await.unwrap();
+        assert!(result.status.is_success());
+        assert!(result.stdout.contains("PICODE_TEST=test_value"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command_with_clean_env() {
+        let path = std::env::var("PATH").unwrap_or_default();
+        let cmd = CommandBuilder::shell("env")
+            .with_clean_env([("PATH".to_string(), path), ("PICODE_ONLY".to_string(), "1".to_string())]);
+        
+        let result = cmd.execute().await.unwrap();
+        assert!(result.stdout.contains("PICODE_ONLY=1"));
+        assert!(!result.stdout.contains("HOME="));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,88 @@
+//! Follow-up messages composed while a reply is generated
+//!
+//! The user can keep typing while the model answers. Text that was typed
+//! but not yet submitted is the draft; submitted lines wait in the queue
+//! and are sent one at a time once the running turn finishes. The composer
+//! is saved with the conversation, so a session that is resumed after a
+//! crash or an exit still has the draft and the queued messages.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Draft and queued follow-up messages of a conversation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Composer {
+    #[serde(default)]
+    pub draft: String,
+    #[serde(default)]
+    pub queued: VecDeque<String>,
+}
+
+impl Composer {
+    pub fn is_empty(&self) -> bool {
+        self.draft.is_empty() && self.queued.is_empty()
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.draft.push(c);
+    }
+
+    pub fn pop_char(&mut self) -> Option<char> {
+        self.draft.pop()
+    }
+
+    /// Queue the draft; returns the queued text, or `None` for a blank draft
+    pub fn submit(&mut self) -> Option<&str> {
+        let draft = std::mem::take(&mut self.draft);
+        let text = draft.trim();
+        if text.is_empty() {
+            return None;
+        }
+        self.queued.push_back(text.to_string());
+        self.queued.back().map(String::as_str)
+    }
+
+    /// The next queued message, oldest first
+    pub fn next_queued(&mut self) -> Option<String> {
+        self.queued.pop_front()
+    }
+
+    /// Hand the draft to the prompt, leaving it empty
+    pub fn take_draft(&mut self) -> String {
+        std::mem::take(&mut self.draft)
+    }
+
+    /// Drop the queue; returns how many messages were dropped
+    pub fn clear_queue(&mut self) -> usize {
+        let dropped = self.queued.len();
+        self.queued.clear();
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn submitted_drafts_are_queued_in_order() {
+        let mut composer = Composer::default();
+        for c in "  also add tests ".chars() {
+            composer.push_char(c);
+        }
+        assert_eq!(composer.submit(), Some("also add tests"));
+        assert_eq!(composer.submit(), None);
+        "and docsx".chars().for_each(|c| composer.push_char(c));
+        composer.pop_char();
+        composer.submit();
+        composer.push_char('?');
+
+        let saved: Composer = serde_json::from_str(&serde_json::to_string(&composer).unwrap()).unwrap();
+        assert_eq!(saved, composer);
+        assert_eq!(composer.next_queued().as_deref(), Some("also add tests"));
+        assert_eq!(composer.next_queued().as_deref(), Some("and docs"));
+        assert_eq!(composer.next_queued(), None);
+        assert_eq!(composer.take_draft(), "?");
+        assert!(composer.is_empty());
+    }
+}
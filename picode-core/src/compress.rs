@@ -0,0 +1,265 @@
+//! Extractive prompt compression
+//!
+//! Large files included in a request are reduced by dropping their least
+//! relevant lines. Every line gets a relevance score — from
+//! [`heuristic_scores`] or from a cheap model — and the best scoring share
+//! allowed by the [`CompressionLevel`] is kept. Structure markers
+//! (declarations, headings, fences, closing braces) are always kept, and
+//! every dropped run is replaced by an indented `⋯ N line(s) omitted` marker
+//! so the model still sees the shape of the file.
+
+use crate::system_prompt::estimate_tokens;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::OnceLock;
+
+/// How much of a file's content may be dropped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionLevel {
+    #[default]
+    Off,
+    Light,
+    Medium,
+    Aggressive,
+}
+
+impl CompressionLevel {
+    /// Share of the droppable lines that is kept
+    pub fn keep_ratio(self) -> f64 {
+        match self {
+            Self::Off => 1.0,
+            Self::Light => 0.75,
+            Self::Medium => 0.5,
+            Self::Aggressive => 0.3,
+        }
+    }
+}
+
+impl std::fmt::Display for CompressionLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Off => "off",
+            Self::Light => "light",
+            Self::Medium => "medium",
+            Self::Aggressive => "aggressive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Declarations, headings and fences that outline a file
+fn structure_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"^\s*(",
+            r"(pub(\(\w+\))?\s+)?(async\s+)?(fn|struct|enum|trait|impl|mod|type|const|static)\b|macro_rules!",
+            r"|(export\s+)?(default\s+)?(async\s+)?(function|class|interface)\b",
+            r"|(async\s+)?def\s|class\s|package\s|func\s",
+            r"|#{1,6}\s|#\[|```",
+            r"|[}\]);]+,?;?\s*$",
+            r")",
+        ))
+        .expect("built-in structure pattern is valid")
+    })
+}
+
+fn is_comment(line: &str) -> bool {
+    ["//", "#", "/*", "*", "--", ";"].iter().any(|prefix| line.starts_with(prefix))
+}
+
+/// Score every line of `text` by how relevant it looks to `query`: words of
+/// the query it mentions, with blank and comment lines least relevant. Lines
+/// next to relevant ones share part of their score.
+pub fn heuristic_scores(text: &str, query: &str) -> Vec<f64> {
+    let mut terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect();
+    terms.sort();
+    terms.dedup();
+
+    let base: Vec<f64> = text
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            if line.is_empty() {
+                return 0.0;
+            }
+            if is_comment(line) {
+                return 0.2;
+            }
+            let lower = line.to_lowercase();
+            0.5 + terms.iter().filter(|term| lower.contains(term.as_str())).count() as f64
+        })
+        .collect();
+    (0..base.len())
+        .map(|i| {
+            let neighbour = [i.checked_sub(1), Some(i + 1)]
+                .into_iter()
+                .flatten()
+                .filter_map(|j| base.get(j).copied())
+                .fold(0.0, f64::max);
+            base[i] + 0.25 * (neighbour - 0.5).max(0.0)
+        })
+        .collect()
+}
+
+/// Result of compressing one text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compressed {
+    pub text: String,
+    pub original_tokens: usize,
+    pub tokens: usize,
+    pub omitted_lines: usize,
+}
+
+impl Compressed {
+    fn unchanged(text: &str) -> Self {
+        let tokens = estimate_tokens(text);
+        Self {
+            text: text.to_string(),
+            original_tokens: tokens,
+            tokens,
+            omitted_lines: 0,
+        }
+    }
+
+    pub fn saved_tokens(&self) -> usize {
+        self.original_tokens.saturating_sub(self.tokens)
+    }
+}
+
+/// Drops low-relevance lines from texts larger than `min_tokens`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptCompressor {
+    pub level: CompressionLevel,
+    pub min_tokens: usize,
+}
+
+impl PromptCompressor {
+    pub fn new(level: CompressionLevel, min_tokens: usize) -> Self {
+        Self { level, min_tokens }
+    }
+
+    /// Whether `text` is large enough to be compressed at all
+    pub fn applies_to(&self, text: &str) -> bool {
+        self.level != CompressionLevel::Off && estimate_tokens(text) >= self.min_tokens
+    }
+
+    /// Keep the structure markers and the best scoring lines of `text`;
+    /// `scores` holds one score per line, missing scores count as average
+    pub fn compress(&self, text: &str, scores: &[f64]) -> Compressed {
+        if !self.applies_to(text) {
+            return Compressed::unchanged(text);
+        }
+        let lines: Vec<&str> = text.lines().collect();
+        let score = |i: usize| scores.get(i).copied().unwrap_or(0.5);
+
+        let mut keep: Vec<bool> = lines.iter().map(|line| structure_pattern().is_match(line)).collect();
+        let mut candidates: Vec<usize> = (0..lines.len()).filter(|&i| !keep[i]).collect();
+        let budget = (candidates.len() as f64 * self.level.keep_ratio()).ceil() as usize;
+        candidates.sort_by(|&a, &b| score(b).partial_cmp(&score(a)).unwrap_or(Ordering::Equal).then(a.cmp(&b)));
+        for &i in candidates.iter().take(budget) {
+            keep[i] = true;
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut omitted_lines = 0;
+        // Indentation, length and whether anything but blank lines was dropped
+        let mut run: Option<(&str, usize, bool)> = None;
+        for (i, line) in lines.iter().enumerate() {
+            if keep[i] {
+                flush_run(&mut out, &mut run);
+                out.push_str(line);
+                out.push('\n');
+                continue;
+            }
+            omitted_lines += 1;
+            let has_content = !line.trim().is_empty();
+            match &mut run {
+                Some((indent, count, content)) => {
+                    if !*content && has_content {
+                        *indent = &line[..line.len() - line.trim_start().len()];
+                    }
+                    *count += 1;
+                    *content |= has_content;
+                }
+                None => run = Some((&line[..line.len() - line.trim_start().len()], 1, has_content)),
+            }
+        }
+        flush_run(&mut out, &mut run);
+
+        Compressed {
+            original_tokens: estimate_tokens(text),
+            tokens: estimate_tokens(&out),
+            text: out,
+            omitted_lines,
+        }
+    }
+}
+
+/// Replace a dropped run with its marker; runs of blank lines just vanish
+fn flush_run(out: &mut String, run: &mut Option<(&str, usize, bool)>) {
+    if let Some((indent, count, has_content)) = run.take() {
+        if has_content {
+            out.push_str(&format!("{}⋯ {} line(s) omitted\n", indent, count));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+/// Connection pool
+pub struct Pool {
+    // TODO: tune
+    size: usize,
+    idle: Vec<Conn>,
+}
+
+impl Pool {
+    pub fn checkout(&mut self) -> Option<Conn> {
+        let conn = self.idle.pop();
+        metrics::increment(\"checkout\");
+        conn
+    }
+
+    pub fn resize(&mut self, size: usize) {
+        self.size = size;
+        self.idle.truncate(size);
+    }
+}
+";
+
+    #[test]
+    fn keeps_structure_and_relevant_lines() {
+        let compressor = PromptCompressor::new(CompressionLevel::Aggressive, 0);
+        let compressed = compressor.compress(SOURCE, &heuristic_scores(SOURCE, "why does resize truncate idle?"));
+
+        assert!(compressed.text.contains("pub struct Pool {"));
+        assert!(compressed.text.contains("    pub fn checkout(&mut self) -> Option<Conn> {"));
+        assert!(compressed.text.contains("        self.idle.truncate(size);"));
+        assert!(!compressed.text.contains("metrics::increment"));
+        assert!(compressed.text.contains("        ⋯ "));
+        assert!(compressed.omitted_lines > 0);
+        assert!(compressed.tokens < compressed.original_tokens);
+        assert_eq!(compressed.saved_tokens(), compressed.original_tokens - compressed.tokens);
+    }
+
+    #[test]
+    fn small_texts_and_off_level_are_untouched() {
+        let off = PromptCompressor::new(CompressionLevel::Off, 0).compress(SOURCE, &[]);
+        assert_eq!(off.text, SOURCE);
+        assert_eq!(off.saved_tokens(), 0);
+
+        let small = PromptCompressor::new(CompressionLevel::Medium, 10_000);
+        assert!(!small.applies_to(SOURCE));
+        assert_eq!(small.compress(SOURCE, &[]).text, SOURCE);
+    }
+}
@@ -0,0 +1,369 @@
+//! Shared file content cache
+//!
+//! The indexer, the context builders and the edit paths all read the same
+//! workspace files over and over. [`ContentCache`] keeps their contents in
+//! memory keyed by path, together with a blake3 hash of the content and the
+//! modification time seen when the file was read. A cached entry is reused
+//! while the file's mtime is unchanged; hosts without mtimes fall back to
+//! re-reading and comparing hashes. File watchers call
+//! [`ContentCache::handle_fs_event`] to drop entries eagerly, and the cache
+//! evicts least recently used entries once it grows past its memory limit.
+
+use crate::io::FileSystem;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Default memory limit for cached file contents
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Hex encoded blake3 hash of some content
+pub fn content_hash(content: &[u8]) -> String {
+    blake3::hash(content).to_hex().to_string()
+}
+
+/// A file as served from the cache
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedFile {
+    pub path: PathBuf,
+    pub content: Arc<str>,
+    /// blake3 hash of the content
+    pub hash: String,
+}
+
+/// Counters describing cache effectiveness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from memory
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    content: Arc<str>,
+    hash: String,
+    modified: Option<SystemTime>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<PathBuf, CacheEntry>,
+    clock: u64,
+    stats: CacheStats,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, path: &Path) -> bool {
+        match self.entries.remove(path) {
+            Some(entry) => {
+                self.stats.bytes -= entry.content.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, path: PathBuf, entry: CacheEntry) {
+        self.remove(&path);
+        self.stats.bytes += entry.content.len();
+        self.entries.insert(path, entry);
+    }
+
+    /// Evict least recently used entries until the cache fits `max_bytes`
+    fn evict(&mut self, max_bytes: usize) {
+        while self.stats.bytes > max_bytes {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone());
+            match oldest {
+                Some(path) => {
+                    self.remove(&path);
+                    self.stats.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// In-memory cache of file contents, shared as `Arc<ContentCache>`
+#[derive(Debug)]
+pub struct ContentCache {
+    max_bytes: usize,
+    state: Mutex<CacheState>,
+}
+
+impl ContentCache {
+    pub fn new() -> Self {
+        Self::with_max_bytes(DEFAULT_MAX_BYTES)
+    }
+
+    /// Create a cache holding at most `max_bytes` of file content
+    pub fn with_max_bytes(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Process-wide cache shared by subsystems that are not handed one
+    pub fn global() -> Arc<ContentCache> {
+        static GLOBAL: OnceLock<Arc<ContentCache>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(ContentCache::new())).clone()
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Read a UTF-8 file through the cache
+    pub async fn read(&self, fs: &dyn FileSystem, path: &Path) -> io::Result<CachedFile> {
+        let modified = fs.modified(path).await?;
+        if let Some(modified) = modified {
+            let mut state = self.lock();
+            let tick = state.tick();
+            if let Some(entry) = state.entries.get_mut(path) {
+                if entry.modified == Some(modified) {
+                    entry.last_used = tick;
+                    let cached = CachedFile {
+                        path: path.to_path_buf(),
+                        content: entry.content.clone(),
+                        hash: entry.hash.clone(),
+                    };
+                    state.stats.hits += 1;
+                    return Ok(cached);
+                }
+            }
+        }
+
+        let content = fs.read_to_string(path).await?;
+        let hash = content_hash(content.as_bytes());
+
+        let mut state = self.lock();
+        let tick = state.tick();
+        // Without an mtime (or after a touch) an unchanged hash still counts
+        // as a hit: the cached copy is kept and only revalidated
+        if let Some(entry) = state.entries.get_mut(path) {
+            if entry.hash == hash {
+                entry.modified = modified;
+                entry.last_used = tick;
+                let cached = CachedFile {
+                    path: path.to_path_buf(),
+                    content: entry.content.clone(),
+                    hash,
+                };
+                state.stats.hits += 1;
+                return Ok(cached);
+            }
+        }
+
+        state.stats.misses += 1;
+        let content: Arc<str> = Arc::from(content);
+        if content.len() <= self.max_bytes {
+            state.insert(
+                path.to_path_buf(),
+                CacheEntry {
+                    content: content.clone(),
+                    hash: hash.clone(),
+                    modified,
+                    last_used: tick,
+                },
+            );
+            state.evict(self.max_bytes);
+        } else {
+            state.remove(path);
+        }
+
+        Ok(CachedFile {
+            path: path.to_path_buf(),
+            content,
+            hash,
+        })
+    }
+
+    /// Write a file and keep the cache in step with the new content
+    pub async fn write(&self, fs: &dyn FileSystem, path: &Path, content: &str) -> io::Result<CachedFile> {
+        fs.write(path, content.as_bytes()).await?;
+        let modified = fs.modified(path).await.unwrap_or(None);
+        Ok(self.store(path, content, modified))
+    }
+
+    /// Record content that is known to be on disk, e.g. after an edit
+    pub fn store(&self, path: &Path, content: &str, modified: Option<SystemTime>) -> CachedFile {
+        let cached = CachedFile {
+            path: path.to_path_buf(),
+            content: Arc::from(content),
+            hash: content_hash(content.as_bytes()),
+        };
+
+        let mut state = self.lock();
+        if cached.content.len() <= self.max_bytes {
+            let last_used = state.tick();
+            state.insert(
+                path.to_path_buf(),
+                CacheEntry {
+                    content: cached.content.clone(),
+                    hash: cached.hash.clone(),
+                    modified,
+                    last_used,
+                },
+            );
+            state.evict(self.max_bytes);
+        } else {
+            state.remove(path);
+        }
+        cached
+    }
+
+    /// Hash of the cached content of a file, without touching the disk
+    pub fn cached_hash(&self, path: &Path) -> Option<String> {
+        self.lock().entries.get(path).map(|entry| entry.hash.clone())
+    }
+
+    /// Drop a cached file; returns true when an entry was removed
+    pub fn invalidate(&self, path: &Path) -> bool {
+        let mut state = self.lock();
+        let removed = state.remove(path);
+        if removed {
+            state.stats.invalidations += 1;
+        }
+        removed
+    }
+
+    /// Drop every cached file below a directory
+    pub fn invalidate_dir(&self, dir: &Path) -> usize {
+        let mut state = self.lock();
+        let paths: Vec<PathBuf> = state
+            .entries
+            .keys()
+            .filter(|path| path.starts_with(dir))
+            .cloned()
+            .collect();
+        for path in &paths {
+            state.remove(path);
+        }
+        state.stats.invalidations += paths.len() as u64;
+        paths.len()
+    }
+
+    /// Notify the cache of a file system change reported by a watcher
+    pub fn handle_fs_event(&self, path: &Path) -> bool {
+        self.invalidate(path) || self.invalidate_dir(path) > 0
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.entries.clear();
+        state.stats.bytes = 0;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.lock();
+        CacheStats {
+            entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for ContentCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    #[tokio::test]
+    async fn unchanged_content_is_served_from_memory() {
+        let fs = MemoryFileSystem::new();
+        fs.insert("/ws/lib.rs", "pub fn a() {}");
+        let cache = ContentCache::new();
+
+        let first = cache.read(&fs, Path::new("/ws/lib.rs")).await.unwrap();
+        let second = cache.read(&fs, Path::new("/ws/lib.rs")).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.hash, content_hash(b"pub fn a() {}"));
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+
+        fs.insert("/ws/lib.rs", "pub fn b() {}");
+        let changed = cache.read(&fs, Path::new("/ws/lib.rs")).await.unwrap();
+        assert_eq!(&*changed.content, "pub fn b() {}");
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(cache.stats().bytes, "pub fn b() {}".len());
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_entries_are_evicted() {
+        let fs = MemoryFileSystem::new();
+        for name in ["a", "b", "c"] {
+            fs.insert(format!("/ws/{}", name), "0123456789");
+        }
+        let cache = ContentCache::with_max_bytes(20);
+
+        cache.read(&fs, Path::new("/ws/a")).await.unwrap();
+        cache.read(&fs, Path::new("/ws/b")).await.unwrap();
+        cache.read(&fs, Path::new("/ws/a")).await.unwrap();
+        cache.read(&fs, Path::new("/ws/c")).await.unwrap();
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.bytes, stats.evictions), (2, 20, 1));
+        assert!(cache.cached_hash(Path::new("/ws/a")).is_some());
+        assert!(cache.cached_hash(Path::new("/ws/b")).is_none());
+    }
+
+    #[tokio::test]
+    async fn watcher_events_and_writes_update_entries() {
+        let fs = MemoryFileSystem::new();
+        fs.insert("/ws/src/main.rs", "fn main() {}");
+        let cache = ContentCache::new();
+
+        cache.read(&fs, Path::new("/ws/src/main.rs")).await.unwrap();
+        assert!(cache.handle_fs_event(Path::new("/ws/src")));
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().invalidations, 1);
+
+        let written = cache
+            .write(&fs, Path::new("/ws/src/main.rs"), "fn main() { run() }")
+            .await
+            .unwrap();
+        assert_eq!(cache.cached_hash(Path::new("/ws/src/main.rs")), Some(written.hash));
+        let read = cache.read(&fs, Path::new("/ws/src/main.rs")).await.unwrap();
+        assert_eq!(&*read.content, "fn main() { run() }");
+        assert_eq!(cache.stats().hits, 1);
+    }
+}
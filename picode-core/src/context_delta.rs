@@ -0,0 +1,238 @@
+//! Differential context updates between turns
+//!
+//! Long sessions used to resend every context file on each turn. The
+//! [`ContextTracker`] remembers what the model has already been shown in the
+//! conversation and produces a [`ContextUpdate`] with only what changed since:
+//! new files in full, modified files as unified diff hunks and files that
+//! left the context by name. The update is rendered as an explicit
+//! `<context-update>` message so the model knows to apply it to what it saw
+//! earlier rather than treat it as the whole picture.
+
+use crate::content_cache::content_hash;
+use crate::system_prompt::estimate_tokens;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Tag wrapping rendered context updates
+pub const CONTEXT_UPDATE_TAG: &str = "context-update";
+
+/// Unchanged lines kept around each diff hunk
+const CONTEXT_RADIUS: usize = 3;
+
+/// Change to a single context file since the previous turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum FileDelta {
+    /// Not seen before; sent in full
+    Added { path: PathBuf, content: String },
+    /// Seen before; only the changed hunks are sent
+    Changed { path: PathBuf, diff: String },
+    /// Changed so much that the full content is smaller than the diff
+    Replaced { path: PathBuf, content: String },
+    /// No longer part of the context
+    Removed { path: PathBuf },
+}
+
+impl FileDelta {
+    /// Delta from `old` to `new` content of a file: its changed hunks, or
+    /// the full content when that is smaller than the diff
+    pub fn between(path: PathBuf, old: &str, new: &str) -> Self {
+        let diff = TextDiff::from_lines(old, new)
+            .unified_diff()
+            .context_radius(CONTEXT_RADIUS)
+            .to_string();
+        if estimate_tokens(&diff) < estimate_tokens(new) {
+            FileDelta::Changed { path, diff }
+        } else {
+            FileDelta::Replaced { path, content: new.to_string() }
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            FileDelta::Added { path, .. }
+            | FileDelta::Changed { path, .. }
+            | FileDelta::Replaced { path, .. }
+            | FileDelta::Removed { path } => path,
+        }
+    }
+}
+
+/// Context changes to send with one turn
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextUpdate {
+    pub turn: u64,
+    pub deltas: Vec<FileDelta>,
+    /// Files still in context that the model already has verbatim
+    pub unchanged: Vec<PathBuf>,
+}
+
+impl ContextUpdate {
+    /// True when the model's view of the context is already current
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Message text describing the update
+    pub fn render(&self) -> String {
+        let mut out = format!("<{} turn=\"{}\">\n", CONTEXT_UPDATE_TAG, self.turn);
+        out.push_str("Apply these changes to the file context you were given earlier.\n");
+        for delta in &self.deltas {
+            let path = delta.path().display();
+            let _ = match delta {
+                FileDelta::Added { content, .. } => write!(out, "\nadded: {}\n```\n{}\n```\n", path, content.trim_end()),
+                FileDelta::Replaced { content, .. } => {
+                    write!(out, "\nreplaced: {}\n```\n{}\n```\n", path, content.trim_end())
+                }
+                FileDelta::Changed { diff, .. } => write!(out, "\nchanged: {}\n```diff\n{}\n```\n", path, diff.trim_end()),
+                FileDelta::Removed { .. } => write!(out, "\nremoved: {} (no longer relevant)\n", path),
+            };
+        }
+        if !self.unchanged.is_empty() {
+            let names: Vec<String> = self.unchanged.iter().map(|p| p.display().to_string()).collect();
+            let _ = write!(out, "\nunchanged: {}\n", names.join(", "));
+        }
+        let _ = write!(out, "</{}>", CONTEXT_UPDATE_TAG);
+        out
+    }
+}
+
+/// Tokens sent versus what resending full context would have cost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextStats {
+    pub turns: u64,
+    pub full_tokens: usize,
+    pub sent_tokens: usize,
+}
+
+impl ContextStats {
+    pub fn tokens_saved(&self) -> usize {
+        self.full_tokens.saturating_sub(self.sent_tokens)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SeenFile {
+    content: String,
+    hash: String,
+}
+
+/// What file context a conversation's model has already seen
+#[derive(Debug, Clone, Default)]
+pub struct ContextTracker {
+    seen: BTreeMap<PathBuf, SeenFile>,
+    stats: ContextStats,
+}
+
+impl ContextTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compute the update for this turn's context files and remember them as
+    /// seen; files seen earlier but absent now are reported as removed
+    pub fn update(&mut self, files: impl IntoIterator<Item = (PathBuf, String)>) -> ContextUpdate {
+        let current: BTreeMap<PathBuf, String> = files.into_iter().collect();
+        self.stats.turns += 1;
+        let mut update = ContextUpdate {
+            turn: self.stats.turns,
+            ..ContextUpdate::default()
+        };
+
+        let removed: Vec<PathBuf> = self.seen.keys().filter(|path| !current.contains_key(*path)).cloned().collect();
+        for path in removed {
+            self.seen.remove(&path);
+            update.deltas.push(FileDelta::Removed { path });
+        }
+
+        for (path, content) in current {
+            self.stats.full_tokens += estimate_tokens(&content);
+            let hash = content_hash(content.as_bytes());
+            let delta = match self.seen.get(&path) {
+                Some(seen) if seen.hash == hash => {
+                    update.unchanged.push(path);
+                    continue;
+                }
+                Some(seen) => FileDelta::between(path.clone(), &seen.content, &content),
+                None => FileDelta::Added { path: path.clone(), content: content.clone() },
+            };
+            self.seen.insert(path, SeenFile { content, hash });
+            update.deltas.push(delta);
+        }
+
+        if !update.is_empty() {
+            self.stats.sent_tokens += estimate_tokens(&update.render());
+        }
+        update
+    }
+
+    /// Forget everything, e.g. after the conversation history was compacted
+    /// and earlier context messages are gone
+    pub fn reset(&mut self) {
+        self.seen.clear();
+    }
+
+    pub fn is_seen(&self, path: &std::path::Path) -> bool {
+        self.seen.contains_key(path)
+    }
+
+    pub fn stats(&self) -> ContextStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: &str) -> (PathBuf, String) {
+        (PathBuf::from(path), content.to_string())
+    }
+
+    fn numbered(count: usize, changed: Option<usize>) -> String {
+        (0..count)
+            .map(|i| if Some(i) == changed { format!("let changed_{} = {};\n", i, i) } else { format!("let line_{} = {};\n", i, i) })
+            .collect()
+    }
+
+    #[test]
+    fn sends_only_deltas_after_first_turn() {
+        let mut tracker = ContextTracker::new();
+        let first = tracker.update([file("src/a.rs", &numbered(200, None)), file("src/b.rs", "fn b() {}\n")]);
+        assert_eq!(first.deltas.len(), 2);
+        assert!(matches!(first.deltas[0], FileDelta::Added { .. }));
+
+        let second = tracker.update([file("src/a.rs", &numbered(200, Some(100))), file("src/b.rs", "fn b() {}\n")]);
+        assert_eq!(second.unchanged, vec![PathBuf::from("src/b.rs")]);
+        let FileDelta::Changed { diff, .. } = &second.deltas[0] else {
+            panic!("expected a diff, got {:?}", second.deltas[0]);
+        };
+        assert!(diff.contains("-let line_100 = 100;"));
+        assert!(diff.contains("+let changed_100 = 100;"));
+        assert!(!diff.contains("line_10 = 10;"));
+
+        let third = tracker.update([file("src/a.rs", &numbered(200, Some(100))), file("src/b.rs", "fn b() {}\n")]);
+        assert!(third.is_empty());
+        assert!(tracker.stats().tokens_saved() > tracker.stats().sent_tokens);
+    }
+
+    #[test]
+    fn removed_files_and_reset() {
+        let mut tracker = ContextTracker::new();
+        tracker.update([file("a.txt", "a\n"), file("b.txt", "b\n")]);
+
+        let update = tracker.update([file("a.txt", "a\n")]);
+        assert_eq!(update.deltas, vec![FileDelta::Removed { path: PathBuf::from("b.txt") }]);
+        let rendered = update.render();
+        assert!(rendered.starts_with("<context-update turn=\"2\">"));
+        assert!(rendered.contains("removed: b.txt"));
+        assert!(rendered.contains("unchanged: a.txt"));
+
+        tracker.reset();
+        let resent = tracker.update([file("a.txt", "a\n")]);
+        assert!(matches!(resent.deltas[0], FileDelta::Added { .. }));
+    }
+}
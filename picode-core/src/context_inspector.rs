@@ -0,0 +1,321 @@
+//! Prompt composition breakdown (`/context`)
+//!
+//! A [`ContextBreakdown`] lists everything that goes into the next request —
+//! system prompt layers, pinned items, file context chunks and conversation
+//! history — with estimated token counts, so users can see what fills the
+//! context window. Items are numbered and can be pruned with
+//! [`ContextBreakdown::prune`]. Items sent compressed (see
+//! [`crate::compress`]) also show their compressed size and the savings.
+
+use crate::conversation::ConversationLog;
+use crate::system_prompt::{estimate_tokens, PromptLayerKind, SystemPrompt};
+use std::fmt::Write;
+
+/// Width of the heat bars in the rendered breakdown
+const BAR_WIDTH: usize = 24;
+
+/// Section of the prompt an item belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ContextItemKind {
+    System,
+    Pinned,
+    Files,
+    History,
+}
+
+impl std::fmt::Display for ContextItemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ContextItemKind::System => "system prompt",
+            ContextItemKind::Pinned => "pinned",
+            ContextItemKind::Files => "file chunks",
+            ContextItemKind::History => "history",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Where an item lives, so it can be pruned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextItemSource {
+    Layer(PromptLayerKind),
+    Pinned(usize),
+    Message(usize),
+}
+
+/// One numbered entry of the breakdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextItem {
+    pub kind: ContextItemKind,
+    pub label: String,
+    pub tokens: usize,
+    /// Size after prompt compression, when it is sent compressed
+    pub compressed_tokens: Option<usize>,
+    pub source: ContextItemSource,
+}
+
+/// What the next request is made of
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextBreakdown {
+    pub items: Vec<ContextItem>,
+}
+
+impl ContextBreakdown {
+    pub fn build(system: &SystemPrompt, log: &ConversationLog) -> Self {
+        let mut items: Vec<ContextItem> = system
+            .layers()
+            .iter()
+            .map(|layer| ContextItem {
+                kind: ContextItemKind::System,
+                label: format!("{} ({})", layer.kind, layer.source),
+                tokens: layer.tokens(),
+                compressed_tokens: None,
+                source: ContextItemSource::Layer(layer.kind),
+            })
+            .collect();
+
+        items.extend(log.pinned.iter().enumerate().map(|(index, pinned)| ContextItem {
+            kind: ContextItemKind::Pinned,
+            label: pinned.label.clone(),
+            tokens: estimate_tokens(&pinned.content),
+            compressed_tokens: None,
+            source: ContextItemSource::Pinned(index),
+        }));
+
+        for (index, message) in log.messages.iter().enumerate() {
+            if message.is_annotation() {
+                continue;
+            }
+            let (kind, label) = if message.is_context_update() {
+                (ContextItemKind::Files, message.content.lines().next().unwrap_or_default().to_string())
+            } else {
+                (ContextItemKind::History, format!("{}: {}", message.role, preview(&message.content)))
+            };
+            items.push(ContextItem {
+                kind,
+                label,
+                tokens: estimate_tokens(&message.content),
+                compressed_tokens: None,
+                source: ContextItemSource::Message(index),
+            });
+        }
+
+        Self { items }
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.items.iter().map(|item| item.tokens).sum()
+    }
+
+    /// Record the size an item is sent at after prompt compression
+    pub fn set_compressed(&mut self, source: ContextItemSource, tokens: usize) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.source == source) {
+            item.compressed_tokens = Some(tokens);
+        }
+    }
+
+    /// Tokens prompt compression removes from the request
+    pub fn compression_savings(&self) -> usize {
+        self.items
+            .iter()
+            .filter_map(|item| item.compressed_tokens.map(|tokens| item.tokens.saturating_sub(tokens)))
+            .sum()
+    }
+
+    /// Token total per section, in prompt order
+    pub fn sections(&self) -> Vec<(ContextItemKind, usize)> {
+        let mut sections: Vec<(ContextItemKind, usize)> = Vec::new();
+        for item in &self.items {
+            match sections.iter_mut().find(|(kind, _)| *kind == item.kind) {
+                Some((_, tokens)) => *tokens += item.tokens,
+                None => sections.push((item.kind, item.tokens)),
+            }
+        }
+        sections.sort_by_key(|(kind, _)| *kind);
+        sections
+    }
+
+    /// Sections with heat bars, then every numbered item; percentages are of
+    /// the model's context window when known, otherwise of the prompt total
+    pub fn render(&self, window: Option<usize>) -> String {
+        let total = self.total_tokens();
+        let denominator = window.unwrap_or(total).max(1);
+        let mut out = match window {
+            Some(window) => format!(
+                "Context: ~{} of {} tokens ({:.1}% of the window)\n\n",
+                total,
+                window,
+                percent(total, denominator)
+            ),
+            None => format!("Context: ~{} tokens\n\n", total),
+        };
+        let saved = self.compression_savings();
+        if saved > 0 {
+            let _ = writeln!(
+                out,
+                "Compression: ~{} tokens saved ({:.1}%), ~{} sent\n",
+                saved,
+                percent(saved, total.max(1)),
+                total - saved
+            );
+        }
+
+        for (kind, tokens) in self.sections() {
+            let _ = writeln!(
+                out,
+                "  {:<14} {} {:>7} tok {:>5.1}%",
+                kind.to_string(),
+                bar(tokens, denominator),
+                tokens,
+                percent(tokens, denominator)
+            );
+        }
+
+        out.push('\n');
+        for (number, item) in self.items.iter().enumerate() {
+            let compressed = match item.compressed_tokens {
+                Some(tokens) if tokens < item.tokens => format!("  (→ ~{} tok compressed)", tokens),
+                _ => String::new(),
+            };
+            let _ = writeln!(
+                out,
+                "  {:>3}. {:<13} {:>7} tok {:>5.1}%  {}{}",
+                number + 1,
+                item.kind.to_string(),
+                item.tokens,
+                percent(item.tokens, denominator),
+                item.label,
+                compressed
+            );
+        }
+        out.push_str("\nPrune with /context drop <n>[,<n>...]\n");
+        out
+    }
+
+    /// Remove the items with the given 1-based numbers from the prompt and
+    /// return how many were removed. The base system prompt cannot be
+    /// removed. Dropping a file chunk resets the context tracker, since later
+    /// chunks are diffs against what the dropped one showed.
+    pub fn prune(&self, numbers: &[usize], system: &mut SystemPrompt, log: &mut ConversationLog) -> usize {
+        let mut layers = Vec::new();
+        let mut pinned = Vec::new();
+        let mut messages = Vec::new();
+        let mut reset_files = false;
+        for item in numbers.iter().filter_map(|n| n.checked_sub(1).and_then(|i| self.items.get(i))) {
+            match item.source {
+                ContextItemSource::Layer(PromptLayerKind::Base) => {}
+                ContextItemSource::Layer(kind) => layers.push(kind),
+                ContextItemSource::Pinned(index) => pinned.push(index),
+                ContextItemSource::Message(index) => {
+                    reset_files |= item.kind == ContextItemKind::Files;
+                    messages.push(index);
+                }
+            }
+        }
+        layers.dedup();
+        pinned.sort_unstable();
+        pinned.dedup();
+        messages.sort_unstable();
+        messages.dedup();
+
+        for kind in &layers {
+            system.remove_layer(*kind);
+        }
+        for index in pinned.iter().rev() {
+            log.pinned.remove(*index);
+        }
+        for index in messages.iter().rev() {
+            log.messages.remove(*index);
+        }
+        if !messages.is_empty() {
+            log.recompute_derived();
+        }
+        if reset_files {
+            log.context.reset();
+        }
+        layers.len() + pinned.len() + messages.len()
+    }
+}
+
+fn preview(content: &str) -> String {
+    let line = content.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > 48 {
+        format!("{}…", line.chars().take(48).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn percent(tokens: usize, of: usize) -> f64 {
+    tokens as f64 * 100.0 / of as f64
+}
+
+fn bar(tokens: usize, of: usize) -> String {
+    let filled = ((tokens * BAR_WIDTH).div_ceil(of)).min(BAR_WIDTH);
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::{ConversationMessage, PinnedItem};
+    use crate::session::SessionId;
+    use std::path::PathBuf;
+
+    fn setup() -> (SystemPrompt, ConversationLog) {
+        let system = SystemPrompt::new().with_layer(PromptLayerKind::Pane, "pane override", "Answer tersely.");
+        let mut log = ConversationLog::new(SessionId::new());
+        log.pinned.push(PinnedItem::new("notes.md", "Remember the release checklist."));
+        log.push_context([(PathBuf::from("src/lib.rs"), "pub fn lib() {}\n".repeat(40))]);
+        log.push(ConversationMessage::new("user", "Why is lib slow?"));
+        log.push(ConversationMessage::annotation("model switched"));
+        log.push(ConversationMessage::new("assistant", "It recomputes everything."));
+        (system, log)
+    }
+
+    #[test]
+    fn breakdown_groups_sections_and_renders_percentages() {
+        let (system, log) = setup();
+        let breakdown = ContextBreakdown::build(&system, &log);
+
+        let kinds: Vec<ContextItemKind> = breakdown.sections().iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(
+            kinds,
+            vec![ContextItemKind::System, ContextItemKind::Pinned, ContextItemKind::Files, ContextItemKind::History]
+        );
+        assert_eq!(breakdown.items.len(), 6);
+        assert_eq!(breakdown.total_tokens(), breakdown.sections().iter().map(|(_, t)| t).sum::<usize>());
+
+        let rendered = breakdown.render(Some(8_000));
+        assert!(rendered.starts_with(&format!("Context: ~{} of 8000 tokens", breakdown.total_tokens())));
+        assert!(rendered.contains("file chunks"));
+        assert!(rendered.contains("user: Why is lib slow?"));
+        assert!(!rendered.contains("Compression:"));
+
+        let mut breakdown = breakdown;
+        let pinned = breakdown.items.iter().find(|item| item.kind == ContextItemKind::Pinned).unwrap().clone();
+        breakdown.set_compressed(pinned.source, pinned.tokens - 3);
+        assert_eq!(breakdown.compression_savings(), 3);
+        let rendered = breakdown.render(None);
+        assert!(rendered.contains("Compression: ~3 tokens saved"));
+        assert!(rendered.contains(&format!("notes.md  (→ ~{} tok compressed)", pinned.tokens - 3)));
+    }
+
+    #[test]
+    fn prune_removes_items_but_keeps_base_prompt() {
+        let (mut system, mut log) = setup();
+        let breakdown = ContextBreakdown::build(&system, &log);
+        let number = |kind| breakdown.items.iter().position(|item| item.kind == kind).unwrap() + 1;
+
+        let removed = breakdown.prune(
+            &[1, 2, number(ContextItemKind::Pinned), number(ContextItemKind::Files)],
+            &mut system,
+            &mut log,
+        );
+        assert_eq!(removed, 3);
+        assert_eq!(system.layers().len(), 1);
+        assert!(log.pinned.is_empty());
+        assert!(!log.messages.iter().any(|m| m.is_context_update()));
+        assert!(!log.context.is_seen(std::path::Path::new("src/lib.rs")));
+    }
+}
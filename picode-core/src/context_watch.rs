@@ -0,0 +1,543 @@
+//! Staleness of pinned file context
+//!
+//! Files pinned with `/context pin` are snapshots taken when they were
+//! pinned. [`ContextWatcher`] checks them on disk and, once one no longer
+//! matches its snapshot, publishes [`Event::ContextStale`] on the event bus,
+//! so any registered [`EventHandler`](crate::EventHandler) can react:
+//! interactive mode marks its status line, webhooks forward it. A refresh
+//! re-reads the stale files into their pinned items and returns what changed
+//! as a [`ContextUpdate`], sent as a delta message with the next prompt.
+//!
+//! Checks are woken by the OS's file change notifications where they work.
+//! They are not trusted on network file systems, which do not report
+//! changes made by other machines, and cannot be had in some containers or
+//! once the OS's watch limit is reached; the watcher then polls instead, at
+//! an interval that grows with the amount of pinned content and while
+//! nothing changes, and shrinks back when a change is seen. The current
+//! [`WatchStatus`] tells which applies.
+
+use crate::content_cache::content_hash;
+use crate::context_delta::{ContextUpdate, FileDelta};
+use crate::conversation::{ConversationLog, PinnedItem};
+use crate::event::{Event, EventBus};
+use crate::io::FileSystem;
+use crate::session::SessionId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Pinned content that makes one poll as expensive as the minimum interval
+/// is worth; the interval is multiplied for every such amount
+const BYTES_PER_STEP: u64 = 1024 * 1024;
+
+/// Time given to a burst of notifications, as editors write files in
+/// several steps, before checking
+const NOTIFY_SETTLE: Duration = Duration::from_millis(100);
+
+/// File system types whose notifications miss changes made elsewhere
+#[cfg(feature = "native")]
+const NETWORK_FILE_SYSTEMS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "afs", "ceph", "glusterfs", "9p", "fuse.sshfs", "fuse.rclone",
+    "fuse.grpcfuse", "fakeowner", "osxfs",
+];
+
+/// How changes to pinned files are detected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// OS notifications, except on network file systems; polling where
+    /// they are unavailable
+    #[default]
+    Auto,
+    /// OS notifications even on network file systems; polling only where
+    /// they cannot be set up
+    Native,
+    /// Always poll
+    Poll,
+}
+
+/// Watching configuration (`[workspace.watch]`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchSettings {
+    pub mode: WatchMode,
+    /// Shortest time between two polls, in milliseconds, used right after a
+    /// change
+    pub min_poll_ms: u64,
+    /// Longest time between two polls, in milliseconds, reached while
+    /// nothing changes; with notifications, files are still re-checked this
+    /// often in case one was missed
+    pub max_poll_ms: u64,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        Self {
+            mode: WatchMode::Auto,
+            min_poll_ms: 1_000,
+            max_poll_ms: 30_000,
+        }
+    }
+}
+
+impl WatchSettings {
+    /// Time until the next poll of `watched_bytes` of pinned content, after
+    /// `quiet_checks` checks in a row found nothing new: doubled for every
+    /// quiet check and every [`BYTES_PER_STEP`], between the two bounds
+    pub fn poll_interval(&self, watched_bytes: u64, quiet_checks: u32) -> Duration {
+        let min = self.min_poll_ms.max(1);
+        let max = self.max_poll_ms.max(min);
+        let size = 1 + watched_bytes / BYTES_PER_STEP;
+        let idle = 1u64 << quiet_checks.min(16);
+        Duration::from_millis(min.saturating_mul(size).saturating_mul(idle).min(max))
+    }
+
+    fn max_interval(&self) -> Duration {
+        Duration::from_millis(self.max_poll_ms.max(self.min_poll_ms).max(1))
+    }
+}
+
+/// How the watcher currently learns of changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchStatus {
+    /// Woken by OS notifications
+    Native,
+    /// Polling every `interval`; `reason` tells why notifications are not
+    /// used
+    Polling { interval: Duration, reason: String },
+}
+
+impl fmt::Display for WatchStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchStatus::Native => write!(f, "file change notifications"),
+            WatchStatus::Polling { interval, reason } => {
+                write!(f, "polling every {:.1}s ({})", interval.as_secs_f32(), reason)
+            }
+        }
+    }
+}
+
+/// The type of the file system holding `path`, from a `/proc/self/mounts`
+/// listing: that of the longest mount point containing it
+pub fn mount_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            // Spaces in mount points are escaped as \040
+            let mount_point = PathBuf::from(fields.next()?.replace("\\040", " "));
+            let fs_type = fields.next()?;
+            path.starts_with(&mount_point).then_some((mount_point.components().count(), fs_type))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, fs_type)| fs_type)
+}
+
+/// The network file system type holding `path`, where it can be told
+#[cfg(feature = "native")]
+fn network_file_system(path: &Path) -> Option<String> {
+    let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+    let path = crate::paths::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    mount_type(&mounts, &path)
+        .filter(|fs_type| NETWORK_FILE_SYSTEMS.contains(fs_type))
+        .map(str::to_string)
+}
+
+#[derive(Debug)]
+struct State {
+    /// Content hash of each pinned file's snapshot, by label
+    watched: BTreeMap<String, String>,
+    /// Labels of pinned files that changed on disk
+    stale: BTreeSet<String>,
+    /// Size of the watched snapshots
+    bytes: u64,
+    /// Checks in a row that found nothing new
+    quiet: u32,
+    status: WatchStatus,
+    /// Directories registered for notifications
+    dirs: BTreeSet<PathBuf>,
+}
+
+/// Wakes the background task; shared with the notification thread, which
+/// must not hold the watcher itself
+#[derive(Default)]
+struct Signal {
+    wake: tokio::sync::Notify,
+    /// Why notifications stopped working
+    failure: Mutex<Option<String>>,
+}
+
+/// Watches pinned files and reports when their snapshots go stale
+pub struct ContextWatcher {
+    root: PathBuf,
+    fs: Arc<dyn FileSystem>,
+    events: EventBus,
+    session_id: SessionId,
+    settings: WatchSettings,
+    state: Mutex<State>,
+    signal: Arc<Signal>,
+    #[cfg(feature = "native")]
+    native: Mutex<Option<notify::RecommendedWatcher>>,
+}
+
+impl ContextWatcher {
+    /// Watcher for files pinned relative to `root`; staleness is published
+    /// on `events` for `session_id`. It polls until [`spawn_poller`] sets up
+    /// notifications.
+    ///
+    /// [`spawn_poller`]: ContextWatcher::spawn_poller
+    pub fn new(
+        root: impl Into<PathBuf>,
+        fs: Arc<dyn FileSystem>,
+        events: EventBus,
+        session_id: SessionId,
+        settings: WatchSettings,
+    ) -> Arc<Self> {
+        let status = WatchStatus::Polling {
+            interval: settings.poll_interval(0, 0),
+            reason: "not started".to_string(),
+        };
+        Arc::new(Self {
+            root: root.into(),
+            fs,
+            events,
+            session_id,
+            settings,
+            state: Mutex::new(State {
+                watched: BTreeMap::new(),
+                stale: BTreeSet::new(),
+                bytes: 0,
+                quiet: 0,
+                status,
+                dirs: BTreeSet::new(),
+            }),
+            signal: Arc::new(Signal::default()),
+            #[cfg(feature = "native")]
+            native: Mutex::new(None),
+        })
+    }
+
+    /// Check on a background task, woken by OS notifications where they
+    /// can be used and polling otherwise; the task ends once every other
+    /// handle is dropped. Needs a Tokio runtime.
+    pub fn spawn_poller(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        if let Err(reason) = self.start_notifications() {
+            self.fall_back(reason);
+        }
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let wait = watcher.next_wait();
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = watcher.signal.wake.notified() => tokio::time::sleep(NOTIFY_SETTLE).await,
+                }
+                if Arc::strong_count(&watcher) == 1 {
+                    break;
+                }
+                let failure = watcher.signal.failure.lock().unwrap_or_else(|e| e.into_inner()).take();
+                if let Some(reason) = failure {
+                    watcher.fall_back(reason);
+                }
+                watcher.check().await;
+            }
+        })
+    }
+
+    /// How changes are currently detected
+    pub fn status(&self) -> WatchStatus {
+        self.lock().status.clone()
+    }
+
+    /// Time until the next check: the adaptive interval while polling, a
+    /// safety net re-check with notifications
+    fn next_wait(&self) -> Duration {
+        let mut state = self.lock();
+        let next = self.settings.poll_interval(state.bytes, state.quiet);
+        match &mut state.status {
+            WatchStatus::Native => self.settings.max_interval(),
+            WatchStatus::Polling { interval, .. } => {
+                *interval = next;
+                next
+            }
+        }
+    }
+
+    /// Stop using notifications and poll from now on
+    fn fall_back(&self, reason: String) {
+        #[cfg(feature = "native")]
+        self.native.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let mut state = self.lock();
+        if state.status == WatchStatus::Native {
+            tracing::info!("File change notifications failed ({}); polling pinned files", reason);
+        }
+        state.dirs.clear();
+        state.status = WatchStatus::Polling {
+            interval: self.settings.poll_interval(state.bytes, state.quiet),
+            reason,
+        };
+    }
+
+    /// Set up OS notifications, or say why they are not used
+    #[cfg(feature = "native")]
+    fn start_notifications(&self) -> Result<(), String> {
+        use notify::Watcher;
+
+        match self.settings.mode {
+            WatchMode::Poll => return Err("polling configured".to_string()),
+            WatchMode::Auto => {
+                if let Some(fs_type) = network_file_system(&self.root) {
+                    return Err(format!("{} file system", fs_type));
+                }
+            }
+            WatchMode::Native => {}
+        }
+        let signal = self.signal.clone();
+        let mut native = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            if let Err(e) = result {
+                *signal.failure.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.to_string());
+            }
+            signal.wake.notify_one();
+        })
+        .map_err(|e| e.to_string())?;
+        // The root at least must be watchable, which fails early where the
+        // OS has no notifications for this file system
+        native
+            .watch(&self.root, notify::RecursiveMode::NonRecursive)
+            .map_err(|e| e.to_string())?;
+        *self.native.lock().unwrap_or_else(|e| e.into_inner()) = Some(native);
+        {
+            let mut state = self.lock();
+            state.dirs.insert(self.root.clone());
+            state.status = WatchStatus::Native;
+        }
+        let labels: Vec<String> = self.lock().watched.keys().cloned().collect();
+        self.sync_notifications(&labels);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "native"))]
+    fn start_notifications(&self) -> Result<(), String> {
+        Err("no file change notifications in this build".to_string())
+    }
+
+    /// Register the directories of the watched `labels` for notifications,
+    /// and drop the others; falls back to polling when one cannot be
+    #[cfg(feature = "native")]
+    fn sync_notifications(&self, labels: &[String]) {
+        use notify::Watcher;
+
+        let mut wanted: BTreeSet<PathBuf> = labels
+            .iter()
+            .filter_map(|label| self.root.join(label).parent().map(Path::to_path_buf))
+            .collect();
+        wanted.insert(self.root.clone());
+
+        let mut failure = None;
+        {
+            let mut native = self.native.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(native) = native.as_mut() else {
+                return;
+            };
+            let mut state = self.lock();
+            for dir in state.dirs.difference(&wanted) {
+                let _ = native.unwatch(dir);
+            }
+            state.dirs.retain(|dir| wanted.contains(dir));
+            let missing: Vec<PathBuf> = wanted.difference(&state.dirs).cloned().collect();
+            for dir in missing {
+                match native.watch(&dir, notify::RecursiveMode::NonRecursive) {
+                    Ok(()) => {
+                        state.dirs.insert(dir);
+                    }
+                    // Pinned files may be gone already; the next check says so
+                    Err(_) if !dir.exists() => {}
+                    Err(e) => {
+                        failure = Some(format!("{}: {}", dir.display(), e));
+                        break;
+                    }
+                }
+            }
+        }
+        if let Some(reason) = failure {
+            self.fall_back(reason);
+        }
+    }
+
+    #[cfg(not(feature = "native"))]
+    fn sync_notifications(&self, _labels: &[String]) {}
+
+    /// Follow the pinned items that are workspace files; other items, such
+    /// as issues, are ignored. Call whenever the pinned items may have changed.
+    pub async fn watch(&self, pinned: &[PinnedItem]) {
+        let previous = self.lock().watched.clone();
+        let mut watched = BTreeMap::new();
+        for item in pinned {
+            let hash = content_hash(item.content.as_bytes());
+            if previous.get(&item.label) == Some(&hash) || self.fs.exists(&self.root.join(&item.label)).await {
+                watched.insert(item.label.clone(), hash);
+            }
+        }
+        let labels: Vec<String> = watched.keys().cloned().collect();
+        {
+            let mut state = self.lock();
+            // A snapshot that was replaced is no longer known to be stale
+            state.stale.retain(|label| watched.contains_key(label) && watched.get(label) == previous.get(label));
+            if watched != previous {
+                state.quiet = 0;
+            }
+            state.bytes = pinned
+                .iter()
+                .filter(|item| watched.contains_key(&item.label))
+                .map(|item| item.content.len() as u64)
+                .sum();
+            state.watched = watched;
+        }
+        self.sync_notifications(&labels);
+    }
+
+    /// Compare the pinned files with their snapshots and return the labels
+    /// of the stale ones; [`Event::ContextStale`] is published when a file
+    /// went stale since the last check
+    pub async fn check(&self) -> Vec<String> {
+        let watched = self.lock().watched.clone();
+        let mut stale = BTreeSet::new();
+        for (label, hash) in &watched {
+            let current = self.fs.read(&self.root.join(label)).await.map(|bytes| content_hash(&bytes));
+            if current.ok().as_ref() != Some(hash) {
+                stale.insert(label.clone());
+            }
+        }
+
+        let newly_stale = {
+            let mut state = self.lock();
+            // Items may have been pinned again while the files were read
+            stale.retain(|label| state.watched.get(label) == watched.get(label));
+            let newly_stale = stale.difference(&state.stale).next().is_some();
+            // Activity keeps polling frequent; quiet stretches slow it down
+            state.quiet = if stale == state.stale { state.quiet.saturating_add(1) } else { 0 };
+            state.stale = stale.clone();
+            newly_stale
+        };
+        if newly_stale {
+            let event = Event::ContextStale {
+                session_id: self.session_id.clone(),
+                files: stale.iter().map(PathBuf::from).collect(),
+            };
+            if let Err(e) = self.events.publish(event, "context-watcher".to_string()).await {
+                tracing::warn!("Failed to publish context staleness: {}", e);
+            }
+        }
+        stale.into_iter().collect()
+    }
+
+    /// Labels of the pinned files found stale by the last check
+    pub fn stale(&self) -> Vec<String> {
+        self.lock().stale.iter().cloned().collect()
+    }
+
+    /// Re-read the stale files into their pinned items, unpinning files that
+    /// were deleted, and return the changes to send with the next prompt
+    pub async fn refresh(&self, log: &mut ConversationLog) -> ContextUpdate {
+        let stale = self.lock().stale.clone();
+        let mut update = ContextUpdate {
+            turn: log
+                .messages
+                .iter()
+                .filter(|m| m.role == "user" && !m.is_context_update())
+                .count() as u64
+                + 1,
+            ..ContextUpdate::default()
+        };
+        let mut deleted = BTreeSet::new();
+        for item in log.pinned.iter_mut() {
+            let path = PathBuf::from(&item.label);
+            if !stale.contains(&item.label) {
+                if self.lock().watched.contains_key(&item.label) {
+                    update.unchanged.push(path);
+                }
+                continue;
+            }
+            match self.fs.read_to_string(&self.root.join(&item.label)).await {
+                Ok(content) => {
+                    update.deltas.push(FileDelta::between(path, &item.content, &content));
+                    item.content = content;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    update.deltas.push(FileDelta::Removed { path });
+                    deleted.insert(item.label.clone());
+                }
+                Err(e) => tracing::warn!("Could not refresh {}: {}", item.label, e),
+            }
+        }
+        log.pinned.retain(|item| !deleted.contains(&item.label));
+        self.watch(&log.pinned).await;
+        update
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    #[tokio::test]
+    async fn stale_pins_are_published_and_refreshed_as_deltas() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        let lib: String = (0..40).map(|i| format!("pub fn f{}() {{}}\n", i)).collect();
+        fs.insert("/ws/src/lib.rs", lib.clone());
+        fs.insert("/ws/old.rs", "fn old() {}\n");
+        let events = EventBus::new(16, 16);
+        let mut received = events.subscribe();
+        let watcher = ContextWatcher::new("/ws", fs.clone(), events, SessionId::new(), WatchSettings::default());
+
+        let mut log = ConversationLog::new(SessionId::new());
+        log.pinned.push(PinnedItem::new("src/lib.rs", lib.clone()));
+        log.pinned.push(PinnedItem::new("old.rs", "fn old() {}\n"));
+        log.pinned.push(PinnedItem::new("issue #9", "Retry uploads"));
+        watcher.watch(&log.pinned).await;
+        assert!(watcher.check().await.is_empty());
+
+        fs.insert("/ws/src/lib.rs", lib.replace("f20", "renamed"));
+        fs.remove_file(std::path::Path::new("/ws/old.rs")).await.unwrap();
+        assert_eq!(watcher.check().await, vec!["old.rs".to_string(), "src/lib.rs".to_string()]);
+        let envelope = received.recv().await.unwrap();
+        assert!(matches!(envelope.event, Event::ContextStale { ref files, .. } if files.len() == 2));
+        // Still stale, but nothing new to report
+        watcher.check().await;
+        assert!(received.try_recv().is_err());
+
+        let update = watcher.refresh(&mut log).await;
+        let FileDelta::Changed { diff, .. } = &update.deltas[0] else {
+            panic!("expected a diff, got {:?}", update.deltas[0]);
+        };
+        assert!(diff.contains("+pub fn renamed() {}"));
+        assert_eq!(update.deltas[1], FileDelta::Removed { path: PathBuf::from("old.rs") });
+        assert_eq!(log.pinned.len(), 2);
+        assert!(log.pinned[0].content.contains("renamed"));
+        assert!(watcher.stale().is_empty());
+        assert!(watcher.check().await.is_empty());
+    }
+
+    #[test]
+    fn polling_adapts_to_size_and_activity() {
+        let settings = WatchSettings { mode: WatchMode::Poll, min_poll_ms: 500, max_poll_ms: 8_000 };
+        assert_eq!(settings.poll_interval(10_000, 0), Duration::from_millis(500));
+        assert_eq!(settings.poll_interval(3 * BYTES_PER_STEP, 0), Duration::from_millis(2_000));
+        assert_eq!(settings.poll_interval(10_000, 2), Duration::from_millis(2_000));
+        assert_eq!(settings.poll_interval(10_000, 40), Duration::from_millis(8_000));
+
+        let mounts = "/dev/sda1 / ext4 rw 0 0\nserver:/export /mnt/my\\040code nfs4 rw 0 0\n";
+        assert_eq!(mount_type(mounts, Path::new("/mnt/my code/repo")), Some("nfs4"));
+        assert_eq!(mount_type(mounts, Path::new("/home/me/repo")), Some("ext4"));
+    }
+}
@@ -0,0 +1,146 @@
+//! Size-bounded conversation storage
+//!
+//! A conversation file is rewritten on every save, so a long session made
+//! each save slower and its file grew without bound. Once the live messages
+//! exceed [`ArchiveSettings::max_live_bytes`], the oldest ones are moved to an
+//! archived segment: JSON lines compressed with zstd, stored next to the live
+//! file and listed in [`ConversationLog::archive`](crate::ConversationLog).
+//! Loading reads the segments back in front of the live messages, so history
+//! search, export and resumed sessions see the whole conversation.
+
+use crate::conversation::ConversationMessage;
+use crate::session::SessionId;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Extension of archived segment files
+pub const SEGMENT_EXTENSION: &str = "jsonl.zst";
+
+/// An archived run of the oldest messages of a conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedSegment {
+    /// File name inside the conversations directory
+    pub file: String,
+    pub messages: usize,
+    /// Size of the JSON lines before compression
+    pub raw_bytes: u64,
+    pub compressed_bytes: u64,
+}
+
+/// When and how conversations are rotated into archived segments
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveSettings {
+    /// Size of the live messages that triggers rotation; 0 disables it
+    pub max_live_bytes: u64,
+    /// zstd compression level of archived segments
+    pub level: i32,
+}
+
+impl Default for ArchiveSettings {
+    fn default() -> Self {
+        Self {
+            max_live_bytes: 512 * 1024,
+            level: 9,
+        }
+    }
+}
+
+/// Space used by stored conversations before and after compaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactReport {
+    pub conversations: usize,
+    /// Segments written by the compaction
+    pub segments: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl CompactReport {
+    pub fn reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+/// File name of a conversation's `index`th segment
+pub fn segment_file(session_id: &SessionId, index: usize) -> String {
+    format!("{}.{:04}.{}", session_id, index, SEGMENT_EXTENSION)
+}
+
+/// Serialized size of a message
+pub fn message_bytes(message: &ConversationMessage) -> u64 {
+    serde_json::to_vec(message).map(|bytes| bytes.len() as u64).unwrap_or(0)
+}
+
+/// How many of the oldest `messages` to archive so the newest ones left
+/// take at most `keep_bytes`
+pub fn rotation_point(messages: &[ConversationMessage], keep_bytes: u64) -> usize {
+    let mut kept_bytes = 0;
+    let mut kept = 0;
+    for message in messages.iter().rev() {
+        kept_bytes += message_bytes(message);
+        if kept_bytes > keep_bytes {
+            break;
+        }
+        kept += 1;
+    }
+    messages.len() - kept
+}
+
+/// Compress messages into a segment; returns it with its uncompressed size
+#[cfg(feature = "native")]
+pub fn encode_segment(messages: &[ConversationMessage], level: i32) -> io::Result<(Vec<u8>, u64)> {
+    let mut lines = Vec::new();
+    for message in messages {
+        serde_json::to_writer(&mut lines, message)?;
+        lines.push(b'\n');
+    }
+    let compressed = zstd::encode_all(lines.as_slice(), level)?;
+    Ok((compressed, lines.len() as u64))
+}
+
+#[cfg(not(feature = "native"))]
+pub fn encode_segment(_messages: &[ConversationMessage], _level: i32) -> io::Result<(Vec<u8>, u64)> {
+    Err(unsupported())
+}
+
+/// Messages of a segment written by [`encode_segment`]
+#[cfg(feature = "native")]
+pub fn decode_segment(bytes: &[u8]) -> io::Result<Vec<ConversationMessage>> {
+    let lines = zstd::decode_all(bytes)?;
+    lines
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).map_err(io::Error::from))
+        .collect()
+}
+
+#[cfg(not(feature = "native"))]
+pub fn decode_segment(_bytes: &[u8]) -> io::Result<Vec<ConversationMessage>> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "native"))]
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "archived conversation segments need the native feature")
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_round_trip_and_rotation_keeps_the_newest() {
+        let messages: Vec<ConversationMessage> = (0..10)
+            .map(|i| ConversationMessage::new("user", format!("message {} {}", i, "padding ".repeat(50))))
+            .collect();
+        let (compressed, raw) = encode_segment(&messages, 3).unwrap();
+        assert!((compressed.len() as u64) < raw);
+        assert_eq!(decode_segment(&compressed).unwrap(), messages);
+
+        let size = message_bytes(&messages[0]);
+        assert_eq!(rotation_point(&messages, size * 3 + size / 2), 7);
+        assert_eq!(rotation_point(&messages, 0), 10);
+        assert_eq!(rotation_point(&messages, size * 100), 0);
+    }
+}
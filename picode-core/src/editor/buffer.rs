@@ -0,0 +1,309 @@
+//! Line-based text buffer with a cursor
+
+/// Cursor position; `column` counts characters, not bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cursor {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Text being edited, stored as lines without their terminators
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextBuffer {
+    lines: Vec<String>,
+    cursor: Cursor,
+    trailing_newline: bool,
+    dirty: bool,
+}
+
+impl TextBuffer {
+    pub fn new() -> Self {
+        Self::from_text("")
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Self {
+            lines,
+            cursor: Cursor::default(),
+            trailing_newline: text.is_empty() || text.ends_with('\n'),
+            dirty: false,
+        }
+    }
+
+    /// Full text, keeping the original trailing newline convention
+    pub fn text(&self) -> String {
+        let mut text = self.lines.join("\n");
+        if self.trailing_newline {
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Mark the buffer as matching what is on disk
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Replace the whole text, e.g. with a merge result, keeping the cursor
+    /// where possible; the buffer counts as modified
+    pub fn replace_text(&mut self, text: &str) {
+        let cursor = self.cursor;
+        *self = Self::from_text(text);
+        self.set_cursor(cursor.line, cursor.column, false);
+        self.dirty = true;
+    }
+
+    fn line_len(&self, line: usize) -> usize {
+        self.lines[line].chars().count()
+    }
+
+    fn byte_index(&self, line: usize, column: usize) -> usize {
+        self.lines[line]
+            .char_indices()
+            .nth(column)
+            .map_or(self.lines[line].len(), |(i, _)| i)
+    }
+
+    /// Move the cursor, clamping it to the text; `past_end` allows the
+    /// position after the last character (insert mode)
+    pub fn set_cursor(&mut self, line: usize, column: usize, past_end: bool) {
+        let line = line.min(self.lines.len() - 1);
+        let len = self.line_len(line);
+        let max = if past_end || len == 0 { len } else { len - 1 };
+        self.cursor = Cursor {
+            line,
+            column: column.min(max),
+        };
+    }
+
+    pub fn move_left(&mut self) {
+        let Cursor { line, column } = self.cursor;
+        self.set_cursor(line, column.saturating_sub(1), true);
+    }
+
+    pub fn move_right(&mut self, past_end: bool) {
+        let Cursor { line, column } = self.cursor;
+        self.set_cursor(line, column + 1, past_end);
+    }
+
+    pub fn move_up(&mut self, past_end: bool) {
+        let Cursor { line, column } = self.cursor;
+        self.set_cursor(line.saturating_sub(1), column, past_end);
+    }
+
+    pub fn move_down(&mut self, past_end: bool) {
+        let Cursor { line, column } = self.cursor;
+        self.set_cursor(line + 1, column, past_end);
+    }
+
+    pub fn move_line_start(&mut self) {
+        self.cursor.column = 0;
+    }
+
+    pub fn move_line_end(&mut self, past_end: bool) {
+        self.set_cursor(self.cursor.line, usize::MAX, past_end);
+    }
+
+    /// Jump to the start of the next word on the current line, or the next line
+    pub fn move_word_forward(&mut self) {
+        let Cursor { line, column } = self.cursor;
+        let chars: Vec<char> = self.lines[line].chars().collect();
+        let mut i = column;
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        if i < chars.len() {
+            let start_is_word = is_word(chars[i]);
+            while i < chars.len() && !chars[i].is_whitespace() && is_word(chars[i]) == start_is_word {
+                i += 1;
+            }
+        }
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if i >= chars.len() && line + 1 < self.lines.len() {
+            self.set_cursor(line + 1, 0, false);
+        } else {
+            self.set_cursor(line, i, false);
+        }
+    }
+
+    /// Jump to the start of the previous word on the current line
+    pub fn move_word_back(&mut self) {
+        let Cursor { line, column } = self.cursor;
+        let chars: Vec<char> = self.lines[line].chars().collect();
+        let mut i = column.min(chars.len());
+        while i > 0 && chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        while i > 0 && !chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        self.set_cursor(line, i, false);
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let Cursor { line, column } = self.cursor;
+        let at = self.byte_index(line, column);
+        self.lines[line].insert(at, c);
+        self.cursor.column += 1;
+        self.dirty = true;
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.insert_newline();
+            } else {
+                self.insert_char(c);
+            }
+        }
+    }
+
+    /// Split the line at the cursor
+    pub fn insert_newline(&mut self) {
+        let Cursor { line, column } = self.cursor;
+        let at = self.byte_index(line, column);
+        let rest = self.lines[line].split_off(at);
+        self.lines.insert(line + 1, rest);
+        self.cursor = Cursor { line: line + 1, column: 0 };
+        self.dirty = true;
+    }
+
+    /// Delete the character before the cursor, joining lines at column 0
+    pub fn backspace(&mut self) {
+        let Cursor { line, column } = self.cursor;
+        if column > 0 {
+            let at = self.byte_index(line, column - 1);
+            self.lines[line].remove(at);
+            self.cursor.column -= 1;
+        } else if line > 0 {
+            let current = self.lines.remove(line);
+            let previous_len = self.line_len(line - 1);
+            self.lines[line - 1].push_str(&current);
+            self.cursor = Cursor { line: line - 1, column: previous_len };
+        } else {
+            return;
+        }
+        self.dirty = true;
+    }
+
+    /// Delete the character under the cursor, joining lines at the end
+    pub fn delete_char(&mut self) {
+        let Cursor { line, column } = self.cursor;
+        if column < self.line_len(line) {
+            let at = self.byte_index(line, column);
+            self.lines[line].remove(at);
+        } else if line + 1 < self.lines.len() {
+            let next = self.lines.remove(line + 1);
+            self.lines[line].push_str(&next);
+        } else {
+            return;
+        }
+        self.dirty = true;
+    }
+
+    /// Remove the cursor line, returning its text
+    pub fn delete_line(&mut self) -> String {
+        let line = self.cursor.line;
+        self.dirty = true;
+        if self.lines.len() == 1 {
+            self.cursor.column = 0;
+            return std::mem::take(&mut self.lines[0]);
+        }
+        let removed = self.lines.remove(line);
+        self.set_cursor(line, 0, false);
+        removed
+    }
+
+    /// Insert an empty line below (or above) the cursor and move onto it
+    pub fn open_line(&mut self, below: bool) {
+        let line = if below { self.cursor.line + 1 } else { self.cursor.line };
+        self.lines.insert(line, String::new());
+        self.cursor = Cursor { line, column: 0 };
+        self.dirty = true;
+    }
+
+    /// First line to draw so the cursor stays visible in `height` rows
+    pub fn scroll_offset(&self, current: usize, height: usize) -> usize {
+        let line = self.cursor.line;
+        if height == 0 || line < current {
+            line
+        } else if line >= current + height {
+            line + 1 - height
+        } else {
+            current
+        }
+    }
+}
+
+impl Default for TextBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_roundtrip_text() {
+        let mut buffer = TextBuffer::from_text("fn main() {\n}\n");
+        assert!(!buffer.is_dirty());
+
+        buffer.move_line_end(true);
+        buffer.insert_newline();
+        buffer.insert_str("    run();");
+        assert_eq!(buffer.text(), "fn main() {\n    run();\n}\n");
+        assert!(buffer.is_dirty());
+
+        buffer.move_line_start();
+        buffer.backspace();
+        assert_eq!(buffer.text(), "fn main() {    run();\n}\n");
+        assert_eq!(buffer.cursor(), Cursor { line: 0, column: 11 });
+    }
+
+    #[test]
+    fn cursor_handles_multibyte_text() {
+        let mut buffer = TextBuffer::from_text("héllo");
+        buffer.set_cursor(0, 2, false);
+        buffer.delete_char();
+        assert_eq!(buffer.text(), "hélo");
+        buffer.move_line_end(false);
+        assert_eq!(buffer.cursor().column, 3);
+    }
+
+    #[test]
+    fn word_motions_and_line_deletion() {
+        let mut buffer = TextBuffer::from_text("let x = value;\nnext");
+        buffer.move_word_forward();
+        assert_eq!(buffer.cursor().column, 4);
+        buffer.move_word_forward();
+        buffer.move_word_forward();
+        assert_eq!(buffer.cursor().column, 8);
+        buffer.move_word_back();
+        assert_eq!(buffer.cursor().column, 6);
+
+        assert_eq!(buffer.delete_line(), "let x = value;");
+        assert_eq!(buffer.text(), "next");
+    }
+}
@@ -0,0 +1,201 @@
+//! Diagnostics extension point for the editor
+//!
+//! Editors display diagnostics from any [`DiagnosticsProvider`]. PiCode does
+//! not speak LSP itself yet; [`ExternalDiagnostics`] runs a plugin or
+//! external checker that receives the buffer on stdin and prints one
+//! diagnostic per line, either as JSON
+//! (`{"line":3,"column":5,"severity":"error","message":"..."}`) or in the
+//! common compiler format `path:line:column: severity: message`. Lines and
+//! columns in both formats are 1-based.
+
+use super::EditorError;
+use crate::io::ProcessRunner;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Placeholder in checker arguments replaced with the edited file's path
+pub const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Diagnostic severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "error" | "fatal" | "fatal error" => Some(Severity::Error),
+            "warning" | "warn" => Some(Severity::Warning),
+            "info" | "note" => Some(Severity::Info),
+            "hint" | "help" => Some(Severity::Hint),
+            _ => None,
+        }
+    }
+}
+
+/// A problem reported for a position in the buffer (0-based)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// Source of diagnostics for an edited file
+#[async_trait]
+pub trait DiagnosticsProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn diagnostics(&self, path: &Path, content: &str) -> Result<Vec<Diagnostic>, EditorError>;
+}
+
+/// Diagnostics produced by an external process
+#[derive(Debug, Clone)]
+pub struct ExternalDiagnostics {
+    name: String,
+    program: String,
+    args: Vec<String>,
+    runner: Arc<dyn ProcessRunner>,
+}
+
+impl ExternalDiagnostics {
+    pub fn new(name: impl Into<String>, program: impl Into<String>, runner: Arc<dyn ProcessRunner>) -> Self {
+        Self {
+            name: name.into(),
+            program: program.into(),
+            args: Vec::new(),
+            runner,
+        }
+    }
+
+    /// Arguments for the checker; `{file}` is replaced with the file path
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Parse checker output, skipping lines in neither supported format
+    pub fn parse_output(&self, output: &str) -> Vec<Diagnostic> {
+        output
+            .lines()
+            .filter_map(|line| parse_json_line(line).or_else(|| parse_compiler_line(line)))
+            .map(|mut diagnostic| {
+                diagnostic.source.get_or_insert_with(|| self.name.clone());
+                diagnostic
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DiagnosticsProvider for ExternalDiagnostics {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn diagnostics(&self, path: &Path, content: &str) -> Result<Vec<Diagnostic>, EditorError> {
+        let file = path.display().to_string();
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| arg.replace(FILE_PLACEHOLDER, &file))
+            .collect();
+
+        let output = self
+            .runner
+            .run(&self.program, &args, path.parent(), &[], Some(content.as_bytes()))
+            .await
+            .map_err(|e| EditorError::Diagnostics(self.name.clone(), e.to_string()))?;
+
+        // Checkers commonly exit non-zero when they find problems
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push('\n');
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        Ok(self.parse_output(&text))
+    }
+}
+
+/// 1-based JSON diagnostic
+#[derive(Deserialize)]
+struct JsonDiagnostic {
+    line: usize,
+    #[serde(default)]
+    column: Option<usize>,
+    severity: String,
+    message: String,
+    #[serde(default)]
+    source: Option<String>,
+}
+
+fn parse_json_line(line: &str) -> Option<Diagnostic> {
+    let json: JsonDiagnostic = serde_json::from_str(line.trim()).ok()?;
+    Some(Diagnostic {
+        line: json.line.saturating_sub(1),
+        column: json.column.unwrap_or(1).saturating_sub(1),
+        severity: Severity::parse(&json.severity)?,
+        message: json.message,
+        source: json.source,
+    })
+}
+
+/// `path:line:column: severity: message` (column optional)
+fn parse_compiler_line(line: &str) -> Option<Diagnostic> {
+    let mut parts = line.splitn(3, ':');
+    let _path = parts.next()?;
+    let line_no: usize = parts.next()?.trim().parse().ok()?;
+    let rest = parts.next()?;
+
+    let (column, rest) = match rest.split_once(':').map(|(column, after)| (column.trim().parse::<usize>(), after)) {
+        Some((Ok(column), after)) => (column, after),
+        _ => (1, rest),
+    };
+    let (severity, message) = rest.split_once(':')?;
+    Some(Diagnostic {
+        line: line_no.saturating_sub(1),
+        column: column.saturating_sub(1),
+        severity: Severity::parse(severity)?,
+        message: message.trim().to_string(),
+        source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::NoProcessRunner;
+
+    fn checker() -> ExternalDiagnostics {
+        ExternalDiagnostics::new("lint", "lint", Arc::new(NoProcessRunner))
+    }
+
+    #[test]
+    fn parses_json_and_compiler_lines() {
+        let output = "\
+{\"line\":3,\"column\":5,\"severity\":\"error\",\"message\":\"unknown name\"}
+src/main.rs:10:2: warning: unused variable: `x`
+checking...
+";
+        let diagnostics = checker().parse_output(output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 4));
+        assert_eq!(diagnostics[0].source.as_deref(), Some("lint"));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].message, "unused variable: `x`");
+    }
+
+    #[tokio::test]
+    async fn runner_errors_are_reported() {
+        let err = checker().diagnostics(Path::new("/ws/a.rs"), "").await.unwrap_err();
+        assert!(matches!(err, EditorError::Diagnostics(name, _) if name == "lint"));
+    }
+}
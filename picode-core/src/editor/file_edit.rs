@@ -0,0 +1,174 @@
+//! Writing edited files back to disk
+//!
+//! Every save goes through a [`FileEdit`], which remembers the hash of the
+//! content the edit was based on. If the file changed on disk in the
+//! meantime (another pane, the agent, an external editor) the save is
+//! refused instead of silently overwriting those changes. An edit that also
+//! carries the base content can instead be merged with those changes
+//! ([`FileEdit::apply_or_merge`]). Writes go through the shared
+//! [`ContentCache`] so other readers see the new content at once, and
+//! through an [`EditJournal`] when one is set, so a crash mid-write can be
+//! recovered from.
+
+use super::journal::EditJournal;
+use super::merge::{merge3, MergeResult};
+use super::EditorError;
+use crate::content_cache::{content_hash, CachedFile, ContentCache};
+use crate::io::FileSystem;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// What [`FileEdit::apply_or_merge`] did
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditOutcome {
+    /// The file was unchanged and the edit was written as is
+    Applied(CachedFile),
+    /// The file had changed; the merge of both was written
+    Merged(CachedFile),
+    /// Both changed the same lines; nothing was written. `merge` holds the
+    /// text with conflict markers, `on_disk` the version it was merged with
+    Conflicted { merge: MergeResult, on_disk: String },
+}
+
+/// New content for a file, based on a known version of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileEdit {
+    pub path: PathBuf,
+    pub content: String,
+    /// Hash of the content the edit started from; `None` for a new file
+    pub base_hash: Option<String>,
+    /// The content the edit started from, when it was captured
+    pub base: Option<Arc<str>>,
+    /// Write-ahead journal the write is recorded in
+    pub journal: Option<EditJournal>,
+}
+
+impl FileEdit {
+    pub fn new(path: impl Into<PathBuf>, content: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            content: content.into(),
+            base_hash: None,
+            base: None,
+            journal: None,
+        }
+    }
+
+    /// Refuse to save if the file no longer has this content hash
+    pub fn based_on(mut self, hash: impl Into<String>) -> Self {
+        self.base_hash = Some(hash.into());
+        self
+    }
+
+    /// Base the edit on captured content, so concurrent changes can be merged
+    pub fn based_on_content(mut self, content: impl Into<Arc<str>>) -> Self {
+        let content = content.into();
+        self.base_hash = Some(content_hash(content.as_bytes()));
+        self.base = Some(content);
+        self
+    }
+
+    /// Record the write in `journal` before making it
+    pub fn with_journal(mut self, journal: EditJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Write the edit, checking the file was not changed since it was read
+    pub async fn apply(&self, fs: &dyn FileSystem, cache: &ContentCache) -> Result<CachedFile, EditorError> {
+        if let Some(journal) = &self.journal {
+            let mut written = journal.apply(std::slice::from_ref(self), fs, cache).await?;
+            return Ok(written.remove(0));
+        }
+        let current = match fs.read(&self.path).await {
+            Ok(bytes) => Some(content_hash(&bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        if current != self.base_hash {
+            return Err(EditorError::Conflict(self.path.clone()));
+        }
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs.create_dir_all(parent).await?;
+        }
+        Ok(cache.write(fs, &self.path, &self.content).await?)
+    }
+
+    /// Write the edit, three-way merging it with changes made on disk since
+    /// the base was captured. Without a captured base, or when the file was
+    /// deleted meanwhile, this behaves like [`FileEdit::apply`].
+    pub async fn apply_or_merge(&self, fs: &dyn FileSystem, cache: &ContentCache) -> Result<EditOutcome, EditorError> {
+        let Some(base) = &self.base else {
+            return self.apply(fs, cache).await.map(EditOutcome::Applied);
+        };
+        let on_disk = match fs.read(&self.path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(EditorError::Conflict(self.path.clone())),
+            Err(e) => return Err(e.into()),
+        };
+        if Some(content_hash(&on_disk)) == self.base_hash {
+            return self.apply(fs, cache).await.map(EditOutcome::Applied);
+        }
+
+        let on_disk = String::from_utf8(on_disk).map_err(|_| EditorError::Conflict(self.path.clone()))?;
+        let merge = merge3(base, &on_disk, &self.content);
+        if !merge.is_clean() {
+            return Ok(EditOutcome::Conflicted { merge, on_disk });
+        }
+        let mut merged = FileEdit::new(&self.path, merge.content).based_on(content_hash(on_disk.as_bytes()));
+        merged.journal = self.journal.clone();
+        merged.apply(fs, cache).await.map(EditOutcome::Merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn saves_only_from_the_current_version() {
+        let fs = MemoryFileSystem::new();
+        let cache = ContentCache::new();
+        let path = Path::new("/ws/notes.md");
+
+        let created = FileEdit::new(path, "v1").apply(&fs, &cache).await.unwrap();
+        let saved = FileEdit::new(path, "v2").based_on(created.hash.clone()).apply(&fs, &cache).await.unwrap();
+        assert_eq!(fs.read_to_string(path).await.unwrap(), "v2");
+        assert_eq!(cache.cached_hash(path), Some(saved.hash));
+
+        let stale = FileEdit::new(path, "v3").based_on(created.hash);
+        assert!(matches!(stale.apply(&fs, &cache).await, Err(EditorError::Conflict(_))));
+        assert!(matches!(FileEdit::new(path, "v3").apply(&fs, &cache).await, Err(EditorError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn concurrent_changes_are_merged_or_reported() {
+        let fs = MemoryFileSystem::new();
+        let cache = ContentCache::new();
+        let path = Path::new("/ws/list.txt");
+        let base = "one\ntwo\nthree\n";
+        fs.insert(path, base);
+
+        // Someone edits line one while the edit changes line three
+        fs.insert(path, "ONE\ntwo\nthree\n");
+        let edit = FileEdit::new(path, "one\ntwo\nTHREE\n").based_on_content(base);
+        assert!(matches!(edit.apply_or_merge(&fs, &cache).await.unwrap(), EditOutcome::Merged(_)));
+        assert_eq!(fs.read_to_string(path).await.unwrap(), "ONE\ntwo\nTHREE\n");
+
+        // Both change line three: nothing is written
+        let edit = FileEdit::new(path, "ONE\ntwo\n3\n").based_on_content(base.replace("one", "ONE"));
+        fs.insert(path, "ONE\ntwo\nthree!\n");
+        match edit.apply_or_merge(&fs, &cache).await.unwrap() {
+            EditOutcome::Conflicted { merge, on_disk } => {
+                assert_eq!(merge.conflicts.len(), 1);
+                assert_eq!(on_disk, "ONE\ntwo\nthree!\n");
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(fs.read_to_string(path).await.unwrap(), "ONE\ntwo\nthree!\n");
+    }
+}
@@ -0,0 +1,216 @@
+//! Lightweight per-line syntax highlighting
+//!
+//! Recognizes keywords, string and character literals, numbers and line
+//! comments for common languages. Block comments and multi-line strings are
+//! not tracked across lines; this is meant for readable display, not parsing.
+
+use std::ops::Range;
+
+/// Token class of a highlighted span
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightKind {
+    Keyword,
+    String,
+    Number,
+    Comment,
+}
+
+/// A highlighted byte range of a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub kind: HighlightKind,
+    pub range: Range<usize>,
+}
+
+/// Keyword and comment rules for one language
+#[derive(Debug, Clone, Copy)]
+pub struct Highlighter {
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    /// Single quotes delimit short char literals only (Rust lifetimes)
+    char_literals: bool,
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else",
+    "except", "False", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "None",
+    "not", "or", "pass", "raise", "return", "True", "try", "while", "with", "yield",
+];
+
+const JS_KEYWORDS: &[&str] = &[
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete", "else",
+    "export", "extends", "false", "finally", "for", "from", "function", "if", "import", "in", "instanceof",
+    "interface", "let", "new", "null", "return", "switch", "this", "throw", "true", "try", "type",
+    "typeof", "undefined", "var", "while", "yield",
+];
+
+const GO_KEYWORDS: &[&str] = &[
+    "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "for", "func",
+    "go", "goto", "if", "import", "interface", "map", "nil", "package", "range", "return", "select",
+    "struct", "switch", "type", "var",
+];
+
+const SHELL_KEYWORDS: &[&str] = &[
+    "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if", "in", "local",
+    "return", "then", "while",
+];
+
+impl Highlighter {
+    /// Rules for a language name or file extension; unknown languages only
+    /// get strings and numbers highlighted
+    pub fn for_language(language: Option<&str>) -> Self {
+        let (keywords, line_comment): (&'static [&'static str], Option<&'static str>) = match language {
+            Some("rs" | "rust") => (RUST_KEYWORDS, Some("//")),
+            Some("py" | "python") => (PYTHON_KEYWORDS, Some("#")),
+            Some("js" | "jsx" | "ts" | "tsx" | "javascript" | "typescript") => (JS_KEYWORDS, Some("//")),
+            Some("go") => (GO_KEYWORDS, Some("//")),
+            Some("sh" | "bash" | "zsh" | "shell") => (SHELL_KEYWORDS, Some("#")),
+            Some("toml" | "yaml" | "yml") => (&[], Some("#")),
+            Some("c" | "h" | "cpp" | "hpp" | "java" | "kt" | "swift") => (&[], Some("//")),
+            _ => (&[], None),
+        };
+        Self {
+            keywords,
+            line_comment,
+            char_literals: matches!(language, Some("rs" | "rust")),
+        }
+    }
+
+    /// Highlighted spans of one line, in order and non-overlapping
+    pub fn highlight(&self, line: &str) -> Vec<HighlightSpan> {
+        let mut spans = Vec::new();
+        let bytes = line.as_bytes();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let rest = &line[i..];
+            if let Some(comment) = self.line_comment {
+                if rest.starts_with(comment) {
+                    spans.push(HighlightSpan {
+                        kind: HighlightKind::Comment,
+                        range: i..line.len(),
+                    });
+                    break;
+                }
+            }
+
+            let c = bytes[i];
+            if c == b'"' || c == b'\'' || c == b'`' {
+                let end = string_end(bytes, i);
+                let closed = end > i + 1 && bytes[end - 1] == c;
+                let lifetime = self.char_literals && c == b'\'' && !(closed && end - i <= 4);
+                if !lifetime {
+                    spans.push(HighlightSpan {
+                        kind: HighlightKind::String,
+                        range: i..end,
+                    });
+                    i = end;
+                    continue;
+                }
+            }
+
+            if c.is_ascii_digit() && (i == 0 || !is_ident_byte(bytes[i - 1])) {
+                let end = scan(bytes, i, |b| b.is_ascii_alphanumeric() || b == b'_' || b == b'.');
+                spans.push(HighlightSpan {
+                    kind: HighlightKind::Number,
+                    range: i..end,
+                });
+                i = end;
+                continue;
+            }
+
+            if is_ident_byte(c) {
+                let end = scan(bytes, i, is_ident_byte);
+                if self.keywords.contains(&&line[i..end]) {
+                    spans.push(HighlightSpan {
+                        kind: HighlightKind::Keyword,
+                        range: i..end,
+                    });
+                }
+                i = end;
+                continue;
+            }
+
+            i += line[i..].chars().next().map_or(1, char::len_utf8);
+        }
+        spans
+    }
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+fn scan(bytes: &[u8], start: usize, accept: impl Fn(u8) -> bool) -> usize {
+    let mut end = start;
+    while end < bytes.len() && accept(bytes[end]) {
+        end += 1;
+    }
+    end
+}
+
+/// End (exclusive) of the string literal opening at `start`
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(highlighter: &Highlighter, line: &str) -> Vec<(HighlightKind, String)> {
+        highlighter
+            .highlight(line)
+            .into_iter()
+            .map(|span| (span.kind, line[span.range].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn rust_line() {
+        let rust = Highlighter::for_language(Some("rs"));
+        assert_eq!(
+            kinds(&rust, r#"let s = "a \" b"; // note"#),
+            vec![
+                (HighlightKind::Keyword, "let".to_string()),
+                (HighlightKind::String, r#""a \" b""#.to_string()),
+                (HighlightKind::Comment, "// note".to_string()),
+            ]
+        );
+        assert_eq!(
+            kinds(&rust, "fn f(x: u32) -> u32 { x + 42 }"),
+            vec![
+                (HighlightKind::Keyword, "fn".to_string()),
+                (HighlightKind::Number, "42".to_string()),
+            ]
+        );
+        assert_eq!(
+            kinds(&rust, "fn f<'a>(c: &'a str) -> char { 'x' }"),
+            vec![
+                (HighlightKind::Keyword, "fn".to_string()),
+                (HighlightKind::String, "'x'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_language_has_no_keywords() {
+        let plain = Highlighter::for_language(None);
+        assert_eq!(kinds(&plain, "let x = 1"), vec![(HighlightKind::Number, "1".to_string())]);
+    }
+}
@@ -0,0 +1,399 @@
+//! Write-ahead journal for applying edits
+//!
+//! Before an edit touches the file system, an intent record with every file's
+//! previous and new content, their hashes and a diff is written to
+//! [`JOURNAL_DIR`] and synced to disk. The record is removed once all files
+//! are written. A record still present whose owning process is gone belongs
+//! to an application that crashed midway; [`EditJournal::recover`] rolls it
+//! back (or replays it), so the workspace is never left with only some of
+//! the files of an edit written. Records of processes still running are
+//! applications in progress and are left alone.
+//! Completed applications are kept in the journal's [`FileTimeline`] for the
+//! rest of the session.
+
+use super::timeline::FileTimeline;
+use super::{EditorError, FileEdit};
+use crate::content_cache::{content_hash, CachedFile, ContentCache};
+use crate::io::{process_alive, FileSystem};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// Directory (relative to the workspace root) holding intent records
+pub const JOURNAL_DIR: &str = ".picode/journal";
+
+/// Age from which a record that does not parse was cut off by a crash
+/// rather than being written right now
+const TORN_RECORD_AGE: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// What to do with an application interrupted by a crash
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalRecovery {
+    /// Restore every file to its content before the edit
+    #[default]
+    Rollback,
+    /// Finish the edit by writing every file's new content
+    Replay,
+}
+
+/// One file of a journaled application
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct JournaledFile {
+    path: PathBuf,
+    /// Content before the edit; `None` when the edit creates the file
+    before: Option<String>,
+    before_hash: Option<String>,
+    after: String,
+    after_hash: String,
+    /// Unified diff, for inspecting a record by hand
+    diff: String,
+}
+
+/// Intent record of one application
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IntentRecord {
+    id: Uuid,
+    created_at: chrono::DateTime<chrono::Utc>,
+    /// Process applying the edit; 0 in records written before owners were
+    /// kept
+    #[serde(default)]
+    owner: u32,
+    files: Vec<JournaledFile>,
+}
+
+/// What recovering one interrupted application did
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredEdit {
+    pub record: PathBuf,
+    pub mode: JournalRecovery,
+    /// Files that were rewritten (or removed, for rolled back new files)
+    pub restored: Vec<PathBuf>,
+}
+
+impl fmt::Display for RecoveredEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.mode {
+            JournalRecovery::Rollback => "rolled back",
+            JournalRecovery::Replay => "replayed",
+        };
+        let files: Vec<String> = self.restored.iter().map(|path| path.display().to_string()).collect();
+        write!(f, "⚠️  An edit interrupted by a crash was {}: {}", action, files.join(", "))
+    }
+}
+
+/// Journal of edit applications in one directory. Clones share the
+/// timeline of completed applications.
+#[derive(Debug, Clone)]
+pub struct EditJournal {
+    dir: PathBuf,
+    timeline: Arc<Mutex<FileTimeline>>,
+}
+
+impl PartialEq for EditJournal {
+    fn eq(&self, other: &Self) -> bool {
+        self.dir == other.dir
+    }
+}
+
+impl EditJournal {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            timeline: Arc::default(),
+        }
+    }
+
+    /// The journal of the workspace at `root`
+    pub fn for_workspace(root: &Path) -> Self {
+        Self::new(root.join(JOURNAL_DIR))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Versions of the files edited through this journal
+    pub fn timeline(&self) -> MutexGuard<'_, FileTimeline> {
+        self.timeline.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Write all `edits` or none of them. Every edit's base is checked
+    /// before anything is written; if a write fails, the files already
+    /// written are restored.
+    pub async fn apply(
+        &self,
+        edits: &[FileEdit],
+        fs: &dyn FileSystem,
+        cache: &ContentCache,
+    ) -> Result<Vec<CachedFile>, EditorError> {
+        let mut files = Vec::with_capacity(edits.len());
+        for edit in edits {
+            let before = match fs.read(&edit.path).await {
+                Ok(bytes) => Some(bytes),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            let before_hash = before.as_deref().map(content_hash);
+            if before_hash != edit.base_hash {
+                return Err(EditorError::Conflict(edit.path.clone()));
+            }
+            let before = before
+                .map(String::from_utf8)
+                .transpose()
+                .map_err(|_| EditorError::Journal(format!("{} is not UTF-8 text", edit.path.display())))?;
+            let diff = TextDiff::from_lines(before.as_deref().unwrap_or(""), &edit.content)
+                .unified_diff()
+                .header(&edit.path.display().to_string(), &edit.path.display().to_string())
+                .to_string();
+            files.push(JournaledFile {
+                path: edit.path.clone(),
+                before,
+                before_hash,
+                after: edit.content.clone(),
+                after_hash: content_hash(edit.content.as_bytes()),
+                diff,
+            });
+        }
+
+        let record = IntentRecord {
+            id: Uuid::new_v4(),
+            created_at: chrono::Utc::now(),
+            owner: std::process::id(),
+            files,
+        };
+        let record_path = self.dir.join(format!("{}.json", record.id));
+        let json = serde_json::to_vec_pretty(&record).map_err(|e| EditorError::Journal(e.to_string()))?;
+        fs.create_dir_all(&self.dir).await?;
+        fs.write_synced(&record_path, &json).await?;
+
+        let mut written = Vec::with_capacity(record.files.len());
+        for file in &record.files {
+            match write_file(fs, cache, &file.path, &file.after).await {
+                Ok(cached) => written.push(cached),
+                Err(e) => {
+                    for done in &record.files[..written.len()] {
+                        restore(fs, cache, done, JournalRecovery::Rollback).await?;
+                    }
+                    fs.remove_file(&record_path).await?;
+                    return Err(e.into());
+                }
+            }
+        }
+        fs.remove_file(&record_path).await?;
+
+        let mut timeline = self.timeline();
+        for file in &record.files {
+            timeline.record(&file.path, file.before.as_deref(), &file.after);
+        }
+        Ok(written)
+    }
+
+    /// Roll back or replay every application a crash left unfinished;
+    /// records of running processes are skipped
+    pub async fn recover(
+        &self,
+        fs: &dyn FileSystem,
+        cache: &ContentCache,
+        mode: JournalRecovery,
+    ) -> io::Result<Vec<RecoveredEdit>> {
+        if !fs.exists(&self.dir).await {
+            return Ok(Vec::new());
+        }
+        let mut records = fs.read_dir(&self.dir).await?;
+        records.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        records.sort();
+
+        let mut recovered = Vec::new();
+        for path in records {
+            // The record is written before any file; one that does not parse
+            // was cut off while being written, so no file was touched yet
+            let Ok(record) = serde_json::from_slice::<IntentRecord>(&fs.read(&path).await?) else {
+                let age = fs.modified(&path).await?.and_then(|modified| modified.elapsed().ok());
+                if age.is_none_or(|age| age >= TORN_RECORD_AGE) {
+                    fs.remove_file(&path).await?;
+                }
+                continue;
+            };
+            if process_alive(record.owner) {
+                continue;
+            }
+
+            // All files written: the crash came just before the record was
+            // removed, and the edit is complete
+            let mut complete = true;
+            for file in &record.files {
+                complete &= current_hash(fs, &file.path).await?.as_ref() == Some(&file.after_hash);
+            }
+            let mut restored = Vec::new();
+            if !complete {
+                for file in &record.files {
+                    if restore(fs, cache, file, mode).await? {
+                        restored.push(file.path.clone());
+                    }
+                }
+            }
+            fs.remove_file(&path).await?;
+            if !restored.is_empty() {
+                recovered.push(RecoveredEdit { record: path, mode, restored });
+            }
+        }
+        Ok(recovered)
+    }
+}
+
+async fn write_file(fs: &dyn FileSystem, cache: &ContentCache, path: &Path, content: &str) -> io::Result<CachedFile> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs.create_dir_all(parent).await?;
+    }
+    cache.write(fs, path, content).await
+}
+
+async fn current_hash(fs: &dyn FileSystem, path: &Path) -> io::Result<Option<String>> {
+    match fs.read(path).await {
+        Ok(bytes) => Ok(Some(content_hash(&bytes))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Bring one file to its state before (rollback) or after (replay) the
+/// edit; false when it already was
+async fn restore(fs: &dyn FileSystem, cache: &ContentCache, file: &JournaledFile, mode: JournalRecovery) -> io::Result<bool> {
+    let (target, target_hash) = match mode {
+        JournalRecovery::Rollback => (file.before.as_deref(), file.before_hash.clone()),
+        JournalRecovery::Replay => (Some(file.after.as_str()), Some(file.after_hash.clone())),
+    };
+    if current_hash(fs, &file.path).await? == target_hash {
+        return Ok(false);
+    }
+    match target {
+        Some(content) => {
+            write_file(fs, cache, &file.path, content).await?;
+        }
+        None => {
+            fs.remove_file(&file.path).await?;
+            cache.invalidate(&file.path);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    /// An intent record as left behind by a crash after writing `written`
+    /// of the files
+    async fn crashed(fs: &MemoryFileSystem, journal: &EditJournal, edits: &[FileEdit], written: usize) {
+        let cache = ContentCache::new();
+        let before: Vec<_> = edits.iter().map(|edit| fs.get(&edit.path)).collect();
+        journal.apply(edits, fs, &cache).await.unwrap();
+
+        // Put the record back and undo the writes that "did not happen"
+        let record = IntentRecord {
+            id: Uuid::new_v4(),
+            created_at: chrono::Utc::now(),
+            owner: 0,
+            files: edits
+                .iter()
+                .zip(&before)
+                .map(|(edit, before)| {
+                    let before = before.clone().map(|bytes| String::from_utf8(bytes).unwrap());
+                    JournaledFile {
+                        path: edit.path.clone(),
+                        before_hash: before.as_deref().map(|b| content_hash(b.as_bytes())),
+                        before,
+                        after: edit.content.clone(),
+                        after_hash: content_hash(edit.content.as_bytes()),
+                        diff: String::new(),
+                    }
+                })
+                .collect(),
+        };
+        fs.insert(journal.dir().join("crashed.json"), serde_json::to_vec(&record).unwrap());
+        for (edit, before) in edits.iter().zip(before).skip(written) {
+            match before {
+                Some(bytes) => fs.insert(&edit.path, bytes),
+                None => fs.remove_file(&edit.path).await.unwrap(),
+            }
+        }
+    }
+
+    fn edits() -> Vec<FileEdit> {
+        vec![
+            FileEdit::new("/ws/src/lib.rs", "pub mod api;\n").based_on(content_hash(b"")),
+            FileEdit::new("/ws/src/api.rs", "pub fn call() {}\n"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn applies_all_or_nothing_and_clears_the_record() {
+        let fs = MemoryFileSystem::new();
+        let cache = ContentCache::new();
+        let journal = EditJournal::for_workspace(Path::new("/ws"));
+        fs.insert("/ws/src/lib.rs", "");
+
+        let stale = [edits()[0].clone(), FileEdit::new("/ws/src/lib.rs", "x").based_on("other")];
+        assert!(matches!(journal.apply(&stale, &fs, &cache).await, Err(EditorError::Conflict(_))));
+        assert_eq!(fs.read_to_string(Path::new("/ws/src/lib.rs")).await.unwrap(), "");
+
+        let written = journal.apply(&edits(), &fs, &cache).await.unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(fs.read_to_string(Path::new("/ws/src/api.rs")).await.unwrap(), "pub fn call() {}\n");
+        assert!(fs.read_dir(journal.dir()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn interrupted_applications_are_rolled_back_or_replayed() {
+        let journal = EditJournal::for_workspace(Path::new("/ws"));
+        let cache = ContentCache::new();
+
+        let fs = MemoryFileSystem::new();
+        fs.insert("/ws/src/lib.rs", "");
+        crashed(&fs, &journal, &edits(), 1).await;
+        fs.insert(journal.dir().join("torn.json"), "{\"id\": \"");
+        let recovered = journal.recover(&fs, &cache, JournalRecovery::Rollback).await.unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].restored, vec![PathBuf::from("/ws/src/lib.rs")]);
+        assert_eq!(fs.read_to_string(Path::new("/ws/src/lib.rs")).await.unwrap(), "");
+        assert!(!fs.exists(Path::new("/ws/src/api.rs")).await);
+        assert!(fs.read_dir(journal.dir()).await.unwrap().is_empty());
+
+        let fs = MemoryFileSystem::new();
+        fs.insert("/ws/src/lib.rs", "");
+        crashed(&fs, &journal, &edits(), 1).await;
+        let recovered = journal.recover(&fs, &cache, JournalRecovery::Replay).await.unwrap();
+        assert_eq!(recovered[0].restored, vec![PathBuf::from("/ws/src/api.rs")]);
+        assert_eq!(fs.read_to_string(Path::new("/ws/src/api.rs")).await.unwrap(), "pub fn call() {}\n");
+
+        // Every file written before the crash: nothing to undo
+        let fs = MemoryFileSystem::new();
+        fs.insert("/ws/src/lib.rs", "");
+        crashed(&fs, &journal, &edits(), 2).await;
+        assert!(journal.recover(&fs, &cache, JournalRecovery::Rollback).await.unwrap().is_empty());
+        assert_eq!(fs.read_to_string(Path::new("/ws/src/lib.rs")).await.unwrap(), "pub mod api;\n");
+    }
+
+    #[tokio::test]
+    async fn applications_of_running_processes_are_left_alone() {
+        let journal = EditJournal::for_workspace(Path::new("/ws"));
+        let cache = ContentCache::new();
+        let fs = MemoryFileSystem::new();
+        fs.insert("/ws/src/lib.rs", "");
+        crashed(&fs, &journal, &edits(), 1).await;
+        let record_path = journal.dir().join("crashed.json");
+        let mut record: IntentRecord = serde_json::from_slice(&fs.get(&record_path).unwrap()).unwrap();
+        record.owner = std::process::id();
+        fs.insert(&record_path, serde_json::to_vec(&record).unwrap());
+
+        assert!(journal.recover(&fs, &cache, JournalRecovery::Rollback).await.unwrap().is_empty());
+        assert!(fs.exists(&record_path).await);
+        assert_eq!(fs.read_to_string(Path::new("/ws/src/lib.rs")).await.unwrap(), "pub mod api;\n");
+    }
+}
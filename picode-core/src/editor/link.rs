@@ -0,0 +1,213 @@
+//! Linked editor and chat panes
+//!
+//! A [`LinkedPanes`] binds an editor pane and a chat pane to the same file.
+//! Every chat prompt carries the file, or only the region visible in the
+//! editor, as context; replies propose changes as SEARCH/REPLACE blocks
+//! ([`ProposedEdit`]) that the host applies to the open buffer with one key.
+//! The link tracks the editor's viewport so the context follows scrolling,
+//! and applying an edit moves the viewport to the changed lines.
+
+use super::{EditorError, TextBuffer};
+use crate::pane::{Pane, PaneError, PaneId, PaneType};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+const SEARCH_MARKER: &str = "<<<<<<< SEARCH";
+const DIVIDER_MARKER: &str = "=======";
+const REPLACE_MARKER: &str = ">>>>>>> REPLACE";
+
+/// Instructions added to the system prompt of a linked chat
+pub const LINKED_CHAT_INSTRUCTIONS: &str = "You are editing the file shown in the context next to \
+the user. To propose a change, reply with one block per change:\n\
+<<<<<<< SEARCH\n<exact lines currently in the file>\n=======\n<replacement lines>\n>>>>>>> REPLACE\n\
+The SEARCH part must match the file exactly, including indentation.";
+
+/// How much of the file a linked chat sends as context
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkScope {
+    #[default]
+    File,
+    /// Only the lines visible in the editor
+    Visible,
+}
+
+/// Lines shown in the editor pane
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub first_line: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    /// Zero-based lines in view, clamped to `line_count`
+    pub fn range(&self, line_count: usize) -> std::ops::Range<usize> {
+        let start = self.first_line.min(line_count);
+        start..(start + self.height).min(line_count)
+    }
+}
+
+/// An editor pane and a chat pane bound to the same file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkedPanes {
+    pub editor: PaneId,
+    pub chat: PaneId,
+    pub file: PathBuf,
+    pub language: Option<String>,
+    pub scope: LinkScope,
+    pub viewport: Viewport,
+}
+
+impl LinkedPanes {
+    /// Link `editor` to `chat`; the panes must be of those types
+    pub fn new(editor: &Pane, chat: &Pane) -> Result<Self, PaneError> {
+        let PaneType::Editor { file_path, language } = &editor.pane_type else {
+            return Err(PaneError::InvalidType(format!("'{}' is not an editor pane", editor.title)));
+        };
+        if chat.llm_model().is_none() {
+            return Err(PaneError::InvalidType(format!("'{}' is not a chat pane", chat.title)));
+        }
+        Ok(Self {
+            editor: editor.id.clone(),
+            chat: chat.id.clone(),
+            file: file_path.clone(),
+            language: language.clone(),
+            scope: LinkScope::default(),
+            viewport: Viewport::default(),
+        })
+    }
+
+    pub fn with_scope(mut self, scope: LinkScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Follow the editor's scrolling
+    pub fn set_viewport(&mut self, first_line: usize, height: usize) {
+        self.viewport = Viewport { first_line, height };
+    }
+
+    /// Scroll so `line` is in view, keeping the viewport height
+    pub fn reveal(&mut self, line: usize) {
+        let range = self.viewport.range(usize::MAX);
+        if !range.contains(&line) {
+            self.viewport.first_line = line.saturating_sub(self.viewport.height / 3);
+        }
+    }
+
+    /// Context message for the next chat prompt
+    pub fn context(&self, buffer: &TextBuffer) -> String {
+        let lines = buffer.lines();
+        let range = match self.scope {
+            LinkScope::File => 0..lines.len(),
+            LinkScope::Visible => self.viewport.range(lines.len()),
+        };
+        let mut out = format!("File {}", self.file.display());
+        if self.scope == LinkScope::Visible {
+            let _ = write!(out, ", lines {}-{} of {}", range.start + 1, range.end, lines.len());
+        }
+        if buffer.is_dirty() {
+            out.push_str(" (unsaved changes)");
+        }
+        let _ = writeln!(out, ":\n```{}", self.language.as_deref().unwrap_or(""));
+        for line in &lines[range] {
+            let _ = writeln!(out, "{}", line);
+        }
+        out.push_str("```\n");
+        out
+    }
+}
+
+/// A change proposed in a chat reply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposedEdit {
+    pub search: String,
+    pub replace: String,
+}
+
+impl ProposedEdit {
+    /// Every SEARCH/REPLACE block in `reply`, in order
+    pub fn parse_all(reply: &str) -> Vec<Self> {
+        let mut edits = Vec::new();
+        let mut lines = reply.lines();
+        while let Some(line) = lines.next() {
+            if line.trim_end() != SEARCH_MARKER {
+                continue;
+            }
+            let search: Vec<&str> = lines.by_ref().take_while(|l| l.trim_end() != DIVIDER_MARKER).collect();
+            let replace: Vec<&str> = lines.by_ref().take_while(|l| l.trim_end() != REPLACE_MARKER).collect();
+            edits.push(Self {
+                search: search.join("\n"),
+                replace: replace.join("\n"),
+            });
+        }
+        edits
+    }
+
+    /// Apply the edit to the first occurrence of its SEARCH lines; returns
+    /// the zero-based line where the replacement starts
+    pub fn apply(&self, buffer: &mut TextBuffer) -> Result<usize, EditorError> {
+        let lines = buffer.lines();
+        let search: Vec<&str> = self.search.lines().collect();
+        let start = if search.is_empty() {
+            // An empty SEARCH appends to the file
+            lines.len()
+        } else {
+            lines
+                .windows(search.len())
+                .position(|window| window.iter().zip(&search).all(|(a, b)| a == b))
+                .ok_or_else(|| EditorError::EditMismatch(search[0].trim().to_string()))?
+        };
+
+        let mut updated: Vec<&str> = lines[..start].iter().map(String::as_str).collect();
+        updated.extend(self.replace.lines());
+        updated.extend(lines[(start + search.len()).min(lines.len())..].iter().map(String::as_str));
+        let mut text = updated.join("\n");
+        if buffer.text().ends_with('\n') {
+            text.push('\n');
+        }
+        buffer.replace_text(&text);
+        buffer.set_cursor(start, 0, false);
+        Ok(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link() -> LinkedPanes {
+        let editor = Pane::new_editor(PathBuf::from("src/lib.rs"), "lib.rs".to_string());
+        let chat = Pane::new_llm_chat("openai".to_string(), "gpt-4".to_string(), "Chat".to_string());
+        LinkedPanes::new(&editor, &chat).unwrap()
+    }
+
+    #[test]
+    fn context_follows_the_viewport() {
+        let buffer = TextBuffer::from_text("a\nb\nc\nd\ne\n");
+        let mut link = link().with_scope(LinkScope::Visible);
+        link.set_viewport(1, 2);
+        assert_eq!(link.context(&buffer), "File src/lib.rs, lines 2-3 of 5:\n```rs\nb\nc\n```\n");
+
+        link.reveal(4);
+        assert_eq!(link.viewport.range(5), 4..5);
+        assert!(link.with_scope(LinkScope::File).context(&buffer).contains("a\nb\nc\nd\ne\n"));
+
+        let output = Pane::new_output("text".to_string(), "Out".to_string());
+        assert!(LinkedPanes::new(&output, &output).is_err());
+    }
+
+    #[test]
+    fn applies_proposed_edits_to_the_buffer() {
+        let reply = "Rename it:\n<<<<<<< SEARCH\nfn old() {\n}\n=======\nfn new() {\n}\n>>>>>>> REPLACE\n";
+        let edits = ProposedEdit::parse_all(reply);
+        assert_eq!(edits.len(), 1);
+
+        let mut buffer = TextBuffer::from_text("use x;\n\nfn old() {\n}\n");
+        assert_eq!(edits[0].apply(&mut buffer).unwrap(), 2);
+        assert_eq!(buffer.text(), "use x;\n\nfn new() {\n}\n");
+        assert!(buffer.is_dirty());
+        assert!(edits[0].apply(&mut buffer).is_err());
+    }
+}
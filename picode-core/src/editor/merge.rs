@@ -0,0 +1,183 @@
+//! Three-way merge of text
+//!
+//! When a file changed on disk after an edit was prepared, [`merge3`]
+//! combines the captured base, the version now on disk and the edit line by
+//! line. Changes made on only one side are taken as they are; regions both
+//! sides changed differently become conflicts, written with git-style
+//! markers so they can be resolved in the editor.
+
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+use std::ops::Range;
+
+/// Marker opening a conflict; the disk version follows
+pub const MARKER_CURRENT: &str = "<<<<<<< on disk";
+/// Marker before the common base of a conflict
+pub const MARKER_BASE: &str = "||||||| base";
+/// Marker between the disk version and the edit
+pub const MARKER_SEPARATOR: &str = "=======";
+/// Marker closing a conflict, after the edit
+pub const MARKER_EDIT: &str = ">>>>>>> edit";
+
+/// A region both sides changed differently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// Line of the opening marker in the merged text (0-based)
+    pub line: usize,
+    pub base: String,
+    pub current: String,
+    pub edit: String,
+}
+
+/// Outcome of a three-way merge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeResult {
+    /// Merged text, with conflict markers around every conflict
+    pub content: String,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// One side's replacement of a base line range
+struct Hunk<'a> {
+    base: Range<usize>,
+    lines: &'a [&'a str],
+    edit_side: bool,
+}
+
+fn hunks<'a>(base: &[&str], side: &'a [&'a str], edit_side: bool) -> Vec<Hunk<'a>> {
+    capture_diff_slices(Algorithm::Myers, base, side)
+        .iter()
+        .map(|op| op.as_tag_tuple())
+        .filter(|(tag, _, _)| *tag != DiffTag::Equal)
+        .map(|(_, base, new)| Hunk { base, lines: &side[new], edit_side })
+        .collect()
+}
+
+/// Base lines `range` with one side's hunks applied
+fn side_text(base: &[&str], range: Range<usize>, hunks: &[&Hunk<'_>]) -> String {
+    let mut out = String::new();
+    let mut position = range.start;
+    for hunk in hunks {
+        out.extend(base[position..hunk.base.start].iter().copied());
+        out.extend(hunk.lines.iter().copied());
+        position = hunk.base.end;
+    }
+    out.extend(base[position..range.end].iter().copied());
+    out
+}
+
+fn push_section(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Merge `current` (on disk) and `edit`, both derived from `base`
+pub fn merge3(base: &str, current: &str, edit: &str) -> MergeResult {
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let current_lines: Vec<&str> = current.split_inclusive('\n').collect();
+    let edit_lines: Vec<&str> = edit.split_inclusive('\n').collect();
+
+    let mut all = hunks(&base_lines, &current_lines, false);
+    all.extend(hunks(&base_lines, &edit_lines, true));
+    all.sort_by_key(|hunk| (hunk.base.start, hunk.base.end));
+
+    let mut content = String::new();
+    let mut conflicts = Vec::new();
+    let mut position = 0;
+    let mut index = 0;
+    while index < all.len() {
+        // Group hunks whose base ranges overlap or touch
+        let start = all[index].base.start;
+        let mut end = all[index].base.end;
+        let mut group_end = index + 1;
+        while group_end < all.len() && all[group_end].base.start <= end {
+            end = end.max(all[group_end].base.end);
+            group_end += 1;
+        }
+        let group = &all[index..group_end];
+        index = group_end;
+
+        content.extend(base_lines[position..start].iter().copied());
+        position = end;
+
+        let current_hunks: Vec<&Hunk> = group.iter().filter(|hunk| !hunk.edit_side).collect();
+        let edit_hunks: Vec<&Hunk> = group.iter().filter(|hunk| hunk.edit_side).collect();
+        let current_text = side_text(&base_lines, start..end, &current_hunks);
+        let edit_text = side_text(&base_lines, start..end, &edit_hunks);
+        if current_hunks.is_empty() || edit_hunks.is_empty() || current_text == edit_text {
+            let text = if current_hunks.is_empty() { edit_text } else { current_text };
+            content.push_str(&text);
+            continue;
+        }
+
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        let base_text: String = base_lines[start..end].concat();
+        conflicts.push(MergeConflict {
+            line: content.matches('\n').count(),
+            base: base_text.clone(),
+            current: current_text.clone(),
+            edit: edit_text.clone(),
+        });
+        content.push_str(MARKER_CURRENT);
+        content.push('\n');
+        push_section(&mut content, &current_text);
+        content.push_str(MARKER_BASE);
+        content.push('\n');
+        push_section(&mut content, &base_text);
+        content.push_str(MARKER_SEPARATOR);
+        content.push('\n');
+        push_section(&mut content, &edit_text);
+        content.push_str(MARKER_EDIT);
+        content.push('\n');
+    }
+    content.extend(base_lines[position..].iter().copied());
+
+    MergeResult { content, conflicts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{}\", a + b);\n}\n";
+
+    #[test]
+    fn merges_changes_to_different_regions() {
+        let current = BASE.replace("let a = 1;", "let a = 10;");
+        let edit = BASE.replace("println!(\"{}\", a + b);", "println!(\"sum: {}\", a + b);");
+        let merged = merge3(BASE, &current, &edit);
+        assert!(merged.is_clean());
+        assert_eq!(
+            merged.content,
+            "fn main() {\n    let a = 10;\n    let b = 2;\n    println!(\"sum: {}\", a + b);\n}\n"
+        );
+
+        // The same change on both sides is not a conflict
+        assert_eq!(merge3(BASE, &current, &current).content, current);
+        assert_eq!(merge3(BASE, BASE, &edit).content, edit);
+    }
+
+    #[test]
+    fn overlapping_changes_become_marked_conflicts() {
+        let current = BASE.replace("let b = 2;", "let b = 3;");
+        let edit = BASE.replace("let b = 2;", "let b = 4;").replace("fn main()", "pub fn main()");
+        let merged = merge3(BASE, &current, &edit);
+
+        assert_eq!(merged.conflicts.len(), 1);
+        let conflict = &merged.conflicts[0];
+        assert_eq!(conflict.line, 2);
+        assert_eq!(conflict.current, "    let b = 3;\n");
+        assert_eq!(conflict.edit, "    let b = 4;\n");
+        assert!(merged.content.starts_with("pub fn main() {\n    let a = 1;\n<<<<<<< on disk\n    let b = 3;\n"));
+        assert!(merged.content.contains("=======\n    let b = 4;\n>>>>>>> edit\n    println!"));
+    }
+}
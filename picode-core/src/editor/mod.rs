@@ -0,0 +1,56 @@
+//! Editor pane support
+//!
+//! Host-independent pieces of the `PaneType::Editor` pane: a text buffer
+//! with vi-style modal keybindings, per-line syntax highlighting, saving
+//! through [`FileEdit`] with three-way merging of concurrent changes and a
+//! write-ahead journal, an extension point for diagnostics, linking an
+//! editor pane to a chat pane about the same file, and a review queue of
+//! pending edits exchanged as patch files. The terminal front-end
+//! lives in the main binary.
+
+pub mod buffer;
+pub mod diagnostics;
+pub mod file_edit;
+pub mod highlight;
+pub mod journal;
+pub mod link;
+pub mod merge;
+pub mod modal;
+pub mod review;
+pub mod timeline;
+
+pub use buffer::{Cursor, TextBuffer};
+pub use diagnostics::{Diagnostic, DiagnosticsProvider, ExternalDiagnostics, Severity};
+pub use file_edit::{EditOutcome, FileEdit};
+pub use highlight::{HighlightKind, HighlightSpan, Highlighter};
+pub use journal::{EditJournal, JournalRecovery, RecoveredEdit, JOURNAL_DIR};
+pub use link::{LinkScope, LinkedPanes, ProposedEdit, Viewport, LINKED_CHAT_INSTRUCTIONS};
+pub use merge::{merge3, MergeConflict, MergeResult};
+pub use modal::{EditorCommand, Key, ModalEditor, Mode};
+pub use review::{PendingEdit, ReviewImport, ReviewQueue, REVIEW_FILE};
+pub use timeline::{diff_versions, restore_version, FileTimeline, FileVersion, VersionSource};
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Editor errors
+#[derive(Error, Debug)]
+pub enum EditorError {
+    #[error("{0} changed on disk since it was opened")]
+    Conflict(PathBuf),
+
+    #[error("Diagnostics from '{0}' failed: {1}")]
+    Diagnostics(String, String),
+
+    #[error("Edit journal: {0}")]
+    Journal(String),
+
+    #[error("Proposed edit does not match the buffer near `{0}`")]
+    EditMismatch(String),
+
+    #[error("Review: {0}")]
+    Review(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
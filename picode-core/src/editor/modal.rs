@@ -0,0 +1,250 @@
+//! Modal (vi-style) key handling
+//!
+//! Keys are described with the host-independent [`Key`] type so the
+//! keybindings can be tested without a terminal; the TUI front-end maps its
+//! key events onto it.
+
+use super::buffer::TextBuffer;
+
+/// A key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    Esc,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+/// Editing mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    /// Typing an ex command after `:`
+    Command(String),
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mode::Normal => f.pad("NORMAL"),
+            Mode::Insert => f.pad("INSERT"),
+            Mode::Command(_) => f.pad("COMMAND"),
+        }
+    }
+}
+
+/// Request for the host, produced by a key press
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorCommand {
+    Save,
+    /// Quit unless there are unsaved changes
+    Quit,
+    /// Quit discarding unsaved changes
+    ForceQuit,
+    SaveAndQuit,
+    /// Unknown ex command; the text is shown to the user
+    Unknown(String),
+}
+
+/// Buffer plus the modal key state machine
+#[derive(Debug, Clone)]
+pub struct ModalEditor {
+    pub buffer: TextBuffer,
+    mode: Mode,
+    /// First key of a two-key normal mode command (`dd`, `gg`)
+    pending: Option<char>,
+    /// Text inserted for Tab in insert mode
+    indent: String,
+}
+
+impl ModalEditor {
+    pub fn new(buffer: TextBuffer) -> Self {
+        Self {
+            buffer,
+            mode: Mode::Normal,
+            pending: None,
+            indent: "    ".to_string(),
+        }
+    }
+
+    /// Set the text inserted for Tab (e.g. four spaces or `\t`)
+    pub fn with_indent(mut self, indent: impl Into<String>) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    pub fn mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    /// Handle a key; returns a command when the host has to act
+    pub fn handle_key(&mut self, key: Key) -> Option<EditorCommand> {
+        if key == Key::Ctrl('s') {
+            return Some(EditorCommand::Save);
+        }
+        match self.mode {
+            Mode::Normal => self.normal(key),
+            Mode::Insert => {
+                self.insert(key);
+                None
+            }
+            Mode::Command(_) => self.command(key),
+        }
+    }
+
+    fn enter_insert(&mut self) {
+        self.mode = Mode::Insert;
+    }
+
+    fn normal(&mut self, key: Key) -> Option<EditorCommand> {
+        let pending = self.pending.take();
+        let buffer = &mut self.buffer;
+        match key {
+            Key::Char('h') | Key::Left | Key::Backspace => buffer.move_left(),
+            Key::Char('l') | Key::Right => buffer.move_right(false),
+            Key::Char('k') | Key::Up => buffer.move_up(false),
+            Key::Char('j') | Key::Down | Key::Enter => buffer.move_down(false),
+            Key::Char('0') | Key::Home => buffer.move_line_start(),
+            Key::Char('$') | Key::End => buffer.move_line_end(false),
+            Key::Char('w') => buffer.move_word_forward(),
+            Key::Char('b') => buffer.move_word_back(),
+            Key::Char('G') => {
+                let last = buffer.line_count() - 1;
+                buffer.set_cursor(last, 0, false);
+            }
+            Key::Char('g') if pending == Some('g') => buffer.set_cursor(0, 0, false),
+            Key::Char('d') if pending == Some('d') => {
+                buffer.delete_line();
+            }
+            Key::Char(c @ ('g' | 'd')) => self.pending = Some(c),
+            Key::Char('x') | Key::Delete if !buffer.lines()[buffer.cursor().line].is_empty() => {
+                buffer.delete_char();
+                let cursor = buffer.cursor();
+                buffer.set_cursor(cursor.line, cursor.column, false);
+            }
+            Key::Char('i') => self.enter_insert(),
+            Key::Char('a') => {
+                buffer.move_right(true);
+                self.enter_insert();
+            }
+            Key::Char('I') => {
+                buffer.move_line_start();
+                self.enter_insert();
+            }
+            Key::Char('A') => {
+                buffer.move_line_end(true);
+                self.enter_insert();
+            }
+            Key::Char('o') => {
+                buffer.open_line(true);
+                self.enter_insert();
+            }
+            Key::Char('O') => {
+                buffer.open_line(false);
+                self.enter_insert();
+            }
+            Key::Char(':') => self.mode = Mode::Command(String::new()),
+            _ => {}
+        }
+        None
+    }
+
+    fn insert(&mut self, key: Key) {
+        let buffer = &mut self.buffer;
+        match key {
+            Key::Esc => {
+                self.mode = Mode::Normal;
+                let cursor = buffer.cursor();
+                buffer.set_cursor(cursor.line, cursor.column.saturating_sub(1), false);
+            }
+            Key::Char(c) => buffer.insert_char(c),
+            Key::Enter => buffer.insert_newline(),
+            Key::Backspace => buffer.backspace(),
+            Key::Delete => buffer.delete_char(),
+            Key::Tab => buffer.insert_str(&self.indent),
+            Key::Left => buffer.move_left(),
+            Key::Right => buffer.move_right(true),
+            Key::Up => buffer.move_up(true),
+            Key::Down => buffer.move_down(true),
+            Key::Home => buffer.move_line_start(),
+            Key::End => buffer.move_line_end(true),
+            Key::Ctrl(_) => {}
+        }
+    }
+
+    fn command(&mut self, key: Key) -> Option<EditorCommand> {
+        let Mode::Command(text) = &mut self.mode else {
+            return None;
+        };
+        match key {
+            Key::Esc => self.mode = Mode::Normal,
+            Key::Backspace if text.is_empty() => self.mode = Mode::Normal,
+            Key::Backspace => {
+                text.pop();
+            }
+            Key::Char(c) => text.push(c),
+            Key::Enter => {
+                let command = match text.trim() {
+                    "w" => EditorCommand::Save,
+                    "q" => EditorCommand::Quit,
+                    "q!" => EditorCommand::ForceQuit,
+                    "wq" | "x" => EditorCommand::SaveAndQuit,
+                    other => EditorCommand::Unknown(other.to_string()),
+                };
+                self.mode = Mode::Normal;
+                return Some(command);
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(editor: &mut ModalEditor, text: &str) -> Option<EditorCommand> {
+        text.chars().fold(None, |_, c| {
+            editor.handle_key(match c {
+                '\n' => Key::Enter,
+                '\x1b' => Key::Esc,
+                c => Key::Char(c),
+            })
+        })
+    }
+
+    #[test]
+    fn insert_and_normal_mode_editing() {
+        let mut editor = ModalEditor::new(TextBuffer::from_text("one\ntwo\n"));
+        keys(&mut editor, "Afoo\x1b");
+        assert_eq!(editor.buffer.text(), "onefoo\ntwo\n");
+        assert_eq!(editor.mode(), &Mode::Normal);
+
+        keys(&mut editor, "jdd");
+        assert_eq!(editor.buffer.text(), "onefoo\n");
+
+        keys(&mut editor, "ggOzero\x1b");
+        assert_eq!(editor.buffer.text(), "zero\nonefoo\n");
+    }
+
+    #[test]
+    fn ex_commands() {
+        let mut editor = ModalEditor::new(TextBuffer::new());
+        assert_eq!(keys(&mut editor, ":wq\n"), Some(EditorCommand::SaveAndQuit));
+        assert_eq!(keys(&mut editor, ":q!\n"), Some(EditorCommand::ForceQuit));
+        assert_eq!(keys(&mut editor, ":e x\n"), Some(EditorCommand::Unknown("e x".to_string())));
+        assert_eq!(editor.handle_key(Key::Ctrl('s')), Some(EditorCommand::Save));
+        assert_eq!(editor.mode(), &Mode::Normal);
+    }
+}
@@ -0,0 +1,345 @@
+//! Pending edits and patch handoff
+//!
+//! An agent run with review enabled stages its file writes in a
+//! [`ReviewQueue`] instead of making them. The queue can be exported as a
+//! git-style patch, reviewed and edited in any tool that understands patches
+//! (magit, an IDE, `git apply --check`), and imported back: hunks edited in
+//! the patch change the pending content, and files whose section was removed
+//! from the patch are dropped. Applying the queue writes every pending file
+//! at once through the edit journal.
+
+use super::{EditJournal, EditorError, FileEdit};
+use crate::content_cache::{content_hash, CachedFile, ContentCache};
+use crate::io::FileSystem;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File (relative to the workspace root) holding the pending edits
+pub const REVIEW_FILE: &str = ".picode/review.json";
+
+/// Context lines around each change in exported patches
+const CONTEXT_LINES: usize = 3;
+
+/// A file write waiting for review
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingEdit {
+    /// Relative to the workspace root
+    pub path: PathBuf,
+    /// Content when the edit was staged; `None` for a new file
+    pub base: Option<String>,
+    pub content: String,
+}
+
+/// What importing a patch changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReviewImport {
+    /// Pending edits whose content the patch sets
+    pub updated: Vec<PathBuf>,
+    /// Pending edits the patch no longer contains
+    pub dropped: Vec<PathBuf>,
+}
+
+/// Edits staged for review, in staging order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewQueue {
+    pub edits: Vec<PendingEdit>,
+}
+
+impl ReviewQueue {
+    /// The queue of the workspace at `root`; empty when nothing is staged
+    pub async fn load(fs: &dyn FileSystem, root: &Path) -> Result<Self, EditorError> {
+        match fs.read(&root.join(REVIEW_FILE)).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| EditorError::Review(e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn save(&self, fs: &dyn FileSystem, root: &Path) -> Result<(), EditorError> {
+        let path = root.join(REVIEW_FILE);
+        if self.edits.is_empty() {
+            return match fs.remove_file(&path).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+        if let Some(parent) = path.parent() {
+            fs.create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec_pretty(self).map_err(|e| EditorError::Review(e.to_string()))?;
+        Ok(fs.write(&path, &json).await?)
+    }
+
+    /// Stage new content for `path`. A file staged twice keeps its first
+    /// base; staging the base content again unstages it.
+    pub fn stage(&mut self, path: impl Into<PathBuf>, base: Option<String>, content: impl Into<String>) {
+        let path = path.into();
+        let content = content.into();
+        let base = match self.edits.iter().position(|edit| edit.path == path) {
+            Some(index) => self.edits.remove(index).base,
+            None => base,
+        };
+        if base.as_deref() != Some(content.as_str()) {
+            self.edits.push(PendingEdit { path, base, content });
+        }
+    }
+
+    /// Every pending edit as a patch `git apply` accepts at the workspace root
+    pub fn to_patch(&self) -> String {
+        let mut out = String::new();
+        for edit in &self.edits {
+            let path = crate::paths::to_slash(&edit.path);
+            out.push_str(&format!("diff --git a/{0} b/{0}\n", path));
+            let old = match &edit.base {
+                Some(_) => format!("a/{}", path),
+                None => {
+                    out.push_str("new file mode 100644\n");
+                    "/dev/null".to_string()
+                }
+            };
+            let diff = TextDiff::from_lines(edit.base.as_deref().unwrap_or(""), &edit.content)
+                .unified_diff()
+                .context_radius(CONTEXT_LINES)
+                .header(&old, &format!("b/{}", path))
+                .to_string();
+            out.push_str(&diff);
+        }
+        out
+    }
+
+    /// Take back a reviewed patch. Files the queue does not hold yet are
+    /// staged against their current content in the workspace.
+    pub async fn import_patch(&mut self, patch: &str, fs: &dyn FileSystem, root: &Path) -> Result<ReviewImport, EditorError> {
+        let files = parse_patch(patch)?;
+        let mut result = ReviewImport::default();
+        let mut reviewed = Vec::with_capacity(files.len());
+        for file in files {
+            let base = match self.edits.iter().find(|edit| edit.path == file.path) {
+                Some(edit) => edit.base.clone(),
+                None => read_text(fs, &root.join(&file.path)).await?,
+            };
+            let content = apply_hunks(base.as_deref().unwrap_or(""), &file.hunks)
+                .map_err(|reason| EditorError::Review(format!("{}: {}", file.path.display(), reason)))?;
+            result.updated.push(file.path.clone());
+            reviewed.push(PendingEdit { path: file.path, base, content });
+        }
+        result.dropped = self
+            .edits
+            .iter()
+            .filter(|edit| !reviewed.iter().any(|r| r.path == edit.path))
+            .map(|edit| edit.path.clone())
+            .collect();
+        reviewed.retain(|edit| edit.base.as_deref() != Some(edit.content.as_str()));
+        self.edits = reviewed;
+        Ok(result)
+    }
+
+    /// Write every pending edit, or none if any file changed since it was
+    /// staged, and empty the queue
+    pub async fn apply(
+        &mut self,
+        root: &Path,
+        journal: &EditJournal,
+        fs: &dyn FileSystem,
+        cache: &ContentCache,
+    ) -> Result<Vec<CachedFile>, EditorError> {
+        let edits: Vec<FileEdit> = self
+            .edits
+            .iter()
+            .map(|edit| {
+                let file = FileEdit::new(root.join(&edit.path), edit.content.clone());
+                match &edit.base {
+                    Some(base) => file.based_on(content_hash(base.as_bytes())),
+                    None => file,
+                }
+            })
+            .collect();
+        let written = journal.apply(&edits, fs, cache).await?;
+        self.edits.clear();
+        Ok(written)
+    }
+}
+
+/// Text content of `path`; `None` when it does not exist
+pub async fn read_text(fs: &dyn FileSystem, path: &Path) -> Result<Option<String>, EditorError> {
+    match fs.read(path).await {
+        Ok(bytes) => String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| EditorError::Review(format!("{} is not UTF-8 text", path.display()))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// One file's section of a patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FilePatch {
+    path: PathBuf,
+    hunks: Vec<PatchHunk>,
+}
+
+/// Lines of a hunk, each with its `' '`, `'-'` or `'+'` tag and its line
+/// ending
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct PatchHunk {
+    /// First old line, 1-based (0 when the old side is empty)
+    old_start: usize,
+    lines: Vec<(char, String)>,
+}
+
+/// Parse a unified or git patch. Hunk line counts are not trusted, since
+/// patches edited by hand rarely keep them right; a hunk runs until the next
+/// hunk or file header.
+fn parse_patch(text: &str) -> Result<Vec<FilePatch>, EditorError> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut in_hunk = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let is_file_header = line.starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ "));
+        if line.starts_with("diff --git ") {
+            in_hunk = false;
+        } else if is_file_header {
+            let path = header_path(lines[i + 1])
+                .ok_or_else(|| EditorError::Review(format!("deleting files is not supported: {}", line.trim_end())))?;
+            files.push(FilePatch { path, hunks: Vec::new() });
+            in_hunk = false;
+            i += 1;
+        } else if line.starts_with("@@") {
+            let file = files.last_mut().ok_or_else(|| EditorError::Review("hunk before any file header".to_string()))?;
+            file.hunks.push(PatchHunk { old_start: hunk_old_start(line)?, lines: Vec::new() });
+            in_hunk = true;
+        } else if in_hunk {
+            let hunk = files.last_mut().and_then(|file| file.hunks.last_mut()).expect("inside a hunk");
+            match line.chars().next() {
+                Some(tag @ (' ' | '-' | '+')) => hunk.lines.push((tag, line[1..].to_string())),
+                // Editors strip the space of blank context lines
+                Some('\n' | '\r') => hunk.lines.push((' ', line.to_string())),
+                Some('\\') => {
+                    if let Some((_, last)) = hunk.lines.last_mut() {
+                        let trimmed = last.trim_end_matches(['\n', '\r']).len();
+                        last.truncate(trimmed);
+                    }
+                }
+                _ => in_hunk = false,
+            }
+        }
+        i += 1;
+    }
+    Ok(files)
+}
+
+/// Path of a `+++ b/path` header; `None` for `/dev/null`
+fn header_path(line: &str) -> Option<PathBuf> {
+    let path = line["+++ ".len()..].trim_end_matches(['\n', '\r']);
+    let path = path.split('\t').next().unwrap_or(path);
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(PathBuf::from(path.strip_prefix("b/").unwrap_or(path)))
+}
+
+fn hunk_old_start(line: &str) -> Result<usize, EditorError> {
+    line.split_whitespace()
+        .nth(1)
+        .and_then(|old| old.strip_prefix('-'))
+        .and_then(|old| old.split(',').next())
+        .and_then(|start| start.parse().ok())
+        .ok_or_else(|| EditorError::Review(format!("invalid hunk header: {}", line.trim_end())))
+}
+
+/// Apply `hunks` to `base`. Each hunk is looked for at the line its header
+/// names, shifted by the lines earlier hunks added or removed, and then
+/// anywhere after the previous hunk.
+fn apply_hunks(base: &str, hunks: &[PatchHunk]) -> Result<String, String> {
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let mut out = String::new();
+    let mut position = 0;
+    let mut offset: isize = 0;
+    for hunk in hunks {
+        let old: Vec<&str> = hunk.lines.iter().filter(|(tag, _)| *tag != '+').map(|(_, line)| line.as_str()).collect();
+        let matches_at = |start: usize| base_lines.get(start..start + old.len()).is_some_and(|lines| lines == old.as_slice());
+        let expected = (hunk.old_start.saturating_sub(1) as isize + offset).max(position as isize) as usize;
+        let start = if matches_at(expected) {
+            expected
+        } else {
+            (position..=base_lines.len().saturating_sub(old.len()))
+                .find(|start| matches_at(*start))
+                .ok_or_else(|| format!("hunk at line {} does not match", hunk.old_start))?
+        };
+        out.extend(base_lines[position..start].iter().copied());
+        let mut added = 0;
+        for (tag, line) in &hunk.lines {
+            if *tag != '-' {
+                out.push_str(line);
+            }
+            if *tag == '+' {
+                added += 1;
+            }
+        }
+        offset += added as isize - (old.len() - hunk.lines.iter().filter(|(tag, _)| *tag == ' ').count()) as isize;
+        position = start + old.len();
+    }
+    out.extend(base_lines[position..].iter().copied());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    const BASE: &str = "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{}\", a + b);\n}\n";
+
+    fn queue() -> ReviewQueue {
+        let mut queue = ReviewQueue::default();
+        queue.stage("src/main.rs", Some(BASE.to_string()), BASE.replace("let b = 2;", "let b = 3;"));
+        queue.stage("src/new.rs", None, "pub fn new() {}");
+        queue
+    }
+
+    #[tokio::test]
+    async fn exported_patches_round_trip_with_review_edits() {
+        let fs = MemoryFileSystem::new();
+        let root = Path::new("/ws");
+        let mut queue = queue();
+        let patch = queue.to_patch();
+        assert!(patch.starts_with("diff --git a/src/main.rs b/src/main.rs\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,5 +1,5 @@\n"));
+        assert!(patch.contains("new file mode 100644\n--- /dev/null\n+++ b/src/new.rs\n"));
+        assert!(patch.contains("\\ No newline at end of file"));
+
+        // Unchanged patch: same queue
+        let mut same = queue.clone();
+        let import = same.import_patch(&patch, &fs, root).await.unwrap();
+        assert_eq!(same, queue);
+        assert!(import.dropped.is_empty());
+
+        // The reviewer changes the new value and drops the new file
+        let reviewed = patch.replace("+    let b = 3;", "+    let b = 4;");
+        let reviewed = &reviewed[..reviewed.find("diff --git a/src/new.rs").unwrap()];
+        let import = queue.import_patch(reviewed, &fs, root).await.unwrap();
+        assert_eq!(import.updated, vec![PathBuf::from("src/main.rs")]);
+        assert_eq!(import.dropped, vec![PathBuf::from("src/new.rs")]);
+        assert_eq!(queue.edits[0].content, BASE.replace("let b = 2;", "let b = 4;"));
+
+        fs.write(&root.join("src/main.rs"), BASE.as_bytes()).await.unwrap();
+        let journal = EditJournal::for_workspace(root);
+        queue.apply(root, &journal, &fs, &ContentCache::new()).await.unwrap();
+        assert!(fs.read_to_string(&root.join("src/main.rs")).await.unwrap().contains("let b = 4;"));
+        assert!(queue.edits.is_empty());
+    }
+
+    #[test]
+    fn hunks_tolerate_shifted_lines_and_wrong_counts() {
+        let patch = "--- a/f\n+++ b/f\n@@ -2,9 +2,9 @@\n     let a = 1;\n-    let b = 2;\n+    let b = 5;\n";
+        let files = parse_patch(patch).unwrap();
+        let shifted = format!("// header\n{}", BASE);
+        let patched = apply_hunks(&shifted, &files[0].hunks).unwrap();
+        assert_eq!(patched, format!("// header\n{}", BASE.replace("let b = 2;", "let b = 5;")));
+        assert!(apply_hunks("unrelated\n", &files[0].hunks).is_err());
+        assert!(parse_patch("--- a/f\n+++ /dev/null\n").is_err());
+    }
+}
@@ -0,0 +1,199 @@
+//! Versions of files edited during a session
+//!
+//! Every application that goes through an [`EditJournal`] is also recorded
+//! here: the content a file had before it was first edited, and the content
+//! after each edit. `/timeline <file>` lists the versions (plus the current
+//! content when it changed outside PiCode), diffs any two of them and
+//! restores an earlier one. A restore is an edit like any other, so it is
+//! journaled and becomes the newest version itself.
+
+use super::journal::EditJournal;
+use super::{EditorError, FileEdit};
+use crate::content_cache::{content_hash, CachedFile, ContentCache};
+use crate::io::FileSystem;
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How a version came to be
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionSource {
+    /// The file before its first edit in the session
+    Initial,
+    Edit,
+    /// Written by restoring the version with this number
+    Restore(usize),
+    /// Changed outside of the journal before the next edit
+    External,
+    /// On disk now, changed outside of the journal
+    Current,
+}
+
+impl fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSource::Initial => write!(f, "initial"),
+            VersionSource::Edit => write!(f, "edit"),
+            VersionSource::Restore(number) => write!(f, "restore of v{}", number),
+            VersionSource::External => write!(f, "changed outside"),
+            VersionSource::Current => write!(f, "current"),
+        }
+    }
+}
+
+/// One version of a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileVersion {
+    pub number: usize,
+    pub source: VersionSource,
+    /// `None` when the file did not exist
+    pub content: Option<String>,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+impl FileVersion {
+    pub fn lines(&self) -> usize {
+        self.content.as_deref().map_or(0, |content| content.lines().count())
+    }
+}
+
+/// Versions of every file edited in the session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileTimeline {
+    files: BTreeMap<PathBuf, Vec<FileVersion>>,
+}
+
+impl FileTimeline {
+    /// Record an edit of `path` from `before` to `after`
+    pub fn record(&mut self, path: &Path, before: Option<&str>, after: &str) {
+        let versions = self.files.entry(path.to_path_buf()).or_default();
+        let source = match versions.last() {
+            None => Some(VersionSource::Initial),
+            Some(last) if last.content.as_deref() != before => Some(VersionSource::External),
+            Some(_) => None,
+        };
+        if let Some(source) = source {
+            versions.push(FileVersion {
+                number: versions.len(),
+                source,
+                content: before.map(str::to_string),
+                at: chrono::Utc::now(),
+            });
+        }
+        versions.push(FileVersion {
+            number: versions.len(),
+            source: VersionSource::Edit,
+            content: Some(after.to_string()),
+            at: chrono::Utc::now(),
+        });
+    }
+
+    /// Files with recorded versions
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    pub fn versions(&self, path: &Path) -> &[FileVersion] {
+        self.files.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Versions of `path`, followed by its `current` content when that
+    /// differs from the newest recorded version
+    pub fn view(&self, path: &Path, current: Option<String>) -> Vec<FileVersion> {
+        let mut versions = self.versions(path).to_vec();
+        if versions.last().is_some_and(|last| last.content != current) {
+            versions.push(FileVersion {
+                number: versions.len(),
+                source: VersionSource::Current,
+                content: current,
+                at: chrono::Utc::now(),
+            });
+        }
+        versions
+    }
+
+    fn mark_restored(&mut self, path: &Path, number: usize) {
+        if let Some(last) = self.files.get_mut(path).and_then(|versions| versions.last_mut()) {
+            last.source = VersionSource::Restore(number);
+        }
+    }
+}
+
+/// Unified diff from one version to another
+pub fn diff_versions(path: &Path, from: &FileVersion, to: &FileVersion) -> String {
+    TextDiff::from_lines(from.content.as_deref().unwrap_or(""), to.content.as_deref().unwrap_or(""))
+        .unified_diff()
+        .header(
+            &format!("{} (v{})", path.display(), from.number),
+            &format!("{} (v{})", path.display(), to.number),
+        )
+        .to_string()
+}
+
+/// Write version `number` of `path` back, through the journal
+pub async fn restore_version(
+    journal: &EditJournal,
+    path: &Path,
+    number: usize,
+    fs: &dyn FileSystem,
+    cache: &ContentCache,
+) -> Result<CachedFile, EditorError> {
+    let version = journal
+        .timeline()
+        .versions(path)
+        .get(number)
+        .cloned()
+        .ok_or_else(|| EditorError::Journal(format!("{} has no version {}", path.display(), number)))?;
+    let content = version
+        .content
+        .ok_or_else(|| EditorError::Journal(format!("{} did not exist at version {}", path.display(), number)))?;
+
+    let mut edit = FileEdit::new(path, content).with_journal(journal.clone());
+    match fs.read(path).await {
+        Ok(bytes) => edit = edit.based_on(content_hash(&bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let restored = edit.apply(fs, cache).await?;
+    journal.timeline().mark_restored(path, number);
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    #[tokio::test]
+    async fn journaled_edits_build_a_timeline_that_can_be_restored() {
+        let fs = MemoryFileSystem::new();
+        let cache = ContentCache::new();
+        let journal = EditJournal::for_workspace(Path::new("/ws"));
+        let path = Path::new("/ws/notes.md");
+        fs.insert(path, "one\n");
+
+        let v1 = FileEdit::new(path, "one\ntwo\n").based_on(content_hash(b"one\n")).with_journal(journal.clone());
+        let saved = v1.apply(&fs, &cache).await.unwrap();
+        FileEdit::new(path, "two\n").based_on(saved.hash).with_journal(journal.clone()).apply(&fs, &cache).await.unwrap();
+
+        // Clones share the timeline
+        let timeline = journal.timeline().clone();
+        let sources: Vec<_> = timeline.versions(path).iter().map(|v| v.source.clone()).collect();
+        assert_eq!(sources, vec![VersionSource::Initial, VersionSource::Edit, VersionSource::Edit]);
+        let diff = diff_versions(path, &timeline.versions(path)[0], &timeline.versions(path)[2]);
+        assert!(diff.contains("-one\n") && diff.contains("+two\n"));
+
+        // Changed outside the journal, then restored to the first edit
+        fs.insert(path, "three\n");
+        let view = timeline.view(path, Some("three\n".to_string()));
+        assert_eq!(view.last().unwrap().source, VersionSource::Current);
+        restore_version(&journal, path, 1, &fs, &cache).await.unwrap();
+        assert_eq!(fs.read_to_string(path).await.unwrap(), "one\ntwo\n");
+        let timeline = journal.timeline();
+        let sources: Vec<_> = timeline.versions(path)[3..].iter().map(|v| v.source.clone()).collect();
+        assert_eq!(sources, vec![VersionSource::External, VersionSource::Restore(1)]);
+        assert_eq!(timeline.view(path, Some("one\ntwo\n".to_string())).len(), 5);
+    }
+}
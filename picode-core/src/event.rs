@@ -4,27 +4,14 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::sync::{broadcast, RwLock};
-use uuid::Uuid;
+use tokio::sync::{broadcast, mpsc};
 
-/// Unique identifier for events
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct EventId(pub Uuid);
-
-impl EventId {
-    pub fn new() -> Self {
-        Self(Uuid::new_v4())
-    }
-}
-
-impl std::fmt::Display for EventId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
+pub use crate::id::EventId;
 
 /// Core event types in PiCode
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -118,6 +105,11 @@ pub enum Event {
         pane_id: super::PaneId,
         file_path: std::path::PathBuf,
     },
+    /// Files pinned into a conversation changed on disk since they were read
+    ContextStale {
+        session_id: super::SessionId,
+        files: Vec<std::path::PathBuf>,
+    },
     
     // Workspace events
     WorkspaceScanned {
@@ -133,6 +125,13 @@ pub enum Event {
         event_type: String,
         context: HashMap<String, String>,
     },
+    /// Follow-up event for an async hook job that finished
+    HookCompleted {
+        hook_name: String,
+        job_id: u64,
+        success: bool,
+        output: String,
+    },
     
     // System events
     SystemShutdown,
@@ -170,8 +169,10 @@ impl Event {
             Event::FileOpened { .. } => "file_opened",
             Event::FileModified { .. } => "file_modified",
             Event::FileSaved { .. } => "file_saved",
+            Event::ContextStale { .. } => "context_stale",
             Event::WorkspaceScanned { .. } => "workspace_scanned",
             Event::HookTriggered { .. } => "hook_triggered",
+            Event::HookCompleted { .. } => "hook_completed",
             Event::SystemShutdown => "system_shutdown",
             Event::SystemError { .. } => "system_error",
             Event::Custom { .. } => "custom",
@@ -195,6 +196,7 @@ impl Event {
             | Event::FileOpened { session_id, .. }
             | Event::FileModified { session_id, .. }
             | Event::FileSaved { session_id, .. }
+            | Event::ContextStale { session_id, .. }
             | Event::WorkspaceScanned { session_id, .. }
             | Event::HookTriggered { session_id, .. } => Some(session_id),
             _ => None,
@@ -242,85 +244,231 @@ pub trait EventHandler: Send + Sync {
     fn name(&self) -> &str;
 }
 
+/// Events a handler may have queued before further ones are dropped for it
+pub const DEFAULT_HANDLER_QUEUE: usize = 256;
+
+/// Time a handler may spend on one event before it is abandoned
+pub const DEFAULT_HANDLER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A registered handler: it runs on its own task, fed by a bounded queue
+struct HandlerWorker {
+    event_types: Vec<&'static str>,
+    queue: mpsc::Sender<Arc<EventEnvelope>>,
+}
+
+/// Event history; publishers only send to `pending`, which is appended to
+/// `events` by whoever holds the lock next
+struct History {
+    pending: mpsc::UnboundedReceiver<EventEnvelope>,
+    events: VecDeque<EventEnvelope>,
+    max_size: usize,
+}
+
+impl History {
+    fn drain(&mut self) -> &VecDeque<EventEnvelope> {
+        while let Ok(envelope) = self.pending.try_recv() {
+            self.events.push_back(envelope);
+            if self.events.len() > self.max_size {
+                self.events.pop_front();
+            }
+        }
+        &self.events
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    published: AtomicU64,
+    publish_nanos: AtomicU64,
+    max_publish_nanos: AtomicU64,
+    dropped: AtomicU64,
+    timed_out: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Publish latency and handler outcomes since the bus was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventBusMetrics {
+    pub published: u64,
+    pub mean_publish_latency: Duration,
+    pub max_publish_latency: Duration,
+    /// Events dropped for a handler whose queue was full
+    pub dropped: u64,
+    pub timed_out: u64,
+    pub failed: u64,
+}
+
 /// Event bus for coordinating events across the system
+///
+/// Publishing never waits on handlers: each handler runs on its own task
+/// with a bounded queue and a per-event timeout, so a slow handler only
+/// delays (or, once its queue is full, loses) its own events.
+#[derive(Clone)]
 pub struct EventBus {
     sender: broadcast::Sender<EventEnvelope>,
-    handlers: Arc<RwLock<HashMap<String, Box<dyn EventHandler>>>>,
-    event_history: Arc<RwLock<Vec<EventEnvelope>>>,
-    max_history_size: usize,
+    handlers: Arc<RwLock<HashMap<String, HandlerWorker>>>,
+    history_sender: mpsc::UnboundedSender<EventEnvelope>,
+    history: Arc<Mutex<History>>,
+    handler_queue: usize,
+    handler_timeout: Duration,
+    counters: Arc<Counters>,
 }
 
 impl EventBus {
     pub fn new(channel_capacity: usize, max_history_size: usize) -> Self {
         let (sender, _) = broadcast::channel(channel_capacity);
+        let (history_sender, pending) = mpsc::unbounded_channel();
         
         Self {
             sender,
             handlers: Arc::new(RwLock::new(HashMap::new())),
-            event_history: Arc::new(RwLock::new(Vec::new())),
-            max_history_size,
+            history_sender,
+            history: Arc::new(Mutex::new(History {
+                pending,
+                events: VecDeque::new(),
+                max_size: max_history_size,
+            })),
+            handler_queue: DEFAULT_HANDLER_QUEUE,
+            handler_timeout: DEFAULT_HANDLER_TIMEOUT,
+            counters: Arc::new(Counters::default()),
         }
     }
     
-    /// Register an event handler
+    /// Queue size for handlers registered afterwards
+    pub fn with_handler_queue(mut self, capacity: usize) -> Self {
+        self.handler_queue = capacity.max(1);
+        self
+    }
+    
+    /// Per-event timeout for handlers registered afterwards
+    pub fn with_handler_timeout(mut self, timeout: Duration) -> Self {
+        self.handler_timeout = timeout;
+        self
+    }
+    
+    /// Register an event handler, replacing one with the same name; it runs
+    /// on a task of its own
     pub async fn register_handler(&self, handler: Box<dyn EventHandler>) {
         let name = handler.name().to_string();
-        let mut handlers = self.handlers.write().await;
-        handlers.insert(name, handler);
+        let (queue, mut events) = mpsc::channel::<Arc<EventEnvelope>>(self.handler_queue);
+        let worker = HandlerWorker {
+            event_types: handler.event_types(),
+            queue,
+        };
+        
+        let timeout = self.handler_timeout;
+        let counters = self.counters.clone();
+        tokio::spawn(async move {
+            // Ends once the handler is unregistered and its queue is empty
+            while let Some(envelope) = events.recv().await {
+                match tokio::time::timeout(timeout, handler.handle(&envelope)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
+                        counters.failed.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!("Handler {} failed to process event: {}", handler.name(), e);
+                    }
+                    Err(_) => {
+                        counters.timed_out.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!(
+                            "Handler {} timed out after {:?} on {}",
+                            handler.name(),
+                            timeout,
+                            envelope.event.event_type()
+                        );
+                    }
+                }
+            }
+        });
+        
+        self.handlers.write().unwrap_or_else(|e| e.into_inner()).insert(name, worker);
     }
     
-    /// Unregister an event handler
+    /// Unregister an event handler; events already queued for it still run
     pub async fn unregister_handler(&self, name: &str) {
-        let mut handlers = self.handlers.write().await;
-        handlers.remove(name);
+        self.handlers.write().unwrap_or_else(|e| e.into_inner()).remove(name);
     }
     
     /// Publish an event
     pub async fn publish(&self, event: Event, source: String) -> Result<(), EventError> {
+        let started = Instant::now();
         let envelope = EventEnvelope::new(event, source);
+        let event_type = envelope.event.event_type();
         
-        // Add to history
-        let mut history = self.event_history.write().await;
-        history.push(envelope.clone());
-        
-        // Maintain history size limit
-        if history.len() > self.max_history_size {
-            history.remove(0);
+        // Hand the event to interested handlers without waiting for them
+        {
+            let handlers = self.handlers.read().unwrap_or_else(|e| e.into_inner());
+            let mut shared = None;
+            for (name, worker) in handlers.iter().filter(|(_, w)| w.event_types.contains(&event_type)) {
+                let shared = shared.get_or_insert_with(|| Arc::new(envelope.clone()));
+                match worker.queue.try_send(shared.clone()) {
+                    Ok(()) => {}
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!("Handler {} is behind; dropped {}", name, event_type);
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => {
+                        tracing::trace!("Handler {} has stopped", name);
+                    }
+                }
+            }
         }
-        drop(history);
         
-        // Send to broadcast channel
-        self.sender.send(envelope.clone())
-            .map_err(|_| EventError::PublishFailed("No receivers".to_string()))?;
+        // Send to broadcast channel; having no subscribers is not an error,
+        // registered handlers still see the event
+        if self.sender.send(envelope.clone()).is_err() {
+            tracing::trace!("No subscribers for {}", event_type);
+        }
         
-        // Handle with registered handlers
-        let handlers = self.handlers.read().await;
-        for handler in handlers.values() {
-            if handler.event_types().contains(&envelope.event.event_type()) {
-                if let Err(e) = handler.handle(&envelope).await {
-                    tracing::warn!("Handler {} failed to process event: {}", handler.name(), e);
-                }
-            }
+        // Append to history; trimming it is left to a reader when the
+        // history is locked
+        let _ = self.history_sender.send(envelope);
+        if let Ok(mut history) = self.history.try_lock() {
+            history.drain();
         }
         
+        self.record_publish(started.elapsed());
         Ok(())
     }
     
+    fn record_publish(&self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.counters.published.fetch_add(1, Ordering::Relaxed);
+        self.counters.publish_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.counters.max_publish_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+    
+    /// Publish latency and handler outcomes so far
+    pub fn metrics(&self) -> EventBusMetrics {
+        let published = self.counters.published.load(Ordering::Relaxed);
+        let total = self.counters.publish_nanos.load(Ordering::Relaxed);
+        EventBusMetrics {
+            published,
+            mean_publish_latency: Duration::from_nanos(total.checked_div(published).unwrap_or(0)),
+            max_publish_latency: Duration::from_nanos(self.counters.max_publish_nanos.load(Ordering::Relaxed)),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            timed_out: self.counters.timed_out.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+        }
+    }
+    
     /// Subscribe to events
     pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope> {
         self.sender.subscribe()
     }
     
+    fn history(&self) -> MutexGuard<'_, History> {
+        self.history.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
     /// Get event history
     pub async fn get_history(&self) -> Vec<EventEnvelope> {
-        let history = self.event_history.read().await;
-        history.clone()
+        self.history().drain().iter().cloned().collect()
     }
     
     /// Get events filtered by session
     pub async fn get_session_events(&self, session_id: &super::SessionId) -> Vec<EventEnvelope> {
-        let history = self.event_history.read().await;
-        history
+        self.history()
+            .drain()
             .iter()
             .filter(|e| e.event.session_id() == Some(session_id))
             .cloned()
@@ -329,14 +477,14 @@ impl EventBus {
     
     /// Clear event history
     pub async fn clear_history(&self) {
-        let mut history = self.event_history.write().await;
-        history.clear();
+        let mut history = self.history();
+        history.drain();
+        history.events.clear();
     }
     
     /// Get handler count
     pub async fn handler_count(&self) -> usize {
-        let handlers = self.handlers.read().await;
-        handlers.len()
+        self.handlers.read().unwrap_or_else(|e| e.into_inner()).len()
     }
 }
 
@@ -397,7 +545,7 @@ mod tests {
         let event = Event::SessionCreated {
             session_id: super::super::SessionId::new(),
             name: "test".to_string(),
-            workspace_path: std::path::PathBuf::from("/tmp"),
+            workspace_path: std::env::temp_dir(),
         };
         
         assert_eq!(event.event_type(), "session_created");
@@ -454,7 +602,7 @@ mod tests {
         let event = Event::SessionCreated {
             session_id: super::super::SessionId::new(),
             name: "test".to_string(),
-            workspace_path: std::path::PathBuf::from("/tmp"),
+            workspace_path: std::env::temp_dir(),
         };
         
         bus.publish(event, "test".to_string()).await.unwrap();
@@ -493,7 +641,7 @@ mod tests {
         let event2 = Event::SessionCreated {
             session_id: super::super::SessionId::new(),
             name: "test".to_string(),
-            workspace_path: std::path::PathBuf::from("/tmp"),
+            workspace_path: std::env::temp_dir(),
         };
         
         bus.publish(event1, "source1".to_string()).await.unwrap();
@@ -514,13 +662,13 @@ mod tests {
         let event1 = Event::SessionCreated {
             session_id: session_id.clone(),
             name: "test1".to_string(),
-            workspace_path: std::path::PathBuf::from("/tmp"),
+            workspace_path: std::env::temp_dir(),
         };
         
         let event2 = Event::SessionCreated {
             session_id: other_session_id,
             name: "test2".to_string(),
-            workspace_path: std::path::PathBuf::from("/tmp"),
+            workspace_path: std::env::temp_dir(),
         };
         
         let event3 = Event::SystemShutdown; // No session ID
@@ -551,4 +699,50 @@ mod tests {
         assert_eq!(history[0].source, "source_3");
         assert_eq!(history[1].source, "source_4");
     }
-}
\ No newline at end of file
+
+    /// Handler that takes longer than any reasonable timeout
+    struct SlowHandler;
+
+    #[async_trait]
+    impl EventHandler for SlowHandler {
+        async fn handle(&self, _event: &EventEnvelope) -> Result<(), EventError> {
+            sleep(Duration::from_secs(60)).await;
+            Ok(())
+        }
+
+        fn event_types(&self) -> Vec<&'static str> {
+            vec!["system_shutdown"]
+        }
+
+        fn name(&self) -> &str {
+            "slow"
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_handlers_do_not_block_publishing() {
+        let bus = EventBus::new(100, 1000)
+            .with_handler_queue(1)
+            .with_handler_timeout(Duration::from_millis(20));
+        let call_count = Arc::new(AtomicUsize::new(0));
+        bus.register_handler(Box::new(SlowHandler)).await;
+        bus.register_handler(Box::new(TestHandler {
+            name: "test_handler".to_string(),
+            event_types: vec!["system_shutdown"],
+            call_count: call_count.clone(),
+        }))
+        .await;
+
+        // Handlers have not run yet: each queues the first event and drops the rest
+        for _ in 0..3 {
+            bus.publish(Event::SystemShutdown, "test".to_string()).await.unwrap();
+        }
+        assert_eq!(bus.get_history().await.len(), 3);
+
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(call_count.load(Ordering::SeqCst), 1);
+        let metrics = bus.metrics();
+        assert_eq!((metrics.published, metrics.dropped, metrics.timed_out), (3, 4, 1));
+        assert!(metrics.max_publish_latency >= metrics.mean_publish_latency);
+    }
+}
@@ -0,0 +1,410 @@
+//! Advisory per-file locks shared by the sessions on a workspace
+//!
+//! Interactive sessions, agent runs and server users working on the same
+//! workspace take a lock on a file before editing it, so two agents never
+//! interleave edits to one file. Each lock is a small JSON file under
+//! [`LOCKS_DIR`], visible to every process on the workspace, and is only
+//! ever created when no lock file exists, so of two racing holders exactly
+//! one gets it. Locks are leases: one expires `lease_secs` after it was last
+//! taken, and one whose owning process has exited is stale at once, so a
+//! crashed holder cannot keep a file. A lock is replaced by first moving it
+//! aside and checking it is the one that was looked at. When another holder
+//! has the file, the [`LockPolicy`] decides whether to fail, wait for it or
+//! steal it.
+
+use crate::io::{process_alive, FileSystem};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::warn;
+
+/// Lock files, relative to the workspace root
+pub const LOCKS_DIR: &str = ".picode/locks";
+
+/// How often a waiting acquire looks at the lock again
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// File lock errors
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("{path} is being edited by {holder} (lock expires {expires_at})")]
+    Held { path: String, holder: String, expires_at: DateTime<Utc> },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// What to do when another holder has the file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockPolicy {
+    /// Give up at once
+    Fail,
+    /// Wait up to `wait_secs` for the lock to be released or to expire
+    #[default]
+    Wait,
+    /// Take the lock over, noting whom it was taken from
+    Steal,
+}
+
+/// `[agent.locks]` settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockSettings {
+    pub policy: LockPolicy,
+    pub wait_secs: u64,
+    pub lease_secs: u64,
+}
+
+impl Default for LockSettings {
+    fn default() -> Self {
+        Self {
+            policy: LockPolicy::Wait,
+            wait_secs: 30,
+            lease_secs: 600,
+        }
+    }
+}
+
+/// A lock on one workspace file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileLock {
+    /// Workspace-relative, `/`-separated path
+    pub path: String,
+    pub holder: String,
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Holder the lock was stolen from, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stolen_from: Option<String>,
+    /// Process id of the lock's owner; 0 when unknown
+    #[serde(default)]
+    pub owner: u32,
+}
+
+impl FileLock {
+    /// Whether the lease is current and, where the host can tell, its
+    /// owning process still runs
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at > now && !owner_exited(self.owner)
+    }
+}
+
+fn owner_exited(owner: u32) -> bool {
+    owner != 0 && cfg!(all(unix, feature = "native")) && !process_alive(owner)
+}
+
+/// Takes and releases file locks on behalf of one holder
+#[derive(Debug)]
+pub struct FileLockService {
+    root: PathBuf,
+    fs: Arc<dyn FileSystem>,
+    holder: String,
+    settings: LockSettings,
+    held: Mutex<BTreeSet<String>>,
+}
+
+impl FileLockService {
+    pub fn new(root: PathBuf, fs: Arc<dyn FileSystem>, holder: impl Into<String>, settings: LockSettings) -> Self {
+        Self {
+            root,
+            fs,
+            holder: holder.into(),
+            settings,
+            held: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    pub fn settings(&self) -> LockSettings {
+        self.settings
+    }
+
+    fn lock_path(&self, path: &str) -> PathBuf {
+        let hash = blake3::hash(path.as_bytes()).to_hex();
+        self.root.join(LOCKS_DIR).join(format!("{}.json", &hash[..16]))
+    }
+
+    /// The live lock on `path`, if any
+    pub async fn lock_of(&self, path: &str) -> Result<Option<FileLock>, LockError> {
+        Ok(self.read_lock(path).await?.and_then(|(_, lock)| lock).filter(|lock| lock.is_live(Utc::now())))
+    }
+
+    /// The lock file's bytes and, unless it is corrupt, the lock in it
+    async fn read_lock(&self, path: &str) -> Result<Option<(Vec<u8>, Option<FileLock>)>, LockError> {
+        let lock_path = self.lock_path(path);
+        let bytes = match self.fs.read(&lock_path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match serde_json::from_slice::<FileLock>(&bytes) {
+            Ok(lock) => Ok(Some((bytes, Some(lock)))),
+            Err(e) => {
+                // A corrupt lock cannot be honoured; it is replaced like a stale one
+                warn!("Ignoring corrupt lock {}: {}", lock_path.display(), e);
+                Ok(Some((bytes, None)))
+            }
+        }
+    }
+
+    /// Lock `path` for this holder, following the policy when someone else
+    /// has it. Taking a lock already held renews its lease.
+    pub async fn acquire(&self, path: &str) -> Result<FileLock, LockError> {
+        let deadline = Instant::now() + Duration::from_secs(self.settings.wait_secs);
+        loop {
+            let now = Utc::now();
+            let (acquired_at, stolen_from) = match self.read_lock(path).await? {
+                None => (now, None),
+                Some((bytes, current)) => {
+                    let renewed = match current {
+                        Some(lock) if lock.holder == self.holder => (lock.acquired_at, lock.stolen_from),
+                        Some(lock) if lock.is_live(now) => match self.settings.policy {
+                            LockPolicy::Steal => (now, Some(lock.holder)),
+                            LockPolicy::Wait if Instant::now() < deadline => {
+                                tokio::time::sleep(POLL_INTERVAL).await;
+                                continue;
+                            }
+                            _ => return Err(held(lock)),
+                        },
+                        _ => (now, None),
+                    };
+                    if !self.remove_if_unchanged(path, &bytes).await? {
+                        continue;
+                    }
+                    renewed
+                }
+            };
+            let lock = FileLock {
+                path: path.to_string(),
+                holder: self.holder.clone(),
+                acquired_at,
+                expires_at: now + ChronoDuration::seconds(self.settings.lease_secs as i64),
+                stolen_from,
+                owner: std::process::id(),
+            };
+            let lock_path = self.lock_path(path);
+            if let Some(parent) = lock_path.parent() {
+                self.fs.create_dir_all(parent).await?;
+            }
+            match self.fs.create_new(&lock_path, &serde_json::to_vec_pretty(&lock)?).await {
+                Ok(()) => {
+                    self.held.lock().unwrap_or_else(|e| e.into_inner()).insert(path.to_string());
+                    return Ok(lock);
+                }
+                // Someone else created it first
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Remove the lock file on `path` if it still holds `expected`; another
+    /// holder may have replaced it since it was read. The file is moved to a
+    /// name of its own first, so only one remover can get it, and put back
+    /// if it turns out to be a different lock.
+    async fn remove_if_unchanged(&self, path: &str, expected: &[u8]) -> Result<bool, LockError> {
+        let lock_path = self.lock_path(path);
+        let claimed = lock_path.with_extension(format!("{}.claim", uuid::Uuid::new_v4().simple()));
+        match self.fs.rename(&lock_path, &claimed).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        let bytes = self.fs.read(&claimed).await?;
+        if bytes != expected {
+            match self.fs.create_new(&lock_path, &bytes).await {
+                Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e.into()),
+                _ => {}
+            }
+        }
+        self.fs.remove_file(&claimed).await?;
+        Ok(bytes == expected)
+    }
+
+    /// Release `path` if this holder has it; returns whether it did
+    pub async fn release(&self, path: &str) -> Result<bool, LockError> {
+        self.held.lock().unwrap_or_else(|e| e.into_inner()).remove(path);
+        match self.read_lock(path).await? {
+            Some((bytes, Some(lock))) if lock.holder == self.holder && lock.is_live(Utc::now()) => {
+                self.remove_if_unchanged(path, &bytes).await
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Release every lock this service took; returns how many were released
+    pub async fn release_all(&self) -> Result<usize, LockError> {
+        let held: Vec<String> = std::mem::take(&mut *self.held.lock().unwrap_or_else(|e| e.into_inner()))
+            .into_iter()
+            .collect();
+        let mut released = 0;
+        for path in held {
+            if self.release(&path).await? {
+                released += 1;
+            }
+        }
+        Ok(released)
+    }
+
+    /// Every live lock on the workspace, by path
+    pub async fn list(&self) -> Result<Vec<FileLock>, LockError> {
+        let entries = match self.fs.read_dir(&self.root.join(LOCKS_DIR)).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let now = Utc::now();
+        let mut locks = Vec::new();
+        for entry in entries.into_iter().filter(|entry| entry.extension().is_some_and(|ext| ext == "json")) {
+            if let Ok(lock) = serde_json::from_slice::<FileLock>(&self.fs.read(&entry).await?) {
+                if lock.is_live(now) {
+                    locks.push(lock);
+                }
+            }
+        }
+        locks.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(locks)
+    }
+}
+
+fn held(lock: FileLock) -> LockError {
+    LockError::Held { path: lock.path, holder: lock.holder, expires_at: lock.expires_at }
+}
+
+/// Indented tree of workspace-relative `/`-separated `paths`, marking the
+/// files in `locks` with their holder
+pub fn render_tree(paths: &[String], locks: &[FileLock], now: DateTime<Utc>) -> String {
+    let mut paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    paths.sort_unstable();
+    let mut out = String::new();
+    let mut open: Vec<&str> = Vec::new();
+    for path in paths {
+        let parts: Vec<&str> = path.split('/').collect();
+        let (dirs, file) = parts.split_at(parts.len() - 1);
+        let common = open.iter().zip(dirs).take_while(|(a, b)| a == b).count();
+        open.truncate(common);
+        for dir in &dirs[common..] {
+            let _ = writeln!(out, "{}{}/", "  ".repeat(open.len()), dir);
+            open.push(*dir);
+        }
+        let _ = write!(out, "{}{}", "  ".repeat(open.len()), file[0]);
+        if let Some(lock) = locks.iter().find(|lock| lock.path == path && lock.is_live(now)) {
+            let minutes = (lock.expires_at - now).num_minutes().max(1);
+            let _ = write!(out, "  🔒 {} ({}m left)", lock.holder, minutes);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    fn service(fs: &Arc<MemoryFileSystem>, holder: &str, policy: LockPolicy) -> FileLockService {
+        let settings = LockSettings { policy, wait_secs: 0, lease_secs: 60 };
+        FileLockService::new(PathBuf::from("/ws"), fs.clone(), holder, settings)
+    }
+
+    #[tokio::test]
+    async fn locks_follow_the_policy() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        let alice = service(&fs, "alice", LockPolicy::Fail);
+        let first = alice.acquire("src/lib.rs").await.unwrap();
+        // Renewing keeps the original acquisition time
+        assert_eq!(alice.acquire("src/lib.rs").await.unwrap().acquired_at, first.acquired_at);
+
+        let bob = service(&fs, "bob", LockPolicy::Wait);
+        assert!(matches!(bob.acquire("src/lib.rs").await, Err(LockError::Held { holder, .. }) if holder == "alice"));
+        assert!(!bob.release("src/lib.rs").await.unwrap());
+        bob.acquire("src/main.rs").await.unwrap();
+        let paths: Vec<String> = bob.list().await.unwrap().into_iter().map(|lock| lock.path).collect();
+        assert_eq!(paths, vec!["src/lib.rs", "src/main.rs"]);
+
+        let carol = service(&fs, "carol", LockPolicy::Steal);
+        let stolen = carol.acquire("src/lib.rs").await.unwrap();
+        assert_eq!(stolen.stolen_from.as_deref(), Some("alice"));
+        assert!(!alice.release("src/lib.rs").await.unwrap());
+
+        assert_eq!(carol.release_all().await.unwrap(), 1);
+        assert_eq!(alice.lock_of("src/lib.rs").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn expired_locks_are_free() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        let settings = LockSettings { policy: LockPolicy::Fail, wait_secs: 0, lease_secs: 0 };
+        let crashed = FileLockService::new(PathBuf::from("/ws"), fs.clone(), "crashed", settings);
+        crashed.acquire("README.md").await.unwrap();
+        assert!(crashed.list().await.unwrap().is_empty());
+
+        let bob = service(&fs, "bob", LockPolicy::Fail);
+        assert_eq!(bob.acquire("README.md").await.unwrap().holder, "bob");
+    }
+
+    #[cfg(all(unix, feature = "native"))]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn racing_holders_get_one_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let fs: Arc<dyn FileSystem> = Arc::new(crate::io::NativeFileSystem);
+        let settings = LockSettings { policy: LockPolicy::Fail, wait_secs: 0, lease_secs: 60 };
+        let attempts = (0..8).map(|n| {
+            let service = FileLockService::new(dir.path().to_path_buf(), fs.clone(), format!("agent {}", n), settings);
+            tokio::spawn(async move { service.acquire("src/lib.rs").await.is_ok() })
+        });
+        let mut acquired = 0;
+        for attempt in attempts.collect::<Vec<_>>() {
+            acquired += attempt.await.unwrap() as usize;
+        }
+        assert_eq!(acquired, 1);
+        assert_eq!(std::fs::read_dir(dir.path().join(LOCKS_DIR)).unwrap().count(), 1);
+    }
+
+    #[cfg(all(unix, feature = "native"))]
+    #[tokio::test]
+    async fn locks_of_exited_processes_are_free() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        let alice = service(&fs, "alice", LockPolicy::Fail);
+        let mut lock = alice.acquire("src/lib.rs").await.unwrap();
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        lock.owner = child.id();
+        child.wait().unwrap();
+        fs.insert(alice.lock_path("src/lib.rs"), serde_json::to_vec(&lock).unwrap());
+
+        let bob = service(&fs, "bob", LockPolicy::Fail);
+        let taken = bob.acquire("src/lib.rs").await.unwrap();
+        assert_eq!((taken.holder.as_str(), taken.stolen_from), ("bob", None));
+        assert_eq!(fs.paths().len(), 1);
+    }
+
+    #[test]
+    fn renders_tree_with_lock_holders() {
+        let now = Utc::now();
+        let lock = FileLock {
+            path: "src/agent/tools.rs".to_string(),
+            holder: "agent 42".to_string(),
+            acquired_at: now,
+            expires_at: now + ChronoDuration::minutes(10),
+            stolen_from: None,
+            owner: 0,
+        };
+        let paths = ["src/lib.rs", "README.md", "src/agent/tools.rs", "src/agent/mod.rs"].map(String::from);
+        assert_eq!(
+            render_tree(&paths, &[lock], now),
+            "README.md\nsrc/\n  agent/\n    mod.rs\n    tools.rs  🔒 agent 42 (10m left)\n  lib.rs\n"
+        );
+    }
+}
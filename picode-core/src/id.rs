@@ -0,0 +1,318 @@
+//! Typed identifiers
+//!
+//! Sessions, panes, commands, events and agent runs are identified by an
+//! [`Id`] tagged with the kind of thing it names, so a pane id cannot be
+//! passed where a session id is expected. Ids are ULIDs: they start with
+//! their creation time, so they sort in creation order, and the ids one
+//! process creates are strictly increasing. They display as 26 Crockford
+//! base32 characters. The first 10 are the creation time, so listings show
+//! the [`short_forms`] of their ids, which grow past 8 characters where ids
+//! of the listing share them. Users refer to ids
+//! through [`IdPrefix`], which accepts any unambiguous prefix. Ids written
+//! as UUIDs by earlier versions still parse.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::sync::Mutex;
+use thiserror::Error;
+use ulid::Ulid;
+use uuid::Uuid;
+
+/// Characters of an id's short form
+pub const SHORT_LEN: usize = 8;
+
+/// Kind of thing an [`Id`] names
+pub trait IdKind: 'static {
+    /// Name used in messages, e.g. `session`
+    const NAME: &'static str;
+}
+
+macro_rules! id_kinds {
+    ($($(#[$doc:meta])* $kind:ident / $alias:ident => $name:literal;)*) => {$(
+        #[doc = concat!("Marker for ", $name, " ids")]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $kind {}
+
+        impl IdKind for $kind {
+            const NAME: &'static str = $name;
+        }
+
+        $(#[$doc])*
+        pub type $alias = Id<$kind>;
+    )*};
+}
+
+id_kinds! {
+    /// Unique identifier for a session
+    SessionKind / SessionId => "session";
+    /// Unique identifier for a pane
+    PaneKind / PaneId => "pane";
+    /// Unique identifier for a command
+    CommandKind / CommandId => "command";
+    /// Unique identifier for events
+    EventKind / EventId => "event";
+    /// Unique identifier for an agent run
+    AgentRunKind / AgentRunId => "agent run";
+    /// Unique identifier for a file held in the agent trash
+    TrashKind / TrashId => "trash entry";
+}
+
+/// Identifier of a `K`
+pub struct Id<K> {
+    ulid: Ulid,
+    kind: PhantomData<fn() -> K>,
+}
+
+/// Last id created by the process, so the next one sorts after it
+static LAST: Mutex<Ulid> = Mutex::new(Ulid::nil());
+
+impl<K: IdKind> Id<K> {
+    pub fn new() -> Self {
+        // The random part comes from a v4 UUID and the time from chrono,
+        // both of which also work in the browser
+        let millis = u64::try_from(chrono::Utc::now().timestamp_millis()).unwrap_or_default();
+        let mut ulid = Ulid::from_parts(millis, Uuid::new_v4().as_u128());
+        let mut last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+        if ulid <= *last {
+            ulid = last.increment().unwrap_or(ulid);
+        }
+        *last = ulid;
+        Self::from_ulid(ulid)
+    }
+
+    /// The same id for the same name, e.g. for well-known sessions
+    pub fn from_name(name: &str) -> Self {
+        Self::from_uuid(Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()))
+    }
+
+    pub fn from_ulid(ulid: Ulid) -> Self {
+        Self { ulid, kind: PhantomData }
+    }
+
+    /// An id stored as a UUID by an earlier version
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        Self::from_ulid(Ulid::from(uuid.as_u128()))
+    }
+
+    pub fn ulid(&self) -> Ulid {
+        self.ulid
+    }
+}
+
+impl<K: IdKind> Default for Id<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> Clone for Id<K> {
+    fn clone(&self) -> Self {
+        Self { ulid: self.ulid, kind: PhantomData }
+    }
+}
+
+impl<K> PartialEq for Id<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ulid == other.ulid
+    }
+}
+
+impl<K> Eq for Id<K> {}
+
+impl<K> PartialOrd for Id<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K> Ord for Id<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ulid.cmp(&other.ulid)
+    }
+}
+
+impl<K> Hash for Id<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ulid.hash(state);
+    }
+}
+
+impl<K: IdKind> fmt::Debug for Id<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Id<{}>({})", K::NAME, self.ulid)
+    }
+}
+
+impl<K> fmt::Display for Id<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ulid)
+    }
+}
+
+impl<K: IdKind> FromStr for Id<K> {
+    type Err = IdError;
+
+    /// A full ULID, or a UUID written by an earlier version
+    fn from_str(input: &str) -> Result<Self, IdError> {
+        let input = input.trim();
+        if let Ok(ulid) = Ulid::from_string(input) {
+            return Ok(Self::from_ulid(ulid));
+        }
+        match Uuid::parse_str(input) {
+            Ok(uuid) => Ok(Self::from_uuid(uuid)),
+            Err(_) => Err(IdError::Invalid { kind: K::NAME, input: input.to_string() }),
+        }
+    }
+}
+
+impl<K> Serialize for Id<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.ulid)
+    }
+}
+
+impl<'de, K: IdKind> Deserialize<'de> for Id<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let input = String::deserialize(deserializer)?;
+        input.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// An id as a user types it: the full id or an unambiguous prefix
+pub struct IdPrefix<K> {
+    /// Upper-cased, as ids display
+    prefix: String,
+    kind: PhantomData<fn() -> K>,
+}
+
+impl<K: IdKind> IdPrefix<K> {
+    /// The id among `ids` this refers to
+    pub fn resolve(&self, ids: impl IntoIterator<Item = Id<K>>) -> Result<Id<K>, IdError> {
+        let exact = self.prefix.parse::<Id<K>>().ok();
+        let mut matches: Vec<Id<K>> = ids
+            .into_iter()
+            .filter(|id| exact.as_ref() == Some(id) || id.to_string().starts_with(&self.prefix))
+            .collect();
+        matches.sort();
+        matches.dedup();
+        match matches.as_slice() {
+            [id] => Ok(id.clone()),
+            [] => Err(IdError::NotFound { kind: K::NAME, input: self.prefix.clone() }),
+            _ => Err(IdError::Ambiguous {
+                kind: K::NAME,
+                input: self.prefix.clone(),
+                matches: short_forms(&matches).join(", "),
+            }),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.prefix
+    }
+}
+
+impl<K> Clone for IdPrefix<K> {
+    fn clone(&self) -> Self {
+        Self { prefix: self.prefix.clone(), kind: PhantomData }
+    }
+}
+
+impl<K: IdKind> fmt::Debug for IdPrefix<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "IdPrefix<{}>({})", K::NAME, self.prefix)
+    }
+}
+
+impl<K> fmt::Display for IdPrefix<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.prefix)
+    }
+}
+
+impl<K: IdKind> FromStr for IdPrefix<K> {
+    type Err = IdError;
+
+    fn from_str(input: &str) -> Result<Self, IdError> {
+        let input = input.trim();
+        if input.is_empty() || !input.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(IdError::Invalid { kind: K::NAME, input: input.to_string() });
+        }
+        Ok(Self { prefix: input.to_ascii_uppercase(), kind: PhantomData })
+    }
+}
+
+/// The shortest prefix of each id, at least [`SHORT_LEN`] characters, that
+/// no other id of `ids` shares
+pub fn short_forms<K>(ids: &[Id<K>]) -> Vec<String> {
+    let full: Vec<String> = ids.iter().map(ToString::to_string).collect();
+    full.iter()
+        .map(|id| {
+            let len = full
+                .iter()
+                .filter(|other| *other != id)
+                .map(|other| id.bytes().zip(other.bytes()).take_while(|(a, b)| a == b).count() + 1)
+                .fold(SHORT_LEN, usize::max)
+                .min(id.len());
+            id[..len].to_string()
+        })
+        .collect()
+}
+
+/// Id parsing and lookup errors
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IdError {
+    #[error("'{input}' is not a valid {kind} id")]
+    Invalid { kind: &'static str, input: String },
+
+    #[error("no {kind} id starts with '{input}'")]
+    NotFound { kind: &'static str, input: String },
+
+    #[error("'{input}' matches several {kind} ids: {matches}")]
+    Ambiguous { kind: &'static str, input: String, matches: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_sort_by_creation_and_parse_back() {
+        let ids: Vec<SessionId> = (0..50).map(|_| SessionId::new()).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let id = ids[0].clone();
+        assert_eq!(id.to_string().len(), 26);
+        assert_eq!(id.to_string().to_lowercase().parse::<SessionId>(), Ok(id.clone()));
+        assert_eq!(serde_json::from_str::<SessionId>(&serde_json::to_string(&id).unwrap()).unwrap(), id);
+        assert_eq!(SessionId::from_name("main"), SessionId::from_name("main"));
+
+        // Ids stored as UUIDs keep their identity
+        let legacy = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let parsed: SessionId = serde_json::from_str(&format!("\"{}\"", legacy)).unwrap();
+        assert_eq!(parsed, SessionId::from_uuid(Uuid::parse_str(legacy).unwrap()));
+        assert!(matches!("not-an-id".parse::<PaneId>(), Err(IdError::Invalid { kind: "pane", .. })));
+    }
+
+    #[test]
+    fn short_forms_grow_until_unique_and_prefixes_resolve() {
+        let a = SessionId::from_ulid("01HZX3K5W7AAAAAAAAAAAAAAAA".parse().unwrap());
+        let b = SessionId::from_ulid("01HZX3K5W7BBBBBBBBBBBBBBBB".parse().unwrap());
+        let c = SessionId::from_ulid("01J0000000CCCCCCCCCCCCCCCC".parse().unwrap());
+        let ids = [a, b.clone(), c.clone()];
+        assert_eq!(short_forms(&ids), vec!["01HZX3K5W7A", "01HZX3K5W7B", "01J00000"]);
+
+        let resolve = |input: &str| input.parse::<IdPrefix<SessionKind>>().unwrap().resolve(ids.clone());
+        assert_eq!(resolve("01j0"), Ok(c));
+        assert_eq!(resolve(&b.to_string()), Ok(b));
+        assert_eq!(
+            resolve("01HZX3K5").unwrap_err().to_string(),
+            "'01HZX3K5' matches several session ids: 01HZX3K5W7A, 01HZX3K5W7B"
+        );
+        assert!(matches!(resolve("7Z"), Err(IdError::NotFound { .. })));
+        assert!("3f 2a".parse::<IdPrefix<SessionKind>>().is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Which workspace paths are ignored, and why
+//!
+//! Paths are matched with `.gitignore` semantics: the workspace's configured
+//! patterns apply first and are overridden by `.gitignore` and
+//! `.picodeignore` files, a deeper directory's file taking precedence over
+//! its parents' and `.picodeignore` over `.gitignore` in the same directory.
+//! `picode dev why-ignored <path>` prints the pattern that decided.
+
+use crate::workspace::WorkspaceError;
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use ignore::Match;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// PiCode-specific ignore file, read like `.gitignore`
+pub const PICODE_IGNORE_FILE: &str = ".picodeignore";
+
+/// Ignore files read in every directory, lowest precedence first
+const IGNORE_FILES: [&str; 2] = [".gitignore", PICODE_IGNORE_FILE];
+
+/// The pattern that decided whether a path is ignored
+#[derive(Debug, Clone, PartialEq)]
+pub struct IgnoreMatch {
+    /// The pattern as written, e.g. `!keep.log`
+    pub pattern: String,
+    /// Ignore file it came from; `None` for the configured patterns
+    pub source: Option<PathBuf>,
+    /// A `!` pattern that re-includes the path
+    pub whitelisted: bool,
+}
+
+impl IgnoreMatch {
+    fn from_glob(glob: &Glob) -> Self {
+        Self {
+            pattern: glob.original().to_string(),
+            source: glob.from().map(Path::to_path_buf),
+            whitelisted: glob.is_whitelist(),
+        }
+    }
+}
+
+impl fmt::Display for IgnoreMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.whitelisted { "kept by" } else { "ignored by" };
+        match &self.source {
+            Some(source) => write!(f, "{} `{}` in {}", verdict, self.pattern, source.display()),
+            None => write!(f, "{} configured pattern `{}`", verdict, self.pattern),
+        }
+    }
+}
+
+/// Ignore rules of a workspace; ignore files are read once per directory
+#[derive(Debug)]
+pub struct IgnoreRules {
+    root: PathBuf,
+    configured: Gitignore,
+    dirs: Mutex<HashMap<PathBuf, Arc<Gitignore>>>,
+}
+
+impl IgnoreRules {
+    pub fn new(root: impl Into<PathBuf>, patterns: &[String]) -> Result<Self, WorkspaceError> {
+        let root = root.into();
+        let invalid = |e: ignore::Error| WorkspaceError::InvalidConfig(format!("ignore pattern: {}", e));
+        let mut builder = GitignoreBuilder::new(&root);
+        for pattern in patterns {
+            builder.add_line(None, pattern).map_err(invalid)?;
+        }
+        Ok(Self {
+            configured: builder.build().map_err(invalid)?,
+            root,
+            dirs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The pattern deciding whether `path` (absolute or relative to the
+    /// root) is ignored, or `None` when no pattern matches it
+    pub fn explain(&self, path: &Path, is_dir: bool) -> Option<IgnoreMatch> {
+        // Components drop a trailing separator, which the matcher would keep
+        let relative = crate::paths::strip_root(path, &self.root).unwrap_or(path).components().as_path();
+        if relative.as_os_str().is_empty() || relative.has_root() {
+            return None;
+        }
+
+        // Ignore files of the directories containing the path, deepest first
+        for dir in relative.ancestors().skip(1) {
+            let rules = self.dir_rules(dir);
+            let below = relative.strip_prefix(dir).unwrap_or(relative);
+            match rules.matched_path_or_any_parents(below, is_dir) {
+                Match::None => {}
+                Match::Ignore(glob) | Match::Whitelist(glob) => return Some(IgnoreMatch::from_glob(glob)),
+            }
+        }
+        match self.configured.matched_path_or_any_parents(relative, is_dir) {
+            Match::None => None,
+            Match::Ignore(glob) | Match::Whitelist(glob) => Some(IgnoreMatch::from_glob(glob)),
+        }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.explain(path, is_dir).is_some_and(|m| !m.whitelisted)
+    }
+
+    /// Rules from the ignore files in `dir` (relative to the root)
+    fn dir_rules(&self, dir: &Path) -> Arc<Gitignore> {
+        let dir = self.root.join(dir);
+        let mut dirs = self.dirs.lock().unwrap_or_else(|e| e.into_inner());
+        dirs.entry(dir.clone())
+            .or_insert_with(|| {
+                let mut builder = GitignoreBuilder::new(&dir);
+                for name in IGNORE_FILES {
+                    let file = dir.join(name);
+                    if file.is_file() {
+                        if let Some(e) = builder.add(&file) {
+                            tracing::warn!("Skipping part of {}: {}", file.display(), e);
+                        }
+                    }
+                }
+                Arc::new(builder.build().unwrap_or_else(|_| Gitignore::empty()))
+            })
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(root: &Path) -> IgnoreRules {
+        let patterns = ["target/", "node_modules/", "*.log", ".DS_Store"].map(String::from);
+        IgnoreRules::new(root, &patterns).unwrap()
+    }
+
+    #[test]
+    fn configured_patterns_match_names_not_substrings() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = rules(dir.path());
+
+        assert!(rules.is_ignored(Path::new("target"), true));
+        assert!(rules.is_ignored(Path::new("crates/app/target/debug/app"), false));
+        assert!(rules.is_ignored(Path::new("logs/server.log"), false));
+        assert!(!rules.is_ignored(Path::new("src/target_utils.rs"), false));
+        // `target/` only matches directories
+        assert!(!rules.is_ignored(Path::new("docs/target"), false));
+        assert!(!rules.is_ignored(Path::new("src/logger.rs"), false));
+        assert!(!rules.is_ignored(Path::new("node_modules_docs.md"), false));
+    }
+
+    #[test]
+    fn nested_ignore_files_override_their_parents() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "/build\n*.gen.rs\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("api")).unwrap();
+        std::fs::write(dir.path().join("api/.gitignore"), "!schema.gen.rs\n").unwrap();
+        std::fs::write(dir.path().join("api/.picodeignore"), "fixtures/\n!debug.log\n").unwrap();
+        let rules = rules(dir.path());
+
+        // Anchored patterns only match at their own directory
+        assert!(rules.is_ignored(&dir.path().join("build"), true));
+        assert!(!rules.is_ignored(Path::new("api/build"), true));
+
+        assert!(rules.is_ignored(Path::new("api/client.gen.rs"), false));
+        let kept = rules.explain(Path::new("api/schema.gen.rs"), false).unwrap();
+        assert!(kept.whitelisted);
+        assert_eq!(kept.source, Some(dir.path().join("api/.gitignore")));
+
+        assert!(rules.is_ignored(Path::new("api/fixtures/user.json"), false));
+        assert!(!rules.is_ignored(Path::new("api/debug.log"), false));
+        assert_eq!(
+            rules.explain(Path::new("api/trace.log"), false).unwrap().to_string(),
+            "ignored by configured pattern `*.log`"
+        );
+        assert_eq!(rules.explain(Path::new("src/main.rs"), false), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Symbol index for workspaces
+//!
+//! A lightweight, language-agnostic index of definitions (functions, types,
+//! classes, ...) found by scanning source lines. It is kept up to date
+//! incrementally: re-indexing a file replaces only that file's symbols.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Keywords introducing a definition, followed by the symbol name
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn", "struct", "enum", "trait", "type", "const", "static", "mod", "impl", "class", "def",
+    "function", "interface", "func",
+];
+
+/// Modifiers allowed before a definition keyword
+const MODIFIERS: &[&str] = &["async", "unsafe", "export", "default", "abstract", "static", "extern"];
+
+/// Where a symbol is defined
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolLocation {
+    pub path: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    /// Trimmed definition line
+    pub signature: String,
+}
+
+/// Index from symbol name to definition locations
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    symbols: BTreeMap<String, Vec<SymbolLocation>>,
+    /// Symbol names defined by each file, for incremental updates
+    files: HashMap<PathBuf, Vec<String>>,
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index (or re-index) a file's content
+    pub fn index_file(&mut self, path: &Path, content: &str) {
+        self.remove_file(path);
+
+        let mut names = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            if let Some(name) = definition_name(line) {
+                self.symbols.entry(name.to_string()).or_default().push(SymbolLocation {
+                    path: path.to_path_buf(),
+                    line: i + 1,
+                    signature: line.trim().to_string(),
+                });
+                names.push(name.to_string());
+            }
+        }
+
+        if !names.is_empty() {
+            self.files.insert(path.to_path_buf(), names);
+        }
+    }
+
+    /// Drop every symbol defined by a file
+    pub fn remove_file(&mut self, path: &Path) {
+        let Some(names) = self.files.remove(path) else {
+            return;
+        };
+
+        for name in names {
+            if let Some(locations) = self.symbols.get_mut(&name) {
+                locations.retain(|l| l.path != path);
+                if locations.is_empty() {
+                    self.symbols.remove(&name);
+                }
+            }
+        }
+    }
+
+    /// Definitions of a symbol
+    pub fn lookup(&self, name: &str) -> &[SymbolLocation] {
+        self.symbols.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Symbols whose name starts with a prefix
+    pub fn search_prefix(&self, prefix: &str) -> Vec<(&str, &SymbolLocation)> {
+        self.symbols
+            .range(prefix.to_string()..)
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .flat_map(|(name, locations)| locations.iter().map(move |l| (name.as_str(), l)))
+            .collect()
+    }
+
+    /// Every indexed symbol name, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.symbols.keys().map(String::as_str)
+    }
+
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.values().map(Vec::len).sum()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+}
+
+/// Name of the symbol defined on a line, if the line is a definition
+pub fn definition_name(line: &str) -> Option<&str> {
+    let mut tokens = line.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if DEFINITION_KEYWORDS.contains(&token) {
+            let next = tokens.next()?;
+            let name = next
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or("");
+            return (!name.is_empty()).then_some(name);
+        }
+        if !(token.starts_with("pub") || MODIFIERS.contains(&token)) {
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn definition_names() {
+        assert_eq!(definition_name("pub(crate) async fn load(path: &Path)"), Some("load"));
+        assert_eq!(definition_name("class Parser:"), Some("Parser"));
+        assert_eq!(definition_name("export default function render() {"), Some("render"));
+        assert_eq!(definition_name("let fn_ptr = foo;"), None);
+        assert_eq!(definition_name("    map: HashMap<String, String>,"), None);
+    }
+
+    #[test]
+    fn incremental_reindexing() {
+        let mut index = SymbolIndex::new();
+        index.index_file(Path::new("src/a.rs"), "pub struct Config;\nfn load() {}\n");
+        index.index_file(Path::new("src/b.rs"), "fn load() {}\n");
+
+        assert_eq!(index.lookup("load").len(), 2);
+        assert_eq!(index.lookup("Config")[0].line, 1);
+
+        index.index_file(Path::new("src/a.rs"), "pub struct Settings;\n");
+        assert!(index.lookup("Config").is_empty());
+        assert_eq!(index.lookup("load").len(), 1);
+        assert_eq!(index.search_prefix("Set").len(), 1);
+        assert_eq!((index.file_count(), index.symbol_count()), (2, 2));
+    }
+}
@@ -0,0 +1,474 @@
+//! Host-agnostic IO for PiCode core
+//!
+//! Core logic reaches the file system and child processes through the
+//! `FileSystem` and `ProcessRunner` traits instead of calling `tokio::fs` or
+//! `tokio::process` directly. Native builds use the real OS; WASM builds (e.g.
+//! PiCode as a WASM MCP tool) use an in-memory file system and a runner that
+//! reports processes as unsupported.
+
+use crate::large_file::{LineRange, Window};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+/// Output of a finished process
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessOutput {
+    pub exit_code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl ProcessOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// File system operations needed by core components
+#[async_trait]
+pub trait FileSystem: Send + Sync + std::fmt::Debug {
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Write a file and wait until it is on disk, where the host can tell
+    async fn write_synced(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.write(path, contents).await
+    }
+
+    /// Create a file with all of its contents at once, failing with
+    /// `AlreadyExists` if the path is taken
+    async fn create_new(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Move a file, replacing whatever is at `to`
+    async fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// List the entries directly inside a directory
+    async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    async fn exists(&self, path: &Path) -> bool;
+
+    /// Last modification time, when the host tracks one
+    async fn modified(&self, _path: &Path) -> io::Result<Option<SystemTime>> {
+        Ok(None)
+    }
+
+    async fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        let bytes = self.read(path).await?;
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Size of a file in bytes
+    async fn file_size(&self, path: &Path) -> io::Result<u64> {
+        Ok(self.read(path).await?.len() as u64)
+    }
+
+    /// Lines `range` of a file; hosts that can avoid reading the whole file
+    /// for it do
+    async fn read_window(&self, path: &Path, range: LineRange) -> io::Result<Window> {
+        Ok(crate::large_file::window(&self.read(path).await?, range))
+    }
+}
+
+/// Child process execution
+#[async_trait]
+pub trait ProcessRunner: Send + Sync + std::fmt::Debug {
+    async fn run(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&Path>,
+        env: &[(String, String)],
+        stdin: Option<&[u8]>,
+    ) -> io::Result<ProcessOutput>;
+}
+
+/// File system backed by the operating system
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeFileSystem;
+
+#[cfg(feature = "native")]
+#[async_trait]
+impl FileSystem for NativeFileSystem {
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        tokio::fs::read(path).await
+    }
+
+    async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        tokio::fs::write(path, contents).await
+    }
+
+    async fn write_synced(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+        let mut file = tokio::fs::File::create(path).await?;
+        file.write_all(contents).await?;
+        file.sync_all().await
+    }
+
+    async fn create_new(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        // Linking a finished temporary file is atomic and fails when the
+        // path exists, so nobody sees the file half-written
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(format!(".{}.tmp", uuid::Uuid::new_v4().simple()));
+        let temp = PathBuf::from(temp);
+        tokio::fs::write(&temp, contents).await?;
+        let linked = tokio::fs::hard_link(&temp, path).await;
+        let _ = tokio::fs::remove_file(&temp).await;
+        linked
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        tokio::fs::rename(from, to).await
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        tokio::fs::create_dir_all(path).await
+    }
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()> {
+        tokio::fs::remove_file(path).await
+    }
+
+    async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = Vec::new();
+        let mut dir = tokio::fs::read_dir(path).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            entries.push(entry.path());
+        }
+        Ok(entries)
+    }
+
+    async fn exists(&self, path: &Path) -> bool {
+        tokio::fs::metadata(path).await.is_ok()
+    }
+
+    async fn modified(&self, path: &Path) -> io::Result<Option<SystemTime>> {
+        Ok(Some(tokio::fs::metadata(path).await?.modified()?))
+    }
+
+    async fn file_size(&self, path: &Path) -> io::Result<u64> {
+        Ok(tokio::fs::metadata(path).await?.len())
+    }
+
+    async fn read_window(&self, path: &Path, range: LineRange) -> io::Result<Window> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || Ok(crate::large_file::MappedFile::open(&path)?.window(range)))
+            .await
+            .map_err(io::Error::other)?
+    }
+}
+
+/// Process runner spawning real OS processes
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeProcessRunner;
+
+#[cfg(feature = "native")]
+#[async_trait]
+impl ProcessRunner for NativeProcessRunner {
+    async fn run(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&Path>,
+        env: &[(String, String)],
+        stdin: Option<&[u8]>,
+    ) -> io::Result<ProcessOutput> {
+        let mut cmd = tokio::process::Command::new(program);
+        cmd.envs(env.iter().map(|(k, v)| (k, v)));
+        spawn(cmd, args, working_dir, stdin).await
+    }
+}
+
+/// Process runner spawning real OS processes with a fixed environment
+/// instead of PiCode's own, e.g. one filtered by
+/// [`InheritRules`](crate::pane::InheritRules)
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Default)]
+pub struct ScopedProcessRunner {
+    env: Vec<(String, String)>,
+}
+
+#[cfg(feature = "native")]
+impl ScopedProcessRunner {
+    pub fn new(env: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self { env: env.into_iter().collect() }
+    }
+}
+
+#[cfg(feature = "native")]
+#[async_trait]
+impl ProcessRunner for ScopedProcessRunner {
+    async fn run(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&Path>,
+        env: &[(String, String)],
+        stdin: Option<&[u8]>,
+    ) -> io::Result<ProcessOutput> {
+        let mut cmd = tokio::process::Command::new(program);
+        cmd.env_clear().envs(self.env.iter().chain(env).map(|(k, v)| (k, v)));
+        spawn(cmd, args, working_dir, stdin).await
+    }
+}
+
+#[cfg(feature = "native")]
+async fn spawn(
+    mut cmd: tokio::process::Command,
+    args: &[String],
+    working_dir: Option<&Path>,
+    stdin: Option<&[u8]>,
+) -> io::Result<ProcessOutput> {
+    use std::process::Stdio;
+    use tokio::io::AsyncWriteExt;
+
+    cmd.args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+    if let Some(dir) = working_dir {
+        cmd.current_dir(dir);
+    }
+
+    let mut child = cmd.spawn()?;
+    if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(data).await?;
+    }
+
+    let output = child.wait_with_output().await?;
+    Ok(ProcessOutput {
+        exit_code: output.status.code(),
+        stdout: output.stdout,
+        stderr: output.stderr,
+    })
+}
+
+/// Virtual in-memory file system (used by WASM builds and tests)
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: RwLock<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Synchronously add a file, e.g. to seed the virtual file system
+    pub fn insert(&self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) {
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        files.insert(path.into(), contents.into());
+    }
+
+    /// Synchronously read a file
+    pub fn get(&self, path: &Path) -> Option<Vec<u8>> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        files.get(path).cloned()
+    }
+
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        files.keys().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl FileSystem for MemoryFileSystem {
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.get(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.display().to_string()))
+    }
+
+    async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.insert(path, contents);
+        Ok(())
+    }
+
+    async fn create_new(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        if files.contains_key(path) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, path.display().to_string()));
+        }
+        files.insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        let contents = files
+            .remove(from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, from.display().to_string()))?;
+        files.insert(to.to_path_buf(), contents);
+        Ok(())
+    }
+
+    async fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        // Directories are implicit in the virtual file system
+        Ok(())
+    }
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        files
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.display().to_string()))
+    }
+
+    async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        let mut entries: Vec<PathBuf> = files
+            .keys()
+            .filter_map(|file| {
+                let relative = file.strip_prefix(path).ok()?;
+                let first = relative.components().next()?;
+                Some(path.join(first))
+            })
+            .collect();
+        entries.dedup();
+        Ok(entries)
+    }
+
+    async fn exists(&self, path: &Path) -> bool {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        files.contains_key(path) || files.keys().any(|file| file.starts_with(path))
+    }
+}
+
+/// Process runner for hosts without process support (e.g. WASM)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProcessRunner;
+
+#[async_trait]
+impl ProcessRunner for NoProcessRunner {
+    async fn run(
+        &self,
+        program: &str,
+        _args: &[String],
+        _working_dir: Option<&Path>,
+        _env: &[(String, String)],
+        _stdin: Option<&[u8]>,
+    ) -> io::Result<ProcessOutput> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("cannot run '{}': processes are not supported on this host", program),
+        ))
+    }
+}
+
+/// Whether process `pid` is still running. Hosts that cannot tell only
+/// know about the current process.
+pub fn process_alive(pid: u32) -> bool {
+    if pid == std::process::id() {
+        return true;
+    }
+    if pid == 0 {
+        return false;
+    }
+    #[cfg(all(unix, feature = "native"))]
+    {
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        // Signal 0 only checks the process exists; EPERM means it does but
+        // belongs to another user
+        // SAFETY: kill with signal 0 sends nothing
+        let found = unsafe { libc::kill(pid, 0) } == 0;
+        found || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    #[cfg(not(all(unix, feature = "native")))]
+    {
+        false
+    }
+}
+
+/// Default file system for the current build
+pub fn default_file_system() -> std::sync::Arc<dyn FileSystem> {
+    #[cfg(feature = "native")]
+    {
+        std::sync::Arc::new(NativeFileSystem)
+    }
+
+    #[cfg(not(feature = "native"))]
+    {
+        std::sync::Arc::new(MemoryFileSystem::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_file_system_roundtrip() {
+        let fs = MemoryFileSystem::new();
+        fs.write(Path::new("/ws/src/lib.rs"), b"pub fn a() {}").await.unwrap();
+        fs.write(Path::new("/ws/README.md"), b"# ws").await.unwrap();
+
+        assert_eq!(fs.read_to_string(Path::new("/ws/README.md")).await.unwrap(), "# ws");
+        assert!(fs.exists(Path::new("/ws/src")).await);
+
+        let entries = fs.read_dir(Path::new("/ws")).await.unwrap();
+        assert_eq!(entries, vec![PathBuf::from("/ws/README.md"), PathBuf::from("/ws/src")]);
+
+        fs.remove_file(Path::new("/ws/README.md")).await.unwrap();
+        assert!(fs.read(Path::new("/ws/README.md")).await.is_err());
+
+        let err = fs.create_new(Path::new("/ws/src/lib.rs"), b"").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        fs.rename(Path::new("/ws/src/lib.rs"), Path::new("/ws/src/main.rs")).await.unwrap();
+        fs.create_new(Path::new("/ws/src/lib.rs"), b"pub fn b() {}").await.unwrap();
+        assert_eq!(fs.read_to_string(Path::new("/ws/src/main.rs")).await.unwrap(), "pub fn a() {}");
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn native_create_new_does_not_replace_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lock.json");
+        NativeFileSystem.create_new(&path, b"first").await.unwrap();
+        let err = NativeFileSystem.create_new(&path, b"second").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(&path).unwrap(), b"first");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn no_process_runner_is_unsupported() {
+        let err = NoProcessRunner.run("ls", &[], None, &[], None).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[cfg(all(feature = "native", unix))]
+    #[test]
+    fn finished_processes_are_not_alive() {
+        assert!(process_alive(std::process::id()));
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(!process_alive(pid));
+        assert!(!process_alive(0));
+    }
+
+    #[cfg(all(feature = "native", unix))]
+    #[tokio::test]
+    async fn native_process_runner_pipes_stdin() {
+        let output = NativeProcessRunner
+            .run("cat", &[], None, &[], Some(b"hello"))
+            .await
+            .unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout, b"hello");
+    }
+}
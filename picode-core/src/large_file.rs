@@ -0,0 +1,242 @@
+//! Large files
+//!
+//! Files over [`LargeFileSettings::threshold`] are not read whole. On native
+//! builds they are memory-mapped ([`MappedFile`]), so binary detection and
+//! line windows only touch the pages they need. Editor panes show them one
+//! [`Window`] of lines at a time, read-only; context pinned from them is
+//! restricted to a [`LineRange`] or a symbol's definition; and the agent's
+//! `read_file` returns their first window with a warning unless it asks for
+//! a range.
+
+use crate::index::definition_name;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Default size from which a file is large
+pub const DEFAULT_THRESHOLD: u64 = 4 * 1024 * 1024;
+
+/// Bytes looked at to tell binary files from text
+const BINARY_SNIFF_BYTES: usize = 512;
+
+/// Large file handling (`[workspace.large_files]`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LargeFileSettings {
+    /// Size in bytes from which a file is treated as large
+    pub threshold: u64,
+    /// Lines shown or sent at once from a large file
+    pub window_lines: usize,
+}
+
+impl Default for LargeFileSettings {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            window_lines: 400,
+        }
+    }
+}
+
+impl LargeFileSettings {
+    pub fn is_large(&self, size: u64) -> bool {
+        size > self.threshold
+    }
+
+    /// The window starting at line `start`
+    pub fn window_at(&self, start: usize) -> LineRange {
+        LineRange::new(start, start.max(1) + self.window_lines.max(1) - 1)
+    }
+}
+
+/// A line range that could not be parsed
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid line range '{0}'; expected START-END, e.g. 120-180")]
+pub struct InvalidRange(String);
+
+/// Inclusive range of 1-based line numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LineRange {
+    pub fn new(start: usize, end: usize) -> Self {
+        let start = start.max(1);
+        Self { start, end: end.max(start) }
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+}
+
+impl FromStr for LineRange {
+    type Err = InvalidRange;
+
+    /// `START-END` or a single line
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidRange(s.to_string());
+        let (start, end) = s.split_once('-').unwrap_or((s, s));
+        let start: usize = start.trim().parse().map_err(|_| invalid())?;
+        let end: usize = end.trim().parse().map_err(|_| invalid())?;
+        if start == 0 || end < start {
+            return Err(invalid());
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl fmt::Display for LineRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Some lines of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    /// The lines read: the requested range cut at the end of the file, so
+    /// `end < start` when it starts past the end
+    pub range: LineRange,
+    /// Lines in the whole file
+    pub total_lines: usize,
+    pub text: String,
+}
+
+impl Window {
+    /// Whether the window holds the whole file
+    pub fn is_complete(&self) -> bool {
+        self.range.start == 1 && self.range.end >= self.total_lines
+    }
+}
+
+/// The rule for binary content: a null byte near the start
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+/// Lines of `bytes`, without their line feeds
+fn lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    // An empty file has no lines rather than one empty line
+    (!bytes.is_empty()).then(|| bytes.split(|byte| *byte == b'\n')).into_iter().flatten()
+}
+
+/// Lines `range` of `bytes`; invalid UTF-8 is replaced
+pub fn window(bytes: &[u8], range: LineRange) -> Window {
+    let mut text = Vec::new();
+    let mut total_lines = 0;
+    for (index, line) in lines(bytes).enumerate() {
+        total_lines = index + 1;
+        if range.contains(total_lines) {
+            text.extend_from_slice(line);
+            text.push(b'\n');
+        }
+    }
+    Window {
+        range: LineRange { start: range.start, end: range.end.min(total_lines) },
+        total_lines,
+        text: String::from_utf8_lossy(&text).into_owned(),
+    }
+}
+
+/// Lines of the definition of `symbol` in `bytes`: from its definition line
+/// up to the next definition that is not nested in it, at most `max_lines`
+pub fn find_symbol(bytes: &[u8], symbol: &str, max_lines: usize) -> Option<LineRange> {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut found: Option<(usize, usize)> = None;
+    let mut last = 0;
+    for (index, line) in lines(bytes).enumerate() {
+        let number = index + 1;
+        let Ok(line) = std::str::from_utf8(line) else {
+            continue;
+        };
+        match found {
+            None if definition_name(line) == Some(symbol) => found = Some((number, indent(line))),
+            Some((start, depth)) => {
+                let next_definition = definition_name(line).is_some() && indent(line) <= depth;
+                if next_definition || number - start >= max_lines.max(1) {
+                    return Some(LineRange::new(start, number - 1));
+                }
+            }
+            None => {}
+        }
+        last = number;
+    }
+    found.map(|(start, _)| LineRange::new(start, last))
+}
+
+/// A file mapped into memory, read-only
+#[cfg(feature = "native")]
+pub struct MappedFile {
+    /// `None` for an empty file, which cannot be mapped
+    map: Option<memmap2::Mmap>,
+}
+
+#[cfg(feature = "native")]
+impl MappedFile {
+    pub fn open(path: &std::path::Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(Self { map: None });
+        }
+        // SAFETY: the mapping is read-only and never handed out beyond
+        // borrows of `self`; as with any mapped read, a file truncated by
+        // another process meanwhile can still fault
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self { map: Some(map) })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+
+    pub fn window(&self, range: LineRange) -> Window {
+        window(self.bytes(), range)
+    }
+
+    pub fn find_symbol(&self, symbol: &str, max_lines: usize) -> Option<LineRange> {
+        find_symbol(self.bytes(), symbol, max_lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_and_symbols() {
+        let text = "mod a;\n\npub struct Parser {\n    pos: usize,\n}\n\nimpl Parser {\n    fn next(&mut self) {}\n}\nfn main() {}\n";
+        let window = window(text.as_bytes(), "3-5".parse().unwrap());
+        assert_eq!(window.text, "pub struct Parser {\n    pos: usize,\n}\n");
+        assert_eq!((window.total_lines, window.is_complete()), (10, false));
+        assert_eq!(super::window(text.as_bytes(), LineRange::new(9, 40)).range, LineRange::new(9, 10));
+        assert!(super::window(b"", LineRange::new(1, 5)).is_complete());
+
+        assert_eq!(find_symbol(text.as_bytes(), "Parser", 100), Some(LineRange::new(3, 6)));
+        assert_eq!(find_symbol(text.as_bytes(), "next", 100), Some(LineRange::new(8, 9)));
+        assert_eq!(find_symbol(text.as_bytes(), "main", 100), Some(LineRange::new(10, 10)));
+        assert_eq!(find_symbol(text.as_bytes(), "Parser", 2), Some(LineRange::new(3, 4)));
+        assert_eq!(find_symbol(text.as_bytes(), "missing", 100), None);
+
+        assert_eq!("7".parse::<LineRange>(), Ok(LineRange::new(7, 7)));
+        assert!("9-3".parse::<LineRange>().is_err() && "0-3".parse::<LineRange>().is_err());
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn mapped_files_read_windows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        let content: String = (1..=1000).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(&path, content).unwrap();
+        let mapped = MappedFile::open(&path).unwrap();
+        let window = mapped.window(LargeFileSettings::default().window_at(999));
+        assert_eq!((window.text.as_str(), window.total_lines), ("line 999\nline 1000\n", 1000));
+        std::fs::write(&path, "").unwrap();
+        assert!(MappedFile::open(&path).unwrap().bytes().is_empty());
+    }
+}
@@ -2,19 +2,81 @@
 
 // use chrono::{DateTime, Utc}; // Unused import
 
+pub mod id;
 pub mod session;
+#[cfg(feature = "native")]
 pub mod workspace;
+#[cfg(feature = "native")]
+pub mod ignore_rules;
+#[cfg(feature = "native")]
+pub mod workspace_stats;
 pub mod pane;
 pub mod command;
 pub mod event;
 pub mod traits;
+pub mod agent;
+pub mod annotation;
+pub mod ansi;
+pub mod bookmark;
+pub mod io;
+pub mod paths;
+pub mod conversation;
+pub mod conversation_archive;
+pub mod redact;
+pub mod summarize;
+pub mod compress;
+pub mod index;
+pub mod large_file;
+pub mod system_prompt;
+pub mod content_cache;
+pub mod context_delta;
+pub mod context_inspector;
+pub mod context_watch;
+pub mod editor;
+pub mod file_locks;
+pub mod recovery;
+pub mod write_coalescer;
+pub mod word_diff;
+pub mod todos;
+pub mod composer;
 
+pub use id::{short_forms, Id, IdError, IdKind, IdPrefix};
 pub use session::{Session, SessionId, SessionManager};
+#[cfg(feature = "native")]
 pub use workspace::{Workspace, WorkspaceConfig};
-pub use pane::{Pane, PaneId, PaneType};
+#[cfg(feature = "native")]
+pub use ignore_rules::{IgnoreMatch, IgnoreRules};
+#[cfg(feature = "native")]
+pub use workspace_stats::{FrameworkMatch, LanguageStats, WorkspaceStats};
+pub use pane::{CwdRule, InheritRules, Pane, PaneId, PaneManager, PaneType};
 pub use command::{Command, CommandResult, CommandStatus, CommandBuilder};
-pub use event::{Event, EventHandler, EventBus};
+pub use event::{Event, EventHandler, EventBus, EventBusMetrics};
 pub use traits::*;
+pub use agent::{AgentRunId, AgentTrace, ToolCache};
+pub use annotation::{Annotations, Note};
+pub use bookmark::{Bookmark, BookmarkStore};
+pub use conversation::{ConversationLog, ConversationMessage, TurnContext};
+pub use conversation_archive::{ArchiveSettings, CompactReport};
+pub use redact::Redactor;
+pub use summarize::{CommandOutputSummarizer, OutputSummary};
+pub use compress::{CompressionLevel, Compressed, PromptCompressor};
+pub use index::{SymbolIndex, SymbolLocation};
+pub use large_file::{LargeFileSettings, LineRange, Window};
+pub use system_prompt::{PromptLayer, PromptLayerKind, SystemPrompt};
+pub use content_cache::{CacheStats, CachedFile, ContentCache};
+pub use context_delta::{ContextStats, ContextTracker, ContextUpdate, FileDelta};
+pub use context_inspector::{ContextBreakdown, ContextItem, ContextItemKind};
+pub use context_watch::{ContextWatcher, WatchMode, WatchSettings, WatchStatus};
+pub use editor::{EditorError, FileEdit, ModalEditor};
+pub use file_locks::{FileLock, FileLockService, LockPolicy, LockSettings};
+pub use recovery::RecoveryReport;
+pub use write_coalescer::{CoalescingFileSystem, CoalescingStats};
+pub use word_diff::WordDiff;
+pub use todos::{TodoItem, TodoKind, TodoList, TodoStatus};
+pub use composer::Composer;
+pub use io::{FileSystem, MemoryFileSystem, NoProcessRunner, ProcessOutput, ProcessRunner};
+#[cfg(feature = "native")]
+pub use io::{NativeFileSystem, NativeProcessRunner, ScopedProcessRunner};
 
 /// Core result type
 pub type Result<T> = std::result::Result<T, CoreError>;
@@ -27,6 +89,7 @@ pub enum CoreError {
     #[error("Session error: {0}")]
     Session(#[from] session::SessionError),
     
+    #[cfg(feature = "native")]
     #[error("Workspace error: {0}")]
     Workspace(#[from] workspace::WorkspaceError),
     
@@ -39,6 +102,36 @@ pub enum CoreError {
     #[error("Event error: {0}")]
     Event(#[from] event::EventError),
     
+    #[error("Bookmark error: {0}")]
+    Bookmark(#[from] bookmark::BookmarkError),
+    
+    #[error("Annotation error: {0}")]
+    Annotation(#[from] annotation::AnnotationError),
+    
+    #[error("Agent report error: {0}")]
+    Report(#[from] agent::ReportError),
+    
+    #[error("Trash error: {0}")]
+    Trash(#[from] agent::TrashError),
+    
+    #[error("Guardrail error: {0}")]
+    Guardrail(#[from] agent::GuardrailError),
+    
+    #[error("Permission error: {0}")]
+    Permission(#[from] agent::PermissionError),
+    
+    #[error("Editor error: {0}")]
+    Editor(#[from] editor::EditorError),
+    
+    #[error("File lock error: {0}")]
+    Lock(#[from] file_locks::LockError),
+    
+    #[error("Redaction error: {0}")]
+    Redact(#[from] redact::RedactError),
+    
+    #[error("TODO list error: {0}")]
+    Todo(#[from] todos::TodoError),
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
@@ -1,28 +1,20 @@
 //! Pane management for PiCode workspace
 //! 
 //! Inspired by Zellij's pane system with AI-focused enhancements
+//!
+//! What a new terminal pane inherits is decided by [`InheritRules`]: it
+//! starts in the workspace root, the focused pane's directory or a fixed
+//! path, and its commands see only the parent environment variables the
+//! allowlist lets through, so tokens and keys exported in the user's shell
+//! do not reach commands run on the agent's behalf.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
-use uuid::Uuid;
 
-/// Unique identifier for a pane
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct PaneId(pub Uuid);
-
-impl PaneId {
-    pub fn new() -> Self {
-        Self(Uuid::new_v4())
-    }
-}
-
-impl std::fmt::Display for PaneId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
+pub use crate::id::PaneId;
 
 /// Types of panes available in PiCode
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -64,6 +56,10 @@ pub struct Pane {
     pub size: PaneSize,
     pub position: PanePosition,
     pub metadata: HashMap<String, String>,
+    /// The whole environment of the pane's commands; `None` passes the
+    /// parent's on unchanged
+    #[serde(default)]
+    pub env: Option<BTreeMap<String, String>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub last_activity: chrono::DateTime<chrono::Utc>,
 }
@@ -117,6 +113,7 @@ impl Pane {
             size: PaneSize::default(),
             position: PanePosition::default(),
             metadata: HashMap::new(),
+            env: None,
             created_at: now,
             last_activity: now,
         }
@@ -136,6 +133,7 @@ impl Pane {
             size: PaneSize::default(),
             position: PanePosition::default(),
             metadata: HashMap::new(),
+            env: None,
             created_at: now,
             last_activity: now,
         }
@@ -156,6 +154,7 @@ impl Pane {
             size: PaneSize::default(),
             position: PanePosition::default(),
             metadata: HashMap::new(),
+            env: None,
             created_at: now,
             last_activity: now,
         }
@@ -171,11 +170,18 @@ impl Pane {
             size: PaneSize::default(),
             position: PanePosition::default(),
             metadata: HashMap::new(),
+            env: None,
             created_at: now,
             last_activity: now,
         }
     }
     
+    /// Run the pane's commands with exactly `env`
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.env = Some(env);
+        self
+    }
+    
     pub fn activate(&mut self) {
         self.is_active = true;
         self.touch();
@@ -228,6 +234,38 @@ impl Pane {
         }
     }
     
+    /// Provider and model of an LLM chat pane
+    pub fn llm_model(&self) -> Option<(&str, &str)> {
+        match &self.pane_type {
+            PaneType::LLMChat { provider, model, .. } => Some((provider.as_str(), model.as_str())),
+            _ => None,
+        }
+    }
+    
+    /// Switch an LLM chat pane to another provider/model, keeping its
+    /// conversation; returns the previous provider and model
+    pub fn switch_model(&mut self, provider: String, model: String) -> Result<(String, String), PaneError> {
+        match &mut self.pane_type {
+            PaneType::LLMChat { provider: current_provider, model: current_model, .. } => {
+                let previous = (
+                    std::mem::replace(current_provider, provider),
+                    std::mem::replace(current_model, model),
+                );
+                self.touch();
+                Ok(previous)
+            }
+            _ => Err(PaneError::InvalidType(format!("cannot switch the model of '{}'", self.title))),
+        }
+    }
+    
+    /// Per-pane system prompt override (LLM chat panes only)
+    pub fn system_prompt_override(&self) -> Option<&str> {
+        match &self.pane_type {
+            PaneType::LLMChat { system_prompt, .. } => system_prompt.as_deref(),
+            _ => None,
+        }
+    }
+    
     pub fn can_receive_input(&self) -> bool {
         matches!(
             self.pane_type,
@@ -236,6 +274,280 @@ impl Pane {
     }
 }
 
+/// The panes of a workspace and where typed input goes
+///
+/// Input goes to the focused pane. With broadcast on, it goes to every
+/// selected terminal pane at once instead, like tmux's `synchronize-panes`,
+/// e.g. to restart each service of a multi-service dev environment.
+#[derive(Debug, Clone, Default)]
+pub struct PaneManager {
+    panes: Vec<Pane>,
+    focused: Option<PaneId>,
+    broadcast: Vec<PaneId>,
+}
+
+impl PaneManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a pane; the first pane added gets the focus
+    pub fn add(&mut self, mut pane: Pane) -> PaneId {
+        let id = pane.id.clone();
+        if self.focused.is_none() {
+            pane.activate();
+            self.focused = Some(id.clone());
+        }
+        self.panes.push(pane);
+        id
+    }
+    
+    /// Remove a pane, also from the broadcast selection
+    pub fn remove(&mut self, id: &PaneId) -> Result<Pane, PaneError> {
+        let index = self
+            .panes
+            .iter()
+            .position(|pane| &pane.id == id)
+            .ok_or_else(|| PaneError::NotFound(id.to_string()))?;
+        let pane = self.panes.remove(index);
+        self.broadcast.retain(|selected| selected != id);
+        if self.focused.as_ref() == Some(id) {
+            self.focused = None;
+        }
+        Ok(pane)
+    }
+    
+    pub fn panes(&self) -> &[Pane] {
+        &self.panes
+    }
+    
+    pub fn get(&self, id: &PaneId) -> Option<&Pane> {
+        self.panes.iter().find(|pane| &pane.id == id)
+    }
+    
+    /// Pane titled `name`, or with `name` as its id
+    pub fn find(&self, name: &str) -> Option<&Pane> {
+        self.panes
+            .iter()
+            .find(|pane| pane.title == name)
+            .or_else(|| self.panes.iter().find(|pane| pane.id.to_string() == name))
+    }
+    
+    pub fn focused(&self) -> Option<&Pane> {
+        self.focused.as_ref().and_then(|id| self.get(id))
+    }
+    
+    pub fn focus(&mut self, id: &PaneId) -> Result<(), PaneError> {
+        if self.get(id).is_none() {
+            return Err(PaneError::NotFound(id.to_string()));
+        }
+        for pane in &mut self.panes {
+            if &pane.id == id {
+                pane.activate();
+            } else if pane.is_active {
+                pane.deactivate();
+            }
+        }
+        self.focused = Some(id.clone());
+        Ok(())
+    }
+    
+    /// Add a terminal pane whose directory and environment follow `rules`;
+    /// `parent_env` is the environment it would otherwise inherit
+    pub fn add_terminal(
+        &mut self,
+        shell: String,
+        title: String,
+        rules: &InheritRules,
+        workspace_root: &Path,
+        parent_env: impl IntoIterator<Item = (String, String)>,
+    ) -> PaneId {
+        let working_dir = rules.working_dir(workspace_root, self.focused());
+        self.add(Pane::new_terminal(shell, working_dir, title).with_env(rules.environment(parent_env)))
+    }
+    
+    /// Broadcast input to the panes `ids`, which must be terminal panes
+    pub fn broadcast_to(&mut self, ids: &[PaneId]) -> Result<(), PaneError> {
+        for id in ids {
+            let pane = self.get(id).ok_or_else(|| PaneError::NotFound(id.to_string()))?;
+            if !matches!(pane.pane_type, PaneType::Terminal { .. }) {
+                return Err(PaneError::InvalidType(format!("cannot broadcast to '{}', not a terminal", pane.title)));
+            }
+        }
+        self.broadcast.clear();
+        for id in ids {
+            if !self.broadcast.contains(id) {
+                self.broadcast.push(id.clone());
+            }
+        }
+        Ok(())
+    }
+    
+    /// Broadcast input to every terminal pane; returns how many there are
+    pub fn broadcast_all(&mut self) -> usize {
+        self.broadcast = self
+            .panes
+            .iter()
+            .filter(|pane| matches!(pane.pane_type, PaneType::Terminal { .. }))
+            .map(|pane| pane.id.clone())
+            .collect();
+        self.broadcast.len()
+    }
+    
+    pub fn stop_broadcast(&mut self) {
+        self.broadcast.clear();
+    }
+    
+    pub fn is_broadcasting(&self) -> bool {
+        !self.broadcast.is_empty()
+    }
+    
+    /// The panes selected for broadcast, in pane order
+    pub fn broadcast_panes(&self) -> Vec<&Pane> {
+        self.panes.iter().filter(|pane| self.broadcast.contains(&pane.id)).collect()
+    }
+    
+    /// Panes a line of input goes to, marking their activity: the broadcast
+    /// selection while broadcasting, otherwise the focused pane
+    pub fn route_input(&mut self) -> Vec<PaneId> {
+        let targets: Vec<PaneId> = if self.is_broadcasting() {
+            self.broadcast.clone()
+        } else {
+            self.focused.iter().cloned().collect()
+        };
+        for pane in self.panes.iter_mut().filter(|pane| targets.contains(&pane.id)) {
+            pane.touch();
+        }
+        targets
+    }
+}
+
+/// Where a new terminal pane starts
+///
+/// Written as `workspace_root`, `active_pane` or a path, which is relative
+/// to the workspace root unless absolute.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum CwdRule {
+    #[default]
+    WorkspaceRoot,
+    /// The focused pane's directory, or the workspace root without one
+    ActivePane,
+    Fixed(PathBuf),
+}
+
+impl From<String> for CwdRule {
+    fn from(rule: String) -> Self {
+        match rule.as_str() {
+            "workspace_root" => Self::WorkspaceRoot,
+            "active_pane" => Self::ActivePane,
+            _ => Self::Fixed(PathBuf::from(rule)),
+        }
+    }
+}
+
+impl From<CwdRule> for String {
+    fn from(rule: CwdRule) -> Self {
+        rule.to_string()
+    }
+}
+
+impl fmt::Display for CwdRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WorkspaceRoot => f.write_str("workspace_root"),
+            Self::ActivePane => f.write_str("active_pane"),
+            Self::Fixed(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Parent variables passed to pane commands by default: what shells and
+/// toolchains need to work, nothing that usually holds a secret
+pub const DEFAULT_ENV_ALLOWLIST: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "TERM", "COLORTERM", "LANG", "LC_*", "TZ", "TMPDIR", "TEMP", "TMP",
+    "EDITOR", "VISUAL", "PAGER", "XDG_*", "SSH_AUTH_SOCK", "CARGO_HOME", "RUSTUP_HOME", "GOPATH", "GOROOT",
+    "JAVA_HOME", "NVM_DIR", "PYENV_ROOT", "VIRTUAL_ENV", "CONDA_PREFIX", "SYSTEMROOT", "COMSPEC", "PATHEXT",
+    "USERPROFILE", "APPDATA", "LOCALAPPDATA", "PROGRAMDATA",
+];
+
+/// What new terminal panes, and the commands run in them or by the agent,
+/// inherit from PiCode's own process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InheritRules {
+    pub cwd: CwdRule,
+    /// Parent environment variables passed on; `*` matches any characters,
+    /// and `["*"]` passes everything
+    pub env: Vec<String>,
+    /// Variables dropped even when allowed, unless allowed by exact name
+    pub env_deny: Vec<String>,
+    /// Variables set for every pane, over the inherited ones
+    pub set_env: BTreeMap<String, String>,
+}
+
+impl Default for InheritRules {
+    fn default() -> Self {
+        Self {
+            cwd: CwdRule::default(),
+            env: DEFAULT_ENV_ALLOWLIST.iter().map(|name| name.to_string()).collect(),
+            env_deny: ["*TOKEN*", "*SECRET*", "*PASSWORD*", "*API_KEY*", "*ACCESS_KEY*", "*PRIVATE_KEY*", "*CREDENTIAL*"]
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            set_env: BTreeMap::new(),
+        }
+    }
+}
+
+impl InheritRules {
+    /// Directory a new pane starts in
+    pub fn working_dir(&self, workspace_root: &Path, active: Option<&Pane>) -> PathBuf {
+        match &self.cwd {
+            CwdRule::WorkspaceRoot => workspace_root.to_path_buf(),
+            CwdRule::ActivePane => active.and_then(Pane::get_working_dir).unwrap_or_else(|| workspace_root.to_path_buf()),
+            CwdRule::Fixed(path) => workspace_root.join(path),
+        }
+    }
+    
+    /// Whether the parent variable `name` is passed on
+    pub fn allows(&self, name: &str) -> bool {
+        if self.env.iter().any(|pattern| pattern == name) {
+            return true;
+        }
+        self.env.iter().any(|pattern| glob_matches(pattern, name))
+            && !self.env_deny.iter().any(|pattern| glob_matches(pattern, name))
+    }
+    
+    /// The environment of a new pane: the allowed part of `parent`, then
+    /// `set_env`
+    pub fn environment(&self, parent: impl IntoIterator<Item = (String, String)>) -> BTreeMap<String, String> {
+        let mut env: BTreeMap<String, String> = parent.into_iter().filter(|(name, _)| self.allows(name)).collect();
+        env.extend(self.set_env.iter().map(|(name, value)| (name.clone(), value.clone())));
+        env
+    }
+}
+
+/// Case-insensitive match of `name` against a pattern where `*` matches
+/// any run of characters
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.to_ascii_uppercase(), name.to_ascii_uppercase());
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let [first, middle @ .., last] = parts.as_slice() else {
+        return pattern == name;
+    };
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
 /// Pane-related errors
 #[derive(Error, Debug)]
 pub enum PaneError {
@@ -272,7 +584,7 @@ mod tests {
     #[test]
     fn pane_creation_terminal() {
         let shell = "bash".to_string();
-        let working_dir = PathBuf::from("/tmp");
+        let working_dir = std::env::temp_dir();
         let title = "Terminal".to_string();
         
         let pane = Pane::new_terminal(shell.clone(), working_dir.clone(), title.clone());
@@ -310,6 +622,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn switch_model_keeps_chat_pane() {
+        let mut pane = Pane::new_llm_chat("openai".to_string(), "gpt-4".to_string(), "Chat".to_string());
+        let previous = pane.switch_model("anthropic".to_string(), "claude-3-opus".to_string()).unwrap();
+        
+        assert_eq!(previous, ("openai".to_string(), "gpt-4".to_string()));
+        assert_eq!(pane.llm_model(), Some(("anthropic", "claude-3-opus")));
+        
+        let mut editor = Pane::new_editor(PathBuf::from("test.rs"), "Editor".to_string());
+        assert!(editor.switch_model("openai".to_string(), "gpt-4".to_string()).is_err());
+    }
+
     #[test]
     fn pane_creation_editor() {
         let file_path = PathBuf::from("test.rs");
@@ -329,11 +653,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn broadcast_routes_input_to_selected_terminals() {
+        let mut panes = PaneManager::new();
+        let chat = panes.add(Pane::new_llm_chat("openai".to_string(), "gpt-4".to_string(), "chat".to_string()));
+        let api = panes.add(Pane::new_terminal("sh".to_string(), PathBuf::from("api"), "api".to_string()));
+        let web = panes.add(Pane::new_terminal("sh".to_string(), PathBuf::from("web"), "web".to_string()));
+        assert!(panes.focused().unwrap().is_active);
+        assert_eq!(panes.route_input(), vec![chat.clone()]);
+
+        assert_eq!(panes.broadcast_all(), 2);
+        assert_eq!(panes.route_input(), vec![api.clone(), web.clone()]);
+        assert!(panes.broadcast_to(&[api.clone(), chat.clone()]).is_err());
+        let selected = panes.find("web").unwrap().id.clone();
+        panes.broadcast_to(&[selected.clone(), selected]).unwrap();
+        assert_eq!(panes.route_input(), vec![web.clone()]);
+
+        panes.remove(&web).unwrap();
+        assert!(!panes.is_broadcasting());
+        assert_eq!(panes.route_input(), vec![chat]);
+    }
+
+    #[test]
+    fn new_terminals_follow_inherit_rules() {
+        let root = PathBuf::from("/ws");
+        let parent = [
+            ("PATH", "/usr/bin"),
+            ("LC_ALL", "C"),
+            ("GITHUB_TOKEN", "ghp_x"),
+            ("XDG_SECRET_DIR", "/s"),
+            ("OPENAI_API_KEY", "sk-x"),
+            ("NPM_TOKEN", "npm_x"),
+            ("DATABASE_URL", "postgres://"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let mut rules = InheritRules { env: vec!["PATH".into(), "LC_*".into(), "XDG_*".into(), "NPM_TOKEN".into()], ..InheritRules::default() };
+        rules.set_env.insert("CI".into(), "1".into());
+        let env = rules.environment(parent.clone());
+        assert_eq!(env.keys().map(String::as_str).collect::<Vec<_>>(), vec!["CI", "LC_ALL", "NPM_TOKEN", "PATH"]);
+        assert!(InheritRules::default().environment(parent).keys().all(|name| !name.contains("TOKEN") && name != "DATABASE_URL"));
+        assert!(glob_matches("*API_KEY*", "openai_api_key") && !glob_matches("LC_*", "CLC_X"));
+
+        let mut panes = PaneManager::new();
+        let first = panes.add_terminal("sh".into(), "api".into(), &InheritRules { cwd: CwdRule::from("services/api".to_string()), ..rules.clone() }, &root, []);
+        assert_eq!(panes.get(&first).unwrap().get_working_dir(), Some(PathBuf::from("/ws/services/api")));
+        let follow = InheritRules { cwd: CwdRule::ActivePane, ..rules };
+        let second = panes.add_terminal("sh".into(), "api-2".into(), &follow, &root, []);
+        let second = panes.get(&second).unwrap();
+        assert_eq!(second.get_working_dir(), Some(PathBuf::from("/ws/services/api")));
+        assert_eq!(second.env.as_ref().map(|env| env.len()), Some(1));
+        assert_eq!(follow.working_dir(&root, None), root);
+
+        let rules: InheritRules = serde_json::from_str(r#"{"cwd": "active_pane", "env": ["*"]}"#).unwrap();
+        assert_eq!(rules.cwd, CwdRule::ActivePane);
+        assert!(rules.allows("HOME") && !rules.allows("AWS_SECRET_ACCESS_KEY"));
+    }
+
     #[test]
     fn pane_resize_validation() {
         let mut pane = Pane::new_terminal(
             "bash".to_string(),
-            PathBuf::from("/tmp"),
+            std::env::temp_dir(),
             "Test".to_string(),
         );
         
@@ -354,7 +734,7 @@ mod tests {
     fn pane_activation() {
         let mut pane = Pane::new_terminal(
             "bash".to_string(),
-            PathBuf::from("/tmp"),
+            std::env::temp_dir(),
             "Test".to_string(),
         );
         
@@ -371,7 +751,7 @@ mod tests {
     fn pane_positioning() {
         let mut pane = Pane::new_terminal(
             "bash".to_string(),
-            PathBuf::from("/tmp"),
+            std::env::temp_dir(),
             "Test".to_string(),
         );
         
@@ -389,20 +769,20 @@ mod tests {
 
     #[test]
     fn pane_working_directory() {
-        let working_dir = PathBuf::from("/tmp/test");
+        let working_dir = std::env::temp_dir().join("test");
         let pane = Pane::new_terminal(
             "bash".to_string(),
             working_dir.clone(),
             "Test".to_string(),
         );
         
-        assert_eq!(pane.get_working_dir(), Some(&working_dir));
+        assert_eq!(pane.get_working_dir(), Some(working_dir.clone()));
         
-        let file_path = PathBuf::from("/home/user/project/main.rs");
+        let file_path = std::env::temp_dir().join("project").join("main.rs");
         let editor_pane = Pane::new_editor(file_path, "Editor".to_string());
         
         // Editor pane working dir should be the parent directory of the file
-        let expected_dir = PathBuf::from("/home/user/project");
+        let expected_dir = std::env::temp_dir().join("project");
         let actual_dir = editor_pane.get_working_dir().unwrap();
         assert_eq!(actual_dir.file_name(), expected_dir.file_name());
     }
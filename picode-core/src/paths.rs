@@ -0,0 +1,139 @@
+//! Platform-neutral path handling
+//!
+//! On Windows `std::fs::canonicalize` returns verbatim `\\?\C:\...` paths
+//! that neither compare equal to the paths users type nor work with many
+//! tools, and drive letters and directory names compare case-insensitively.
+//! Workspace code canonicalizes, strips the workspace root and renders
+//! model-facing `/`-separated paths through these helpers instead of
+//! assuming Unix paths.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Canonicalize `path`, without the verbatim prefix where Windows allows it
+pub fn canonicalize(path: impl AsRef<Path>) -> std::io::Result<PathBuf> {
+    dunce::canonicalize(path)
+}
+
+/// Canonicalize the nearest existing ancestor of `path` and append the
+/// rest, so symlinks are resolved for paths that are yet to be created
+pub fn canonicalize_existing(path: impl AsRef<Path>) -> std::io::Result<PathBuf> {
+    let path = path.as_ref();
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(last)) => {
+                missing.push(last);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = if existing.as_os_str().is_empty() { canonicalize(".")? } else { canonicalize(existing)? };
+    for component in missing.into_iter().rev() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(part) => resolved.push(part),
+            _ => {}
+        }
+    }
+    Ok(resolved)
+}
+
+/// `path` with a removable `\\?\` or `\\?\UNC\` prefix stripped; other
+/// paths are returned as they are
+pub fn simplified(path: &Path) -> &Path {
+    dunce::simplified(path)
+}
+
+/// `path` relative to `root`, tolerating verbatim prefixes and, on Windows,
+/// differences in case
+pub fn strip_root<'a>(path: &'a Path, root: &Path) -> Option<&'a Path> {
+    if let Ok(relative) = path.strip_prefix(root) {
+        return Some(relative);
+    }
+    let (path, root) = (simplified(path), simplified(root));
+    if let Ok(relative) = path.strip_prefix(root) {
+        return Some(relative);
+    }
+    if !cfg!(windows) {
+        return None;
+    }
+    let mut components = path.components();
+    for expected in root.components() {
+        let actual = components.next()?;
+        if !component_eq_ignore_case(actual, expected) {
+            return None;
+        }
+    }
+    Some(components.as_path())
+}
+
+fn component_eq_ignore_case(a: Component<'_>, b: Component<'_>) -> bool {
+    a.as_os_str().to_string_lossy().to_lowercase() == b.as_os_str().to_string_lossy().to_lowercase()
+}
+
+/// `path` with `/` separators, as shown to the model and stored in patches
+/// and manifests; a `\` is only a separator where the platform says so
+pub fn to_slash(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    if std::path::MAIN_SEPARATOR == '/' {
+        text.into_owned()
+    } else {
+        text.replace(std::path::MAIN_SEPARATOR, "/")
+    }
+}
+
+/// Whether `path` was written with a trailing separator, i.e. names a directory
+pub fn ends_with_separator(path: &Path) -> bool {
+    path.as_os_str().to_string_lossy().ends_with(std::path::is_separator)
+}
+
+/// Expand a leading `~` to the user's home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    match (components.next(), dirs::home_dir()) {
+        (Some(Component::Normal(first)), Some(home)) if first == "~" => home.join(components.as_path()),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_roots_and_renders_slashes() {
+        let root = std::env::temp_dir().join("picode-ws");
+        let file = root.join("src").join("lib.rs");
+        assert_eq!(strip_root(&file, &root), Some(Path::new("src").join("lib.rs").as_path()));
+        assert_eq!(strip_root(&root, &file), None);
+        assert_eq!(to_slash(strip_root(&file, &root).unwrap()), "src/lib.rs");
+
+        assert!(ends_with_separator(Path::new("target/")));
+        assert!(!ends_with_separator(Path::new("target")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn canonicalizes_through_the_nearest_existing_ancestor() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = canonicalize(dir.path()).unwrap();
+        std::fs::create_dir(root.join("real")).unwrap();
+        std::os::unix::fs::symlink(root.join("real"), root.join("link")).unwrap();
+        assert_eq!(canonicalize_existing(root.join("link/new/file.txt")).unwrap(), root.join("real/new/file.txt"));
+        assert_eq!(canonicalize_existing(root.join("missing/../real")).unwrap(), root.join("real"));
+    }
+
+    #[test]
+    fn expands_home() {
+        let home = dirs::home_dir().unwrap_or_default();
+        let expanded = expand_home(&Path::new("~").join("projects"));
+        if !home.as_os_str().is_empty() {
+            assert_eq!(expanded, home.join("projects"));
+        }
+        assert_eq!(expand_home(Path::new("src/~")), PathBuf::from("src/~"));
+    }
+}
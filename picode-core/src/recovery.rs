@@ -0,0 +1,175 @@
+//! Recovery from corrupted on-disk state
+//!
+//! A session or config file that no longer parses (a crash mid-write, a bad
+//! merge, a manual edit) should not keep PiCode from starting. Unparsable
+//! files are moved aside to [`CORRUPT_DIR`] so nothing is lost, conversation
+//! logs are salvaged message by message where possible, and everything that
+//! happened is collected into a [`RecoveryReport`] shown as a warning.
+
+use crate::conversation::ConversationMessage;
+use crate::editor::RecoveredEdit;
+use crate::io::FileSystem;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Where unparsable files are moved, relative to the workspace
+pub const CORRUPT_DIR: &str = ".picode/corrupt";
+
+/// A file moved out of the way because it could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuarantinedFile {
+    pub original: PathBuf,
+    pub moved_to: PathBuf,
+    pub reason: String,
+}
+
+/// A conversation log rebuilt from the parts that still parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialRecovery {
+    pub path: PathBuf,
+    pub recovered: usize,
+    pub skipped: usize,
+}
+
+/// Everything recovered or set aside while loading state
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecoveryReport {
+    pub quarantined: Vec<QuarantinedFile>,
+    pub partial: Vec<PartialRecovery>,
+    /// Edits a crash interrupted, rolled back or replayed from the journal
+    pub edits: Vec<RecoveredEdit>,
+}
+
+impl RecoveryReport {
+    pub fn is_empty(&self) -> bool {
+        self.quarantined.is_empty() && self.partial.is_empty() && self.edits.is_empty()
+    }
+
+    pub fn merge(&mut self, other: RecoveryReport) {
+        self.quarantined.extend(other.quarantined);
+        self.partial.extend(other.partial);
+        self.edits.extend(other.edits);
+    }
+}
+
+impl fmt::Display for RecoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.quarantined {
+            writeln!(
+                f,
+                "⚠️  {} could not be read ({}); moved to {}",
+                file.original.display(),
+                file.reason,
+                file.moved_to.display()
+            )?;
+        }
+        for partial in &self.partial {
+            writeln!(
+                f,
+                "⚠️  {} was damaged; recovered {} message(s), skipped {} unreadable entr{}",
+                partial.path.display(),
+                partial.recovered,
+                partial.skipped,
+                if partial.skipped == 1 { "y" } else { "ies" }
+            )?;
+        }
+        for edit in &self.edits {
+            writeln!(f, "{}", edit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Move `path` into `corrupt_dir` under a timestamped name and return the new
+/// location; the original is only removed once the copy is written
+pub async fn quarantine(fs: &dyn FileSystem, path: &Path, corrupt_dir: &Path) -> std::io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "unnamed".to_string());
+    let moved_to = corrupt_dir.join(format!("{}-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S%3f"), file_name));
+
+    let bytes = fs.read(path).await?;
+    fs.create_dir_all(corrupt_dir).await?;
+    fs.write(&moved_to, &bytes).await?;
+    fs.remove_file(path).await?;
+    Ok(moved_to)
+}
+
+/// Salvage the messages of a damaged conversation log.
+///
+/// JSONL logs are read line by line, skipping lines that do not parse. For a
+/// single JSON document (for instance one truncated mid-write) every message
+/// object that is still complete is kept. Returns the messages and the number
+/// of entries that had to be skipped.
+pub fn recover_messages(content: &str) -> (Vec<ConversationMessage>, usize) {
+    let lines: Vec<&str> = content.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    let mut messages = Vec::new();
+    let mut skipped = 0;
+    for line in &lines {
+        match serde_json::from_str::<ConversationMessage>(line) {
+            Ok(message) => messages.push(message),
+            Err(_) => skipped += 1,
+        }
+    }
+    if !messages.is_empty() {
+        return (messages, skipped);
+    }
+
+    (scan_messages(content), 0)
+}
+
+/// Find every complete message object in a possibly truncated JSON document
+fn scan_messages(content: &str) -> Vec<ConversationMessage> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = content[offset..].find('{').map(|i| offset + i) {
+        let mut stream = serde_json::Deserializer::from_str(&content[start..]).into_iter::<ConversationMessage>();
+        match stream.next() {
+            Some(Ok(message)) => {
+                messages.push(message);
+                offset = start + stream.byte_offset();
+            }
+            _ => offset = start + 1,
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    #[tokio::test]
+    async fn quarantine_moves_file_aside() {
+        let fs = MemoryFileSystem::new();
+        let path = PathBuf::from("/sessions/broken.json");
+        fs.insert(&path, b"{ not json".to_vec());
+
+        let moved_to = quarantine(&fs, &path, Path::new("/ws/.picode/corrupt")).await.unwrap();
+        assert!(!fs.exists(&path).await);
+        assert!(moved_to.starts_with("/ws/.picode/corrupt"));
+        assert!(moved_to.to_string_lossy().ends_with("-broken.json"));
+        assert_eq!(fs.get(&moved_to).unwrap(), b"{ not json".to_vec());
+    }
+
+    #[test]
+    fn recovers_jsonl_and_truncated_json() {
+        let good = serde_json::to_string(&ConversationMessage::new("user", "hello")).unwrap();
+        let jsonl = format!("{}\n{{\"role\": \"assis\n{}\n", good, good);
+        let (messages, skipped) = recover_messages(&jsonl);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(skipped, 1);
+
+        let log = serde_json::json!({
+            "session_id": uuid::Uuid::new_v4(),
+            "messages": [ConversationMessage::new("user", "first"), ConversationMessage::new("assistant", "second")],
+        });
+        let pretty = serde_json::to_string_pretty(&log).unwrap();
+        let truncated = &pretty[..pretty.find("second").unwrap()];
+        let (messages, _) = recover_messages(truncated);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "first");
+    }
+}
@@ -0,0 +1,354 @@
+//! Smart truncation of command output before it is sent to the model
+//!
+//! Long outputs (test logs, build errors) are reduced to their head and tail,
+//! error lines found via rustc/pytest/tsc aware patterns, and stack traces with
+//! repeated frames collapsed. Every omission is annotated so the model knows
+//! the output was shortened.
+
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Error line patterns, most specific first
+fn error_patterns() -> &'static [Regex] {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // rustc / cargo
+            r"^error(\[E\d{4}\])?: ",
+            r"^\s+--> \S+:\d+:\d+",
+            r"^thread '.*' panicked at",
+            r"^test \S+ \.\.\. FAILED$",
+            // pytest
+            r"^E\s{2,}\S",
+            r"^(FAILED|ERROR) \S+",
+            r"^\S+\.py:\d+: \w+(Error|Exception)",
+            // tsc
+            r"^\S+\(\d+,\d+\): error TS\d+: ",
+            r"^\S+:\d+:\d+ - error TS\d+: ",
+            // generic
+            r"^(?i)(fatal )?error: ",
+            r"^Traceback \(most recent call last\):",
+        ]
+        .iter()
+        .map(|p| Regex::new(p).expect("built-in error pattern is valid"))
+        .collect()
+    })
+}
+
+/// Stack frame patterns (Python, Rust backtraces, JS/Java)
+fn frame_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"^\s*(File ".+", line \d+, in .+|\d+: \S+|at \S.*)$"#)
+            .expect("built-in frame pattern is valid")
+    })
+}
+
+fn is_python_frame(line: &str) -> bool {
+    line.trim_start().starts_with("File \"")
+}
+
+/// Summarized command output
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputSummary {
+    pub text: String,
+    pub truncated: bool,
+    pub original_lines: usize,
+    /// Error lines found in the omitted middle section
+    pub extracted_errors: usize,
+    /// Stack frames or repeated lines collapsed
+    pub collapsed_lines: usize,
+}
+
+/// Reduces long command output to the parts an agent needs
+#[derive(Debug, Clone)]
+pub struct CommandOutputSummarizer {
+    max_chars: usize,
+    head_lines: usize,
+    tail_lines: usize,
+    max_error_lines: usize,
+}
+
+impl Default for CommandOutputSummarizer {
+    fn default() -> Self {
+        Self {
+            max_chars: 8_000,
+            head_lines: 40,
+            tail_lines: 60,
+            max_error_lines: 50,
+        }
+    }
+}
+
+impl CommandOutputSummarizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output at or below this size is passed through (after frame dedup);
+    /// summaries never exceed it, clipping lines too long to fit
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    pub fn with_head_lines(mut self, lines: usize) -> Self {
+        self.head_lines = lines;
+        self
+    }
+
+    pub fn with_tail_lines(mut self, lines: usize) -> Self {
+        self.tail_lines = lines;
+        self
+    }
+
+    pub fn with_max_error_lines(mut self, lines: usize) -> Self {
+        self.max_error_lines = lines;
+        self
+    }
+
+    pub fn summarize(&self, output: &str) -> OutputSummary {
+        let original_lines = output.lines().count();
+        let (lines, collapsed_lines) = collapse_repeats(output.lines());
+
+        let joined_len: usize = lines.iter().map(|l| l.len() + 1).sum();
+        if joined_len <= self.max_chars {
+            return OutputSummary {
+                text: lines.join("\n"),
+                truncated: false,
+                original_lines,
+                extracted_errors: 0,
+                collapsed_lines,
+            };
+        }
+        if lines.len() <= self.head_lines + self.tail_lines {
+            // Few but long lines: each gets an equal share of the limit
+            let line_budget = (self.max_chars / lines.len()).saturating_sub(1);
+            let clipped: Vec<String> = lines.iter().map(|line| clip(line, line_budget)).collect();
+            return OutputSummary {
+                text: clip(&clipped.join("\n"), self.max_chars),
+                truncated: true,
+                original_lines,
+                extracted_errors: 0,
+                collapsed_lines,
+            };
+        }
+
+        let middle = &lines[self.head_lines..lines.len() - self.tail_lines];
+        let errors: Vec<String> = middle
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| error_patterns().iter().any(|p| p.is_match(line)))
+            .map(|(i, line)| format!("{:>6}: {}", self.head_lines + i + 1, line))
+            .collect();
+        let shown_errors = errors.len().min(self.max_error_lines);
+
+        let render = |line_budget: usize| {
+            let shown = |lines: &[String]| lines.iter().map(|line| clip(line, line_budget)).collect::<Vec<_>>().join("\n");
+
+            let mut text = format!(
+                "[picode: output truncated; showing first {} and last {} of {} lines",
+                self.head_lines, self.tail_lines, original_lines
+            );
+            if collapsed_lines > 0 {
+                text.push_str(&format!(", {} repeated lines collapsed", collapsed_lines));
+            }
+            text.push_str("]\n");
+
+            text.push_str(&shown(&lines[..self.head_lines]));
+            text.push_str(&format!("\n… [{} lines omitted] …\n", middle.len()));
+
+            if !errors.is_empty() {
+                text.push_str(&format!("[picode: {} error line(s) from the omitted section]\n", errors.len()));
+                for error in &errors[..shown_errors] {
+                    text.push_str(&clip(error, line_budget));
+                    text.push('\n');
+                }
+                if errors.len() > shown_errors {
+                    text.push_str(&format!("… [{} more error lines] …\n", errors.len() - shown_errors));
+                }
+                text.push_str("…\n");
+            }
+
+            text.push_str(&shown(&lines[lines.len() - self.tail_lines..]));
+            text
+        };
+        let mut text = render(usize::MAX);
+        if text.len() > self.max_chars {
+            // Shown lines share the limit, with a few lines' worth for the notes
+            text = render((self.max_chars / (self.head_lines + self.tail_lines + shown_errors + 4)).saturating_sub(1));
+        }
+
+        OutputSummary {
+            text: clip(&text, self.max_chars),
+            truncated: true,
+            original_lines,
+            extracted_errors: errors.len(),
+            collapsed_lines,
+        }
+    }
+}
+
+/// `text` cut to at most `max_len` bytes, noting how much was cut when
+/// there is room for the note
+fn clip(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    // The note is at most as long as with the whole text omitted
+    let note_len = format!(" … [{} chars omitted]", text.len()).len();
+    let with_note = max_len > 2 * note_len;
+    let mut cut = if with_note { max_len - note_len } else { max_len };
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    if with_note {
+        format!("{} … [{} chars omitted]", &text[..cut], text.len() - cut)
+    } else {
+        text[..cut].to_string()
+    }
+}
+
+/// Collapse runs of identical lines and stack frames already seen in the
+/// current trace, returning the kept lines and how many were dropped
+fn collapse_repeats<'a>(lines: impl Iterator<Item = &'a str>) -> (Vec<String>, usize) {
+    let mut kept: Vec<String> = Vec::new();
+    let mut seen_frames: HashSet<&'a str> = HashSet::new();
+    let mut collapsed = 0;
+    let mut run: usize = 0;
+    let mut skipped_frames: usize = 0;
+    let mut skip_python_source = false;
+    let mut after_python_frame = false;
+    let mut previous: Option<&'a str> = None;
+
+    let flush = |kept: &mut Vec<String>, run: &mut usize, skipped_frames: &mut usize| {
+        if *run > 0 {
+            kept.push(format!("[previous line repeated {} more times]", run));
+            *run = 0;
+        }
+        if *skipped_frames > 0 {
+            kept.push(format!("[{} duplicate stack frames omitted]", skipped_frames));
+            *skipped_frames = 0;
+        }
+    };
+
+    for line in lines {
+        // Python frames are followed by the indented source line they point at
+        let is_frame = frame_pattern().is_match(line);
+        let is_python_source = after_python_frame && !is_frame && line.starts_with("    ");
+        after_python_frame = false;
+        if is_python_source {
+            if std::mem::take(&mut skip_python_source) {
+                collapsed += 1;
+            } else {
+                kept.push(line.to_string());
+            }
+            previous = Some(line);
+            continue;
+        }
+        skip_python_source = false;
+
+        if previous == Some(line) && !line.trim().is_empty() {
+            run += 1;
+            collapsed += 1;
+            continue;
+        }
+
+        if is_frame {
+            after_python_frame = is_python_frame(line);
+            if !seen_frames.insert(line.trim()) {
+                skipped_frames += 1;
+                collapsed += 1;
+                skip_python_source = after_python_frame;
+                previous = Some(line);
+                continue;
+            }
+        } else {
+            seen_frames.clear();
+        }
+
+        flush(&mut kept, &mut run, &mut skipped_frames);
+        kept.push(line.to_string());
+        previous = Some(line);
+    }
+    flush(&mut kept, &mut run, &mut skipped_frames);
+
+    (kept, collapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_output_passes_through() {
+        let summary = CommandOutputSummarizer::new().summarize("ok\nall good");
+        assert_eq!(summary.text, "ok\nall good");
+        assert!(!summary.truncated);
+    }
+
+    #[test]
+    fn long_output_keeps_head_tail_and_errors() {
+        let mut output: Vec<String> = (0..500).map(|i| format!("   Compiling crate{} v0.1.0", i)).collect();
+        output[200] = "error[E0308]: mismatched types".to_string();
+        output[201] = "  --> src/lib.rs:10:5".to_string();
+        output[300] = "src/app.ts(3,7): error TS2322: Type 'string' is not assignable".to_string();
+        output[350] = "E       assert 1 == 2".to_string();
+
+        let summary = CommandOutputSummarizer::new()
+            .with_max_chars(1_000)
+            .with_head_lines(5)
+            .with_tail_lines(5)
+            .summarize(&output.join("\n"));
+
+        assert!(summary.truncated);
+        assert_eq!(summary.original_lines, 500);
+        assert_eq!(summary.extracted_errors, 4);
+        assert!(summary.text.starts_with("[picode: output truncated; showing first 5 and last 5 of 500 lines]"));
+        assert!(summary.text.contains("   201: error[E0308]: mismatched types"));
+        assert!(summary.text.contains("   202:   --> src/lib.rs:10:5"));
+        assert!(summary.text.contains("error TS2322"));
+        assert!(summary.text.contains("[490 lines omitted]"));
+        assert!(summary.text.ends_with("   Compiling crate499 v0.1.0"));
+    }
+
+    #[test]
+    fn long_lines_are_clipped_to_the_limit() {
+        let summarizer = CommandOutputSummarizer::new().with_max_chars(200);
+        let minified = "x".repeat(10_000);
+        let summary = summarizer.summarize(&minified);
+        assert!(summary.truncated && summary.text.len() <= 200);
+        assert!(summary.text.ends_with("chars omitted]"));
+
+        let output: Vec<String> = (0..300).map(|i| format!("{} {}", i, "é".repeat(500))).collect();
+        let summary = summarizer.with_head_lines(2).with_tail_lines(2).summarize(&output.join("\n"));
+        assert!(summary.truncated && summary.text.len() <= 200);
+
+        assert_eq!(clip("héllo", 2), "h");
+    }
+
+    #[test]
+    fn collapses_repeated_frames_and_lines() {
+        let mut output = vec!["Traceback (most recent call last):".to_string()];
+        for _ in 0..3 {
+            output.push("  File \"app.py\", line 3, in recurse".to_string());
+            output.push("    return recurse(n - 1)".to_string());
+        }
+        output.push("RecursionError: maximum recursion depth exceeded".to_string());
+        output.extend(std::iter::repeat_n("retrying...".to_string(), 4));
+
+        let summary = CommandOutputSummarizer::new().summarize(&output.join("\n"));
+        assert_eq!(
+            summary.text,
+            "Traceback (most recent call last):\n\
+             \x20 File \"app.py\", line 3, in recurse\n\
+             \x20   return recurse(n - 1)\n\
+             [2 duplicate stack frames omitted]\n\
+             RecursionError: maximum recursion depth exceeded\n\
+             retrying...\n\
+             [previous line repeated 3 more times]"
+        );
+        assert_eq!(summary.collapsed_lines, 7);
+    }
+}
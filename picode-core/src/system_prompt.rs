@@ -0,0 +1,213 @@
+//! Layered system prompt assembly
+//!
+//! The effective system prompt is built from ordered layers: the built-in
+//! PiCode instructions, the workspace `PICODE.md`, the project fingerprint
+//! (languages and frameworks), the workspace's defined tasks, profile-level instructions, the response policy and per-pane overrides. Layers are
+//! always emitted in that order regardless of insertion order, and each
+//! layer's token cost is tracked so the prompt can be inspected
+//! (`/system show`).
+
+use crate::io::FileSystem;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Workspace instruction file merged into the system prompt
+pub const WORKSPACE_INSTRUCTIONS_FILE: &str = "PICODE.md";
+
+/// Built-in instructions forming the base layer
+pub const BASE_INSTRUCTIONS: &str = "You are PiCode, an AI coding assistant working inside the \
+user's terminal workspace. Be concise and precise. Prefer small, reviewable changes, explain \
+what you are about to do before running commands, and never invent file contents you have not \
+read.";
+
+/// Rough token estimate (about 4 characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Kind of layer; the declaration order is the assembly order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptLayerKind {
+    Base,
+    Workspace,
+    /// Languages and frameworks found by scanning the workspace
+    Project,
+    /// Named workspace tasks the agent should prefer over ad-hoc commands
+    Tasks,
+    Profile,
+    /// Response language, verbosity and style rules
+    Policy,
+    Pane,
+}
+
+impl std::fmt::Display for PromptLayerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PromptLayerKind::Base => "base",
+            PromptLayerKind::Workspace => "workspace",
+            PromptLayerKind::Project => "project",
+            PromptLayerKind::Tasks => "tasks",
+            PromptLayerKind::Profile => "profile",
+            PromptLayerKind::Policy => "policy",
+            PromptLayerKind::Pane => "pane",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One contribution to the system prompt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptLayer {
+    pub kind: PromptLayerKind,
+    /// Where the layer came from (e.g. a file path or profile name)
+    pub source: String,
+    pub content: String,
+}
+
+impl PromptLayer {
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.content)
+    }
+}
+
+/// System prompt assembled from layers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SystemPrompt {
+    layers: Vec<PromptLayer>,
+}
+
+impl SystemPrompt {
+    /// Prompt containing only the built-in base layer
+    pub fn new() -> Self {
+        Self::default().with_layer(PromptLayerKind::Base, "built-in", BASE_INSTRUCTIONS)
+    }
+
+    /// Set a layer, replacing any existing layer of the same kind; blank
+    /// content removes the layer
+    pub fn with_layer(mut self, kind: PromptLayerKind, source: impl Into<String>, content: impl Into<String>) -> Self {
+        self.set_layer(kind, source, content);
+        self
+    }
+
+    pub fn set_layer(&mut self, kind: PromptLayerKind, source: impl Into<String>, content: impl Into<String>) {
+        self.layers.retain(|layer| layer.kind != kind);
+
+        let content = content.into();
+        if content.trim().is_empty() {
+            return;
+        }
+
+        let layer = PromptLayer {
+            kind,
+            source: source.into(),
+            content: content.trim().to_string(),
+        };
+        let position = self.layers.partition_point(|l| l.kind < kind);
+        self.layers.insert(position, layer);
+    }
+
+    pub fn remove_layer(&mut self, kind: PromptLayerKind) {
+        self.layers.retain(|layer| layer.kind != kind);
+    }
+
+    /// Add the workspace `PICODE.md` layer if the file exists
+    pub async fn with_workspace_instructions(
+        mut self,
+        fs: &dyn FileSystem,
+        workspace_root: &Path,
+    ) -> std::io::Result<Self> {
+        let path = workspace_root.join(WORKSPACE_INSTRUCTIONS_FILE);
+        if fs.exists(&path).await {
+            let content = fs.read_to_string(&path).await?;
+            self.set_layer(PromptLayerKind::Workspace, path.display().to_string(), content);
+        }
+        Ok(self)
+    }
+
+    pub fn layers(&self) -> &[PromptLayer] {
+        &self.layers
+    }
+
+    /// The prompt sent to the model
+    pub fn effective(&self) -> String {
+        self.layers
+            .iter()
+            .map(|layer| layer.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        estimate_tokens(&self.effective())
+    }
+
+    /// Human readable breakdown of every layer, used by `/system show`
+    pub fn render_inspection(&self) -> String {
+        let mut out = String::new();
+        for layer in &self.layers {
+            out.push_str(&format!(
+                "── {} ({}) · ~{} tokens ──\n{}\n\n",
+                layer.kind,
+                layer.source,
+                layer.tokens(),
+                layer.content
+            ));
+        }
+        out.push_str(&format!(
+            "Effective system prompt: {} layer(s), ~{} tokens\n",
+            self.layers.len(),
+            self.total_tokens()
+        ));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    #[test]
+    fn layers_are_ordered_regardless_of_insertion() {
+        let prompt = SystemPrompt::new()
+            .with_layer(PromptLayerKind::Pane, "pane", "Answer in French.")
+            .with_layer(PromptLayerKind::Profile, "work", "Follow the company style guide.")
+            .with_layer(PromptLayerKind::Workspace, "PICODE.md", "Use tokio for async.");
+
+        let kinds: Vec<_> = prompt.layers().iter().map(|l| l.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![PromptLayerKind::Base, PromptLayerKind::Workspace, PromptLayerKind::Profile, PromptLayerKind::Pane]
+        );
+        assert!(prompt.effective().ends_with("Follow the company style guide.\n\nAnswer in French."));
+    }
+
+    #[test]
+    fn replacing_and_removing_layers() {
+        let mut prompt = SystemPrompt::new().with_layer(PromptLayerKind::Pane, "pane", "first");
+        prompt.set_layer(PromptLayerKind::Pane, "pane", "second");
+        assert_eq!(prompt.layers().len(), 2);
+        assert!(prompt.effective().ends_with("second"));
+
+        prompt.set_layer(PromptLayerKind::Pane, "pane", "   ");
+        assert_eq!(prompt.layers().len(), 1);
+
+        let inspection = prompt.render_inspection();
+        assert!(inspection.starts_with("── base (built-in) · ~"));
+        assert!(inspection.contains("1 layer(s)"));
+    }
+
+    #[tokio::test]
+    async fn loads_workspace_instructions() {
+        let fs = MemoryFileSystem::new();
+        fs.insert("/ws/PICODE.md", "Run `cargo fmt` before committing.\n");
+
+        let prompt = SystemPrompt::new()
+            .with_workspace_instructions(&fs, Path::new("/ws"))
+            .await
+            .unwrap();
+        assert_eq!(prompt.layers()[1].source, "/ws/PICODE.md");
+        assert_eq!(prompt.layers()[1].tokens(), estimate_tokens("Run `cargo fmt` before committing."));
+    }
+}
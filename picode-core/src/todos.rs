@@ -0,0 +1,283 @@
+//! TODO, FIXME and HACK comments as a tracked list
+//!
+//! [`extract`] finds the markers in a file's comments, with the owner named
+//! as `TODO(alice):` or `TODO @alice:`. A [`TodoList`], persisted under
+//! `.picode/todos.json`, follows them across scans: an item keeps its id
+//! while its comment moves around, and once the comment is gone from the
+//! code the item is marked done. Agents can also mark items done, with a
+//! note, when their work settles one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+use crate::content_cache::content_hash;
+use crate::io::FileSystem;
+
+/// File name of the TODO list inside the workspace `.picode` directory
+pub const TODOS_FILE: &str = "todos.json";
+
+/// Kind of marker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TodoKind {
+    Todo,
+    Fixme,
+    Hack,
+}
+
+impl fmt::Display for TodoKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Todo => "TODO",
+            Self::Fixme => "FIXME",
+            Self::Hack => "HACK",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoStatus {
+    Open,
+    Done,
+}
+
+/// One marker comment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TodoItem {
+    /// Stable across line moves: derived from the file, kind and text
+    pub id: String,
+    pub kind: TodoKind,
+    /// Workspace-relative file
+    pub file: PathBuf,
+    /// 1-based line of the comment at the last scan
+    pub line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub text: String,
+    pub status: TodoStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Why an item was marked done by hand; unset when its comment was removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl TodoItem {
+    /// `file:line`
+    pub fn location(&self) -> String {
+        format!("{}:{}", crate::paths::to_slash(&self.file), self.line)
+    }
+}
+
+/// Marker comments of a file's content
+pub fn extract(file: &Path, content: &str) -> Vec<TodoItem> {
+    static MARKER: OnceLock<regex::Regex> = OnceLock::new();
+    let marker = MARKER.get_or_init(|| {
+        regex::Regex::new(
+            r"(?://+|#+|/\*+|^\s*\*|--|<!--|;+)\s*\b(TODO|FIXME|HACK)\b(?:\(([^)]*)\)|\s+@([\w.-]+))?:?\s*(.*)",
+        )
+        .expect("valid marker pattern")
+    });
+
+    let mut items = Vec::new();
+    let mut seen = BTreeSet::new();
+    for (index, line) in content.lines().enumerate() {
+        let Some(captures) = marker.captures(line) else {
+            continue;
+        };
+        let kind = match &captures[1] {
+            "TODO" => TodoKind::Todo,
+            "FIXME" => TodoKind::Fixme,
+            _ => TodoKind::Hack,
+        };
+        let owner = captures
+            .get(2)
+            .or_else(|| captures.get(3))
+            .map(|owner| owner.as_str().trim().trim_start_matches('@').to_string())
+            .filter(|owner| !owner.is_empty());
+        let text = captures[4].trim().trim_end_matches("*/").trim_end_matches("-->").trim().to_string();
+        // Identical comments in one file are told apart by their order
+        let mut key = format!("{}\0{}\0{}", crate::paths::to_slash(file), kind, text);
+        let mut repeat = 1;
+        while !seen.insert(key.clone()) {
+            repeat += 1;
+            key = format!("{}\0{}\0{}\0{}", crate::paths::to_slash(file), kind, text, repeat);
+        }
+        items.push(TodoItem {
+            id: content_hash(key.as_bytes())[..8].to_string(),
+            kind,
+            file: file.to_path_buf(),
+            line: index + 1,
+            owner,
+            text,
+            status: TodoStatus::Open,
+            done_at: None,
+            note: None,
+        });
+    }
+    items
+}
+
+/// What a rescan changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TodoChanges {
+    pub added: Vec<TodoItem>,
+    /// Items whose comment is gone, now marked done
+    pub resolved: Vec<TodoItem>,
+}
+
+/// The tracked TODO items of a workspace
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TodoList {
+    #[serde(skip)]
+    file_path: PathBuf,
+    pub items: Vec<TodoItem>,
+}
+
+impl TodoList {
+    /// Load the list of a workspace, starting empty if none was saved
+    pub async fn load(fs: &dyn FileSystem, workspace_root: &Path) -> Result<Self, TodoError> {
+        let file_path = workspace_root.join(".picode").join(TODOS_FILE);
+        let mut list = if fs.exists(&file_path).await {
+            serde_json::from_str::<TodoList>(&fs.read_to_string(&file_path).await?)?
+        } else {
+            TodoList::default()
+        };
+        list.file_path = file_path;
+        Ok(list)
+    }
+
+    pub async fn save(&self, fs: &dyn FileSystem) -> Result<(), TodoError> {
+        if let Some(parent) = self.file_path.parent() {
+            fs.create_dir_all(parent).await?;
+        }
+        fs.write(&self.file_path, serde_json::to_string_pretty(self)?.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Merge a full scan of the workspace: new comments are added, moved
+    /// ones updated and items whose comment is gone marked done. An item
+    /// resolved that way reopens if its comment comes back; one marked done
+    /// by hand stays done.
+    pub fn reconcile(&mut self, scanned: Vec<TodoItem>) -> TodoChanges {
+        let mut changes = TodoChanges::default();
+        let found: BTreeSet<String> = scanned.iter().map(|item| item.id.clone()).collect();
+        let now = chrono::Utc::now();
+        for item in self.items.iter_mut().filter(|item| item.status == TodoStatus::Open) {
+            if !found.contains(&item.id) {
+                item.status = TodoStatus::Done;
+                item.done_at = Some(now);
+                changes.resolved.push(item.clone());
+            }
+        }
+        for scanned in scanned {
+            match self.items.iter_mut().find(|item| item.id == scanned.id) {
+                Some(item) => {
+                    item.line = scanned.line;
+                    item.owner = scanned.owner;
+                    if item.status == TodoStatus::Done && item.note.is_none() {
+                        item.status = TodoStatus::Open;
+                        item.done_at = None;
+                    }
+                }
+                None => {
+                    changes.added.push(scanned.clone());
+                    self.items.push(scanned);
+                }
+            }
+        }
+        self.items.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        changes
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TodoItem> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    pub fn open(&self) -> impl Iterator<Item = &TodoItem> {
+        self.items.iter().filter(|item| item.status == TodoStatus::Open)
+    }
+
+    /// Mark an item done by hand, e.g. by an agent whose change settles it
+    pub fn mark_done(&mut self, id: &str, note: impl Into<String>) -> Result<&TodoItem, TodoError> {
+        let item = self
+            .items
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| TodoError::NotFound(id.to_string()))?;
+        item.status = TodoStatus::Done;
+        item.done_at = Some(chrono::Utc::now());
+        item.note = Some(note.into());
+        Ok(&*item)
+    }
+
+    /// One line per open item: `id KIND file:line (owner) text`
+    pub fn render_open(&self) -> String {
+        let mut out = String::new();
+        for item in self.open() {
+            let owner = item.owner.as_deref().map(|owner| format!(" ({})", owner)).unwrap_or_default();
+            let _ = writeln!(out, "{} {} {}{} {}", item.id, item.kind, item.location(), owner, item.text);
+        }
+        out
+    }
+}
+
+/// TODO list errors
+#[derive(Error, Debug)]
+pub enum TodoError {
+    #[error("TODO item not found: {0}")]
+    NotFound(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_are_extracted_with_owners() {
+        let content = "fn f() {\n    // TODO(alice): handle empty input\n    let s = \"TODO not a comment\";\n    # FIXME @bob retry on 429\n    /* HACK: skip the cache */\n}\n";
+        let items = extract(Path::new("src/lib.rs"), content);
+        let summary: Vec<(TodoKind, usize, Option<&str>, &str)> =
+            items.iter().map(|item| (item.kind, item.line, item.owner.as_deref(), item.text.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (TodoKind::Todo, 2, Some("alice"), "handle empty input"),
+                (TodoKind::Fixme, 4, Some("bob"), "retry on 429"),
+                (TodoKind::Hack, 5, None, "skip the cache"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rescans_follow_moves_and_resolve_removed_comments() {
+        let path = Path::new("src/lib.rs");
+        let mut list = TodoList::default();
+        let changes = list.reconcile(extract(path, "// TODO: one\n// FIXME: two\n// TODO: three\n"));
+        assert_eq!(changes.added.len(), 3);
+        let one = list.items[0].id.clone();
+        let three = list.items[2].id.clone();
+        list.mark_done(&three, "done in the retry change").unwrap();
+
+        let changes = list.reconcile(extract(path, "\n\n// TODO: one\n// TODO: three\n"));
+        assert_eq!(changes.resolved.iter().map(|item| item.text.as_str()).collect::<Vec<_>>(), vec!["two"]);
+        assert_eq!(list.get(&one).unwrap().line, 3);
+        assert_eq!(list.get(&three).unwrap().status, TodoStatus::Done);
+        assert_eq!(list.render_open(), format!("{} TODO src/lib.rs:3 one\n", one));
+
+        // A resolved comment that comes back reopens
+        list.reconcile(extract(path, "// TODO: one\n// FIXME: two\n"));
+        assert_eq!(list.open().count(), 2);
+    }
+}
@@ -0,0 +1,132 @@
+//! Word-level diff between two versions of a response
+//!
+//! When a response is regenerated, the new answer is compared word by word
+//! with the one it replaces, so only what changed has to be read. The diff
+//! renders for the terminal (struck-through red removals, green additions)
+//! and as Markdown for exports.
+
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+/// Writes one changed word to the output, with its markers
+type Marker<'a> = &'a dyn Fn(&mut String, &str);
+
+/// A run of words the two versions share, or that only one of them has
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "text", rename_all = "lowercase")]
+pub enum Segment {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Word-level differences between an old and a new text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordDiff {
+    pub segments: Vec<Segment>,
+}
+
+impl WordDiff {
+    pub fn new(old: &str, new: &str) -> Self {
+        let diff = TextDiff::from_words(old, new);
+        let mut segments: Vec<Segment> = Vec::new();
+        for change in diff.iter_all_changes() {
+            let text = change.value();
+            match (change.tag(), segments.last_mut()) {
+                (ChangeTag::Equal, Some(Segment::Same(run)))
+                | (ChangeTag::Delete, Some(Segment::Removed(run)))
+                | (ChangeTag::Insert, Some(Segment::Added(run))) => run.push_str(text),
+                (ChangeTag::Equal, _) => segments.push(Segment::Same(text.to_string())),
+                (ChangeTag::Delete, _) => segments.push(Segment::Removed(text.to_string())),
+                (ChangeTag::Insert, _) => segments.push(Segment::Added(text.to_string())),
+            }
+        }
+        Self { segments }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.segments.iter().all(|segment| matches!(segment, Segment::Same(_)))
+    }
+
+    /// Share of the words that were removed or added, from 0.0 to 1.0
+    pub fn change_ratio(&self) -> f64 {
+        let (mut changed, mut total) = (0, 0);
+        for segment in &self.segments {
+            let (words, is_change) = match segment {
+                Segment::Same(text) => (text.split_whitespace().count(), false),
+                Segment::Removed(text) | Segment::Added(text) => (text.split_whitespace().count(), true),
+            };
+            total += words;
+            if is_change {
+                changed += words;
+            }
+        }
+        if total == 0 {
+            0.0
+        } else {
+            changed as f64 / total as f64
+        }
+    }
+
+    /// The diff with ANSI colors: removals red and struck through, additions green
+    pub fn render_ansi(&self) -> String {
+        self.render(|out, text| out.push_str(&format!("\x1b[9;31m{}\x1b[0m", text)), |out, text| {
+            out.push_str(&format!("\x1b[32m{}\x1b[0m", text))
+        })
+    }
+
+    /// The diff as Markdown: removals as `~~struck~~`, additions in **bold**
+    pub fn render_markdown(&self) -> String {
+        self.render(|out, text| out.push_str(&format!("~~{}~~", text)), |out, text| {
+            out.push_str(&format!("**{}**", text))
+        })
+    }
+
+    /// Changed runs are wrapped word by word, so the markers never span
+    /// whitespace or line breaks
+    fn render(&self, removed: impl Fn(&mut String, &str), added: impl Fn(&mut String, &str)) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            let (text, mark): (&str, Marker<'_>) = match segment {
+                Segment::Same(text) => {
+                    out.push_str(text);
+                    continue;
+                }
+                Segment::Removed(text) => (text, &removed),
+                Segment::Added(text) => (text, &added),
+            };
+            let mut rest = text;
+            while !rest.is_empty() {
+                let word_start = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+                out.push_str(&rest[..word_start]);
+                rest = &rest[word_start..];
+                let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                if word_end > 0 {
+                    mark(&mut out, &rest[..word_end]);
+                }
+                rest = &rest[word_end..];
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_words_and_renders() {
+        let diff = WordDiff::new("Use a mutex to guard the cache.", "Use an RwLock to guard the cache.");
+        assert!(!diff.is_unchanged());
+        let markdown = diff.render_markdown();
+        assert!(markdown.starts_with("Use ") && markdown.ends_with(" to guard the cache."));
+        assert!(markdown.contains("~~mutex~~") && markdown.contains("**RwLock**"));
+        assert!(diff.render_ansi().contains("\x1b[32mRwLock\x1b[0m"));
+        assert!(diff.change_ratio() > 0.0 && diff.change_ratio() < 1.0);
+
+        let same = WordDiff::new("unchanged answer", "unchanged answer");
+        assert!(same.is_unchanged());
+        assert_eq!(same.change_ratio(), 0.0);
+    }
+}
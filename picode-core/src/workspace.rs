@@ -5,9 +5,14 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 use walkdir::WalkDir;
-use ignore::gitignore::GitignoreBuilder;
+use crate::content_cache::ContentCache;
+use crate::ignore_rules::IgnoreRules;
+use crate::large_file::is_binary;
+use crate::workspace_stats::WorkspaceStats;
 
 /// Workspace configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +44,11 @@ impl Default for WorkspaceConfig {
         file_associations.insert("py".to_string(), "python".to_string());
         file_associations.insert("js".to_string(), "javascript".to_string());
         file_associations.insert("ts".to_string(), "typescript".to_string());
+        file_associations.insert("jsx".to_string(), "javascript".to_string());
+        file_associations.insert("tsx".to_string(), "typescript".to_string());
+        file_associations.insert("go".to_string(), "go".to_string());
+        file_associations.insert("java".to_string(), "java".to_string());
+        file_associations.insert("kt".to_string(), "kotlin".to_string());
         file_associations.insert("md".to_string(), "markdown".to_string());
         file_associations.insert("json".to_string(), "json".to_string());
         file_associations.insert("yaml".to_string(), "yaml".to_string());
@@ -65,6 +75,13 @@ pub struct Workspace {
     pub files: Vec<WorkspaceFile>,
     pub git_status: Option<GitStatus>,
     pub last_scan: chrono::DateTime<chrono::Utc>,
+    /// Languages, test/source ratio and frameworks as of the last scan
+    #[serde(default)]
+    pub stats: WorkspaceStats,
+    #[serde(skip)]
+    git_cache: GitStatusCache,
+    #[serde(skip)]
+    content_cache: Option<Arc<ContentCache>>,
 }
 
 /// File information within a workspace
@@ -102,6 +119,39 @@ pub struct GitStatus {
     pub untracked_files: usize,
     pub remote_ahead: usize,
     pub remote_behind: usize,
+    /// Submodules and nested repositories, each with its own status
+    #[serde(default)]
+    pub repos: Vec<NestedRepoStatus>,
+}
+
+/// How a repository inside the workspace repository is attached to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NestedRepoKind {
+    /// Registered in `.gitmodules`
+    Submodule,
+    /// A standalone clone inside the working tree
+    Nested,
+}
+
+/// Status of a repository inside the workspace, reported separately from
+/// the repository that contains it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NestedRepoStatus {
+    /// Repository directory relative to the containing repository
+    pub path: PathBuf,
+    pub kind: NestedRepoKind,
+    pub status: GitStatus,
+}
+
+/// Changed files belonging to a single repository, committed together
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoChanges {
+    /// Repository directory relative to the workspace root; empty for the
+    /// workspace repository itself
+    pub repo: PathBuf,
+    /// Changed paths relative to `repo`
+    pub files: Vec<PathBuf>,
 }
 
 /// Git file status
@@ -117,6 +167,284 @@ pub enum GitFileStatus {
     Ignored,
 }
 
+/// Raw result of a git status query
+#[derive(Debug, Clone, Default)]
+struct GitSnapshot {
+    branch: String,
+    entries: Vec<(PathBuf, GitFileStatus)>,
+    nested: Vec<NestedSnapshot>,
+}
+
+#[derive(Debug, Clone)]
+struct NestedSnapshot {
+    path: PathBuf,
+    kind: NestedRepoKind,
+    snapshot: GitSnapshot,
+}
+
+impl GitSnapshot {
+    /// Entries of this repository and all repositories below it, with paths
+    /// relative to this repository
+    fn all_entries(&self) -> Vec<(PathBuf, GitFileStatus)> {
+        let mut entries = self.entries.clone();
+        for nested in &self.nested {
+            entries.extend(
+                nested
+                    .snapshot
+                    .all_entries()
+                    .into_iter()
+                    .map(|(path, status)| (nested.path.join(path), status)),
+            );
+        }
+        entries
+    }
+    
+    fn to_status(&self) -> GitStatus {
+        let mut staged_files = 0;
+        let mut modified_files = 0;
+        let mut untracked_files = 0;
+        
+        let statuses: HashMap<&Path, &GitFileStatus> = self
+            .entries
+            .iter()
+            .map(|(path, status)| (path.as_path(), status))
+            .collect();
+        
+        for status in statuses.values() {
+            match status {
+                GitFileStatus::Added => staged_files += 1,
+                GitFileStatus::Modified | GitFileStatus::Deleted => modified_files += 1,
+                GitFileStatus::Untracked => untracked_files += 1,
+                _ => {}
+            }
+        }
+        
+        // TODO: Calculate remote ahead/behind (requires network operation)
+        GitStatus {
+            branch: self.branch.clone(),
+            is_dirty: !self.entries.is_empty(),
+            staged_files,
+            modified_files,
+            untracked_files,
+            remote_ahead: 0,
+            remote_behind: 0,
+            repos: self
+                .nested
+                .iter()
+                .map(|nested| NestedRepoStatus {
+                    path: nested.path.clone(),
+                    kind: nested.kind,
+                    status: nested.snapshot.to_status(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// How long a cached git status is reused at most, so edits the workspace
+/// was not notified of still show up
+const GIT_STATUS_TTL: Duration = Duration::from_secs(2);
+
+/// In-memory git status cache, valid until `.git/index` or `.git/HEAD`
+/// change, a working tree file changes or [`GIT_STATUS_TTL`] passes
+#[derive(Debug, Clone, Default)]
+struct GitStatusCache {
+    snapshot: Option<GitSnapshot>,
+    index_mtime: Option<SystemTime>,
+    head_mtime: Option<SystemTime>,
+    stored_at: Option<Instant>,
+    /// A working tree file changed since the snapshot; it is kept so a
+    /// partial refresh can merge into it, but not reused as is
+    worktree_changed: bool,
+}
+
+impl GitStatusCache {
+    fn store(&mut self, root: &Path, snapshot: GitSnapshot) {
+        // Record mtimes after the query since update_index may rewrite the index
+        self.index_mtime = git_file_mtime(root, "index");
+        self.head_mtime = git_file_mtime(root, "HEAD");
+        self.snapshot = Some(snapshot);
+        self.stored_at = Some(Instant::now());
+        self.worktree_changed = false;
+    }
+    
+    fn is_fresh(&self, root: &Path) -> bool {
+        self.snapshot.is_some()
+            && !self.worktree_changed
+            && self.stored_at.is_some_and(|stored_at| stored_at.elapsed() < GIT_STATUS_TTL)
+            && self.index_mtime.is_some()
+            && self.index_mtime == git_file_mtime(root, "index")
+            && self.head_mtime == git_file_mtime(root, "HEAD")
+    }
+    
+    fn invalidate(&mut self) {
+        self.snapshot = None;
+        self.index_mtime = None;
+        self.head_mtime = None;
+        self.stored_at = None;
+        self.worktree_changed = false;
+    }
+    
+    fn handle_fs_event(&mut self, path: &Path) -> bool {
+        let is_git_state = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .is_some_and(|dir| dir == ".git")
+            && path
+                .file_name()
+                .is_some_and(|name| name == "index" || name == "HEAD");
+        
+        if is_git_state {
+            self.invalidate();
+        } else if !path.components().any(|c| c.as_os_str() == ".git") {
+            self.worktree_changed = true;
+        }
+        is_git_state
+    }
+}
+
+fn git_file_mtime(root: &Path, name: &str) -> Option<SystemTime> {
+    std::fs::metadata(root.join(".git").join(name))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+/// Query git status on the blocking thread pool so it never stalls the UI
+async fn read_git_status_blocking(
+    root: PathBuf,
+    pathspecs: Vec<String>,
+) -> Result<Option<GitSnapshot>, WorkspaceError> {
+    tokio::task::spawn_blocking(move || read_git_status(&root, &pathspecs))
+        .await
+        .map_err(|e| WorkspaceError::Git(e.to_string()))?
+}
+
+fn read_git_status(root: &Path, pathspecs: &[String]) -> Result<Option<GitSnapshot>, WorkspaceError> {
+    let repo = match git2::Repository::open(root) {
+        Ok(repo) => repo,
+        Err(_) => return Ok(None),
+    };
+    
+    // Get current branch; a freshly initialized repository has no commit yet
+    let branch = match repo.head() {
+        Ok(head) => head.shorthand().unwrap_or("HEAD").to_string(),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => repo
+            .find_reference("HEAD")
+            .ok()
+            .and_then(|head| head.symbolic_target().map(|target| target.trim_start_matches("refs/heads/").to_string()))
+            .unwrap_or_else(|| "HEAD".to_string()),
+        Err(e) => return Err(WorkspaceError::Git(e.to_string())),
+    };
+    
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false)
+        .exclude_submodules(true)
+        .update_index(true);
+    for spec in pathspecs {
+        options.pathspec(spec);
+    }
+    
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| WorkspaceError::Git(e.to_string()))?;
+    
+    let mut entries: Vec<(PathBuf, GitFileStatus)> = statuses
+        .iter()
+        .map(|entry| {
+            let flags = entry.status();
+            let status = if flags.is_index_new() || flags.is_index_modified() {
+                GitFileStatus::Added
+            } else if flags.is_wt_modified() {
+                GitFileStatus::Modified
+            } else if flags.is_wt_new() {
+                GitFileStatus::Untracked
+            } else if flags.is_wt_deleted() {
+                GitFileStatus::Deleted
+            } else {
+                GitFileStatus::Unmodified
+            };
+            (PathBuf::from(entry.path().unwrap_or("")), status)
+        })
+        .collect();
+    
+    // Pathspec refreshes only touch this repository; nested repositories
+    // are rediscovered on full scans
+    let mut nested = Vec::new();
+    if pathspecs.is_empty() {
+        for (path, kind) in discover_nested_repos(&repo, root) {
+            // A nested repository that cannot be read (e.g. an uninitialized
+            // submodule) is left out rather than failing the whole scan
+            if let Ok(Some(snapshot)) = read_git_status(&root.join(&path), &[]) {
+                nested.push(NestedSnapshot { path, kind, snapshot });
+            }
+        }
+        // Nested clones show up as untracked directories of the outer repository
+        entries.retain(|(path, _)| !nested.iter().any(|n| path.starts_with(&n.path)));
+    }
+    
+    Ok(Some(GitSnapshot { branch, entries, nested }))
+}
+
+/// Submodules and nested clones directly inside `root`'s working tree, as
+/// paths relative to `root`; repositories inside those are left to them
+fn discover_nested_repos(repo: &git2::Repository, root: &Path) -> Vec<(PathBuf, NestedRepoKind)> {
+    let mut found: Vec<(PathBuf, NestedRepoKind)> = repo
+        .submodules()
+        .map(|submodules| {
+            submodules
+                .iter()
+                .map(|submodule| (submodule.path().to_path_buf(), NestedRepoKind::Submodule))
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+    for entry in walker.flatten() {
+        let path = entry.path();
+        if path == root || !entry.file_type().is_some_and(|t| t.is_dir()) || !path.join(".git").exists() {
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(path).to_path_buf();
+        if !found.iter().any(|(known, _)| relative.starts_with(known)) {
+            found.push((relative, NestedRepoKind::Nested));
+        }
+    }
+    
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
+}
+
+/// Stage `files` (relative to `repo_dir`) and commit them on HEAD
+fn commit_paths(repo_dir: &Path, files: &[PathBuf], message: &str) -> Result<String, WorkspaceError> {
+    let git = |e: git2::Error| WorkspaceError::Git(e.to_string());
+    let repo = git2::Repository::open(repo_dir).map_err(git)?;
+    
+    let mut index = repo.index().map_err(git)?;
+    for file in files {
+        if repo_dir.join(file).exists() {
+            index.add_path(file).map_err(git)?;
+        } else {
+            index.remove_path(file).map_err(git)?;
+        }
+    }
+    index.write().map_err(git)?;
+    
+    let tree = repo.find_tree(index.write_tree().map_err(git)?).map_err(git)?;
+    let signature = repo.signature().map_err(git)?;
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let oid = repo
+        .commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+        .map_err(git)?;
+    Ok(oid.to_string())
+}
+
 impl Workspace {
     pub fn new(config: WorkspaceConfig) -> Self {
         Self {
@@ -124,9 +452,22 @@ impl Workspace {
             files: Vec::new(),
             git_status: None,
             last_scan: chrono::Utc::now(),
+            stats: WorkspaceStats::default(),
+            git_cache: GitStatusCache::default(),
+            content_cache: None,
         }
     }
     
+    /// Share a file content cache that is kept in step with fs events
+    pub fn with_content_cache(mut self, cache: Arc<ContentCache>) -> Self {
+        self.content_cache = Some(cache);
+        self
+    }
+    
+    pub fn content_cache(&self) -> Option<&Arc<ContentCache>> {
+        self.content_cache.as_ref()
+    }
+    
     pub async fn scan(&mut self) -> Result<(), WorkspaceError> {
         self.scan_files().await?;
         if self.config.git_enabled {
@@ -138,27 +479,38 @@ impl Workspace {
     
     async fn scan_files(&mut self) -> Result<(), WorkspaceError> {
         let mut files = Vec::new();
-        let ignore_patterns = GitignoreBuilder::new(&self.config.root_path)
-            .build()
-            .map_err(|e| WorkspaceError::FileScan(e.to_string()))?;
+        let mut stats = WorkspaceStats::default();
+        let ignore_rules = self.ignore_rules()?;
         
         for entry in WalkDir::new(&self.config.root_path)
             .into_iter()
-            .filter_entry(|e| !self.should_ignore(e.path()))
+            .filter_entry(|e| !ignore_rules.is_ignored(e.path(), e.file_type().is_dir()))
         {
             let entry = entry.map_err(|e| WorkspaceError::FileScan(e.to_string()))?;
             
             if entry.file_type().is_file() {
                 let path = entry.path().to_path_buf();
-                let relative_path = path
-                    .strip_prefix(&self.config.root_path)
+                let relative_path = crate::paths::strip_root(&path, &self.config.root_path)
                     .unwrap_or(&path)
                     .to_path_buf();
                 
                 let metadata = entry.metadata().map_err(|e| WorkspaceError::FileScan(e.to_string()))?;
                 let file_type = self.classify_file(&relative_path);
                 let language = self.detect_language(&relative_path);
-                let is_binary = self.is_binary_file(&path).await;
+                // Large files are mapped rather than read into memory
+                let mapped = if metadata.len() > crate::large_file::DEFAULT_THRESHOLD {
+                    crate::large_file::MappedFile::open(&path).ok()
+                } else {
+                    None
+                };
+                let content = match mapped {
+                    Some(_) => Vec::new(),
+                    None => tokio::fs::read(&path).await.unwrap_or_default(),
+                };
+                let content = mapped.as_ref().map_or(content.as_slice(), |mapped| mapped.bytes());
+                let is_binary = is_binary(content);
+                let text: &[u8] = if is_binary { &[] } else { content };
+                stats.record(&relative_path, language.as_deref(), &file_type, text);
                 
                 let file = WorkspaceFile {
                     path: path.clone(),
@@ -178,97 +530,184 @@ impl Workspace {
             }
         }
         
+        stats.finish();
         self.files = files;
+        self.stats = stats;
         Ok(())
     }
     
     async fn scan_git(&mut self) -> Result<(), WorkspaceError> {
-        let repo = match git2::Repository::open(&self.config.root_path) {
-            Ok(repo) => repo,
-            Err(_) => {
+        if self.git_cache.is_fresh(&self.config.root_path) {
+            if let Some(snapshot) = self.git_cache.snapshot.clone() {
+                self.apply_git_snapshot(&snapshot);
+                return Ok(());
+            }
+        }
+        
+        let snapshot = read_git_status_blocking(self.config.root_path.clone(), Vec::new()).await?;
+        match snapshot {
+            Some(snapshot) => {
+                self.apply_git_snapshot(&snapshot);
+                self.git_cache.store(&self.config.root_path, snapshot);
+            }
+            None => {
                 // Not a git repository
                 self.git_status = None;
-                return Ok(());
+                self.git_cache.invalidate();
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Refresh git status only for the given path prefixes (relative to the
+    /// workspace root), merging the result into the cached status
+    pub async fn scan_git_paths(&mut self, pathspecs: &[String]) -> Result<(), WorkspaceError> {
+        let mut cached = match self.git_cache.snapshot.clone() {
+            Some(snapshot) if !pathspecs.is_empty() => snapshot,
+            _ => {
+                self.git_cache.invalidate();
+                return self.scan_git().await;
             }
         };
         
-        // Get current branch
-        let head = repo.head().map_err(|e| WorkspaceError::Git(e.to_string()))?;
-        let branch = head
-            .shorthand()
-            .unwrap_or("HEAD")
-            .to_string();
+        // Paths inside a submodule or nested clone are invisible to the outer
+        // repository's status, so those need a full rescan
+        let nested = self.nested_repos();
+        if pathspecs
+            .iter()
+            .any(|spec| nested.iter().any(|(repo, _)| Path::new(spec).starts_with(repo) || repo.starts_with(spec)))
+        {
+            self.git_cache.invalidate();
+            return self.scan_git().await;
+        }
         
-        // Get status
-        let statuses = repo
-            .statuses(None)
-            .map_err(|e| WorkspaceError::Git(e.to_string()))?;
+        let partial = match read_git_status_blocking(self.config.root_path.clone(), pathspecs.to_vec()).await? {
+            Some(partial) => partial,
+            None => return Ok(()),
+        };
         
-        let mut staged_files = 0;
-        let mut modified_files = 0;
-        let mut untracked_files = 0;
-        let is_dirty = !statuses.is_empty();
+        cached
+            .entries
+            .retain(|(path, _)| !pathspecs.iter().any(|spec| path.starts_with(spec)));
+        cached.entries.extend(partial.entries);
+        cached.branch = partial.branch;
         
-        // Update file git status
-        for status in statuses.iter() {
-            let status_flags = status.status();
-            let file_path = PathBuf::from(status.path().unwrap_or(""));
-            
-            let git_status = if status_flags.is_index_new() || status_flags.is_index_modified() {
-                staged_files += 1;
-                Some(GitFileStatus::Added)
-            } else if status_flags.is_wt_modified() {
-                modified_files += 1;
-                Some(GitFileStatus::Modified)
-            } else if status_flags.is_wt_new() {
-                untracked_files += 1;
-                Some(GitFileStatus::Untracked)
-            } else if status_flags.is_wt_deleted() {
-                modified_files += 1;
-                Some(GitFileStatus::Deleted)
-            } else {
-                Some(GitFileStatus::Unmodified)
-            };
-            
-            // Update corresponding file
-            if let Some(file) = self.files.iter_mut().find(|f| f.relative_path == file_path) {
-                file.git_status = git_status;
-            }
+        self.apply_git_snapshot(&cached);
+        self.git_cache.store(&self.config.root_path, cached);
+        Ok(())
+    }
+    
+    /// Notify the workspace of a file system change; changes to git state
+    /// files (`.git/index`, `.git/HEAD`) invalidate the cached status,
+    /// working tree changes make the next scan query it again, and any
+    /// cached content of the path is dropped
+    pub fn handle_fs_event(&mut self, path: &Path) -> bool {
+        if let Some(cache) = &self.content_cache {
+            cache.handle_fs_event(path);
         }
+        self.git_cache.handle_fs_event(path)
+    }
+    
+    fn apply_git_snapshot(&mut self, snapshot: &GitSnapshot) {
+        let entries = snapshot.all_entries();
+        let statuses: HashMap<&Path, &GitFileStatus> = entries
+            .iter()
+            .map(|(path, status)| (path.as_path(), status))
+            .collect();
         
-        // TODO: Calculate remote ahead/behind (requires network operation)
-        let remote_ahead = 0;
-        let remote_behind = 0;
-        
-        self.git_status = Some(GitStatus {
-            branch,
-            is_dirty,
-            staged_files,
-            modified_files,
-            untracked_files,
-            remote_ahead,
-            remote_behind,
-        });
+        // Update file git status, including files inside nested repositories
+        for file in self.files.iter_mut() {
+            file.git_status = statuses.get(file.relative_path.as_path()).map(|s| (*s).clone());
+        }
         
-        Ok(())
+        self.git_status = Some(snapshot.to_status());
     }
     
-    fn should_ignore(&self, path: &Path) -> bool {
-        let path_str = path.to_string_lossy();
+    /// Submodules and nested repositories at any depth, as paths relative to
+    /// the workspace root
+    pub fn nested_repos(&self) -> Vec<(PathBuf, NestedRepoKind)> {
+        fn collect(repos: &[NestedRepoStatus], prefix: &Path, out: &mut Vec<(PathBuf, NestedRepoKind)>) {
+            for repo in repos {
+                let path = prefix.join(&repo.path);
+                collect(&repo.status.repos, &path, out);
+                out.push((path, repo.kind));
+            }
+        }
         
-        self.config.ignore_patterns.iter().any(|pattern| {
-            if pattern.ends_with('/') {
-                // Directory pattern
-                path.is_dir() && path_str.contains(pattern.trim_end_matches('/'))
-            } else if pattern.contains('*') {
-                // Glob pattern (simple implementation)
-                let pattern_without_star = pattern.replace('*', "");
-                path_str.contains(&pattern_without_star)
-            } else {
-                // Exact match
-                path_str.contains(pattern)
+        let mut repos = Vec::new();
+        if let Some(status) = &self.git_status {
+            collect(&status.repos, Path::new(""), &mut repos);
+        }
+        repos
+    }
+    
+    /// Repository owning a workspace-relative path: the innermost nested
+    /// repository containing it, or the empty path for the workspace repository
+    pub fn repo_for(&self, relative_path: &Path) -> PathBuf {
+        self.nested_repos()
+            .into_iter()
+            .map(|(repo, _)| repo)
+            .filter(|repo| relative_path.starts_with(repo))
+            .max_by_key(|repo| repo.components().count())
+            .unwrap_or_default()
+    }
+    
+    /// Split paths (absolute or relative to the workspace root) by the
+    /// repository they belong to, so each group can be committed on its own
+    pub fn group_by_repo(&self, paths: &[PathBuf]) -> Vec<RepoChanges> {
+        let mut groups: Vec<RepoChanges> = Vec::new();
+        for path in paths {
+            let relative = crate::paths::strip_root(path, &self.config.root_path).unwrap_or(path);
+            let repo = self.repo_for(relative);
+            let file = relative.strip_prefix(&repo).unwrap_or(relative).to_path_buf();
+            match groups.iter_mut().find(|group| group.repo == repo) {
+                Some(group) => group.files.push(file),
+                None => groups.push(RepoChanges { repo, files: vec![file] }),
             }
-        })
+        }
+        groups.sort_by(|a, b| a.repo.cmp(&b.repo));
+        groups
+    }
+    
+    /// Every changed path from the last git scan, across all repositories,
+    /// relative to the workspace root
+    pub fn changed_paths(&self) -> Vec<PathBuf> {
+        self.git_cache
+            .snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.all_entries().into_iter().map(|(path, _)| path).collect())
+            .unwrap_or_default()
+    }
+    
+    /// Commit one repository's changes. Files belonging to a different
+    /// repository are rejected so one commit never spans repositories.
+    pub async fn commit(&self, changes: &RepoChanges, message: &str) -> Result<String, WorkspaceError> {
+        if let Some(stray) = changes
+            .files
+            .iter()
+            .find(|file| self.repo_for(&changes.repo.join(file)) != changes.repo)
+        {
+            return Err(WorkspaceError::Git(format!(
+                "{} belongs to repository {}, not {}",
+                changes.repo.join(stray).display(),
+                self.repo_for(&changes.repo.join(stray)).display(),
+                changes.repo.display()
+            )));
+        }
+        
+        let repo_dir = self.config.root_path.join(&changes.repo);
+        let files = changes.files.clone();
+        let message = message.to_string();
+        tokio::task::spawn_blocking(move || commit_paths(&repo_dir, &files, &message))
+            .await
+            .map_err(|e| WorkspaceError::Git(e.to_string()))?
+    }
+
+    /// The configured ignore patterns together with the workspace's
+    /// `.gitignore` and `.picodeignore` files
+    pub fn ignore_rules(&self) -> Result<IgnoreRules, WorkspaceError> {
+        IgnoreRules::new(&self.config.root_path, &self.config.ignore_patterns)
     }
     
     fn classify_file(&self, path: &Path) -> FileType {
@@ -305,17 +744,6 @@ impl Workspace {
             .cloned()
     }
     
-    async fn is_binary_file(&self, path: &Path) -> bool {
-        // Simple binary detection: read first few bytes and check for null bytes
-        match tokio::fs::read(path).await {
-            Ok(bytes) => {
-                let sample_size = std::cmp::min(bytes.len(), 512);
-                bytes[..sample_size].contains(&0)
-            }
-            Err(_) => false,
-        }
-    }
-    
     pub fn get_files_by_type(&self, file_type: FileType) -> Vec<&WorkspaceFile> {
         self.files.iter().filter(|f| f.file_type == file_type).collect()
     }
@@ -414,13 +842,14 @@ mod tests {
     #[test]
     fn ignore_patterns() {
         let config = WorkspaceConfig::default();
-        let workspace = Workspace::new(config);
+        let rules = Workspace::new(config).ignore_rules().unwrap();
         
-        assert!(workspace.should_ignore(Path::new("target/")));
-        assert!(workspace.should_ignore(Path::new("node_modules/")));
-        assert!(workspace.should_ignore(Path::new("file.log")));
-        assert!(workspace.should_ignore(Path::new("temp.tmp")));
-        assert!(!workspace.should_ignore(Path::new("src/main.rs")));
+        assert!(rules.is_ignored(Path::new("target"), true));
+        assert!(rules.is_ignored(Path::new("node_modules"), true));
+        assert!(rules.is_ignored(Path::new("file.log"), false));
+        assert!(rules.is_ignored(Path::new("temp.tmp"), false));
+        assert!(!rules.is_ignored(Path::new("src/main.rs"), false));
+        assert!(!rules.is_ignored(Path::new("src/target_utils.rs"), false));
     }
 
     #[tokio::test]
@@ -453,6 +882,101 @@ mod tests {
         assert_eq!(doc_files.len(), 1);
     }
 
+    #[test]
+    fn git_cache_invalidated_by_git_state_events() {
+        let mut workspace = Workspace::new(WorkspaceConfig::default());
+        workspace.git_cache.snapshot = Some(GitSnapshot::default());
+        
+        assert!(!workspace.handle_fs_event(Path::new("repo/src/main.rs")));
+        assert!(workspace.git_cache.snapshot.is_some());
+        assert!(workspace.git_cache.worktree_changed);
+        
+        assert!(workspace.handle_fs_event(Path::new("repo/.git/index")));
+        assert!(workspace.git_cache.snapshot.is_none());
+    }
+    
+    #[test]
+    fn git_snapshot_applied_to_files() {
+        let mut workspace = Workspace::new(WorkspaceConfig::default());
+        workspace.files.push(WorkspaceFile {
+            path: PathBuf::from("main.rs"),
+            relative_path: PathBuf::from("main.rs"),
+            file_type: FileType::Source,
+            language: Some("rust".to_string()),
+            size: 10,
+            modified: chrono::Utc::now(),
+            is_binary: false,
+            git_status: None,
+        });
+        
+        workspace.apply_git_snapshot(&GitSnapshot {
+            branch: "main".to_string(),
+            entries: vec![
+                (PathBuf::from("main.rs"), GitFileStatus::Modified),
+                (PathBuf::from("new.rs"), GitFileStatus::Untracked),
+            ],
+            ..GitSnapshot::default()
+        });
+        
+        let status = workspace.git_status.as_ref().unwrap();
+        assert_eq!(status.branch, "main");
+        assert_eq!(status.modified_files, 1);
+        assert_eq!(status.untracked_files, 1);
+        assert_eq!(workspace.files[0].git_status, Some(GitFileStatus::Modified));
+    }
+
+    fn init_repo(path: &Path) -> git2::Repository {
+        let repo = git2::Repository::init(path).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "PiCode Test").unwrap();
+        config.set_str("user.email", "test@picode.org").unwrap();
+        repo
+    }
+    
+    #[tokio::test]
+    async fn nested_repos_reported_and_committed_separately() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        init_repo(&root);
+        init_repo(&root.join("vendor").join("lib"));
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("vendor/lib/lib.rs"), "// vendored").unwrap();
+        
+        let mut workspace = Workspace::new(WorkspaceConfig { root_path: root.clone(), ..Default::default() });
+        workspace.scan().await.unwrap();
+        
+        let status = workspace.git_status.as_ref().unwrap();
+        assert_eq!(status.untracked_files, 1);
+        assert_eq!(status.repos.len(), 1);
+        assert_eq!(status.repos[0].path, PathBuf::from("vendor/lib"));
+        assert_eq!(status.repos[0].kind, NestedRepoKind::Nested);
+        assert_eq!(status.repos[0].status.untracked_files, 1);
+        assert_eq!(
+            workspace.find_file(Path::new("vendor/lib/lib.rs")).unwrap().git_status,
+            Some(GitFileStatus::Untracked)
+        );
+        
+        let groups = workspace.group_by_repo(&workspace.changed_paths());
+        assert_eq!(
+            groups,
+            vec![
+                RepoChanges { repo: PathBuf::new(), files: vec![PathBuf::from("main.rs")] },
+                RepoChanges { repo: PathBuf::from("vendor/lib"), files: vec![PathBuf::from("lib.rs")] },
+            ]
+        );
+        
+        let mixed = RepoChanges {
+            repo: PathBuf::new(),
+            files: vec![PathBuf::from("main.rs"), PathBuf::from("vendor/lib/lib.rs")],
+        };
+        assert!(workspace.commit(&mixed, "mixed").await.is_err());
+        
+        workspace.commit(&groups[1], "Add vendored lib").await.unwrap();
+        let nested = git2::Repository::open(root.join("vendor/lib")).unwrap();
+        assert_eq!(nested.head().unwrap().peel_to_commit().unwrap().message(), Some("Add vendored lib"));
+        assert!(git2::Repository::open(&root).unwrap().head().is_err());
+    }
+    
     #[test]
     fn git_file_status_classification() {
         assert_eq!(GitFileStatus::Modified, GitFileStatus::Modified);
@@ -0,0 +1,285 @@
+//! Workspace language statistics and framework detection
+//!
+//! A [`WorkspaceStats`] is built while the workspace is scanned: every text
+//! file adds its lines to its language and to the source or test total,
+//! manifests (`Cargo.toml`, `package.json`, `pyproject.toml`, ...) name the
+//! frameworks a project depends on, and imports show how much of the code
+//! actually uses them. The result is a short project fingerprint for users
+//! (`/analyze`, `picode stats workspace`) and for the model's system prompt.
+
+use crate::workspace::FileType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Frameworks recognised: name, languages, dependency names looked up in
+/// manifests, and import fragments looked up in source files
+struct FrameworkSignature {
+    name: &'static str,
+    languages: &'static [&'static str],
+    dependencies: &'static [&'static str],
+    imports: &'static [&'static str],
+}
+
+const RUST: &[&str] = &["rust"];
+const JS: &[&str] = &["javascript", "typescript"];
+const PYTHON: &[&str] = &["python"];
+const JAVA: &[&str] = &["java", "kotlin"];
+const GO: &[&str] = &["go"];
+
+const FRAMEWORKS: &[FrameworkSignature] = &[
+    FrameworkSignature { name: "Tokio", languages: RUST, dependencies: &["tokio"], imports: &["tokio::"] },
+    FrameworkSignature { name: "Axum", languages: RUST, dependencies: &["axum"], imports: &["axum::"] },
+    FrameworkSignature { name: "Actix Web", languages: RUST, dependencies: &["actix-web"], imports: &["actix_web::"] },
+    FrameworkSignature { name: "Clap", languages: RUST, dependencies: &["clap"], imports: &["clap::"] },
+    FrameworkSignature { name: "Ratatui", languages: RUST, dependencies: &["ratatui"], imports: &["ratatui::"] },
+    FrameworkSignature { name: "React", languages: JS, dependencies: &["react"], imports: &["from 'react'", "from \"react\"", "require('react')"] },
+    FrameworkSignature { name: "Next.js", languages: JS, dependencies: &["next"], imports: &["from 'next/", "from \"next/"] },
+    FrameworkSignature { name: "Vue", languages: JS, dependencies: &["vue"], imports: &["from 'vue'", "from \"vue\""] },
+    FrameworkSignature { name: "Express", languages: JS, dependencies: &["express"], imports: &["require('express')", "from 'express'", "from \"express\""] },
+    FrameworkSignature { name: "Django", languages: PYTHON, dependencies: &["django"], imports: &["from django", "import django"] },
+    FrameworkSignature { name: "Flask", languages: PYTHON, dependencies: &["flask"], imports: &["from flask", "import flask"] },
+    FrameworkSignature { name: "FastAPI", languages: PYTHON, dependencies: &["fastapi"], imports: &["from fastapi", "import fastapi"] },
+    FrameworkSignature { name: "pytest", languages: PYTHON, dependencies: &["pytest"], imports: &["import pytest"] },
+    FrameworkSignature { name: "Spring", languages: JAVA, dependencies: &["org.springframework"], imports: &["import org.springframework"] },
+    FrameworkSignature { name: "Gin", languages: GO, dependencies: &["github.com/gin-gonic/gin"], imports: &["\"github.com/gin-gonic/gin\""] },
+];
+
+/// Manifest file names and the language whose frameworks they declare
+const MANIFESTS: &[(&str, &[&str])] = &[
+    ("Cargo.toml", RUST),
+    ("package.json", JS),
+    ("pyproject.toml", PYTHON),
+    ("requirements.txt", PYTHON),
+    ("setup.py", PYTHON),
+    ("pom.xml", JAVA),
+    ("build.gradle", JAVA),
+    ("build.gradle.kts", JAVA),
+    ("go.mod", GO),
+];
+
+/// Files and lines of one language
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanguageStats {
+    pub files: usize,
+    pub lines: usize,
+}
+
+/// A framework the workspace uses, with the evidence for it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameworkMatch {
+    pub name: String,
+    /// Manifests declaring it, relative to the workspace root
+    pub manifests: Vec<String>,
+    /// Source files importing it
+    pub importing_files: usize,
+}
+
+impl FrameworkMatch {
+    /// Declarations weigh more than any single import
+    fn score(&self) -> usize {
+        self.manifests.len() * 10 + self.importing_files
+    }
+}
+
+/// Project fingerprint gathered during a workspace scan
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceStats {
+    pub files: usize,
+    /// Text files by language
+    pub languages: BTreeMap<String, LanguageStats>,
+    pub source_lines: usize,
+    pub test_lines: usize,
+    /// Test lines per source line; `None` without source
+    pub test_ratio: Option<f64>,
+    /// Most used first
+    pub frameworks: Vec<FrameworkMatch>,
+    /// Language with the most lines
+    pub dominant_language: Option<String>,
+    /// Best-supported framework of the dominant language, or overall
+    pub dominant_framework: Option<String>,
+}
+
+impl WorkspaceStats {
+    /// Add one scanned file
+    pub fn record(&mut self, relative_path: &Path, language: Option<&str>, file_type: &FileType, content: &[u8]) {
+        self.files += 1;
+        let Ok(text) = std::str::from_utf8(content) else {
+            return;
+        };
+        let lines = text.lines().count();
+        if let Some(language) = language {
+            let stats = self.languages.entry(language.to_string()).or_default();
+            stats.files += 1;
+            stats.lines += lines;
+        }
+        match file_type {
+            FileType::Source => self.source_lines += lines,
+            FileType::Test => self.test_lines += lines,
+            _ => {}
+        }
+
+        let file_name = relative_path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if let Some((_, languages)) = MANIFESTS.iter().find(|(manifest, _)| *manifest == file_name) {
+            let text = text.to_lowercase();
+            for signature in FRAMEWORKS.iter().filter(|s| s.languages == *languages) {
+                if signature.dependencies.iter().any(|dependency| contains_word(&text, dependency)) {
+                    let manifest = crate::paths::to_slash(relative_path);
+                    self.framework(signature.name).manifests.push(manifest);
+                }
+            }
+        } else if let Some(language) = language {
+            for signature in FRAMEWORKS.iter().filter(|s| s.languages.contains(&language)) {
+                if signature.imports.iter().any(|import| text.contains(import)) {
+                    self.framework(signature.name).importing_files += 1;
+                }
+            }
+        }
+    }
+
+    /// Derive the ratio, the ordering and the dominant entries once every
+    /// file is recorded
+    pub fn finish(&mut self) {
+        self.test_ratio = (self.source_lines > 0).then(|| self.test_lines as f64 / self.source_lines as f64);
+        self.frameworks.sort_by(|a, b| b.score().cmp(&a.score()).then_with(|| a.name.cmp(&b.name)));
+        self.dominant_language = self
+            .languages
+            .iter()
+            .filter(|(language, _)| is_programming_language(language))
+            .max_by_key(|(_, stats)| stats.lines)
+            .map(|(language, _)| language.clone());
+
+        let of_dominant_language = |framework: &&FrameworkMatch| {
+            let languages = FRAMEWORKS.iter().find(|s| s.name == framework.name).map(|s| s.languages).unwrap_or_default();
+            self.dominant_language.as_deref().is_some_and(|language| languages.contains(&language))
+        };
+        self.dominant_framework = self
+            .frameworks
+            .iter()
+            .find(of_dominant_language)
+            .or_else(|| self.frameworks.first())
+            .map(|framework| framework.name.clone());
+    }
+
+    /// Human readable report, used by `/analyze` and `picode stats workspace`
+    pub fn render(&self) -> String {
+        let mut out = format!("{} files", self.files);
+        if let Some(language) = &self.dominant_language {
+            let _ = write!(out, ", mostly {}", language);
+        }
+        if let Some(framework) = &self.dominant_framework {
+            let _ = write!(out, " ({})", framework);
+        }
+        out.push('\n');
+
+        let mut languages: Vec<_> = self.languages.iter().collect();
+        languages.sort_by(|a, b| b.1.lines.cmp(&a.1.lines).then_with(|| a.0.cmp(b.0)));
+        for (language, stats) in languages {
+            let _ = writeln!(out, "  {:<12} {:>5} files {:>8} lines", language, stats.files, stats.lines);
+        }
+        let _ = write!(out, "Source {} lines, tests {} lines", self.source_lines, self.test_lines);
+        if let Some(ratio) = self.test_ratio {
+            let _ = write!(out, " (test/source {:.2})", ratio);
+        }
+        out.push('\n');
+        for framework in &self.frameworks {
+            let mut evidence = framework.manifests.clone();
+            if framework.importing_files > 0 {
+                evidence.push(format!("imported in {} file(s)", framework.importing_files));
+            }
+            let _ = writeln!(out, "Framework {}: {}", framework.name, evidence.join(", "));
+        }
+        out
+    }
+
+    /// Project summary for the system prompt; `None` for an empty workspace
+    pub fn prompt_layer(&self) -> Option<String> {
+        let language = self.dominant_language.as_ref()?;
+        let mut out = format!("The project is written mainly in {}", language);
+        let others: Vec<_> = self
+            .languages
+            .iter()
+            .filter(|(name, stats)| *name != language && is_programming_language(name) && stats.lines > 0)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !others.is_empty() {
+            let _ = write!(out, ", with some {}", others.join(", "));
+        }
+        out.push('.');
+        if !self.frameworks.is_empty() {
+            let names: Vec<_> = self.frameworks.iter().map(|f| f.name.as_str()).collect();
+            let _ = write!(out, " It uses {}; follow their conventions.", names.join(", "));
+        }
+        if let Some(ratio) = self.test_ratio {
+            let _ = write!(out, " Test code is {:.0}% of the size of the source.", ratio * 100.0);
+        }
+        Some(out)
+    }
+
+    fn framework(&mut self, name: &str) -> &mut FrameworkMatch {
+        let position = match self.frameworks.iter().position(|f| f.name == name) {
+            Some(position) => position,
+            None => {
+                self.frameworks.push(FrameworkMatch { name: name.to_string(), manifests: Vec::new(), importing_files: 0 });
+                self.frameworks.len() - 1
+            }
+        };
+        &mut self.frameworks[position]
+    }
+}
+
+/// Markup and data formats do not make a project's language
+fn is_programming_language(language: &str) -> bool {
+    !matches!(language, "markdown" | "json" | "yaml" | "toml")
+}
+
+/// Whether `needle` occurs in `text` as a whole dependency name, so `tokio`
+/// does not match `tokio-util` and `react` does not match `react-dom`
+fn contains_word(text: &str, needle: &str) -> bool {
+    let is_name = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    text.match_indices(needle).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + needle.len()..].chars().next();
+        !before.is_some_and(is_name) && !after.is_some_and(is_name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprints_a_rust_project() {
+        let mut stats = WorkspaceStats::default();
+        let cargo = "[dependencies]\ntokio = { version = \"1\" }\ntokio-util = \"0.7\"\nserde = \"1\"\n";
+        stats.record(Path::new("Cargo.toml"), Some("toml"), &FileType::Config, cargo.as_bytes());
+        stats.record(Path::new("src/main.rs"), Some("rust"), &FileType::Source, b"use tokio::net;\nfn main() {}\n\n\n");
+        stats.record(Path::new("tests/it.rs"), Some("rust"), &FileType::Test, b"#[tokio::test]\nasync fn t() {}\n");
+        stats.record(Path::new("web/app.js"), Some("javascript"), &FileType::Source, b"import React from 'react';\n");
+        stats.record(Path::new("logo.png"), None, &FileType::Asset, &[0x89, 0xff, 0x00]);
+        stats.finish();
+
+        assert_eq!(stats.files, 5);
+        assert_eq!(stats.languages["rust"], LanguageStats { files: 2, lines: 6 });
+        assert_eq!(stats.test_ratio, Some(2.0 / 5.0));
+        assert_eq!(stats.dominant_language.as_deref(), Some("rust"));
+        assert_eq!(stats.dominant_framework.as_deref(), Some("Tokio"));
+        assert_eq!(stats.frameworks[0].manifests, vec!["Cargo.toml"]);
+        assert_eq!(stats.frameworks[0].importing_files, 2);
+        assert_eq!(stats.frameworks.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["Tokio", "React"]);
+
+        let layer = stats.prompt_layer().unwrap();
+        assert!(layer.starts_with("The project is written mainly in rust, with some javascript."));
+        assert!(layer.contains("It uses Tokio, React"));
+        assert!(stats.render().contains("Framework Tokio: Cargo.toml, imported in 2 file(s)"));
+    }
+
+    #[test]
+    fn dependency_names_match_whole_words() {
+        assert!(contains_word("\"react\": \"^18\"", "react"));
+        assert!(!contains_word("\"react-dom\": \"^18\"", "react"));
+        assert!(contains_word("<groupid>org.springframework.boot</groupid>", "org.springframework"));
+        assert!(WorkspaceStats::default().prompt_layer().is_none());
+    }
+}
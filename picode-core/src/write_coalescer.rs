@@ -0,0 +1,343 @@
+//! Batched persistence for frequently saved files
+//!
+//! With auto-save on, every message rewrites the session and conversation
+//! files. [`CoalescingFileSystem`] wraps another [`FileSystem`] and keeps
+//! only the latest content of each written path in memory, writing it out
+//! every flush interval and on [`flush`](CoalescingFileSystem::flush) (call
+//! it on shutdown). Ten saves of a conversation between two flushes cost a
+//! single write, which matters on network home directories. Reads see
+//! pending content, and content that is being flushed until its write has
+//! finished, so callers cannot tell writes are deferred. Removes and renames
+//! wait for a running flush, so a write landing late cannot undo them.
+
+use crate::io::FileSystem;
+use crate::large_file::{LineRange, Window};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Default time between flushes
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Writes requested versus performed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoalescingStats {
+    pub requested: u64,
+    pub written: u64,
+    /// Paths waiting for the next flush
+    pub pending: usize,
+}
+
+/// Content not yet on the inner file system
+#[derive(Debug, Default)]
+struct Buffers {
+    /// Written since the last flush began
+    pending: BTreeMap<PathBuf, Arc<[u8]>>,
+    /// Taken by the running flush and not written yet
+    in_flight: BTreeMap<PathBuf, Arc<[u8]>>,
+}
+
+impl Buffers {
+    /// The latest content of `path` that is not on the inner file system
+    fn get(&self, path: &Path) -> Option<Arc<[u8]>> {
+        self.pending.get(path).or_else(|| self.in_flight.get(path)).cloned()
+    }
+}
+
+/// File system whose writes are held back and flushed in batches
+#[derive(Debug)]
+pub struct CoalescingFileSystem {
+    inner: Arc<dyn FileSystem>,
+    buffers: Mutex<Buffers>,
+    /// Held while a flush writes, and by removes and renames
+    flushing: tokio::sync::Mutex<()>,
+    interval: Duration,
+    requested: AtomicU64,
+    written: AtomicU64,
+}
+
+impl CoalescingFileSystem {
+    pub fn new(inner: Arc<dyn FileSystem>, interval: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            inner,
+            buffers: Mutex::new(Buffers::default()),
+            flushing: tokio::sync::Mutex::new(()),
+            interval,
+            requested: AtomicU64::new(0),
+            written: AtomicU64::new(0),
+        })
+    }
+
+    /// Flush every interval on a background task. Once every other handle
+    /// is dropped the task flushes one last time and ends. Needs a Tokio
+    /// runtime; flush explicitly before the process exits.
+    pub fn spawn_flusher(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let fs = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(fs.interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = fs.flush().await {
+                    tracing::warn!("Deferred write failed, retrying at the next flush: {}", e);
+                }
+                if Arc::strong_count(&fs) == 1 {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Write all pending content now; returns the number of files written.
+    /// Files that fail stay pending unless they were written again meanwhile.
+    pub async fn flush(&self) -> io::Result<usize> {
+        let _flushing = self.flushing.lock().await;
+        let batch = {
+            let mut buffers = self.lock();
+            buffers.in_flight = std::mem::take(&mut buffers.pending);
+            buffers.in_flight.clone()
+        };
+        let mut written = 0;
+        let mut first_error = None;
+        for (path, contents) in batch {
+            let result = self.inner.write(&path, &contents).await;
+            let mut buffers = self.lock();
+            buffers.in_flight.remove(&path);
+            match result {
+                Ok(()) => written += 1,
+                Err(e) => {
+                    buffers.pending.entry(path).or_insert(contents);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        self.written.fetch_add(written as u64, Ordering::Relaxed);
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(written),
+        }
+    }
+
+    pub fn stats(&self) -> CoalescingStats {
+        CoalescingStats {
+            requested: self.requested.load(Ordering::Relaxed),
+            written: self.written.load(Ordering::Relaxed),
+            pending: self.lock().pending.len(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Buffers> {
+        self.buffers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn pending_content(&self, path: &Path) -> Option<Arc<[u8]>> {
+        self.lock().get(path)
+    }
+}
+
+#[async_trait]
+impl FileSystem for CoalescingFileSystem {
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.pending_content(path) {
+            Some(contents) => Ok(contents.to_vec()),
+            None => self.inner.read(path).await,
+        }
+    }
+
+    async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.requested.fetch_add(1, Ordering::Relaxed);
+        self.lock().pending.insert(path.to_path_buf(), contents.into());
+        Ok(())
+    }
+
+    async fn create_new(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        if self.pending_content(path).is_some() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, path.display().to_string()));
+        }
+        self.inner.create_new(path, contents).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let _flushing = self.flushing.lock().await;
+        let pending = {
+            let mut buffers = self.lock();
+            buffers.pending.remove(to);
+            buffers.pending.remove(from)
+        };
+        match pending {
+            Some(contents) => {
+                self.lock().pending.insert(to.to_path_buf(), contents);
+                match self.inner.remove_file(from).await {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                    _ => Ok(()),
+                }
+            }
+            None => self.inner.rename(from, to).await,
+        }
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.inner.create_dir_all(path).await
+    }
+
+    async fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let _flushing = self.flushing.lock().await;
+        let was_pending = self.lock().pending.remove(path).is_some();
+        match self.inner.remove_file(path).await {
+            Err(e) if was_pending && e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = match self.inner.read_dir(path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let buffers = self.lock();
+        for buffered in buffers.pending.keys().chain(buffers.in_flight.keys()) {
+            if buffered.parent() == Some(path) && !entries.contains(buffered) {
+                entries.push(buffered.clone());
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn exists(&self, path: &Path) -> bool {
+        self.pending_content(path).is_some() || self.inner.exists(path).await
+    }
+
+    async fn modified(&self, path: &Path) -> io::Result<Option<SystemTime>> {
+        self.inner.modified(path).await
+    }
+
+    async fn file_size(&self, path: &Path) -> io::Result<u64> {
+        match self.pending_content(path) {
+            Some(contents) => Ok(contents.len() as u64),
+            None => self.inner.file_size(path).await,
+        }
+    }
+
+    async fn read_window(&self, path: &Path, range: LineRange) -> io::Result<Window> {
+        match self.pending_content(path) {
+            Some(contents) => Ok(crate::large_file::window(&contents, range)),
+            None => self.inner.read_window(path, range).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryFileSystem;
+
+    #[tokio::test]
+    async fn repeated_writes_cost_one_flush() {
+        let disk = Arc::new(MemoryFileSystem::new());
+        let fs = CoalescingFileSystem::new(disk.clone(), DEFAULT_FLUSH_INTERVAL);
+        let path = Path::new("/sessions/conversations/a.json");
+
+        for n in 0..10 {
+            fs.write(path, format!("v{}", n).as_bytes()).await.unwrap();
+        }
+        assert!(!disk.exists(path).await);
+        assert_eq!(fs.read_to_string(path).await.unwrap(), "v9");
+        assert_eq!(fs.read_dir(Path::new("/sessions/conversations")).await.unwrap(), vec![path.to_path_buf()]);
+
+        assert_eq!(fs.flush().await.unwrap(), 1);
+        assert_eq!(disk.read_to_string(path).await.unwrap(), "v9");
+        assert_eq!(fs.stats(), CoalescingStats { requested: 10, written: 1, pending: 0 });
+    }
+
+    /// Holds every write until released
+    #[derive(Debug, Default)]
+    struct SlowDisk {
+        disk: MemoryFileSystem,
+        started: tokio::sync::Notify,
+        release: tokio::sync::Notify,
+    }
+
+    #[async_trait]
+    impl FileSystem for SlowDisk {
+        async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.disk.read(path).await
+        }
+
+        async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.started.notify_one();
+            self.release.notified().await;
+            self.disk.write(path, contents).await
+        }
+
+        async fn create_new(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.disk.create_new(path, contents).await
+        }
+
+        async fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            self.disk.rename(from, to).await
+        }
+
+        async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+            self.disk.create_dir_all(path).await
+        }
+
+        async fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.disk.remove_file(path).await
+        }
+
+        async fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            self.disk.read_dir(path).await
+        }
+
+        async fn exists(&self, path: &Path) -> bool {
+            self.disk.exists(path).await
+        }
+    }
+
+    #[tokio::test]
+    async fn content_being_flushed_stays_visible_and_removable() {
+        let slow = Arc::new(SlowDisk::default());
+        let fs = CoalescingFileSystem::new(slow.clone(), DEFAULT_FLUSH_INTERVAL);
+        let path = Path::new("/sessions/a.json");
+        fs.write(path, b"v1").await.unwrap();
+
+        let flush = tokio::spawn({
+            let fs = fs.clone();
+            async move { fs.flush().await }
+        });
+        slow.started.notified().await;
+        assert_eq!(fs.read_to_string(path).await.unwrap(), "v1");
+        assert_eq!(fs.file_size(path).await.unwrap(), 2);
+
+        let remove = tokio::spawn({
+            let fs = fs.clone();
+            async move { fs.remove_file(Path::new("/sessions/a.json")).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!remove.is_finished());
+
+        slow.release.notify_one();
+        assert_eq!(flush.await.unwrap().unwrap(), 1);
+        remove.await.unwrap().unwrap();
+        assert!(!fs.exists(path).await);
+        assert!(!slow.disk.exists(path).await);
+    }
+
+    #[tokio::test]
+    async fn flusher_writes_after_the_last_handle_is_dropped() {
+        let disk = Arc::new(MemoryFileSystem::new());
+        let fs = CoalescingFileSystem::new(disk.clone(), Duration::from_millis(20));
+        let flusher = fs.spawn_flusher();
+        fs.write(Path::new("/s.json"), b"{}").await.unwrap();
+        drop(fs);
+
+        // The last flush happens after every handle is gone
+        tokio::time::timeout(Duration::from_secs(5), flusher).await.unwrap().unwrap();
+        assert!(disk.exists(Path::new("/s.json")).await);
+    }
+}
@@ -0,0 +1,87 @@
+//! Windows behaviour of the workspace crate: verbatim and UNC paths,
+//! case-insensitive roots, backslash-separated input and cmd.exe
+#![cfg(windows)]
+
+use picode_core::agent::permissions::workspace_relative;
+use picode_core::command::CommandBuilder;
+use picode_core::paths;
+use picode_core::workspace::{Workspace, WorkspaceConfig};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn canonical_paths_have_no_verbatim_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    let canonical = paths::canonicalize(dir.path()).unwrap();
+    assert!(!canonical.to_string_lossy().starts_with(r"\\?\"));
+
+    let verbatim = std::fs::canonicalize(dir.path()).unwrap();
+    assert!(verbatim.to_string_lossy().starts_with(r"\\?\"));
+    assert_eq!(paths::simplified(&verbatim), canonical.as_path());
+    assert_eq!(paths::strip_root(&verbatim.join("src"), &canonical), Some(Path::new("src")));
+}
+
+#[test]
+fn roots_strip_across_prefixes_and_case() {
+    let root = Path::new(r"C:\Users\Dev\project");
+    let lib = Some(Path::new(r"src\lib.rs"));
+    assert_eq!(paths::strip_root(Path::new(r"\\?\C:\Users\Dev\project\src\lib.rs"), root), lib);
+    assert_eq!(paths::strip_root(Path::new(r"c:\users\dev\PROJECT\src\lib.rs"), root), lib);
+    assert_eq!(paths::strip_root(Path::new(r"D:\Users\Dev\project\src\lib.rs"), root), None);
+    assert_eq!(paths::to_slash(Path::new(r"src\lib.rs")), "src/lib.rs");
+    assert!(paths::ends_with_separator(Path::new(r"target\")));
+}
+
+#[test]
+fn unc_workspaces_confine_tool_paths() {
+    let root = Path::new(r"\\server\share\project");
+    assert_eq!(
+        paths::strip_root(Path::new(r"\\?\UNC\server\share\project\src\lib.rs"), root),
+        Some(Path::new(r"src\lib.rs"))
+    );
+    assert_eq!(workspace_relative(root, r"\\server\share\project\src\main.rs").unwrap(), "src/main.rs");
+    assert_eq!(workspace_relative(root, r"src\main.rs").unwrap(), "src/main.rs");
+    assert!(workspace_relative(root, r"\\server\other\secret.txt").is_err());
+    assert!(workspace_relative(root, r"\Windows\System32\drivers\etc\hosts").is_err());
+    assert!(workspace_relative(root, r"C:relative.txt").is_err());
+    assert!(workspace_relative(root, r"src\..\..\escape.txt").is_err());
+}
+
+#[tokio::test]
+async fn workspace_scan_uses_relative_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src").join("lib.rs"), "pub fn lib() {}\r\n").unwrap();
+    std::fs::write(dir.path().join("build.log"), "noise\r\n").unwrap();
+
+    // Scanning a verbatim root still yields plain relative paths
+    let mut workspace = Workspace::new(WorkspaceConfig {
+        root_path: std::fs::canonicalize(dir.path()).unwrap(),
+        git_enabled: false,
+        ..WorkspaceConfig::default()
+    });
+    workspace.scan().await.unwrap();
+    let files: Vec<PathBuf> = workspace.files.iter().map(|file| file.relative_path.clone()).collect();
+    assert_eq!(files, vec![PathBuf::from(r"src\lib.rs")]);
+
+    let absolute = paths::canonicalize(dir.path()).unwrap().join("src").join("lib.rs");
+    let groups = workspace.group_by_repo(&[absolute]);
+    assert_eq!(groups[0].files, vec![PathBuf::from(r"src\lib.rs")]);
+}
+
+#[tokio::test]
+async fn shell_commands_run_through_cmd() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = CommandBuilder::shell("echo %PICODE_TEST% & cd")
+        .with_env("PICODE_TEST".to_string(), "from-cmd".to_string())
+        .with_working_dir(dir.path().to_path_buf())
+        .execute()
+        .await
+        .unwrap();
+    assert!(result.status.is_success());
+    let mut lines = result.stdout.lines().map(str::trim);
+    assert_eq!(lines.next(), Some("from-cmd"));
+    assert_eq!(
+        paths::canonicalize(lines.next().unwrap()).unwrap(),
+        paths::canonicalize(dir.path()).unwrap()
+    );
+}
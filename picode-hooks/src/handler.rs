@@ -0,0 +1,99 @@
+//! Event bus integration
+//!
+//! [`HookEventHandler`] runs hooks in response to `hook_triggered` events.
+//! Inline hooks are awaited by the handler; async hooks are only enqueued,
+//! so the event publisher is never blocked by them.
+
+use crate::{HookManager, HookTrigger};
+use async_trait::async_trait;
+use picode_core::event::{EventEnvelope, EventError, EventHandler};
+use picode_core::Event;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Runs hooks named by `hook_triggered` events
+pub struct HookEventHandler {
+    manager: Arc<HookManager>,
+}
+
+impl HookEventHandler {
+    pub fn new(manager: Arc<HookManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl EventHandler for HookEventHandler {
+    async fn handle(&self, envelope: &EventEnvelope) -> Result<(), EventError> {
+        let Event::HookTriggered { hook_name, event_type, context, .. } = &envelope.event else {
+            return Ok(());
+        };
+        let Some(hook) = self.manager.get_hook(hook_name) else {
+            debug!("No hook named '{}' for event {}", hook_name, event_type);
+            return Ok(());
+        };
+
+        // Expose the triggering event to the script through its environment
+        let mut hook = hook.clone();
+        hook.env.insert("PICODE_EVENT".to_string(), event_type.clone());
+        for (key, value) in context {
+            hook.env.insert(format!("PICODE_{}", key.to_uppercase()), value.clone());
+        }
+
+        match self.manager.dispatch(hook, Vec::new()).await {
+            Ok(HookTrigger::Queued(id)) => {
+                debug!("Hook '{}' enqueued as job {}", hook_name, id);
+                Ok(())
+            }
+            Ok(HookTrigger::Completed(_)) => Ok(()),
+            Err(e) => Err(EventError::Handler(e.to_string())),
+        }
+    }
+
+    fn event_types(&self) -> Vec<&'static str> {
+        vec!["hook_triggered"]
+    }
+
+    fn name(&self) -> &str {
+        "hooks"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Hook, TaskManager};
+    use picode_core::{EventBus, SessionId};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn async_hooks_are_enqueued_and_reported() {
+        let bus = Arc::new(EventBus::new(16, 16));
+        let mut events = bus.subscribe();
+
+        let mut manager = HookManager::new().with_task_manager(TaskManager::new().with_event_bus(&bus));
+        manager
+            .register_hook(Hook::new("notify".to_string(), PathBuf::from("/nonexistent/notify")).with_async(true))
+            .unwrap();
+        let manager = Arc::new(manager);
+        bus.register_handler(Box::new(HookEventHandler::new(manager.clone()))).await;
+
+        bus.publish(
+            Event::HookTriggered {
+                session_id: SessionId::new(),
+                hook_name: "notify".to_string(),
+                event_type: "command_completed".to_string(),
+                context: HashMap::new(),
+            },
+            "test".to_string(),
+        )
+        .await
+        .unwrap();
+
+        // The publisher returned before the job ran; its result follows as an event
+        assert_eq!(events.recv().await.unwrap().event.event_type(), "hook_triggered");
+        assert_eq!(events.recv().await.unwrap().event.event_type(), "hook_completed");
+        assert_eq!(manager.task_manager().jobs().len(), 1);
+    }
+}
@@ -7,8 +7,11 @@ use crate::registry::HookRegistry;
 use crate::{HookResult, HooksError};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::jobs::{HookJobId, TaskManager};
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
 use tracing::{info, warn, error};
 
 /// A hook represents a script that can be executed at specific points
@@ -24,6 +27,22 @@ pub struct Hook {
     pub working_dir: Option<PathBuf>,
     /// Whether the hook should run in the background
     pub background: bool,
+    /// Whether triggering the hook enqueues it as a job instead of waiting
+    /// for it to finish
+    #[serde(default, rename = "async")]
+    pub run_async: bool,
+    /// Lifecycle events (e.g. `pre_llm_request`) this hook subscribes to
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+/// Outcome of triggering a hook
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookTrigger {
+    /// The hook ran inline and produced this output
+    Completed(String),
+    /// The hook was enqueued as an async job
+    Queued(HookJobId),
 }
 
 impl Hook {
@@ -35,6 +54,8 @@ impl Hook {
             env: HashMap::new(),
             working_dir: None,
             background: false,
+            run_async: false,
+            events: Vec::new(),
         }
     }
 
@@ -56,8 +77,19 @@ impl Hook {
         self
     }
 
+    /// Set whether triggering this hook enqueues it as an async job
+    pub fn with_async(mut self, run_async: bool) -> Self {
+        self.run_async = run_async;
+        self
+    }
+
     /// Execute this hook with the given arguments
     pub async fn execute(&self, args: Vec<String>) -> HookResult<String> {
+        self.execute_with_input(args, None).await
+    }
+
+    /// Execute this hook, writing `stdin` to its standard input
+    pub async fn execute_with_input(&self, args: Vec<String>, stdin: Option<&[u8]>) -> HookResult<String> {
         info!("Executing hook '{}' with args: {:?}", self.name, args);
 
         // Check if script exists
@@ -88,8 +120,23 @@ impl Hook {
             cmd.stderr(Stdio::piped());
         }
 
+        cmd.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+
         // Execute the command
-        match cmd.output() {
+        let result = async {
+            let mut child = cmd.spawn()?;
+            if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+                // Hooks may exit without reading their input
+                if let Err(e) = pipe.write_all(data).await {
+                    if e.kind() != std::io::ErrorKind::BrokenPipe {
+                        return Err(e);
+                    }
+                }
+            }
+            child.wait_with_output().await
+        }
+        .await;
+        match result {
             Ok(output) => {
                 let stdout = String::from_utf8_lossy(&output.stdout);
                 let stderr = String::from_utf8_lossy(&output.stderr);
@@ -117,6 +164,7 @@ impl Hook {
 #[derive(Debug)]
 pub struct HookManager {
     registry: HookRegistry,
+    tasks: TaskManager,
 }
 
 impl HookManager {
@@ -124,13 +172,29 @@ impl HookManager {
     pub fn new() -> Self {
         Self {
             registry: HookRegistry::new(),
+            tasks: TaskManager::new(),
         }
     }
 
     /// Load hook manager from a configuration directory
+    ///
+    /// Async hook jobs are recorded in the directory's jobs file so they can
+    /// be listed with `picode hooks jobs`.
     pub fn load_from_dir(hooks_dir: PathBuf) -> HookResult<Self> {
+        let tasks = TaskManager::new().with_jobs_file(hooks_dir.join(crate::jobs::JOBS_FILE))?;
         let registry = HookRegistry::load_from_dir(hooks_dir)?;
-        Ok(Self { registry })
+        Ok(Self { registry, tasks })
+    }
+
+    /// Use a specific task manager for async hooks
+    pub fn with_task_manager(mut self, tasks: TaskManager) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// Task manager running async hooks
+    pub fn task_manager(&self) -> &TaskManager {
+        &self.tasks
     }
 
     /// Register a new hook
@@ -156,6 +220,25 @@ impl HookManager {
         }
     }
 
+    /// Trigger a hook by name, enqueueing it if it is marked async
+    pub async fn trigger(&self, name: &str, args: Vec<String>) -> HookResult<HookTrigger> {
+        let hook = self
+            .registry
+            .get(name)
+            .cloned()
+            .ok_or_else(|| HooksError::HookNotFound(name.to_string()))?;
+        self.dispatch(hook, args).await
+    }
+
+    /// Run a hook inline, or enqueue it in the task manager if it is async
+    pub async fn dispatch(&self, hook: Hook, args: Vec<String>) -> HookResult<HookTrigger> {
+        if hook.run_async {
+            Ok(HookTrigger::Queued(self.tasks.enqueue(hook, args)))
+        } else {
+            hook.execute(args).await.map(HookTrigger::Completed)
+        }
+    }
+
     /// List all registered hooks
     pub fn list_hooks(&self) -> Vec<String> {
         self.registry.list_hooks()
@@ -196,6 +279,28 @@ mod tests {
         assert!(hook.env.is_empty());
         assert!(hook.working_dir.is_none());
         assert!(!hook.background);
+        assert!(!hook.run_async);
+    }
+
+    #[test]
+    fn test_hook_async_flag_serde() {
+        let hook: Hook = serde_json::from_str(
+            r#"{"name":"lint","script_path":"/hooks/lint","env":{},"working_dir":null,"background":false,"async":true}"#,
+        )
+        .unwrap();
+        assert!(hook.run_async);
+
+        let json = serde_json::to_value(Hook::new("fmt".to_string(), PathBuf::from("/hooks/fmt"))).unwrap();
+        assert_eq!(json["async"], false);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_unknown_hook() {
+        let manager = HookManager::new();
+        assert!(matches!(
+            manager.trigger("missing", vec![]).await,
+            Err(HooksError::HookNotFound(_))
+        ));
     }
 
     #[test]
@@ -0,0 +1,208 @@
+//! Community hook index client
+//!
+//! A hook index is a static JSON document listing community hooks with their
+//! descriptions, triggers, download URLs and SHA-256 checksums. Scripts are
+//! only installed after their checksum has been verified.
+
+use crate::{HookResult, HooksError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Default location of the community hook index
+pub const DEFAULT_INDEX_URL: &str = "https://picode.org/hooks/index.json";
+
+/// A single hook listed in the index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookIndexEntry {
+    /// Hook name, also used as the installed file name
+    pub name: String,
+    /// Short description
+    pub description: String,
+    /// Events the hook is meant to be triggered by
+    #[serde(default)]
+    pub triggers: Vec<String>,
+    /// URL of the hook script
+    pub url: String,
+    /// Hex-encoded SHA-256 checksum of the script
+    pub sha256: String,
+    /// Author or maintainer
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// Hook index document
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HookIndex {
+    #[serde(default)]
+    pub hooks: Vec<HookIndexEntry>,
+}
+
+impl HookIndex {
+    /// Parse an index from its JSON representation
+    pub fn from_json(json: &str) -> HookResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Fetch an index from a URL (or a local file path)
+    pub async fn fetch(url: &str) -> HookResult<Self> {
+        info!("Fetching hook index from {}", url);
+        let body = fetch_text(url).await?;
+        Self::from_json(&body)
+    }
+
+    /// Search hooks by name, description or trigger (case-insensitive)
+    pub fn search(&self, term: &str) -> Vec<&HookIndexEntry> {
+        let term = term.to_lowercase();
+        self.hooks
+            .iter()
+            .filter(|hook| {
+                hook.name.to_lowercase().contains(&term)
+                    || hook.description.to_lowercase().contains(&term)
+                    || hook.triggers.iter().any(|t| t.to_lowercase().contains(&term))
+            })
+            .collect()
+    }
+
+    /// Find a hook by exact name
+    pub fn find(&self, name: &str) -> Option<&HookIndexEntry> {
+        self.hooks.iter().find(|hook| hook.name == name)
+    }
+}
+
+impl HookIndexEntry {
+    /// Download the hook script and verify it against the published checksum
+    pub async fn download(&self) -> HookResult<String> {
+        debug!("Downloading hook '{}' from {}", self.name, self.url);
+        let script = fetch_text(&self.url).await?;
+        self.verify(&script)?;
+        Ok(script)
+    }
+
+    /// Verify script contents against the published checksum
+    pub fn verify(&self, script: &str) -> HookResult<()> {
+        let actual = sha256_hex(script.as_bytes());
+        if actual.eq_ignore_ascii_case(&self.sha256) {
+            Ok(())
+        } else {
+            Err(HooksError::ChecksumMismatch {
+                name: self.name.clone(),
+                expected: self.sha256.clone(),
+                actual,
+            })
+        }
+    }
+
+    /// Write a verified script into the hooks directory and make it executable
+    pub fn install_script(&self, hooks_dir: &Path, script: &str) -> HookResult<PathBuf> {
+        if self.name.contains(['/', '\\']) || self.name.starts_with('.') {
+            return Err(HooksError::RegistryError(format!("Invalid hook name: {}", self.name)));
+        }
+
+        std::fs::create_dir_all(hooks_dir)?;
+        let path = hooks_dir.join(&self.name);
+        std::fs::write(&path, script)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(&path)?.permissions();
+            perms.set_mode(0o755);
+            std::fs::set_permissions(&path, perms)?;
+        }
+
+        info!("Installed hook '{}' to {}", self.name, path.display());
+        Ok(path)
+    }
+}
+
+/// Hex-encoded SHA-256 digest
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+async fn fetch_text(url: &str) -> HookResult<String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        let response = reqwest::get(url)
+            .await
+            .map_err(|e| HooksError::RegistryError(e.to_string()))?
+            .error_for_status()
+            .map_err(|e| HooksError::RegistryError(e.to_string()))?;
+        response
+            .text()
+            .await
+            .map_err(|e| HooksError::RegistryError(e.to_string()))
+    } else {
+        let path = url.strip_prefix("file://").unwrap_or(url);
+        Ok(tokio::fs::read_to_string(path).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SCRIPT: &str = "#!/bin/sh\necho formatted\n";
+
+    fn index() -> HookIndex {
+        HookIndex {
+            hooks: vec![
+                HookIndexEntry {
+                    name: "fmt-on-save".to_string(),
+                    description: "Run the formatter after file saves".to_string(),
+                    triggers: vec!["file_saved".to_string()],
+                    url: "https://example.com/fmt-on-save.sh".to_string(),
+                    sha256: sha256_hex(SCRIPT.as_bytes()),
+                    author: None,
+                },
+                HookIndexEntry {
+                    name: "notify".to_string(),
+                    description: "Desktop notification when a command completes".to_string(),
+                    triggers: vec!["command_completed".to_string()],
+                    url: "https://example.com/notify.sh".to_string(),
+                    sha256: "00".to_string(),
+                    author: Some("picode".to_string()),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_index_search() {
+        let index = index();
+        assert_eq!(index.search("FORMAT").len(), 1);
+        assert_eq!(index.search("command_completed")[0].name, "notify");
+        assert!(index.search("nothing").is_empty());
+        assert!(index.find("notify").is_some());
+    }
+
+    #[test]
+    fn test_checksum_verification() {
+        let index = index();
+        assert!(index.find("fmt-on-save").unwrap().verify(SCRIPT).is_ok());
+
+        match index.find("notify").unwrap().verify(SCRIPT) {
+            Err(HooksError::ChecksumMismatch { name, .. }) => assert_eq!(name, "notify"),
+            _ => panic!("Expected ChecksumMismatch error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_local_index_and_install() {
+        let temp_dir = TempDir::new().unwrap();
+        let index_path = temp_dir.path().join("index.json");
+        std::fs::write(&index_path, serde_json::to_string(&index()).unwrap()).unwrap();
+
+        let fetched = HookIndex::fetch(index_path.to_str().unwrap()).await.unwrap();
+        assert_eq!(fetched.hooks.len(), 2);
+
+        let hooks_dir = temp_dir.path().join("hooks");
+        let path = fetched.hooks[0].install_script(&hooks_dir, SCRIPT).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), SCRIPT);
+    }
+}
@@ -0,0 +1,358 @@
+//! Job queue for async hooks
+//!
+//! Hooks marked `async: true` are not awaited by whoever triggers them.
+//! Instead they are enqueued in the [`TaskManager`], which runs them on the
+//! tokio runtime with bounded concurrency, keeps a table of queued, running
+//! and finished jobs, and reports every finished job as a follow-up
+//! [`Event::HookCompleted`] on the event bus and to in-process subscribers.
+
+use crate::{Hook, HookResult};
+use chrono::{DateTime, Utc};
+use picode_core::{Event, EventBus};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::{broadcast, Notify, Semaphore};
+use tracing::{debug, warn};
+
+/// File in the hooks directory recording async hook jobs
+pub const JOBS_FILE: &str = "jobs.json";
+
+/// Default number of async hooks running at the same time
+const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+/// Number of finished jobs kept in the job table
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Identifier of an async hook job
+pub type HookJobId = u64;
+
+/// Lifecycle of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookJobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl HookJobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, HookJobStatus::Succeeded | HookJobStatus::Failed)
+    }
+}
+
+impl std::fmt::Display for HookJobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            HookJobStatus::Queued => "queued",
+            HookJobStatus::Running => "running",
+            HookJobStatus::Succeeded => "succeeded",
+            HookJobStatus::Failed => "failed",
+        };
+        f.pad(name)
+    }
+}
+
+/// An async hook execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookJob {
+    pub id: HookJobId,
+    pub hook_name: String,
+    pub args: Vec<String>,
+    pub status: HookJobStatus,
+    /// Hook stdout on success, error message on failure
+    pub output: Option<String>,
+    pub queued_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JobTable {
+    next_id: HookJobId,
+    jobs: BTreeMap<HookJobId, HookJob>,
+}
+
+impl JobTable {
+    /// Drop the oldest finished jobs beyond the history limit
+    fn prune(&mut self) {
+        let finished: Vec<HookJobId> = self
+            .jobs
+            .values()
+            .filter(|job| job.status.is_finished())
+            .map(|job| job.id)
+            .collect();
+        for id in finished.iter().take(finished.len().saturating_sub(MAX_FINISHED_JOBS)) {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+/// Runs async hooks in the background and tracks their jobs
+#[derive(Clone)]
+pub struct TaskManager {
+    table: Arc<Mutex<JobTable>>,
+    permits: Arc<Semaphore>,
+    finished: Arc<Notify>,
+    events: broadcast::Sender<HookJob>,
+    jobs_file: Option<PathBuf>,
+    event_bus: Option<Weak<EventBus>>,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(64);
+        Self {
+            table: Arc::new(Mutex::new(JobTable::default())),
+            permits: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT)),
+            finished: Arc::new(Notify::new()),
+            events,
+            jobs_file: None,
+            event_bus: None,
+        }
+    }
+
+    /// Limit the number of hooks running at the same time
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(max_concurrent.max(1)));
+        self
+    }
+
+    /// Persist the job table to a file, continuing any jobs recorded there
+    pub fn with_jobs_file(mut self, path: PathBuf) -> HookResult<Self> {
+        let table = Self::read_table(&path)?;
+        *self.table.lock().expect("job table poisoned") = table;
+        self.jobs_file = Some(path);
+        Ok(self)
+    }
+
+    /// Publish [`Event::HookCompleted`] on this bus when a job finishes
+    ///
+    /// Only a weak reference is kept, so the task manager can be owned by a
+    /// handler registered on the same bus.
+    pub fn with_event_bus(mut self, bus: &Arc<EventBus>) -> Self {
+        self.event_bus = Some(Arc::downgrade(bus));
+        self
+    }
+
+    /// Enqueue a hook; returns immediately with the job id
+    pub fn enqueue(&self, hook: Hook, args: Vec<String>) -> HookJobId {
+        let id = {
+            let mut table = self.table.lock().expect("job table poisoned");
+            let id = table.next_id;
+            table.next_id += 1;
+            table.jobs.insert(
+                id,
+                HookJob {
+                    id,
+                    hook_name: hook.name.clone(),
+                    args: args.clone(),
+                    status: HookJobStatus::Queued,
+                    output: None,
+                    queued_at: Utc::now(),
+                    started_at: None,
+                    finished_at: None,
+                },
+            );
+            self.persist(&table);
+            id
+        };
+        debug!("Enqueued hook '{}' as job {}", hook.name, id);
+
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let _permit = manager.permits.clone().acquire_owned().await;
+            manager.update(id, |job| {
+                job.status = HookJobStatus::Running;
+                job.started_at = Some(Utc::now());
+            });
+
+            let result = hook.execute(args).await;
+            let job = manager.update(id, |job| {
+                job.finished_at = Some(Utc::now());
+                match result {
+                    Ok(output) => {
+                        job.status = HookJobStatus::Succeeded;
+                        job.output = Some(output);
+                    }
+                    Err(e) => {
+                        job.status = HookJobStatus::Failed;
+                        job.output = Some(e.to_string());
+                    }
+                }
+            });
+
+            if let Some(job) = job {
+                manager.report(job).await;
+            }
+        });
+
+        id
+    }
+
+    /// Receive every job as it finishes
+    pub fn subscribe(&self) -> broadcast::Receiver<HookJob> {
+        self.events.subscribe()
+    }
+
+    pub fn get(&self, id: HookJobId) -> Option<HookJob> {
+        self.table.lock().expect("job table poisoned").jobs.get(&id).cloned()
+    }
+
+    /// All known jobs, oldest first
+    pub fn jobs(&self) -> Vec<HookJob> {
+        self.table.lock().expect("job table poisoned").jobs.values().cloned().collect()
+    }
+
+    /// Wait until a job has finished
+    pub async fn wait(&self, id: HookJobId) -> Option<HookJob> {
+        loop {
+            let notified = self.finished.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            match self.get(id) {
+                Some(job) if !job.status.is_finished() => notified.await,
+                other => return other,
+            }
+        }
+    }
+
+    /// Read the jobs recorded in a jobs file
+    pub fn load_jobs(path: &Path) -> HookResult<Vec<HookJob>> {
+        Ok(Self::read_table(path)?.jobs.into_values().collect())
+    }
+
+    fn read_table(path: &Path) -> HookResult<JobTable> {
+        if !path.exists() {
+            return Ok(JobTable::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn update(&self, id: HookJobId, apply: impl FnOnce(&mut HookJob)) -> Option<HookJob> {
+        let mut table = self.table.lock().expect("job table poisoned");
+        let job = table.jobs.get_mut(&id)?;
+        apply(job);
+        let job = job.clone();
+        if job.status.is_finished() {
+            table.prune();
+        }
+        self.persist(&table);
+        Some(job)
+    }
+
+    fn persist(&self, table: &JobTable) {
+        let Some(path) = &self.jobs_file else {
+            return;
+        };
+        let result = serde_json::to_string_pretty(table)
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(path, json));
+        if let Err(e) = result {
+            warn!("Failed to record hook jobs in {}: {}", path.display(), e);
+        }
+    }
+
+    /// Deliver the result callbacks for a finished job
+    async fn report(&self, job: HookJob) {
+        self.finished.notify_waiters();
+        let _ = self.events.send(job.clone());
+
+        if let Some(bus) = self.event_bus.as_ref().and_then(Weak::upgrade) {
+            let event = Event::HookCompleted {
+                hook_name: job.hook_name,
+                job_id: job.id,
+                success: job.status == HookJobStatus::Succeeded,
+                output: job.output.unwrap_or_default(),
+            };
+            if let Err(e) = bus.publish(event, "hooks".to_string()).await {
+                debug!("Hook completion event not delivered: {}", e);
+            }
+        }
+    }
+}
+
+impl Default for TaskManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for TaskManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskManager")
+            .field("jobs", &self.table.lock().map(|t| t.jobs.len()).unwrap_or_default())
+            .field("jobs_file", &self.jobs_file)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn failed_jobs_are_recorded_and_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let jobs_file = temp_dir.path().join(JOBS_FILE);
+        let manager = TaskManager::new().with_jobs_file(jobs_file.clone()).unwrap();
+        let mut results = manager.subscribe();
+
+        let hook = Hook::new("missing".to_string(), PathBuf::from("/nonexistent/hook.sh"));
+        let id = manager.enqueue(hook, vec!["--fast".to_string()]);
+
+        let job = manager.wait(id).await.unwrap();
+        assert_eq!(job.status, HookJobStatus::Failed);
+        assert!(job.output.unwrap().contains("/nonexistent/hook.sh"));
+        assert_eq!(results.recv().await.unwrap().id, id);
+
+        let recorded = TaskManager::load_jobs(&jobs_file).unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].args, vec!["--fast"]);
+    }
+
+    #[tokio::test]
+    async fn completion_is_published_on_the_event_bus() {
+        let bus = Arc::new(EventBus::new(16, 16));
+        let mut events = bus.subscribe();
+        let manager = TaskManager::new().with_event_bus(&bus);
+
+        let id = manager.enqueue(Hook::new("gone".to_string(), PathBuf::from("/nonexistent/gone")), vec![]);
+        match events.recv().await.unwrap().event {
+            Event::HookCompleted { hook_name, job_id, success, .. } => {
+                assert_eq!((hook_name.as_str(), job_id, success), ("gone", id, false));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn prune_keeps_recent_finished_jobs() {
+        let mut table = JobTable::default();
+        for id in 0..(MAX_FINISHED_JOBS as u64 + 5) {
+            table.jobs.insert(
+                id,
+                HookJob {
+                    id,
+                    hook_name: "h".to_string(),
+                    args: vec![],
+                    status: if id == 0 { HookJobStatus::Running } else { HookJobStatus::Succeeded },
+                    output: None,
+                    queued_at: Utc::now(),
+                    started_at: None,
+                    finished_at: None,
+                },
+            );
+        }
+        table.prune();
+        assert_eq!(table.jobs.len(), MAX_FINISHED_JOBS + 1);
+        assert!(table.jobs.contains_key(&0));
+        assert!(!table.jobs.contains_key(&1));
+    }
+}
@@ -3,10 +3,18 @@
 //! This crate provides a flexible hook system that allows users to extend PiCode
 //! with custom scripts and automation at various execution points.
 
+pub mod handler;
 pub mod hooks;
+pub mod index;
+pub mod jobs;
+pub mod protocol;
 pub mod registry;
 
+pub use handler::HookEventHandler;
 pub use hooks::*;
+pub use index::{HookIndex, HookIndexEntry, DEFAULT_INDEX_URL};
+pub use jobs::{HookJob, HookJobId, HookJobStatus, TaskManager, JOBS_FILE};
+pub use protocol::{HookAction, HookDecision, HookEvent, HookInput, HookOutcome, PROTOCOL_VERSION};
 pub use registry::*;
 
 use std::path::PathBuf;
@@ -36,6 +44,15 @@ pub enum HooksError {
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[error("Invalid hook options in {0}: {1}")]
+    InvalidOptions(PathBuf, String),
+
+    #[error("Checksum mismatch for hook '{name}': expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
 }
 
 /// Command types that hooks can handle (matches CLI structure)
@@ -43,12 +60,22 @@ pub enum HooksError {
 pub enum HooksCommand {
     /// List available hooks
     List,
-    /// Install a hook
-    Install { name: String },
+    /// Search the hook index
+    Search { term: String, index_url: String },
+    /// Install a hook from the hook index
+    Install {
+        name: String,
+        index_url: String,
+        hooks_dir: PathBuf,
+        /// Skip the interactive review prompt
+        yes: bool,
+    },
     /// Remove a hook
     Remove { name: String },
     /// Run a specific hook
     Run { name: String, args: Vec<String> },
+    /// List async hook jobs recorded in a jobs file
+    Jobs { jobs_file: PathBuf, running_only: bool },
 }
 
 /// Main function to handle hook commands (required by main.rs)
@@ -68,10 +95,39 @@ pub async fn handle_command(command: HooksCommand) -> Result<(), crate::HooksErr
             }
             Ok(())
         }
-        HooksCommand::Install { name } => {
+        HooksCommand::Search { term, index_url } => {
+            let index = HookIndex::fetch(&index_url).await?;
+            let results = index.search(&term);
+            if results.is_empty() {
+                println!("No hooks matching '{}'", term);
+            }
+            for hook in results {
+                println!("  • {} - {}", hook.name, hook.description);
+                if !hook.triggers.is_empty() {
+                    println!("    triggers: {}", hook.triggers.join(", "));
+                }
+            }
+            Ok(())
+        }
+        HooksCommand::Install { name, index_url, hooks_dir, yes } => {
             println!("📦 Installing hook: {}", name);
-            // TODO: Implement hook installation from repository or local script
-            println!("Hook installation not yet implemented");
+            let index = HookIndex::fetch(&index_url).await?;
+            let entry = index
+                .find(&name)
+                .ok_or_else(|| HooksError::HookNotFound(name.clone()))?;
+            let script = entry.download().await?;
+            println!("✅ Checksum verified ({})", entry.sha256);
+
+            // Always show the script so it can be reviewed before it is installed
+            println!("----- {} -----\n{}\n-----", entry.url, script);
+            if !yes && !confirm_install(&name)? {
+                println!("Installation of '{}' cancelled", name);
+                return Ok(());
+            }
+
+            let path = entry.install_script(&hooks_dir, &script)?;
+            manager.register_hook(Hook::new(name.clone(), path))?;
+            println!("✅ Hook '{}' installed", name);
             Ok(())
         }
         HooksCommand::Remove { name } => {
@@ -103,7 +159,52 @@ pub async fn handle_command(command: HooksCommand) -> Result<(), crate::HooksErr
                 }
             }
         }
+        HooksCommand::Jobs { jobs_file, running_only } => {
+            let jobs: Vec<HookJob> = TaskManager::load_jobs(&jobs_file)?
+                .into_iter()
+                .filter(|job| !running_only || !job.status.is_finished())
+                .collect();
+            println!("🪝 Async Hook Jobs:");
+            if jobs.is_empty() {
+                println!("  No jobs recorded");
+            }
+            for job in jobs.iter().rev() {
+                println!("{}", format_job(job));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// One line of the `hooks jobs` listing
+fn format_job(job: &HookJob) -> String {
+    let elapsed = match (job.started_at, job.finished_at) {
+        (Some(start), Some(end)) => format!(" in {}ms", (end - start).num_milliseconds()),
+        _ => String::new(),
+    };
+    let mut line = format!(
+        "  #{:<4} {:<10} {:<20} queued {}{}",
+        job.id,
+        job.status,
+        job.hook_name,
+        job.queued_at.format("%Y-%m-%d %H:%M:%S"),
+        elapsed
+    );
+    if let Some(first) = job.output.as_deref().and_then(|o| o.lines().find(|l| !l.trim().is_empty())) {
+        line.push_str(&format!("\n         {}", first.trim()));
     }
+    line
+}
+
+/// Ask the user to confirm installation of a reviewed script
+fn confirm_install(name: &str) -> HookResult<bool> {
+    use std::io::Write;
+
+    print!("Install hook '{}'? [y/N] ", name);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
 #[cfg(test)]
@@ -122,6 +223,17 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_handle_jobs_without_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let result = handle_command(HooksCommand::Jobs {
+            jobs_file: temp_dir.path().join(JOBS_FILE),
+            running_only: false,
+        })
+        .await;
+        assert!(result.is_ok());
+    }
+
     #[tokio::test]
     async fn test_handle_remove_nonexistent() {
         let result = handle_command(HooksCommand::Remove { 
@@ -0,0 +1,331 @@
+//! Hook protocol v2: JSON in, decision out
+//!
+//! Lifecycle hooks (e.g. around LLM calls) receive a [`HookInput`] as JSON on
+//! stdin and may answer with a [`HookDecision`] as JSON on stdout. Empty
+//! output means "continue unchanged", so a hook that only observes can
+//! ignore stdout entirely. Hooks subscribe to lifecycle events by listing
+//! them under `events:` in their options file, or by being named after the
+//! event (e.g. `pre_llm_request.sh`).
+//!
+//! Destructive events (`pre_commit`, `pre_push`, `file_delete`) get a
+//! dry-run pass instead: the input carries `"dry_run": true`, nothing has
+//! happened yet, and a hook vetoes either with a `veto` decision or simply
+//! by exiting with a failure status, its stderr giving the reason.
+
+use crate::{Hook, HookManager, HookResult, HooksError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info};
+
+/// Version of the JSON protocol sent to lifecycle hooks
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Lifecycle points at which hooks can inspect and change PiCode's behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    /// Before a prompt is sent to a model; payload: provider, model, messages
+    PreLlmRequest,
+    /// After a model replied; payload: provider, model, response, usage
+    PostLlmResponse,
+    /// Before a commit is made; payload: repo, files, message
+    PreCommit,
+    /// Before commits are pushed; payload: remote, branch, commits
+    PrePush,
+    /// Before files are deleted for good; payload: paths
+    FileDelete,
+}
+
+impl HookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookEvent::PreLlmRequest => "pre_llm_request",
+            HookEvent::PostLlmResponse => "post_llm_response",
+            HookEvent::PreCommit => "pre_commit",
+            HookEvent::PrePush => "pre_push",
+            HookEvent::FileDelete => "file_delete",
+        }
+    }
+
+    /// Whether the event precedes an operation that cannot be taken back,
+    /// so hooks run as a dry-run pass that may only veto
+    pub fn is_destructive(self) -> bool {
+        matches!(self, HookEvent::PreCommit | HookEvent::PrePush | HookEvent::FileDelete)
+    }
+}
+
+/// JSON document written to a lifecycle hook's stdin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookInput {
+    pub version: u32,
+    pub event: HookEvent,
+    pub payload: Value,
+    /// Set for destructive events: the operation has not happened yet and
+    /// the hook may only let it proceed or veto it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl HookInput {
+    pub fn new(event: HookEvent, payload: Value) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            event,
+            payload,
+            dry_run: event.is_destructive(),
+        }
+    }
+}
+
+/// What a hook wants done with the event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookAction {
+    #[default]
+    Continue,
+    /// Replace the payload with the one returned by the hook
+    Modify,
+    /// Stop the operation
+    Veto,
+}
+
+/// JSON document a lifecycle hook may print on stdout
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookDecision {
+    pub action: HookAction,
+    /// Shown to the user when the hook vetoes
+    pub reason: Option<String>,
+    /// Replacement payload for `modify`
+    pub payload: Option<Value>,
+}
+
+impl HookDecision {
+    /// Parse a hook's stdout; empty output continues unchanged
+    pub fn parse(hook_name: &str, output: &str) -> HookResult<Self> {
+        if output.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(output).map_err(|e| {
+            HooksError::ExecutionFailed(hook_name.to_string(), format!("invalid v2 decision: {}", e))
+        })
+    }
+}
+
+/// Result of running every hook subscribed to an event
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutcome {
+    /// Proceed with this (possibly modified) payload
+    Proceed(Value),
+    /// A hook stopped the operation
+    Vetoed { hook: String, reason: String },
+}
+
+impl Hook {
+    /// Whether this hook subscribes to a lifecycle event
+    pub fn handles(&self, event: HookEvent) -> bool {
+        self.name == event.as_str() || self.events.iter().any(|e| e == event.as_str())
+    }
+
+    /// Run this hook with the v2 protocol
+    pub async fn execute_v2(&self, input: &HookInput) -> HookResult<HookDecision> {
+        let stdin = serde_json::to_vec(input)?;
+        let output = self.execute_with_input(Vec::new(), Some(&stdin)).await?;
+        HookDecision::parse(&self.name, &output)
+    }
+}
+
+impl HookManager {
+    /// Hooks subscribed to an event, in name order
+    pub fn hooks_for(&self, event: HookEvent) -> Vec<&Hook> {
+        let mut hooks: Vec<&Hook> = self
+            .list_hooks()
+            .iter()
+            .filter_map(|name| self.get_hook(name))
+            .filter(|hook| hook.handles(event))
+            .collect();
+        hooks.sort_by(|a, b| a.name.cmp(&b.name));
+        hooks
+    }
+
+    /// Pass a payload through every hook subscribed to the event; each hook
+    /// sees the payload as modified by the hooks before it
+    pub async fn run_event(&self, event: HookEvent, payload: Value) -> HookResult<HookOutcome> {
+        let mut payload = payload;
+        for hook in self.hooks_for(event) {
+            let decision = hook.execute_v2(&HookInput::new(event, payload.clone())).await?;
+            match decision.action {
+                HookAction::Continue => {}
+                HookAction::Modify => match decision.payload {
+                    Some(modified) => {
+                        debug!("Hook '{}' modified the {} payload", hook.name, event.as_str());
+                        payload = modified;
+                    }
+                    None => {
+                        return Err(HooksError::ExecutionFailed(
+                            hook.name.clone(),
+                            "modify decision without a payload".to_string(),
+                        ))
+                    }
+                },
+                HookAction::Veto => {
+                    let reason = decision.reason.unwrap_or_else(|| "no reason given".to_string());
+                    info!("Hook '{}' vetoed {}: {}", hook.name, event.as_str(), reason);
+                    return Ok(HookOutcome::Vetoed {
+                        hook: hook.name.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+        Ok(HookOutcome::Proceed(payload))
+    }
+
+    /// Ask every hook subscribed to a destructive event whether the
+    /// operation may go ahead. A failure exit status vetoes like a `veto`
+    /// decision does; `modify` decisions are ignored.
+    pub async fn dry_run(&self, event: HookEvent, payload: Value) -> HookResult<HookOutcome> {
+        let input = HookInput {
+            dry_run: true,
+            ..HookInput::new(event, payload.clone())
+        };
+        let stdin = serde_json::to_vec(&input)?;
+        for hook in self.hooks_for(event) {
+            let decision = match hook.execute_with_input(Vec::new(), Some(&stdin)).await {
+                Ok(output) => HookDecision::parse(&hook.name, &output)?,
+                Err(HooksError::ExecutionFailed(_, stderr)) => HookDecision {
+                    action: HookAction::Veto,
+                    reason: Some(stderr.trim().to_string()).filter(|reason| !reason.is_empty()),
+                    payload: None,
+                },
+                Err(e) => return Err(e),
+            };
+            if decision.action == HookAction::Veto {
+                let reason = decision.reason.unwrap_or_else(|| "no reason given".to_string());
+                info!("Hook '{}' vetoed {} in its dry run: {}", hook.name, event.as_str(), reason);
+                return Ok(HookOutcome::Vetoed {
+                    hook: hook.name.clone(),
+                    reason,
+                });
+            }
+        }
+        Ok(HookOutcome::Proceed(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use tempfile::TempDir;
+
+    #[test]
+    fn empty_output_continues() {
+        assert_eq!(HookDecision::parse("h", " \n").unwrap(), HookDecision::default());
+        let decision = HookDecision::parse("h", r#"{"action":"veto","reason":"PII"}"#).unwrap();
+        assert_eq!(decision.action, HookAction::Veto);
+        assert!(HookDecision::parse("h", "not json").is_err());
+    }
+
+    #[test]
+    fn hooks_subscribe_by_name_or_events() {
+        let by_name = Hook::new("pre_llm_request".to_string(), PathBuf::from("/hooks/pre_llm_request"));
+        let mut by_events = Hook::new("guard".to_string(), PathBuf::from("/hooks/guard"));
+        by_events.events = vec!["post_llm_response".to_string()];
+
+        assert!(by_name.handles(HookEvent::PreLlmRequest));
+        assert!(!by_name.handles(HookEvent::PostLlmResponse));
+        assert!(by_events.handles(HookEvent::PostLlmResponse));
+    }
+
+    #[cfg(unix)]
+    fn script(dir: &Path, name: &str, body: &str) -> Hook {
+        script_for(dir, name, "pre_llm_request", body)
+    }
+
+    #[cfg(unix)]
+    fn script_for(dir: &Path, name: &str, event: &str, body: &str) -> Hook {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mut hook = Hook::new(name.to_string(), path);
+        hook.events = vec![event.to_string()];
+        hook
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn destructive_events_are_vetoed_by_exit_status() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = HookManager::new();
+        manager
+            .register_hook(script_for(
+                temp_dir.path(),
+                "a_checks_dry_run",
+                "pre_commit",
+                r#"grep -q '"dry_run":true' || exit 3; echo '{"action":"modify","payload":{}}'"#,
+            ))
+            .unwrap();
+        let payload = serde_json::json!({ "files": ["src/lib.rs"], "message": "wip" });
+        let outcome = manager.dry_run(HookEvent::PreCommit, payload.clone()).await.unwrap();
+        assert_eq!(outcome, HookOutcome::Proceed(payload.clone()));
+
+        manager
+            .register_hook(script_for(
+                temp_dir.path(),
+                "b_tests",
+                "pre_commit",
+                "cat > /dev/null; echo '2 tests failing' >&2; exit 1",
+            ))
+            .unwrap();
+        let outcome = manager.dry_run(HookEvent::PreCommit, payload).await.unwrap();
+        assert_eq!(
+            outcome,
+            HookOutcome::Vetoed {
+                hook: "b_tests".to_string(),
+                reason: "2 tests failing".to_string()
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hooks_modify_then_veto() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = HookManager::new();
+        manager
+            .register_hook(script(
+                temp_dir.path(),
+                "a_augment",
+                r#"cat > /dev/null; echo '{"action":"modify","payload":{"messages":["augmented"]}}'"#,
+            ))
+            .unwrap();
+
+        let outcome = manager
+            .run_event(HookEvent::PreLlmRequest, serde_json::json!({ "messages": [] }))
+            .await
+            .unwrap();
+        assert_eq!(outcome, HookOutcome::Proceed(serde_json::json!({ "messages": ["augmented"] })));
+
+        manager
+            .register_hook(script(
+                temp_dir.path(),
+                "b_guard",
+                r#"grep -q augmented && echo '{"action":"veto","reason":"blocked"}'"#,
+            ))
+            .unwrap();
+        let outcome = manager
+            .run_event(HookEvent::PreLlmRequest, serde_json::json!({ "messages": [] }))
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome,
+            HookOutcome::Vetoed {
+                hook: "b_guard".to_string(),
+                reason: "blocked".to_string()
+            }
+        );
+    }
+}
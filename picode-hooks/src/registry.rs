@@ -1,10 +1,52 @@
 //! Hook registry for managing installed hooks
 
 use crate::{Hook, HookResult, HooksError};
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
+/// Optional settings for a hook, read from a `<name>.yaml` (or `.yml`) file
+/// next to the hook script
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HookOptions {
+    pub env: HashMap<String, String>,
+    pub working_dir: Option<PathBuf>,
+    pub background: bool,
+    /// Enqueue the hook as a job instead of running it inline
+    #[serde(rename = "async")]
+    pub run_async: bool,
+    /// Lifecycle events handled with the v2 protocol
+    pub events: Vec<String>,
+}
+
+impl HookOptions {
+    /// Load the options file for a hook script, if present
+    pub fn load_for(script_path: &Path) -> HookResult<Option<Self>> {
+        for extension in ["yaml", "yml"] {
+            let path = script_path.with_extension(extension);
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)?;
+                let options = serde_yaml::from_str(&content)
+                    .map_err(|e| HooksError::InvalidOptions(path.clone(), e.to_string()))?;
+                return Ok(Some(options));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Apply these options to a hook
+    pub fn apply(self, mut hook: Hook) -> Hook {
+        hook.env = self.env;
+        hook.working_dir = self.working_dir;
+        hook.background = self.background;
+        hook.run_async = self.run_async;
+        hook.events = self.events;
+        hook
+    }
+}
+
 /// Registry for managing hooks
 #[derive(Debug)]
 pub struct HookRegistry {
@@ -113,7 +155,10 @@ impl HookRegistry {
 
                 if is_executable {
                     if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
-                        let hook = Hook::new(name.to_string(), path.clone());
+                        let mut hook = Hook::new(name.to_string(), path.clone());
+                        if let Some(options) = HookOptions::load_for(&path)? {
+                            hook = options.apply(hook);
+                        }
                         registry.register(hook)?;
                         debug!("Loaded hook from file: {}", name);
                     }
@@ -181,6 +226,24 @@ mod tests {
         assert!(hooks.contains(&"hook2".to_string()));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_load_hook_options() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let script = temp_dir.path().join("notify.sh");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(temp_dir.path().join("notify.yaml"), "async: true\nenv:\n  CHANNEL: builds\n").unwrap();
+
+        let registry = HookRegistry::load_from_dir(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(registry.count(), 1);
+        let hook = registry.get("notify").unwrap();
+        assert!(hook.run_async);
+        assert_eq!(hook.env.get("CHANNEL").map(String::as_str), Some("builds"));
+    }
+
     #[test]
     fn test_load_from_nonexistent_dir() {
         let temp_dir = TempDir::new().unwrap();
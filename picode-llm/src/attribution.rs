@@ -0,0 +1,144 @@
+//! Usage attribution: organization and project headers and request tags
+//!
+//! Enterprise accounts split usage by organization and project, and
+//! dashboards can group requests by the user or session that made them.
+//! [`Attribution`] is configured per provider: `organization` and
+//! `project` become the `OpenAI-Organization` and `OpenAI-Project` headers,
+//! `headers` adds any others, and tags (configured ones plus those of each
+//! [`ChatRequest`](crate::ChatRequest)) are written to the body fields the
+//! provider reads them from.
+//!
+//! ```toml
+//! [llm.providers.openai.attribution]
+//! organization = "org-acme"
+//! project = "proj_payments"
+//! tags = { team = "payments" }
+//! tags_field = "metadata"   # OpenAI stores these only with `store = true`
+//! ```
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Key of [`Attribution`] in `ProviderConfig::extra`
+pub const ATTRIBUTION_KEY: &str = "attribution";
+
+/// Body field `ChatRequest` serializes its tags to
+pub const TAGS_FIELD: &str = "metadata";
+
+/// Tag naming the user a request is made for
+pub const USER_TAG: &str = "user_id";
+
+/// Tag naming the session a request belongs to
+pub const SESSION_TAG: &str = "session_id";
+
+/// How requests to a provider are attributed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Attribution {
+    /// Sent as `OpenAI-Organization`
+    pub organization: Option<String>,
+    /// Sent as `OpenAI-Project`
+    pub project: Option<String>,
+    /// More headers for every request, e.g. a gateway's cost-center header
+    pub headers: BTreeMap<String, String>,
+    /// Tags of every request; a request's own tags win on conflicts
+    pub tags: BTreeMap<String, String>,
+    /// Body field (a dotted path) the tags are sent in; unset, they are not sent
+    pub tags_field: Option<String>,
+    /// Body field the `user_id` tag is also sent in
+    pub user_field: Option<String>,
+}
+
+impl Attribution {
+    pub fn from_provider_config(config: &crate::ProviderConfig) -> Result<Option<Self>> {
+        crate::mapping::from_extra(config, ATTRIBUTION_KEY)
+    }
+
+    /// Fill unset fields with where the provider type expects the user:
+    /// `user` for OpenAI, `metadata.user_id` for Anthropic
+    pub fn with_defaults_for(mut self, provider_type: &str) -> Self {
+        let user_field = match provider_type {
+            "openai" => "user",
+            "anthropic" => "metadata.user_id",
+            _ => return self,
+        };
+        self.user_field.get_or_insert_with(|| user_field.to_string());
+        self
+    }
+
+    /// Headers sent with every request
+    pub fn headers(&self) -> HashMap<String, String> {
+        let mut headers: HashMap<String, String> = self.headers.clone().into_iter().collect();
+        if let Some(organization) = &self.organization {
+            headers.insert("OpenAI-Organization".to_string(), organization.clone());
+        }
+        if let Some(project) = &self.project {
+            headers.insert("OpenAI-Project".to_string(), project.clone());
+        }
+        headers
+    }
+
+    /// Move the tags of a serialized request, merged with the configured
+    /// ones, to the fields the provider reads; bodies never keep the
+    /// request's own `metadata` field unless that is the tags field
+    pub fn apply(&self, body: &mut Value) {
+        let Some(object) = body.as_object_mut() else {
+            return;
+        };
+        let mut tags: Map<String, Value> =
+            self.tags.iter().map(|(key, value)| (key.clone(), Value::String(value.clone()))).collect();
+        if let Some(Value::Object(request_tags)) = object.remove(TAGS_FIELD) {
+            tags.extend(request_tags);
+        }
+        if tags.is_empty() {
+            return;
+        }
+        let user = tags.get(USER_TAG).cloned();
+        if let Some(field) = &self.tags_field {
+            crate::mapping::set(object, field, Value::Object(tags));
+        }
+        if let (Some(field), Some(user)) = (&self.user_field, user) {
+            crate::mapping::set(object, field, user);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tags_go_where_each_provider_reads_them() {
+        let attribution: Attribution = serde_json::from_value(json!({
+            "organization": "org-acme",
+            "project": "proj_payments",
+            "headers": { "X-Cost-Center": "42" },
+            "tags": { "team": "payments", "user_id": "ci" },
+        }))
+        .unwrap();
+        let headers = attribution.headers();
+        assert_eq!(headers["OpenAI-Organization"], "org-acme");
+        assert_eq!(headers["OpenAI-Project"], "proj_payments");
+        assert_eq!(headers["X-Cost-Center"], "42");
+
+        let request = json!({ "model": "m", "metadata": { "user_id": "alice", "session_id": "s1" } });
+        let mut openai = request.clone();
+        attribution.clone().with_defaults_for("openai").apply(&mut openai);
+        assert_eq!(openai, json!({ "model": "m", "user": "alice" }));
+
+        let mut anthropic = request.clone();
+        Attribution { tags_field: None, ..attribution.clone() }.with_defaults_for("anthropic").apply(&mut anthropic);
+        assert_eq!(anthropic, json!({ "model": "m", "metadata": { "user_id": "alice" } }));
+
+        let mut tagged = request;
+        Attribution { tags_field: Some("metadata".to_string()), ..attribution }.apply(&mut tagged);
+        assert_eq!(tagged["metadata"], json!({ "team": "payments", "user_id": "alice", "session_id": "s1" }));
+
+        let mut plain = json!({ "model": "m" });
+        Attribution::default().with_defaults_for("generic").apply(&mut plain);
+        assert_eq!(plain, json!({ "model": "m" }));
+    }
+}
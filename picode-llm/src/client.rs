@@ -1,16 +1,97 @@
+use crate::signing::{RequestSigner, SignableRequest, SigningError};
 use anyhow::Result;
-use reqwest::{Client, Response};
+use futures::StreamExt;
+use reqwest::{Client, Response, Url};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use tokio::time::timeout;
 
+/// Response bodies larger than this are spooled to a temporary file and
+/// parsed from there instead of being buffered in memory
+pub const DEFAULT_SPILL_THRESHOLD: usize = 8 * 1024 * 1024;
+
+/// How long a streamed reply may take in total
+pub const STREAM_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Key of the connection pool settings in `ProviderConfig::extra`
+pub const POOL_KEY: &str = "pool";
+
+/// Connection reuse of a provider's HTTP client. Agent loops send many
+/// requests to the same host, so keeping connections warm saves a TLS
+/// handshake per turn.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoolSettings {
+    /// Idle connections kept open per host
+    pub max_idle_per_host: usize,
+    /// Seconds an idle connection is kept before it is closed
+    pub idle_timeout_secs: Option<u64>,
+    /// Speak HTTP/2 without negotiating it first; only for servers known to
+    /// support it, typically local gateways without TLS
+    pub http2_prior_knowledge: bool,
+    /// Interval of TCP keepalive probes, in seconds
+    pub tcp_keepalive_secs: Option<u64>,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: 8,
+            idle_timeout_secs: Some(90),
+            http2_prior_knowledge: false,
+            tcp_keepalive_secs: Some(60),
+        }
+    }
+}
+
+impl PoolSettings {
+    pub fn from_provider_config(config: &crate::ProviderConfig) -> Result<Option<Self>> {
+        crate::mapping::from_extra(config, POOL_KEY)
+    }
+
+    fn client(&self) -> reqwest::Result<Client> {
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent("PiCode/0.1.0")
+            .gzip(true)
+            .brotli(true)
+            .pool_max_idle_per_host(self.max_idle_per_host)
+            .pool_idle_timeout(self.idle_timeout_secs.map(Duration::from_secs))
+            .tcp_keepalive(self.tcp_keepalive_secs.map(Duration::from_secs));
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        builder.build()
+    }
+}
+
+/// HTTP clients shared by every provider instance talking to the same base
+/// URL with the same settings, so their connection pools are too
+static SHARED_CLIENTS: OnceLock<Mutex<HashMap<(String, PoolSettings), Client>>> = OnceLock::new();
+
+fn shared_client(base_url: &str, pool: &PoolSettings) -> reqwest::Result<Client> {
+    let mut clients = SHARED_CLIENTS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    let key = (base_url.to_string(), pool.clone());
+    if let Some(client) = clients.get(&key) {
+        return Ok(client.clone());
+    }
+    let client = pool.client()?;
+    clients.insert(key, client.clone());
+    Ok(client)
+}
+
 /// HTTP client for LLM providers
 #[derive(Debug, Clone)]
 pub struct LlmClient {
     client: Client,
     timeout_duration: Duration,
     default_headers: HashMap<String, String>,
+    signer: Option<Arc<dyn RequestSigner>>,
+    spill_threshold: usize,
 }
 
 /// Request configuration
@@ -61,21 +142,50 @@ pub enum ClientError {
     
     #[error("Rate limit exceeded: retry after {retry_after_seconds}s")]
     RateLimitError { retry_after_seconds: u64 },
+    
+    #[error("Request signing failed: {0}")]
+    SigningError(#[from] SigningError),
+    
+    #[error("Response buffering failed: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("Request failed with status {status}: {body}")]
+    Status { status: u16, body: String },
 }
 
 impl LlmClient {
     /// Create a new LLM client
     pub fn new() -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("PiCode/0.1.0")
-            .build()?;
+        Ok(Self::with_client(PoolSettings::default().client()?))
+    }
+
+    /// A client for `base_url` sharing its connections with every other
+    /// client created here for the same URL and settings
+    pub fn pooled(base_url: &str, pool: &PoolSettings) -> Result<Self> {
+        Ok(Self::with_client(shared_client(base_url, pool)?))
+    }
 
-        Ok(Self {
+    fn with_client(client: Client) -> Self {
+        Self {
             client,
             timeout_duration: Duration::from_secs(30),
             default_headers: HashMap::new(),
-        })
+            signer: None,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+        }
+    }
+
+    /// Send through the pool shared for `base_url` and `pool`, keeping the
+    /// headers, signer and timeouts
+    pub fn with_pool(mut self, base_url: &str, pool: &PoolSettings) -> Result<Self> {
+        self.client = shared_client(base_url, pool)?;
+        Ok(self)
+    }
+
+    /// Spool response bodies larger than `bytes` to a temporary file
+    pub fn with_spill_threshold(mut self, bytes: usize) -> Self {
+        self.spill_threshold = bytes;
+        self
     }
 
     /// Set default timeout for requests
@@ -96,10 +206,60 @@ impl LlmClient {
         self
     }
 
+    /// Sign every request, replacing any static `Authorization` header
+    pub fn with_signer(mut self, signer: Arc<dyn RequestSigner>) -> Self {
+        self.default_headers.retain(|key, _| !key.eq_ignore_ascii_case("authorization"));
+        self.signer = Some(signer);
+        self
+    }
+
     /// Execute a request
     pub async fn execute(&self, config: RequestConfig) -> Result<LlmResponse, ClientError> {
         let start_time = std::time::Instant::now();
+        let response = self.send(config).await?;
+        let response_time_ms = start_time.elapsed().as_millis();
+        let status = response.status();
+
+        // Extract headers
+        let mut response_headers = HashMap::new();
+        for (name, value) in response.headers() {
+            if let Ok(value_str) = value.to_str() {
+                response_headers.insert(name.to_string(), value_str.to_string());
+            }
+        }
+
+        let body = self.read_body(response).await?;
 
+        Ok(LlmResponse {
+            status: status.as_u16(),
+            headers: response_headers,
+            body,
+            response_time_ms,
+        })
+    }
+
+    /// POST a JSON body and return the response with its body unread, for
+    /// server-sent events (see [`crate::streaming`])
+    pub async fn post_stream(&self, url: &str, body: serde_json::Value) -> Result<Response, ClientError> {
+        let response = self
+            .send(RequestConfig {
+                url: url.to_string(),
+                method: "POST".to_string(),
+                headers: HashMap::from([("Accept".to_string(), "text/event-stream".to_string())]),
+                timeout_seconds: Some(STREAM_TIMEOUT.as_secs()),
+                body: Some(body),
+            })
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(ClientError::Status { status: status.as_u16(), body });
+        }
+        Ok(response)
+    }
+
+    /// Build, sign and send a request; the body is left to the caller
+    async fn send(&self, config: RequestConfig) -> Result<Response, ClientError> {
         // Build request
         let mut request = match config.method.to_uppercase().as_str() {
             "GET" => self.client.get(&config.url),
@@ -110,26 +270,43 @@ impl LlmClient {
             _ => return Err(ClientError::InvalidUrl { url: config.url }),
         };
 
-        // Add default headers
-        for (key, value) in &self.default_headers {
-            request = request.header(key, value);
+        // Request-specific headers override the defaults
+        let mut headers = self.default_headers.clone();
+        headers.extend(config.headers.clone());
+
+        // Serialize the body up front so signers see the exact bytes sent
+        let body = config.body.as_ref().map(serde_json::to_vec).transpose()?;
+        if body.is_some() && !headers.keys().any(|key| key.eq_ignore_ascii_case("content-type")) {
+            headers.insert("Content-Type".to_string(), "application/json".to_string());
         }
 
-        // Add request-specific headers
-        for (key, value) in &config.headers {
-            request = request.header(key, value);
+        if let Some(signer) = &self.signer {
+            let url = Url::parse(&config.url).map_err(|_| ClientError::InvalidUrl { url: config.url.clone() })?;
+            let signed = signer.sign(
+                &SignableRequest {
+                    method: &config.method,
+                    url: &url,
+                    headers: &headers,
+                    body: body.as_deref().unwrap_or_default(),
+                },
+                chrono::Utc::now(),
+            )?;
+            headers.extend(signed);
         }
 
-        // Add body if present
-        if let Some(body) = &config.body {
-            request = request.json(body);
+        for (key, value) in &headers {
+            request = request.header(key, value);
+        }
+        if let Some(body) = body {
+            request = request.body(body);
         }
 
-        // Set timeout
+        // Set timeout; it also bounds reading the body
         let timeout_duration = config
             .timeout_seconds
             .map(Duration::from_secs)
             .unwrap_or(self.timeout_duration);
+        request = request.timeout(timeout_duration);
 
         // Execute request with timeout
         let response = timeout(timeout_duration, request.send()).await
@@ -138,8 +315,6 @@ impl LlmClient {
             })?
             .map_err(ClientError::HttpError)?;
 
-        let response_time_ms = start_time.elapsed().as_millis();
-
         // Handle common HTTP errors
         let status = response.status();
         if status == reqwest::StatusCode::UNAUTHORIZED {
@@ -161,28 +336,47 @@ impl LlmClient {
             });
         }
 
-        // Extract headers
-        let mut response_headers = HashMap::new();
-        for (name, value) in response.headers() {
-            if let Ok(value_str) = value.to_str() {
-                response_headers.insert(name.to_string(), value_str.to_string());
+        Ok(response)
+    }
+
+    /// Stream the (already decompressed) body and parse it as JSON. Small
+    /// bodies are parsed from memory; once a body grows past the spill
+    /// threshold the rest is written to a temporary file and parsed
+    /// incrementally from there, so huge responses are never held twice.
+    async fn read_body(&self, response: Response) -> Result<serde_json::Value, ClientError> {
+        let mut stream = response.bytes_stream();
+        let mut buffer = Vec::new();
+        let mut spill: Option<tokio::fs::File> = None;
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            match spill.as_mut() {
+                Some(file) => file.write_all(&chunk).await?,
+                None if buffer.len() + chunk.len() > self.spill_threshold => {
+                    let mut file = tokio::fs::File::from_std(tempfile::tempfile()?);
+                    file.write_all(&buffer).await?;
+                    file.write_all(&chunk).await?;
+                    buffer = Vec::new();
+                    spill = Some(file);
+                }
+                None => buffer.extend_from_slice(&chunk),
             }
         }
 
-        // Parse response body
-        let body_text = response.text().await.map_err(ClientError::HttpError)?;
-        let body: serde_json::Value = if body_text.is_empty() {
-            serde_json::Value::Null
-        } else {
-            serde_json::from_str(&body_text).map_err(ClientError::JsonError)?
-        };
-
-        Ok(LlmResponse {
-            status: status.as_u16(),
-            headers: response_headers,
-            body,
-            response_time_ms,
-        })
+        match spill {
+            None if buffer.is_empty() => Ok(serde_json::Value::Null),
+            None => Ok(serde_json::from_slice(&buffer)?),
+            Some(mut file) => {
+                file.flush().await?;
+                let mut file = file.into_std().await;
+                tokio::task::spawn_blocking(move || -> Result<serde_json::Value, ClientError> {
+                    file.seek(SeekFrom::Start(0))?;
+                    Ok(serde_json::from_reader(BufReader::new(file))?)
+                })
+                .await
+                .map_err(|e| ClientError::Io(std::io::Error::other(e)))?
+            }
+        }
     }
 
     /// Convenience method for GET requests
@@ -246,4 +440,70 @@ mod tests {
             Some(&"Bearer token".to_string())
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn pooled_clients_are_shared_per_url_and_settings() {
+        let config = crate::ProviderConfig {
+            provider_type: "generic".to_string(),
+            name: None,
+            base_url: None,
+            api_key: String::new(),
+            default_model: None,
+            extra: HashMap::from([(
+                POOL_KEY.to_string(),
+                serde_json::json!({ "max_idle_per_host": 2, "http2_prior_knowledge": true }),
+            )]),
+        };
+        let pool = PoolSettings::from_provider_config(&config).unwrap().unwrap();
+        assert_eq!((pool.max_idle_per_host, pool.http2_prior_knowledge), (2, true));
+        assert_eq!(pool.tcp_keepalive_secs, Some(60));
+
+        let url = "https://pool-test.example.com";
+        LlmClient::pooled(url, &pool).unwrap();
+        LlmClient::new().unwrap().with_header("X-Test", "1").with_pool(url, &pool).unwrap();
+        LlmClient::pooled(url, &PoolSettings::default()).unwrap();
+        let clients = SHARED_CLIENTS.get().unwrap().lock().unwrap();
+        assert_eq!(clients.keys().filter(|(base_url, _)| base_url == url).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn decodes_gzip_responses() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(br#"{"data": [{"id": "gpt-4o"}]}"#).unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_raw(encoder.finish().unwrap(), "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        let response = LlmClient::new().unwrap().get(&server.uri()).await.unwrap();
+        assert_eq!(response.body["data"][0]["id"], "gpt-4o");
+    }
+
+    #[tokio::test]
+    async fn large_bodies_spill_to_disk() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let models: Vec<serde_json::Value> = (0..500).map(|i| serde_json::json!({ "id": format!("model-{}", i) })).collect();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "data": models })))
+            .mount(&server)
+            .await;
+
+        let client = LlmClient::new().unwrap().with_spill_threshold(1024);
+        let response = client.get(&server.uri()).await.unwrap();
+        assert_eq!(response.body["data"].as_array().unwrap().len(), 500);
+        assert_eq!(response.body["data"][499]["id"], "model-499");
+    }
+}
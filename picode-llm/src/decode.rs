@@ -0,0 +1,310 @@
+//! Tolerant decoding of chat responses
+//!
+//! OpenAI-compatible servers differ in the details of their replies: no
+//! `usage`, a `null` finish reason, `content` as an array of parts, or an
+//! Anthropic Messages body where chat completions were expected. Decoding
+//! with serde alone fails on any of these with an error that does not say
+//! where. Responses are therefore decoded field by field: missing optional
+//! data gets its default, fields PiCode does not model are kept in
+//! [`ChatResponse::metadata`] under their JSON path, and each variation that
+//! was accepted is reported as a [`Hint`]. What cannot be decoded is a
+//! [`DecodeError`] naming the path of the offending value.
+
+use crate::providers::{ChatChoice, ChatMessage, ChatResponse, TokenUsage};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use thiserror::Error;
+
+/// Characters of a value quoted in a [`DecodeError`]
+const EXCERPT_CHARS: usize = 80;
+
+/// A variation from the chat completions format that was decoded anyway
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Hint {
+    /// `content` was an array of content parts rather than a string
+    ContentParts { path: String },
+    /// The body was an Anthropic Messages reply
+    MessagesFormat,
+    /// The response carried no token usage
+    NoUsage,
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ContentParts { path } => write!(
+                f,
+                "provider returned `{}` as an array of content parts; their text was joined, other parts were dropped",
+                path
+            ),
+            Self::MessagesFormat => f.write_str(
+                "provider answered in Anthropic's Messages format instead of chat completions; \
+                 set `endpoints = { chat = \"...\" }` to its OpenAI-compatible path if it has one",
+            ),
+            Self::NoUsage => f.write_str("provider returned no `usage`; token counts and costs are recorded as zero"),
+        }
+    }
+}
+
+/// A response that could not be decoded
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("cannot decode chat response: `{path}` {problem}{}", hint.as_ref().map(|hint| format!(" ({})", hint)).unwrap_or_default())]
+pub struct DecodeError {
+    /// JSON path of the offending value, e.g. `choices[0].message.role`
+    pub path: String,
+    pub problem: String,
+    /// What the provider probably meant, when that can be told
+    pub hint: Option<String>,
+}
+
+/// Decode a chat completions (or Anthropic Messages) response body
+pub fn decode_chat_response(body: &Value) -> Result<(ChatResponse, Vec<Hint>), DecodeError> {
+    let mut decoder = Decoder::default();
+    let response = decoder.response(body)?;
+    Ok((response, decoder.hints))
+}
+
+/// Log each hint once per provider, however many responses show it
+pub fn log_hints(provider: &str, hints: &[Hint]) {
+    static LOGGED: OnceLock<Mutex<HashSet<(String, Hint)>>> = OnceLock::new();
+    let mut logged = LOGGED.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    for hint in hints {
+        if logged.insert((provider.to_string(), hint.clone())) {
+            tracing::warn!("{}: {}", provider, hint);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Decoder {
+    hints: Vec<Hint>,
+    metadata: HashMap<String, Value>,
+}
+
+impl Decoder {
+    fn response(&mut self, body: &Value) -> Result<ChatResponse, DecodeError> {
+        let object = object(body, "")?;
+        if let Some(Value::Object(metadata)) = object.get("metadata") {
+            self.metadata.extend(metadata.iter().map(|(key, value)| (key.clone(), value.clone())));
+        }
+        let choices = match object.get("choices") {
+            Some(Value::Array(choices)) => {
+                self.keep_unknown(object, "", &["choices", "usage", "metadata"]);
+                choices
+                    .iter()
+                    .enumerate()
+                    .map(|(index, choice)| self.choice(choice, &format!("choices[{}]", index)))
+                    .collect::<Result<_, _>>()?
+            }
+            Some(other) => return Err(mismatch("choices", "should be an array", other)),
+            None if object.get("type").and_then(Value::as_str) == Some("message") => {
+                self.hints.push(Hint::MessagesFormat);
+                self.keep_unknown(object, "", &["content", "role", "stop_reason", "usage", "metadata"]);
+                let content = self.text(object.get("content"), "content")?;
+                let role = optional_string(object.get("role"), "role")?.unwrap_or_else(|| "assistant".to_string());
+                let finish_reason = optional_string(object.get("stop_reason"), "stop_reason")?.unwrap_or_default();
+                vec![ChatChoice { message: ChatMessage { role, content }, finish_reason }]
+            }
+            None => {
+                let hint = error_message(object.get("error")).map(|message| format!("the provider returned an error: {}", message));
+                return Err(DecodeError { path: "choices".to_string(), problem: "is missing".to_string(), hint });
+            }
+        };
+        let usage = self.usage(object.get("usage"))?;
+        Ok(ChatResponse { choices, usage, metadata: std::mem::take(&mut self.metadata) })
+    }
+
+    fn choice(&mut self, choice: &Value, path: &str) -> Result<ChatChoice, DecodeError> {
+        let object = object(choice, path)?;
+        self.keep_unknown(object, path, &["index", "message", "finish_reason"]);
+        let message_path = join(path, "message");
+        let message = object.get("message").ok_or_else(|| DecodeError {
+            path: message_path.clone(),
+            problem: "is missing".to_string(),
+            hint: object.contains_key("delta").then(|| "this is a streaming chunk; the request was sent without `stream: false`?".to_string()),
+        })?;
+        let fields = self::object(message, &message_path)?;
+        self.keep_unknown(fields, &message_path, &["role", "content"]);
+        let role = optional_string(fields.get("role"), &join(&message_path, "role"))?.unwrap_or_else(|| "assistant".to_string());
+        let content = self.text(fields.get("content"), &join(&message_path, "content"))?;
+        let finish_reason = optional_string(object.get("finish_reason"), &join(path, "finish_reason"))?.unwrap_or_default();
+        Ok(ChatChoice { message: ChatMessage { role, content }, finish_reason })
+    }
+
+    /// Text of a string or an array of content parts; empty when missing or
+    /// `null`, as next to tool calls
+    fn text(&mut self, value: Option<&Value>, path: &str) -> Result<String, DecodeError> {
+        match value {
+            None | Some(Value::Null) => Ok(String::new()),
+            Some(Value::String(text)) => Ok(text.clone()),
+            Some(Value::Array(parts)) => {
+                self.hints.push(Hint::ContentParts { path: path.to_string() });
+                let mut text = String::new();
+                for (index, part) in parts.iter().enumerate() {
+                    match part {
+                        Value::String(part) => text.push_str(part),
+                        Value::Object(part) => {
+                            if let Some(part) = part.get("text").and_then(Value::as_str) {
+                                text.push_str(part);
+                            }
+                        }
+                        other => return Err(mismatch(&format!("{}[{}]", path, index), "should be a content part", other)),
+                    }
+                }
+                Ok(text)
+            }
+            Some(other) => Err(mismatch(path, "should be a string", other)),
+        }
+    }
+
+    /// Token counts under the OpenAI or the Anthropic names; zero when missing
+    fn usage(&mut self, value: Option<&Value>) -> Result<TokenUsage, DecodeError> {
+        let usage = match value {
+            None | Some(Value::Null) => {
+                self.hints.push(Hint::NoUsage);
+                return Ok(TokenUsage { prompt_tokens: 0, completion_tokens: 0, total_tokens: 0 });
+            }
+            Some(usage) => object(usage, "usage")?,
+        };
+        let known = ["prompt_tokens", "completion_tokens", "total_tokens", "input_tokens", "output_tokens"];
+        self.keep_unknown(usage, "usage", &known);
+        let tokens = |names: &[&str]| -> Result<Option<u32>, DecodeError> {
+            names.iter().find_map(|name| usage.get(*name).map(|value| (name, value))).map_or(Ok(None), |(name, value)| {
+                count(value, &join("usage", name))
+            })
+        };
+        let prompt_tokens = tokens(&["prompt_tokens", "input_tokens"])?.unwrap_or(0);
+        let completion_tokens = tokens(&["completion_tokens", "output_tokens"])?.unwrap_or(0);
+        let total_tokens = tokens(&["total_tokens"])?.unwrap_or(prompt_tokens.saturating_add(completion_tokens));
+        Ok(TokenUsage { prompt_tokens, completion_tokens, total_tokens })
+    }
+
+    /// Keep the fields of `object` that are not `known` in the metadata
+    fn keep_unknown(&mut self, object: &Map<String, Value>, path: &str, known: &[&str]) {
+        for (key, value) in object {
+            if !known.contains(&key.as_str()) && !value.is_null() {
+                self.metadata.insert(join(path, key), value.clone());
+            }
+        }
+    }
+}
+
+fn object<'a>(value: &'a Value, path: &str) -> Result<&'a Map<String, Value>, DecodeError> {
+    value.as_object().ok_or_else(|| mismatch(if path.is_empty() { "(response)" } else { path }, "should be an object", value))
+}
+
+fn optional_string(value: Option<&Value>, path: &str) -> Result<Option<String>, DecodeError> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) => Ok(Some(text.clone())),
+        Some(other) => Err(mismatch(path, "should be a string", other)),
+    }
+}
+
+fn count(value: &Value, path: &str) -> Result<Option<u32>, DecodeError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Number(number) => match number.as_u64().and_then(|count| u32::try_from(count).ok()) {
+            Some(count) => Ok(Some(count)),
+            None => Err(mismatch(path, "should be a token count", value)),
+        },
+        other => Err(mismatch(path, "should be a number", other)),
+    }
+}
+
+/// The `message` of an error body (`{"error": {"message": ...}}` or `{"error": "..."}`)
+fn error_message(error: Option<&Value>) -> Option<String> {
+    match error? {
+        Value::String(message) => Some(message.clone()),
+        Value::Object(error) => error.get("message").and_then(Value::as_str).map(str::to_string),
+        _ => None,
+    }
+}
+
+fn mismatch(path: &str, problem: &str, found: &Value) -> DecodeError {
+    let kind = match found {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    };
+    let mut excerpt = found.to_string();
+    if excerpt.chars().count() > EXCERPT_CHARS {
+        excerpt = excerpt.chars().take(EXCERPT_CHARS).collect::<String>() + "…";
+    }
+    DecodeError { path: path.to_string(), problem: format!("{}, found {} {}", problem, kind, excerpt), hint: None }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fills_defaults_keeps_extras_and_hints_at_variations() {
+        let body = json!({
+            "id": "chatcmpl-1",
+            "model": "qwen2.5",
+            "choices": [{
+                "index": 0,
+                "message": {"content": [{"type": "text", "text": "Hello"}, {"type": "image_url"}, " there"], "tool_calls": []},
+                "finish_reason": null,
+                "logprobs": null
+            }]
+        });
+        let (response, hints) = decode_chat_response(&body).unwrap();
+        let choice = &response.choices[0];
+        assert_eq!((choice.message.role.as_str(), choice.message.content.as_str()), ("assistant", "Hello there"));
+        assert_eq!(choice.finish_reason, "");
+        assert_eq!(response.usage.total_tokens, 0);
+        assert_eq!(response.metadata.get("model"), Some(&json!("qwen2.5")));
+        assert_eq!(response.metadata.get("choices[0].message.tool_calls"), Some(&json!([])));
+        assert!(!response.metadata.contains_key("choices[0].logprobs"));
+        assert_eq!(hints, vec![Hint::ContentParts { path: "choices[0].message.content".into() }, Hint::NoUsage]);
+
+        let anthropic = json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 12, "output_tokens": 3}
+        });
+        let (response, hints) = decode_chat_response(&anthropic).unwrap();
+        assert_eq!(response.choices[0].finish_reason, "end_turn");
+        assert_eq!((response.usage.prompt_tokens, response.usage.total_tokens), (12, 15));
+        assert!(hints.contains(&Hint::MessagesFormat));
+    }
+
+    #[test]
+    fn errors_name_the_path_of_the_mismatch() {
+        let body = json!({"choices": [{"message": {"role": "assistant", "content": "ok"}}, {"message": {"role": 7}}]});
+        let error = decode_chat_response(&body).unwrap_err();
+        assert_eq!(error.path, "choices[1].message.role");
+        assert_eq!(error.to_string(), "cannot decode chat response: `choices[1].message.role` should be a string, found a number 7");
+
+        let error = decode_chat_response(&json!({"usage": {"prompt_tokens": -1}, "choices": []})).unwrap_err();
+        assert_eq!(error.path, "usage.prompt_tokens");
+
+        let error = decode_chat_response(&json!({"error": {"message": "model not found"}})).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot decode chat response: `choices` is missing (the provider returned an error: model not found)"
+        );
+        let error = decode_chat_response(&json!({"choices": [{"delta": {"content": "x"}}]})).unwrap_err();
+        assert_eq!(error.path, "choices[0].message");
+        assert!(error.hint.is_some());
+    }
+}
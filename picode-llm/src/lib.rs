@@ -1,11 +1,26 @@
 //! PiCode LLM - Large Language Model integrations
 
+pub mod attribution;
 pub mod client;
+pub mod decode;
+pub mod llama;
+pub mod mapping;
 pub mod providers;
 pub mod openapi;
+pub mod shaping;
+pub mod signing;
+pub mod streaming;
 
 pub use client::*;
 pub use providers::*;
+pub use signing::{AwsCredentials, HmacSigner, RequestSigner, SigV4Signer, SigningConfig, SigningError};
+pub use mapping::{EndpointPaths, PayloadMapping};
+pub use attribution::Attribution;
+pub use decode::{decode_chat_response, DecodeError, Hint};
+pub use streaming::{EventStream, PartialToolCall, StreamAssembler, StreamError, StreamEvent, ToolCall};
+pub use llama::{ChatTemplate, LlamaCppConfig, LLAMA_CPP_PROVIDER};
+#[cfg(feature = "llama-cpp")]
+pub use llama::LlamaCppProvider;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,510 @@
+//! Local GGUF inference through llama.cpp
+//!
+//! With the `llama-cpp` feature enabled, [`LlamaCppProvider`] loads a GGUF
+//! model file directly (no HTTP server or Ollama daemon) and generates
+//! completions on the local CPU/GPU. Configuration and prompt templating are
+//! always compiled so provider settings can be validated without the native
+//! library.
+
+use crate::providers::{ChatMessage, ProviderConfig};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Provider type selecting the llama.cpp backend
+pub const LLAMA_CPP_PROVIDER: &str = "llama-cpp";
+
+/// Default context window in tokens
+const DEFAULT_CONTEXT_SIZE: u32 = 4096;
+
+/// Default number of tokens generated when a request sets no limit
+const DEFAULT_MAX_TOKENS: u32 = 512;
+
+/// Prompt format expected by the model
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatTemplate {
+    /// `<|im_start|>role ... <|im_end|>` (Qwen, Mistral-instruct finetunes, ...)
+    #[default]
+    ChatMl,
+    /// Llama 3 header tokens
+    Llama3,
+    /// `Role: content` lines, for base models
+    Plain,
+}
+
+impl ChatTemplate {
+    /// Render messages into a prompt ending with the assistant turn
+    pub fn render(&self, messages: &[ChatMessage]) -> String {
+        let mut prompt = String::new();
+        match self {
+            ChatTemplate::ChatMl => {
+                for message in messages {
+                    prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", message.role, message.content));
+                }
+                prompt.push_str("<|im_start|>assistant\n");
+            }
+            ChatTemplate::Llama3 => {
+                prompt.push_str("<|begin_of_text|>");
+                for message in messages {
+                    prompt.push_str(&format!(
+                        "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                        message.role, message.content
+                    ));
+                }
+                prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            }
+            ChatTemplate::Plain => {
+                for message in messages {
+                    prompt.push_str(&format!("{}: {}\n\n", capitalize(&message.role), message.content));
+                }
+                prompt.push_str("Assistant:");
+            }
+        }
+        prompt
+    }
+
+    /// Marker ending the assistant turn
+    pub fn stop_sequence(&self) -> &'static str {
+        match self {
+            ChatTemplate::ChatMl => "<|im_end|>",
+            ChatTemplate::Llama3 => "<|eot_id|>",
+            ChatTemplate::Plain => "\nUser:",
+        }
+    }
+}
+
+fn capitalize(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Settings for a local GGUF model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlamaCppConfig {
+    /// Path to the `.gguf` model file
+    pub model_path: PathBuf,
+    /// Context window in tokens
+    #[serde(default = "default_context_size")]
+    pub context_size: u32,
+    /// Number of layers offloaded to the GPU (0 = CPU only)
+    #[serde(default)]
+    pub gpu_layers: u32,
+    /// Generation threads (llama.cpp default when unset)
+    #[serde(default)]
+    pub threads: Option<u32>,
+    #[serde(default)]
+    pub chat_template: ChatTemplate,
+    /// Tokens generated when a request sets no limit
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+}
+
+fn default_context_size() -> u32 {
+    DEFAULT_CONTEXT_SIZE
+}
+
+fn default_max_tokens() -> u32 {
+    DEFAULT_MAX_TOKENS
+}
+
+impl LlamaCppConfig {
+    pub fn new(model_path: impl Into<PathBuf>) -> Self {
+        Self {
+            model_path: model_path.into(),
+            context_size: DEFAULT_CONTEXT_SIZE,
+            gpu_layers: 0,
+            threads: None,
+            chat_template: ChatTemplate::default(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+        }
+    }
+
+    pub fn with_context_size(mut self, context_size: u32) -> Self {
+        self.context_size = context_size;
+        self
+    }
+
+    pub fn with_gpu_layers(mut self, gpu_layers: u32) -> Self {
+        self.gpu_layers = gpu_layers;
+        self
+    }
+
+    /// Read the settings from a provider configuration's `extra` map
+    pub fn from_provider_config(config: &ProviderConfig) -> Result<Self> {
+        let extra = serde_json::Value::Object(config.extra.clone().into_iter().collect());
+        let config: Self = serde_json::from_value(extra)
+            .map_err(|e| anyhow::anyhow!("invalid llama-cpp provider settings: {}", e))?;
+        if config.context_size == 0 {
+            anyhow::bail!("llama-cpp context_size must be greater than zero");
+        }
+        Ok(config)
+    }
+
+    /// Model name reported to callers: the file name without extension
+    pub fn model_name(&self) -> String {
+        self.model_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "local-model".to_string())
+    }
+}
+
+#[cfg(feature = "llama-cpp")]
+pub use provider::LlamaCppProvider;
+
+#[cfg(feature = "llama-cpp")]
+mod provider {
+    use super::*;
+    use crate::providers::{
+        ChatChoice, ChatRequest, ChatResponse, ChatStream, CompletionChoice, CompletionRequest,
+        CompletionResponse, LlmProvider, ModelInfo, TokenUsage,
+    };
+    use llama_cpp::standard_sampler::StandardSampler;
+    use llama_cpp::{LlamaModel, LlamaParams, SessionParams};
+    use std::collections::HashMap;
+    use tokio::sync::{mpsc, OnceCell};
+
+    /// Output of a generation thread
+    enum Generated {
+        Text(String),
+        /// Generation ended, with an OpenAI-style finish reason
+        Finished(&'static str),
+    }
+
+    /// Provider running a GGUF model in-process
+    pub struct LlamaCppProvider {
+        config: LlamaCppConfig,
+        model: OnceCell<LlamaModel>,
+    }
+
+    impl LlamaCppProvider {
+        /// Create the provider; the model is loaded on first use
+        pub fn new(config: LlamaCppConfig) -> Self {
+            Self {
+                config,
+                model: OnceCell::new(),
+            }
+        }
+
+        async fn model(&self) -> Result<&LlamaModel> {
+            self.model
+                .get_or_try_init(|| async {
+                    if !self.config.model_path.is_file() {
+                        anyhow::bail!("model file not found: {}", self.config.model_path.display());
+                    }
+                    let params = LlamaParams {
+                        n_gpu_layers: self.config.gpu_layers,
+                        ..Default::default()
+                    };
+                    LlamaModel::load_from_file_async(&self.config.model_path, params)
+                        .await
+                        .map_err(|e| anyhow::anyhow!("failed to load {}: {}", self.config.model_path.display(), e))
+                })
+                .await
+        }
+
+        /// Generate from a raw prompt, sending each piece of text as it is produced
+        ///
+        /// Generation runs on a blocking thread so the async runtime stays
+        /// responsive; errors are delivered through the channel. Text that may
+        /// begin a stop sequence is held back until the next piece settles it.
+        async fn generate(
+            &self,
+            prompt: String,
+            max_tokens: u32,
+            stop: Vec<String>,
+            chunks: mpsc::UnboundedSender<Result<Generated>>,
+        ) -> Result<()> {
+            let model = self.model().await?.clone();
+            let mut params = SessionParams {
+                n_ctx: self.config.context_size,
+                ..Default::default()
+            };
+            if let Some(threads) = self.config.threads {
+                params.n_threads = threads;
+            }
+
+            tokio::task::spawn_blocking(move || {
+                let result = (|| -> Result<()> {
+                    let mut session = model
+                        .create_session(params)
+                        .map_err(|e| anyhow::anyhow!("failed to create llama.cpp session: {}", e))?;
+                    session
+                        .advance_context(prompt.as_bytes())
+                        .map_err(|e| anyhow::anyhow!("prompt does not fit the context: {}", e))?;
+                    let completion = session
+                        .start_completing_with(StandardSampler::default(), max_tokens as usize)
+                        .map_err(|e| anyhow::anyhow!("generation failed: {}", e))?;
+
+                    let mut scanner = StopScanner::new(stop);
+                    let mut tokens = 0;
+                    for piece in completion.into_strings() {
+                        tokens += 1;
+                        let (text, stopped) = scanner.push(&piece);
+                        if !text.is_empty() && chunks.send(Ok(Generated::Text(text))).is_err() {
+                            // Receiver dropped: the caller stopped listening
+                            return Ok(());
+                        }
+                        if stopped {
+                            let _ = chunks.send(Ok(Generated::Finished("stop")));
+                            return Ok(());
+                        }
+                    }
+                    let rest = scanner.finish();
+                    if !rest.is_empty() {
+                        let _ = chunks.send(Ok(Generated::Text(rest)));
+                    }
+                    let reason = if tokens >= max_tokens as usize { "length" } else { "stop" };
+                    let _ = chunks.send(Ok(Generated::Finished(reason)));
+                    Ok(())
+                })();
+                if let Err(e) = result {
+                    let _ = chunks.send(Err(e));
+                }
+            });
+            Ok(())
+        }
+
+        async fn start(
+            &self,
+            prompt: String,
+            max_tokens: Option<u32>,
+            mut stop: Vec<String>,
+        ) -> Result<mpsc::UnboundedReceiver<Result<Generated>>> {
+            stop.push(self.config.chat_template.stop_sequence().to_string());
+            let (sender, receiver) = mpsc::unbounded_channel();
+            self.generate(prompt, max_tokens.unwrap_or(self.config.max_tokens), stop, sender)
+                .await?;
+            Ok(receiver)
+        }
+
+        async fn stream_prompt(&self, prompt: String, max_tokens: Option<u32>, stop: Vec<String>) -> Result<ChatStream> {
+            let receiver = self.start(prompt, max_tokens, stop).await?;
+            Ok(Box::pin(futures::stream::unfold(receiver, |mut receiver| async move {
+                loop {
+                    match receiver.recv().await? {
+                        Ok(Generated::Text(text)) => return Some((Ok(text), receiver)),
+                        Ok(Generated::Finished(_)) => continue,
+                        Err(e) => return Some((Err(e), receiver)),
+                    }
+                }
+            })))
+        }
+
+        /// The whole completion, why it ended, and its approximate usage
+        async fn collect(
+            &self,
+            prompt: String,
+            max_tokens: Option<u32>,
+            stop: Vec<String>,
+        ) -> Result<(String, &'static str, TokenUsage)> {
+            let prompt_tokens = crate::llama::approx_tokens(&prompt);
+            let mut receiver = self.start(prompt, max_tokens, stop).await?;
+            let mut text = String::new();
+            let mut finish_reason = "stop";
+            while let Some(generated) = receiver.recv().await {
+                match generated? {
+                    Generated::Text(piece) => text.push_str(&piece),
+                    Generated::Finished(reason) => finish_reason = reason,
+                }
+            }
+            let completion_tokens = crate::llama::approx_tokens(&text);
+            Ok((
+                text,
+                finish_reason,
+                TokenUsage {
+                    prompt_tokens,
+                    completion_tokens,
+                    total_tokens: prompt_tokens + completion_tokens,
+                },
+            ))
+        }
+
+        fn metadata(&self) -> HashMap<String, serde_json::Value> {
+            HashMap::from([
+                ("provider".to_string(), serde_json::json!(LLAMA_CPP_PROVIDER)),
+                ("model_path".to_string(), serde_json::json!(self.config.model_path)),
+            ])
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for LlamaCppProvider {
+        fn name(&self) -> &'static str {
+            LLAMA_CPP_PROVIDER
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            Ok(self.model().await.is_ok())
+        }
+
+        async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+            let (text, finish_reason, usage) = self
+                .collect(request.prompt, request.max_tokens, request.stop.unwrap_or_default())
+                .await?;
+            Ok(CompletionResponse {
+                choices: vec![CompletionChoice {
+                    text,
+                    finish_reason: finish_reason.to_string(),
+                    logprobs: None,
+                }],
+                usage,
+                metadata: self.metadata(),
+            })
+        }
+
+        async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+            let prompt = self.config.chat_template.render(&request.messages);
+            let (content, finish_reason, usage) = self
+                .collect(prompt, request.max_tokens, request.stop.unwrap_or_default())
+                .await?;
+            Ok(ChatResponse {
+                choices: vec![ChatChoice {
+                    message: ChatMessage {
+                        role: "assistant".to_string(),
+                        content: content.trim().to_string(),
+                    },
+                    finish_reason: finish_reason.to_string(),
+                }],
+                usage,
+                metadata: self.metadata(),
+            })
+        }
+
+        async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+            let prompt = self.config.chat_template.render(&request.messages);
+            self.stream_prompt(prompt, request.max_tokens, request.stop.unwrap_or_default())
+                .await
+        }
+
+        async fn get_models(&self) -> Result<Vec<ModelInfo>> {
+            Ok(vec![ModelInfo {
+                id: self.config.model_name(),
+                name: self.config.model_name(),
+                description: Some(format!("Local GGUF model at {}", self.config.model_path.display())),
+                context_window: Some(self.config.context_size),
+                max_output_tokens: None,
+                capabilities: vec!["text-completion".to_string(), "chat".to_string(), "streaming".to_string()],
+            }])
+        }
+    }
+}
+
+/// Finds stop sequences in streamed text without emitting part of one
+///
+/// A tail as long as the longest stop sequence (less one byte) is held back,
+/// since the next piece may complete a stop sequence that starts in it.
+#[cfg_attr(not(feature = "llama-cpp"), allow(dead_code))]
+struct StopScanner {
+    stop: Vec<String>,
+    held: String,
+}
+
+#[cfg_attr(not(feature = "llama-cpp"), allow(dead_code))]
+impl StopScanner {
+    fn new(stop: Vec<String>) -> Self {
+        Self {
+            stop: stop.into_iter().filter(|s| !s.is_empty()).collect(),
+            held: String::new(),
+        }
+    }
+
+    /// Text that can be emitted after `piece`, and whether a stop sequence was reached
+    fn push(&mut self, piece: &str) -> (String, bool) {
+        self.held.push_str(piece);
+        if let Some(stop_at) = self.stop.iter().filter_map(|s| self.held.find(s.as_str())).min() {
+            self.held.truncate(stop_at);
+            return (std::mem::take(&mut self.held), true);
+        }
+        let tail = self.stop.iter().map(|s| s.len() - 1).max().unwrap_or(0);
+        let mut split = self.held.len().saturating_sub(tail);
+        while !self.held.is_char_boundary(split) {
+            split -= 1;
+        }
+        let rest = self.held.split_off(split);
+        (std::mem::replace(&mut self.held, rest), false)
+    }
+
+    /// Text still held back when generation ends without a stop sequence
+    fn finish(self) -> String {
+        self.held
+    }
+}
+
+/// Rough token count used for usage reporting (about 4 characters per token)
+#[cfg_attr(not(feature = "llama-cpp"), allow(dead_code))]
+fn approx_tokens(text: &str) -> u32 {
+    text.chars().count().div_ceil(4) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn renders_chat_templates() {
+        let messages = vec![message("system", "Be brief."), message("user", "Hi")];
+
+        let chatml = ChatTemplate::ChatMl.render(&messages);
+        assert!(chatml.starts_with("<|im_start|>system\nBe brief.<|im_end|>\n"));
+        assert!(chatml.ends_with("<|im_start|>assistant\n"));
+
+        let plain = ChatTemplate::Plain.render(&messages);
+        assert_eq!(plain, "System: Be brief.\n\nUser: Hi\n\nAssistant:");
+    }
+
+    #[test]
+    fn stop_sequences_split_across_pieces_are_never_emitted() {
+        let mut scanner = StopScanner::new(vec!["<|im_end|>".to_string()]);
+        let mut emitted = String::new();
+        let mut stopped = false;
+        for piece in ["Hello", " world<|im", "_end|>ignored"] {
+            let (text, stop) = scanner.push(piece);
+            assert!(!text.contains('<'));
+            emitted.push_str(&text);
+            stopped = stop;
+        }
+        assert_eq!((emitted.as_str(), stopped), ("Hello world", true));
+
+        let mut scanner = StopScanner::new(vec!["\nUser:".to_string()]);
+        assert_eq!(scanner.push("aé1234"), ("a".to_string(), false));
+        assert_eq!(scanner.finish(), "é1234");
+    }
+
+    #[test]
+    fn config_from_provider_extra() {
+        let config = ProviderConfig {
+            provider_type: LLAMA_CPP_PROVIDER.to_string(),
+            name: None,
+            base_url: None,
+            api_key: String::new(),
+            default_model: None,
+            extra: HashMap::from([
+                ("model_path".to_string(), serde_json::json!("/models/qwen2.5-coder-7b-q4_k_m.gguf")),
+                ("gpu_layers".to_string(), serde_json::json!(35)),
+            ]),
+        };
+
+        let llama = LlamaCppConfig::from_provider_config(&config).unwrap();
+        assert_eq!(llama.context_size, DEFAULT_CONTEXT_SIZE);
+        assert_eq!(llama.gpu_layers, 35);
+        assert_eq!(llama.chat_template, ChatTemplate::ChatMl);
+        assert_eq!(llama.max_tokens, DEFAULT_MAX_TOKENS);
+        assert_eq!(llama.model_name(), "qwen2.5-coder-7b-q4_k_m");
+
+        let missing = ProviderConfig { extra: HashMap::new(), ..config };
+        assert!(LlamaCppConfig::from_provider_config(&missing).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Endpoint paths and request payload mapping for quirky providers
+//!
+//! OpenAI-compatible servers do not all agree on paths (`/api/v1/chat`
+//! instead of `/v1/chat/completions`) or on request fields (a required
+//! `stream: false`, `max_new_tokens` instead of `max_tokens`). Both are
+//! configurable per provider so such servers work without code changes.
+//!
+//! ```toml
+//! [llm.providers.quirky]
+//! endpoint = "https://llm.internal"
+//! endpoints = { chat = "/api/v1/chat" }
+//! payload_mapping = { rename = { max_tokens = "max_new_tokens" }, add = { stream = false }, remove = ["top_p"] }
+//! ```
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Key of [`EndpointPaths`] in `ProviderConfig::extra`
+pub const ENDPOINTS_KEY: &str = "endpoints";
+
+/// Key of [`PayloadMapping`] in `ProviderConfig::extra`
+pub const PAYLOAD_MAPPING_KEY: &str = "payload_mapping";
+
+/// Paths of a provider's endpoints, appended to its base URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointPaths {
+    pub chat: String,
+    pub completions: String,
+    pub models: String,
+    pub health: String,
+}
+
+impl Default for EndpointPaths {
+    fn default() -> Self {
+        Self {
+            chat: "/v1/chat/completions".to_string(),
+            completions: "/v1/completions".to_string(),
+            models: "/v1/models".to_string(),
+            health: "/health".to_string(),
+        }
+    }
+}
+
+impl EndpointPaths {
+    pub fn from_provider_config(config: &crate::ProviderConfig) -> Result<Option<Self>> {
+        from_extra(config, ENDPOINTS_KEY)
+    }
+
+    /// `path` appended to `base_url`, with exactly one slash between them
+    pub fn url(base_url: &str, path: &str) -> String {
+        format!("{}/{}", base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+    }
+}
+
+/// Changes to request bodies before they are sent: fields are removed,
+/// then renamed, then added. Field names may be dotted paths
+/// (`options.num_predict`) to reach into nested objects.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PayloadMapping {
+    /// Old field name to new field name
+    pub rename: BTreeMap<String, String>,
+    /// Fields to set, replacing any existing value
+    pub add: BTreeMap<String, Value>,
+    pub remove: Vec<String>,
+}
+
+impl PayloadMapping {
+    pub fn from_provider_config(config: &crate::ProviderConfig) -> Result<Option<Self>> {
+        from_extra(config, PAYLOAD_MAPPING_KEY)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rename.is_empty() && self.add.is_empty() && self.remove.is_empty()
+    }
+
+    /// Apply the mapping to a JSON object body; other bodies are left alone
+    pub fn apply(&self, body: &mut Value) {
+        let Some(object) = body.as_object_mut() else {
+            return;
+        };
+        for field in &self.remove {
+            take(object, field);
+        }
+        for (from, to) in &self.rename {
+            if let Some(value) = take(object, from) {
+                set(object, to, value);
+            }
+        }
+        for (field, value) in &self.add {
+            set(object, field, value.clone());
+        }
+    }
+}
+
+pub(crate) fn from_extra<T: serde::de::DeserializeOwned>(config: &crate::ProviderConfig, key: &str) -> Result<Option<T>> {
+    config
+        .extra
+        .get(key)
+        .map(|value| serde_json::from_value(value.clone()).map_err(|e| anyhow::anyhow!("invalid `{}`: {}", key, e)))
+        .transpose()
+}
+
+/// Remove the field at a dotted path
+fn take(object: &mut Map<String, Value>, path: &str) -> Option<Value> {
+    match path.split_once('.') {
+        None => object.remove(path),
+        Some((head, rest)) => take(object.get_mut(head)?.as_object_mut()?, rest),
+    }
+}
+
+/// Set the field at a dotted path, creating intermediate objects
+pub(crate) fn set(object: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        None => {
+            object.insert(path.to_string(), value);
+        }
+        Some((head, rest)) => {
+            let child = object.entry(head).or_insert_with(|| Value::Object(Map::new()));
+            if !child.is_object() {
+                *child = Value::Object(Map::new());
+            }
+            if let Some(child) = child.as_object_mut() {
+                set(child, rest, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn maps_fields_in_order() {
+        let mapping: PayloadMapping = serde_json::from_value(json!({
+            "rename": { "max_tokens": "options.num_predict", "model": "model_id" },
+            "add": { "stream": false, "options.seed": 7 },
+            "remove": ["top_p", "missing.field"],
+        }))
+        .unwrap();
+        let mut body = json!({ "model": "m", "max_tokens": 64, "top_p": 0.9, "messages": [] });
+        mapping.apply(&mut body);
+        assert_eq!(
+            body,
+            json!({
+                "model_id": "m",
+                "messages": [],
+                "stream": false,
+                "options": { "num_predict": 64, "seed": 7 },
+            })
+        );
+        assert!(PayloadMapping::default().is_empty());
+    }
+
+    #[test]
+    fn endpoint_paths_default_and_join() {
+        let paths: EndpointPaths = serde_json::from_value(json!({ "chat": "api/v1/chat" })).unwrap();
+        assert_eq!(paths.models, "/v1/models");
+        assert_eq!(EndpointPaths::url("https://llm.internal/", &paths.chat), "https://llm.internal/api/v1/chat");
+        assert_eq!(EndpointPaths::url("https://llm.internal", &paths.models), "https://llm.internal/v1/models");
+    }
+}
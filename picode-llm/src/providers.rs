@@ -1,7 +1,14 @@
-use crate::client::{LlmClient, LlmResponse, RequestConfig};
+use crate::attribution::Attribution;
+use crate::client::{LlmClient, PoolSettings};
+use crate::mapping::{EndpointPaths, PayloadMapping};
 use anyhow::Result;
+use futures::Stream;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::pin::Pin;
+
+/// Stream of generated text chunks
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<String>> + Send>>;
 
 /// LLM provider trait
 #[async_trait::async_trait]
@@ -18,6 +25,20 @@ pub trait LlmProvider: Send + Sync {
     /// Generate chat completion
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse>;
     
+    /// Generate chat completion as a stream of text chunks
+    ///
+    /// Providers without native streaming yield the whole reply as one chunk.
+    async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+        let response = self.chat(request).await?;
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .unwrap_or_default();
+        Ok(Box::pin(futures::stream::once(async move { Ok(content) })))
+    }
+    
     /// Get model information
     async fn get_models(&self) -> Result<Vec<ModelInfo>>;
 }
@@ -76,8 +97,14 @@ pub struct ChatRequest {
     pub temperature: Option<f32>,
     /// Top-p nucleus sampling
     pub top_p: Option<f32>,
+    /// Sampling seed, for providers that support reproducible output
+    pub seed: Option<u64>,
     /// Stop sequences
     pub stop: Option<Vec<String>>,
+    /// Tags attributing the request, e.g. `user_id` and `session_id`; the
+    /// provider's [`Attribution`] decides where they are sent
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
 }
 
 /// Chat message
@@ -144,12 +171,16 @@ pub struct GenericProvider {
     base_url: String,
     api_key: String,
     name: String,
+    endpoints: EndpointPaths,
+    payload_mapping: PayloadMapping,
+    attribution: Attribution,
 }
 
 impl GenericProvider {
-    /// Create a new generic provider
+    /// Create a new generic provider; it shares connections with every
+    /// other provider for `base_url`
     pub fn new(name: String, base_url: String, api_key: String) -> Self {
-        let client = LlmClient::new()
+        let client = LlmClient::pooled(&base_url, &PoolSettings::default())
             .expect("Failed to create HTTP client")
             .with_header("Authorization", format!("Bearer {}", api_key))
             .with_header("Content-Type", "application/json");
@@ -159,8 +190,66 @@ impl GenericProvider {
             base_url,
             api_key,
             name,
+            endpoints: EndpointPaths::default(),
+            payload_mapping: PayloadMapping::default(),
+            attribution: Attribution::default(),
         }
     }
+
+    /// Tune connection reuse; providers with the same base URL and settings
+    /// keep sharing one pool
+    pub fn with_pool(mut self, pool: &PoolSettings) -> Result<Self> {
+        self.client = self.client.with_pool(&self.base_url, pool)?;
+        Ok(self)
+    }
+
+    /// Use nonstandard endpoint paths
+    pub fn with_endpoints(mut self, endpoints: EndpointPaths) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Rewrite request bodies before sending them
+    pub fn with_payload_mapping(mut self, mapping: PayloadMapping) -> Self {
+        self.payload_mapping = mapping;
+        self
+    }
+
+    /// Attribute requests with organization/project headers and tags
+    pub fn with_attribution(mut self, attribution: Attribution) -> Self {
+        self.client = self.client.with_headers(attribution.headers());
+        self.attribution = attribution;
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        EndpointPaths::url(&self.base_url, path)
+    }
+
+    /// Sign requests instead of sending the API key as a bearer token
+    pub fn with_signer(mut self, signer: std::sync::Arc<dyn crate::signing::RequestSigner>) -> Self {
+        self.client = self.client.with_signer(signer);
+        self
+    }
+
+    /// Stream a chat reply as text and complete tool calls
+    pub async fn chat_events(&self, request: ChatRequest) -> Result<crate::streaming::EventStream> {
+        let url = self.url(&self.endpoints.chat);
+        let mut body = crate::shaping::shape_chat_request(&request)?;
+        body["stream"] = serde_json::Value::Bool(true);
+        self.attribution.apply(&mut body);
+        self.payload_mapping.apply(&mut body);
+        let response = self.client.post_stream(&url, body).await?;
+        Ok(crate::streaming::events_from_response(response))
+    }
+
+    /// Apply the provider's `signing` configuration, if any
+    fn with_signing(self, signing: Option<&crate::signing::SigningConfig>) -> Result<Self> {
+        Ok(match signing {
+            Some(signing) => self.with_signer(signing.signer()?),
+            None => self,
+        })
+    }
 }
 
 #[async_trait::async_trait]
@@ -172,12 +261,12 @@ impl LlmProvider for GenericProvider {
     }
 
     async fn health_check(&self) -> Result<bool> {
-        let url = format!("{}/health", self.base_url);
+        let url = self.url(&self.endpoints.health);
         match self.client.get(&url).await {
             Ok(response) => Ok(response.status == 200),
             Err(_) => {
                 // Try models endpoint as fallback
-                let models_url = format!("{}/v1/models", self.base_url);
+                let models_url = self.url(&self.endpoints.models);
                 match self.client.get(&models_url).await {
                     Ok(response) => Ok(response.status == 200),
                     Err(_) => Ok(false),
@@ -187,9 +276,11 @@ impl LlmProvider for GenericProvider {
     }
 
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
-        let url = format!("{}/v1/completions", self.base_url);
+        let url = self.url(&self.endpoints.completions);
         
-        let response = self.client.post_json(&url, serde_json::to_value(&request)?).await?;
+        let mut body = serde_json::to_value(&request)?;
+        self.payload_mapping.apply(&mut body);
+        let response = self.client.post_json(&url, body).await?;
         
         if response.status != 200 {
             anyhow::bail!("API request failed with status {}: {}", response.status, response.body);
@@ -200,20 +291,38 @@ impl LlmProvider for GenericProvider {
     }
 
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
-        let url = format!("{}/v1/chat/completions", self.base_url);
+        let url = self.url(&self.endpoints.chat);
         
-        let response = self.client.post_json(&url, serde_json::to_value(&request)?).await?;
+        // Adjust parameters the target model does not accept (e.g. o1 temperature),
+        // then apply the provider's own field mapping
+        let mut body = crate::shaping::shape_chat_request(&request)?;
+        self.attribution.apply(&mut body);
+        self.payload_mapping.apply(&mut body);
+        let response = self.client.post_json(&url, body).await?;
         
         if response.status != 200 {
             anyhow::bail!("API request failed with status {}: {}", response.status, response.body);
         }
 
-        let chat_response: ChatResponse = serde_json::from_value(response.body)?;
+        let (chat_response, hints) = crate::decode::decode_chat_response(&response.body)?;
+        crate::decode::log_hints(&self.name, &hints);
         Ok(chat_response)
     }
 
+    async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+        use futures::StreamExt;
+        let events = self.chat_events(request).await?;
+        Ok(Box::pin(events.filter_map(|event| async move {
+            match event {
+                Ok(crate::streaming::StreamEvent::Text(text)) => Some(Ok(text)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            }
+        })))
+    }
+
     async fn get_models(&self) -> Result<Vec<ModelInfo>> {
-        let url = format!("{}/v1/models", self.base_url);
+        let url = self.url(&self.endpoints.models);
         
         let response = self.client.get(&url).await?;
         
@@ -247,13 +356,25 @@ impl LlmProvider for GenericProvider {
 
 /// Create a provider from configuration
 pub fn create_provider(config: ProviderConfig) -> Result<Box<dyn LlmProvider>> {
+    let signing = crate::signing::SigningConfig::from_provider_config(&config)?;
+    let endpoints = EndpointPaths::from_provider_config(&config)?.unwrap_or_default();
+    let payload_mapping = PayloadMapping::from_provider_config(&config)?.unwrap_or_default();
+    let pool = PoolSettings::from_provider_config(&config)?.unwrap_or_default();
+    let attribution = Attribution::from_provider_config(&config)?
+        .unwrap_or_default()
+        .with_defaults_for(&config.provider_type);
     match config.provider_type.as_str() {
         "openai" => {
             let provider = GenericProvider::new(
                 "OpenAI".to_string(),
                 config.base_url.unwrap_or_else(|| "https://api.openai.com".to_string()),
                 config.api_key,
-            );
+            )
+            .with_pool(&pool)?
+            .with_signing(signing.as_ref())?
+            .with_endpoints(endpoints)
+            .with_payload_mapping(payload_mapping)
+            .with_attribution(attribution);
             Ok(Box::new(provider))
         }
         "anthropic" => {
@@ -261,24 +382,49 @@ pub fn create_provider(config: ProviderConfig) -> Result<Box<dyn LlmProvider>> {
                 "Anthropic".to_string(),
                 config.base_url.unwrap_or_else(|| "https://api.anthropic.com".to_string()),
                 config.api_key,
-            );
+            )
+            .with_pool(&pool)?
+            .with_signing(signing.as_ref())?
+            .with_endpoints(endpoints)
+            .with_payload_mapping(payload_mapping)
+            .with_attribution(attribution);
             Ok(Box::new(provider))
         }
+        crate::llama::LLAMA_CPP_PROVIDER => {
+            let llama = crate::llama::LlamaCppConfig::from_provider_config(&config)?;
+            #[cfg(feature = "llama-cpp")]
+            {
+                Ok(Box::new(crate::llama::LlamaCppProvider::new(llama)))
+            }
+            #[cfg(not(feature = "llama-cpp"))]
+            {
+                anyhow::bail!(
+                    "cannot load {}: PiCode was built without the `llama-cpp` feature",
+                    llama.model_path.display()
+                )
+            }
+        }
         "generic" | _ => {
             let provider = GenericProvider::new(
                 config.name.unwrap_or_else(|| "Generic Provider".to_string()),
                 config.base_url.ok_or_else(|| anyhow::anyhow!("base_url required for generic provider"))?,
                 config.api_key,
-            );
+            )
+            .with_pool(&pool)?
+            .with_signing(signing.as_ref())?
+            .with_endpoints(endpoints)
+            .with_payload_mapping(payload_mapping)
+            .with_attribution(attribution);
             Ok(Box::new(provider))
         }
     }
 }
 
-/// Provider configuration
+/// Provider configuration; `extra` carries the optional `signing`,
+/// `endpoints`, `payload_mapping`, `pool` and `attribution` settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
-    /// Provider type (openai, anthropic, generic)
+    /// Provider type (openai, anthropic, llama-cpp, generic)
     pub provider_type: String,
     /// Provider name
     pub name: Option<String>,
@@ -311,6 +457,22 @@ mod tests {
         assert_eq!(config.api_key, "test-key");
     }
 
+    #[cfg(not(feature = "llama-cpp"))]
+    #[test]
+    fn test_llama_cpp_requires_feature() {
+        let config = ProviderConfig {
+            provider_type: "llama-cpp".to_string(),
+            name: None,
+            base_url: None,
+            api_key: String::new(),
+            default_model: None,
+            extra: HashMap::from([("model_path".to_string(), serde_json::json!("/models/local.gguf"))]),
+        };
+
+        let err = create_provider(config).err().unwrap();
+        assert!(err.to_string().contains("llama-cpp"));
+    }
+
     #[tokio::test]
     async fn test_generic_provider_creation() {
         let provider = GenericProvider::new(
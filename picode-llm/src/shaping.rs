@@ -0,0 +1,212 @@
+//! Per-model request shaping
+//!
+//! Reasoning models (o1, o3, ...) reject parameters that other chat models
+//! accept: `temperature`/`top_p`, the `system` role, or `max_tokens` in favor
+//! of `max_completion_tokens`. A small capability table drives adjustments of
+//! outgoing requests so they do not fail with provider 400s.
+
+use crate::providers::{ChatMessage, ChatRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How a model accepts system instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SystemRoleSupport {
+    /// Standard `system` role
+    System,
+    /// System instructions must use the `developer` role
+    Developer,
+    /// No system role; instructions are folded into the first user message
+    Unsupported,
+}
+
+/// Field used to limit the number of generated tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaxTokensField {
+    MaxTokens,
+    MaxCompletionTokens,
+}
+
+impl MaxTokensField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaxTokensField::MaxTokens => "max_tokens",
+            MaxTokensField::MaxCompletionTokens => "max_completion_tokens",
+        }
+    }
+}
+
+/// Request-relevant capabilities of a model family
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    pub supports_temperature: bool,
+    pub supports_top_p: bool,
+    pub supports_stop: bool,
+    pub system_role: SystemRoleSupport,
+    pub max_tokens_field: MaxTokensField,
+}
+
+impl Default for ModelCapabilities {
+    fn default() -> Self {
+        Self {
+            supports_temperature: true,
+            supports_top_p: true,
+            supports_stop: true,
+            system_role: SystemRoleSupport::System,
+            max_tokens_field: MaxTokensField::MaxTokens,
+        }
+    }
+}
+
+/// Capability table keyed by model name prefix; more specific prefixes first
+const CAPABILITY_TABLE: &[(&str, SystemRoleSupport)] = &[
+    ("o1-mini", SystemRoleSupport::Unsupported),
+    ("o1-preview", SystemRoleSupport::Unsupported),
+    ("o1", SystemRoleSupport::Developer),
+    ("o3", SystemRoleSupport::Developer),
+    ("o4", SystemRoleSupport::Developer),
+];
+
+/// Look up the capabilities of a model by name
+pub fn capabilities_for(model: &str) -> ModelCapabilities {
+    // Strip provider prefixes such as "openai/o1-mini"
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+
+    CAPABILITY_TABLE
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|(_, system_role)| ModelCapabilities {
+            supports_temperature: false,
+            supports_top_p: false,
+            supports_stop: false,
+            system_role: *system_role,
+            max_tokens_field: MaxTokensField::MaxCompletionTokens,
+        })
+        .unwrap_or_default()
+}
+
+/// Adjust a chat request to what the target model accepts
+pub fn shape_chat_request(request: &ChatRequest) -> serde_json::Result<Value> {
+    let capabilities = capabilities_for(&request.model);
+
+    let mut shaped = request.clone();
+    if !capabilities.supports_temperature {
+        shaped.temperature = None;
+    }
+    if !capabilities.supports_top_p {
+        shaped.top_p = None;
+    }
+    if !capabilities.supports_stop {
+        shaped.stop = None;
+    }
+    shaped.messages = shape_messages(&request.messages, capabilities.system_role);
+
+    let mut value = serde_json::to_value(&shaped)?;
+    if let Value::Object(map) = &mut value {
+        // Omit unset parameters entirely; some models reject explicit nulls
+        map.retain(|_, v| !v.is_null());
+
+        if capabilities.max_tokens_field == MaxTokensField::MaxCompletionTokens {
+            if let Some(max_tokens) = map.remove(MaxTokensField::MaxTokens.as_str()) {
+                map.insert(MaxTokensField::MaxCompletionTokens.as_str().to_string(), max_tokens);
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+fn shape_messages(messages: &[ChatMessage], system_role: SystemRoleSupport) -> Vec<ChatMessage> {
+    match system_role {
+        SystemRoleSupport::System => messages.to_vec(),
+        SystemRoleSupport::Developer => messages
+            .iter()
+            .map(|m| ChatMessage {
+                role: if m.role == "system" { "developer".to_string() } else { m.role.clone() },
+                content: m.content.clone(),
+            })
+            .collect(),
+        SystemRoleSupport::Unsupported => {
+            let instructions: Vec<&str> = messages
+                .iter()
+                .filter(|m| m.role == "system")
+                .map(|m| m.content.as_str())
+                .collect();
+
+            let mut shaped: Vec<ChatMessage> =
+                messages.iter().filter(|m| m.role != "system").cloned().collect();
+
+            if !instructions.is_empty() {
+                let preamble = instructions.join("\n\n");
+                match shaped.iter_mut().find(|m| m.role == "user") {
+                    Some(first_user) => {
+                        first_user.content = format!("{}\n\n{}", preamble, first_user.content);
+                    }
+                    None => shaped.insert(
+                        0,
+                        ChatMessage {
+                            role: "user".to_string(),
+                            content: preamble,
+                        },
+                    ),
+                }
+            }
+
+            shaped
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(model: &str) -> ChatRequest {
+        ChatRequest {
+            messages: vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: "Be brief.".to_string(),
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: "Hello".to_string(),
+                },
+            ],
+            model: model.to_string(),
+            max_tokens: Some(256),
+            temperature: Some(0.2),
+            top_p: None,
+            seed: None,
+            stop: None,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_regular_models_unchanged() {
+        let shaped = shape_chat_request(&request("gpt-4")).unwrap();
+        assert_eq!(shaped["max_tokens"], 256);
+        assert!((shaped["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+        assert_eq!(shaped["messages"][0]["role"], "system");
+        assert!(shaped.get("top_p").is_none());
+    }
+
+    #[test]
+    fn test_reasoning_model_shaping() {
+        let shaped = shape_chat_request(&request("openai/o1")).unwrap();
+        assert!(shaped.get("temperature").is_none());
+        assert!(shaped.get("max_tokens").is_none());
+        assert_eq!(shaped["max_completion_tokens"], 256);
+        assert_eq!(shaped["messages"][0]["role"], "developer");
+    }
+
+    #[test]
+    fn test_system_role_folded_for_o1_mini() {
+        let shaped = shape_chat_request(&request("o1-mini")).unwrap();
+        let messages = shaped["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(messages[0]["content"], "Be brief.\n\nHello");
+    }
+}
@@ -0,0 +1,611 @@
+//! Streaming chat replies with tool calls
+//!
+//! Streaming providers send a reply as server-sent events, and a tool call's
+//! JSON arguments arrive as fragments spread over many events. Network
+//! chunks are cut anywhere, including inside a multi-byte UTF-8 character
+//! or between `\r` and `\n`. [`SseDecoder`] buffers bytes until a line is
+//! complete, so events are only ever decoded whole; [`StreamAssembler`]
+//! joins the argument fragments of each call (OpenAI `tool_calls` deltas and
+//! Anthropic `input_json_delta` blocks) and hands out a call only once its
+//! arguments parse. While a call is still arriving,
+//! [`PartialToolCall::preview`] repairs the JSON received so far into the
+//! value it is becoming, for progress display.
+
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::pin::Pin;
+use thiserror::Error;
+
+/// Data of the event ending an OpenAI stream
+const DONE_DATA: &str = "[DONE]";
+
+/// Streaming errors
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum StreamError {
+    #[error("stream interrupted: {0}")]
+    Transport(String),
+
+    #[error("invalid stream event: {0}")]
+    InvalidEvent(String),
+
+    #[error("provider error: {0}")]
+    Provider(String),
+
+    #[error("tool call '{name}' has invalid arguments: {reason}")]
+    InvalidArguments { name: String, reason: String },
+}
+
+/// One server-sent event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental decoder of a `text/event-stream` body
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    /// Bytes of the line being received
+    line: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    /// The last chunk ended in `\r`; a `\n` starting the next belongs to it
+    skip_lf: bool,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events completed by `bytes`
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            if std::mem::take(&mut self.skip_lf) && byte == b'\n' {
+                continue;
+            }
+            if byte == b'\n' || byte == b'\r' {
+                self.skip_lf = byte == b'\r';
+                let line = std::mem::take(&mut self.line);
+                events.extend(self.process_line(&line));
+            } else {
+                self.line.push(byte);
+            }
+        }
+        events
+    }
+
+    /// The last event, when the stream ends without a blank line
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let line = std::mem::take(&mut self.line);
+        self.process_line(&line).or_else(|| self.dispatch())
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        // A whole line: newlines are ASCII, so no character straddles one
+        let line = String::from_utf8_lossy(line);
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(SseEvent { event, data: std::mem::take(&mut self.data).join("\n") })
+    }
+}
+
+/// A tool call whose arguments are still arriving
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartialToolCall {
+    pub id: String,
+    pub name: String,
+    /// Argument JSON received so far
+    pub arguments: String,
+}
+
+impl PartialToolCall {
+    /// The arguments as they stand, with unfinished strings, arrays and
+    /// objects closed; `None` while nothing usable has arrived
+    pub fn preview(&self) -> Option<Value> {
+        complete_partial_json(&self.arguments)
+    }
+
+    fn complete(self) -> Result<ToolCall, StreamError> {
+        let text = if self.arguments.trim().is_empty() { "{}" } else { self.arguments.as_str() };
+        let arguments = serde_json::from_str(text).map_err(|e| StreamError::InvalidArguments {
+            name: self.name.clone(),
+            reason: e.to_string(),
+        })?;
+        Ok(ToolCall { id: self.id, name: self.name, arguments })
+    }
+}
+
+/// A tool call with its complete arguments
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// What a streamed reply produces
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Text(String),
+    ToolCall(ToolCall),
+    Finished { reason: Option<String> },
+}
+
+/// Joins the events of one streamed reply
+#[derive(Debug, Default)]
+pub struct StreamAssembler {
+    calls: BTreeMap<u64, PartialToolCall>,
+    finish_reason: Option<String>,
+    finished: bool,
+}
+
+impl StreamAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tool calls still arriving, in order
+    pub fn pending_tool_calls(&self) -> impl Iterator<Item = &PartialToolCall> {
+        self.calls.values()
+    }
+
+    /// Apply one event; text is passed on at once, tool calls once the
+    /// reply ends
+    pub fn apply(&mut self, event: &SseEvent) -> Vec<Result<StreamEvent, StreamError>> {
+        if self.finished {
+            return Vec::new();
+        }
+        if event.data.trim() == DONE_DATA {
+            return self.finish();
+        }
+        let chunk: Value = match serde_json::from_str(&event.data) {
+            Ok(chunk) => chunk,
+            Err(e) => return vec![Err(StreamError::InvalidEvent(e.to_string()))],
+        };
+        if let Some(error) = chunk.get("error") {
+            return vec![Err(StreamError::Provider(error.to_string()))];
+        }
+        let mut events = Vec::new();
+        match chunk.get("type").and_then(Value::as_str) {
+            Some("content_block_start") => {
+                let block = &chunk["content_block"];
+                if block["type"] == "tool_use" {
+                    let call = self.call(index(&chunk, 0));
+                    call.id = string(&block["id"]).to_string();
+                    call.name = string(&block["name"]).to_string();
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = &chunk["delta"];
+                match delta["type"].as_str() {
+                    Some("input_json_delta") => self.call(index(&chunk, 0)).arguments.push_str(string(&delta["partial_json"])),
+                    _ => push_text(&mut events, string(&delta["text"])),
+                }
+            }
+            Some("message_delta") => {
+                if let Some(reason) = chunk["delta"]["stop_reason"].as_str() {
+                    self.finish_reason = Some(reason.to_string());
+                }
+            }
+            Some("message_stop") => return self.finish(),
+            _ => {
+                // OpenAI chat completion chunk
+                let choice = &chunk["choices"][0];
+                let delta = &choice["delta"];
+                push_text(&mut events, string(&delta["content"]));
+                for (position, fragment) in delta["tool_calls"].as_array().into_iter().flatten().enumerate() {
+                    let call = self.call(index(fragment, position as u64));
+                    if let Some(id) = fragment["id"].as_str() {
+                        call.id = id.to_string();
+                    }
+                    // Some servers repeat the full name in every fragment
+                    let name = string(&fragment["function"]["name"]);
+                    if call.name.is_empty() {
+                        call.name = name.to_string();
+                    } else if name != call.name {
+                        call.name.push_str(name);
+                    }
+                    call.arguments.push_str(string(&fragment["function"]["arguments"]));
+                }
+                if let Some(reason) = choice["finish_reason"].as_str() {
+                    self.finish_reason = Some(reason.to_string());
+                }
+            }
+        }
+        events
+    }
+
+    /// End the reply: every tool call, then [`StreamEvent::Finished`]
+    pub fn finish(&mut self) -> Vec<Result<StreamEvent, StreamError>> {
+        if std::mem::replace(&mut self.finished, true) {
+            return Vec::new();
+        }
+        let mut events: Vec<_> = std::mem::take(&mut self.calls)
+            .into_values()
+            .map(|call| call.complete().map(StreamEvent::ToolCall))
+            .collect();
+        events.push(Ok(StreamEvent::Finished { reason: self.finish_reason.take() }));
+        events
+    }
+
+    fn call(&mut self, index: u64) -> &mut PartialToolCall {
+        self.calls.entry(index).or_default()
+    }
+}
+
+fn index(value: &Value, default: u64) -> u64 {
+    value["index"].as_u64().unwrap_or(default)
+}
+
+fn string(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+fn push_text(events: &mut Vec<Result<StreamEvent, StreamError>>, text: &str) {
+    if !text.is_empty() {
+        events.push(Ok(StreamEvent::Text(text.to_string())));
+    }
+}
+
+/// Stream of a reply's events
+pub type EventStream = Pin<Box<dyn Stream<Item = Result<StreamEvent, StreamError>> + Send>>;
+
+/// Decode a streamed reply's body. A server that ignored the stream request
+/// and answered with a plain JSON completion is handled too.
+pub fn events_from_response(response: reqwest::Response) -> EventStream {
+    let is_sse = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"));
+    if !is_sse {
+        let events = async move {
+            match response.json::<Value>().await {
+                Ok(body) => completion_events(&body),
+                Err(e) => vec![Err(StreamError::Transport(e.to_string()))],
+            }
+        };
+        return Box::pin(futures::stream::once(events).flat_map(futures::stream::iter));
+    }
+    assemble(response.bytes_stream())
+}
+
+/// Events of a non-streamed completion
+fn completion_events(body: &Value) -> Vec<Result<StreamEvent, StreamError>> {
+    let mut assembler = StreamAssembler::new();
+    let message = &body["choices"][0]["message"];
+    let mut events = Vec::new();
+    push_text(&mut events, string(&message["content"]));
+    for (position, call) in message["tool_calls"].as_array().into_iter().flatten().enumerate() {
+        let partial = assembler.call(position as u64);
+        partial.id = string(&call["id"]).to_string();
+        partial.name = string(&call["function"]["name"]).to_string();
+        partial.arguments = string(&call["function"]["arguments"]).to_string();
+    }
+    assembler.finish_reason = body["choices"][0]["finish_reason"].as_str().map(str::to_string);
+    events.extend(assembler.finish());
+    events
+}
+
+/// Turn a body's byte chunks into reply events
+pub fn assemble<S, B, E>(bytes: S) -> EventStream
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    struct State<S> {
+        bytes: Pin<Box<S>>,
+        decoder: SseDecoder,
+        assembler: StreamAssembler,
+        queue: VecDeque<Result<StreamEvent, StreamError>>,
+        ended: bool,
+    }
+
+    let state = State {
+        bytes: Box::pin(bytes),
+        decoder: SseDecoder::new(),
+        assembler: StreamAssembler::new(),
+        queue: VecDeque::new(),
+        ended: false,
+    };
+    Box::pin(futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.queue.pop_front() {
+                return Some((event, state));
+            }
+            if state.ended {
+                return None;
+            }
+            match state.bytes.next().await {
+                Some(Ok(chunk)) => {
+                    for event in state.decoder.feed(chunk.as_ref()) {
+                        state.queue.extend(state.assembler.apply(&event));
+                    }
+                }
+                Some(Err(e)) => {
+                    state.ended = true;
+                    state.queue.push_back(Err(StreamError::Transport(e.to_string())));
+                }
+                None => {
+                    state.ended = true;
+                    if let Some(event) = state.decoder.finish() {
+                        state.queue.extend(state.assembler.apply(&event));
+                    }
+                    // Streams cut off without an end marker still yield their calls
+                    state.queue.extend(state.assembler.finish());
+                }
+            }
+        }
+    }))
+}
+
+/// Best-effort value of a JSON document cut off anywhere: open strings,
+/// arrays and objects are closed, and a trailing key, comma or unfinished
+/// literal is dropped
+pub fn complete_partial_json(text: &str) -> Option<Value> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Expect {
+        Key,
+        Value,
+        /// After a key or value, before `:` / `,`
+        Separator,
+    }
+
+    let mut closers: Vec<char> = Vec::new();
+    let mut expect = Expect::Value;
+    let mut in_string = false;
+    let mut string_is_key = false;
+    // Escape being read: where it started and how many hex digits it still
+    // needs (0 right after the backslash)
+    let mut escape: Option<(usize, usize)> = None;
+    // Longest prefix that is valid JSON once closed, with its closers
+    let mut safe: (usize, Vec<char>) = (0, Vec::new());
+    let mut scalar_start: Option<usize> = None;
+
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match escape {
+                Some((start, 0)) if c == 'u' => escape = Some((start, 4)),
+                Some((_, 0 | 1)) => escape = None,
+                Some((start, n)) if c.is_ascii_hexdigit() => escape = Some((start, n - 1)),
+                Some(_) => return None,
+                None if c == '\\' => escape = Some((i, 0)),
+                None if c == '"' => {
+                    in_string = false;
+                    expect = Expect::Separator;
+                    if !string_is_key {
+                        safe = (i + 1, closers.clone());
+                    }
+                }
+                None => {}
+            }
+            continue;
+        }
+        if let Some(start) = scalar_start {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+') {
+                continue;
+            }
+            scalar_start = None;
+            if serde_json::from_str::<Value>(&text[start..i]).is_err() {
+                return None;
+            }
+            expect = Expect::Separator;
+            safe = (i, closers.clone());
+        }
+        match c {
+            c if c.is_whitespace() => {}
+            '{' => {
+                closers.push('}');
+                expect = Expect::Key;
+                safe = (i + 1, closers.clone());
+            }
+            '[' => {
+                closers.push(']');
+                expect = Expect::Value;
+                safe = (i + 1, closers.clone());
+            }
+            '}' | ']' => {
+                if closers.pop() != Some(c) {
+                    return None;
+                }
+                expect = Expect::Separator;
+                safe = (i + 1, closers.clone());
+            }
+            '"' => {
+                in_string = true;
+                string_is_key = expect == Expect::Key;
+            }
+            ':' => expect = Expect::Value,
+            ',' => expect = if closers.last() == Some(&'}') { Expect::Key } else { Expect::Value },
+            _ if expect == Expect::Value => scalar_start = Some(i),
+            _ => return None,
+        }
+    }
+
+    // Everything received, closed as it stands
+    let mut whole = text.to_string();
+    if in_string {
+        if let Some((start, _)) = escape {
+            whole.truncate(start);
+        }
+        whole.push('"');
+    }
+    whole.extend(closers.iter().rev());
+    if let Ok(value) = serde_json::from_str(&whole) {
+        return Some(value);
+    }
+    // Otherwise the last point where a value was complete
+    let (end, closers) = safe;
+    if end == 0 {
+        return None;
+    }
+    let mut prefix = text[..end].to_string();
+    prefix.extend(closers.iter().rev());
+    serde_json::from_str(&prefix).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    fn openai_chunks(name: &str, fragments: &[String]) -> String {
+        let mut body = String::new();
+        let first = json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "id": "call_1", "type": "function", "function": { "name": name, "arguments": "" } }] } }] });
+        body.push_str(&format!("data: {}\n\n", first));
+        for fragment in fragments {
+            let chunk = json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "function": { "arguments": fragment } }] } }] });
+            body.push_str(&format!("data: {}\r\n\r\n", chunk));
+        }
+        body.push_str("data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\ndata: [DONE]\n\n");
+        body
+    }
+
+    /// Split `text` into pieces at the given fractions of its length,
+    /// ignoring character boundaries
+    fn split_bytes(text: &[u8], cuts: &[usize]) -> Vec<Vec<u8>> {
+        let mut cuts: Vec<usize> = cuts.iter().map(|cut| cut % (text.len() + 1)).collect();
+        cuts.sort_unstable();
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for cut in cuts.into_iter().chain([text.len()]) {
+            pieces.push(text[start..cut].to_vec());
+            start = cut;
+        }
+        pieces
+    }
+
+    fn split_str(text: &str, cuts: &[usize]) -> Vec<String> {
+        let mut boundaries: Vec<usize> = cuts
+            .iter()
+            .map(|cut| cut % (text.len() + 1))
+            .filter(|cut| text.is_char_boundary(*cut))
+            .collect();
+        boundaries.sort_unstable();
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for cut in boundaries.into_iter().chain([text.len()]) {
+            pieces.push(text[start..cut].to_string());
+            start = cut;
+        }
+        pieces
+    }
+
+    async fn collect(pieces: Vec<Vec<u8>>) -> Vec<StreamEvent> {
+        let bytes = futures::stream::iter(pieces.into_iter().map(Ok::<_, std::io::Error>));
+        assemble(bytes).map(|event| event.unwrap()).collect().await
+    }
+
+    fn arguments() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            any::<i64>().prop_map(Value::from),
+            any::<bool>().prop_map(Value::from),
+            "[a-zé😀\"\\\\ \n/]{0,12}".prop_map(Value::from),
+        ];
+        leaf.prop_recursive(3, 24, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+                prop::collection::btree_map("[a-z_ü]{1,8}", inner, 0..4)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn assembles_anthropic_tool_use_blocks() {
+        let events = [
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Reading." } }),
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {} } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"pa" } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "th\": \"src/lib.rs\"}" } }),
+            json!({ "type": "message_delta", "delta": { "stop_reason": "tool_use" } }),
+            json!({ "type": "message_stop" }),
+        ];
+        let body: String = events.iter().map(|event| format!("event: x\ndata: {}\n\n", event)).collect();
+        assert_eq!(
+            collect(vec![body.into_bytes()]).await,
+            vec![
+                StreamEvent::Text("Reading.".to_string()),
+                StreamEvent::ToolCall(ToolCall {
+                    id: "toolu_1".to_string(),
+                    name: "read_file".to_string(),
+                    arguments: json!({ "path": "src/lib.rs" }),
+                }),
+                StreamEvent::Finished { reason: Some("tool_use".to_string()) },
+            ]
+        );
+    }
+
+    #[test]
+    fn previews_partial_arguments() {
+        let preview = |text: &str| complete_partial_json(text);
+        assert_eq!(preview(r#"{"path": "src/li"#), Some(json!({ "path": "src/li" })));
+        assert_eq!(preview(r#"{"path": "a", "con"#), Some(json!({ "path": "a" })));
+        assert_eq!(preview(r#"{"lines": [1, 2, tr"#), Some(json!({ "lines": [1, 2] })));
+        assert_eq!(preview(r#"{"text": "caf\u00"#), Some(json!({ "text": "caf" })));
+        assert_eq!(preview(r#"{"a": {"b": [true, null], "c": -1.5e3}}"#), Some(json!({ "a": { "b": [true, null], "c": -1500.0 } })));
+        assert_eq!(preview(""), None);
+        assert_eq!(preview("{]"), None);
+    }
+
+    proptest! {
+        #[test]
+        fn tool_calls_survive_any_chunking(
+            value in arguments(),
+            fragment_cuts in prop::collection::vec(any::<usize>(), 0..8),
+            byte_cuts in prop::collection::vec(any::<usize>(), 0..16),
+        ) {
+            let text = value.to_string();
+            let body = openai_chunks("write_file", &split_str(&text, &fragment_cuts));
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            let events = runtime.block_on(collect(split_bytes(body.as_bytes(), &byte_cuts)));
+            prop_assert_eq!(
+                events,
+                vec![
+                    StreamEvent::ToolCall(ToolCall { id: "call_1".to_string(), name: "write_file".to_string(), arguments: value }),
+                    StreamEvent::Finished { reason: Some("tool_calls".to_string()) },
+                ]
+            );
+        }
+
+        #[test]
+        fn every_prefix_previews_as_the_same_kind(value in arguments()) {
+            let text = value.to_string();
+            for end in (1..=text.len()).filter(|end| text.is_char_boundary(*end)) {
+                let preview = complete_partial_json(&text[..end]);
+                // A cut-off object or array still previews as one
+                if value.is_object() || value.is_array() {
+                    prop_assert_eq!(preview.map(|p| p.is_object()), Some(value.is_object()));
+                }
+            }
+            prop_assert_eq!(complete_partial_json(&text), Some(value));
+        }
+    }
+}
@@ -1,15 +1,75 @@
 //! PiCode WASM - WebAssembly bindings
+//!
+//! Exposes the host-agnostic parts of picode-core (virtual file system,
+//! output sanitization, tool argument normalization) so PiCode logic can run
+//! as a WASM MCP tool. Processes are unavailable in this build.
 
+use picode_core::ansi::{self, AnsiPolicy};
+use picode_core::MemoryFileSystem;
+use std::path::Path;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
-pub struct PiCodeWasm;
+pub struct PiCodeWasm {
+    fs: MemoryFileSystem,
+}
 
 #[wasm_bindgen]
 impl PiCodeWasm {
     #[wasm_bindgen(constructor)]
     pub fn new() -> PiCodeWasm {
-        PiCodeWasm
+        PiCodeWasm {
+            fs: MemoryFileSystem::new(),
+        }
+    }
+
+    /// Add or replace a file in the virtual file system
+    #[wasm_bindgen(js_name = writeFile)]
+    pub fn write_file(&self, path: &str, contents: &str) {
+        self.fs.insert(path, contents);
+    }
+
+    /// Read a file from the virtual file system
+    #[wasm_bindgen(js_name = readFile)]
+    pub fn read_file(&self, path: &str) -> Option<String> {
+        self.fs
+            .get(Path::new(path))
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// List every path in the virtual file system
+    #[wasm_bindgen(js_name = listFiles)]
+    pub fn list_files(&self) -> Vec<String> {
+        self.fs
+            .paths()
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect()
+    }
+
+    /// Strip unsafe escape sequences from output, keeping colors if `keep_styling`
+    #[wasm_bindgen(js_name = sanitizeOutput)]
+    pub fn sanitize_output(&self, input: &str, keep_styling: bool) -> String {
+        let policy = if keep_styling {
+            AnsiPolicy::Styling
+        } else {
+            AnsiPolicy::Strip
+        };
+        ansi::sanitize(input, policy)
+    }
+
+    /// Normalize tool call arguments (JSON) into their canonical form
+    #[wasm_bindgen(js_name = normalizeToolArguments)]
+    pub fn normalize_tool_arguments(&self, arguments: &str) -> Result<String, JsValue> {
+        let value: serde_json::Value =
+            serde_json::from_str(arguments).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(picode_core::agent::tool_cache::normalize_arguments(&value).to_string())
+    }
+}
+
+impl Default for PiCodeWasm {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -21,4 +81,20 @@ mod tests {
     fn it_works() {
         let _wasm = PiCodeWasm::new();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn virtual_file_system() {
+        let wasm = PiCodeWasm::new();
+        wasm.write_file("/ws/main.rs", "fn main() {}");
+
+        assert_eq!(wasm.read_file("/ws/main.rs").as_deref(), Some("fn main() {}"));
+        assert_eq!(wasm.read_file("/ws/missing.rs"), None);
+        assert_eq!(wasm.list_files(), vec!["/ws/main.rs".to_string()]);
+    }
+
+    #[test]
+    fn sanitizes_output() {
+        let wasm = PiCodeWasm::new();
+        assert_eq!(wasm.sanitize_output("\x1b[31mred\x1b[0m\x1b]0;t\x07", false), "red");
+    }
+}
@@ -0,0 +1,636 @@
+//! `picode agent run`: a multi-turn agent loop with a budget
+//!
+//! The agent works on the task over several turns until it reports it is
+//! done. With a [`RunBudget`] each turn's tokens and cost are counted; past
+//! the wrap-up threshold the next turn asks for a summary of the partial
+//! results and ends the run, and a turn is never allowed more completion
+//! tokens than the budget has left. The run report records the consumption.
+//!
+//! Tools are called with `TOOL <name> <json arguments>` lines in a reply and
+//! go through the run's [`ToolRegistry`], which refuses anything the
+//! selected permission profile (`--permissions`) does not allow; results
+//! and refusals are both passed back to the agent, followed by what the
+//! project's linters found in the files the turn wrote. The run report
+//! lists the diff of every file written and every command run. Repeated read-only
+//! calls are answered from the run's [`ToolCache`] until a call that may
+//! change the workspace clears it. Calls that write files or run commands
+//! count against the run's [`RunLimits`] first; past a limit the run pauses
+//! to ask whether to go on, and stops unless told to.
+//!
+//! With a [`Verification`], a reply ending in `DONE` first has to pass the
+//! configured checks and the critic's review of the diff; what failed is
+//! passed back and the agent gets to repair it, a bounded number of times.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::Result;
+use picode_core::agent::verify::{self, VerificationRound, VerifySettings};
+use picode_core::agent::{
+    AgentRunId, AgentRunReport, AgentTrace, BudgetStatus, BudgetTracker, GuardrailError, Guardrails,
+    LimitConfirmation, LimitKind, RunBudget, RunLimits, ToolCache, ToolCallRecord, ToolRegistry, CRITIC_INSTRUCTIONS,
+    WRAP_UP_INSTRUCTIONS,
+};
+use picode_llm::TokenUsage;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Turns a run may take before it is asked to wrap up
+pub const DEFAULT_MAX_TURNS: usize = 20;
+
+/// Line the agent ends its reply with once the task is complete
+const DONE_MARKER: &str = "DONE";
+
+/// Prefix of a tool call line
+const TOOL_PREFIX: &str = "TOOL ";
+
+const AGENT_INSTRUCTIONS: &str = "You are running as an autonomous agent. Work on the task step \
+by step; after each of your replies you will be asked to continue. When the task is complete, \
+end your reply with a line containing only DONE. To call a tool, put a line \
+`TOOL <name> <json arguments>` in your reply; the results follow in the next message.";
+
+/// Why a run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Finished,
+    /// Asked to summarize as the budget ran low
+    WrappedUp,
+    /// Spent the budget before it could be asked to wrap up
+    BudgetExhausted,
+    /// Asked to summarize after the last allowed turn
+    TurnLimit,
+    /// Said it was done, but verification still failed after the last repair
+    Unverified,
+    /// A tool call would have gone past a run limit and was not allowed to
+    LimitReached(LimitKind),
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Finished => write!(f, "finished"),
+            StopReason::WrappedUp => write!(f, "stopped near its budget"),
+            StopReason::BudgetExhausted => write!(f, "budget exhausted"),
+            StopReason::TurnLimit => write!(f, "turn limit reached"),
+            StopReason::Unverified => write!(f, "finished with verification failing"),
+            StopReason::LimitReached(kind) => write!(f, "stopped at its {} limit", kind),
+        }
+    }
+}
+
+/// Result of an agent run
+#[derive(Debug, Clone)]
+pub struct AgentRunOutcome {
+    /// The agent's last reply: its answer or partial-results summary
+    pub reply: String,
+    pub stop: StopReason,
+    pub turns: usize,
+    pub report: AgentRunReport,
+}
+
+/// How a run's work is verified once the agent says it is done
+pub struct Verification {
+    pub settings: VerifySettings,
+    /// Reviews the diff against the task; `None` skips the review
+    pub critic: Option<Assistant>,
+}
+
+impl Verification {
+    /// Verification from the configured `[agent.verify]` settings, or
+    /// `None` when it is off or has nothing to run; `force` turns it on
+    pub fn from_config(config: &Config, assistant: &Assistant, force: bool) -> Result<Option<Self>> {
+        let mut settings = config.agent.verify.clone();
+        settings.enabled |= force;
+        if !settings.is_active() {
+            return Ok(None);
+        }
+        let critic = if settings.critic {
+            let model = settings.critic_model.clone().unwrap_or_else(|| assistant.model().to_string());
+            Some(Assistant::for_provider(config, assistant.provider_name())?.with_model(model))
+        } else {
+            None
+        };
+        Ok(Some(Self { settings, critic }))
+    }
+}
+
+/// The limits on what a run's tool calls change, and who is asked when a
+/// call would go past one
+pub struct RunGuardrails<'a> {
+    guardrails: Guardrails,
+    confirmation: &'a (dyn LimitConfirmation + Sync),
+}
+
+impl<'a> RunGuardrails<'a> {
+    pub fn new(limits: RunLimits, confirmation: &'a (dyn LimitConfirmation + Sync)) -> Self {
+        Self { guardrails: Guardrails::new(limits), confirmation }
+    }
+
+    /// Count a call that is about to run, and may run a command or write
+    /// `changes`, against the limits
+    fn check(&mut self, runs_command: bool, changes: &[(PathBuf, String)]) -> std::result::Result<(), GuardrailError> {
+        if runs_command {
+            self.guardrails.command(self.confirmation)?;
+        }
+        for (path, diff) in changes {
+            self.guardrails.file_change(path, diff.len(), self.confirmation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prompt and completion prices per million tokens for the provider
+pub fn prices(config: &Config, provider: &str) -> Option<(f64, f64)> {
+    let provider = config.llm.providers.get(provider)?;
+    Some((provider.prompt_price_per_million?, provider.completion_price_per_million?))
+}
+
+fn cost(prices: Option<(f64, f64)>, usage: &TokenUsage) -> f64 {
+    prices.map_or(0.0, |(prompt, completion)| {
+        (usage.prompt_tokens as f64 * prompt + usage.completion_tokens as f64 * completion) / 1_000_000.0
+    })
+}
+
+/// Run `task` to completion, its budget or `max_turns`
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    assistant: &Assistant,
+    system: &str,
+    task: &str,
+    budget: RunBudget,
+    prices: Option<(f64, f64)>,
+    max_turns: usize,
+    tools: &ToolRegistry,
+    mut guardrails: RunGuardrails<'_>,
+    verification: Option<&Verification>,
+) -> Result<AgentRunOutcome> {
+    let system = format!("{}\n\n{}\n\n{}", system, AGENT_INSTRUCTIONS, tools.instructions());
+    let mut trace = AgentTrace::new(AgentRunId::new());
+    let mut cache = ToolCache::new();
+    let mut tracker = BudgetTracker::new(budget);
+    let mut transcript = format!("Task: {}\n\n", task);
+    let mut reply = String::new();
+    let mut turns = 0;
+    let mut rounds: Vec<VerificationRound> = Vec::new();
+
+    let stop = loop {
+        let wrap_up = match tracker.status() {
+            BudgetStatus::Exhausted => break StopReason::BudgetExhausted,
+            BudgetStatus::WrapUp => Some(StopReason::WrappedUp),
+            BudgetStatus::Within if turns + 1 >= max_turns => Some(StopReason::TurnLimit),
+            BudgetStatus::Within => None,
+        };
+        let prompt = match wrap_up {
+            Some(_) => format!("{}user: {}\n\n", transcript, WRAP_UP_INSTRUCTIONS),
+            None => transcript.clone(),
+        };
+        if wrap_up == Some(StopReason::WrappedUp) {
+            tracker.mark_wrapped_up();
+        }
+
+        let max_tokens = budget.max_tokens.map(|max| {
+            let used = tracker.used().prompt_tokens + tracker.used().completion_tokens;
+            max.saturating_sub(used).clamp(1, u32::MAX as u64) as u32
+        });
+        let (text, usage) = assistant.ask_with_usage(&system, &prompt, max_tokens).await?;
+        tracker.record(usage.prompt_tokens as u64, usage.completion_tokens as u64, cost(prices, &usage));
+        turns += 1;
+        reply = text;
+
+        let done = reply.lines().last().is_some_and(|line| line.trim() == DONE_MARKER);
+        if let Some(stop) = wrap_up {
+            break stop;
+        }
+        if done {
+            let Some(verification) = verification else {
+                break StopReason::Finished;
+            };
+            let round = verify(verification, task, &reply, tools, &mut tracker, prices).await?;
+            // The checks may have rewritten files, e.g. with a formatter
+            cache.invalidate();
+            let passed = round.passed();
+            let failures = round.render_failures();
+            rounds.push(round);
+            if passed {
+                break StopReason::Finished;
+            }
+            if rounds.len() > verification.settings.max_repairs {
+                break StopReason::Unverified;
+            }
+            transcript.push_str(&format!(
+                "assistant: {}\n\nuser: Verification of your work failed.\n{}Fix these problems, then end your reply with {} again.\n\n",
+                reply.trim_end(),
+                failures,
+                DONE_MARKER
+            ));
+            continue;
+        }
+        let results = match call_tools(&reply, tools, &mut guardrails, &mut trace, &mut cache).await {
+            Ok(results) => results,
+            Err(GuardrailError::Stopped(exceeded)) => break StopReason::LimitReached(exceeded.kind),
+        };
+        transcript.push_str(&format!("assistant: {}\n\nuser: {}Continue.\n\n", reply.trim_end(), results));
+    };
+
+    let report = AgentRunReport::from_trace(task.to_string(), &trace)
+        .with_budget(tracker.report())
+        .with_permissions(tools.profile_name())
+        .with_verification(rounds);
+    Ok(AgentRunOutcome { reply, stop, turns, report })
+}
+
+/// Run the checks in the workspace and have the critic review the diff;
+/// the critic's tokens count against the run's budget
+async fn verify(
+    verification: &Verification,
+    task: &str,
+    reply: &str,
+    tools: &ToolRegistry,
+    tracker: &mut BudgetTracker,
+    prices: Option<(f64, f64)>,
+) -> Result<VerificationRound> {
+    let context = tools.context();
+    let checks = verify::run_checks(context.processes.as_ref(), &context.root, &verification.settings.checks).await;
+    let objections = match &verification.critic {
+        Some(critic) => {
+            let diff = verify::workspace_diff(context.processes.as_ref(), &context.root).await;
+            let prompt = verify::critic_prompt(task, reply, &diff);
+            let (text, usage) = critic.ask_with_usage(CRITIC_INSTRUCTIONS, &prompt, None).await?;
+            tracker.record(usage.prompt_tokens as u64, usage.completion_tokens as u64, cost(prices, &usage));
+            verify::parse_verdict(&text)
+        }
+        None => None,
+    };
+    Ok(VerificationRound { checks, objections })
+}
+
+/// Run the tool calls in `reply` and describe their results; stops at the
+/// first call the guardrails do not let through
+async fn call_tools(
+    reply: &str,
+    tools: &ToolRegistry,
+    guardrails: &mut RunGuardrails<'_>,
+    trace: &mut AgentTrace,
+    cache: &mut ToolCache,
+) -> std::result::Result<String, GuardrailError> {
+    let mut results = String::new();
+    for line in reply.lines() {
+        let Some(call) = line.trim().strip_prefix(TOOL_PREFIX) else {
+            continue;
+        };
+        let (name, arguments) = call.trim().split_once(' ').unwrap_or((call.trim(), "{}"));
+        let arguments = serde_json::from_str(arguments).unwrap_or(serde_json::Value::Null);
+        let started = std::time::Instant::now();
+        let cached = cache.get(name, &arguments);
+        let output = match &cached {
+            Some(hit) => format!("Result of {}:\n{}\n\n", name, hit.marked_output()),
+            None => {
+                // What the call would run and write; calls the registry
+                // refuses do neither
+                let (command, changes) = match tools.authorize(name, &arguments) {
+                    Ok(()) => (
+                        tools.command(name, &arguments).ok().flatten(),
+                        tools.planned_changes(name, &arguments).await.unwrap_or_default(),
+                    ),
+                    Err(_) => (None, Vec::new()),
+                };
+                guardrails.check(command.is_some(), &changes)?;
+                let result = tools.call(name, &arguments).await;
+                cache.observe_tool(name);
+                match result {
+                    Ok(output) => {
+                        for (path, diff) in changes {
+                            trace.record_file_change(path, diff);
+                        }
+                        if let Some(command) = command {
+                            trace.record_command(command.join(" "), exit_code(&output), started.elapsed());
+                        }
+                        cache.insert(name, &arguments, output.clone());
+                        format!("Result of {}:\n{}\n\n", name, output)
+                    }
+                    Err(e) => format!("{} failed: {}\n\n", name, e),
+                }
+            }
+        };
+        trace.record_tool_call(ToolCallRecord {
+            tool: name.to_string(),
+            arguments,
+            output_bytes: output.len(),
+            cached: cached.is_some(),
+            duration: started.elapsed(),
+            timestamp: chrono::Utc::now(),
+        });
+        results.push_str(&output);
+    }
+    trace.update_cache_stats(cache);
+    let findings = tools.lint_written().await;
+    if !findings.is_empty() {
+        results.push_str(&format!(
+            "Lint findings in the files you wrote:\n{}\n",
+            picode_core::agent::project_tools::render_findings(&findings)
+        ));
+    }
+    Ok(results)
+}
+
+/// Exit code reported on the first line of `run_command` output
+fn exit_code(output: &str) -> Option<i32> {
+    output.lines().next()?.strip_prefix("exit code ")?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picode_core::agent::{PermissionProfile, ToolContext};
+    use picode_core::{FileSystem, MemoryFileSystem, NoProcessRunner};
+    use picode_llm::{ChatRequest, LlmProvider, ModelInfo};
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    fn tools(profile: &str, fs: Arc<MemoryFileSystem>) -> ToolRegistry {
+        let context = ToolContext { root: PathBuf::from("/repo"), fs, processes: Arc::new(NoProcessRunner) };
+        ToolRegistry::new(context, profile, PermissionProfile::builtin(profile).unwrap()).with_builtin_tools()
+    }
+
+    /// Refuses to go past any limit
+    struct Refuse;
+
+    impl LimitConfirmation for Refuse {
+        fn confirm(&self, _exceeded: &picode_core::agent::LimitExceeded) -> bool {
+            false
+        }
+    }
+
+    fn unlimited() -> RunGuardrails<'static> {
+        let limits = RunLimits { max_files_modified: None, max_diff_bytes: None, max_commands: None };
+        RunGuardrails::new(limits, &Refuse)
+    }
+
+    /// Provider that never finishes and spends 100 tokens per turn
+    struct Busy;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for Busy {
+        fn name(&self) -> &'static str {
+            "busy"
+        }
+
+        async fn health_check(&self) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn complete(&self, _request: picode_llm::CompletionRequest) -> anyhow::Result<picode_llm::CompletionResponse> {
+            anyhow::bail!("unsupported")
+        }
+
+        async fn chat(&self, request: ChatRequest) -> anyhow::Result<picode_llm::ChatResponse> {
+            let prompt = &request.messages.last().unwrap().content;
+            let content = if prompt.ends_with(&format!("{}\n\n", WRAP_UP_INSTRUCTIONS)) {
+                "Summary: half done".to_string()
+            } else {
+                "Still working".to_string()
+            };
+            Ok(picode_llm::ChatResponse {
+                choices: vec![picode_llm::ChatChoice {
+                    message: picode_llm::ChatMessage { role: "assistant".to_string(), content },
+                    finish_reason: "stop".to_string(),
+                }],
+                usage: TokenUsage { prompt_tokens: 80, completion_tokens: 20, total_tokens: 100 },
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn get_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_wrap_up_before_the_budget_is_spent() {
+        let assistant = Assistant::with_provider("busy", Box::new(Busy), "busy-1");
+        let budget: RunBudget = "500-tokens".parse().unwrap();
+        let tools = tools("reader", Arc::new(MemoryFileSystem::new()));
+        let outcome = run(
+            &assistant,
+            "sys",
+            "Refactor everything",
+            budget,
+            Some((1.0, 2.0)),
+            DEFAULT_MAX_TURNS,
+            &tools,
+            unlimited(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // 400 tokens reach the 80% threshold, so the fifth turn is the summary
+        assert_eq!(outcome.stop, StopReason::WrappedUp);
+        assert_eq!(outcome.turns, 5);
+        assert_eq!(outcome.reply, "Summary: half done");
+        let budget = outcome.report.budget.clone().unwrap();
+        assert!(budget.wrapped_up);
+        assert_eq!(budget.used.prompt_tokens, 400);
+        assert!((outcome.report.usage.cost_usd - 0.0006).abs() < 1e-9);
+        assert!(outcome.report.to_markdown().contains("- Budget: 500 tokens (100% used, asked to wrap up)"));
+    }
+
+    /// Provider that says it is done, mentioning the failure it was shown
+    struct Finisher;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for Finisher {
+        fn name(&self) -> &'static str {
+            "finisher"
+        }
+
+        async fn health_check(&self) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn complete(&self, _request: picode_llm::CompletionRequest) -> anyhow::Result<picode_llm::CompletionResponse> {
+            anyhow::bail!("unsupported")
+        }
+
+        async fn chat(&self, request: ChatRequest) -> anyhow::Result<picode_llm::ChatResponse> {
+            let prompt = &request.messages.last().unwrap().content;
+            let content = if prompt.contains("Verification of your work failed") {
+                "Fixed the failing test.\nDONE".to_string()
+            } else {
+                "Implemented it.\nDONE".to_string()
+            };
+            Ok(picode_llm::ChatResponse {
+                choices: vec![picode_llm::ChatChoice {
+                    message: picode_llm::ChatMessage { role: "assistant".to_string(), content },
+                    finish_reason: "stop".to_string(),
+                }],
+                usage: TokenUsage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15 },
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn get_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Fails as many commands as its counter starts at
+    #[derive(Debug)]
+    struct Flaky(std::sync::atomic::AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl picode_core::ProcessRunner for Flaky {
+        async fn run(
+            &self,
+            _program: &str,
+            _args: &[String],
+            _working_dir: Option<&Path>,
+            _env: &[(String, String)],
+            _stdin: Option<&[u8]>,
+        ) -> std::io::Result<picode_core::ProcessOutput> {
+            let failing = self.0.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok();
+            let exit_code = if failing { 1 } else { 0 };
+            Ok(picode_core::ProcessOutput { exit_code: Some(exit_code), stdout: b"1 test failed".to_vec(), stderr: Vec::new() })
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_verification_is_repaired_within_the_limit() {
+        let run_with = |failures: usize| async move {
+            let context = ToolContext {
+                root: PathBuf::from("/repo"),
+                fs: Arc::new(MemoryFileSystem::new()),
+                processes: Arc::new(Flaky(failures.into())),
+            };
+            let tools = ToolRegistry::new(context, "reader", PermissionProfile::builtin("reader").unwrap());
+            let settings = VerifySettings { enabled: true, checks: vec!["cargo test".to_string()], ..Default::default() };
+            let verification = Verification { settings, critic: None };
+            let assistant = Assistant::with_provider("finisher", Box::new(Finisher), "finisher-1");
+            let guardrails = unlimited();
+            run(&assistant, "sys", "Add a flag", RunBudget::default(), None, DEFAULT_MAX_TURNS, &tools, guardrails, Some(&verification))
+                .await
+                .unwrap()
+        };
+
+        let outcome = run_with(1).await;
+        assert_eq!(outcome.stop, StopReason::Finished);
+        assert_eq!(outcome.turns, 2);
+        assert_eq!(outcome.reply, "Fixed the failing test.\nDONE");
+        assert_eq!(outcome.report.verification.len(), 2);
+        assert!(outcome.report.to_markdown().contains("## Verification\n\n1. failed: `cargo test`\n2. passed (1 check(s))"));
+
+        // The default two repairs are not enough, so the run ends unverified
+        let outcome = run_with(3).await;
+        assert_eq!(outcome.stop, StopReason::Unverified);
+        assert_eq!(outcome.turns, 3);
+        assert!(outcome.report.verification.iter().all(|round| !round.passed()));
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_limited_by_the_permission_profile() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        fs.write(Path::new("/repo/notes.md"), b"todo").await.unwrap();
+        let tools = tools("reader", fs.clone());
+        let mut trace = AgentTrace::new(AgentRunId::new());
+
+        let reply = "Let me look.\nTOOL read_file {\"path\": \"notes.md\"}\nTOOL write_file {\"path\": \"notes.md\", \"content\": \"\"}";
+        let results = call_tools(reply, &tools, &mut unlimited(), &mut trace, &mut ToolCache::new()).await.unwrap();
+        assert!(results.contains("Result of read_file:\ntodo"));
+        assert!(results.contains("write_file failed: tool 'write_file' is not allowed with 'reader' permissions"));
+        assert_eq!(fs.read_to_string(Path::new("/repo/notes.md")).await.unwrap(), "todo");
+        assert_eq!(trace.tool_call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn repeated_reads_come_from_the_cache_until_a_write() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        fs.write(Path::new("/repo/notes.md"), b"todo").await.unwrap();
+        let tools = tools("editor", fs.clone());
+        let mut trace = AgentTrace::new(AgentRunId::new());
+        let mut cache = ToolCache::new();
+
+        let read = "TOOL read_file {\"path\": \"notes.md\"}";
+        call_tools(read, &tools, &mut unlimited(), &mut trace, &mut cache).await.unwrap();
+        let results = call_tools(read, &tools, &mut unlimited(), &mut trace, &mut cache).await.unwrap();
+        assert!(results.contains("[cached result] todo"));
+
+        let write = "TOOL write_file {\"path\": \"notes.md\", \"content\": \"done\"}";
+        call_tools(&format!("{}\n{}", write, read), &tools, &mut unlimited(), &mut trace, &mut cache).await.unwrap();
+        assert!(trace.tool_calls.last().is_some_and(|call| !call.cached));
+        assert_eq!((trace.cache_stats.hits, trace.cache_stats.invalidations), (1, 1));
+        let results = call_tools(read, &tools, &mut unlimited(), &mut trace, &mut cache).await.unwrap();
+        assert!(results.contains("[cached result] done"));
+    }
+
+    #[tokio::test]
+    async fn written_files_and_commands_are_traced() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        fs.write(Path::new("/repo/notes.md"), b"todo\n").await.unwrap();
+        let context = ToolContext { root: PathBuf::from("/repo"), fs, processes: Arc::new(Flaky(1.into())) };
+        let tools =
+            ToolRegistry::new(context, "operator", PermissionProfile::builtin("operator").unwrap()).with_builtin_tools();
+        let mut trace = AgentTrace::new(AgentRunId::new());
+
+        let reply = "TOOL write_file {\"path\": \"notes.md\", \"content\": \"done\\n\"}\nTOOL run_command {\"program\": \"cargo\", \"args\": [\"test\"]}";
+        call_tools(reply, &tools, &mut unlimited(), &mut trace, &mut ToolCache::new()).await.unwrap();
+        assert_eq!(trace.file_changes.len(), 1);
+        assert_eq!(trace.file_changes[0].path, PathBuf::from("notes.md"));
+        assert!(trace.file_changes[0].diff.contains("-todo\n+done\n"));
+        assert_eq!(trace.commands.len(), 1);
+        assert_eq!((trace.commands[0].command.as_str(), trace.commands[0].exit_code), ("cargo test", Some(1)));
+    }
+
+    /// Provider that keeps rewriting a file
+    struct Writer;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for Writer {
+        fn name(&self) -> &'static str {
+            "writer"
+        }
+
+        async fn health_check(&self) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn complete(&self, _request: picode_llm::CompletionRequest) -> anyhow::Result<picode_llm::CompletionResponse> {
+            anyhow::bail!("unsupported")
+        }
+
+        async fn chat(&self, _request: ChatRequest) -> anyhow::Result<picode_llm::ChatResponse> {
+            let content = "TOOL write_file {\"path\": \"notes.md\", \"content\": \"rewritten\"}".to_string();
+            Ok(picode_llm::ChatResponse {
+                choices: vec![picode_llm::ChatChoice {
+                    message: picode_llm::ChatMessage { role: "assistant".to_string(), content },
+                    finish_reason: "stop".to_string(),
+                }],
+                usage: TokenUsage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15 },
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn get_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_stop_at_a_limit_that_is_not_lifted() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        fs.write(Path::new("/repo/notes.md"), b"todo").await.unwrap();
+        let tools = tools("editor", fs.clone());
+        let assistant = Assistant::with_provider("writer", Box::new(Writer), "writer-1");
+        let limits = RunLimits { max_diff_bytes: Some(10), ..RunLimits::default() };
+        let guardrails = RunGuardrails::new(limits, &Refuse);
+        let outcome =
+            run(&assistant, "sys", "Tidy the notes", RunBudget::default(), None, DEFAULT_MAX_TURNS, &tools, guardrails, None)
+                .await
+                .unwrap();
+
+        assert_eq!(outcome.stop, StopReason::LimitReached(LimitKind::DiffBytes));
+        assert_eq!(outcome.turns, 1);
+        assert_eq!(fs.read_to_string(Path::new("/repo/notes.md")).await.unwrap(), "todo");
+        assert_eq!(outcome.stop.to_string(), "stopped at its diff bytes limit");
+    }
+}
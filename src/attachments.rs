@@ -0,0 +1,201 @@
+//! Command output attached to chat prompts
+//!
+//! `/run <cmd>` in interactive mode runs a shell command through the command
+//! subsystem and shows its output in an output pane. Unless run with
+//! `--no-attach`, a summary of the output (head, tail and error lines, see
+//! [`CommandOutputSummarizer`]) is attached to the next chat prompt so the
+//! model sees what the user just saw.
+
+use crate::error::{PiCodeError, Result};
+use picode_core::ansi::AnsiPolicy;
+use picode_core::{CommandBuilder, CommandOutputSummarizer, CommandResult, Pane};
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// Flag keeping a command's output out of the next prompt
+pub const NO_ATTACH_FLAG: &str = "--no-attach";
+
+/// Longest a `/run` command may take
+const RUN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A parsed `/run` invocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunRequest {
+    pub command: String,
+    /// Whether the output is attached to the next prompt
+    pub attach: bool,
+}
+
+impl RunRequest {
+    /// Parse `[--no-attach] <cmd>`
+    pub fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        let (attach, command) = match args.strip_prefix(NO_ATTACH_FLAG) {
+            Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => (false, rest.trim()),
+            _ => (true, args),
+        };
+        if command.is_empty() {
+            return Err(PiCodeError::InvalidCommand("/run requires a command".to_string()));
+        }
+        Ok(Self { command: command.to_string(), attach })
+    }
+}
+
+/// Summarized output of one command, waiting for the next prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandAttachment {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub summary: String,
+}
+
+impl CommandAttachment {
+    pub fn new(command: impl Into<String>, result: &CommandResult, summarizer: &CommandOutputSummarizer) -> Self {
+        Self {
+            command: command.into(),
+            exit_code: result.status.exit_code(),
+            summary: result.prompt_output(summarizer),
+        }
+    }
+
+    /// Context block for the prompt
+    pub fn render(&self) -> String {
+        let status = self.exit_code.map_or_else(|| "unknown".to_string(), |code| code.to_string());
+        format!(
+            "Output of `{}` (exit code {}):\n```\n{}\n```\n",
+            self.command,
+            status,
+            self.summary.trim_end()
+        )
+    }
+}
+
+/// Attachments waiting for the next chat prompt
+#[derive(Debug, Clone, Default)]
+pub struct PendingAttachments {
+    items: Vec<CommandAttachment>,
+    summarizer: CommandOutputSummarizer,
+}
+
+impl PendingAttachments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Summarize `result` and attach it to the next prompt
+    pub fn attach(&mut self, command: &str, result: &CommandResult) -> &CommandAttachment {
+        self.items.push(CommandAttachment::new(command, result, &self.summarizer));
+        self.items.last().expect("attachment was just pushed")
+    }
+
+    pub fn items(&self) -> &[CommandAttachment] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Drop everything attached so far
+    pub fn clear(&mut self) -> usize {
+        std::mem::take(&mut self.items).len()
+    }
+
+    /// The prompt with the pending attachments as context before it; the
+    /// attachments are consumed
+    pub fn take_prompt(&mut self, prompt: &str) -> String {
+        if self.items.is_empty() {
+            return prompt.to_string();
+        }
+        let mut out = String::new();
+        for item in self.items.drain(..) {
+            out.push_str(&item.render());
+            out.push('\n');
+        }
+        out.push_str(prompt);
+        out
+    }
+}
+
+/// Run `command` in a shell in `working_dir`, returning the output pane
+/// showing it and the result
+pub async fn run(command: &str, working_dir: &Path) -> Result<(Pane, CommandResult)> {
+    let pane = Pane::new_output("text/plain".to_string(), format!("run: {}", command));
+    let result = CommandBuilder::shell(command)
+        .with_working_dir(working_dir.to_path_buf())
+        .with_timeout(RUN_TIMEOUT)
+        .execute()
+        .await
+        .map_err(picode_core::CoreError::from)?;
+    Ok((pane, result))
+}
+
+/// Run `command` in a terminal pane's directory and environment, for lines
+/// broadcast to it
+pub async fn run_in_pane(pane: &Pane, command: &str) -> Result<CommandResult> {
+    let working_dir = pane
+        .get_working_dir()
+        .ok_or_else(|| PiCodeError::InvalidCommand(format!("'{}' is not a terminal pane", pane.title)))?;
+    let mut shell = CommandBuilder::shell(command).with_working_dir(working_dir);
+    if let Some(env) = &pane.env {
+        shell = shell.with_clean_env(env.clone());
+    }
+    let result = shell
+        .with_timeout(RUN_TIMEOUT)
+        .execute()
+        .await
+        .map_err(picode_core::CoreError::from)?;
+    Ok(result)
+}
+
+/// Output pane contents: title, output and exit status
+pub fn render_output(pane: &Pane, result: &CommandResult, policy: AnsiPolicy) -> String {
+    let mut out = format!("── {} ──\n", pane.title);
+    for stream in [result.rendered_stdout(policy), result.rendered_stderr(policy)] {
+        if !stream.is_empty() {
+            out.push_str(&stream);
+            if !stream.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+    let status = match result.status.exit_code() {
+        Some(code) => format!("exit code {}", code),
+        None => format!("{:?}", result.status).to_lowercase(),
+    };
+    let _ = writeln!(out, "── {} in {:.1}s ──", status, result.duration.as_secs_f64());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_run_requests() {
+        assert_eq!(RunRequest::parse("cargo test").unwrap(), RunRequest { command: "cargo test".to_string(), attach: true });
+        let detached = RunRequest::parse("--no-attach ls -la").unwrap();
+        assert_eq!((detached.command.as_str(), detached.attach), ("ls -la", false));
+        assert!(RunRequest::parse("--no-attach").is_err());
+        assert!(RunRequest::parse("").is_err());
+        assert!(RunRequest::parse("--no-attachments").unwrap().attach);
+    }
+
+    #[tokio::test]
+    async fn command_output_is_attached_to_next_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let (pane, result) = run("echo hello; echo oops >&2; exit 3", dir.path()).await.unwrap();
+        assert_eq!(pane.title, "run: echo hello; echo oops >&2; exit 3");
+        let rendered = render_output(&pane, &result, AnsiPolicy::Strip);
+        assert!(rendered.contains("hello\noops\n── exit code 3"));
+
+        let mut pending = PendingAttachments::new();
+        pending.attach("echo hello", &result);
+        let prompt = pending.take_prompt("why did it fail?");
+        assert!(prompt.starts_with("Output of `echo hello` (exit code 3):\n```\nhello\noops\n```\n"));
+        assert!(prompt.ends_with("why did it fail?"));
+        assert!(pending.is_empty());
+        assert_eq!(pending.take_prompt("again"), "again");
+    }
+}
@@ -0,0 +1,796 @@
+//! `picode audit deps`: known vulnerabilities in the workspace's dependencies
+//!
+//! Lockfiles anywhere in the workspace are read for their pinned versions:
+//! `Cargo.lock` (checked against the RustSec advisories cargo-audit uses),
+//! `package-lock.json` (npm advisories), and `poetry.lock`, `uv.lock` and
+//! `requirements*.txt` pins (PyPI advisories). All of them are looked up
+//! through the OSV API, which publishes those databases, so no ecosystem
+//! tool has to be installed. `--plan` asks the model for an upgrade plan.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_core::workspace::{Workspace, WorkspaceConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Public OSV API
+pub const DEFAULT_OSV_URL: &str = "https://api.osv.dev";
+
+/// Queries per OSV batch request, the API's limit
+const BATCH_SIZE: usize = 1000;
+
+const PLAN_SYSTEM: &str = "You plan dependency upgrades that fix known vulnerabilities. For each \
+affected package, say which version to move to, in which lockfile or manifest, and whether the \
+upgrade crosses a major version that may need code changes. Group packages that are upgraded \
+together, put the most severe first and keep it short.";
+
+/// `[audit]` section of the configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// OSV API base URL, e.g. an internal mirror
+    pub osv_url: String,
+
+    /// Advisory ids (or aliases such as CVE ids) that are not reported
+    pub ignore: Vec<String>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self { osv_url: DEFAULT_OSV_URL.to_string(), ignore: Vec::new() }
+    }
+}
+
+/// Package registry a dependency comes from, named as OSV names it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Ecosystem {
+    #[serde(rename = "crates.io")]
+    CratesIo,
+    #[serde(rename = "npm")]
+    Npm,
+    #[serde(rename = "PyPI")]
+    PyPi,
+}
+
+impl Ecosystem {
+    pub fn osv_name(self) -> &'static str {
+        match self {
+            Self::CratesIo => "crates.io",
+            Self::Npm => "npm",
+            Self::PyPi => "PyPI",
+        }
+    }
+
+    /// Package names as the registry compares them
+    fn normalize(self, name: &str) -> String {
+        match self {
+            // PEP 503: case, `_` and `.` do not matter
+            Self::PyPi => name.to_ascii_lowercase().replace(['_', '.'], "-"),
+            Self::CratesIo | Self::Npm => name.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Ecosystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.osv_name())
+    }
+}
+
+/// A pinned dependency and the lockfiles pinning it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dependency {
+    pub ecosystem: Ecosystem,
+    pub name: String,
+    pub version: String,
+    pub lockfiles: Vec<PathBuf>,
+}
+
+/// Whether `path` names a lockfile [`dependencies`] reads
+pub fn is_lockfile(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    matches!(name, "Cargo.lock" | "package-lock.json" | "poetry.lock" | "uv.lock")
+        || (name.starts_with("requirements") && name.ends_with(".txt"))
+}
+
+/// The pinned dependencies of the lockfile at `path`
+pub fn dependencies(path: &Path, content: &str) -> Result<Vec<Dependency>> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let invalid = |e: &dyn fmt::Display| PiCodeError::Parse(format!("{}: {}", path.display(), e));
+    let pins: Vec<(Ecosystem, String, String)> = match name {
+        "Cargo.lock" => {
+            let lock: TomlLock = toml::from_str(content).map_err(|e| invalid(&e))?;
+            lock.package
+                .into_iter()
+                // Workspace members and path crates have no source; git
+                // dependencies are not on crates.io
+                .filter(|package| {
+                    package.source.as_ref().and_then(toml::Value::as_str).is_some_and(|source| source.starts_with("registry+"))
+                })
+                .map(|package| (Ecosystem::CratesIo, package.name, package.version))
+                .collect()
+        }
+        "package-lock.json" => {
+            let lock: serde_json::Value = serde_json::from_str(content).map_err(|e| invalid(&e))?;
+            let mut pins = Vec::new();
+            npm_pins(&lock, &mut pins);
+            pins.into_iter().map(|(name, version)| (Ecosystem::Npm, name, version)).collect()
+        }
+        "poetry.lock" | "uv.lock" => {
+            let lock: TomlLock = toml::from_str(content).map_err(|e| invalid(&e))?;
+            lock.package
+                .into_iter()
+                .filter(|package| !package.source.as_ref().is_some_and(is_local_source))
+                .map(|package| (Ecosystem::PyPi, package.name, package.version))
+                .collect()
+        }
+        _ if is_lockfile(path) => content.lines().filter_map(requirement_pin).map(|(name, version)| (Ecosystem::PyPi, name, version)).collect(),
+        _ => Vec::new(),
+    };
+    Ok(pins
+        .into_iter()
+        .map(|(ecosystem, name, version)| Dependency { ecosystem, name, version, lockfiles: vec![path.to_path_buf()] })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct TomlLock {
+    #[serde(default)]
+    package: Vec<TomlPackage>,
+}
+
+#[derive(Deserialize)]
+struct TomlPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    source: Option<toml::Value>,
+}
+
+/// uv and poetry sources for the project itself and local paths
+fn is_local_source(source: &toml::Value) -> bool {
+    let Some(table) = source.as_table() else {
+        return false;
+    };
+    ["editable", "virtual", "directory", "path"].iter().any(|key| table.contains_key(*key))
+        || table.get("type").and_then(toml::Value::as_str).is_some_and(|kind| matches!(kind, "directory" | "file"))
+}
+
+/// Installed packages of a lockfile v2/v3 (`packages`) or v1 (`dependencies`)
+fn npm_pins(lock: &serde_json::Value, pins: &mut Vec<(String, String)>) {
+    if let Some(packages) = lock.get("packages").and_then(|packages| packages.as_object()) {
+        for (path, package) in packages {
+            // The root package is keyed "", linked workspace packages have no version
+            let Some((_, installed)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            if package.get("link").and_then(|link| link.as_bool()) == Some(true) {
+                continue;
+            }
+            let name = package.get("name").and_then(|name| name.as_str()).unwrap_or(installed);
+            if let Some(version) = package.get("version").and_then(|version| version.as_str()) {
+                pins.push((name.to_string(), version.to_string()));
+            }
+        }
+        return;
+    }
+    if let Some(dependencies) = lock.get("dependencies").and_then(|dependencies| dependencies.as_object()) {
+        for (name, dependency) in dependencies {
+            if let Some(version) = dependency.get("version").and_then(|version| version.as_str()) {
+                // Aliases and local packages have a URL or path as version
+                if version.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+                    pins.push((name.clone(), version.to_string()));
+                }
+            }
+            npm_pins(dependency, pins);
+        }
+    }
+}
+
+/// `name==version` of a requirements line; ranges are not pins and are skipped
+fn requirement_pin(line: &str) -> Option<(String, String)> {
+    let line = line.split(" #").next().unwrap_or(line).split(';').next().unwrap_or(line).trim();
+    if line.starts_with('#') || line.starts_with('-') {
+        return None;
+    }
+    let (name, version) = line.split_once("==")?;
+    let name = name.split('[').next().unwrap_or(name).trim();
+    let version = version.trim();
+    (!name.is_empty() && !version.is_empty() && !version.contains(['*', ',', ' '])).then(|| (name.to_string(), version.to_string()))
+}
+
+/// How bad an advisory is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The advisory gives no severity
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Rating of a CVSS base score
+    pub fn from_score(score: f32) -> Self {
+        match score {
+            s if s >= 9.0 => Self::Critical,
+            s if s >= 7.0 => Self::High,
+            s if s >= 4.0 => Self::Medium,
+            s if s > 0.0 => Self::Low,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// A database's own label, e.g. GitHub's `MODERATE`
+    fn from_label(label: &str) -> Option<Self> {
+        match label.to_ascii_uppercase().as_str() {
+            "CRITICAL" => Some(Self::Critical),
+            "HIGH" => Some(Self::High),
+            "MODERATE" | "MEDIUM" => Some(Self::Medium),
+            "LOW" => Some(Self::Low),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Unknown => "unknown",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        })
+    }
+}
+
+/// Base score of a CVSS 3.x vector such as `CVSS:3.1/AV:N/AC:L/...`
+pub fn cvss3_score(vector: &str) -> Option<f32> {
+    if !vector.starts_with("CVSS:3") {
+        return None;
+    }
+    let metrics: BTreeMap<&str, &str> = vector.split('/').skip(1).filter_map(|metric| metric.split_once(':')).collect();
+    let changed = *metrics.get("S")? == "C";
+    let av = match *metrics.get("AV")? { "N" => 0.85, "A" => 0.62, "L" => 0.55, "P" => 0.2, _ => return None };
+    let ac = match *metrics.get("AC")? { "L" => 0.77, "H" => 0.44, _ => return None };
+    let pr = match (*metrics.get("PR")?, changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let ui = match *metrics.get("UI")? { "N" => 0.85, "R" => 0.62, _ => return None };
+    let cia = |key: &str| match metrics.get(key).copied() {
+        Some("H") => Some(0.56),
+        Some("L") => Some(0.22),
+        Some("N") => Some(0.0),
+        _ => None,
+    };
+    let iss: f64 = 1.0 - (1.0 - cia("C")?) * (1.0 - cia("I")?) * (1.0 - cia("A")?);
+    let impact = if changed { 7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15) } else { 6.42 * iss };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 8.22 * av * ac * pr * ui;
+    let base = if changed { 1.08 * (impact + exploitability) } else { impact + exploitability };
+    // CVSS rounds up to one decimal
+    Some(((base.min(10.0) * 10.0 - 1e-9).ceil() / 10.0) as f32)
+}
+
+/// An advisory as the OSV API returns it, trimmed to what a report needs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Advisory {
+    pub id: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    severity: Vec<OsvSeverity>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+    #[serde(default)]
+    database_specific: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OsvSeverity {
+    #[serde(rename = "type")]
+    kind: String,
+    score: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OsvAffected {
+    #[serde(default)]
+    package: Option<OsvPackage>,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+    #[serde(default)]
+    database_specific: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OsvPackage {
+    name: String,
+    ecosystem: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OsvRange {
+    #[serde(default)]
+    events: Vec<OsvEvent>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OsvEvent {
+    #[serde(default)]
+    fixed: Option<String>,
+}
+
+impl Advisory {
+    /// The CVSS 3 score and the severity, preferring the database's own label
+    pub fn severity(&self) -> (Option<f32>, Severity) {
+        let score = self
+            .severity
+            .iter()
+            .filter(|severity| severity.kind == "CVSS_V3")
+            .find_map(|severity| cvss3_score(&severity.score));
+        let label = std::iter::once(&self.database_specific)
+            .chain(self.affected.iter().map(|affected| &affected.database_specific))
+            .flatten()
+            .find_map(|specific| specific.get("severity").and_then(|label| label.as_str()).and_then(Severity::from_label));
+        (score, label.or(score.map(Severity::from_score)).unwrap_or(Severity::Unknown))
+    }
+
+    /// Versions that fix the advisory for `dependency`
+    pub fn fixed_versions(&self, dependency: &Dependency) -> Vec<String> {
+        let name = dependency.ecosystem.normalize(&dependency.name);
+        let mut fixed: Vec<String> = self
+            .affected
+            .iter()
+            .filter(|affected| {
+                affected.package.as_ref().is_some_and(|package| {
+                    package.ecosystem == dependency.ecosystem.osv_name() && dependency.ecosystem.normalize(&package.name) == name
+                })
+            })
+            .flat_map(|affected| affected.ranges.iter().flat_map(|range| &range.events))
+            .filter_map(|event| event.fixed.clone())
+            .collect();
+        fixed.dedup();
+        fixed
+    }
+}
+
+/// A vulnerable dependency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub advisory: String,
+    pub aliases: Vec<String>,
+    pub summary: String,
+    pub severity: Severity,
+    /// CVSS 3 base score, when the advisory has a vector
+    pub score: Option<f32>,
+    pub ecosystem: Ecosystem,
+    pub package: String,
+    pub version: String,
+    pub fixed_in: Vec<String>,
+    pub lockfiles: Vec<PathBuf>,
+    pub url: String,
+}
+
+impl Finding {
+    pub fn new(advisory: &Advisory, dependency: &Dependency) -> Self {
+        let (score, severity) = advisory.severity();
+        Self {
+            advisory: advisory.id.clone(),
+            aliases: advisory.aliases.clone(),
+            summary: advisory.summary.clone().unwrap_or_default(),
+            severity,
+            score,
+            ecosystem: dependency.ecosystem,
+            package: dependency.name.clone(),
+            version: dependency.version.clone(),
+            fixed_in: advisory.fixed_versions(dependency),
+            lockfiles: dependency.lockfiles.clone(),
+            url: format!("https://osv.dev/vulnerability/{}", advisory.id),
+        }
+    }
+
+    /// Whether `ids` names this finding's advisory or one of its aliases
+    fn matches(&self, ids: &[String]) -> bool {
+        ids.iter().any(|id| id.eq_ignore_ascii_case(&self.advisory) || self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(id)))
+    }
+}
+
+/// Outcome of `picode audit deps`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub lockfiles: Vec<PathBuf>,
+    /// Distinct pinned dependencies looked up
+    pub dependencies: usize,
+    /// Most severe first
+    pub findings: Vec<Finding>,
+    /// Findings left out by `[audit] ignore`
+    pub ignored: usize,
+    /// The model's upgrade plan, with `--plan`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+}
+
+impl AuditReport {
+    /// Number of findings per severity, most severe first
+    pub fn counts(&self) -> Vec<(Severity, usize)> {
+        let mut counts: BTreeMap<Severity, usize> = BTreeMap::new();
+        for finding in &self.findings {
+            *counts.entry(finding.severity).or_default() += 1;
+        }
+        counts.into_iter().rev().collect()
+    }
+
+    pub fn render_markdown(&self) -> String {
+        let mut out = String::from("# Dependency audit\n\n");
+        let lockfiles: Vec<String> = self.lockfiles.iter().map(|path| path.display().to_string()).collect();
+        let _ = writeln!(
+            out,
+            "Scanned {} dependencies in {} lockfile(s): {}.",
+            self.dependencies,
+            lockfiles.len(),
+            lockfiles.join(", ")
+        );
+        if self.findings.is_empty() {
+            out.push_str("No known vulnerabilities.\n");
+        } else {
+            let counts: Vec<String> = self.counts().iter().map(|(severity, count)| format!("{} {}", count, severity)).collect();
+            let _ = writeln!(out, "Vulnerabilities: {} ({}).", self.findings.len(), counts.join(", "));
+        }
+        if self.ignored > 0 {
+            let _ = writeln!(out, "{} finding(s) ignored by `[audit] ignore`.", self.ignored);
+        }
+        if !self.findings.is_empty() {
+            out.push_str("\n| Severity | Package | Version | Advisory | Fixed in | Lockfile |\n|---|---|---|---|---|---|\n");
+            for finding in &self.findings {
+                let severity = match finding.score {
+                    Some(score) => format!("{} ({:.1})", finding.severity, score),
+                    None => finding.severity.to_string(),
+                };
+                let aliases = finding.aliases.iter().filter(|alias| alias.starts_with("CVE-")).cloned().collect::<Vec<_>>();
+                let mut advisory = format!("[{}]({})", finding.advisory, finding.url);
+                if !aliases.is_empty() {
+                    let _ = write!(advisory, " {}", aliases.join(" "));
+                }
+                if !finding.summary.is_empty() {
+                    let _ = write!(advisory, ": {}", finding.summary);
+                }
+                let fixed = if finding.fixed_in.is_empty() { "no fix".to_string() } else { finding.fixed_in.join(", ") };
+                let lockfiles: Vec<String> = finding.lockfiles.iter().map(|path| path.display().to_string()).collect();
+                let _ = writeln!(
+                    out,
+                    "| {} | {} {} | {} | {} | {} | {} |",
+                    severity,
+                    finding.ecosystem,
+                    finding.package,
+                    finding.version,
+                    advisory.replace('|', "\\|"),
+                    fixed,
+                    lockfiles.join(", ")
+                );
+            }
+        }
+        if let Some(plan) = &self.plan {
+            let _ = write!(out, "\n## Upgrade plan\n\n{}\n", plan.trim());
+        }
+        out
+    }
+
+    /// The request for an upgrade plan
+    fn plan_prompt(&self) -> String {
+        let mut prompt = String::from("Vulnerable dependencies found in this workspace:\n\n");
+        for finding in &self.findings {
+            let fixed = if finding.fixed_in.is_empty() { "none yet".to_string() } else { finding.fixed_in.join(", ") };
+            let lockfiles: Vec<String> = finding.lockfiles.iter().map(|path| path.display().to_string()).collect();
+            let _ = writeln!(
+                prompt,
+                "- {} {} {} ({}, {}): {}; fixed in {}; pinned by {}",
+                finding.ecosystem,
+                finding.package,
+                finding.version,
+                finding.advisory,
+                finding.severity,
+                finding.summary,
+                fixed,
+                lockfiles.join(", ")
+            );
+        }
+        prompt
+    }
+}
+
+/// Client of the OSV API
+pub struct OsvClient {
+    client: reqwest::Client,
+    base: String,
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    #[serde(default)]
+    results: Vec<BatchResult>,
+}
+
+#[derive(Deserialize)]
+struct BatchResult {
+    #[serde(default)]
+    vulns: Vec<BatchVuln>,
+}
+
+#[derive(Deserialize)]
+struct BatchVuln {
+    id: String,
+}
+
+impl OsvClient {
+    pub fn new(base: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("picode/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(60))
+            .build()?;
+        Ok(Self { client, base: base.trim_end_matches('/').to_string() })
+    }
+
+    /// Ids of the advisories affecting each of `dependencies`, in order
+    pub async fn query(&self, dependencies: &[Dependency]) -> Result<Vec<Vec<String>>> {
+        let mut ids = Vec::with_capacity(dependencies.len());
+        for batch in dependencies.chunks(BATCH_SIZE) {
+            let queries: Vec<serde_json::Value> = batch
+                .iter()
+                .map(|dependency| {
+                    serde_json::json!({
+                        "package": { "name": dependency.name, "ecosystem": dependency.ecosystem.osv_name() },
+                        "version": dependency.version,
+                    })
+                })
+                .collect();
+            let response: BatchResponse = self
+                .client
+                .post(format!("{}/v1/querybatch", self.base))
+                .json(&serde_json::json!({ "queries": queries }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            if response.results.len() != batch.len() {
+                return Err(PiCodeError::Parse(format!(
+                    "OSV answered {} of {} queries",
+                    response.results.len(),
+                    batch.len()
+                )));
+            }
+            ids.extend(response.results.into_iter().map(|result| result.vulns.into_iter().map(|vuln| vuln.id).collect()));
+        }
+        Ok(ids)
+    }
+
+    pub async fn advisory(&self, id: &str) -> Result<Advisory> {
+        Ok(self
+            .client
+            .get(format!("{}/v1/vulns/{}", self.base, id))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+/// Options of `picode audit deps`
+#[derive(Debug, Clone, Default)]
+pub struct DepsOptions {
+    pub json: bool,
+    pub output: Option<PathBuf>,
+    pub plan: bool,
+    /// Fail when a finding is at least this severe
+    pub fail_on: Option<Severity>,
+}
+
+/// Run `picode audit deps`
+pub async fn deps(config: &Config, options: DepsOptions) -> Result<()> {
+    let root = crate::stats::workspace_root(config)?;
+    let report = audit(config, &root).await?;
+    let report = if options.plan && !report.findings.is_empty() {
+        let assistant = Assistant::from_config(config)?;
+        eprintln!("🛠  Planning upgrades with {}...", assistant.model());
+        let plan = assistant.ask(PLAN_SYSTEM, &report.plan_prompt(), Some(2000)).await?;
+        AuditReport { plan: Some(plan), ..report }
+    } else {
+        report
+    };
+
+    let rendered = if options.json { serde_json::to_string_pretty(&report)? + "\n" } else { report.render_markdown() };
+    match &options.output {
+        Some(path) => {
+            tokio::fs::write(path, rendered).await?;
+            println!("📝 Audit report written to {}", path.display());
+        }
+        None => print!("{}", rendered),
+    }
+
+    if let Some(threshold) = options.fail_on {
+        let failing = report.findings.iter().filter(|finding| finding.severity >= threshold).count();
+        if failing > 0 {
+            return Err(PiCodeError::Internal(format!("{} vulnerable dependencies at {} severity or above", failing, threshold)));
+        }
+    }
+    Ok(())
+}
+
+/// Look up the dependencies pinned by the lockfiles of `root`
+pub async fn audit(config: &Config, root: &Path) -> Result<AuditReport> {
+    let mut workspace = Workspace::new(WorkspaceConfig {
+        root_path: root.to_path_buf(),
+        git_enabled: false,
+        ..WorkspaceConfig::default()
+    });
+    workspace.scan().await.map_err(picode_core::CoreError::from)?;
+
+    let mut lockfiles = Vec::new();
+    let mut pinned: BTreeMap<(Ecosystem, String, String), Dependency> = BTreeMap::new();
+    for file in workspace.files.iter().filter(|file| is_lockfile(&file.relative_path)) {
+        let content = tokio::fs::read_to_string(&file.path).await?;
+        for dependency in dependencies(&file.relative_path, &content)? {
+            let key = (dependency.ecosystem, dependency.ecosystem.normalize(&dependency.name), dependency.version.clone());
+            match pinned.get_mut(&key) {
+                Some(known) if !known.lockfiles.contains(&file.relative_path) => known.lockfiles.push(file.relative_path.clone()),
+                Some(_) => {}
+                None => {
+                    pinned.insert(key, dependency);
+                }
+            }
+        }
+        lockfiles.push(file.relative_path.clone());
+    }
+    let dependencies: Vec<Dependency> = pinned.into_values().collect();
+    if dependencies.is_empty() {
+        return Ok(AuditReport { lockfiles, ..AuditReport::default() });
+    }
+
+    let client = OsvClient::new(&config.audit.osv_url)?;
+    let affected = client.query(&dependencies).await?;
+    let mut advisories = BTreeMap::new();
+    for id in affected.iter().flatten().collect::<BTreeSet<_>>() {
+        advisories.insert(id.clone(), client.advisory(id).await?);
+    }
+
+    let mut report = AuditReport { lockfiles, dependencies: dependencies.len(), ..AuditReport::default() };
+    for (dependency, ids) in dependencies.iter().zip(&affected) {
+        for advisory in ids.iter().filter_map(|id| advisories.get(id)) {
+            let finding = Finding::new(advisory, dependency);
+            if finding.matches(&config.audit.ignore) {
+                report.ignored += 1;
+            } else {
+                report.findings.push(finding);
+            }
+        }
+    }
+    report.findings.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.package.cmp(&b.package)));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pins(path: &str, content: &str) -> Vec<(Ecosystem, String, String)> {
+        dependencies(Path::new(path), content)
+            .unwrap()
+            .into_iter()
+            .map(|dependency| (dependency.ecosystem, dependency.name, dependency.version))
+            .collect()
+    }
+
+    #[test]
+    fn reads_pins_from_each_lockfile_format() {
+        let cargo = r#"
+            version = 3
+            [[package]]
+            name = "picode"
+            version = "0.1.0"
+            [[package]]
+            name = "time"
+            version = "0.1.45"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+            [[package]]
+            name = "forked"
+            version = "1.0.0"
+            source = "git+https://example.com/forked#abc"
+        "#;
+        assert_eq!(pins("Cargo.lock", cargo), vec![(Ecosystem::CratesIo, "time".into(), "0.1.45".into())]);
+
+        let npm = r#"{"lockfileVersion": 3, "packages": {
+            "": {"name": "web", "version": "1.0.0"},
+            "node_modules/lodash": {"version": "4.17.15"},
+            "node_modules/a/node_modules/@scope/b": {"version": "2.0.0"},
+            "node_modules/ui": {"resolved": "packages/ui", "link": true}
+        }}"#;
+        assert_eq!(
+            pins("web/package-lock.json", npm),
+            vec![(Ecosystem::Npm, "@scope/b".into(), "2.0.0".into()), (Ecosystem::Npm, "lodash".into(), "4.17.15".into())]
+        );
+        let npm_v1 = r#"{"dependencies": {"minimist": {"version": "0.0.8", "dependencies": {"x": {"version": "file:../x"}}}}}"#;
+        assert_eq!(pins("package-lock.json", npm_v1), vec![(Ecosystem::Npm, "minimist".into(), "0.0.8".into())]);
+
+        let uv = r#"
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            source = { editable = "." }
+            [[package]]
+            name = "jinja2"
+            version = "2.10"
+            source = { registry = "https://pypi.org/simple" }
+        "#;
+        assert_eq!(pins("uv.lock", uv), vec![(Ecosystem::PyPi, "jinja2".into(), "2.10".into())]);
+
+        let requirements = "# pinned\nDjango[bcrypt]==3.2.1 ; python_version >= '3.8'\nrequests>=2.0\n-r base.txt\nurllib3==1.26.4  # via requests\n";
+        assert_eq!(
+            pins("requirements-dev.txt", requirements),
+            vec![(Ecosystem::PyPi, "Django".into(), "3.2.1".into()), (Ecosystem::PyPi, "urllib3".into(), "1.26.4".into())]
+        );
+        assert!(!is_lockfile(Path::new("Cargo.toml")));
+        assert!(dependencies(Path::new("Cargo.lock"), "[[package]\n").is_err());
+    }
+
+    #[test]
+    fn findings_carry_severity_and_fixes() {
+        assert_eq!(cvss3_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"), Some(9.8));
+        assert_eq!(cvss3_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N"), Some(6.1));
+        assert_eq!(cvss3_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N"), Some(0.0));
+        assert_eq!(cvss3_score("CVSS:4.0/AV:N/AC:L/AT:N/PR:N/UI:N/VC:H/VI:H/VA:H/SC:N/SI:N/SA:N"), None);
+
+        let advisory: Advisory = serde_json::from_value(serde_json::json!({
+            "id": "GHSA-35jh-r3h4-6jhm",
+            "summary": "Command Injection in lodash",
+            "aliases": ["CVE-2021-23337"],
+            "severity": [{"type": "CVSS_V3", "score": "CVSS:3.1/AV:N/AC:L/PR:H/UI:N/S:U/C:H/I:H/A:H"}],
+            "affected": [{
+                "package": {"name": "lodash", "ecosystem": "npm"},
+                "ranges": [{"type": "SEMVER", "events": [{"introduced": "0"}, {"fixed": "4.17.21"}]}]
+            }],
+            "database_specific": {"severity": "HIGH"}
+        }))
+        .unwrap();
+        let dependency = Dependency {
+            ecosystem: Ecosystem::Npm,
+            name: "lodash".into(),
+            version: "4.17.15".into(),
+            lockfiles: vec![PathBuf::from("web/package-lock.json")],
+        };
+        let finding = Finding::new(&advisory, &dependency);
+        assert_eq!((finding.score, finding.severity), (Some(7.2), Severity::High));
+        assert_eq!(finding.fixed_in, vec!["4.17.21"]);
+        assert!(finding.matches(&["cve-2021-23337".to_string()]));
+
+        let report = AuditReport {
+            lockfiles: vec![PathBuf::from("web/package-lock.json")],
+            dependencies: 12,
+            findings: vec![finding],
+            ..AuditReport::default()
+        };
+        let markdown = report.render_markdown();
+        assert!(markdown.contains("Vulnerabilities: 1 (1 high)."));
+        assert!(markdown.contains(
+            "| high (7.2) | npm lodash | 4.17.15 | [GHSA-35jh-r3h4-6jhm](https://osv.dev/vulnerability/GHSA-35jh-r3h4-6jhm) CVE-2021-23337: Command Injection in lodash | 4.17.21 | web/package-lock.json |"
+        ));
+        assert!(Severity::Critical > Severity::High && Severity::Low > Severity::Unknown);
+    }
+}
@@ -0,0 +1,364 @@
+//! `picode bench llm` - provider benchmarking
+//!
+//! Sends a suite of prompts to one or more providers and reports latency
+//! percentiles, completion throughput and failure rates per provider, plus
+//! an optional 0-10 quality score from a judge model. The comparison is
+//! printed as a table or as JSON for capacity planning. `picode bench core`
+//! is implemented in [`crate::bench_core`].
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_core::system_prompt::estimate_tokens;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const BENCH_SYSTEM_PROMPT: &str = "You are a helpful software engineering assistant. Answer concisely.";
+
+const JUDGE_SYSTEM_PROMPT: &str = "You grade answers from AI assistants. \
+Given a task, an optional reference and an answer, rate the answer's correctness and usefulness \
+from 0 (useless) to 10 (excellent). Reply with the number only.";
+
+/// One prompt of a benchmark suite
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchPrompt {
+    pub name: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    /// What a good answer covers; given to the judge model
+    #[serde(default)]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+}
+
+/// Prompts to benchmark with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchSuite {
+    pub prompts: Vec<BenchPrompt>,
+}
+
+impl BenchSuite {
+    /// Small default suite covering short answers, code and explanation
+    pub fn builtin() -> Self {
+        let prompt = |name: &str, prompt: &str, max_tokens: u32| BenchPrompt {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            system: None,
+            reference: None,
+            max_tokens: Some(max_tokens),
+        };
+        Self {
+            prompts: vec![
+                prompt("short-answer", "What does the `?` operator do in Rust? One sentence.", 64),
+                prompt(
+                    "code",
+                    "Write a Rust function that returns the n-th Fibonacci number iteratively.",
+                    256,
+                ),
+                prompt(
+                    "explain",
+                    "Explain the difference between a mutex and a read-write lock, with one use case each.",
+                    384,
+                ),
+            ],
+        }
+    }
+
+    /// Load a suite from YAML or JSON
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path).await?;
+        let suite: Self = serde_yaml::from_str(&content)?;
+        if suite.prompts.is_empty() {
+            return Err(PiCodeError::Parse(format!("{} contains no prompts", path.display())));
+        }
+        Ok(suite)
+    }
+}
+
+/// Options of `picode bench llm`
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// `provider` or `provider/model`; empty means the default provider
+    pub providers: Vec<String>,
+    pub suite: Option<PathBuf>,
+    pub iterations: usize,
+    pub judge: Option<String>,
+    pub json: bool,
+}
+
+/// Result of one request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub prompt: String,
+    pub latency: Duration,
+    pub completion_tokens: u32,
+    pub error: Option<String>,
+    pub quality: Option<f64>,
+}
+
+/// Latency distribution in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub mean: f64,
+}
+
+impl Percentiles {
+    pub fn from_values(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        Self {
+            p50: percentile(&sorted, 50.0),
+            p90: percentile(&sorted, 90.0),
+            p99: percentile(&sorted, 99.0),
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Aggregated results for one provider/model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetReport {
+    pub target: String,
+    pub requests: usize,
+    pub failures: usize,
+    pub failure_rate: f64,
+    /// Latency of successful requests
+    pub latency_ms: Percentiles,
+    /// Completion tokens per second of successful requests
+    pub tokens_per_sec: f64,
+    /// Mean judge score (0-10), when a judge was used
+    pub quality: Option<f64>,
+    /// Distinct error messages
+    pub errors: Vec<String>,
+}
+
+impl TargetReport {
+    pub fn from_samples(target: impl Into<String>, samples: &[Sample]) -> Self {
+        let succeeded: Vec<&Sample> = samples.iter().filter(|s| s.error.is_none()).collect();
+        let latencies: Vec<f64> = succeeded.iter().map(|s| s.latency.as_secs_f64() * 1000.0).collect();
+        let seconds: f64 = succeeded.iter().map(|s| s.latency.as_secs_f64()).sum();
+        let tokens: u32 = succeeded.iter().map(|s| s.completion_tokens).sum();
+        let scores: Vec<f64> = samples.iter().filter_map(|s| s.quality).collect();
+
+        let mut errors: Vec<String> = samples.iter().filter_map(|s| s.error.clone()).collect();
+        errors.sort();
+        errors.dedup();
+
+        let failures = samples.len() - succeeded.len();
+        Self {
+            target: target.into(),
+            requests: samples.len(),
+            failures,
+            failure_rate: if samples.is_empty() { 0.0 } else { failures as f64 / samples.len() as f64 },
+            latency_ms: Percentiles::from_values(&latencies),
+            tokens_per_sec: if seconds > 0.0 { tokens as f64 / seconds } else { 0.0 },
+            quality: (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64),
+            errors,
+        }
+    }
+}
+
+/// Comparison table of the reports
+pub fn render_table(reports: &[TargetReport]) -> String {
+    let width = reports.iter().map(|r| r.target.len()).max().unwrap_or(0).max("target".len());
+    let mut out = format!(
+        "{:<width$}  {:>8}  {:>8}  {:>8}  {:>8}  {:>9}  {:>7}  {:>7}\n",
+        "target", "requests", "p50 ms", "p90 ms", "p99 ms", "tokens/s", "failed", "quality",
+    );
+    for report in reports {
+        out.push_str(&format!(
+            "{:<width$}  {:>8}  {:>8.0}  {:>8.0}  {:>8.0}  {:>9.1}  {:>6.1}%  {:>7}\n",
+            report.target,
+            report.requests,
+            report.latency_ms.p50,
+            report.latency_ms.p90,
+            report.latency_ms.p99,
+            report.tokens_per_sec,
+            report.failure_rate * 100.0,
+            report.quality.map_or_else(|| "-".to_string(), |q| format!("{:.1}", q)),
+        ));
+    }
+    out
+}
+
+/// First number in a judge reply, clamped to 0-10
+pub fn parse_score(reply: &str) -> Option<f64> {
+    let start = reply.find(|c: char| c.is_ascii_digit())?;
+    let number: String = reply[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    number.trim_end_matches('.').parse::<f64>().ok().map(|score| score.clamp(0.0, 10.0))
+}
+
+/// Assistant for `provider` or `provider/model`
+fn assistant_for(config: &Config, target: &str) -> Result<Assistant> {
+    let assistant = match target.split_once('/') {
+        Some((provider, model)) => Assistant::for_provider(config, provider)?.with_model(model),
+        None => Assistant::for_provider(config, target)?,
+    };
+    Ok(assistant)
+}
+
+async fn judge_answer(judge: &Assistant, prompt: &BenchPrompt, answer: &str) -> Option<f64> {
+    let mut request = format!("Task:\n{}\n", prompt.prompt);
+    if let Some(reference) = &prompt.reference {
+        request.push_str(&format!("\nReference:\n{}\n", reference));
+    }
+    request.push_str(&format!("\nAnswer:\n{}\n", answer));
+
+    match judge.ask(JUDGE_SYSTEM_PROMPT, &request, Some(8)).await {
+        Ok(reply) => parse_score(&reply),
+        Err(e) => {
+            warn!("Judge failed on '{}': {}", prompt.name, e);
+            None
+        }
+    }
+}
+
+async fn bench_target(assistant: &Assistant, suite: &BenchSuite, iterations: usize, judge: Option<&Assistant>) -> Vec<Sample> {
+    let mut samples = Vec::new();
+    for prompt in &suite.prompts {
+        for _ in 0..iterations {
+            let system = prompt.system.as_deref().unwrap_or(BENCH_SYSTEM_PROMPT);
+            let started = Instant::now();
+            let result = assistant.ask_with_usage(system, &prompt.prompt, prompt.max_tokens).await;
+            let latency = started.elapsed();
+
+            let sample = match result {
+                Ok((reply, usage)) => {
+                    // Some providers do not report usage; fall back to an estimate
+                    let completion_tokens = match usage.completion_tokens {
+                        0 => estimate_tokens(&reply) as u32,
+                        tokens => tokens,
+                    };
+                    let quality = match judge {
+                        Some(judge) => judge_answer(judge, prompt, &reply).await,
+                        None => None,
+                    };
+                    Sample { prompt: prompt.name.clone(), latency, completion_tokens, error: None, quality }
+                }
+                Err(e) => Sample {
+                    prompt: prompt.name.clone(),
+                    latency,
+                    completion_tokens: 0,
+                    error: Some(e.to_string()),
+                    quality: None,
+                },
+            };
+            samples.push(sample);
+        }
+    }
+    samples
+}
+
+/// Run the benchmark and print the comparison
+pub async fn run(options: BenchOptions, config: Config) -> Result<()> {
+    let suite = match &options.suite {
+        Some(path) => BenchSuite::load(path).await?,
+        None => BenchSuite::builtin(),
+    };
+    let targets = if options.providers.is_empty() {
+        vec![config.llm.default_provider.clone()]
+    } else {
+        options.providers.clone()
+    };
+    let judge = options.judge.as_deref().map(|judge| assistant_for(&config, judge)).transpose()?;
+    let iterations = options.iterations.max(1);
+
+    let mut reports = Vec::new();
+    for target in &targets {
+        let assistant = assistant_for(&config, target)?;
+        let label = format!("{}/{}", assistant.provider_name(), assistant.model());
+        if !options.json {
+            println!(
+                "⏱️  Benchmarking {} ({} prompts × {})...",
+                label,
+                suite.prompts.len(),
+                iterations
+            );
+        }
+        info!("Benchmarking {}", label);
+        let samples = bench_target(&assistant, &suite, iterations, judge.as_ref()).await;
+        reports.push(TargetReport::from_samples(label, &samples));
+    }
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        println!();
+        print!("{}", render_table(&reports));
+        for report in reports.iter().filter(|r| !r.errors.is_empty()) {
+            println!("\n{} errors:", report.target);
+            for error in &report.errors {
+                println!("  - {}", error);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ms: u64, tokens: u32, error: Option<&str>, quality: Option<f64>) -> Sample {
+        Sample {
+            prompt: "p".to_string(),
+            latency: Duration::from_millis(ms),
+            completion_tokens: tokens,
+            error: error.map(str::to_string),
+            quality,
+        }
+    }
+
+    #[test]
+    fn aggregates_latency_throughput_and_failures() {
+        let mut samples: Vec<Sample> = (1..=10).map(|i| sample(i * 100, 50, None, Some(7.0))).collect();
+        samples.push(sample(30_000, 0, Some("timeout"), None));
+        samples.push(sample(30_000, 0, Some("timeout"), None));
+
+        let report = TargetReport::from_samples("openai/gpt-4o", &samples);
+        assert_eq!(report.requests, 12);
+        assert_eq!(report.failures, 2);
+        assert_eq!(report.latency_ms.p50, 500.0);
+        assert_eq!(report.latency_ms.p90, 900.0);
+        assert_eq!(report.latency_ms.p99, 1000.0);
+        // 500 tokens over 5.5 seconds of successful requests
+        assert!((report.tokens_per_sec - 500.0 / 5.5).abs() < 1e-9);
+        assert_eq!(report.quality, Some(7.0));
+        assert_eq!(report.errors, vec!["timeout".to_string()]);
+
+        let table = render_table(&[report]);
+        assert!(table.lines().nth(1).unwrap().starts_with("openai/gpt-4o"));
+        assert!(table.contains("16.7%"));
+    }
+
+    #[test]
+    fn parses_judge_scores_and_suites() {
+        assert_eq!(parse_score("8"), Some(8.0));
+        assert_eq!(parse_score("Score: 7.5/10"), Some(7.5));
+        assert_eq!(parse_score("12."), Some(10.0));
+        assert_eq!(parse_score("no idea"), None);
+
+        let suite: BenchSuite = serde_yaml::from_str("prompts:\n  - name: hi\n    prompt: Say hi\n").unwrap();
+        assert_eq!(suite.prompts[0].max_tokens, None);
+        assert!(!BenchSuite::builtin().prompts.is_empty());
+    }
+}
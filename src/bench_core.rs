@@ -0,0 +1,449 @@
+//! `picode bench core` - performance regression harness
+//!
+//! Measures the operations every session leans on, with criterion doing
+//! the warm-up, sampling and statistics: scanning a workspace, publishing
+//! events, building the request context and saving and loading a session's
+//! conversation. They run against a generated workspace of fixed size in a
+//! temporary directory, so results from different checkouts compare.
+//!
+//! Results are kept under `.picode/bench/`: criterion's data in
+//! `criterion/`, the last run in `latest.json` and named baselines in
+//! `baselines/<name>.json`. Each run is compared with a baseline; an
+//! operation regressed when its mean is more than the threshold slower and
+//! the confidence intervals of the two runs do not overlap, which keeps
+//! noise from failing the run.
+
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_core::{
+    ContextBreakdown, ConversationLog, ConversationMessage, Event, EventBus, PromptLayerKind, SessionId,
+    SessionManager, SystemPrompt, Workspace, WorkspaceConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Directory, under the config directory, holding results and baselines
+pub const BENCH_DIR: &str = "bench";
+
+/// Baseline compared with when none is named
+pub const DEFAULT_BASELINE: &str = "main";
+
+/// Slowdown, in percent, beyond which an operation has regressed
+pub const DEFAULT_THRESHOLD: f64 = 10.0;
+
+/// Size of the generated workspace
+const FIXTURE_DIRS: usize = 20;
+const FIXTURE_FILES_PER_DIR: usize = 25;
+
+/// Events published per measured iteration
+const EVENTS_PER_ITERATION: usize = 1000;
+
+/// Files put into the context, and messages in the saved conversation
+const CONTEXT_FILES: usize = 100;
+const SESSION_MESSAGES: usize = 200;
+
+/// A measured operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoreBench {
+    WorkspaceScan,
+    EventPublish,
+    ContextBuild,
+    SessionPersist,
+}
+
+impl CoreBench {
+    pub const ALL: [CoreBench; 4] =
+        [CoreBench::WorkspaceScan, CoreBench::EventPublish, CoreBench::ContextBuild, CoreBench::SessionPersist];
+
+    /// Id of the benchmark in criterion and in baselines
+    pub fn name(self) -> &'static str {
+        match self {
+            CoreBench::WorkspaceScan => "workspace_scan",
+            CoreBench::EventPublish => "event_publish",
+            CoreBench::ContextBuild => "context_build",
+            CoreBench::SessionPersist => "session_persist",
+        }
+    }
+
+    /// What one iteration does
+    pub fn description(self) -> String {
+        match self {
+            CoreBench::WorkspaceScan => format!("scan {} files", FIXTURE_DIRS * FIXTURE_FILES_PER_DIR),
+            CoreBench::EventPublish => format!("publish {} events", EVENTS_PER_ITERATION),
+            CoreBench::ContextBuild => format!("add {} files to the context and break it down", CONTEXT_FILES),
+            CoreBench::SessionPersist => format!("save and load {} messages", SESSION_MESSAGES),
+        }
+    }
+}
+
+/// Options of `picode bench core`
+#[derive(Debug, Clone)]
+pub struct CoreBenchOptions {
+    /// Operations to measure; empty means all of them
+    pub benches: Vec<CoreBench>,
+    /// Baseline to compare with
+    pub baseline: String,
+    /// Store this run as the baseline afterwards
+    pub save_baseline: bool,
+    /// Slowdown in percent that counts as a regression
+    pub threshold: f64,
+    /// Fewer samples and shorter measurement, for a rough check
+    pub quick: bool,
+}
+
+/// Criterion's estimate of an operation's time per iteration, in
+/// nanoseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    pub mean: f64,
+    /// 95% confidence interval of the mean
+    pub lower: f64,
+    pub upper: f64,
+    pub median: f64,
+}
+
+/// Measurements stored under a name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub name: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub measurements: BTreeMap<String, Measurement>,
+}
+
+/// How an operation compares with the baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Not in the baseline
+    New,
+    Unchanged,
+    Improved,
+    Regressed,
+}
+
+/// One operation of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comparison {
+    pub name: String,
+    pub current: Measurement,
+    pub baseline: Option<Measurement>,
+    /// Change of the mean, in percent; positive is slower
+    pub change: Option<f64>,
+    pub verdict: Verdict,
+}
+
+/// Compare `current` with `baseline`
+pub fn compare(current: &BTreeMap<String, Measurement>, baseline: Option<&Baseline>, threshold: f64) -> Vec<Comparison> {
+    current
+        .iter()
+        .map(|(name, current)| {
+            let before = baseline.and_then(|baseline| baseline.measurements.get(name)).copied();
+            let change = before.filter(|before| before.mean > 0.0).map(|before| (current.mean / before.mean - 1.0) * 100.0);
+            let verdict = match (before, change) {
+                (Some(before), Some(change)) if change > threshold && current.lower > before.upper => Verdict::Regressed,
+                (Some(before), Some(change)) if change < -threshold && current.upper < before.lower => Verdict::Improved,
+                (Some(_), _) => Verdict::Unchanged,
+                (None, _) => Verdict::New,
+            };
+            Comparison { name: name.clone(), current: *current, baseline: before, change, verdict }
+        })
+        .collect()
+}
+
+/// Human-readable duration of `nanos`
+fn format_nanos(nanos: f64) -> String {
+    match nanos {
+        n if n >= 1e9 => format!("{:.2} s", n / 1e9),
+        n if n >= 1e6 => format!("{:.2} ms", n / 1e6),
+        n if n >= 1e3 => format!("{:.2} µs", n / 1e3),
+        n => format!("{:.0} ns", n),
+    }
+}
+
+/// Comparison table of a run
+pub fn render_table(comparisons: &[Comparison]) -> String {
+    let width = comparisons.iter().map(|c| c.name.len()).max().unwrap_or(0).max("operation".len());
+    let mut out = format!("{:<width$}  {:>10}  {:>10}  {:>8}  {}\n", "operation", "mean", "baseline", "change", "verdict");
+    for comparison in comparisons {
+        let verdict = match comparison.verdict {
+            Verdict::New => "new",
+            Verdict::Unchanged => "unchanged",
+            Verdict::Improved => "✅ improved",
+            Verdict::Regressed => "❌ regressed",
+        };
+        out.push_str(&format!(
+            "{:<width$}  {:>10}  {:>10}  {:>8}  {}\n",
+            comparison.name,
+            format_nanos(comparison.current.mean),
+            comparison.baseline.map_or_else(|| "-".to_string(), |baseline| format_nanos(baseline.mean)),
+            comparison.change.map_or_else(|| "-".to_string(), |change| format!("{:+.1}%", change)),
+            verdict,
+        ));
+    }
+    out
+}
+
+/// Directory holding the results of a workspace
+pub fn bench_dir(workspace_root: &Path) -> PathBuf {
+    workspace_root.join(crate::defaults::CONFIG_DIR).join(BENCH_DIR)
+}
+
+fn baseline_path(bench_dir: &Path, name: &str) -> PathBuf {
+    bench_dir.join("baselines").join(format!("{}.json", name))
+}
+
+/// The `estimates.json` criterion writes for a benchmark
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+    median: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+    confidence_interval: ConfidenceInterval,
+}
+
+#[derive(Deserialize)]
+struct ConfidenceInterval {
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+/// The measurement criterion stored for `name` in `criterion_dir`
+fn read_estimates(criterion_dir: &Path, name: &str) -> Result<Measurement> {
+    let path = criterion_dir.join(name).join("new").join("estimates.json");
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| PiCodeError::NotFound(format!("criterion estimates {}: {}", path.display(), e)))?;
+    let estimates: Estimates = serde_json::from_str(&content)?;
+    Ok(Measurement {
+        mean: estimates.mean.point_estimate,
+        lower: estimates.mean.confidence_interval.lower_bound,
+        upper: estimates.mean.confidence_interval.upper_bound,
+        median: estimates.median.point_estimate,
+    })
+}
+
+/// Write the generated workspace under `root`: the same files every run
+fn write_fixture(root: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::with_capacity(FIXTURE_DIRS * FIXTURE_FILES_PER_DIR);
+    for dir in 0..FIXTURE_DIRS {
+        let dir_path = root.join("src").join(format!("module_{:02}", dir));
+        std::fs::create_dir_all(&dir_path)?;
+        for file in 0..FIXTURE_FILES_PER_DIR {
+            let mut content = format!("//! Module {} file {}\n\nuse std::collections::HashMap;\n\n", dir, file);
+            for function in 0..20 {
+                content.push_str(&format!(
+                    "/// Computes value {function}\npub fn value_{function}(input: &HashMap<String, u64>) -> u64 {{\n    input.values().sum::<u64>() * {function}\n}}\n\n"
+                ));
+            }
+            let path = dir_path.join(format!("file_{:02}.rs", file));
+            std::fs::write(&path, &content)?;
+            files.push((path, content));
+        }
+    }
+    Ok(files)
+}
+
+/// Run the benchmarks with criterion, blocking; returns what criterion
+/// measured per benchmark name
+fn measure(benches: &[CoreBench], criterion_dir: &Path, fixture: &Path, quick: bool) -> Result<BTreeMap<String, Measurement>> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let files = write_fixture(fixture)?;
+    let mut criterion = criterion::Criterion::default()
+        .output_directory(criterion_dir)
+        .without_plots()
+        .sample_size(if quick { 10 } else { 50 })
+        .warm_up_time(Duration::from_secs(if quick { 1 } else { 3 }))
+        .measurement_time(Duration::from_secs(if quick { 2 } else { 5 }));
+
+    for bench in benches {
+        match bench {
+            CoreBench::WorkspaceScan => {
+                criterion.bench_function(bench.name(), |b| {
+                    b.iter(|| {
+                        runtime.block_on(async {
+                            let mut workspace = Workspace::new(WorkspaceConfig {
+                                root_path: fixture.to_path_buf(),
+                                git_enabled: false,
+                                ..WorkspaceConfig::default()
+                            });
+                            workspace.scan().await.map(|_| workspace.files.len())
+                        })
+                    })
+                });
+            }
+            CoreBench::EventPublish => {
+                let bus = EventBus::new(EVENTS_PER_ITERATION, EVENTS_PER_ITERATION);
+                let _subscriber = bus.subscribe();
+                let session_id = SessionId::new();
+                criterion.bench_function(bench.name(), |b| {
+                    b.iter(|| {
+                        runtime.block_on(async {
+                            for _ in 0..EVENTS_PER_ITERATION {
+                                let event = Event::SessionActivated { session_id: session_id.clone() };
+                                let _ = bus.publish(event, "bench".to_string()).await;
+                            }
+                        })
+                    })
+                });
+            }
+            CoreBench::ContextBuild => {
+                let system = SystemPrompt::new()
+                    .with_layer(PromptLayerKind::Base, "bench", "You are a helpful assistant.".repeat(20))
+                    .with_layer(PromptLayerKind::Workspace, "bench", "Rust workspace with 500 files.".repeat(20));
+                let session_id = SessionId::new();
+                criterion.bench_function(bench.name(), |b| {
+                    b.iter(|| {
+                        let mut log = ConversationLog::new(session_id.clone());
+                        log.push_context(files.iter().take(CONTEXT_FILES).cloned());
+                        ContextBreakdown::build(&system, &log)
+                    })
+                });
+            }
+            CoreBench::SessionPersist => {
+                let manager = SessionManager::new(fixture.join("sessions"));
+                let session_id = runtime
+                    .block_on(manager.create_session("bench".to_string(), fixture.to_path_buf()))
+                    .map_err(picode_core::CoreError::from)?;
+                let mut log = ConversationLog::new(session_id.clone());
+                for index in 0..SESSION_MESSAGES {
+                    let role = if index % 2 == 0 { "user" } else { "assistant" };
+                    log.push(ConversationMessage::new(role, format!("Message {} ", index).repeat(40)));
+                }
+                criterion.bench_function(bench.name(), |b| {
+                    b.iter(|| {
+                        runtime.block_on(async {
+                            manager.save_conversation(&mut log).await?;
+                            manager.load_conversation(&session_id).await
+                        })
+                    })
+                });
+            }
+        }
+    }
+
+    benches.iter().map(|bench| Ok((bench.name().to_string(), read_estimates(criterion_dir, bench.name())?))).collect()
+}
+
+/// Run `picode bench core`
+pub async fn run(options: CoreBenchOptions, config: Config) -> Result<()> {
+    let root = crate::stats::workspace_root(&config)?;
+    let dir = bench_dir(&root);
+    let criterion_dir = dir.join("criterion");
+    tokio::fs::create_dir_all(&criterion_dir).await?;
+    let benches = if options.benches.is_empty() { CoreBench::ALL.to_vec() } else { options.benches.clone() };
+    for bench in &benches {
+        println!("⏱️  {}: {}", bench.name(), bench.description());
+    }
+
+    let fixture = std::env::temp_dir().join(format!("picode-bench-{}", std::process::id()));
+    let measured = {
+        let (criterion_dir, fixture, quick) = (criterion_dir.clone(), fixture.clone(), options.quick);
+        tokio::task::spawn_blocking(move || measure(&benches, &criterion_dir, &fixture, quick))
+            .await
+            .map_err(|e| PiCodeError::Internal(format!("benchmark thread failed: {}", e)))?
+    };
+    let _ = tokio::fs::remove_dir_all(&fixture).await;
+    let measurements = measured?;
+
+    let path = baseline_path(&dir, &options.baseline);
+    let baseline: Option<Baseline> = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => Some(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let comparisons = compare(&measurements, baseline.as_ref(), options.threshold);
+    println!();
+    match &baseline {
+        Some(baseline) => println!("Compared with baseline '{}' of {}", baseline.name, baseline.created_at.format("%Y-%m-%d %H:%M")),
+        None => println!("No baseline '{}' yet; pass --save-baseline to record one", options.baseline),
+    }
+    print!("{}", render_table(&comparisons));
+
+    let run = serde_json::json!({
+        "baseline": options.baseline,
+        "threshold": options.threshold,
+        "created_at": chrono::Utc::now(),
+        "comparisons": comparisons,
+    });
+    tokio::fs::write(dir.join("latest.json"), serde_json::to_string_pretty(&run)?).await?;
+    if options.save_baseline {
+        let mut stored = baseline.unwrap_or_else(|| Baseline {
+            name: options.baseline.clone(),
+            created_at: chrono::Utc::now(),
+            measurements: BTreeMap::new(),
+        });
+        stored.created_at = chrono::Utc::now();
+        stored.measurements.extend(measurements);
+        tokio::fs::create_dir_all(dir.join("baselines")).await?;
+        tokio::fs::write(&path, serde_json::to_string_pretty(&stored)?).await?;
+        println!("💾 Saved baseline '{}' to {}", options.baseline, path.display());
+        return Ok(());
+    }
+
+    let regressed: Vec<&str> =
+        comparisons.iter().filter(|c| c.verdict == Verdict::Regressed).map(|c| c.name.as_str()).collect();
+    if !regressed.is_empty() {
+        return Err(PiCodeError::Internal(format!(
+            "{} operation(s) regressed by more than {}%: {}",
+            regressed.len(),
+            options.threshold,
+            regressed.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(mean: f64, spread: f64) -> Measurement {
+        Measurement { mean, lower: mean - spread, upper: mean + spread, median: mean }
+    }
+
+    #[test]
+    fn regressions_need_the_threshold_and_separate_intervals() {
+        let baseline = Baseline {
+            name: "main".to_string(),
+            created_at: chrono::Utc::now(),
+            measurements: [
+                ("workspace_scan", measurement(1000.0, 10.0)),
+                ("event_publish", measurement(1000.0, 10.0)),
+                ("context_build", measurement(1000.0, 200.0)),
+                ("session_persist", measurement(1000.0, 10.0)),
+            ]
+            .into_iter()
+            .map(|(name, m)| (name.to_string(), m))
+            .collect(),
+        };
+        let current: BTreeMap<String, Measurement> = [
+            ("workspace_scan", measurement(1200.0, 10.0)),
+            ("event_publish", measurement(1050.0, 10.0)),
+            ("context_build", measurement(1200.0, 200.0)),
+            ("session_persist", measurement(700.0, 10.0)),
+            ("new_operation", measurement(10.0, 1.0)),
+        ]
+        .into_iter()
+        .map(|(name, m)| (name.to_string(), m))
+        .collect();
+
+        let verdicts: BTreeMap<String, Verdict> =
+            compare(&current, Some(&baseline), DEFAULT_THRESHOLD).into_iter().map(|c| (c.name, c.verdict)).collect();
+        assert_eq!(verdicts["workspace_scan"], Verdict::Regressed);
+        assert_eq!(verdicts["event_publish"], Verdict::Unchanged);
+        // 20% slower, but within the noise of the measurements
+        assert_eq!(verdicts["context_build"], Verdict::Unchanged);
+        assert_eq!(verdicts["session_persist"], Verdict::Improved);
+        assert_eq!(verdicts["new_operation"], Verdict::New);
+
+        let table = render_table(&compare(&current, Some(&baseline), DEFAULT_THRESHOLD));
+        assert!(table.contains("workspace_scan      1.20 µs     1.00 µs    +20.0%  ❌ regressed"));
+        assert_eq!(format_nanos(2.5e9), "2.50 s");
+    }
+}
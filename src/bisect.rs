@@ -0,0 +1,327 @@
+//! `picode git bisect-assist`
+//!
+//! Drives `git bisect` between a good and a bad commit. At each step the
+//! check runs in the work tree and its exit status decides, as with
+//! `git bisect run` (0 good, 125 skip, anything else bad); with `--judge`
+//! the model reads the check's output against the bug description instead.
+//! Every step's verdict and reasoning is kept in `.picode/bisect.json`, and
+//! the culprit commit is summarized by the model.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Report of the last bisection, relative to the workspace root
+pub const BISECT_LOG: &str = ".picode/bisect.json";
+
+/// Check output shown to the model, keeping the end where failures are reported
+const OUTPUT_CHARS: usize = 6000;
+
+/// Culprit diff shown to the model
+const DIFF_CHARS: usize = 16000;
+
+const JUDGE_SYSTEM: &str = "You are helping bisect a bug. Given its description and the output of a \
+    check run at one commit, decide whether the bug is present at that commit. Answer with GOOD (the \
+    bug is absent), BAD (the bug is present) or SKIP (the output cannot tell, e.g. the build broke for \
+    an unrelated reason), then a colon and one sentence of reasoning.";
+
+const SUMMARY_SYSTEM: &str = "You are reviewing the commit a git bisection found to introduce a bug. \
+    Explain in a short paragraph which part of the change most likely causes the described bug and \
+    why, then suggest where a fix should start. Be factual and concise.";
+
+/// Verdict on one bisection step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Good,
+    Bad,
+    Skip,
+}
+
+impl Verdict {
+    /// Verdict for a check's exit code, following `git bisect run`
+    pub fn from_exit(code: Option<i32>) -> Self {
+        match code {
+            Some(0) => Self::Good,
+            Some(125) => Self::Skip,
+            _ => Self::Bad,
+        }
+    }
+
+    /// Parse a model judgement starting with GOOD, BAD or SKIP into the
+    /// verdict and its reasoning
+    pub fn parse_judgement(reply: &str) -> Option<(Self, String)> {
+        let reply = reply.trim_start_matches(|c: char| !c.is_ascii_alphabetic());
+        let end = reply.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(reply.len());
+        let verdict = match reply[..end].to_ascii_uppercase().as_str() {
+            "GOOD" => Self::Good,
+            "BAD" => Self::Bad,
+            "SKIP" => Self::Skip,
+            _ => return None,
+        };
+        let reasoning = reply[end..]
+            .trim_start_matches(|c: char| c == '*' || c == ':' || c == '-' || c.is_whitespace())
+            .trim();
+        Some((verdict, reasoning.to_string()))
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Good => "good",
+            Self::Bad => "bad",
+            Self::Skip => "skip",
+        }
+    }
+}
+
+/// One tested commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BisectStep {
+    pub commit: String,
+    pub subject: String,
+    pub verdict: Verdict,
+    pub reasoning: String,
+}
+
+/// Outcome of a bisection, saved to [`BISECT_LOG`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BisectReport {
+    pub description: String,
+    pub good: String,
+    pub bad: String,
+    pub check: String,
+    pub steps: Vec<BisectStep>,
+    pub culprit: Option<String>,
+    pub summary: Option<String>,
+}
+
+/// What `git bisect` said after a step
+#[derive(Debug, PartialEq, Eq)]
+enum Progress {
+    Continue,
+    Found(String),
+    /// Only skipped commits are left
+    Inconclusive,
+}
+
+impl Progress {
+    fn parse(output: &str) -> Self {
+        if let Some(line) = output.lines().find(|line| line.trim_end().ends_with("is the first bad commit")) {
+            let commit = line.split_whitespace().next().unwrap_or_default();
+            return Self::Found(commit.to_string());
+        }
+        if output.contains("only 'skip'ped commits left") {
+            return Self::Inconclusive;
+        }
+        Self::Continue
+    }
+}
+
+/// Run `picode git bisect-assist`; the bisection is always reset afterwards
+pub async fn run(
+    config: &Config,
+    description: &str,
+    good: &str,
+    bad: &str,
+    check: Option<String>,
+    judge: bool,
+) -> Result<()> {
+    let root = crate::stats::workspace_root(config)?;
+    let check = match check {
+        Some(check) => check,
+        None => crate::tasks::WorkspaceTasks::load(config, &root)
+            .await?
+            .get("test")
+            .map(|task| task.command.clone())
+            .ok_or_else(|| {
+                PiCodeError::InvalidCommand("no check given (--check) and no `test` task defined".to_string())
+            })?,
+    };
+    let assistant = match Assistant::from_config(config) {
+        Ok(assistant) => Some(assistant),
+        Err(e) if judge => return Err(e),
+        Err(e) => {
+            warn!("No model for the culprit summary: {}", e);
+            None
+        }
+    };
+
+    let mut report = BisectReport {
+        description: description.to_string(),
+        good: good.to_string(),
+        bad: bad.to_string(),
+        check,
+        ..BisectReport::default()
+    };
+    let outcome = bisect(&root, &mut report, assistant.as_ref(), judge).await;
+    if let Err(e) = git(&root, &["bisect", "reset"]).await {
+        warn!("git bisect reset: {}", e);
+    }
+    outcome?;
+
+    match (&report.culprit, &assistant) {
+        (Some(culprit), Some(assistant)) => {
+            let show = git(&root, &["show", "--stat", "--patch", "--format=fuller", culprit]).await?;
+            let prompt = format!(
+                "Bug: {}\n\nCommit:\n```diff\n{}\n```",
+                description,
+                head(&show, DIFF_CHARS)
+            );
+            match assistant.ask(SUMMARY_SYSTEM, &prompt, Some(800)).await {
+                Ok(summary) => report.summary = Some(summary.trim().to_string()),
+                Err(e) => warn!("Culprit summary failed: {}", e),
+            }
+        }
+        (None, _) => println!("⚠️  Only skipped commits are left; the culprit could not be narrowed down"),
+        _ => {}
+    }
+
+    if let Some(culprit) = &report.culprit {
+        let line = git(&root, &["log", "-1", "--format=%h %s", culprit]).await?;
+        println!("\n🎯 First bad commit: {}", line.trim());
+        if let Some(summary) = &report.summary {
+            println!("\n{}", summary);
+        }
+    }
+    let path = save(&root, &report).await?;
+    println!("\n📝 Steps and reasoning saved to {}", path.display());
+    Ok(())
+}
+
+async fn bisect(root: &Path, report: &mut BisectReport, assistant: Option<&Assistant>, judge: bool) -> Result<()> {
+    let mut progress = Progress::parse(&git(root, &["bisect", "start", &report.bad, &report.good]).await?);
+    while progress == Progress::Continue {
+        let commit = git(root, &["rev-parse", "HEAD"]).await?.trim().to_string();
+        let subject = git(root, &["log", "-1", "--format=%s"]).await?.trim().to_string();
+
+        let output = run_check(root, &report.check).await?;
+        let (verdict, reasoning) = match assistant.filter(|_| judge) {
+            Some(assistant) => {
+                let prompt = format!(
+                    "Bug: {}\n\nCheck `{}` at commit {} exited with {:?}. Output:\n```\n{}\n```",
+                    report.description,
+                    report.check,
+                    &commit[..commit.len().min(12)],
+                    output.0,
+                    tail(&output.1, OUTPUT_CHARS)
+                );
+                let reply = assistant.ask(JUDGE_SYSTEM, &prompt, Some(200)).await?;
+                Verdict::parse_judgement(&reply).unwrap_or((Verdict::Skip, format!("unclear judgement: {}", reply.trim())))
+            }
+            None => {
+                let verdict = Verdict::from_exit(output.0);
+                let reasoning = match output.0 {
+                    Some(code) => format!("check exited with {}", code),
+                    None => "check was killed by a signal".to_string(),
+                };
+                (verdict, reasoning)
+            }
+        };
+        println!("🔎 {} {} — {}: {}", &commit[..commit.len().min(7)], subject, verdict.as_str(), reasoning);
+        report.steps.push(BisectStep { commit, subject, verdict, reasoning });
+
+        progress = Progress::parse(&git(root, &["bisect", verdict.as_str()]).await?);
+    }
+    if let Progress::Found(culprit) = progress {
+        report.culprit = Some(culprit);
+    }
+    Ok(())
+}
+
+/// Run the check through the shell, returning its exit code and combined output
+async fn run_check(root: &Path, check: &str) -> Result<(Option<i32>, String)> {
+    let mut command = if cfg!(windows) {
+        let mut command = tokio::process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    let output = command.arg(check).current_dir(root).output().await?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((output.status.code(), text))
+}
+
+async fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(PiCodeError::Internal(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+async fn save(root: &Path, report: &BisectReport) -> Result<PathBuf> {
+    let path = root.join(BISECT_LOG);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&path, serde_json::to_string_pretty(report)?).await?;
+    Ok(path)
+}
+
+fn head(text: &str, max: usize) -> &str {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn tail(text: &str, max: usize) -> &str {
+    let mut start = text.len().saturating_sub(max);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_judgements_and_exit_codes() {
+        assert_eq!(
+            Verdict::parse_judgement("**BAD**: the login test returns 500"),
+            Some((Verdict::Bad, "the login test returns 500".to_string()))
+        );
+        assert_eq!(Verdict::parse_judgement("good - tests pass").unwrap().0, Verdict::Good);
+        assert_eq!(Verdict::parse_judgement("Skip"), Some((Verdict::Skip, String::new())));
+        assert_eq!(Verdict::parse_judgement("Probably bad"), None);
+
+        assert_eq!(Verdict::from_exit(Some(0)), Verdict::Good);
+        assert_eq!(Verdict::from_exit(Some(125)), Verdict::Skip);
+        assert_eq!(Verdict::from_exit(Some(101)), Verdict::Bad);
+        assert_eq!(Verdict::from_exit(None), Verdict::Bad);
+    }
+
+    #[test]
+    fn recognizes_bisect_progress() {
+        let found = "3f2a9c1d0e5b7a8c9d0e1f2a3b4c5d6e7f8a9b0c is the first bad commit\ncommit 3f2a9c1d\nAuthor: A <a@b>\n";
+        assert_eq!(Progress::parse(found), Progress::Found("3f2a9c1d0e5b7a8c9d0e1f2a3b4c5d6e7f8a9b0c".to_string()));
+        assert_eq!(
+            Progress::parse("Bisecting: 3 revisions left to test after this (roughly 2 steps)\n[abc] Subject\n"),
+            Progress::Continue
+        );
+        assert_eq!(
+            Progress::parse("There are only 'skip'ped commits left to test.\nThe first bad commit could be any of:\n"),
+            Progress::Inconclusive
+        );
+        assert_eq!(tail("héllo", 4), "llo");
+        assert_eq!(head("héllo", 2), "h");
+    }
+}
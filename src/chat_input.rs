@@ -0,0 +1,247 @@
+//! Line editor for the chat prompt
+//!
+//! Reads one line in raw mode and completes the word before the cursor as
+//! it is typed: `@` with workspace files, `#` with symbols and a leading `/`
+//! with slash commands (see [`crate::mentions`]). The matches are listed
+//! below the prompt; Up and Down pick one, Tab inserts it and Esc hides the
+//! list. Ctrl-P and Ctrl-R are handed back as the control characters the
+//! line-mode prompt delivers, so the interactive loop treats both alike.
+
+use crate::error::Result;
+use crate::mentions::{Completion, CompletionKind, MentionIndex, MAX_COMPLETIONS};
+use crate::slash::SlashCommandRegistry;
+use crossterm::cursor::{self, MoveToNextLine, MoveUp, RestorePosition, SavePosition};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use crossterm::{execute, queue};
+use std::io::{self, Write};
+
+/// Result of feeding a key to the editor
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputOutcome {
+    Continue,
+    Submit(String),
+}
+
+/// State of the line being edited
+pub struct ChatInput<'a> {
+    index: &'a MentionIndex,
+    commands: &'a SlashCommandRegistry,
+    line: String,
+    /// Cursor position in characters
+    cursor: usize,
+    completions: Vec<Completion>,
+    selected: usize,
+    /// Esc hides the list until the next edit
+    dismissed: bool,
+}
+
+impl<'a> ChatInput<'a> {
+    pub fn new(index: &'a MentionIndex, commands: &'a SlashCommandRegistry) -> Self {
+        Self {
+            index,
+            commands,
+            line: String::new(),
+            cursor: 0,
+            completions: Vec::new(),
+            selected: 0,
+            dismissed: false,
+        }
+    }
+
+    /// Start from text typed earlier, e.g. a draft kept from a streamed reply
+    pub fn with_line(mut self, line: String) -> Self {
+        self.cursor = line.chars().count();
+        self.line = line;
+        self
+    }
+
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Completions shown for the word before the cursor
+    pub fn completions(&self) -> &[Completion] {
+        if self.dismissed {
+            &[]
+        } else {
+            &self.completions
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> InputOutcome {
+        if key.kind != KeyEventKind::Press {
+            return InputOutcome::Continue;
+        }
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return InputOutcome::Submit(std::mem::take(&mut self.line)),
+            KeyCode::Char('p') if control => return InputOutcome::Submit("\u{10}".to_string()),
+            KeyCode::Char('r') if control => return InputOutcome::Submit("\u{12}".to_string()),
+            KeyCode::Char('d') if control && self.line.is_empty() => return InputOutcome::Submit("/exit".to_string()),
+            KeyCode::Char('c') if control => {
+                self.line.clear();
+                self.cursor = 0;
+            }
+            KeyCode::Tab => {
+                self.accept();
+                return InputOutcome::Continue;
+            }
+            KeyCode::Esc => {
+                self.dismissed = true;
+                return InputOutcome::Continue;
+            }
+            KeyCode::Up if !self.completions().is_empty() => {
+                self.selected = self.selected.saturating_sub(1);
+                return InputOutcome::Continue;
+            }
+            KeyCode::Down if !self.completions().is_empty() => {
+                self.selected = (self.selected + 1).min(self.completions.len() - 1);
+                return InputOutcome::Continue;
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.line.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.line.chars().count(),
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.byte_offset(self.cursor));
+            }
+            KeyCode::Delete if self.cursor < self.line.chars().count() => {
+                self.line.remove(self.byte_offset(self.cursor));
+            }
+            KeyCode::Char(c) if !control => {
+                self.line.insert(self.byte_offset(self.cursor), c);
+                self.cursor += 1;
+            }
+            _ => return InputOutcome::Continue,
+        }
+        self.dismissed = false;
+        self.update_completions();
+        InputOutcome::Continue
+    }
+
+    fn byte_offset(&self, chars: usize) -> usize {
+        self.line.char_indices().nth(chars).map_or(self.line.len(), |(offset, _)| offset)
+    }
+
+    /// Byte range of the word before the cursor
+    fn word(&self) -> (usize, usize) {
+        let end = self.byte_offset(self.cursor);
+        let start = self.line[..end].rfind(char::is_whitespace).map_or(0, |space| space + 1);
+        (start, end)
+    }
+
+    fn update_completions(&mut self) {
+        let (start, end) = self.word();
+        self.completions = self.index.complete(&self.line[start..end], start == 0, self.commands);
+        self.selected = 0;
+    }
+
+    /// Replace the word before the cursor with the selected completion
+    fn accept(&mut self) {
+        let Some(completion) = self.completions().get(self.selected).cloned() else {
+            return;
+        };
+        let (start, end) = self.word();
+        let inserted = format!("{} ", completion.text);
+        self.line.replace_range(start..end, &inserted);
+        self.cursor = self.line[..start].chars().count() + inserted.chars().count();
+        self.completions.clear();
+        self.selected = 0;
+    }
+
+    /// Redraw from the saved position just after the prompt
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let width = terminal::size().map_or(80, |(columns, _)| columns as usize);
+        queue!(out, RestorePosition, Clear(ClearType::FromCursorDown), Print(&self.line))?;
+        for (i, completion) in self.completions().iter().enumerate() {
+            let sigil = match completion.kind {
+                CompletionKind::File => "file",
+                CompletionKind::Symbol => "symbol",
+                CompletionKind::Command => "command",
+            };
+            let mut entry = format!("  {:<7} {}  {}", sigil, completion.text, completion.detail);
+            if let Some((cut, _)) = entry.char_indices().nth(width.saturating_sub(1)) {
+                entry.truncate(cut);
+            }
+            queue!(out, MoveToNextLine(1))?;
+            if i == self.selected {
+                queue!(out, SetAttribute(Attribute::Reverse), Print(entry), SetAttribute(Attribute::Reset))?;
+            } else {
+                queue!(out, Print(entry))?;
+            }
+        }
+        // Reprint up to the cursor to leave it there
+        let before: String = self.line.chars().take(self.cursor).collect();
+        queue!(out, RestorePosition, Print(before))?;
+        out.flush()
+    }
+}
+
+/// Read one line from the terminal after the prompt was printed, starting
+/// from `draft`
+pub fn read_line(index: &MentionIndex, commands: &SlashCommandRegistry, draft: String) -> Result<String> {
+    let mut out = io::stdout();
+    let (column, _) = cursor::position()?;
+    // Make room below the prompt so the list never scrolls it away
+    execute!(
+        out,
+        Print("\n".repeat(MAX_COMPLETIONS)),
+        MoveUp(MAX_COMPLETIONS as u16),
+        cursor::MoveToColumn(column),
+        SavePosition
+    )?;
+    let mut input = ChatInput::new(index, commands).with_line(draft);
+    enable_raw_mode()?;
+    let result = edit_loop(&mut input, &mut out);
+    disable_raw_mode()?;
+    execute!(out, RestorePosition, Clear(ClearType::FromCursorDown))?;
+    let line = result?;
+    println!("{}", line);
+    Ok(line)
+}
+
+fn edit_loop(input: &mut ChatInput, out: &mut impl Write) -> Result<String> {
+    loop {
+        input.draw(out)?;
+        if let Event::Key(key) = event::read()? {
+            if let InputOutcome::Submit(line) = input.handle_key(key) {
+                return Ok(line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn tab_inserts_the_selected_completion() {
+        let index = MentionIndex::default();
+        let registry = SlashCommandRegistry::builtin();
+        let mut input = ChatInput::new(&index, &registry);
+        for c in "/bookm".chars() {
+            input.handle_key(press(KeyCode::Char(c)));
+        }
+        assert_eq!(input.completions()[0].text, "/bookmark");
+        input.handle_key(press(KeyCode::Tab));
+        assert_eq!(input.line(), "/bookmark ");
+        assert!(input.completions().is_empty());
+
+        input.handle_key(press(KeyCode::Char('/')));
+        assert!(input.completions().is_empty(), "commands complete only at the start");
+        input.handle_key(press(KeyCode::Backspace));
+        assert_eq!(input.handle_key(press(KeyCode::Enter)), InputOutcome::Submit("/bookmark ".to_string()));
+        assert_eq!(
+            input.handle_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL)),
+            InputOutcome::Submit("\u{10}".to_string())
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Typing follow-ups while a reply streams
+//!
+//! During a chat turn the terminal stays in raw mode, so keys typed while
+//! the reply is printed go to the conversation's [`Composer`] instead of
+//! the next prompt: Enter queues the draft as a follow-up, sent once the
+//! turn finishes, and whatever is left unsubmitted pre-fills the next
+//! prompt. Without a terminal, chunks are printed as they come.
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use picode_core::Composer;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+/// Keys typed during a streamed reply, fed to a [`Composer`]
+pub struct FollowUpCapture<'a> {
+    composer: &'a mut Composer,
+    /// Raw mode is on and keys are read
+    active: bool,
+}
+
+impl<'a> FollowUpCapture<'a> {
+    /// Start capturing keys when stdin is a terminal
+    pub fn start(composer: &'a mut Composer) -> Self {
+        let active = std::io::stdin().is_terminal() && enable_raw_mode().is_ok();
+        Self { composer, active }
+    }
+
+    /// Print a chunk of the reply, then take the keys typed meanwhile
+    pub fn print(&mut self, chunk: &str) {
+        if self.active {
+            // Raw mode does not return the carriage on a line feed
+            print!("{}", chunk.replace('\n', "\r\n"));
+            self.poll_keys();
+        } else {
+            print!("{}", chunk);
+        }
+        std::io::stdout().flush().ok();
+    }
+
+    fn poll_keys(&mut self) {
+        while matches!(event::poll(Duration::ZERO), Ok(true)) {
+            if let Ok(Event::Key(key)) = event::read() {
+                if let Some(queued) = handle_key(self.composer, key) {
+                    print!("\r\n📝 Queued: {}\r\n", queued);
+                }
+            }
+        }
+    }
+
+    /// Stop capturing and say what is waiting
+    pub fn finish(mut self) {
+        if self.active {
+            self.poll_keys();
+            self.stop();
+        }
+        if !self.composer.queued.is_empty() {
+            println!("\n📝 {} follow-up(s) queued; /queue lists them", self.composer.queued.len());
+        }
+        if !self.composer.draft.is_empty() {
+            println!("\n✎ Your draft is kept for the next prompt");
+        }
+    }
+
+    fn stop(&mut self) {
+        if std::mem::take(&mut self.active) {
+            let _ = disable_raw_mode();
+        }
+    }
+}
+
+impl Drop for FollowUpCapture<'_> {
+    /// Leave raw mode when the turn fails before [`FollowUpCapture::finish`]
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Apply one key to the composer; returns the text Enter queued
+pub fn handle_key(composer: &mut Composer, key: KeyEvent) -> Option<String> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    let control = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Enter => return composer.submit().map(str::to_string),
+        KeyCode::Backspace => {
+            composer.pop_char();
+        }
+        KeyCode::Char('c') | KeyCode::Char('u') if control => composer.draft.clear(),
+        KeyCode::Char(c) if !control => composer.push_char(c),
+        _ => {}
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn keys_build_and_queue_follow_ups() {
+        let mut composer = Composer::default();
+        for c in "add testz".chars() {
+            assert_eq!(handle_key(&mut composer, press(KeyCode::Char(c))), None);
+        }
+        handle_key(&mut composer, press(KeyCode::Backspace));
+        handle_key(&mut composer, press(KeyCode::Char('s')));
+        assert_eq!(handle_key(&mut composer, press(KeyCode::Enter)), Some("add tests".to_string()));
+
+        handle_key(&mut composer, press(KeyCode::Char('x')));
+        handle_key(&mut composer, KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL));
+        assert_eq!(handle_key(&mut composer, press(KeyCode::Enter)), None);
+        assert_eq!(composer.queued.len(), 1);
+        assert!(composer.draft.is_empty());
+    }
+}
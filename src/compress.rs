@@ -0,0 +1,177 @@
+//! Prompt compression of pinned files
+//!
+//! Before a request is sent, files pinned into the context are shrunk with
+//! [`picode_core::compress`]. Lines are scored heuristically, or by the cheap
+//! model named in `[compression] model` when `scorer = "model"`; the
+//! heuristic is used whenever that model cannot answer. `/context` shows
+//! the savings per item.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::Result;
+use picode_core::compress::{heuristic_scores, Compressed, CompressionLevel, PromptCompressor};
+use picode_core::conversation::PinnedItem;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+use tracing::warn;
+
+const SCORER_SYSTEM: &str = "You select the lines of a file that matter for answering a question. \
+    Reply only with the relevant line numbers as comma-separated ranges, e.g. `3-10, 42`. Include \
+    the lines a reader needs to follow the relevant code.";
+
+/// How line relevance is scored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RelevanceScorer {
+    #[default]
+    Heuristic,
+    Model,
+}
+
+/// `[compression]` settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    /// off, light, medium or aggressive
+    #[serde(default)]
+    pub level: CompressionLevel,
+
+    /// Files smaller than this many tokens are sent whole
+    #[serde(default = "default_min_tokens")]
+    pub min_tokens: usize,
+
+    #[serde(default)]
+    pub scorer: RelevanceScorer,
+
+    /// `provider/model` of the model scorer, e.g. `ollama/qwen2.5:0.5b`;
+    /// the default provider's model when unset
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn default_min_tokens() -> usize {
+    800
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            level: CompressionLevel::Off,
+            min_tokens: default_min_tokens(),
+            scorer: RelevanceScorer::Heuristic,
+            model: None,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn compressor(&self) -> PromptCompressor {
+        PromptCompressor::new(self.level, self.min_tokens)
+    }
+}
+
+/// Compress `text` for a request about `query`
+pub async fn compress(config: &Config, text: &str, query: &str) -> Compressed {
+    let compressor = config.compression.compressor();
+    if !compressor.applies_to(text) {
+        return compressor.compress(text, &[]);
+    }
+    let mut scores = heuristic_scores(text, query);
+    if config.compression.scorer == RelevanceScorer::Model {
+        match model_relevant_lines(config, text, query).await {
+            Ok(relevant) => {
+                for (i, score) in scores.iter_mut().enumerate() {
+                    if relevant.contains(&(i + 1)) {
+                        *score += 2.0;
+                    }
+                }
+            }
+            Err(e) => warn!("Compression model unavailable, scoring heuristically: {}", e),
+        }
+    }
+    compressor.compress(text, &scores)
+}
+
+/// Compress every pinned item for a request about `query`
+pub async fn compress_pinned(config: &Config, pinned: &[PinnedItem], query: &str) -> Vec<Compressed> {
+    let mut compressed = Vec::with_capacity(pinned.len());
+    for item in pinned {
+        compressed.push(compress(config, &item.content, query).await);
+    }
+    compressed
+}
+
+/// The pinned files section placed before the user's prompt
+pub fn render_pinned(pinned: &[PinnedItem], compressed: &[Compressed]) -> String {
+    let mut out = String::new();
+    for (item, compressed) in pinned.iter().zip(compressed) {
+        let note = if compressed.omitted_lines > 0 { " (less relevant lines omitted)" } else { "" };
+        let _ = writeln!(out, "Pinned file {}{}:\n```\n{}```\n", item.label, note, compressed.text);
+    }
+    out
+}
+
+async fn model_relevant_lines(config: &Config, text: &str, query: &str) -> Result<HashSet<usize>> {
+    let (provider, model) = match config.compression.model.as_deref().and_then(|m| m.split_once('/')) {
+        Some((provider, model)) => (provider, Some(model)),
+        None => (config.llm.default_provider.as_str(), None),
+    };
+    let mut assistant = Assistant::for_provider(config, provider)?;
+    if let Some(model) = model {
+        assistant = assistant.with_model(model);
+    }
+    let mut numbered = String::with_capacity(text.len() + text.len() / 8);
+    for (i, line) in text.lines().enumerate() {
+        let _ = writeln!(numbered, "{}| {}", i + 1, line);
+    }
+    let prompt = format!("Question: {}\n\n{}", query, numbered);
+    let reply = assistant.ask(SCORER_SYSTEM, &prompt, Some(300)).await?;
+    Ok(parse_line_ranges(&reply))
+}
+
+/// Line numbers in a reply like `3-10, 42`
+fn parse_line_ranges(reply: &str) -> HashSet<usize> {
+    let mut lines = HashSet::new();
+    for part in reply.split(|c: char| c == ',' || c.is_whitespace()) {
+        let part = part.trim_matches(|c: char| !c.is_ascii_digit());
+        let range = match part.split_once('-') {
+            Some((start, end)) => start.parse::<usize>().ok().zip(end.parse::<usize>().ok()),
+            None => part.parse::<usize>().ok().map(|line| (line, line)),
+        };
+        if let Some((start, end)) = range.filter(|(start, end)| start <= end && end - start < 10_000) {
+            lines.extend(start..=end);
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_model_line_ranges() {
+        let lines = parse_line_ranges("`3-5, 9`\n12 - 14");
+        let mut lines: Vec<usize> = lines.into_iter().collect();
+        lines.sort_unstable();
+        assert_eq!(lines, vec![3, 4, 5, 9, 12, 14]);
+        assert!(parse_line_ranges("none of them").is_empty());
+    }
+
+    #[tokio::test]
+    async fn pinned_files_are_compressed_when_enabled() {
+        let mut config = Config::default();
+        let content = "fn parse() {\n".to_string() + &"    let unrelated = 1;\n".repeat(40) + "    retry_backoff();\n}\n";
+        let pinned = vec![PinnedItem::new("src/net.rs", content.clone())];
+
+        let whole = compress_pinned(&config, &pinned, "retry backoff").await;
+        assert_eq!(whole[0].text, content);
+
+        config.compression.level = CompressionLevel::Medium;
+        config.compression.min_tokens = 0;
+        let compressed = compress_pinned(&config, &pinned, "retry backoff").await;
+        assert!(compressed[0].text.contains("retry_backoff();"));
+        assert!(compressed[0].saved_tokens() > 0);
+        assert!(render_pinned(&pinned, &compressed).starts_with("Pinned file src/net.rs (less relevant lines omitted):"));
+    }
+}
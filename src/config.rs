@@ -1,9 +1,13 @@
 //! Configuration management for PiCode
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use picode_core::editor::JournalRecovery;
+use picode_core::io::FileSystem;
+use picode_core::recovery::{self, QuarantinedFile, RecoveryReport};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
+#[cfg(feature = "cli")]
 use crate::cli::CliArgs;
 
 /// Main configuration structure
@@ -23,6 +27,72 @@ pub struct Config {
     
     /// Hooks configuration
     pub hooks: HooksConfig,
+    
+    /// Serve mode settings
+    #[serde(default)]
+    pub serve: ServeConfig,
+    
+    /// Agent tool behaviour
+    #[serde(default)]
+    pub agent: AgentConfig,
+    
+    /// Webhook delivery of workspace events
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
+    
+    /// Encrypted sync of sessions and templates between machines
+    #[serde(default)]
+    pub sync: crate::sync::SyncConfig,
+    
+    /// Release channel and verification for `picode dev update`
+    #[serde(default)]
+    pub update: crate::update::UpdateConfig,
+    
+    /// Dropping low-relevance lines from pinned files before a request
+    #[serde(default)]
+    pub compression: crate::compress::CompressionConfig,
+    
+    /// Issue tracker used by `picode work-on`
+    #[serde(default)]
+    pub forge: crate::forge::ForgeConfig,
+    
+    /// Advisory database and exceptions of `picode audit deps`
+    #[serde(default)]
+    pub audit: crate::audit::AuditConfig,
+    
+    /// OTLP export of tracing spans (needs the `otel` feature)
+    #[serde(default)]
+    pub telemetry: crate::telemetry::TelemetryConfig,
+    
+    /// Named tasks (test, lint, build, ...); a workspace `picode.toml`
+    /// `[tasks]` section overrides these by name
+    #[serde(default)]
+    pub tasks: BTreeMap<String, TaskDefinition>,
+    
+    /// Named generation presets (temperature, top_p, seed, max_tokens);
+    /// these override the built-in `precise`, `creative` and
+    /// `deterministic`, and a workspace `picode.toml` `[presets]` section
+    /// overrides them by name
+    #[serde(default)]
+    pub presets: BTreeMap<String, crate::presets::GenerationPreset>,
+    
+    /// Named context packs attached with `/pack <name>`; a workspace
+    /// `picode.toml` `[packs]` section overrides these by name
+    #[serde(default)]
+    pub packs: BTreeMap<String, crate::packs::ContextPack>,
+    
+    /// Answer language, verbosity and forbidden phrases; a workspace
+    /// `picode.toml` `[policy]` section replaces this one
+    #[serde(default)]
+    pub policy: crate::policy::ResponsePolicy,
+    
+    /// Named configuration profiles (e.g. work, personal, offline)
+    #[serde(default)]
+    pub profiles: HashMap<String, ProfileConfig>,
+    
+    /// Profile applied when none is given on the command line
+    #[serde(default)]
+    pub active_profile: Option<String>,
 }
 
 impl Default for Config {
@@ -33,6 +103,21 @@ impl Default for Config {
             session: SessionConfig::default(),
             workspace: WorkspaceConfig::default(),
             hooks: HooksConfig::default(),
+            serve: ServeConfig::default(),
+            agent: AgentConfig::default(),
+            webhooks: WebhooksConfig::default(),
+            sync: crate::sync::SyncConfig::default(),
+            update: crate::update::UpdateConfig::default(),
+            compression: crate::compress::CompressionConfig::default(),
+            forge: crate::forge::ForgeConfig::default(),
+            audit: crate::audit::AuditConfig::default(),
+            telemetry: crate::telemetry::TelemetryConfig::default(),
+            tasks: BTreeMap::new(),
+            presets: BTreeMap::new(),
+            packs: BTreeMap::new(),
+            policy: crate::policy::ResponsePolicy::default(),
+            profiles: HashMap::new(),
+            active_profile: None,
         }
     }
 }
@@ -48,6 +133,10 @@ pub struct LlmConfig {
     
     /// Provider-specific configurations
     pub providers: HashMap<String, ProviderConfig>,
+    
+    /// Generation preset of requests (`precise` when unset)
+    #[serde(default)]
+    pub preset: Option<String>,
 }
 
 impl Default for LlmConfig {
@@ -56,6 +145,7 @@ impl Default for LlmConfig {
             default_provider: "anthropic".to_string(),
             default_model: "claude-3-sonnet-20240229".to_string(),
             providers: HashMap::new(),
+            preset: None,
         }
     }
 }
@@ -64,6 +154,7 @@ impl Default for LlmConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
     /// API endpoint URL
+    #[serde(default)]
     pub endpoint: String,
     
     /// API key (stored securely)
@@ -71,6 +162,135 @@ pub struct ProviderConfig {
     
     /// Default model for this provider
     pub default_model: Option<String>,
+    
+    /// Local GGUF model loaded through llama.cpp (requires the `llama-cpp` feature)
+    #[serde(default)]
+    pub model_path: Option<PathBuf>,
+    
+    /// Context window for local models, in tokens
+    #[serde(default)]
+    pub context_size: Option<u32>,
+    
+    /// Layers offloaded to the GPU for local models
+    #[serde(default)]
+    pub gpu_layers: Option<u32>,
+    
+    /// USD per million prompt tokens, shown by the model picker
+    #[serde(default)]
+    pub prompt_price_per_million: Option<f64>,
+    
+    /// USD per million completion tokens, shown by the model picker
+    #[serde(default)]
+    pub completion_price_per_million: Option<f64>,
+    
+    /// Request signing (SigV4 or HMAC) for gateways that reject bearer tokens
+    #[serde(default)]
+    pub signing: Option<picode_llm::SigningConfig>,
+    
+    /// Endpoint paths for servers that do not use `/v1/chat/completions` etc.
+    #[serde(default)]
+    pub endpoints: Option<picode_llm::EndpointPaths>,
+    
+    /// Request fields to rename, add or remove before sending
+    #[serde(default)]
+    pub payload_mapping: Option<picode_llm::PayloadMapping>,
+    
+    /// Connection pool and keep-alive tuning
+    #[serde(default)]
+    pub pool: Option<picode_llm::PoolSettings>,
+    
+    /// Organization/project headers and request tags for usage attribution
+    #[serde(default)]
+    pub attribution: Option<picode_llm::Attribution>,
+}
+
+impl ProviderConfig {
+    /// Whether this provider runs a local model in-process
+    pub fn is_local_model(&self) -> bool {
+        self.model_path.is_some()
+    }
+
+    /// Whether requests leave this machine: neither a local model nor an
+    /// endpoint on the loopback interface
+    pub fn is_remote(&self) -> bool {
+        if self.is_local_model() {
+            return false;
+        }
+        let rest = self.endpoint.split_once("://").map_or(self.endpoint.as_str(), |(_, rest)| rest);
+        let authority = rest.split('/').next().unwrap_or_default();
+        let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+        let host = match host.strip_prefix('[') {
+            Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+            None => host.split(':').next().unwrap_or_default(),
+        };
+        !matches!(host, "localhost" | "127.0.0.1" | "::1")
+    }
+}
+
+/// Named configuration profile with its own providers, limits and policies
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// Human readable description
+    #[serde(default)]
+    pub description: Option<String>,
+    
+    /// Provider used by default while the profile is active
+    #[serde(default)]
+    pub default_provider: Option<String>,
+    
+    /// Model used by default while the profile is active
+    #[serde(default)]
+    pub default_model: Option<String>,
+    
+    /// Provider configurations specific to this profile
+    #[serde(default)]
+    pub providers: HashMap<String, ProviderConfig>,
+    
+    /// Spend limits
+    #[serde(default)]
+    pub spend_limits: SpendLimits,
+    
+    /// Usage policies
+    #[serde(default)]
+    pub policies: ProfilePolicies,
+    
+    /// Extra system prompt instructions while the profile is active
+    #[serde(default)]
+    pub instructions: Option<String>,
+}
+
+/// Spend limits in USD, unlimited when unset
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpendLimits {
+    pub per_request: Option<f64>,
+    pub daily: Option<f64>,
+    pub monthly: Option<f64>,
+}
+
+/// Policies enforced while a profile is active
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfilePolicies {
+    /// Allow requests to remote (non-local) providers
+    pub allow_remote_providers: bool,
+    
+    /// Restrict usage to these providers (empty means any)
+    pub allowed_providers: Vec<String>,
+}
+
+impl Default for ProfilePolicies {
+    fn default() -> Self {
+        Self {
+            allow_remote_providers: true,
+            allowed_providers: Vec::new(),
+        }
+    }
+}
+
+impl ProfilePolicies {
+    /// Check whether a provider may be used under this policy
+    pub fn allows_provider(&self, provider: &str) -> bool {
+        self.allowed_providers.is_empty() || self.allowed_providers.iter().any(|p| p == provider)
+    }
 }
 
 /// UI configuration
@@ -84,6 +304,19 @@ pub struct UiConfig {
     
     /// Editor settings
     pub editor: EditorConfig,
+    
+    /// How escape sequences in LLM/command output are rendered
+    #[serde(default)]
+    pub ansi_policy: picode_core::ansi::AnsiPolicy,
+    
+    /// Language of messages and help (e.g. `de`, `pt-BR`); detected from
+    /// the environment when unset
+    #[serde(default)]
+    pub locale: Option<String>,
+    
+    /// Inline images and diagram rendering
+    #[serde(default)]
+    pub images: crate::images::ImageConfig,
 }
 
 impl Default for UiConfig {
@@ -92,6 +325,9 @@ impl Default for UiConfig {
             theme: "dark".to_string(),
             syntax_highlighting: true,
             editor: EditorConfig::default(),
+            ansi_policy: picode_core::ansi::AnsiPolicy::default(),
+            locale: None,
+            images: crate::images::ImageConfig::default(),
         }
     }
 }
@@ -107,6 +343,20 @@ pub struct EditorConfig {
     
     /// Enable line numbers
     pub line_numbers: bool,
+    
+    /// External checkers whose diagnostics are shown in editor panes
+    #[serde(default)]
+    pub diagnostics: Vec<DiagnosticsCommand>,
+    
+    /// External editor command for `picode open` and `/open`; defaults to
+    /// `$VISUAL`, then `$EDITOR`
+    #[serde(default)]
+    pub external: Option<String>,
+    
+    /// What to do at startup with an edit a crash interrupted: `rollback`
+    /// (default) or `replay`
+    #[serde(default)]
+    pub crash_recovery: JournalRecovery,
 }
 
 impl Default for EditorConfig {
@@ -115,10 +365,42 @@ impl Default for EditorConfig {
             tab_size: 4,
             use_spaces: true,
             line_numbers: true,
+            diagnostics: Vec::new(),
+            external: None,
+            crash_recovery: JournalRecovery::default(),
+        }
+    }
+}
+
+impl EditorConfig {
+    /// Text inserted for the Tab key
+    pub fn indent(&self) -> String {
+        if self.use_spaces {
+            " ".repeat(self.tab_size)
+        } else {
+            "\t".to_string()
         }
     }
 }
 
+/// A plugin or external process providing editor diagnostics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsCommand {
+    /// Name shown next to its diagnostics
+    pub name: String,
+    
+    /// Program to run; it receives the buffer on stdin
+    pub command: String,
+    
+    /// Arguments; `{file}` is replaced with the edited file's path
+    #[serde(default)]
+    pub args: Vec<String>,
+    
+    /// File extensions the checker applies to (all files when empty)
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
 /// Session configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
@@ -130,6 +412,23 @@ pub struct SessionConfig {
     
     /// Maximum session history
     pub max_history: usize,
+    
+    /// Save the conversation after every message
+    #[serde(default)]
+    pub auto_save: bool,
+    
+    /// Milliseconds saves are held back so repeated ones cost a single
+    /// write; 0 writes every save immediately
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    
+    /// Rotation of long conversations into compressed segments
+    #[serde(default)]
+    pub archive: picode_core::ArchiveSettings,
+}
+
+fn default_flush_interval_ms() -> u64 {
+    2000
 }
 
 impl Default for SessionConfig {
@@ -138,6 +437,9 @@ impl Default for SessionConfig {
             default_session: "main".to_string(),
             auto_save_interval: 300, // 5 minutes
             max_history: 100,
+            auto_save: false,
+            flush_interval_ms: default_flush_interval_ms(),
+            archive: picode_core::ArchiveSettings::default(),
         }
     }
 }
@@ -153,6 +455,21 @@ pub struct WorkspaceConfig {
     
     /// Maximum file size to process (in bytes)
     pub max_file_size: u64,
+    
+    /// Working directory and environment of new terminal panes and of the
+    /// commands the agent runs (`[workspace.panes]`)
+    #[serde(default)]
+    pub panes: picode_core::InheritRules,
+    
+    /// How pinned files are watched for changes on disk
+    /// (`[workspace.watch]`)
+    #[serde(default)]
+    pub watch: picode_core::WatchSettings,
+    
+    /// Size from which files are read in windows of lines by the editor,
+    /// `/context pin` and the agent (`[workspace.large_files]`)
+    #[serde(default)]
+    pub large_files: picode_core::LargeFileSettings,
 }
 
 impl Default for WorkspaceConfig {
@@ -168,6 +485,9 @@ impl Default for WorkspaceConfig {
                 ".DS_Store".to_string(),
             ],
             max_file_size: 10 * 1024 * 1024, // 10MB
+            panes: picode_core::InheritRules::default(),
+            watch: picode_core::WatchSettings::default(),
+            large_files: picode_core::LargeFileSettings::default(),
         }
     }
 }
@@ -183,6 +503,19 @@ pub struct HooksConfig {
     
     /// Hook timeout in seconds
     pub timeout: u64,
+    
+    /// Community hook index URL (static JSON)
+    #[serde(default)]
+    pub index_url: Option<String>,
+}
+
+impl HooksConfig {
+    /// Directory hooks are loaded from (`.picode/hooks` unless configured)
+    pub fn dir(&self) -> PathBuf {
+        self.hooks_dir.clone().unwrap_or_else(|| {
+            PathBuf::from(crate::defaults::CONFIG_DIR).join(crate::defaults::HOOKS_DIR)
+        })
+    }
 }
 
 impl Default for HooksConfig {
@@ -191,16 +524,200 @@ impl Default for HooksConfig {
             hooks_dir: None,
             enabled: true,
             timeout: 30,
+            index_url: None,
+        }
+    }
+}
+
+/// Serve mode configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServeConfig {
+    /// USD per million prompt tokens, used for per-user spend accounting
+    pub prompt_price_per_million: f64,
+    
+    /// USD per million completion tokens
+    pub completion_price_per_million: f64,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self {
+            prompt_price_per_million: 3.0,
+            completion_price_per_million: 15.0,
+        }
+    }
+}
+
+impl ServeConfig {
+    /// Cost in USD of a request with the given token counts
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> f64 {
+        (prompt_tokens as f64 * self.prompt_price_per_million
+            + completion_tokens as f64 * self.completion_price_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Agent tool configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Where files deleted by agent tools go: `staging` (`.picode/trash`),
+    /// `system` (OS trash) or `off` (permanent, for strict environments)
+    #[serde(default)]
+    pub trash: picode_core::agent::TrashMode,
+    
+    /// Per-run limits on files modified, diff bytes and commands executed;
+    /// a run reaching one pauses until the user confirms
+    #[serde(default)]
+    pub limits: picode_core::agent::RunLimits,
+    
+    /// Permission profile for agent runs without `--permissions`
+    /// (`editor` when unset)
+    #[serde(default)]
+    pub permissions: Option<String>,
+    
+    /// Named permission profiles; these override the built-in `reader`,
+    /// `editor`, `operator` and `network` profiles of the same name
+    #[serde(default)]
+    pub permission_profiles: HashMap<String, picode_core::agent::PermissionProfile>,
+    
+    /// Run package installs only as pinned by the lockfiles (`npm ci`,
+    /// `cargo build --locked`, hashed requirements), refusing installs that
+    /// would change them
+    #[serde(default)]
+    pub lockfile_only: bool,
+    
+    /// Advisory file locks taken before agent tools write, shared with
+    /// other sessions on the workspace: `policy` is `wait` (up to
+    /// `wait_secs`), `fail` or `steal`; locks expire after `lease_secs`
+    #[serde(default)]
+    pub locks: picode_core::LockSettings,
+    
+    /// Run agent writes through the project's detected formatter and lint
+    /// the files each turn wrote (`format`, `lint`; both on by default)
+    #[serde(default)]
+    pub project_tools: picode_core::agent::ProjectToolSettings,
+    
+    /// Checks and a critic-model review run when the agent says it is
+    /// done; failures go back to it for up to `max_repairs` more iterations
+    #[serde(default)]
+    pub verify: picode_core::agent::VerifySettings,
+}
+
+/// Webhook configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhooksConfig {
+    /// URLs receiving selected events
+    #[serde(default)]
+    pub endpoints: Vec<WebhookEndpoint>,
+    
+    /// Retries after a failed delivery before it goes to the dead-letter log
+    #[serde(default = "default_webhook_retries")]
+    pub max_retries: u32,
+    
+    /// Dead-letter log (JSON lines); `.picode/webhooks-dead-letter.jsonl` unless set
+    #[serde(default)]
+    pub dead_letter_file: Option<PathBuf>,
+}
+
+fn default_webhook_retries() -> u32 {
+    3
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            max_retries: default_webhook_retries(),
+            dead_letter_file: None,
         }
     }
 }
 
+impl WebhooksConfig {
+    pub fn dead_letter_path(&self) -> PathBuf {
+        self.dead_letter_file.clone().unwrap_or_else(|| {
+            PathBuf::from(crate::defaults::CONFIG_DIR).join(crate::defaults::WEBHOOK_DEAD_LETTER_FILE)
+        })
+    }
+}
+
+/// A webhook receiver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    /// URL events are POSTed to
+    pub url: String,
+    
+    /// Event types to send (e.g. `command_completed`, `llm_error`, `hook_completed`)
+    pub events: Vec<String>,
+    
+    /// Environment variable holding the HMAC secret used to sign payloads
+    #[serde(default)]
+    pub secret_env: Option<String>,
+}
+
+/// A named command the workspace defines, e.g. how to run its tests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskDefinition {
+    /// Shell command line
+    pub command: String,
+    
+    #[serde(default)]
+    pub description: Option<String>,
+    
+    /// Working directory relative to the workspace root
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+}
+
 impl Config {
     /// Load configuration from default location
     pub async fn load_default() -> Result<Config, ConfigError> {
-        // For now, return default configuration
-        // TODO: Implement actual file loading
-        Ok(Config::default())
+        let corrupt_dir = PathBuf::from(picode_core::recovery::CORRUPT_DIR);
+        let (config, report) =
+            Config::load_from(&picode_core::NativeFileSystem, &Self::default_config_path(), &corrupt_dir).await?;
+        if !report.is_empty() {
+            eprint!("{}", report);
+        }
+        Ok(config)
+    }
+    
+    /// Load configuration from `path`, falling back to defaults when it does
+    /// not exist. A file that cannot be parsed is quarantined to `corrupt_dir`
+    /// and defaults are used so a damaged config never blocks startup.
+    pub async fn load_from(
+        fs: &dyn FileSystem,
+        path: &Path,
+        corrupt_dir: &Path,
+    ) -> Result<(Config, RecoveryReport), ConfigError> {
+        let mut report = RecoveryReport::default();
+        if !fs.exists(path).await {
+            return Ok((Config::default(), report));
+        }
+        
+        let content = fs.read(path).await?;
+        let parsed = String::from_utf8(content)
+            .map_err(|e| e.to_string())
+            .and_then(|content| Self::parse(&content));
+        match parsed {
+            Ok(config) => Ok((config, report)),
+            Err(reason) => {
+                let moved_to = recovery::quarantine(fs, path, corrupt_dir).await?;
+                report.quarantined.push(QuarantinedFile {
+                    original: path.to_path_buf(),
+                    moved_to,
+                    reason,
+                });
+                Ok((Config::default(), report))
+            }
+        }
+    }
+    
+    pub(crate) fn parse(content: &str) -> Result<Config, String> {
+        ::config::Config::builder()
+            .add_source(::config::File::from_str(content, ::config::FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| e.to_string())
     }
     
     /// Save configuration to file
@@ -211,9 +728,15 @@ impl Config {
     }
     
     /// Create configuration from CLI arguments
+    #[cfg(feature = "cli")]
     pub async fn try_from(args: &CliArgs) -> crate::Result<Config> {
         let mut config = Config::load_default().await.map_err(crate::error::PiCodeError::ConfigLocal)?;
         
+        // Apply the requested profile (--profile / PICODE_PROFILE), falling back to the saved one
+        if let Some(profile) = args.profile.clone().or_else(|| config.active_profile.clone()) {
+            config.apply_profile(&profile)?;
+        }
+        
         // Override with CLI arguments
         if args.verbose > 0 {
             println!("Verbose mode enabled (level: {})", args.verbose);
@@ -238,6 +761,90 @@ impl Config {
     pub fn exists() -> bool {
         Self::default_config_path().exists()
     }
+    
+    /// Add a new named profile
+    pub fn create_profile(&mut self, name: String, profile: ProfileConfig) -> Result<(), ConfigError> {
+        if self.profiles.contains_key(&name) {
+            return Err(ConfigError::InvalidConfig(format!("Profile already exists: {}", name)));
+        }
+        self.profiles.insert(name, profile);
+        Ok(())
+    }
+    
+    /// Profile names in alphabetical order
+    pub fn profile_names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.profiles.keys().collect();
+        names.sort();
+        names
+    }
+    
+    /// Make a profile the default for future invocations
+    pub fn switch_profile(&mut self, name: &str) -> Result<(), ConfigError> {
+        if !self.profiles.contains_key(name) {
+            return Err(ConfigError::InvalidConfig(format!("Unknown profile: {}", name)));
+        }
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
+    
+    /// Overlay a profile's provider settings onto the LLM configuration
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), ConfigError> {
+        let profile = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ConfigError::InvalidConfig(format!("Unknown profile: {}", name)))?;
+        
+        if let Some(provider) = profile.default_provider {
+            self.llm.default_provider = provider;
+        }
+        if let Some(model) = profile.default_model {
+            self.llm.default_model = model;
+        }
+        self.llm.providers.extend(profile.providers);
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
+    
+    /// Currently active profile, if any
+    pub fn current_profile(&self) -> Option<&ProfileConfig> {
+        self.active_profile.as_ref().and_then(|name| self.profiles.get(name))
+    }
+    
+    /// Assemble the layered system prompt (base, workspace PICODE.md, project
+    /// fingerprint, tasks, profile, policy)
+    pub async fn system_prompt(&self, workspace_root: &std::path::Path) -> crate::Result<picode_core::SystemPrompt> {
+        // PICODE.md is only followed in trusted workspaces
+        let mut prompt = picode_core::SystemPrompt::new();
+        if crate::trust::is_trusted(workspace_root) {
+            prompt = prompt
+                .with_workspace_instructions(&picode_core::NativeFileSystem, workspace_root)
+                .await?;
+        }
+        
+        let stats = crate::stats::workspace_stats(workspace_root).await?;
+        if let Some(summary) = stats.prompt_layer() {
+            prompt.set_layer(picode_core::PromptLayerKind::Project, "workspace scan", summary);
+        }
+        
+        let tasks = crate::tasks::WorkspaceTasks::load(self, workspace_root).await?;
+        if let Some(instructions) = tasks.prompt_layer() {
+            prompt.set_layer(picode_core::PromptLayerKind::Tasks, crate::tasks::WORKSPACE_CONFIG_FILE, instructions);
+        }
+        
+        if let (Some(name), Some(profile)) = (&self.active_profile, self.current_profile()) {
+            if let Some(instructions) = &profile.instructions {
+                prompt.set_layer(picode_core::PromptLayerKind::Profile, format!("profile {}", name), instructions.clone());
+            }
+        }
+        
+        let policy = crate::policy::ResponsePolicy::load(self, workspace_root).await?;
+        if let Some(instructions) = policy.prompt_layer() {
+            prompt.set_layer(picode_core::PromptLayerKind::Policy, "response policy", instructions);
+        }
+        
+        Ok(prompt)
+    }
 }
 
 /// Configuration errors
@@ -269,6 +876,32 @@ mod tests {
         assert!(config.ui.syntax_highlighting);
     }
     
+    #[test]
+    fn test_profile_switching() {
+        let mut config = Config::default();
+        let profile = ProfileConfig {
+            default_provider: Some("ollama".to_string()),
+            default_model: Some("llama3".to_string()),
+            policies: ProfilePolicies {
+                allow_remote_providers: false,
+                allowed_providers: vec!["ollama".to_string()],
+            },
+            ..Default::default()
+        };
+        
+        config.create_profile("offline".to_string(), profile).unwrap();
+        assert!(config.create_profile("offline".to_string(), ProfileConfig::default()).is_err());
+        assert!(config.switch_profile("missing").is_err());
+        
+        config.apply_profile("offline").unwrap();
+        assert_eq!(config.llm.default_provider, "ollama");
+        assert_eq!(config.llm.default_model, "llama3");
+        
+        let active = config.current_profile().unwrap();
+        assert!(active.policies.allows_provider("ollama"));
+        assert!(!active.policies.allows_provider("openai"));
+    }
+    
     #[test]
     fn test_config_serialization() {
         let config = Config::default();
@@ -277,9 +910,68 @@ mod tests {
         
         assert_eq!(config.llm.default_provider, deserialized.llm.default_provider);
     }
+    
+    #[tokio::test]
+    async fn test_corrupt_config_is_quarantined() {
+        let fs = picode_core::MemoryFileSystem::new();
+        let path = PathBuf::from("/home/.config/picode/config.toml");
+        let corrupt_dir = PathBuf::from("/ws/.picode/corrupt");
+        
+        let (config, report) = Config::load_from(&fs, &path, &corrupt_dir).await.unwrap();
+        assert!(report.is_empty());
+        assert_eq!(config.llm.default_provider, "anthropic");
+        
+        fs.insert(&path, b"[llm\ndefault_provider = ".to_vec());
+        let (config, report) = Config::load_from(&fs, &path, &corrupt_dir).await.unwrap();
+        assert_eq!(config.llm.default_provider, "anthropic");
+        assert_eq!(report.quarantined.len(), 1);
+        assert!(report.quarantined[0].moved_to.starts_with(&corrupt_dir));
+        assert!(!fs.exists(&path).await);
+    }
+}
+
+/// Handle `picode config profile` subcommands
+#[cfg(feature = "cli")]
+pub async fn handle_profile_action(config: &mut Config, action: picode_cli::ProfileAction) -> crate::Result<()> {
+    match action {
+        picode_cli::ProfileAction::Create { name, from, description } => {
+            let mut profile = match from {
+                Some(source) => config
+                    .profiles
+                    .get(&source)
+                    .cloned()
+                    .ok_or_else(|| ConfigError::InvalidConfig(format!("Unknown profile: {}", source)))?,
+                None => ProfileConfig::default(),
+            };
+            if description.is_some() {
+                profile.description = description;
+            }
+            config.create_profile(name.clone(), profile)?;
+            config.save().await?;
+            println!("✅ Profile '{}' created", name);
+        }
+        picode_cli::ProfileAction::List => {
+            let names = config.profile_names();
+            if names.is_empty() {
+                println!("No profiles configured");
+            }
+            for name in names {
+                let marker = if config.active_profile.as_ref() == Some(name) { "*" } else { " " };
+                let description = config.profiles[name].description.as_deref().unwrap_or("");
+                println!("{} {:<16} {}", marker, name, description);
+            }
+        }
+        picode_cli::ProfileAction::Switch { name } => {
+            config.switch_profile(&name)?;
+            config.save().await?;
+            println!("✅ Switched to profile '{}'", name);
+        }
+    }
+    Ok(())
 }
 
 /// Handle configuration commands
+#[cfg(feature = "cli")]
 pub async fn handle_command(cmd: crate::cli::ConfigCommand) -> crate::Result<()> {
     // Basic config command handling - simplified for now
     println!("Config command handling not yet implemented: {:?}", cmd);
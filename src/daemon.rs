@@ -0,0 +1,608 @@
+//! Background indexing daemon
+//!
+//! `picode daemon start` keeps the symbol index, a polling file watcher and
+//! the git status cache and a shared file content cache warm for registered workspaces. The CLI and the
+//! interactive mode talk to it over a local Unix socket using newline
+//! delimited JSON, so large projects do not have to be re-scanned on startup.
+
+use crate::error::{PiCodeError, Result};
+use chrono::{DateTime, Utc};
+use picode_core::workspace::{Workspace, WorkspaceConfig};
+use picode_core::{CacheStats, ContentCache, NativeFileSystem, SymbolIndex, SymbolLocation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Socket file name inside `~/.picode`
+pub const SOCKET_FILE: &str = "daemon.sock";
+
+/// File listing registered workspaces inside `~/.picode`
+pub const REGISTRY_FILE: &str = "daemon-workspaces.json";
+
+/// Files larger than this are not symbol-indexed
+const MAX_INDEXED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Directory holding the daemon socket and registry
+pub fn daemon_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(crate::defaults::CONFIG_DIR)
+}
+
+pub fn socket_path() -> PathBuf {
+    daemon_dir().join(SOCKET_FILE)
+}
+
+/// Request sent to the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    Ping,
+    Register { root: PathBuf },
+    Unregister { root: PathBuf },
+    Status,
+    Lookup { root: PathBuf, symbol: String },
+    Shutdown,
+}
+
+/// Response returned by the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonResponse {
+    Pong { version: String },
+    Ok,
+    Status {
+        workspaces: Vec<WorkspaceSummary>,
+        #[serde(default)]
+        cache: CacheStats,
+    },
+    Symbols { locations: Vec<SymbolLocation> },
+    Error { message: String },
+}
+
+/// Snapshot of an indexed workspace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceSummary {
+    pub root: PathBuf,
+    pub files: usize,
+    pub symbols: usize,
+    pub branch: Option<String>,
+    pub dirty: bool,
+    pub last_refresh: DateTime<Utc>,
+}
+
+/// Options for the daemon process
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    pub socket: PathBuf,
+    /// How often registered workspaces are polled for changes
+    pub refresh_interval: Duration,
+}
+
+impl Default for DaemonOptions {
+    fn default() -> Self {
+        Self {
+            socket: socket_path(),
+            refresh_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// A workspace with its symbol index and last seen file modification times
+struct IndexedWorkspace {
+    workspace: Workspace,
+    index: SymbolIndex,
+    mtimes: HashMap<PathBuf, DateTime<Utc>>,
+    last_refresh: DateTime<Utc>,
+}
+
+impl IndexedWorkspace {
+    async fn open(root: &Path, cache: Arc<ContentCache>) -> Result<Self> {
+        let config = WorkspaceConfig {
+            name: root
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "workspace".to_string()),
+            root_path: root.to_path_buf(),
+            ..WorkspaceConfig::default()
+        };
+
+        let mut indexed = Self {
+            workspace: Workspace::new(config).with_content_cache(cache),
+            index: SymbolIndex::new(),
+            mtimes: HashMap::new(),
+            last_refresh: Utc::now(),
+        };
+        indexed.refresh().await?;
+        Ok(indexed)
+    }
+
+    /// Re-scan the workspace and re-index changed files; returns how many
+    /// files were (re-)indexed or dropped
+    async fn refresh(&mut self) -> Result<usize> {
+        self.workspace
+            .scan()
+            .await
+            .map_err(picode_core::CoreError::from)?;
+
+        let mut changed = 0;
+        let mut seen = HashMap::with_capacity(self.workspace.files.len());
+        for file in &self.workspace.files {
+            seen.insert(file.relative_path.clone(), file.modified);
+            if self.mtimes.get(&file.relative_path) == Some(&file.modified) {
+                continue;
+            }
+
+            if file.is_binary || file.language.is_none() || file.size > MAX_INDEXED_FILE_SIZE {
+                self.index.remove_file(&file.relative_path);
+            } else {
+                let cache = self.workspace.content_cache().cloned().unwrap_or_else(ContentCache::global);
+                match cache.read(&NativeFileSystem, &file.path).await {
+                    Ok(cached) => self.index.index_file(&file.relative_path, &cached.content),
+                    Err(e) => debug!("Skipping {}: {}", file.path.display(), e),
+                }
+            }
+            changed += 1;
+        }
+
+        let root = self.workspace.config.root_path.clone();
+        let removed: Vec<PathBuf> = self.mtimes.keys().filter(|path| !seen.contains_key(*path)).cloned().collect();
+        for path in removed {
+            self.index.remove_file(&path);
+            self.workspace.handle_fs_event(&root.join(&path));
+            changed += 1;
+        }
+
+        self.mtimes = seen;
+        self.last_refresh = Utc::now();
+        Ok(changed)
+    }
+
+    fn summary(&self) -> WorkspaceSummary {
+        let git = self.workspace.git_status.as_ref();
+        WorkspaceSummary {
+            root: self.workspace.config.root_path.clone(),
+            files: self.workspace.total_files(),
+            symbols: self.index.symbol_count(),
+            branch: git.map(|g| g.branch.clone()),
+            dirty: git.is_some_and(|g| g.is_dirty),
+            last_refresh: self.last_refresh,
+        }
+    }
+}
+
+/// In-memory daemon state
+#[derive(Default)]
+pub struct DaemonState {
+    workspaces: HashMap<PathBuf, IndexedWorkspace>,
+    /// File contents shared by every registered workspace
+    cache: Arc<ContentCache>,
+}
+
+impl DaemonState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn roots(&self) -> Vec<PathBuf> {
+        let mut roots: Vec<PathBuf> = self.workspaces.keys().cloned().collect();
+        roots.sort();
+        roots
+    }
+
+    /// Handle a request; the flag is true when the daemon should stop
+    pub async fn handle(&mut self, request: DaemonRequest) -> (DaemonResponse, bool) {
+        let response = match request {
+            DaemonRequest::Ping => DaemonResponse::Pong {
+                version: crate::VERSION.to_string(),
+            },
+            DaemonRequest::Register { root } => {
+                let root = canonical(&root);
+                if self.workspaces.contains_key(&root) {
+                    DaemonResponse::Ok
+                } else {
+                    match IndexedWorkspace::open(&root, self.cache.clone()).await {
+                        Ok(indexed) => {
+                            info!("Registered workspace {}", root.display());
+                            self.workspaces.insert(root, indexed);
+                            DaemonResponse::Ok
+                        }
+                        Err(e) => error_response(e),
+                    }
+                }
+            }
+            DaemonRequest::Unregister { root } => match self.workspaces.remove(&canonical(&root)) {
+                Some(indexed) => {
+                    self.cache.invalidate_dir(&indexed.workspace.config.root_path);
+                    DaemonResponse::Ok
+                }
+                None => DaemonResponse::Error {
+                    message: format!("workspace not registered: {}", root.display()),
+                },
+            },
+            DaemonRequest::Status => DaemonResponse::Status {
+                workspaces: self.roots().iter().map(|root| self.workspaces[root].summary()).collect(),
+                cache: self.cache.stats(),
+            },
+            DaemonRequest::Lookup { root, symbol } => match self.workspaces.get(&canonical(&root)) {
+                Some(indexed) => DaemonResponse::Symbols {
+                    locations: indexed.index.lookup(&symbol).to_vec(),
+                },
+                None => DaemonResponse::Error {
+                    message: format!("workspace not registered: {}", root.display()),
+                },
+            },
+            DaemonRequest::Shutdown => return (DaemonResponse::Ok, true),
+        };
+        (response, false)
+    }
+
+    /// Poll every registered workspace for changes
+    pub async fn refresh_all(&mut self) {
+        for (root, indexed) in self.workspaces.iter_mut() {
+            match indexed.refresh().await {
+                Ok(0) => {}
+                Ok(changed) => debug!("Re-indexed {} file(s) in {}", changed, root.display()),
+                Err(e) => warn!("Refreshing {} failed: {}", root.display(), e),
+            }
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    picode_core::paths::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn error_response(error: PiCodeError) -> DaemonResponse {
+    DaemonResponse::Error {
+        message: error.to_string(),
+    }
+}
+
+async fn load_registry(dir: &Path) -> Vec<PathBuf> {
+    match tokio::fs::read_to_string(dir.join(REGISTRY_FILE)).await {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+async fn save_registry(dir: &Path, roots: &[PathBuf]) -> Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(dir.join(REGISTRY_FILE), serde_json::to_string_pretty(roots)?).await?;
+    Ok(())
+}
+
+/// Run the daemon in the foreground until it receives `Shutdown`
+#[cfg(unix)]
+pub async fn run(opts: DaemonOptions) -> Result<()> {
+    use tokio::net::UnixListener;
+
+    if DaemonClient::connect(&opts.socket).await.is_ok() {
+        return Err(PiCodeError::AlreadyExists(format!(
+            "daemon already running at {}",
+            opts.socket.display()
+        )));
+    }
+    let dir = opts.socket.parent().map(Path::to_path_buf).unwrap_or_else(daemon_dir);
+    tokio::fs::create_dir_all(&dir).await?;
+    let _ = tokio::fs::remove_file(&opts.socket).await;
+
+    let listener = UnixListener::bind(&opts.socket)?;
+    info!("Daemon listening on {}", opts.socket.display());
+    println!("🛰️ PiCode daemon listening on {}", opts.socket.display());
+
+    let mut state = DaemonState::new();
+    for root in load_registry(&dir).await {
+        let (response, _) = state.handle(DaemonRequest::Register { root: root.clone() }).await;
+        if let DaemonResponse::Error { message } = response {
+            warn!("Could not restore workspace {}: {}", root.display(), message);
+        }
+    }
+    let state = Arc::new(tokio::sync::Mutex::new(state));
+    let dir = Arc::new(dir);
+    let (shutdown, mut shutdown_requested) = tokio::sync::mpsc::channel::<()>(1);
+
+    let mut ticker = tokio::time::interval(opts.refresh_interval);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => state.lock().await.refresh_all().await,
+            _ = shutdown_requested.recv() => {
+                info!("Daemon shutting down");
+                let _ = tokio::fs::remove_file(&opts.socket).await;
+                return Ok(());
+            }
+            accepted = listener.accept() => {
+                let stream = match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Could not accept a daemon connection: {}", e);
+                        continue;
+                    }
+                };
+                // A slow or broken client only ever affects its own connection
+                let (state, dir, shutdown) = (state.clone(), dir.clone(), shutdown.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(stream, &state, &dir, &shutdown).await {
+                        warn!("Daemon connection failed: {}", e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Answer the requests of one client until it disconnects or asks the
+/// daemon to stop
+#[cfg(unix)]
+async fn serve_connection(
+    stream: tokio::net::UnixStream,
+    state: &tokio::sync::Mutex<DaemonState>,
+    dir: &Path,
+    shutdown: &tokio::sync::mpsc::Sender<()>,
+) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let (response, stop) = match serde_json::from_str::<DaemonRequest>(&line) {
+            Ok(request) => {
+                let updates_registry = matches!(
+                    request,
+                    DaemonRequest::Register { .. } | DaemonRequest::Unregister { .. }
+                );
+                let mut state = state.lock().await;
+                let handled = state.handle(request).await;
+                if updates_registry {
+                    save_registry(dir, &state.roots()).await?;
+                }
+                handled
+            }
+            Err(e) => (DaemonResponse::Error { message: format!("invalid request: {}", e) }, false),
+        };
+
+        let mut payload = serde_json::to_string(&response)?;
+        payload.push('\n');
+        writer.write_all(payload.as_bytes()).await?;
+
+        if stop {
+            let _ = shutdown.send(()).await;
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn run(_opts: DaemonOptions) -> Result<()> {
+    Err(PiCodeError::Internal(
+        "the background daemon requires Unix domain sockets".to_string(),
+    ))
+}
+
+/// Start the daemon as a detached background process
+pub fn spawn_background() -> Result<u32> {
+    let exe = std::env::current_exe()?;
+    let child = std::process::Command::new(exe)
+        .args(["daemon", "start", "--foreground"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(child.id())
+}
+
+/// Client connection to a running daemon
+#[cfg(unix)]
+pub struct DaemonClient {
+    reader: tokio::io::BufReader<tokio::net::unix::OwnedReadHalf>,
+    writer: tokio::net::unix::OwnedWriteHalf,
+}
+
+#[cfg(unix)]
+impl DaemonClient {
+    pub async fn connect(socket: &Path) -> Result<Self> {
+        let stream = tokio::net::UnixStream::connect(socket).await?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self {
+            reader: tokio::io::BufReader::new(reader),
+            writer,
+        })
+    }
+
+    pub async fn request(&mut self, request: &DaemonRequest) -> Result<DaemonResponse> {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+        let mut payload = serde_json::to_string(request)?;
+        payload.push('\n');
+        self.writer.write_all(payload.as_bytes()).await?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).await? == 0 {
+            return Err(PiCodeError::Internal("daemon closed the connection".to_string()));
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+#[cfg(not(unix))]
+pub struct DaemonClient;
+
+#[cfg(not(unix))]
+impl DaemonClient {
+    pub async fn connect(_socket: &Path) -> Result<Self> {
+        Err(PiCodeError::Internal(
+            "the background daemon requires Unix domain sockets".to_string(),
+        ))
+    }
+
+    pub async fn request(&mut self, _request: &DaemonRequest) -> Result<DaemonResponse> {
+        unreachable!("no daemon client can be constructed on this platform")
+    }
+}
+
+/// Handle `picode daemon ...`
+#[cfg(feature = "cli")]
+pub async fn handle_action(action: picode_cli::DaemonAction) -> Result<()> {
+    use picode_cli::DaemonAction;
+
+    match action {
+        DaemonAction::Start { foreground: true } => run(DaemonOptions::default()).await,
+        DaemonAction::Start { foreground: false } => {
+            if DaemonClient::connect(&socket_path()).await.is_ok() {
+                println!("✅ Daemon already running");
+                return Ok(());
+            }
+            let pid = spawn_background()?;
+            println!("✅ Daemon started (pid {})", pid);
+            Ok(())
+        }
+        DaemonAction::Stop => {
+            let mut client = connect_or_report().await?;
+            client.request(&DaemonRequest::Shutdown).await?;
+            println!("✅ Daemon stopped");
+            Ok(())
+        }
+        DaemonAction::Status => {
+            let mut client = connect_or_report().await?;
+            if let DaemonResponse::Status { workspaces, cache } = client.request(&DaemonRequest::Status).await? {
+                println!("🛰️ Daemon running, {} workspace(s) registered", workspaces.len());
+                println!(
+                    "  Content cache: {} file(s), {} KiB, {:.0}% hits, {} eviction(s)",
+                    cache.entries,
+                    cache.bytes / 1024,
+                    cache.hit_rate() * 100.0,
+                    cache.evictions
+                );
+                for ws in workspaces {
+                    println!(
+                        "  {} - {} files, {} symbols{}{}",
+                        ws.root.display(),
+                        ws.files,
+                        ws.symbols,
+                        ws.branch.map(|b| format!(", branch {}", b)).unwrap_or_default(),
+                        if ws.dirty { " (dirty)" } else { "" }
+                    );
+                }
+            }
+            Ok(())
+        }
+        DaemonAction::Register { path } => {
+            let mut client = connect_or_report().await?;
+            expect_ok(client.request(&DaemonRequest::Register { root: canonical(&path) }).await?)?;
+            println!("✅ Registered {}", path.display());
+            Ok(())
+        }
+        DaemonAction::Unregister { path } => {
+            let mut client = connect_or_report().await?;
+            expect_ok(client.request(&DaemonRequest::Unregister { root: canonical(&path) }).await?)?;
+            println!("✅ Unregistered {}", path.display());
+            Ok(())
+        }
+    }
+}
+
+/// Register a workspace with a running daemon and return its summary, or
+/// `None` when no daemon is available
+pub async fn workspace_summary(root: &Path) -> Option<WorkspaceSummary> {
+    let root = canonical(root);
+    let mut client = DaemonClient::connect(&socket_path()).await.ok()?;
+    client
+        .request(&DaemonRequest::Register { root: root.clone() })
+        .await
+        .ok()?;
+
+    match client.request(&DaemonRequest::Status).await.ok()? {
+        DaemonResponse::Status { workspaces, .. } => workspaces.into_iter().find(|ws| ws.root == root),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "cli")]
+async fn connect_or_report() -> Result<DaemonClient> {
+    DaemonClient::connect(&socket_path()).await.map_err(|_| {
+        PiCodeError::NotFound("daemon is not running (start it with `picode daemon start`)".to_string())
+    })
+}
+
+#[cfg(feature = "cli")]
+fn expect_ok(response: DaemonResponse) -> Result<()> {
+    match response {
+        DaemonResponse::Error { message } => Err(PiCodeError::Internal(message)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn protocol_is_tagged_json() {
+        let request = DaemonRequest::Lookup {
+            root: PathBuf::from("/ws"),
+            symbol: "main".to_string(),
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(json, r#"{"type":"lookup","root":"/ws","symbol":"main"}"#);
+        assert_eq!(serde_json::from_str::<DaemonRequest>(&json).unwrap(), request);
+    }
+
+    #[tokio::test]
+    async fn state_indexes_and_refreshes_workspaces() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "pub fn alpha() {}\n").unwrap();
+
+        let mut state = DaemonState::new();
+        let (response, _) = state
+            .handle(DaemonRequest::Register { root: dir.path().to_path_buf() })
+            .await;
+        assert_eq!(response, DaemonResponse::Ok);
+
+        let lookup = DaemonRequest::Lookup {
+            root: dir.path().to_path_buf(),
+            symbol: "alpha".to_string(),
+        };
+        match state.handle(lookup).await.0 {
+            DaemonResponse::Symbols { locations } => assert_eq!(locations.len(), 1),
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        std::fs::remove_file(dir.path().join("lib.rs")).unwrap();
+        state.refresh_all().await;
+        match state.handle(DaemonRequest::Status).await.0 {
+            DaemonResponse::Status { workspaces, cache } => {
+                assert_eq!(workspaces[0].symbols, 0);
+                assert_eq!(cache.entries, 0);
+                assert_eq!(cache.misses, 1);
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        assert!(state.handle(DaemonRequest::Shutdown).await.1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn an_idle_client_does_not_block_others() {
+        let dir = tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let opts = DaemonOptions { socket: socket.clone(), refresh_interval: Duration::from_secs(60) };
+        let daemon = tokio::spawn(run(opts));
+        while DaemonClient::connect(&socket).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let _idle = DaemonClient::connect(&socket).await.unwrap();
+        let mut client = DaemonClient::connect(&socket).await.unwrap();
+        let pong = tokio::time::timeout(Duration::from_secs(5), client.request(&DaemonRequest::Ping)).await;
+        assert!(matches!(pong, Ok(Ok(DaemonResponse::Pong { .. }))));
+
+        client.request(&DaemonRequest::Shutdown).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), daemon).await.unwrap().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,384 @@
+//! `picode diff explain` - summaries of arbitrary diffs
+//!
+//! Reads a unified diff from stdin, a file or a git range, splits it into
+//! chunks along file and hunk boundaries so each fits the model's budget,
+//! summarizes every chunk and merges the partial summaries into one
+//! human-readable change summary with risk callouts. Obvious risks (public
+//! API changes, code changed without tests) are detected locally and passed
+//! to the model as hints.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_core::system_prompt::estimate_tokens;
+use std::path::PathBuf;
+use tokio::io::AsyncReadExt;
+use tracing::info;
+
+/// Token budget of a single chunk sent to the model
+const CHUNK_TOKENS: usize = 6000;
+
+/// Where to read the diff from
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffSource {
+    Stdin,
+    File(PathBuf),
+    /// Anything `git diff` accepts, e.g. `main..HEAD` or `HEAD~3`
+    GitRange(String),
+}
+
+impl DiffSource {
+    pub async fn read(&self) -> Result<String> {
+        match self {
+            DiffSource::Stdin => {
+                let mut diff = String::new();
+                tokio::io::stdin().read_to_string(&mut diff).await?;
+                Ok(diff)
+            }
+            DiffSource::File(path) => Ok(tokio::fs::read_to_string(path).await?),
+            DiffSource::GitRange(range) => {
+                let output = tokio::process::Command::new("git")
+                    .args(["diff", "--no-color", "--no-ext-diff", range])
+                    .output()
+                    .await?;
+                if !output.status.success() {
+                    return Err(PiCodeError::InvalidCommand(format!(
+                        "git diff {} failed: {}",
+                        range,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+            }
+        }
+    }
+}
+
+/// Changes to a single file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileDiff {
+    pub path: String,
+    /// `diff --git` line and extended headers up to the first hunk
+    pub header: String,
+    pub hunks: Vec<String>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl FileDiff {
+    fn text(&self) -> String {
+        let mut text = self.header.clone();
+        for hunk in &self.hunks {
+            text.push_str(hunk);
+        }
+        text
+    }
+
+    fn is_test(&self) -> bool {
+        let path = self.path.to_lowercase();
+        path.contains("test") || path.contains("spec") || self.hunks.iter().any(|h| h.contains("+#[test]"))
+    }
+
+    /// Added and removed lines (without their `+`/`-` marker)
+    fn changed_lines(&self) -> impl Iterator<Item = (char, &str)> {
+        self.hunks.iter().flat_map(|hunk| hunk.lines()).filter_map(|line| {
+            let marker = line.chars().next()?;
+            matches!(marker, '+' | '-').then(|| (marker, &line[1..]))
+        })
+    }
+}
+
+/// A parsed unified diff
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedDiff {
+    pub files: Vec<FileDiff>,
+}
+
+impl ParsedDiff {
+    pub fn parse(diff: &str) -> Self {
+        let mut files: Vec<FileDiff> = Vec::new();
+        let lines: Vec<&str> = diff.split_inclusive('\n').collect();
+
+        for (i, &line) in lines.iter().enumerate() {
+            // Plain `diff -u` output has no `diff --git` line; a `---`/`+++`
+            // pair outside a file header starts the next file instead
+            let starts_plain_file = line.starts_with("--- ")
+                && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ "))
+                && files.last().is_none_or(|f| !f.hunks.is_empty());
+            if line.starts_with("diff --git ") || starts_plain_file {
+                files.push(FileDiff {
+                    path: line
+                        .trim_end()
+                        .rsplit(' ')
+                        .next()
+                        .map(strip_prefix)
+                        .unwrap_or_default(),
+                    ..Default::default()
+                });
+            }
+            let Some(file) = files.last_mut() else {
+                // Preamble such as a commit message
+                continue;
+            };
+
+            if line.starts_with("@@") {
+                file.hunks.push(line.to_string());
+            } else if let Some(hunk) = file.hunks.last_mut() {
+                hunk.push_str(line);
+                if line.starts_with('+') {
+                    file.added += 1;
+                } else if line.starts_with('-') {
+                    file.removed += 1;
+                }
+            } else {
+                // Plain diffs may append a tab and timestamp to the path
+                if let Some(path) = line.strip_prefix("+++ ").and_then(|p| p.trim_end().split('\t').next()) {
+                    if path != "/dev/null" {
+                        file.path = strip_prefix(path);
+                    }
+                }
+                file.header.push_str(line);
+            }
+        }
+
+        Self { files }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Group the diff into chunks of at most `max_tokens`, splitting only at
+    /// file boundaries, or at hunk boundaries for files that are too large
+    pub fn chunks(&self, max_tokens: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+
+        let mut push = |piece: String, current: &mut String| {
+            if !current.is_empty() && estimate_tokens(current) + estimate_tokens(&piece) > max_tokens {
+                chunks.push(std::mem::take(current));
+            }
+            current.push_str(&piece);
+        };
+
+        for file in &self.files {
+            let text = file.text();
+            if estimate_tokens(&text) <= max_tokens {
+                push(text, &mut current);
+                continue;
+            }
+            // Repeat the file header so every piece stays self-describing
+            for hunk in &file.hunks {
+                push(format!("{}{}", file.header, hunk), &mut current);
+            }
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        chunks
+    }
+}
+
+fn strip_prefix(path: &str) -> String {
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// Kind of risk worth a reviewer's attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskKind {
+    ApiChange,
+    MissingTests,
+}
+
+impl std::fmt::Display for RiskKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RiskKind::ApiChange => write!(f, "API change"),
+            RiskKind::MissingTests => write!(f, "Missing tests"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskCallout {
+    pub kind: RiskKind,
+    pub detail: String,
+}
+
+/// Risks detectable without a model
+pub fn detect_risks(diff: &ParsedDiff) -> Vec<RiskCallout> {
+    let mut risks = Vec::new();
+
+    for file in &diff.files {
+        for (marker, line) in file.changed_lines() {
+            if let Some(signature) = public_signature(line) {
+                let change = if marker == '-' { "removed or changed" } else { "added" };
+                risks.push(RiskCallout {
+                    kind: RiskKind::ApiChange,
+                    detail: format!("{}: public item {} `{}`", file.path, change, signature),
+                });
+            }
+        }
+    }
+
+    let code_changed: Vec<&str> = diff
+        .files
+        .iter()
+        .filter(|f| !f.is_test() && is_code(&f.path) && f.added + f.removed > 0)
+        .map(|f| f.path.as_str())
+        .collect();
+    if !code_changed.is_empty() && !diff.files.iter().any(FileDiff::is_test) {
+        risks.push(RiskCallout {
+            kind: RiskKind::MissingTests,
+            detail: format!("code changed without test changes: {}", code_changed.join(", ")),
+        });
+    }
+
+    risks
+}
+
+fn public_signature(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let is_public = ["pub fn ", "pub async fn ", "pub struct ", "pub enum ", "pub trait ", "pub type ", "pub const ", "export "]
+        .iter()
+        .any(|prefix| line.starts_with(prefix));
+    is_public.then(|| line.trim_end_matches(['{', ' ']))
+}
+
+fn is_code(path: &str) -> bool {
+    const EXTENSIONS: &[&str] = &["rs", "py", "ts", "tsx", "js", "jsx", "go", "java", "kt", "c", "cc", "cpp", "h", "rb", "swift"];
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| EXTENSIONS.contains(&ext))
+}
+
+fn risk_hints(risks: &[RiskCallout]) -> String {
+    if risks.is_empty() {
+        return String::new();
+    }
+    let mut hints = String::from("Automatically detected risks (verify and include if relevant):\n");
+    for risk in risks {
+        hints.push_str(&format!("- {}: {}\n", risk.kind, risk.detail));
+    }
+    hints.push('\n');
+    hints
+}
+
+const CHUNK_SYSTEM: &str = "You summarize one part of a code diff for a reviewer. List what changed \
+and why it likely changed, in terse bullet points. Note behavior changes, API changes and missing \
+tests you can see. Do not speculate about code you cannot see.";
+
+fn final_system(pr: bool) -> &'static str {
+    if pr {
+        "You write pull request descriptions from diffs. Produce markdown with a one-line title, a \
+         `## Summary` of what changed and why, a `## Changes` bullet list and a `## Risks` section \
+         calling out API changes, behavior changes and missing tests. Be factual and concise."
+    } else {
+        "You explain code changes to a reviewer. Produce a short human-readable summary of the \
+         change followed by a `Risks:` list calling out API changes, behavior changes and missing \
+         tests. Say `Risks: none found` when there are none. Be factual and concise."
+    }
+}
+
+/// Run `picode diff explain`
+pub async fn explain(source: DiffSource, pr: bool, config: Config) -> Result<()> {
+    let text = source.read().await?;
+    let diff = ParsedDiff::parse(&text);
+    if diff.is_empty() {
+        println!("No changes to explain");
+        return Ok(());
+    }
+
+    let risks = detect_risks(&diff);
+    let chunks = diff.chunks(CHUNK_TOKENS);
+    info!("Explaining diff of {} file(s) in {} chunk(s)", diff.files.len(), chunks.len());
+
+    let assistant = Assistant::from_config(&config)?;
+    let hints = risk_hints(&risks);
+
+    let changes = if chunks.len() == 1 {
+        format!("Diff:\n```diff\n{}```\n", chunks[0])
+    } else {
+        let mut summaries = String::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            eprintln!("🔍 Summarizing part {}/{}...", i + 1, chunks.len());
+            let summary = assistant
+                .ask(CHUNK_SYSTEM, &format!("```diff\n{}```", chunk), Some(600))
+                .await?;
+            summaries.push_str(&format!("Part {}:\n{}\n\n", i + 1, summary.trim()));
+        }
+        format!("Summaries of the diff, part by part:\n\n{}", summaries)
+    };
+
+    let prompt = format!(
+        "{} file(s) changed, +{} -{} lines.\n\n{}{}",
+        diff.files.len(),
+        diff.files.iter().map(|f| f.added).sum::<usize>(),
+        diff.files.iter().map(|f| f.removed).sum::<usize>(),
+        hints,
+        changes
+    );
+    let explanation = assistant.ask(final_system(pr), &prompt, Some(1500)).await?;
+    println!("{}", picode_core::ansi::sanitize(explanation.trim(), config.ui.ansi_policy));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/src/api.rs b/src/api.rs\n\
+index 1111111..2222222 100644\n\
+--- a/src/api.rs\n\
++++ b/src/api.rs\n\
+@@ -1,3 +1,3 @@\n\
+-pub fn load(path: &str) -> Config {\n\
++pub fn load(path: &Path) -> Result<Config> {\n\
+\x20   parse(path)\n\
+\x20}\n\
+@@ -10,2 +10,3 @@\n\
+\x20fn parse() {}\n\
++fn helper() {}\n\
+diff --git a/README.md b/README.md\n\
+--- a/README.md\n\
++++ b/README.md\n\
+@@ -1 +1 @@\n\
+-Old\n\
++New\n";
+
+    #[test]
+    fn parses_files_and_hunks() {
+        let diff = ParsedDiff::parse(DIFF);
+        assert_eq!(diff.files.len(), 2);
+        assert_eq!(diff.files[0].path, "src/api.rs");
+        assert_eq!(diff.files[0].hunks.len(), 2);
+        assert_eq!((diff.files[0].added, diff.files[0].removed), (2, 1));
+        assert_eq!(diff.files[1].path, "README.md");
+    }
+
+    #[test]
+    fn chunks_split_at_file_and_hunk_boundaries() {
+        let diff = ParsedDiff::parse(DIFF);
+        assert_eq!(diff.chunks(10_000).len(), 1);
+
+        let chunks = diff.chunks(30);
+        assert!(chunks.len() >= 2);
+        // Split hunks keep their file header
+        assert!(chunks.iter().filter(|c| c.contains("+fn helper()")).all(|c| c.starts_with("diff --git a/src/api.rs")));
+        assert_eq!(chunks.concat().matches("+New").count(), 1);
+    }
+
+    #[test]
+    fn detects_api_changes_and_missing_tests() {
+        let risks = detect_risks(&ParsedDiff::parse(DIFF));
+        let api: Vec<_> = risks.iter().filter(|r| r.kind == RiskKind::ApiChange).collect();
+        assert_eq!(api.len(), 2);
+        assert!(api[0].detail.contains("removed or changed `pub fn load(path: &str) -> Config`"));
+
+        let missing = risks.iter().find(|r| r.kind == RiskKind::MissingTests).unwrap();
+        assert!(missing.detail.ends_with("src/api.rs"));
+    }
+}
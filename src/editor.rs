@@ -0,0 +1,757 @@
+//! Terminal front-end for editor panes
+//!
+//! Draws a [`ModalEditor`] with ratatui: highlighted text with a gutter of
+//! line numbers and diagnostic markers, a status line and a message line.
+//! Saves go through [`FileEdit`] so changes made on disk since the file was
+//! opened are never overwritten: they are merged with the buffer, and
+//! conflicting regions are loaded into the buffer between conflict markers
+//! for review. Every save is recorded in the workspace's edit journal first.
+//! The configured diagnostics commands run after every save.
+//!
+//! Files over `[workspace.large_files]`'s threshold are shown one window of
+//! lines at a time, read from a memory map without loading the file; such
+//! files are read-only here, and Ctrl-n / Ctrl-p move to the next or
+//! previous window.
+//!
+//! [`run_linked`] opens the editor next to a chat pane about the same file
+//! (see [`LinkedPanes`]): prompts carry the file or its visible region,
+//! Ctrl-a applies the next edit proposed in chat to the buffer, and both
+//! panes scroll to the edit being applied.
+
+use crate::assistant::Assistant;
+use crate::config::{Config, DiagnosticsCommand, EditorConfig};
+use crate::error::{PiCodeError, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use picode_core::editor::{
+    Diagnostic, DiagnosticsProvider, EditJournal, EditOutcome, EditorCommand, EditorError, ExternalDiagnostics, FileEdit,
+    HighlightKind, Highlighter, Key, LinkScope, LinkedPanes, MergeResult, ModalEditor, Mode, ProposedEdit, Severity,
+    TextBuffer,
+};
+use picode_core::io::FileSystem;
+use picode_core::{
+    ContentCache, ConversationLog, ConversationMessage, LargeFileSettings, LineRange, NativeFileSystem, NativeProcessRunner,
+    Pane, PaneType,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An open file: editor state plus what is needed to save it
+struct EditorPane {
+    path: PathBuf,
+    editor: ModalEditor,
+    highlighter: Highlighter,
+    /// On-disk content the buffer is based on; `None` for a new file
+    base: Option<Arc<str>>,
+    journal: EditJournal,
+    diagnostics: Vec<Diagnostic>,
+    providers: Vec<ExternalDiagnostics>,
+    message: String,
+    scroll: usize,
+    /// Rows of text shown at the last draw
+    view_height: usize,
+    tab_size: usize,
+    line_numbers: bool,
+    large_files: LargeFileSettings,
+    /// The lines shown of a large file, which is read-only
+    windowed: Option<Windowed>,
+}
+
+/// Lines of a large file in the buffer
+#[derive(Debug, Clone, Copy)]
+struct Windowed {
+    range: LineRange,
+    total_lines: usize,
+}
+
+impl EditorPane {
+    async fn open(path: &Path, config: &EditorConfig, large_files: &LargeFileSettings, journal: EditJournal) -> Result<Self> {
+        let pane = Pane::new_editor(path.to_path_buf(), path.display().to_string());
+        let language = match &pane.pane_type {
+            PaneType::Editor { language, .. } => language.clone(),
+            _ => None,
+        };
+
+        let large = match tokio::fs::metadata(path).await {
+            Ok(metadata) => large_files.is_large(metadata.len()),
+            Err(_) => false,
+        };
+        let (buffer, base, message, windowed) = if large {
+            let window = NativeFileSystem.read_window(path, large_files.window_at(1)).await?;
+            let message = format!(
+                "Large file, read-only: lines {} of {} (Ctrl-n / Ctrl-p for more)",
+                window.range, window.total_lines
+            );
+            let windowed = Windowed { range: window.range, total_lines: window.total_lines };
+            (TextBuffer::from_text(&window.text), None, message, Some(windowed))
+        } else {
+            match ContentCache::global().read(&NativeFileSystem, path).await {
+                Ok(file) => (TextBuffer::from_text(&file.content), Some(file.content), String::new(), None),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => (TextBuffer::new(), None, "[New file]".to_string(), None),
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        let runner = Arc::new(NativeProcessRunner);
+        let providers = config
+            .diagnostics
+            .iter()
+            .filter(|command| applies_to(command, language.as_deref()))
+            .map(|command| {
+                ExternalDiagnostics::new(&command.name, &command.command, runner.clone()).with_args(command.args.clone())
+            })
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            editor: ModalEditor::new(buffer).with_indent(config.indent()),
+            highlighter: Highlighter::for_language(language.as_deref()),
+            base,
+            journal,
+            diagnostics: Vec::new(),
+            providers,
+            message,
+            scroll: 0,
+            view_height: 0,
+            tab_size: config.tab_size.max(1),
+            line_numbers: config.line_numbers,
+            large_files: large_files.clone(),
+            windowed,
+        })
+    }
+
+    /// Show the window of a large file starting at line `start`
+    async fn show_window(&mut self, start: usize) {
+        if self.editor.buffer.is_dirty() {
+            self.message = "Changes to this window cannot be saved; :q! to discard them".to_string();
+            return;
+        }
+        match NativeFileSystem.read_window(&self.path, self.large_files.window_at(start)).await {
+            Ok(window) if window.range.end >= window.range.start => {
+                self.editor.buffer.replace_text(&window.text);
+                self.editor.buffer.mark_saved();
+                self.scroll = 0;
+                self.message = format!("Lines {} of {}", window.range, window.total_lines);
+                self.windowed = Some(Windowed { range: window.range, total_lines: window.total_lines });
+            }
+            Ok(_) => self.message = "End of file".to_string(),
+            Err(e) => self.message = format!("Read failed: {}", e),
+        }
+    }
+
+    /// Save through a `FileEdit` based on the version the buffer started
+    /// from, merging changes made on disk since
+    async fn save(&mut self) -> bool {
+        if self.windowed.is_some() {
+            self.message = "Large files are read-only in the editor; not saved (:q! to discard)".to_string();
+            return false;
+        }
+        let mut edit = FileEdit::new(&self.path, self.editor.buffer.text()).with_journal(self.journal.clone());
+        if let Some(base) = &self.base {
+            edit = edit.based_on_content(base.clone());
+        }
+
+        match edit.apply_or_merge(&NativeFileSystem, &ContentCache::global()).await {
+            Ok(EditOutcome::Applied(saved)) => {
+                self.base = Some(saved.content);
+                self.editor.buffer.mark_saved();
+                self.message = format!("\"{}\" written", self.path.display());
+                self.refresh_diagnostics().await;
+                true
+            }
+            Ok(EditOutcome::Merged(saved)) => {
+                self.editor.buffer.replace_text(&saved.content);
+                self.editor.buffer.mark_saved();
+                self.base = Some(saved.content);
+                self.message = format!("\"{}\" written, merged with changes made on disk", self.path.display());
+                self.refresh_diagnostics().await;
+                true
+            }
+            Ok(EditOutcome::Conflicted { merge, on_disk }) => {
+                self.show_conflicts(&merge);
+                self.base = Some(on_disk.into());
+                false
+            }
+            Err(EditorError::Conflict(_)) => {
+                self.message = "File changed on disk since it was opened; not saved (:q! to discard)".to_string();
+                false
+            }
+            Err(e) => {
+                self.message = format!("Save failed: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Load a conflicted merge into the buffer and mark every conflict in
+    /// the gutter; saving again writes the resolution
+    fn show_conflicts(&mut self, merge: &MergeResult) {
+        self.editor.buffer.replace_text(&merge.content);
+        self.diagnostics = merge
+            .conflicts
+            .iter()
+            .map(|conflict| Diagnostic {
+                line: conflict.line,
+                column: 0,
+                severity: Severity::Error,
+                message: "conflicts with changes made on disk; keep one side and delete the markers".to_string(),
+                source: Some("merge".to_string()),
+            })
+            .collect();
+        self.message = format!(
+            "{} conflict(s) with changes made on disk; resolve the marked regions and :w",
+            merge.conflicts.len()
+        );
+    }
+
+    async fn refresh_diagnostics(&mut self) {
+        let content = self.editor.buffer.text();
+        let mut diagnostics = Vec::new();
+        for provider in &self.providers {
+            match provider.diagnostics(&self.path, &content).await {
+                Ok(found) => diagnostics.extend(found),
+                Err(e) => self.message = e.to_string(),
+            }
+        }
+        diagnostics.sort_by_key(|d| (d.line, d.column, d.severity));
+        self.diagnostics = diagnostics;
+    }
+
+    /// Apply a key; returns true when the editor should close
+    async fn handle_key(&mut self, key: Key) -> bool {
+        if let Some(windowed) = self.windowed {
+            match key {
+                Key::Ctrl('n') => {
+                    self.show_window(windowed.range.end + 1).await;
+                    return false;
+                }
+                Key::Ctrl('p') => {
+                    let start = windowed.range.start.saturating_sub(self.large_files.window_lines.max(1)).max(1);
+                    self.show_window(start).await;
+                    return false;
+                }
+                _ => {}
+            }
+        }
+        let Some(command) = self.editor.handle_key(key) else {
+            return false;
+        };
+        match command {
+            EditorCommand::Save => {
+                self.save().await;
+                false
+            }
+            EditorCommand::SaveAndQuit => self.save().await,
+            EditorCommand::Quit if self.editor.buffer.is_dirty() => {
+                self.message = "No write since last change (:q! to discard)".to_string();
+                false
+            }
+            EditorCommand::Quit | EditorCommand::ForceQuit => true,
+            EditorCommand::Unknown(text) => {
+                self.message = format!("Not an editor command: {}", text);
+                false
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, focused: bool) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1), Constraint::Length(1)])
+            .split(area);
+        let text_area = chunks[0];
+
+        let buffer = &self.editor.buffer;
+        let cursor = buffer.cursor();
+        self.scroll = buffer.scroll_offset(self.scroll, text_area.height as usize);
+        self.view_height = text_area.height as usize;
+        let gutter_width = if self.line_numbers {
+            self.windowed.map_or(buffer.line_count(), |windowed| windowed.total_lines).to_string().len() + 2
+        } else {
+            2
+        };
+
+        let lines: Vec<Line> = buffer
+            .lines()
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(text_area.height as usize)
+            .map(|(index, text)| {
+                let mut spans = vec![self.gutter(index, gutter_width)];
+                spans.extend(self.highlighted(text));
+                Line::from(spans)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), text_area);
+
+        frame.render_widget(Paragraph::new(self.status_line()), chunks[1]);
+        let message = match self.editor.mode() {
+            Mode::Command(text) => format!(":{}", text),
+            _ => self
+                .diagnostics
+                .iter()
+                .find(|d| d.line == cursor.line)
+                .map(|d| format!("{}: {}", d.source.as_deref().unwrap_or("diagnostics"), d.message))
+                .unwrap_or_else(|| self.message.clone()),
+        };
+        frame.render_widget(Paragraph::new(message), chunks[2]);
+
+        if !focused {
+            return;
+        }
+        match self.editor.mode() {
+            Mode::Command(text) => frame.set_cursor(chunks[2].x + 1 + text.chars().count() as u16, chunks[2].y),
+            _ => {
+                let line = &buffer.lines()[cursor.line];
+                let column: usize = line.chars().take(cursor.column).map(|c| self.char_width(c)).sum();
+                let x = text_area.x as usize + gutter_width + column;
+                let y = text_area.y as usize + cursor.line - self.scroll;
+                if x < (text_area.x + text_area.width) as usize {
+                    frame.set_cursor(x as u16, y as u16);
+                }
+            }
+        }
+    }
+
+    /// Line number plus a marker for the most severe diagnostic on the line
+    fn gutter(&self, index: usize, width: usize) -> Span<'static> {
+        let severity = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.line == index)
+            .map(|d| d.severity)
+            .min();
+        let (marker, style) = match severity {
+            Some(Severity::Error) => ('E', Style::default().fg(Color::Red)),
+            Some(Severity::Warning) => ('W', Style::default().fg(Color::Yellow)),
+            Some(Severity::Info | Severity::Hint) => ('I', Style::default().fg(Color::Blue)),
+            None => (' ', Style::default().fg(Color::DarkGray)),
+        };
+        // Windows of large files keep the file's line numbers
+        let first = self.windowed.map_or(1, |windowed| windowed.range.start);
+        let number = if self.line_numbers {
+            format!("{:>1$}", index + first, width - 2)
+        } else {
+            String::new()
+        };
+        Span::styled(format!("{}{} ", number, marker), style)
+    }
+
+    fn highlighted(&self, line: &str) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        let mut position = 0;
+        for span in self.highlighter.highlight(line) {
+            if span.range.start > position {
+                spans.push(Span::raw(self.expand_tabs(&line[position..span.range.start])));
+            }
+            spans.push(Span::styled(self.expand_tabs(&line[span.range.clone()]), highlight_style(span.kind)));
+            position = span.range.end;
+        }
+        if position < line.len() {
+            spans.push(Span::raw(self.expand_tabs(&line[position..])));
+        }
+        spans
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        let buffer = &self.editor.buffer;
+        let cursor = buffer.cursor();
+        let errors = self.diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+        let warnings = self.diagnostics.iter().filter(|d| d.severity == Severity::Warning).count();
+        Line::from(vec![
+            Span::styled(
+                format!(" {:<7} ", self.editor.mode()),
+                Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                " {}{}{}  {}:{}  E{} W{}",
+                self.path.display(),
+                if buffer.is_dirty() { " [+]" } else { "" },
+                self.windowed
+                    .map(|windowed| format!(" [lines {} of {}, read-only]", windowed.range, windowed.total_lines))
+                    .unwrap_or_default(),
+                cursor.line + self.windowed.map_or(1, |windowed| windowed.range.start),
+                cursor.column + 1,
+                errors,
+                warnings,
+            )),
+        ])
+    }
+
+    fn char_width(&self, c: char) -> usize {
+        if c == '\t' {
+            self.tab_size
+        } else {
+            1
+        }
+    }
+
+    fn expand_tabs(&self, text: &str) -> String {
+        text.replace('\t', &" ".repeat(self.tab_size))
+    }
+}
+
+fn highlight_style(kind: HighlightKind) -> Style {
+    match kind {
+        HighlightKind::Keyword => Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+        HighlightKind::String => Style::default().fg(Color::Green),
+        HighlightKind::Number => Style::default().fg(Color::Yellow),
+        HighlightKind::Comment => Style::default().fg(Color::DarkGray),
+    }
+}
+
+/// Whether a diagnostics command applies to files of this language
+fn applies_to(command: &DiagnosticsCommand, language: Option<&str>) -> bool {
+    command.languages.is_empty() || language.is_some_and(|language| command.languages.iter().any(|l| l == language))
+}
+
+/// Map a terminal key event onto the editor's key type
+fn map_key(event: KeyEvent) -> Option<Key> {
+    if event.kind != KeyEventKind::Press {
+        return None;
+    }
+    let key = match event.code {
+        KeyCode::Char(c) if event.modifiers.contains(KeyModifiers::CONTROL) => Key::Ctrl(c),
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Open `path` in a full-screen editor until the user quits; saves are
+/// recorded in `journal`
+pub async fn run(path: &Path, config: &Config, journal: &EditJournal) -> Result<()> {
+    let mut pane = EditorPane::open(path, &config.ui.editor, &config.workspace.large_files, journal.clone()).await?;
+    if pane.base.is_some() {
+        pane.refresh_diagnostics().await;
+    }
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = event_loop(&mut pane).await;
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+async fn event_loop(pane: &mut EditorPane) -> Result<()> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    loop {
+        terminal.draw(|frame| pane.draw(frame, frame.size(), true))?;
+        match event::read()? {
+            Event::Key(event) => {
+                if let Some(key) = map_key(event) {
+                    if pane.handle_key(key).await {
+                        return Ok(());
+                    }
+                }
+            }
+            Event::Resize(..) => terminal.autoresize()?,
+            _ => {}
+        }
+    }
+}
+
+/// Chat half of a linked editor + chat view
+struct LinkedChat {
+    link: LinkedPanes,
+    assistant: Assistant,
+    system: String,
+    /// Exchanges so far as (role, text)
+    transcript: Vec<(String, String)>,
+    input: String,
+    /// Edits proposed by replies, with the transcript entry they came from
+    proposed: VecDeque<(usize, ProposedEdit)>,
+    /// First transcript row shown
+    scroll: usize,
+    /// Text width at the last draw, for wrapping
+    width: usize,
+    focused: bool,
+}
+
+impl LinkedChat {
+    /// Send the input with the linked file as context
+    async fn send(&mut self, pane: &mut EditorPane) {
+        let question = std::mem::take(&mut self.input);
+        if question.trim().is_empty() {
+            return;
+        }
+        let mut prompt = self.link.context(&pane.editor.buffer);
+        for (role, text) in &self.transcript {
+            let _ = write!(prompt, "\n{}: {}\n", role, text);
+        }
+        let _ = write!(prompt, "\nuser: {}\n", question);
+        self.transcript.push(("user".to_string(), question));
+
+        match self.assistant.ask(&self.system, &prompt, None).await {
+            Ok(reply) => {
+                let entry = self.transcript.len();
+                let edits = ProposedEdit::parse_all(&reply);
+                if !edits.is_empty() {
+                    pane.message = format!("{} proposed edit(s); Ctrl-a applies the next one", edits.len());
+                }
+                self.proposed.extend(edits.into_iter().map(|edit| (entry, edit)));
+                self.transcript.push(("assistant".to_string(), reply));
+                self.scroll = self.entry_row(entry);
+            }
+            Err(e) => pane.message = format!("Chat failed: {}", e),
+        }
+    }
+
+    /// Apply the oldest proposed edit to the buffer and scroll both panes
+    /// to it: the editor to the changed lines, the chat to the reply
+    fn apply_next(&mut self, pane: &mut EditorPane) {
+        let Some((entry, edit)) = self.proposed.pop_front() else {
+            pane.message = "No proposed edits".to_string();
+            return;
+        };
+        match edit.apply(&mut pane.editor.buffer) {
+            Ok(line) => {
+                self.link.reveal(line);
+                pane.scroll = self.link.viewport.first_line;
+                self.scroll = self.entry_row(entry);
+                pane.message = format!("Applied edit at line {} ({} left)", line + 1, self.proposed.len());
+            }
+            Err(e) => pane.message = e.to_string(),
+        }
+    }
+
+    fn toggle_scope(&mut self, pane: &mut EditorPane) {
+        self.link.scope = match self.link.scope {
+            LinkScope::File => LinkScope::Visible,
+            LinkScope::Visible => LinkScope::File,
+        };
+        pane.message = format!("Chat context: {}", scope_label(self.link.scope));
+    }
+
+    /// Transcript wrapped to the pane width
+    fn rows(&self) -> Vec<Line<'static>> {
+        let mut rows = Vec::new();
+        for (role, text) in &self.transcript {
+            rows.push(Line::from(Span::styled(format!("{}:", role), Style::default().add_modifier(Modifier::BOLD))));
+            for line in text.lines() {
+                rows.extend(wrap(line, self.width).into_iter().map(Line::from));
+            }
+            rows.push(Line::from(""));
+        }
+        rows
+    }
+
+    /// Row where transcript entry `index` starts
+    fn entry_row(&self, index: usize) -> usize {
+        self.transcript
+            .iter()
+            .take(index)
+            .map(|(_, text)| 2 + text.lines().map(|line| wrap(line, self.width).len()).sum::<usize>())
+            .sum()
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title(format!(
+            " Chat: {} ({}) ",
+            self.link.file.display(),
+            scope_label(self.link.scope)
+        ));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        self.width = inner.width.max(1) as usize;
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+        let rows = self.rows();
+        self.scroll = self.scroll.min(rows.len().saturating_sub(1));
+        let visible: Vec<Line> = rows.into_iter().skip(self.scroll).take(chunks[0].height as usize).collect();
+        frame.render_widget(Paragraph::new(visible), chunks[0]);
+        frame.render_widget(Paragraph::new(format!("> {}", self.input)), chunks[1]);
+        if self.focused {
+            let x = chunks[1].x as usize + 2 + self.input.chars().count();
+            if x < (chunks[1].x + chunks[1].width) as usize {
+                frame.set_cursor(x as u16, chunks[1].y);
+            }
+        }
+    }
+}
+
+fn scope_label(scope: LinkScope) -> &'static str {
+    match scope {
+        LinkScope::File => "whole file",
+        LinkScope::Visible => "visible lines",
+    }
+}
+
+/// Split `line` into rows of at most `width` characters
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(width.max(1)).map(|chunk| chunk.iter().collect()).collect()
+}
+
+/// Open `path` in an editor linked to a chat with `assistant`; exchanges
+/// are added to `conversation`
+pub async fn run_linked(
+    path: &Path,
+    config: &Config,
+    journal: &EditJournal,
+    chat_pane: &Pane,
+    assistant: Assistant,
+    system: &str,
+    conversation: &mut ConversationLog,
+) -> Result<()> {
+    let mut pane = EditorPane::open(path, &config.ui.editor, &config.workspace.large_files, journal.clone()).await?;
+    if pane.base.is_some() {
+        pane.refresh_diagnostics().await;
+    }
+    let editor_pane = Pane::new_editor(path.to_path_buf(), path.display().to_string());
+    let mut chat = LinkedChat {
+        link: LinkedPanes::new(&editor_pane, chat_pane).map_err(picode_core::CoreError::from)?,
+        assistant,
+        system: format!("{}\n\n{}", system, picode_core::editor::LINKED_CHAT_INSTRUCTIONS),
+        transcript: Vec::new(),
+        input: String::new(),
+        proposed: VecDeque::new(),
+        scroll: 0,
+        width: 1,
+        focused: false,
+    };
+    pane.message = "Ctrl-w switches between editor and chat, Ctrl-a applies a proposed edit, Ctrl-t toggles the chat context".to_string();
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = linked_event_loop(&mut pane, &mut chat).await;
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+
+    for (role, text) in chat.transcript {
+        conversation.push(ConversationMessage::new(role, text));
+    }
+    result
+}
+
+/// Editor on the left, chat on the right
+fn draw_linked(frame: &mut Frame, pane: &mut EditorPane, chat: &mut LinkedChat) {
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(frame.size());
+    pane.draw(frame, halves[0], !chat.focused);
+    chat.draw(frame, halves[1]);
+}
+
+async fn linked_event_loop(pane: &mut EditorPane, chat: &mut LinkedChat) -> Result<()> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    loop {
+        terminal.draw(|frame| draw_linked(frame, pane, chat))?;
+        // The chat context follows the editor's scrolling
+        chat.link.set_viewport(pane.scroll, pane.view_height);
+
+        let event = match event::read()? {
+            Event::Key(event) => event,
+            Event::Resize(..) => {
+                terminal.autoresize()?;
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(key) = map_key(event) else {
+            continue;
+        };
+        match key {
+            Key::Ctrl('w') => chat.focused = !chat.focused,
+            Key::Ctrl('a') => chat.apply_next(pane),
+            Key::Ctrl('t') => chat.toggle_scope(pane),
+            _ if chat.focused => match key {
+                Key::Char(c) => chat.input.push(c),
+                Key::Backspace => {
+                    chat.input.pop();
+                }
+                Key::Enter => {
+                    pane.message = "Waiting for the reply...".to_string();
+                    terminal.draw(|frame| draw_linked(frame, pane, chat))?;
+                    pane.message.clear();
+                    chat.send(pane).await;
+                }
+                Key::Up => chat.scroll = chat.scroll.saturating_sub(1),
+                Key::Down => chat.scroll += 1,
+                Key::Esc => chat.focused = false,
+                _ => {}
+            },
+            _ => {
+                if pane.handle_key(key).await {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Resolve the `/edit` argument against the workspace root
+pub fn resolve_path(workspace: Option<&Path>, target: &str) -> Result<PathBuf> {
+    if target.is_empty() {
+        return Err(PiCodeError::InvalidCommand("/edit requires a file path".to_string()));
+    }
+    let path = PathBuf::from(target);
+    Ok(match workspace {
+        Some(root) if path.is_relative() => root.join(path),
+        _ => path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_terminal_keys() {
+        let press = |code, modifiers| map_key(KeyEvent::new(code, modifiers));
+        assert_eq!(press(KeyCode::Char('s'), KeyModifiers::CONTROL), Some(Key::Ctrl('s')));
+        assert_eq!(press(KeyCode::Char('j'), KeyModifiers::NONE), Some(Key::Char('j')));
+        assert_eq!(press(KeyCode::F(1), KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn wraps_chat_lines_to_the_pane_width() {
+        assert_eq!(wrap("abcdefg", 3), vec!["abc", "def", "g"]);
+        assert_eq!(wrap("", 3), vec![""]);
+        assert_eq!(wrap("héllo", 0).len(), 5);
+    }
+
+    #[test]
+    fn diagnostics_commands_filter_by_language() {
+        let mut command = DiagnosticsCommand {
+            name: "lint".to_string(),
+            command: "lint".to_string(),
+            args: vec![],
+            languages: vec![],
+        };
+        assert!(applies_to(&command, None));
+        command.languages = vec!["rs".to_string()];
+        assert!(applies_to(&command, Some("rs")));
+        assert!(!applies_to(&command, Some("py")));
+    }
+}
@@ -0,0 +1,518 @@
+//! Embedding API
+//!
+//! [`Engine`] is PiCode's agent core without the CLI or terminal UI, for
+//! Rust applications (IDEs, bots) that want to drive it directly. Build the
+//! crate with `default-features = false, features = ["native"]` to leave the
+//! binary-only modules out.
+//!
+//! ```no_run
+//! use picode::engine::{Engine, Tool};
+//! use serde_json::{json, Value};
+//!
+//! struct Clock;
+//!
+//! #[async_trait::async_trait]
+//! impl Tool for Clock {
+//!     fn name(&self) -> &str { "clock" }
+//!     fn description(&self) -> &str { "Current UTC time" }
+//!     async fn call(&self, _arguments: Value) -> picode::Result<Value> {
+//!         Ok(json!(chrono::Utc::now().to_rfc3339()))
+//!     }
+//! }
+//!
+//! # async fn demo() -> picode::Result<()> {
+//! let engine = Engine::builder(picode::config::Config::default()).with_tool(Clock).build()?;
+//! let mut events = engine.subscribe();
+//! let session = engine.create_session("bot").await?;
+//! let reply = engine.send_message(&session, "What time is it?").await?;
+//! println!("{} ({} tool calls)", reply.text, reply.tool_calls.len());
+//! # let _ = events.recv().await;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Tools are offered to the model in the system prompt; a reply that is
+//! only a `{"tool": ..., "arguments": ...}` object runs the tool and hands
+//! its result back to the model, up to [`DEFAULT_MAX_TOOL_STEPS`] times per
+//! message.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use crate::metrics::Metrics;
+use async_trait::async_trait;
+use picode_core::agent::ToolCallRecord;
+use picode_core::command::{CommandId, CommandStatus};
+use picode_core::event::EventEnvelope;
+use picode_core::{ConversationLog, ConversationMessage, Event, EventBus, EventHandler, Pane, SessionId};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{broadcast, Mutex, RwLock};
+
+/// Tool round trips allowed while answering one message
+pub const DEFAULT_MAX_TOOL_STEPS: usize = 8;
+
+/// Source name of events published by the engine
+const EVENT_SOURCE: &str = "engine";
+
+/// A capability the model may call while answering
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+
+    /// JSON schema of the arguments
+    fn parameters(&self) -> Value {
+        serde_json::json!({ "type": "object" })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<Value>;
+}
+
+/// Answer to one message
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub text: String,
+    pub tool_calls: Vec<ToolCallRecord>,
+}
+
+/// A conversation held by the engine
+struct EngineSession {
+    name: String,
+    pane: Pane,
+    log: ConversationLog,
+}
+
+/// Builder for [`Engine`]
+pub struct EngineBuilder {
+    config: Config,
+    assistant: Option<Assistant>,
+    tools: Vec<Arc<dyn Tool>>,
+    handlers: Vec<Box<dyn EventHandler>>,
+    max_tool_steps: usize,
+    system_prompt: Option<String>,
+    workspace: Option<PathBuf>,
+}
+
+impl EngineBuilder {
+    /// Answer with this assistant instead of the configured default provider
+    pub fn with_assistant(mut self, assistant: Assistant) -> Self {
+        self.assistant = Some(assistant);
+        self
+    }
+
+    pub fn with_tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.push(Arc::new(tool));
+        self
+    }
+
+    /// Run `handler` for every event the engine publishes
+    pub fn with_event_handler(mut self, handler: impl EventHandler + 'static) -> Self {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    pub fn with_max_tool_steps(mut self, steps: usize) -> Self {
+        self.max_tool_steps = steps;
+        self
+    }
+
+    /// Base system prompt; the tool list is appended to it
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Directory sessions work in; defaults to the configured workspace root
+    pub fn with_workspace(mut self, path: impl Into<PathBuf>) -> Self {
+        self.workspace = Some(path.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Engine> {
+        let assistant = match self.assistant {
+            Some(assistant) => assistant,
+            None => Assistant::from_config(&self.config)?,
+        };
+        let workspace = match self.workspace.or_else(|| self.config.workspace.root_dir.clone()) {
+            Some(path) => path,
+            None => std::env::current_dir()?,
+        };
+        let tools = self.tools.into_iter().map(|tool| (tool.name().to_string(), tool)).collect();
+        Ok(Engine {
+            assistant,
+            tools: RwLock::new(tools),
+            events: EventBus::new(256, 1024),
+            pending_handlers: Mutex::new(self.handlers),
+            sessions: RwLock::new(HashMap::new()),
+            max_tool_steps: self.max_tool_steps,
+            system_prompt: self.system_prompt.unwrap_or_default(),
+            workspace,
+        })
+    }
+}
+
+/// PiCode's agent core for embedding
+pub struct Engine {
+    assistant: Assistant,
+    tools: RwLock<BTreeMap<String, Arc<dyn Tool>>>,
+    events: EventBus,
+    /// Handlers registered on first use; registration is async
+    pending_handlers: Mutex<Vec<Box<dyn EventHandler>>>,
+    sessions: RwLock<HashMap<SessionId, Mutex<EngineSession>>>,
+    max_tool_steps: usize,
+    system_prompt: String,
+    workspace: PathBuf,
+}
+
+impl Engine {
+    pub fn builder(config: Config) -> EngineBuilder {
+        EngineBuilder {
+            config,
+            assistant: None,
+            tools: Vec::new(),
+            handlers: Vec::new(),
+            max_tool_steps: DEFAULT_MAX_TOOL_STEPS,
+            system_prompt: None,
+            workspace: None,
+        }
+    }
+
+    /// Add or replace a tool; it is offered from the next message on
+    pub async fn register_tool(&self, tool: impl Tool + 'static) {
+        let tool: Arc<dyn Tool> = Arc::new(tool);
+        self.tools.write().await.insert(tool.name().to_string(), tool);
+    }
+
+    /// Names of the registered tools
+    pub async fn tools(&self) -> Vec<String> {
+        self.tools.read().await.keys().cloned().collect()
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope> {
+        self.events.subscribe()
+    }
+
+    async fn publish(&self, event: Event) {
+        let handlers = std::mem::take(&mut *self.pending_handlers.lock().await);
+        for handler in handlers {
+            self.events.register_handler(handler).await;
+        }
+        if let Err(err) = self.events.publish(event, EVENT_SOURCE.to_string()).await {
+            tracing::error!("Failed to publish event: {}", err);
+        }
+    }
+
+    /// Start a conversation
+    pub async fn create_session(&self, name: impl Into<String>) -> Result<SessionId> {
+        let name = name.into();
+        let session_id = SessionId::new();
+        let pane = Pane::new_llm_chat(
+            self.assistant.provider_name().to_string(),
+            self.assistant.model().to_string(),
+            name.clone(),
+        );
+        let session = EngineSession { name: name.clone(), pane, log: ConversationLog::new(session_id.clone()) };
+        self.sessions.write().await.insert(session_id.clone(), Mutex::new(session));
+        Metrics::global().session_started();
+        self.publish(Event::SessionCreated {
+            session_id: session_id.clone(),
+            name,
+            workspace_path: self.workspace.clone(),
+        })
+        .await;
+        Ok(session_id)
+    }
+
+    /// End a conversation, returning its log
+    pub async fn close_session(&self, session_id: &SessionId) -> Result<ConversationLog> {
+        let session = self
+            .sessions
+            .write()
+            .await
+            .remove(session_id)
+            .ok_or_else(|| PiCodeError::NotFound(format!("session {}", session_id)))?;
+        Metrics::global().session_ended();
+        self.publish(Event::SessionClosed { session_id: session_id.clone() }).await;
+        Ok(session.into_inner().log)
+    }
+
+    /// Snapshot of a conversation
+    pub async fn conversation(&self, session_id: &SessionId) -> Option<ConversationLog> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)?;
+        let log = session.lock().await.log.clone();
+        Some(log)
+    }
+
+    /// Name a session was created with
+    pub async fn session_name(&self, session_id: &SessionId) -> Option<String> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)?;
+        let name = session.lock().await.name.clone();
+        Some(name)
+    }
+
+    async fn system_prompt(&self) -> String {
+        let tools = self.tools.read().await;
+        let mut prompt = self.system_prompt.clone();
+        if !tools.is_empty() {
+            prompt.push_str(
+                "\n\nYou can use these tools. To call one, reply with only a JSON object \
+                 {\"tool\": \"<name>\", \"arguments\": {...}}; you will get its result back.\n",
+            );
+            for tool in tools.values() {
+                let _ = writeln!(prompt, "- {}: {} Arguments: {}", tool.name(), tool.description(), tool.parameters());
+            }
+        }
+        prompt
+    }
+
+    /// Send a user message and return the model's answer, running the tools
+    /// it asks for on the way
+    pub async fn send_message(&self, session_id: &SessionId, text: &str) -> Result<Reply> {
+        let sessions = self.sessions.read().await;
+        let mut session = sessions
+            .get(session_id)
+            .ok_or_else(|| PiCodeError::NotFound(format!("session {}", session_id)))?
+            .lock()
+            .await;
+        session.log.push(ConversationMessage::new("user", text));
+        let system = self.system_prompt().await;
+        let mut tool_calls = Vec::new();
+
+        for _ in 0..=self.max_tool_steps {
+            let prompt = transcript(&session.log);
+            let reply = self.ask(session_id, &session.pane, &system, &prompt).await?;
+            let call = tool_call(&reply);
+            session.log.push(ConversationMessage::new("assistant", reply.clone()));
+
+            let Some((name, arguments)) = call else {
+                return Ok(Reply { text: reply, tool_calls });
+            };
+            if tool_calls.len() == self.max_tool_steps {
+                return Err(PiCodeError::Internal(format!(
+                    "tool call limit of {} reached in one message",
+                    self.max_tool_steps
+                )));
+            }
+            let (record, result) = self.call_tool(session_id, &session.pane, &name, arguments).await;
+            tool_calls.push(record);
+            session.log.push(ConversationMessage::new("user", format!("Result of tool {}:\n{}", name, result)));
+        }
+        unreachable!("the loop returns once the tool call limit is reached")
+    }
+
+    async fn ask(&self, session_id: &SessionId, pane: &Pane, system: &str, prompt: &str) -> Result<String> {
+        self.publish(Event::LLMRequestStarted {
+            session_id: session_id.clone(),
+            pane_id: pane.id.clone(),
+            provider: self.assistant.provider_name().to_string(),
+            model: self.assistant.model().to_string(),
+            prompt: prompt.to_string(),
+        })
+        .await;
+        match self.assistant.ask_with_usage(system, prompt, None).await {
+            Ok((reply, usage)) => {
+                self.publish(Event::LLMResponseReceived {
+                    session_id: session_id.clone(),
+                    pane_id: pane.id.clone(),
+                    provider: self.assistant.provider_name().to_string(),
+                    model: self.assistant.model().to_string(),
+                    response: reply.clone(),
+                    tokens_used: Some(usage.total_tokens),
+                })
+                .await;
+                Ok(reply)
+            }
+            Err(err) => {
+                self.publish(Event::LLMError {
+                    session_id: session_id.clone(),
+                    pane_id: pane.id.clone(),
+                    provider: self.assistant.provider_name().to_string(),
+                    error: err.to_string(),
+                })
+                .await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Run a tool; failures are reported back to the model, not to the caller
+    async fn call_tool(&self, session_id: &SessionId, pane: &Pane, name: &str, arguments: Value) -> (ToolCallRecord, String) {
+        let command_id = CommandId::new();
+        self.publish(Event::CommandStarted {
+            session_id: session_id.clone(),
+            pane_id: pane.id.clone(),
+            command_id: command_id.clone(),
+            command: format!("tool {}", name),
+        })
+        .await;
+
+        let started = Instant::now();
+        let tool = self.tools.read().await.get(name).cloned();
+        let (status, output) = match tool {
+            Some(tool) => match tool.call(arguments.clone()).await {
+                Ok(value) => (CommandStatus::Success, value.to_string()),
+                Err(err) => (CommandStatus::Failed(1), format!("error: {}", err)),
+            },
+            None => (CommandStatus::Failed(127), format!("error: no tool named '{}'", name)),
+        };
+        let duration = started.elapsed();
+
+        self.publish(Event::CommandCompleted {
+            session_id: session_id.clone(),
+            pane_id: pane.id.clone(),
+            command_id,
+            status,
+            duration,
+        })
+        .await;
+        let record = ToolCallRecord {
+            tool: name.to_string(),
+            arguments,
+            output_bytes: output.len(),
+            cached: false,
+            duration,
+            timestamp: chrono::Utc::now(),
+        };
+        (record, output)
+    }
+}
+
+/// Conversation so far, as the prompt of the next request
+fn transcript(log: &ConversationLog) -> String {
+    let messages: Vec<&ConversationMessage> = log.messages.iter().filter(|m| !m.is_annotation()).collect();
+    if let [only] = messages.as_slice() {
+        return only.content.clone();
+    }
+    let mut out = String::new();
+    for message in messages {
+        let _ = write!(out, "{}: {}\n\n", message.role, message.content.trim_end());
+    }
+    out
+}
+
+/// The tool a reply asks for, if it is a tool call
+fn tool_call(reply: &str) -> Option<(String, Value)> {
+    let value = crate::eval::extract_json(reply)?;
+    let name = value.get("tool")?.as_str()?.to_string();
+    let arguments = value.get("arguments").cloned().unwrap_or(Value::Null);
+    Some((name, arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picode_llm::{ChatChoice, ChatMessage, ChatRequest, ChatResponse, LlmProvider, ModelInfo, TokenUsage};
+
+    /// Calls the `add` tool until it succeeds, then answers with its result
+    struct ToolUser;
+
+    #[async_trait]
+    impl LlmProvider for ToolUser {
+        fn name(&self) -> &'static str {
+            "tool-user"
+        }
+
+        async fn health_check(&self) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn complete(&self, _request: picode_llm::CompletionRequest) -> anyhow::Result<picode_llm::CompletionResponse> {
+            anyhow::bail!("unsupported")
+        }
+
+        async fn chat(&self, request: ChatRequest) -> anyhow::Result<ChatResponse> {
+            let prompt = &request.messages.last().unwrap().content;
+            let content = match prompt.rsplit_once("Result of tool add:\n") {
+                Some((_, result)) if !result.starts_with("error:") => format!("The sum is {}", result.trim()),
+                _ => r#"{"tool": "add", "arguments": {"a": 2, "b": 3}}"#.to_string(),
+            };
+            Ok(ChatResponse {
+                choices: vec![ChatChoice {
+                    message: ChatMessage { role: "assistant".to_string(), content },
+                    finish_reason: "stop".to_string(),
+                }],
+                usage: TokenUsage { prompt_tokens: 1, completion_tokens: 1, total_tokens: 2 },
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn get_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    struct Add;
+
+    #[async_trait]
+    impl Tool for Add {
+        fn name(&self) -> &str {
+            "add"
+        }
+
+        fn description(&self) -> &str {
+            "Add two numbers."
+        }
+
+        async fn call(&self, arguments: Value) -> Result<Value> {
+            let number = |key| arguments.get(key).and_then(Value::as_i64).unwrap_or_default();
+            Ok(serde_json::json!(number("a") + number("b")))
+        }
+    }
+
+    fn engine(max_tool_steps: usize) -> Engine {
+        Engine::builder(Config::default())
+            .with_assistant(Assistant::with_provider("tool-user", Box::new(ToolUser), "test-1"))
+            .with_workspace(std::env::temp_dir())
+            .with_max_tool_steps(max_tool_steps)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn messages_run_tools_and_publish_events() {
+        let engine = engine(DEFAULT_MAX_TOOL_STEPS);
+        engine.register_tool(Add).await;
+        let mut events = engine.subscribe();
+
+        let session = engine.create_session("calc").await.unwrap();
+        let reply = engine.send_message(&session, "What is 2 + 3?").await.unwrap();
+        assert_eq!(reply.text, "The sum is 5");
+        assert_eq!(reply.tool_calls.len(), 1);
+        assert_eq!(reply.tool_calls[0].arguments["b"], 3);
+
+        let log = engine.conversation(&session).await.unwrap();
+        assert_eq!(log.messages.len(), 4);
+        assert_eq!(engine.session_name(&session).await.as_deref(), Some("calc"));
+
+        let mut kinds = Vec::new();
+        while let Ok(envelope) = events.try_recv() {
+            kinds.push(envelope.event.event_type());
+        }
+        assert_eq!(kinds.first(), Some(&"session_created"));
+        assert!(kinds.contains(&"command_completed"));
+        assert_eq!(kinds.last(), Some(&"llm_response_received"));
+    }
+
+    #[tokio::test]
+    async fn unknown_sessions_and_tools_are_reported() {
+        let engine = engine(1);
+        let missing = engine.send_message(&SessionId::new(), "hi").await.unwrap_err();
+        assert!(matches!(missing, PiCodeError::NotFound(_)));
+
+        // Without the tool registered the model keeps asking for it
+        let session = engine.create_session("calc").await.unwrap();
+        let err = engine.send_message(&session, "What is 2 + 3?").await.unwrap_err();
+        assert!(err.to_string().contains("tool call limit of 1"));
+        let log = engine.close_session(&session).await.unwrap();
+        assert!(log.messages[2].content.contains("no tool named 'add'"));
+        assert!(engine.conversation(&session).await.is_none());
+    }
+}
@@ -0,0 +1,460 @@
+//! `picode eval` - golden prompt tests
+//!
+//! An eval suite is a YAML file of cases: a prompt, the tools the model may
+//! use and assertions on the answer (substrings, regular expressions, a JSON
+//! schema, files that must exist afterwards). Cases run against real
+//! providers or against the `mock` provider, which answers with each case's
+//! `mock_response` so suites can be checked offline. Results are summarized
+//! on the terminal and can be written as JUnit XML, and any failure makes
+//! the command fail, so prompt changes can be gated in CI.
+//!
+//! ```yaml
+//! cases:
+//!   - name: explains-question-mark
+//!     prompt: What does the `?` operator do in Rust? One sentence.
+//!     assertions:
+//!       - type: regex
+//!         pattern: "(?i)error|result"
+//!     mock_response: It returns early with the error of a Result.
+//! ```
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Provider name that answers with each case's `mock_response`
+pub const MOCK_PROVIDER: &str = "mock";
+
+const EVAL_SYSTEM_PROMPT: &str = "You are a helpful software engineering assistant.";
+
+/// A check on a case's answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion {
+    Contains { value: String },
+    NotContains { value: String },
+    Regex { pattern: String },
+    /// The answer (or its first fenced JSON block) validates against the schema
+    JsonSchema { schema: serde_json::Value },
+    /// A file exists, relative to the workspace root, after the case ran
+    FileCreated { path: PathBuf },
+}
+
+impl Assertion {
+    /// `Err` explains why the answer fails the assertion
+    pub fn check(&self, output: &str, workspace_root: &Path) -> std::result::Result<(), String> {
+        match self {
+            Assertion::Contains { value } if !output.contains(value.as_str()) => {
+                Err(format!("expected the answer to contain {:?}", value))
+            }
+            Assertion::NotContains { value } if output.contains(value.as_str()) => {
+                Err(format!("expected the answer not to contain {:?}", value))
+            }
+            Assertion::Regex { pattern } => {
+                let regex = regex::Regex::new(pattern).map_err(|e| format!("invalid regex {:?}: {}", pattern, e))?;
+                if regex.is_match(output) {
+                    Ok(())
+                } else {
+                    Err(format!("expected the answer to match /{}/", pattern))
+                }
+            }
+            Assertion::JsonSchema { schema } => {
+                let compiled = jsonschema::JSONSchema::compile(schema).map_err(|e| format!("invalid schema: {}", e))?;
+                let instance = extract_json(output).ok_or_else(|| "expected a JSON answer".to_string())?;
+                let result = compiled.validate(&instance).map_err(|errors| {
+                    let errors: Vec<String> = errors.map(|e| format!("{} at {}", e, e.instance_path)).collect();
+                    format!("JSON does not match the schema: {}", errors.join("; "))
+                });
+                result
+            }
+            Assertion::FileCreated { path } if !workspace_root.join(path).exists() => {
+                Err(format!("expected {} to exist", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The answer as JSON: the whole text, else the first fenced block
+pub(crate) fn extract_json(output: &str) -> Option<serde_json::Value> {
+    if let Ok(value) = serde_json::from_str(output.trim()) {
+        return Some(value);
+    }
+    let start = output.find("```")?;
+    let block = &output[start + 3..];
+    let block = block.split_once('\n').map_or(block, |(_, rest)| rest);
+    let end = block.find("```")?;
+    serde_json::from_str(block[..end].trim()).ok()
+}
+
+/// One golden prompt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalCase {
+    pub name: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    /// Tools the model may use; listed in the system prompt
+    #[serde(default)]
+    pub tools: Vec<String>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    /// Answer given by the `mock` provider
+    #[serde(default)]
+    pub mock_response: Option<String>,
+}
+
+impl EvalCase {
+    fn system_prompt(&self, suite_system: Option<&str>) -> String {
+        let mut system = self
+            .system
+            .as_deref()
+            .or(suite_system)
+            .unwrap_or(EVAL_SYSTEM_PROMPT)
+            .to_string();
+        if !self.tools.is_empty() {
+            system.push_str(&format!(
+                "\n\nYou may only use these tools: {}. Do not use any other tool.",
+                self.tools.join(", ")
+            ));
+        }
+        system
+    }
+}
+
+/// Cases loaded from a suite file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalSuite {
+    /// System prompt for cases that do not set their own
+    #[serde(default)]
+    pub system: Option<String>,
+    pub cases: Vec<EvalCase>,
+}
+
+impl EvalSuite {
+    pub fn parse(content: &str) -> Result<Self> {
+        let suite: Self = serde_yaml::from_str(content)?;
+        if suite.cases.is_empty() {
+            return Err(PiCodeError::Parse("eval suite contains no cases".to_string()));
+        }
+        Ok(suite)
+    }
+
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path).await?;
+        Self::parse(&content).map_err(|e| PiCodeError::Parse(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Outcome of one case against one target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseResult {
+    pub case: String,
+    pub target: String,
+    pub duration: Duration,
+    pub output: String,
+    /// Failed assertions
+    pub failures: Vec<String>,
+    /// The request itself failed
+    pub error: Option<String>,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.failures.is_empty()
+    }
+}
+
+/// All results of a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalReport {
+    pub results: Vec<CaseResult>,
+}
+
+impl EvalReport {
+    pub fn failed(&self) -> usize {
+        self.results.iter().filter(|result| !result.passed()).count()
+    }
+
+    /// One line per case, failures explained, then a total
+    pub fn render_summary(&self) -> String {
+        let mut out = String::new();
+        for result in &self.results {
+            let mark = if result.passed() { "✅" } else { "❌" };
+            out.push_str(&format!(
+                "{} {} [{}] ({:.1}s)\n",
+                mark,
+                result.case,
+                result.target,
+                result.duration.as_secs_f64()
+            ));
+            if let Some(error) = &result.error {
+                out.push_str(&format!("   error: {}\n", error));
+            }
+            for failure in &result.failures {
+                out.push_str(&format!("   {}\n", failure));
+            }
+        }
+        out.push_str(&format!(
+            "\n{} passed, {} failed\n",
+            self.results.len() - self.failed(),
+            self.failed()
+        ));
+        out
+    }
+
+    /// JUnit XML with one test suite per target
+    pub fn to_junit(&self, suite_name: &str) -> String {
+        let mut targets: Vec<&str> = self.results.iter().map(|result| result.target.as_str()).collect();
+        targets.dedup();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&format!(
+            "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            xml_escape(suite_name),
+            self.results.len(),
+            self.failed()
+        ));
+        for target in targets {
+            let results: Vec<&CaseResult> = self.results.iter().filter(|r| r.target == target).collect();
+            let failures = results.iter().filter(|r| r.error.is_none() && !r.failures.is_empty()).count();
+            let errors = results.iter().filter(|r| r.error.is_some()).count();
+            let time: f64 = results.iter().map(|r| r.duration.as_secs_f64()).sum();
+            out.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+                xml_escape(target),
+                results.len(),
+                failures,
+                errors,
+                time
+            ));
+            for result in results {
+                out.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                    xml_escape(&result.case),
+                    xml_escape(target),
+                    result.duration.as_secs_f64()
+                ));
+                if result.passed() {
+                    out.push_str("/>\n");
+                    continue;
+                }
+                out.push_str(">\n");
+                match &result.error {
+                    Some(error) => out.push_str(&format!(
+                        "      <error message=\"{}\"/>\n",
+                        xml_escape(error)
+                    )),
+                    None => out.push_str(&format!(
+                        "      <failure message=\"{}\">{}</failure>\n",
+                        xml_escape(&result.failures[0]),
+                        xml_escape(&result.failures.join("\n"))
+                    )),
+                }
+                out.push_str(&format!("      <system-out>{}</system-out>\n", xml_escape(&result.output)));
+                out.push_str("    </testcase>\n");
+            }
+            out.push_str("  </testsuite>\n");
+        }
+        out.push_str("</testsuites>\n");
+        out
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t' | '\r'))
+        .fold(String::with_capacity(text.len()), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                '\'' => out.push_str("&apos;"),
+                c => out.push(c),
+            }
+            out
+        })
+}
+
+/// Where answers come from
+enum Target {
+    Mock,
+    Provider(Box<Assistant>),
+}
+
+impl Target {
+    fn resolve(config: &Config, target: &str) -> Result<Self> {
+        if target == MOCK_PROVIDER {
+            return Ok(Self::Mock);
+        }
+        let assistant = match target.split_once('/') {
+            Some((provider, model)) => Assistant::for_provider(config, provider)?.with_model(model),
+            None => Assistant::for_provider(config, target)?,
+        };
+        Ok(Self::Provider(Box::new(assistant)))
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Target::Mock => MOCK_PROVIDER.to_string(),
+            Target::Provider(assistant) => format!("{}/{}", assistant.provider_name(), assistant.model()),
+        }
+    }
+
+    async fn answer(&self, case: &EvalCase, system: &str) -> Result<String> {
+        match self {
+            Target::Mock => case
+                .mock_response
+                .clone()
+                .ok_or_else(|| PiCodeError::InvalidCommand(format!("case '{}' has no mock_response", case.name))),
+            Target::Provider(assistant) => assistant.ask(system, &case.prompt, case.max_tokens).await,
+        }
+    }
+}
+
+async fn run_case(target: &Target, suite: &EvalSuite, case: &EvalCase, workspace_root: &Path) -> CaseResult {
+    let started = Instant::now();
+    let answer = target.answer(case, &case.system_prompt(suite.system.as_deref())).await;
+    let duration = started.elapsed();
+    let (output, error) = match answer {
+        Ok(output) => (output, None),
+        Err(e) => (String::new(), Some(e.to_string())),
+    };
+    let failures = if error.is_some() {
+        Vec::new()
+    } else {
+        case.assertions
+            .iter()
+            .filter_map(|assertion| assertion.check(&output, workspace_root).err())
+            .collect()
+    };
+    CaseResult { case: case.name.clone(), target: target.label(), duration, output, failures, error }
+}
+
+/// Options of `picode eval`
+#[derive(Debug, Clone)]
+pub struct EvalOptions {
+    pub suite: PathBuf,
+    /// `provider`, `provider/model` or `mock`; empty means the default provider
+    pub providers: Vec<String>,
+    /// Only run cases whose name contains this
+    pub filter: Option<String>,
+    pub junit: Option<PathBuf>,
+}
+
+/// Run a suite, print the summary and fail when any case failed
+pub async fn run(options: EvalOptions, config: Config) -> Result<()> {
+    let suite = EvalSuite::load(&options.suite).await?;
+    let workspace_root = match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    let targets = if options.providers.is_empty() {
+        vec![config.llm.default_provider.clone()]
+    } else {
+        options.providers.clone()
+    };
+
+    let mut report = EvalReport::default();
+    for name in &targets {
+        let target = Target::resolve(&config, name)?;
+        println!("🧪 Evaluating {} against {}", options.suite.display(), target.label());
+        for case in &suite.cases {
+            if options.filter.as_ref().is_some_and(|filter| !case.name.contains(filter.as_str())) {
+                continue;
+            }
+            info!("Eval case {} on {}", case.name, target.label());
+            report.results.push(run_case(&target, &suite, case, &workspace_root).await);
+        }
+    }
+
+    print!("\n{}", report.render_summary());
+    if let Some(path) = &options.junit {
+        let name = options.suite.file_stem().map_or_else(|| "eval".to_string(), |s| s.to_string_lossy().into_owned());
+        tokio::fs::write(path, report.to_junit(&name)).await?;
+        println!("JUnit report written to {}", path.display());
+    }
+
+    match report.failed() {
+        0 => Ok(()),
+        failed => Err(PiCodeError::Internal(format!("{} of {} eval cases failed", failed, report.results.len()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = r#"
+cases:
+  - name: json-answer
+    prompt: Describe the crate as JSON
+    tools: [read_file]
+    assertions:
+      - type: contains
+        value: picode
+      - type: json_schema
+        schema:
+          type: object
+          required: [name]
+    mock_response: "Here it is:\n```json\n{\"name\": \"picode\"}\n```"
+  - name: wrong-answer
+    prompt: Say hello
+    assertions:
+      - type: regex
+        pattern: "(?i)^hello"
+      - type: not_contains
+        value: goodbye
+      - type: file_created
+        path: hello.txt
+    mock_response: goodbye
+"#;
+
+    #[tokio::test]
+    async fn mock_run_checks_assertions() {
+        let suite = EvalSuite::parse(SUITE).unwrap();
+        assert!(suite.cases[0].system_prompt(None).contains("only use these tools: read_file"));
+
+        let root = tempfile::tempdir().unwrap();
+        let mut report = EvalReport::default();
+        for case in &suite.cases {
+            report.results.push(run_case(&Target::Mock, &suite, case, root.path()).await);
+        }
+        assert!(report.results[0].passed());
+        assert_eq!(report.results[1].failures.len(), 3);
+        assert_eq!(report.failed(), 1);
+        assert!(report.render_summary().ends_with("1 passed, 1 failed\n"));
+
+        let case = EvalCase { mock_response: None, ..suite.cases[1].clone() };
+        let result = run_case(&Target::Mock, &suite, &case, root.path()).await;
+        assert!(result.error.unwrap().contains("no mock_response"));
+    }
+
+    #[test]
+    fn junit_report_escapes_and_counts() {
+        let result = |case: &str, failures: Vec<&str>| CaseResult {
+            case: case.to_string(),
+            target: "mock".to_string(),
+            duration: Duration::from_millis(250),
+            output: "<answer>".to_string(),
+            failures: failures.into_iter().map(str::to_string).collect(),
+            error: None,
+        };
+        let report = EvalReport {
+            results: vec![result("ok", vec![]), result("bad & wrong", vec!["expected \"x\""])],
+        };
+        let xml = report.to_junit("golden");
+        assert!(xml.contains("<testsuites name=\"golden\" tests=\"2\" failures=\"1\">"));
+        assert!(xml.contains("<testsuite name=\"mock\" tests=\"2\" failures=\"1\" errors=\"0\" time=\"0.500\">"));
+        assert!(xml.contains("<testcase name=\"ok\" classname=\"mock\" time=\"0.250\"/>"));
+        assert!(xml.contains("<testcase name=\"bad &amp; wrong\""));
+        assert!(xml.contains("<failure message=\"expected &quot;x&quot;\">"));
+        assert!(xml.contains("<system-out>&lt;answer&gt;</system-out>"));
+    }
+}
@@ -5,12 +5,22 @@
 
 use crate::config::Config;
 use crate::error::Result;
+use crate::print::PipedInput;
+use crate::tasks::WorkspaceTasks;
 use tracing::{info, error};
 
 /// Execute a single command with the specified provider
 /// 
-/// This is the main entry point for non-interactive command execution
-pub async fn run_command(command: String, provider: Option<String>, config: Config) -> Result<()> {
+/// This is the main entry point for non-interactive command execution.
+/// Piped stdin, if any, is attached as context. With `suggest`, workspace
+/// tasks matching the command are listed first.
+pub async fn run_command(
+    command: String,
+    provider: Option<String>,
+    stdin: Option<PipedInput>,
+    suggest: bool,
+    config: Config,
+) -> Result<()> {
     info!("Executing command: '{}' with provider: {:?}", command, provider);
     
     // Display execution context
@@ -23,9 +33,29 @@ pub async fn run_command(command: String, provider: Option<String>, config: Conf
         println!("Provider: default");
     }
     
+    if let Some(ref stdin) = stdin {
+        println!("Context: {}", stdin.describe());
+    }
+    
     println!("Configuration: {:?}", config);
     println!();
     
+    if suggest {
+        let root = match &config.workspace.root_dir {
+            Some(root) => root.clone(),
+            None => std::env::current_dir()?,
+        };
+        let tasks = WorkspaceTasks::load(&config, &root).await?;
+        let suggestions = tasks.suggest(&command);
+        if !suggestions.is_empty() {
+            println!("💡 Workspace tasks for this:");
+            for (name, task) in suggestions.iter().take(3) {
+                println!("  picode task run {:<10} {}", name, task.description.as_deref().unwrap_or(&task.command));
+            }
+            println!();
+        }
+    }
+    
     // Basic command processing
     match command.as_str() {
         "help" => {
@@ -38,14 +68,11 @@ pub async fn run_command(command: String, provider: Option<String>, config: Conf
         },
         "analyze" => {
             println!("📊 Project Analysis:");
-            println!("  Workspace: {:?}", config.workspace);
-            
-            // TODO: Implement actual project analysis
-            // TODO: Scan for source files
-            // TODO: Detect languages and frameworks
-            // TODO: Generate project summary
-            
-            println!("  Analysis complete (basic implementation)");
+            let stats = crate::stats::workspace_stats(&crate::stats::workspace_root(&config)?).await?;
+            print!("{}", stats.render());
+            if let Some(ref stdin) = stdin {
+                println!("  Piped input:\n{}", stdin.summary.text);
+            }
         },
         "status" => {
             println!("📈 Project Status:");
@@ -0,0 +1,299 @@
+//! `picode explain` - non-interactive code explanations
+//!
+//! Loads a file or line range, gathers minimal surrounding context (imports
+//! and the definitions of symbols the excerpt refers to), asks the model for
+//! an explanation at the requested depth and prints it followed by
+//! `path:line` cross-references.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_cli::ExplainDepth;
+use picode_core::{ContentCache, NativeFileSystem, SymbolIndex};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Maximum number of import lines included as context
+const MAX_IMPORTS: usize = 30;
+
+/// File and optional inclusive 1-based line range to explain
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainTarget {
+    pub path: PathBuf,
+    pub lines: Option<(usize, usize)>,
+}
+
+impl ExplainTarget {
+    /// Parse `path`, `path:LINE` or `path:START-END`
+    pub fn parse(target: &str) -> Result<Self> {
+        if let Some((path, range)) = target.rsplit_once(':') {
+            if !range.is_empty() && range.chars().all(|c| c.is_ascii_digit() || c == '-') {
+                let (start, end) = match range.split_once('-') {
+                    Some((start, end)) => (parse_line(start)?, parse_line(end)?),
+                    None => {
+                        let line = parse_line(range)?;
+                        (line, line)
+                    }
+                };
+                if start == 0 || end < start {
+                    return Err(PiCodeError::Parse(format!("invalid line range '{}'", range)));
+                }
+                return Ok(Self {
+                    path: PathBuf::from(path),
+                    lines: Some((start, end)),
+                });
+            }
+        }
+
+        Ok(Self {
+            path: PathBuf::from(target),
+            lines: None,
+        })
+    }
+}
+
+fn parse_line(value: &str) -> Result<usize> {
+    value
+        .parse()
+        .map_err(|_| PiCodeError::Parse(format!("invalid line number '{}'", value)))
+}
+
+/// A symbol defined in the file outside the explained range
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolReference {
+    pub name: String,
+    /// 1-based line of the definition
+    pub line: usize,
+    pub signature: String,
+}
+
+/// Excerpt plus the context sent to the model
+#[derive(Debug, Clone)]
+pub struct ExplainContext {
+    pub path: PathBuf,
+    pub start_line: usize,
+    pub excerpt: Vec<String>,
+    pub imports: Vec<String>,
+    pub references: Vec<SymbolReference>,
+}
+
+impl ExplainContext {
+    /// Gather the excerpt and its context from file content
+    pub fn gather(target: &ExplainTarget, content: &str) -> Result<Self> {
+        let lines: Vec<&str> = content.lines().collect();
+        let (start, end) = target.lines.unwrap_or((1, lines.len().max(1)));
+        if start > lines.len().max(1) {
+            return Err(PiCodeError::Parse(format!(
+                "{} has only {} lines",
+                target.path.display(),
+                lines.len()
+            )));
+        }
+        let end = end.min(lines.len());
+        let excerpt: Vec<String> = lines
+            .get(start - 1..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+
+        let in_range = |line: usize| line >= start && line <= end;
+
+        let imports = lines
+            .iter()
+            .enumerate()
+            .filter(|(i, line)| !in_range(i + 1) && is_import(line))
+            .map(|(_, line)| line.trim().to_string())
+            .take(MAX_IMPORTS)
+            .collect();
+
+        let mut index = SymbolIndex::new();
+        index.index_file(&target.path, content);
+        let mut references: Vec<SymbolReference> = identifiers(&excerpt.join("\n"))
+            .into_iter()
+            .flat_map(|word| index.lookup(word).iter().map(move |location| (word, location)))
+            .filter(|(_, location)| !in_range(location.line))
+            .map(|(name, location)| SymbolReference {
+                name: name.to_string(),
+                line: location.line,
+                signature: location.signature.clone(),
+            })
+            .collect();
+        references.sort_by_key(|r| r.line);
+
+        Ok(Self {
+            path: target.path.clone(),
+            start_line: start,
+            excerpt,
+            imports,
+            references,
+        })
+    }
+
+    /// Build the system and user prompts for the given depth
+    pub fn prompts(&self, depth: ExplainDepth) -> (String, String) {
+        let detail = match depth {
+            ExplainDepth::Brief => "Give a short summary (at most a few sentences) of what the code does.",
+            ExplainDepth::Standard => {
+                "Explain the purpose of the code, its control flow and any notable details."
+            }
+            ExplainDepth::Deep => {
+                "Walk through the code step by step, covering edge cases, error handling and possible pitfalls."
+            }
+        };
+        let system = format!(
+            "You are a senior engineer explaining code to a colleague. {} \
+             When referring to specific lines, cite them as `{}:LINE`.",
+            detail,
+            self.path.display()
+        );
+
+        let mut prompt = format!("File: {}\n\n", self.path.display());
+        if !self.imports.is_empty() {
+            prompt.push_str("Imports:\n```\n");
+            prompt.push_str(&self.imports.join("\n"));
+            prompt.push_str("\n```\n\n");
+        }
+        if !self.references.is_empty() {
+            prompt.push_str("Referenced definitions:\n```\n");
+            for reference in &self.references {
+                prompt.push_str(&format!("{:>5} | {}\n", reference.line, reference.signature));
+            }
+            prompt.push_str("```\n\n");
+        }
+        prompt.push_str("Code to explain:\n```\n");
+        for (offset, line) in self.excerpt.iter().enumerate() {
+            prompt.push_str(&format!("{:>5} | {}\n", self.start_line + offset, line));
+        }
+        prompt.push_str("```\n");
+
+        (system, prompt)
+    }
+
+    fn max_tokens(depth: ExplainDepth) -> u32 {
+        match depth {
+            ExplainDepth::Brief => 300,
+            ExplainDepth::Standard => 1000,
+            ExplainDepth::Deep => 2500,
+        }
+    }
+}
+
+fn is_import(line: &str) -> bool {
+    let line = line.trim_start();
+    ["use ", "pub use ", "import ", "from ", "#include", "require(", "extern crate "]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+        || (line.starts_with("const ") && line.contains("require("))
+}
+
+fn identifiers(text: &str) -> BTreeSet<&str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| word.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_'))
+        .collect()
+}
+
+/// Run `picode explain`
+pub async fn run(target: &str, depth: ExplainDepth, config: Config) -> Result<()> {
+    let target = ExplainTarget::parse(target)?;
+    info!("Explaining {:?}", target);
+
+    let cached = ContentCache::global()
+        .read(&NativeFileSystem, &target.path)
+        .await
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                PiCodeError::NotFound(target.path.display().to_string())
+            } else {
+                e.into()
+            }
+        })?;
+    let context = ExplainContext::gather(&target, &cached.content)?;
+
+    let assistant = Assistant::from_config(&config)?;
+    let (system, prompt) = context.prompts(depth);
+    println!("📖 Explaining {} with {}...\n", describe(&target), assistant.model());
+
+    let explanation = assistant
+        .ask(&system, &prompt, Some(ExplainContext::max_tokens(depth)))
+        .await?;
+    println!(
+        "{}",
+        picode_core::ansi::sanitize(&explanation, config.ui.ansi_policy)
+    );
+
+    if !context.references.is_empty() {
+        println!("\n🔗 References:");
+        for reference in &context.references {
+            println!("  {}  {}", location(&context.path, reference.line), reference.name);
+        }
+    }
+
+    Ok(())
+}
+
+fn describe(target: &ExplainTarget) -> String {
+    match target.lines {
+        Some((start, end)) if start == end => location(&target.path, start),
+        Some((start, end)) => format!("{}:{}-{}", target.path.display(), start, end),
+        None => target.path.display().to_string(),
+    }
+}
+
+fn location(path: &Path, line: usize) -> String {
+    format!("{}:{}", path.display(), line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "use std::collections::HashMap;\n\
+                          \n\
+                          pub struct Cache {\n\
+                          \x20   map: HashMap<String, String>,\n\
+                          }\n\
+                          \n\
+                          fn helper(x: u32) -> u32 { x * 2 }\n\
+                          \n\
+                          pub fn run(cache: &Cache) -> u32 {\n\
+                          \x20   helper(cache.map.len() as u32)\n\
+                          }\n";
+
+    #[test]
+    fn parses_targets() {
+        assert_eq!(ExplainTarget::parse("src/lib.rs").unwrap().lines, None);
+        assert_eq!(ExplainTarget::parse("src/lib.rs:12").unwrap().lines, Some((12, 12)));
+
+        let target = ExplainTarget::parse("src/lib.rs:10-40").unwrap();
+        assert_eq!(target.path, PathBuf::from("src/lib.rs"));
+        assert_eq!(target.lines, Some((10, 40)));
+
+        assert!(ExplainTarget::parse("src/lib.rs:40-10").is_err());
+        assert!(ExplainTarget::parse("src/lib.rs:0").is_err());
+    }
+
+    #[test]
+    fn gathers_imports_and_referenced_definitions() {
+        let target = ExplainTarget::parse("src/cache.rs:9-11").unwrap();
+        let context = ExplainContext::gather(&target, SOURCE).unwrap();
+
+        assert_eq!(context.excerpt.len(), 3);
+        assert_eq!(context.imports, vec!["use std::collections::HashMap;"]);
+        let names: Vec<_> = context.references.iter().map(|r| (r.name.as_str(), r.line)).collect();
+        assert_eq!(names, vec![("Cache", 3), ("helper", 7)]);
+    }
+
+    #[test]
+    fn prompts_number_lines_and_follow_depth() {
+        let target = ExplainTarget::parse("src/cache.rs:9-11").unwrap();
+        let context = ExplainContext::gather(&target, SOURCE).unwrap();
+
+        let (system, prompt) = context.prompts(ExplainDepth::Brief);
+        assert!(system.contains("short summary"));
+        assert!(system.contains("`src/cache.rs:LINE`"));
+        assert!(prompt.contains("    9 | pub fn run(cache: &Cache) -> u32 {"));
+        assert!(prompt.contains("    7 | fn helper(x: u32) -> u32 { x * 2 }"));
+    }
+}
@@ -0,0 +1,439 @@
+//! Issue tracker integration
+//!
+//! Issues are read from GitHub or GitLab (including self-hosted instances)
+//! through their REST APIs. An issue can be named by its URL, by number in
+//! the configured repository or the one behind the `origin` remote, or as
+//! `owner/repo#42`. The token is read from `[forge] token_env`, or from
+//! `GITHUB_TOKEN` / `GITLAB_TOKEN`; public issues need none.
+
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// Which API a forge speaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+impl ForgeKind {
+    /// Guess the forge from a host name
+    fn for_host(host: &str) -> Self {
+        if host.contains("gitlab") {
+            Self::GitLab
+        } else {
+            Self::GitHub
+        }
+    }
+
+    fn default_host(self) -> &'static str {
+        match self {
+            Self::GitHub => "github.com",
+            Self::GitLab => "gitlab.com",
+        }
+    }
+
+    fn token_env(self) -> &'static str {
+        match self {
+            Self::GitHub => "GITHUB_TOKEN",
+            Self::GitLab => "GITLAB_TOKEN",
+        }
+    }
+}
+
+/// `[forge]` section of the configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForgeConfig {
+    /// `github` or `gitlab`; guessed from the host when unset
+    #[serde(default)]
+    pub kind: Option<ForgeKind>,
+
+    /// `owner/repo` (or `group/subgroup/project`); taken from the `origin`
+    /// remote when unset
+    #[serde(default)]
+    pub repository: Option<String>,
+
+    /// API base URL, for self-hosted forges
+    #[serde(default)]
+    pub api_url: Option<String>,
+
+    /// Environment variable holding the API token
+    #[serde(default)]
+    pub token_env: Option<String>,
+
+    /// Prefix of branches created for issues
+    #[serde(default = "default_branch_prefix")]
+    pub branch_prefix: String,
+
+    /// Session template `picode work-on` opens when it exists
+    #[serde(default = "default_template")]
+    pub template: String,
+}
+
+fn default_branch_prefix() -> String {
+    "issue/".to_string()
+}
+
+fn default_template() -> String {
+    "work-on".to_string()
+}
+
+impl Default for ForgeConfig {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            repository: None,
+            api_url: None,
+            token_env: None,
+            branch_prefix: default_branch_prefix(),
+            template: default_template(),
+        }
+    }
+}
+
+/// An issue as named on the command line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IssueRef {
+    pub host: Option<String>,
+    pub repository: Option<String>,
+    pub number: u64,
+}
+
+impl IssueRef {
+    /// Parse an issue URL, `42`, `#42` or `owner/repo#42`
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let invalid = || PiCodeError::InvalidCommand(format!("not an issue URL or number: {}", input));
+        if let Some(rest) = input.strip_prefix("https://").or_else(|| input.strip_prefix("http://")) {
+            let (host, path) = rest.split_once('/').ok_or_else(invalid)?;
+            let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+            let at = segments.iter().position(|segment| *segment == "issues").ok_or_else(invalid)?;
+            let number = segments.get(at + 1).and_then(|n| n.parse().ok()).ok_or_else(invalid)?;
+            // GitLab puts `/-/` between the project and the issue
+            let repository = segments[..at].iter().filter(|segment| **segment != "-").copied().collect::<Vec<_>>();
+            if repository.len() < 2 {
+                return Err(invalid());
+            }
+            return Ok(Self { host: Some(host.to_string()), repository: Some(repository.join("/")), number });
+        }
+        let (repository, number) = match input.rsplit_once('#') {
+            Some((repository, number)) => ((!repository.is_empty()).then(|| repository.to_string()), number),
+            None => (None, input),
+        };
+        let number = number.parse().map_err(|_| invalid())?;
+        Ok(Self { host: None, repository, number })
+    }
+}
+
+/// An issue fetched from the forge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub url: String,
+    pub labels: Vec<String>,
+}
+
+impl Issue {
+    /// Items of the body's acceptance criteria section, or its task list
+    /// when there is no such section
+    pub fn acceptance_criteria(&self) -> Vec<String> {
+        let mut in_section = false;
+        let mut section = Vec::new();
+        let mut tasks = Vec::new();
+        for line in self.body.lines() {
+            let trimmed = line.trim();
+            let plain = trimmed.trim_start_matches('#').trim_matches(|c: char| c == '*' || c == '_' || c == ':' || c.is_whitespace());
+            let is_heading = trimmed.starts_with('#') || (trimmed.starts_with("**") && trimmed.ends_with("**"));
+            if is_heading || plain.eq_ignore_ascii_case("acceptance criteria") {
+                in_section = plain.to_ascii_lowercase().starts_with("acceptance criteria");
+                continue;
+            }
+            let (item, is_task) = list_item(trimmed);
+            if let Some(item) = item {
+                if in_section {
+                    section.push(item.to_string());
+                }
+                if is_task {
+                    tasks.push(item.to_string());
+                }
+            }
+        }
+        if section.is_empty() {
+            tasks
+        } else {
+            section
+        }
+    }
+
+    /// Branch name for this issue: the prefix, number and a slug of the title
+    pub fn branch_name(&self, prefix: &str) -> String {
+        let mut slug = String::new();
+        for c in self.title.chars().flat_map(char::to_lowercase) {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        let mut slug = slug.trim_end_matches('-').to_string();
+        if slug.len() > 48 {
+            slug.truncate(48);
+            if let Some(cut) = slug.rfind('-').filter(|&cut| cut > 24) {
+                slug.truncate(cut);
+            }
+        }
+        if slug.is_empty() {
+            format!("{}{}", prefix, self.number)
+        } else {
+            format!("{}{}-{}", prefix, self.number, slug)
+        }
+    }
+
+    /// The issue as pinned into a session: description and acceptance criteria
+    pub fn memory(&self) -> String {
+        let mut out = format!("Issue #{}: {}\n{}\n", self.number, self.title, self.url);
+        if !self.labels.is_empty() {
+            let _ = writeln!(out, "Labels: {}", self.labels.join(", "));
+        }
+        let _ = writeln!(out, "\nDescription:\n{}", self.body.trim());
+        let criteria = self.acceptance_criteria();
+        if !criteria.is_empty() {
+            out.push_str("\nAcceptance criteria:\n");
+            for item in criteria {
+                let _ = writeln!(out, "- {}", item);
+            }
+        }
+        out
+    }
+}
+
+/// A Markdown list item's text, and whether it is a task (`- [ ]`)
+fn list_item(line: &str) -> (Option<&str>, bool) {
+    let rest = match line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        Some(rest) => rest,
+        None => match line.split_once(". ") {
+            Some((number, rest)) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => rest,
+            _ => return (None, false),
+        },
+    };
+    for task in ["[ ] ", "[x] ", "[X] "] {
+        if let Some(item) = rest.strip_prefix(task) {
+            return (Some(item.trim()), true);
+        }
+    }
+    (Some(rest.trim()), false)
+}
+
+/// Where and how to fetch an issue
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Endpoint {
+    kind: ForgeKind,
+    api_url: String,
+    repository: String,
+}
+
+impl Endpoint {
+    fn resolve(config: &ForgeConfig, issue: &IssueRef, remote: Option<(String, String)>) -> Result<Self> {
+        let (remote_host, remote_repository) = remote.unzip();
+        let host = issue.host.clone().or(remote_host);
+        let kind = config
+            .kind
+            .or_else(|| host.as_deref().map(ForgeKind::for_host))
+            .unwrap_or(ForgeKind::GitHub);
+        let host = host.unwrap_or_else(|| kind.default_host().to_string());
+        let repository = issue
+            .repository
+            .clone()
+            .or_else(|| config.repository.clone())
+            .or(remote_repository)
+            .ok_or_else(|| {
+                PiCodeError::InvalidCommand("no repository: set [forge] repository or add an `origin` remote".to_string())
+            })?;
+        let api_url = match (&config.api_url, kind) {
+            (Some(url), _) if issue.host.is_none() => url.trim_end_matches('/').to_string(),
+            (_, ForgeKind::GitHub) if host == "github.com" => "https://api.github.com".to_string(),
+            (_, ForgeKind::GitHub) => format!("https://{}/api/v3", host),
+            (_, ForgeKind::GitLab) => format!("https://{}/api/v4", host),
+        };
+        Ok(Self { kind, api_url, repository })
+    }
+
+    fn issue_url(&self, number: u64) -> String {
+        match self.kind {
+            ForgeKind::GitHub => format!("{}/repos/{}/issues/{}", self.api_url, self.repository, number),
+            ForgeKind::GitLab => format!(
+                "{}/projects/{}/issues/{}",
+                self.api_url,
+                self.repository.replace('/', "%2F"),
+                number
+            ),
+        }
+    }
+}
+
+/// Host and repository path of a git remote URL (`git@host:owner/repo.git`
+/// or `https://host/owner/repo`)
+fn parse_remote(url: &str) -> Option<(String, String)> {
+    let url = url.trim();
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => {
+            let (host, path) = rest.split_once('/')?;
+            (host.rsplit('@').next()?.split(':').next()?, path)
+        }
+        None => {
+            let (host, path) = url.split_once(':')?;
+            (host.rsplit('@').next()?, path)
+        }
+    };
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    (!host.is_empty() && path.contains('/')).then(|| (host.to_string(), path.to_string()))
+}
+
+/// Host and repository of the workspace's `origin` remote
+async fn origin(root: &Path) -> Option<(String, String)> {
+    let output = tokio::process::Command::new("git")
+        .args(["remote", "get-url", "origin"])
+        .current_dir(root)
+        .output()
+        .await
+        .ok()?;
+    output.status.success().then(|| parse_remote(&String::from_utf8_lossy(&output.stdout)))?
+}
+
+#[derive(Deserialize)]
+struct GitHubIssue {
+    number: u64,
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    labels: Vec<GitHubLabel>,
+}
+
+#[derive(Deserialize)]
+struct GitHubLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabIssue {
+    iid: u64,
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    web_url: String,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+/// Fetch an issue named by a URL, number or `owner/repo#number`
+pub async fn fetch_issue(config: &Config, root: &Path, issue: &str) -> Result<Issue> {
+    let issue = IssueRef::parse(issue)?;
+    let remote = if issue.host.is_none() { origin(root).await } else { None };
+    let endpoint = Endpoint::resolve(&config.forge, &issue, remote)?;
+    let token_env = config.forge.token_env.as_deref().unwrap_or(endpoint.kind.token_env());
+    let token = std::env::var(token_env).ok().filter(|token| !token.is_empty());
+
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("picode/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let mut request = client.get(endpoint.issue_url(issue.number));
+    request = match (endpoint.kind, &token) {
+        (ForgeKind::GitHub, Some(token)) => request.bearer_auth(token),
+        (ForgeKind::GitLab, Some(token)) => request.header("PRIVATE-TOKEN", token),
+        (_, None) => request,
+    };
+    if endpoint.kind == ForgeKind::GitHub {
+        request = request.header("Accept", "application/vnd.github+json");
+    }
+    let response = request.send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(PiCodeError::NotFound(format!(
+            "issue #{} in {}{}",
+            issue.number,
+            endpoint.repository,
+            if token.is_none() { format!(" (private? set {})", token_env) } else { String::new() }
+        )));
+    }
+    let response = response.error_for_status()?;
+    Ok(match endpoint.kind {
+        ForgeKind::GitHub => {
+            let issue: GitHubIssue = response.json().await?;
+            Issue {
+                number: issue.number,
+                title: issue.title,
+                body: issue.body.unwrap_or_default(),
+                url: issue.html_url,
+                labels: issue.labels.into_iter().map(|label| label.name).collect(),
+            }
+        }
+        ForgeKind::GitLab => {
+            let issue: GitLabIssue = response.json().await?;
+            Issue {
+                number: issue.iid,
+                title: issue.title,
+                body: issue.description.unwrap_or_default(),
+                url: issue.web_url,
+                labels: issue.labels,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_issue_references_and_remotes() {
+        let github = IssueRef::parse("https://github.com/pnocera/PiCode/issues/42").unwrap();
+        assert_eq!(github.host.as_deref(), Some("github.com"));
+        assert_eq!(github.repository.as_deref(), Some("pnocera/PiCode"));
+        assert_eq!(github.number, 42);
+        let gitlab = IssueRef::parse("https://gitlab.example.com/group/sub/app/-/issues/7").unwrap();
+        assert_eq!(gitlab.repository.as_deref(), Some("group/sub/app"));
+        assert_eq!(IssueRef::parse("#12").unwrap(), IssueRef { number: 12, ..IssueRef::default() });
+        assert_eq!(IssueRef::parse("o/r#3").unwrap().repository.as_deref(), Some("o/r"));
+        assert!(IssueRef::parse("https://github.com/o/r/pull/3").is_err());
+        assert!(IssueRef::parse("latest").is_err());
+
+        let remote = parse_remote("git@gitlab.example.com:group/app.git\n");
+        assert_eq!(remote.clone(), Some(("gitlab.example.com".to_string(), "group/app".to_string())));
+        assert_eq!(
+            parse_remote("https://token@github.com/o/r.git"),
+            Some(("github.com".to_string(), "o/r".to_string()))
+        );
+        let endpoint = Endpoint::resolve(&ForgeConfig::default(), &IssueRef::parse("7").unwrap(), remote).unwrap();
+        assert_eq!(endpoint.kind, ForgeKind::GitLab);
+        assert_eq!(endpoint.issue_url(7), "https://gitlab.example.com/api/v4/projects/group%2Fapp/issues/7");
+    }
+
+    #[test]
+    fn derives_branch_and_criteria() {
+        let issue = Issue {
+            number: 42,
+            title: "Crash when the config file is empty!".to_string(),
+            body: "Startup panics.\n\n- [ ] unrelated task\n\n## Acceptance criteria\n- [ ] empty config loads defaults\n* a warning is logged\n\n## Notes\n- not a criterion\n".to_string(),
+            url: "https://github.com/o/r/issues/42".to_string(),
+            labels: vec!["bug".to_string()],
+        };
+        assert_eq!(issue.branch_name("issue/"), "issue/42-crash-when-the-config-file-is-empty");
+        assert_eq!(issue.acceptance_criteria(), vec!["empty config loads defaults", "a warning is logged"]);
+        let memory = issue.memory();
+        assert!(memory.starts_with("Issue #42: Crash when the config file is empty!\n"));
+        assert!(memory.contains("Labels: bug\n"));
+        assert!(memory.ends_with("Acceptance criteria:\n- empty config loads defaults\n- a warning is logged\n"));
+
+        let tasks_only = Issue { body: "- [x] one\n- two\n1. [ ] three".to_string(), ..issue };
+        assert_eq!(tasks_only.acceptance_criteria(), vec!["one", "three"]);
+    }
+}
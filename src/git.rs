@@ -0,0 +1,113 @@
+//! `picode git` subcommands
+//!
+//! Commits are split by repository: files inside a submodule or a nested
+//! clone are committed in that repository, never grouped with files of the
+//! workspace repository. `bisect-assist` is implemented in [`crate::bisect`],
+//! `split-commit` in [`crate::split_commit`] and `resolve` in
+//! [`crate::resolve`].
+
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_core::workspace::{Workspace, WorkspaceConfig};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+/// Handle `picode git` subcommands
+pub async fn handle_action(action: picode_cli::GitAction, config: &Config) -> Result<()> {
+    match action {
+        picode_cli::GitAction::Commit { message, all, paths, override_hooks, .. } => {
+            let message = message.ok_or_else(|| {
+                PiCodeError::InvalidCommand("a commit message is required (--message)".to_string())
+            })?;
+            commit(config, std::env::current_dir()?, paths, all, &message, override_hooks).await
+        }
+        picode_cli::GitAction::BisectAssist { description, good, bad, check, judge } => {
+            crate::bisect::run(config, &description, &good, &bad, check, judge).await
+        }
+        picode_cli::GitAction::SplitCommit { judge, dry_run, yes } => {
+            crate::split_commit::run(config, judge, dry_run, yes).await
+        }
+        picode_cli::GitAction::Resolve { paths, no_ai } => crate::resolve::run(config, paths, no_ai).await,
+        action => {
+            println!("📝 Git action: {:?}", action);
+            println!("Git integration not implemented yet");
+            Ok(())
+        }
+    }
+}
+
+/// Commit `paths` (or every change with `all`) under `root`, one commit per
+/// repository the files belong to. Each commit first goes through the
+/// `pre_commit` hooks' dry run, whose veto `override_hooks` ignores.
+pub async fn commit(
+    config: &Config,
+    root: PathBuf,
+    paths: Vec<PathBuf>,
+    all: bool,
+    message: &str,
+    override_hooks: bool,
+) -> Result<()> {
+    let mut workspace = Workspace::new(WorkspaceConfig {
+        root_path: root,
+        ..WorkspaceConfig::default()
+    });
+    workspace.scan().await.map_err(picode_core::CoreError::from)?;
+    if workspace.git_status.is_none() {
+        return Err(PiCodeError::InvalidCommand("not inside a git repository".to_string()));
+    }
+
+    let paths = if all { workspace.changed_paths() } else { paths };
+    if paths.is_empty() {
+        println!("✅ Nothing to commit");
+        return Ok(());
+    }
+
+    for changes in workspace.group_by_repo(&paths) {
+        let payload = serde_json::json!({
+            "repo": changes.repo,
+            "files": changes.files,
+            "message": message,
+        });
+        crate::hook_guard::check(config, picode_hooks::HookEvent::PreCommit, payload, override_hooks).await?;
+        let oid = workspace
+            .commit(&changes, message)
+            .await
+            .map_err(picode_core::CoreError::from)?;
+        let repo = if changes.repo.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            changes.repo.display().to_string()
+        };
+        println!("✅ {} {} ({} file(s))", repo, &oid[..7], changes.files.len());
+    }
+    Ok(())
+}
+
+/// Run git in `root`, returning its stdout
+pub(crate) async fn git(root: &Path, args: &[&str]) -> Result<String> {
+    git_with_input(root, args, None).await
+}
+
+/// Run git in `root` with `input` on stdin, returning its stdout
+pub(crate) async fn git_with_input(root: &Path, args: &[&str], input: Option<&str>) -> Result<String> {
+    let mut child = tokio::process::Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(PiCodeError::Internal(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
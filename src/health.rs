@@ -0,0 +1,236 @@
+//! Provider health dashboard
+//!
+//! `/health` opens a status pane listing every configured provider with its
+//! last health check, error rate and average latency from the process
+//! metrics, whether it is rate limited right now and what it has cost so
+//! far today. `/health check` re-checks every provider, so "why is it slow
+//! or failing" can be answered without leaving the session.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::metrics::{HealthCheck, Metrics, ProviderMetrics};
+use chrono::{DateTime, Utc};
+use picode_core::Pane;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// One provider's row in the dashboard
+#[derive(Debug, Clone)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub metrics: ProviderMetrics,
+    /// USD per million prompt and completion tokens, when configured
+    pub price: Option<(f64, f64)>,
+}
+
+impl ProviderHealth {
+    /// What today's tokens cost, when the provider has prices
+    pub fn spend_today(&self, now: DateTime<Utc>) -> Option<f64> {
+        let today = self.metrics.today.on(now.date_naive());
+        self.price.map(|(prompt, completion)| {
+            (today.prompt_tokens as f64 * prompt + today.completion_tokens as f64 * completion) / 1_000_000.0
+        })
+    }
+
+    /// Overall state: failing health check, rate limited, erroring or fine
+    pub fn status(&self, now: DateTime<Utc>) -> &'static str {
+        match &self.metrics.last_health_check {
+            Some(check) if !check.healthy => "down",
+            _ if self.metrics.is_rate_limited(now) => "limited",
+            _ if self.metrics.requests > 0 && self.metrics.error_rate() >= 0.5 => "failing",
+            None if self.metrics.requests == 0 => "unknown",
+            _ => "ok",
+        }
+    }
+}
+
+/// Names of the configured providers, the default one first
+pub fn provider_names(config: &Config) -> Vec<String> {
+    let mut names = vec![config.llm.default_provider.clone()];
+    let mut others: Vec<String> = config
+        .llm
+        .providers
+        .keys()
+        .filter(|name| **name != config.llm.default_provider)
+        .cloned()
+        .collect();
+    others.sort();
+    names.extend(others);
+    names
+}
+
+/// Dashboard rows from the recorded metrics
+pub fn collect(config: &Config, metrics: &Metrics) -> Vec<ProviderHealth> {
+    provider_names(config)
+        .into_iter()
+        .map(|provider| {
+            let price = config.llm.providers.get(&provider).and_then(|p| {
+                p.prompt_price_per_million.zip(p.completion_price_per_million)
+            });
+            ProviderHealth {
+                metrics: metrics.provider(&provider).unwrap_or_default(),
+                provider,
+                price,
+            }
+        })
+        .collect()
+}
+
+/// Health check every configured provider concurrently. Providers that
+/// cannot even be set up (e.g. a missing API key) are recorded as down.
+pub async fn check_all(config: &Config) {
+    let checks = provider_names(config).into_iter().map(|provider| async move {
+        match Assistant::for_provider(config, &provider) {
+            Ok(assistant) => {
+                assistant.health_check().await;
+            }
+            Err(err) => Metrics::global().record_health_check(
+                &provider,
+                HealthCheck {
+                    at: Utc::now(),
+                    healthy: false,
+                    latency: Duration::ZERO,
+                    error: Some(err.to_string()),
+                },
+            ),
+        }
+    });
+    futures::future::join_all(checks).await;
+}
+
+fn ago(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - then).num_seconds().max(0);
+    match seconds {
+        0..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        _ => format!("{}h ago", seconds / 3600),
+    }
+}
+
+/// The status pane and its contents
+pub fn render(rows: &[ProviderHealth], now: DateTime<Utc>) -> (Pane, String) {
+    let pane = Pane::new_output("text/plain".to_string(), "provider health".to_string());
+    let mut out = format!("── {} ──\n", pane.title);
+    let _ = writeln!(
+        out,
+        "{:<14} {:<8} {:<16} {:>6} {:>9} {:<16} {:>10}",
+        "provider", "status", "last check", "errors", "latency", "rate limit", "today"
+    );
+    for row in rows {
+        let metrics = &row.metrics;
+        let last_check = metrics.last_health_check.as_ref().map_or_else(
+            || "never".to_string(),
+            |check| format!("{} {}ms", ago(check.at, now), check.latency.as_millis()),
+        );
+        let errors = if metrics.requests == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.0}%", metrics.error_rate() * 100.0)
+        };
+        let latency = metrics
+            .average_latency()
+            .map_or_else(|| "-".to_string(), |latency| format!("{:.2}s", latency.as_secs_f64()));
+        let rate_limit = match metrics.rate_limited_until {
+            Some(until) if until > now => format!("for {}s", (until - now).num_seconds()),
+            _ if metrics.rate_limits > 0 => format!("ok ({} hit)", metrics.rate_limits),
+            _ => "ok".to_string(),
+        };
+        let today = match row.spend_today(now) {
+            Some(spend) => format!("${:.2}", spend),
+            None => {
+                let usage = metrics.today.on(now.date_naive());
+                format!("{} tok", usage.prompt_tokens + usage.completion_tokens)
+            }
+        };
+        let _ = writeln!(
+            out,
+            "{:<14} {:<8} {:<16} {:>6} {:>9} {:<16} {:>10}",
+            row.provider,
+            row.status(now),
+            last_check,
+            errors,
+            latency,
+            rate_limit,
+            today
+        );
+        if let Some(error) = metrics.last_health_check.as_ref().and_then(|check| check.error.as_ref()) {
+            let _ = writeln!(out, "  └ {}", error);
+        }
+    }
+    out.push_str("── /health check re-checks every provider ──\n");
+    (pane, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProviderConfig;
+
+    #[test]
+    fn dashboard_shows_metrics_per_provider() {
+        let mut config = Config::default();
+        config.llm.default_provider = "openai".to_string();
+        config.llm.providers.insert(
+            "openai".to_string(),
+            ProviderConfig {
+                endpoint: String::new(),
+                api_key_env: None,
+                default_model: None,
+                model_path: None,
+                context_size: None,
+                gpu_layers: None,
+                prompt_price_per_million: Some(2.0),
+                completion_price_per_million: Some(10.0),
+                signing: None,
+                endpoints: None,
+                payload_mapping: None,
+                pool: None,
+                attribution: None,
+            },
+        );
+
+        let metrics = Metrics::new();
+        metrics.record_request("openai", Duration::from_millis(400), 500_000, 100_000, true);
+        metrics.record_request("openai", Duration::from_millis(600), 0, 0, false);
+        metrics.record_rate_limit("openai", Duration::from_secs(30));
+        let now = Utc::now();
+
+        let rows = collect(&config, &metrics);
+        assert_eq!(rows[0].provider, "openai");
+        assert_eq!(rows[0].spend_today(now), Some(2.0));
+        assert_eq!(rows[0].status(now), "limited");
+
+        let (pane, text) = render(&rows, now);
+        assert_eq!(pane.title, "provider health");
+        let line = text.lines().find(|line| line.starts_with("openai")).unwrap();
+        assert!(line.contains("50%"));
+        assert!(line.contains("0.50s"));
+        assert!(line.contains("$2.00"));
+    }
+
+    #[test]
+    fn failed_health_check_marks_provider_down() {
+        let metrics = Metrics::new();
+        let now = Utc::now();
+        metrics.record_health_check(
+            "local",
+            HealthCheck {
+                at: now - chrono::Duration::seconds(90),
+                healthy: false,
+                latency: Duration::from_millis(12),
+                error: Some("connection refused".to_string()),
+            },
+        );
+        let row = ProviderHealth {
+            provider: "local".to_string(),
+            metrics: metrics.provider("local").unwrap(),
+            price: None,
+        };
+        assert_eq!(row.status(now), "down");
+
+        let (_, text) = render(&[row], now);
+        assert!(text.contains("1m ago 12ms"));
+        assert!(text.contains("  └ connection refused"));
+        assert!(text.contains("0 tok"));
+    }
+}
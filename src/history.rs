@@ -0,0 +1,380 @@
+//! Searchable conversation history
+//!
+//! Conversations tagged with `/tag` and annotated with `/note` are stored
+//! with their session under `.picode/sessions`. `picode session history`
+//! searches them by text and tags; `picode session export` writes one as
+//! markdown, optionally only the exchanges carrying given tags, and
+//! `picode session publish` as a standalone HTML page (see [`crate::publish`]).
+
+use crate::config::Config;
+use crate::error::Result;
+#[cfg(feature = "cli")]
+use picode_cli::SessionAction;
+use picode_core::annotation::normalize_tag;
+use picode_core::{CoalescingFileSystem, ConversationLog, CoreError, Session, SessionId, SessionManager};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Characters of context shown around a search match
+const SNIPPET_RADIUS: usize = 40;
+
+/// Directory holding the workspace's sessions
+pub fn sessions_dir(config: &Config) -> Result<PathBuf> {
+    let root = match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    Ok(root.join(crate::defaults::CONFIG_DIR).join(crate::defaults::SESSIONS_DIR))
+}
+
+/// Which conversations a search or export covers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryFilter {
+    /// Case-insensitive text looked for in messages, notes and titles
+    pub query: Option<String>,
+    /// Normalized tags that must all be present
+    pub tags: Vec<String>,
+}
+
+impl HistoryFilter {
+    pub fn new(query: Option<String>, tags: &[String]) -> Result<Self> {
+        let tags = tags
+            .iter()
+            .map(|tag| normalize_tag(tag).map_err(CoreError::from))
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self {
+            query: query.filter(|query| !query.trim().is_empty()),
+            tags,
+        })
+    }
+
+    /// The matching snippet, or an empty string when there is no query;
+    /// `None` when the conversation does not match
+    pub fn matches(&self, log: &ConversationLog) -> Option<String> {
+        if !log.annotations.has_all(&self.tags) {
+            return None;
+        }
+        let Some(query) = &self.query else {
+            return Some(String::new());
+        };
+        let mut texts = std::iter::once(log.derived.title.as_str())
+            .chain(log.annotations.notes.iter().map(|note| note.text.as_str()))
+            .chain(log.messages.iter().filter(|m| !m.is_context_update()).map(|m| m.content.as_str()));
+        texts.find_map(|text| snippet(text, query))
+    }
+}
+
+/// Text around the first case-insensitive occurrence of `query`
+fn snippet(text: &str, query: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    let start = lower.find(&query.to_lowercase())?;
+    // Lowercasing can change byte lengths; map back through char counts
+    let start_char = lower[..start].chars().count();
+    let query_chars = query.chars().count();
+    let chars: Vec<char> = text.chars().collect();
+    let from = start_char.saturating_sub(SNIPPET_RADIUS);
+    let to = (start_char + query_chars + SNIPPET_RADIUS).min(chars.len());
+    let mut out: String = chars[from.min(chars.len())..to].iter().collect();
+    out = out.split_whitespace().collect::<Vec<_>>().join(" ");
+    if from > 0 {
+        out.insert(0, '…');
+    }
+    if to < chars.len() {
+        out.push('…');
+    }
+    Some(out)
+}
+
+/// A conversation found by a search
+#[derive(Debug, Clone)]
+pub struct HistoryHit {
+    pub session: String,
+    pub title: String,
+    pub tags: BTreeSet<String>,
+    pub notes: usize,
+    pub snippet: String,
+}
+
+pub(crate) async fn load_manager(config: &Config) -> Result<SessionManager> {
+    load(SessionManager::new(sessions_dir(config)?).with_archive(config.session.archive)).await
+}
+
+async fn load(manager: SessionManager) -> Result<SessionManager> {
+    let recovery = manager.load_sessions().await.map_err(CoreError::from)?;
+    eprint!("{}", recovery);
+    Ok(manager)
+}
+
+/// Stored conversations matching `filter`, most recently active first
+pub async fn search(manager: &SessionManager, filter: &HistoryFilter) -> Result<Vec<HistoryHit>> {
+    let mut sessions = manager.list_sessions().await;
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.last_active));
+    let mut hits = Vec::new();
+    for session in sessions {
+        let log = manager.load_conversation(&session.id).await.map_err(CoreError::from)?;
+        if let Some(snippet) = filter.matches(&log) {
+            hits.push(HistoryHit {
+                session: session.name,
+                title: log.derived.title.clone(),
+                tags: log.annotations.all_tags(),
+                notes: log.annotations.notes.len(),
+                snippet,
+            });
+        }
+    }
+    Ok(hits)
+}
+
+/// Markdown transcript of a conversation. With `tags`, only exchanges
+/// carrying one of them are included (all of them when the conversation
+/// itself has one of the tags). With `response_diffs`, regenerated responses
+/// are followed by a word-level diff against the version they replaced.
+pub fn export_markdown(session: &Session, log: &ConversationLog, tags: &[String], response_diffs: bool) -> String {
+    let mut out = format!("# {}\n\n", session.name);
+    if !log.derived.title.is_empty() {
+        let _ = writeln!(out, "_{}_\n", log.derived.title);
+    }
+    if !log.annotations.tags.is_empty() {
+        let tags: Vec<String> = log.annotations.tags.iter().map(|tag| format!("`#{}`", tag)).collect();
+        let _ = writeln!(out, "Tags: {}\n", tags.join(" "));
+    }
+
+    for (index, message) in log.messages.iter().enumerate() {
+        if message.is_annotation() || message.is_context_update() {
+            continue;
+        }
+        let exchange_tags = log.annotations.exchange_tags(index);
+        if !tags.is_empty() && !tags.iter().any(|tag| exchange_tags.contains(tag)) {
+            continue;
+        }
+        let _ = writeln!(out, "## {}\n\n{}\n", message.role, message.content.trim_end());
+        if let Some(previous) = message.previous.last().filter(|_| response_diffs) {
+            let diff = picode_core::WordDiff::new(previous.trim_end(), message.content.trim_end());
+            let _ = writeln!(
+                out,
+                "<details><summary>Changes from the previous response ({} regeneration(s))</summary>\n\n{}\n\n</details>\n",
+                message.previous.len(),
+                diff.render_markdown()
+            );
+        }
+        for note in log.annotations.notes_for(index) {
+            let _ = writeln!(out, "> 📝 {}\n", note.text);
+        }
+    }
+    out
+}
+
+/// Save an interactive conversation as a named session, creating the
+/// session on first save. With `session.flush_interval_ms` set, saves are
+/// coalesced and written in batches; call [`SessionRecorder::flush`] before
+/// exiting.
+pub struct SessionRecorder {
+    manager: SessionManager,
+    writes: Option<Arc<CoalescingFileSystem>>,
+    name: String,
+    workspace: PathBuf,
+    id: Option<SessionId>,
+}
+
+impl SessionRecorder {
+    /// Recorder for the session `name`, or a new `chat-<timestamp>` one
+    pub async fn open(config: &Config, name: Option<String>) -> Result<Self> {
+        let dir = sessions_dir(config)?;
+        let writes = (config.session.flush_interval_ms > 0).then(|| {
+            let interval = Duration::from_millis(config.session.flush_interval_ms);
+            let fs = CoalescingFileSystem::new(picode_core::io::default_file_system(), interval);
+            fs.spawn_flusher();
+            fs
+        });
+        let manager = match &writes {
+            Some(fs) => SessionManager::with_file_system(dir, fs.clone()),
+            None => SessionManager::new(dir),
+        };
+        let manager = load(manager.with_archive(config.session.archive)).await?;
+        let name = name.unwrap_or_else(|| format!("chat-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        let id = manager.get_session_by_name(&name).await.ok().map(|session| session.id);
+        let workspace = match &config.workspace.root_dir {
+            Some(root) => root.clone(),
+            None => std::env::current_dir()?,
+        };
+        Ok(Self { manager, writes, name, workspace, id })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the session exists on disk
+    pub fn is_saved(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// The stored conversation of an existing session
+    pub async fn load(&self) -> Result<Option<ConversationLog>> {
+        match &self.id {
+            Some(id) => Ok(Some(self.manager.load_conversation(id).await.map_err(CoreError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the conversation under this session
+    pub async fn save(&mut self, log: &mut ConversationLog) -> Result<()> {
+        let id = match &self.id {
+            Some(id) => {
+                self.manager.update_session(id, |session| session.touch()).await.map_err(CoreError::from)?;
+                id.clone()
+            }
+            None => {
+                let id = self
+                    .manager
+                    .create_session(self.name.clone(), self.workspace.clone())
+                    .await
+                    .map_err(CoreError::from)?;
+                self.id = Some(id.clone());
+                id
+            }
+        };
+        log.session_id = id;
+        self.manager.save_conversation(log).await.map_err(CoreError::from)?;
+        Ok(())
+    }
+
+    /// Write saves still held back by coalescing
+    pub async fn flush(&self) -> Result<()> {
+        if let Some(writes) = &self.writes {
+            writes.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `picode session history`, `picode session export`,
+/// `picode session publish` and `picode session compact`
+#[cfg(feature = "cli")]
+pub async fn handle_action(action: SessionAction, config: &Config) -> Result<()> {
+    match action {
+        SessionAction::History { query, tag } => {
+            let filter = HistoryFilter::new(query, &tag)?;
+            let manager = load_manager(config).await?;
+            let hits = search(&manager, &filter).await?;
+            if hits.is_empty() {
+                println!("No matching conversations");
+            }
+            for hit in hits {
+                let tags: Vec<String> = hit.tags.iter().map(|tag| format!("#{}", tag)).collect();
+                println!("{:<24} {} [{}] ({} notes)", hit.session, hit.title, tags.join(" "), hit.notes);
+                if !hit.snippet.is_empty() {
+                    println!("    {}", hit.snippet);
+                }
+            }
+            Ok(())
+        }
+        SessionAction::Export { name, tag, output, response_diffs } => {
+            let filter = HistoryFilter::new(None, &tag)?;
+            let manager = load_manager(config).await?;
+            let session = manager.get_session_by_name(&name).await.map_err(CoreError::from)?;
+            let log = manager.load_conversation(&session.id).await.map_err(CoreError::from)?;
+            // Tags of the whole conversation select every exchange
+            let tags = if filter.tags.iter().any(|tag| log.annotations.tags.contains(tag)) {
+                Vec::new()
+            } else {
+                filter.tags
+            };
+            let markdown = export_markdown(&session, &log, &tags, response_diffs);
+            match output {
+                Some(path) => {
+                    tokio::fs::write(&path, markdown).await?;
+                    println!("✅ Exported session '{}' to {}", name, path.display());
+                }
+                None => print!("{}", markdown),
+            }
+            Ok(())
+        }
+        SessionAction::Publish { name, output, strip_contents } => {
+            let manager = load_manager(config).await?;
+            let session = manager.get_session_by_name(&name).await.map_err(CoreError::from)?;
+            let log = manager.load_conversation(&session.id).await.map_err(CoreError::from)?;
+            let html = crate::publish::render_html(&session, &log, crate::publish::PublishOptions { strip_contents });
+            let path = output.unwrap_or_else(|| PathBuf::from(format!("{}.html", name)));
+            tokio::fs::write(&path, html).await?;
+            println!("✅ Published session '{}' to {}", name, path.display());
+            Ok(())
+        }
+        SessionAction::Compact => {
+            let manager = load_manager(config).await?;
+            let report = manager.compact_conversations().await.map_err(CoreError::from)?;
+            println!(
+                "✅ Compacted {} conversation(s) into {} new segment(s): {} → {} bytes, {} reclaimed",
+                report.conversations,
+                report.segments,
+                report.bytes_before,
+                report.bytes_after,
+                report.reclaimed()
+            );
+            Ok(())
+        }
+        other => Err(crate::error::PiCodeError::Internal(format!("not a history action: {:?}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picode_core::ConversationMessage;
+
+    fn tagged_log() -> ConversationLog {
+        let mut log = ConversationLog::new(SessionId::new());
+        log.push(ConversationMessage::new("user", "The flaky test in auth times out on CI"));
+        log.push(ConversationMessage::new("assistant", "Raise the mock server's timeout."));
+        log.note("this approach worked #flaky").unwrap();
+        log.push(ConversationMessage::new("user", "Now update the changelog"));
+        log.push(ConversationMessage::new("assistant", "Done."));
+        log.annotations.tag("bug-hunt").unwrap();
+        log
+    }
+
+    #[test]
+    fn filters_by_tags_and_text() {
+        let log = tagged_log();
+        let by_tag = HistoryFilter::new(None, &["#Bug-Hunt".to_string()]).unwrap();
+        assert_eq!(by_tag.matches(&log), Some(String::new()));
+        let by_text = HistoryFilter::new(Some("MOCK server".to_string()), &["flaky".to_string()]).unwrap();
+        assert_eq!(by_text.matches(&log).unwrap(), "Raise the mock server's timeout.");
+        assert!(HistoryFilter::new(None, &["perf".to_string()]).unwrap().matches(&log).is_none());
+        assert!(HistoryFilter::new(None, &["not a tag".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn recorder_persists_and_export_keeps_tagged_exchanges() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.workspace.root_dir = Some(dir.path().to_path_buf());
+
+        let mut log = tagged_log();
+        let mut recorder = SessionRecorder::open(&config, Some("auth-debug".to_string())).await.unwrap();
+        assert!(!recorder.is_saved());
+        recorder.save(&mut log).await.unwrap();
+        recorder.flush().await.unwrap();
+        let reopened = SessionRecorder::open(&config, Some("auth-debug".to_string())).await.unwrap();
+        assert_eq!(reopened.load().await.unwrap().unwrap().annotations, log.annotations);
+
+        let manager = load_manager(&config).await.unwrap();
+        let hits = search(&manager, &HistoryFilter::new(None, &["flaky".to_string()]).unwrap()).await.unwrap();
+        assert_eq!(hits[0].session, "auth-debug");
+
+        let session = manager.get_session_by_name("auth-debug").await.unwrap();
+        let markdown = export_markdown(&session, &log, &["flaky".to_string()], false);
+        assert!(markdown.contains("Tags: `#bug-hunt`"));
+        assert!(markdown.contains("Raise the mock server's timeout.\n\n> 📝 this approach worked #flaky"));
+        assert!(!markdown.contains("changelog"));
+
+        log.regenerate_last("Done, the changelog lists the fix.");
+        assert!(!export_markdown(&session, &log, &[], false).contains("Changes from the previous response"));
+        let markdown = export_markdown(&session, &log, &[], true);
+        assert!(markdown.contains("Changes from the previous response (1 regeneration(s))"));
+        assert!(markdown.contains("~~Done.~~") && markdown.contains("**changelog**"));
+    }
+}
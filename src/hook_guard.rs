@@ -0,0 +1,49 @@
+//! Hook dry runs before destructive operations
+//!
+//! Commits and permanent deletions first ask the hooks subscribed to
+//! `pre_commit`, `pre_push` or `file_delete` whether they may go ahead (see
+//! [`picode_hooks::protocol`]). A veto is shown with the hook's reason and
+//! stops the operation unless it is overridden, with `--override-hooks` or
+//! by confirming at the prompt on a terminal.
+
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_hooks::{HookEvent, HookOutcome};
+use serde_json::Value;
+use std::io::IsTerminal;
+use tracing::warn;
+
+/// Run the dry-run pass of `event`; `Ok` when the operation may proceed
+pub async fn check(config: &Config, event: HookEvent, payload: Value, override_veto: bool) -> Result<()> {
+    let Some(hooks) = crate::assistant::configured_hooks(config) else {
+        return Ok(());
+    };
+    let outcome = hooks
+        .dry_run(event, payload)
+        .await
+        .map_err(|e| PiCodeError::Hook(e.to_string()))?;
+    let HookOutcome::Vetoed { hook, reason } = outcome else {
+        return Ok(());
+    };
+
+    println!("⛔ Hook '{}' vetoed {}: {}", hook, event.as_str(), reason);
+    if override_veto {
+        warn!("Veto of hook '{}' on {} overridden", hook, event.as_str());
+        return Ok(());
+    }
+    if std::io::stdin().is_terminal()
+        && dialoguer::Confirm::new()
+            .with_prompt("Override the veto and continue?")
+            .default(false)
+            .interact()?
+    {
+        warn!("Veto of hook '{}' on {} overridden at the prompt", hook, event.as_str());
+        return Ok(());
+    }
+    Err(PiCodeError::Permission(format!(
+        "{} vetoed by hook '{}': {}",
+        event.as_str(),
+        hook,
+        reason
+    )))
+}
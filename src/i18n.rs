@@ -0,0 +1,301 @@
+//! Localization of user-facing strings
+//!
+//! Messages live in Fluent-style `.ftl` files: `key = text`, indented lines
+//! continuing the previous message, `#` comments and `{ $name }`
+//! placeholders. English (`locales/en.ftl`) is compiled in and is the
+//! fallback for every key; translations are read at startup from
+//! `<locale>.ftl` in the workspace's `.picode/locales` and the user's
+//! `~/.config/picode/locales`, so teams can localize help text, prompts and
+//! labels without patching source.
+//!
+//! The locale comes from `ui.locale` in the configuration, else from
+//! `PICODE_LANG`, `LC_ALL`, `LC_MESSAGES` or `LANG`. Look messages up with
+//! the [`tr!`](crate::tr) macro.
+
+use crate::config::Config;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Locale every lookup falls back to
+pub const FALLBACK_LOCALE: &str = "en";
+
+/// Directory, under the configuration directories, holding translations
+pub const LOCALES_DIR: &str = "locales";
+
+/// Translations compiled into the binary
+const BUNDLED: &[(&str, &str)] = &[("en", include_str!("../locales/en.ftl"))];
+
+/// Environment variables consulted for the locale, in order
+const LOCALE_ENV: &[&str] = &["PICODE_LANG", "LC_ALL", "LC_MESSAGES", "LANG"];
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// A language tag such as `de` or `pt-BR`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale(String);
+
+impl Locale {
+    /// Normalize a tag or POSIX locale name (`de_DE.UTF-8@euro` → `de-DE`);
+    /// `C`, `POSIX` and empty names mean no preference
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.split(['.', '@']).next().unwrap_or_default().trim();
+        if name.is_empty() || name == "C" || name == "POSIX" {
+            return None;
+        }
+        let mut parts = name.split(['_', '-']);
+        let language = parts.next()?.to_lowercase();
+        if !language.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let tag = match parts.next() {
+            Some(region) if !region.is_empty() => format!("{}-{}", language, region.to_uppercase()),
+            _ => language,
+        };
+        Some(Self(tag))
+    }
+
+    /// The configured locale, else the first one set in the environment,
+    /// else English
+    pub fn detect(configured: Option<&str>) -> Self {
+        configured
+            .and_then(Self::parse)
+            .or_else(|| {
+                LOCALE_ENV
+                    .iter()
+                    .filter_map(|var| std::env::var(var).ok())
+                    .find_map(|value| Self::parse(&value))
+            })
+            .unwrap_or_else(|| Self(FALLBACK_LOCALE.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Tags to try, most specific first: `pt-BR`, `pt`, then English
+    pub fn fallbacks(&self) -> Vec<String> {
+        let mut tags = vec![self.0.clone()];
+        if let Some((language, _)) = self.0.split_once('-') {
+            tags.push(language.to_string());
+        }
+        if !tags.iter().any(|tag| tag == FALLBACK_LOCALE) {
+            tags.push(FALLBACK_LOCALE.to_string());
+        }
+        tags
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Parse an `.ftl` document into its messages
+pub fn parse_messages(source: &str) -> std::result::Result<BTreeMap<String, String>, String> {
+    let mut messages = BTreeMap::new();
+    let mut current: Option<(String, String)> = None;
+    for (number, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            match &mut current {
+                Some((_, value)) => {
+                    if !value.is_empty() {
+                        value.push('\n');
+                    }
+                    value.push_str(trimmed);
+                }
+                None => return Err(format!("line {}: continuation without a message", number + 1)),
+            }
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected `key = value`", number + 1))?;
+        let key = key.trim();
+        let valid = key.starts_with(|c: char| c.is_ascii_alphabetic())
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!("line {}: invalid message key '{}'", number + 1, key));
+        }
+        if let Some((key, value)) = current.replace((key.to_string(), value.trim().to_string())) {
+            messages.insert(key, value);
+        }
+    }
+    if let Some((key, value)) = current {
+        messages.insert(key, value);
+    }
+    Ok(messages)
+}
+
+/// Substitute `{ $name }` placeholders and `{ "literal" }` strings; unknown
+/// placeholders are left as written
+fn format_pattern(pattern: &str, args: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let placeable = &rest[start..start + end + 1];
+        let inner = placeable[1..placeable.len() - 1].trim();
+        if let Some(name) = inner.strip_prefix('$') {
+            match args.iter().find(|(arg, _)| *arg == name) {
+                Some((_, value)) => out.push_str(value),
+                None => out.push_str(placeable),
+            }
+        } else if inner.len() >= 2 && inner.starts_with('"') && inner.ends_with('"') {
+            out.push_str(&inner[1..inner.len() - 1]);
+        } else {
+            out.push_str(placeable);
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Messages for one locale, with fallbacks down to English
+#[derive(Debug, Clone)]
+pub struct Localizer {
+    locale: Locale,
+    /// Most specific first; the bundled English messages come last
+    bundles: Vec<BTreeMap<String, String>>,
+}
+
+impl Localizer {
+    /// Only the bundled English messages
+    pub fn english() -> Self {
+        Self::load(Locale(FALLBACK_LOCALE.to_string()), &[])
+    }
+
+    /// Messages for `locale` from `search_dirs` (earlier directories win)
+    /// and the bundled translations. Unreadable or malformed files are
+    /// skipped with a warning.
+    pub fn load(locale: Locale, search_dirs: &[PathBuf]) -> Self {
+        let mut bundles = Vec::new();
+        for tag in locale.fallbacks() {
+            for dir in search_dirs {
+                let path = dir.join(format!("{}.ftl", tag));
+                let Ok(source) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                match parse_messages(&source) {
+                    Ok(messages) => bundles.push(messages),
+                    Err(err) => warn!("Ignoring translation {}: {}", path.display(), err),
+                }
+            }
+            if let Some((_, source)) = BUNDLED.iter().find(|(bundled, _)| *bundled == tag) {
+                bundles.push(parse_messages(source).unwrap_or_default());
+            }
+        }
+        Self { locale, bundles }
+    }
+
+    /// Add messages taking precedence over everything loaded so far
+    pub fn with_messages(mut self, source: &str) -> std::result::Result<Self, String> {
+        self.bundles.insert(0, parse_messages(source)?);
+        Ok(self)
+    }
+
+    pub fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        self.bundles.iter().any(|bundle| bundle.contains_key(key))
+    }
+
+    /// The message for `key` with its placeholders filled in; a key no
+    /// bundle defines is returned as is, so missing strings stay visible
+    pub fn message(&self, key: &str, args: &[(&str, String)]) -> String {
+        match self.bundles.iter().find_map(|bundle| bundle.get(key)) {
+            Some(pattern) => format_pattern(pattern, args),
+            None => key.to_string(),
+        }
+    }
+}
+
+/// Directories searched for translations: the workspace's, then the user's
+pub fn search_dirs(config: &Config) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let root = config.workspace.root_dir.clone().or_else(|| std::env::current_dir().ok());
+    if let Some(root) = root {
+        dirs.push(root.join(crate::defaults::CONFIG_DIR).join(LOCALES_DIR));
+    }
+    if let Some(config_dir) = Config::default_config_path().parent() {
+        dirs.push(config_dir.join(LOCALES_DIR));
+    }
+    dirs
+}
+
+/// Select the process-wide locale from the configuration; only the first
+/// call (or lookup) decides
+pub fn init(config: &Config) -> &'static Localizer {
+    LOCALIZER.get_or_init(|| Localizer::load(Locale::detect(config.ui.locale.as_deref()), &search_dirs(config)))
+}
+
+/// The process-wide localizer; English until [`init`] runs
+pub fn localizer() -> &'static Localizer {
+    LOCALIZER.get_or_init(Localizer::english)
+}
+
+/// Look up a localized message, optionally with `name = value` arguments:
+/// `tr!("interactive-unknown-command", command = input)`
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::localizer().message($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::localizer().message($key, &[$((stringify!($name), $value.to_string())),+])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale_parsing_and_fallbacks() {
+        assert_eq!(Locale::parse("de_DE.UTF-8").unwrap().as_str(), "de-DE");
+        assert_eq!(Locale::parse("pt-br").unwrap().as_str(), "pt-BR");
+        assert_eq!(Locale::parse("fr").unwrap().as_str(), "fr");
+        assert!(Locale::parse("C.UTF-8").is_none());
+        assert!(Locale::parse("").is_none());
+        assert_eq!(Locale::detect(Some("es_MX")).fallbacks(), vec!["es-MX", "es", "en"]);
+        assert_eq!(Locale::parse("en_GB").unwrap().fallbacks(), vec!["en-GB", "en"]);
+    }
+
+    #[test]
+    fn translations_override_english_with_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.ftl"),
+            "# German\ninteractive-goodbye = Auf Wiedersehen!\ninteractive-unknown-command =\n    Unbekannter Befehl: { $command }.\n",
+        )
+        .unwrap();
+
+        let localizer = Localizer::load(Locale::parse("de_AT").unwrap(), &[dir.path().to_path_buf()]);
+        assert_eq!(localizer.message("interactive-goodbye", &[]), "Auf Wiedersehen!");
+        assert_eq!(
+            localizer.message("interactive-unknown-command", &[("command", "/foo".to_string())]),
+            "Unbekannter Befehl: /foo."
+        );
+        // Untranslated keys come from the bundled English messages
+        assert_eq!(localizer.message("interactive-analyzing", &[]), "Analyzing project structure...");
+        assert_eq!(localizer.message("interactive-prompt", &[]), "picode> ");
+        assert_eq!(localizer.message("no-such-key", &[]), "no-such-key");
+
+        assert!(parse_messages("not a message").is_err());
+        assert!(parse_messages("  orphan continuation").is_err());
+    }
+}
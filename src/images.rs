@@ -0,0 +1,448 @@
+//! Inline images and diagrams in the terminal
+//!
+//! Mermaid and Graphviz blocks in model replies, and images shown with
+//! `/image`, are drawn inline on terminals that speak the kitty or iTerm2
+//! image protocol (see [`TerminalCapabilities`]). Diagrams are rasterized by
+//! an external renderer (`mmdc`, `dot`) run as a separate process in a
+//! scratch directory, with a cleared environment and a timeout, so a hostile
+//! or runaway diagram cannot touch the workspace or hang the session.
+//! Everywhere else, or when the renderer is missing, diagrams fall back to
+//! a text outline of their edges and images to a one-line description.
+
+use crate::error::{PiCodeError, Result};
+use crate::terminal::{ImageProtocol, TerminalCapabilities};
+use base64::Engine;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::debug;
+
+/// Largest image sent to the terminal
+const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Base64 bytes per kitty graphics escape
+const KITTY_CHUNK: usize = 4096;
+
+/// `[ui.images]` section of the configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageConfig {
+    /// Draw images inline when the terminal supports it
+    #[serde(default = "default_inline")]
+    pub inline: bool,
+
+    /// Mermaid renderer, called as `<mermaid> -i <in.mmd> -o <out.png>`
+    #[serde(default = "default_mermaid")]
+    pub mermaid: String,
+
+    /// Graphviz renderer, called as `<graphviz> -Tpng -o <out.png> <in.dot>`
+    #[serde(default = "default_graphviz")]
+    pub graphviz: String,
+
+    /// Longest a renderer may run
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_inline() -> bool {
+    true
+}
+
+fn default_mermaid() -> String {
+    "mmdc".to_string()
+}
+
+fn default_graphviz() -> String {
+    "dot".to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    15
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            inline: default_inline(),
+            mermaid: default_mermaid(),
+            graphviz: default_graphviz(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+/// Diagram languages recognized in fenced code blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramKind {
+    Mermaid,
+    Graphviz,
+}
+
+impl DiagramKind {
+    /// Kind for a code fence's info string
+    pub fn from_fence(lang: &str) -> Option<Self> {
+        match lang.trim().to_lowercase().as_str() {
+            "mermaid" => Some(Self::Mermaid),
+            "dot" | "graphviz" | "gv" => Some(Self::Graphviz),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mermaid => "mermaid",
+            Self::Graphviz => "graphviz",
+        }
+    }
+}
+
+/// An edge of a diagram, for the text fallback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: Option<String>,
+}
+
+/// A diagram found in a reply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagram {
+    pub kind: DiagramKind,
+    pub source: String,
+}
+
+impl Diagram {
+    /// Every diagram block in `text`, in order
+    pub fn extract_all(text: &str) -> Vec<Self> {
+        let mut diagrams = Vec::new();
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            let Some(lang) = line.trim_start().strip_prefix("```") else {
+                continue;
+            };
+            let code: Vec<&str> = lines.by_ref().take_while(|line| line.trim_start() != "```").collect();
+            if let Some(kind) = DiagramKind::from_fence(lang) {
+                diagrams.push(Self { kind, source: code.join("\n") });
+            }
+        }
+        diagrams
+    }
+
+    /// Edges that can be read from the source; statements other than
+    /// simple `a -> b` edges are skipped
+    pub fn edges(&self) -> Vec<Edge> {
+        self.source
+            .lines()
+            .filter_map(|line| match self.kind {
+                DiagramKind::Mermaid => mermaid_edge(line),
+                DiagramKind::Graphviz => graphviz_edge(line),
+            })
+            .collect()
+    }
+
+    /// Text outline of the diagram, for terminals without images
+    pub fn outline(&self, unicode: bool) -> String {
+        let (corner, bar, arrow) = if unicode { ("┌─", "│", "──▶") } else { ("+-", "|", "-->") };
+        let mut out = format!("{} {} diagram\n", corner, self.kind.name());
+        let edges = self.edges();
+        if edges.is_empty() {
+            for line in self.source.lines() {
+                let _ = writeln!(out, "{} {}", bar, printable(line));
+            }
+        }
+        for edge in edges {
+            let _ = write!(out, "{} {} {} {}", bar, printable(&edge.from), arrow, printable(&edge.to));
+            if let Some(label) = edge.label {
+                let _ = write!(out, "  ({})", printable(&label));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn mermaid_edge(line: &str) -> Option<Edge> {
+    static EDGE: OnceLock<Regex> = OnceLock::new();
+    let edge = EDGE.get_or_init(|| {
+        let node = r"([A-Za-z0-9_]+)(\[[^\]]*\]|\(+[^)]*\)+|\{[^}]*\})?";
+        Regex::new(&format!(
+            r"^\s*{node}\s*(?:-->>|->>|-\.->|==>|-->|---|->)\s*(?:\|([^|]*)\|)?\s*{node}\s*(?::\s*(.+?))?\s*;?\s*$"
+        ))
+        .expect("valid mermaid edge pattern")
+    });
+    let caps = edge.captures(line)?;
+    let node = |id: usize, shape: usize| {
+        let label = caps.get(shape).map_or("", |m| m.as_str().trim_matches(|c| "[](){}\"".contains(c)));
+        if label.is_empty() { caps[id].to_string() } else { label.to_string() }
+    };
+    let label = caps.get(3).or_else(|| caps.get(6)).map(|m| m.as_str().trim().to_string());
+    Some(Edge { from: node(1, 2), to: node(4, 5), label: label.filter(|l| !l.is_empty()) })
+}
+
+fn graphviz_edge(line: &str) -> Option<Edge> {
+    static EDGE: OnceLock<Regex> = OnceLock::new();
+    static LABEL: OnceLock<Regex> = OnceLock::new();
+    let edge = EDGE.get_or_init(|| {
+        Regex::new(r#"^\s*"?([A-Za-z0-9_.]+)"?\s*(?:->|--)\s*"?([A-Za-z0-9_.]+)"?\s*(?:\[(.*)\])?\s*;?\s*$"#)
+            .expect("valid graphviz edge pattern")
+    });
+    let caps = edge.captures(line)?;
+    let label = caps.get(3).and_then(|attrs| {
+        let label = LABEL.get_or_init(|| Regex::new(r#"label\s*=\s*(?:"([^"]*)"|([A-Za-z0-9_]+))"#).expect("valid label pattern"));
+        let caps = label.captures(attrs.as_str())?;
+        caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str().to_string())
+    });
+    Some(Edge { from: caps[1].to_string(), to: caps[2].to_string(), label })
+}
+
+/// Text from a model reply with control characters removed, so a fallback
+/// cannot smuggle escape sequences to the terminal
+fn printable(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// Kitty graphics escapes displaying a PNG at the cursor
+pub fn kitty_sequence(png: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::with_capacity(encoded.len() + chunks.len() * 16);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).expect("base64 is ASCII");
+        if i == 0 {
+            let _ = write!(out, "\x1b_Ga=T,f=100,m={};{}\x1b\\", more, chunk);
+        } else {
+            let _ = write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// iTerm2 inline image escape (also understood by WezTerm)
+pub fn iterm2_sequence(bytes: &[u8], name: &str) -> String {
+    let engine = base64::engine::general_purpose::STANDARD;
+    format!(
+        "\x1b]1337;File=name={};size={};inline=1;preserveAspectRatio=1:{}\x07\n",
+        engine.encode(name),
+        bytes.len(),
+        engine.encode(bytes)
+    )
+}
+
+/// One-line description of an image, e.g. `PNG 800x600, 12 KB`
+pub fn describe_image(bytes: &[u8]) -> String {
+    let dimensions = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") && bytes.len() >= 24 {
+        let be = |at: usize| u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+        Some(("PNG", be(16), be(20)))
+    } else if bytes.starts_with(b"GIF8") && bytes.len() >= 10 {
+        let le = |at: usize| u32::from(u16::from_le_bytes([bytes[at], bytes[at + 1]]));
+        Some(("GIF", le(6), le(8)))
+    } else {
+        None
+    };
+    let size = (bytes.len() as u64).div_ceil(1024);
+    match dimensions {
+        Some((format, width, height)) => format!("{} {}x{}, {} KB", format, width, height, size),
+        None if bytes.starts_with(&[0xff, 0xd8, 0xff]) => format!("JPEG, {} KB", size),
+        None => format!("image, {} KB", size),
+    }
+}
+
+/// Draws diagrams and images for the attached terminal
+#[derive(Debug, Clone)]
+pub struct InlineRenderer {
+    /// Protocol in use; `None` when images are shown as text
+    protocol: Option<ImageProtocol>,
+    unicode: bool,
+    config: ImageConfig,
+}
+
+impl InlineRenderer {
+    pub fn new(capabilities: &TerminalCapabilities, config: ImageConfig) -> Self {
+        // Sixel needs a raster encoder of its own; those terminals get text
+        let protocol = capabilities
+            .image_protocol
+            .filter(|protocol| config.inline && matches!(protocol, ImageProtocol::Kitty | ImageProtocol::ITerm2));
+        Self {
+            protocol,
+            unicode: capabilities.unicode,
+            config,
+        }
+    }
+
+    pub fn protocol(&self) -> Option<ImageProtocol> {
+        self.protocol
+    }
+
+    /// Every diagram in a model reply, ready to print after it
+    pub async fn render_reply(&self, reply: &str) -> Vec<String> {
+        let mut rendered = Vec::new();
+        for diagram in Diagram::extract_all(reply) {
+            rendered.push(self.render_diagram(&diagram).await);
+        }
+        rendered
+    }
+
+    /// The diagram as an inline image, or its text outline
+    pub async fn render_diagram(&self, diagram: &Diagram) -> String {
+        if self.protocol.is_some() {
+            match self.rasterize(diagram).await {
+                Ok(png) => {
+                    if let Some(image) = self.encode(&png, "diagram.png") {
+                        return image;
+                    }
+                }
+                Err(e) => debug!("Drawing the {} diagram as text: {}", diagram.kind.name(), e),
+            }
+        }
+        diagram.outline(self.unicode)
+    }
+
+    /// An image file inline, or a description of it
+    pub async fn render_image(&self, path: &Path) -> Result<String> {
+        let bytes = tokio::fs::read(path).await?;
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(PiCodeError::InvalidCommand(format!(
+                "{} is larger than {} MB",
+                path.display(),
+                MAX_IMAGE_BYTES / (1024 * 1024)
+            )));
+        }
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        Ok(match self.encode(&bytes, &name) {
+            Some(image) => image,
+            None => format!("[image {}: {}]\n", printable(&name), describe_image(&bytes)),
+        })
+    }
+
+    fn encode(&self, bytes: &[u8], name: &str) -> Option<String> {
+        match self.protocol? {
+            // Kitty's f=100 takes PNG only
+            ImageProtocol::Kitty if bytes.starts_with(b"\x89PNG") => Some(kitty_sequence(bytes)),
+            ImageProtocol::ITerm2 => Some(iterm2_sequence(bytes, name)),
+            _ => None,
+        }
+    }
+
+    /// Run the diagram's renderer in a scratch directory and return the PNG
+    async fn rasterize(&self, diagram: &Diagram) -> Result<Vec<u8>> {
+        let dir = std::env::temp_dir().join(format!("picode-render-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await?;
+        let result = self.rasterize_in(&dir, diagram).await;
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        result
+    }
+
+    async fn rasterize_in(&self, dir: &Path, diagram: &Diagram) -> Result<Vec<u8>> {
+        let output = dir.join("diagram.png");
+        let (program, input, args) = match diagram.kind {
+            DiagramKind::Mermaid => {
+                let input = dir.join("diagram.mmd");
+                let args = vec!["-i".into(), input.clone().into_os_string(), "-o".into(), output.clone().into_os_string()];
+                (&self.config.mermaid, input, args)
+            }
+            DiagramKind::Graphviz => {
+                let input = dir.join("diagram.dot");
+                let args = vec!["-Tpng".into(), "-o".into(), output.clone().into_os_string(), input.clone().into_os_string()];
+                (&self.config.graphviz, input, args)
+            }
+        };
+        tokio::fs::write(&input, &diagram.source).await?;
+
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(args)
+            .current_dir(dir)
+            .env_clear()
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Renderers need to find their own tools (mmdc runs a headless browser)
+        for key in ["PATH", "HOME", "TMPDIR"] {
+            if let Some(value) = std::env::var_os(key) {
+                command.env(key, value);
+            }
+        }
+        let child = command
+            .spawn()
+            .map_err(|e| PiCodeError::NotFound(format!("diagram renderer '{}': {}", program, e)))?;
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let result = tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .map_err(|_| PiCodeError::Timeout(format!("'{}' took longer than {:?}", program, timeout)))??;
+        if !result.status.success() {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            return Err(PiCodeError::Internal(format!(
+                "'{}' failed: {}",
+                program,
+                stderr.lines().next().unwrap_or("no output")
+            )));
+        }
+        let png = tokio::fs::read(&output).await?;
+        if png.len() > MAX_IMAGE_BYTES {
+            return Err(PiCodeError::Internal(format!("'{}' produced an oversized image", program)));
+        }
+        Ok(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn capabilities(term: &str) -> TerminalCapabilities {
+        let env: HashMap<String, String> = [("TERM", term), ("LANG", "en_US.UTF-8")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        TerminalCapabilities::from_env(&env, true)
+    }
+
+    #[test]
+    fn outlines_diagrams_from_replies() {
+        let reply = "Flow:\n```mermaid\ngraph TD\n  A[Client] -->|HTTPS| B(Gateway)\n  B --> C\n```\nand\n```dot\ndigraph { api -> db [label=\"sql\"]; }\n```\n```rust\nfn main() {}\n```";
+        let diagrams = Diagram::extract_all(reply);
+        assert_eq!(diagrams.len(), 2);
+        assert_eq!(
+            diagrams[0].outline(true),
+            "┌─ mermaid diagram\n│ Client ──▶ Gateway  (HTTPS)\n│ B ──▶ C\n"
+        );
+        assert_eq!(diagrams[1].kind, DiagramKind::Graphviz);
+
+        let dot = Diagram { kind: DiagramKind::Graphviz, source: "api -> db [label=\"sql\"];".to_string() };
+        assert_eq!(dot.outline(false), "+- graphviz diagram\n| api --> db  (sql)\n");
+        let sequence = Diagram { kind: DiagramKind::Mermaid, source: "Alice->>Bob: Hi\x1b[2J".to_string() };
+        assert_eq!(sequence.edges()[0].label.as_deref(), Some("Hi\x1b[2J"));
+        assert!(!sequence.outline(true).contains('\x1b'));
+    }
+
+    #[tokio::test]
+    async fn images_use_the_terminal_protocol_or_a_description() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 3, 32, 0, 0, 2, 88]);
+        let path = std::env::temp_dir().join(format!("picode-image-{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, &png).unwrap();
+
+        let kitty = InlineRenderer::new(&capabilities("xterm-kitty"), ImageConfig::default());
+        assert!(kitty.render_image(&path).await.unwrap().starts_with("\x1b_Ga=T,f=100,m=0;iVBORw0KGgo"));
+
+        let plain = InlineRenderer::new(&capabilities("xterm-256color"), ImageConfig::default());
+        let description = plain.render_image(&path).await.unwrap();
+        assert!(description.ends_with(": PNG 800x600, 1 KB]\n"));
+        let disabled = ImageConfig { inline: false, ..ImageConfig::default() };
+        assert!(InlineRenderer::new(&capabilities("xterm-kitty"), disabled).protocol().is_none());
+        std::fs::remove_file(path).unwrap();
+
+        // A missing renderer falls back to the outline
+        let config = ImageConfig { graphviz: "picode-no-such-renderer".to_string(), ..ImageConfig::default() };
+        let diagram = Diagram { kind: DiagramKind::Graphviz, source: "a -> b".to_string() };
+        let rendered = InlineRenderer::new(&capabilities("xterm-kitty"), config).render_diagram(&diagram).await;
+        assert_eq!(rendered, "┌─ graphviz diagram\n│ a ──▶ b\n");
+    }
+}
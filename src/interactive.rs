@@ -3,8 +3,21 @@
 //! This module provides the interactive terminal interface for PiCode,
 //! allowing users to chat with LLM providers through a terminal UI.
 
+use crate::attachments::{PendingAttachments, RunRequest};
 use crate::config::Config;
 use crate::error::Result;
+use crate::history::SessionRecorder;
+use crate::images::InlineRenderer;
+use crate::models::ModelCatalog;
+use crate::palette::RecentActions;
+use crate::presets::{GenerationPreset, Presets};
+use crate::session_template::SessionTemplate;
+use crate::slash::SlashCommandRegistry;
+use crate::terminal::{StatusSymbol, TerminalCapabilities};
+use crate::tr;
+use async_trait::async_trait;
+use picode_core::ansi::AnsiPolicy;
+use picode_core::event::{EventEnvelope, EventError, EventHandler};
 use serde::{Deserialize, Serialize};
 use tracing::{info, error};
 
@@ -17,6 +30,16 @@ pub struct InteractiveOptions {
     pub layout: String,
     /// Provider to use for LLM interactions
     pub provider: Option<String>,
+    /// Disable colored output regardless of terminal support
+    #[serde(default)]
+    pub no_color: bool,
+    /// Session template to boot from
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Session the conversation is saved under; tagged conversations
+    /// without one get a `chat-<timestamp>` session
+    #[serde(default)]
+    pub session: Option<String>,
 }
 
 impl Default for InteractiveOptions {
@@ -25,65 +48,510 @@ impl Default for InteractiveOptions {
             debug: false,
             layout: "default".to_string(),
             provider: None,
+            no_color: false,
+            template: None,
+            session: None,
         }
     }
 }
 
+/// Announces pinned files that changed on disk, above the prompt being typed
+struct StaleContextNotice;
+
+#[async_trait]
+impl EventHandler for StaleContextNotice {
+    async fn handle(&self, envelope: &EventEnvelope) -> std::result::Result<(), EventError> {
+        if let picode_core::Event::ContextStale { files, .. } = &envelope.event {
+            let files: Vec<String> = files.iter().map(|file| file.display().to_string()).collect();
+            println!();
+            println!("{}", tr!("interactive-context-stale-notice", files = files.join(", ")));
+            print!("{}{}", tr!("interactive-context-stale", count = files.len()), tr!("interactive-prompt"));
+            std::io::Write::flush(&mut std::io::stdout()).ok();
+        }
+        Ok(())
+    }
+
+    fn event_types(&self) -> Vec<&'static str> {
+        vec!["context_stale"]
+    }
+
+    fn name(&self) -> &str {
+        "stale-context-notice"
+    }
+}
+
 /// Main entry point for interactive mode
 /// 
 /// Launches the terminal UI and handles user interactions
 pub async fn run(opts: InteractiveOptions, config: Config) -> Result<()> {
     info!("Starting interactive mode with options: {:?}", opts);
     
+    // Pick a rendering tier the terminal can actually display
+    let mut capabilities = TerminalCapabilities::detect();
+    if opts.no_color {
+        capabilities = capabilities.without_color();
+    }
+    let tier = capabilities.render_tier();
+    info!("Terminal capabilities: {:?} (render tier: {:?})", capabilities, tier);
+    let renderer = InlineRenderer::new(&capabilities, config.ui.images.clone());
+    
     // Initialize terminal interface
-    println!("🎯 PiCode Interactive Mode");
+    println!("{} {}", tier.symbol(StatusSymbol::Info), tr!("interactive-title"));
     println!("Configuration: {:?}", config);
     println!("Options: {:?}", opts);
+    
+    // Reuse the daemon's warm index when one is running
+    if let Some(summary) = crate::daemon::workspace_summary(&std::env::current_dir()?).await {
+        println!(
+            "{} {}",
+            tier.symbol(StatusSymbol::Success),
+            tr!("interactive-daemon-index", files = summary.files, symbols = summary.symbols)
+        );
+    }
     println!();
     
+    // Files and symbols for @ and # mentions
+    let mentions = match crate::mentions::MentionIndex::build(&std::env::current_dir()?).await {
+        Ok(index) => index,
+        Err(err) => {
+            tracing::warn!("Mention completion unavailable: {}", err);
+            crate::mentions::MentionIndex::default()
+        }
+    };
+    let line_editor = std::io::IsTerminal::is_terminal(&std::io::stdin());
+    let mut send_queued = false;
+    
     // Basic interactive loop for now
-    println!("Available slash commands:");
-    println!("  /help     - Show help information");
-    println!("  /analyze  - Analyze current project"); 
-    println!("  /edit     - Edit files with AI assistance");
-    println!("  /exit     - Exit interactive mode");
+    let registry = SlashCommandRegistry::builtin();
+    let mut recent = RecentActions::default();
+    println!("{}", tr!("interactive-commands"));
+    for line in registry.help_lines() {
+        println!("{}", line);
+    }
+    println!("{}", tr!("interactive-palette-hint"));
     println!();
     
     // TODO: Implement full terminal UI with ratatui
     // TODO: Add LLM provider integration
     // TODO: Add slash command processing
-    // TODO: Add file watching and context updates
+    
+    // Output is sanitized unless the user switches to the raw view
+    let mut ansi_policy = config.ui.ansi_policy;
+    
+    let mut system_prompt = config.system_prompt(&std::env::current_dir()?).await?;
+    
+    // The chat pane and its conversation; `/model` switches the pane's model
+    let provider = opts.provider.clone().unwrap_or_else(|| config.llm.default_provider.clone());
+    let model = config
+        .llm
+        .providers
+        .get(&provider)
+        .and_then(|p| p.default_model.clone())
+        .unwrap_or_else(|| config.llm.default_model.clone());
+    let mut pane = picode_core::Pane::new_llm_chat(provider, model, "chat".to_string());
+    let session_id = picode_core::SessionId::new();
+    let mut conversation = picode_core::ConversationLog::new(session_id.clone());
+    
+    // Edits made in this session are journaled; `/timeline` shows their versions
+    let journal = picode_core::editor::EditJournal::for_workspace(&match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    });
+    
+    // A named session that was saved before resumes its conversation
+    let mut recorder = SessionRecorder::open(&config, opts.session.clone()).await?;
+    if let Some(stored) = recorder.load().await? {
+        println!("{} Resuming session '{}' ({} messages)", tier.symbol(StatusSymbol::Success), recorder.name(), stored.messages.len());
+        conversation = stored;
+        if !conversation.composer.queued.is_empty() {
+            println!(
+                "📝 {} follow-up(s) still queued; /queue send sends them, /queue clear drops them",
+                conversation.composer.queued.len()
+            );
+        }
+    }
+    
+    // Sampling parameters of the chat; `/preset` switches them
+    let presets = Presets::load(
+        &config,
+        &match &config.workspace.root_dir {
+            Some(root) => root.clone(),
+            None => std::env::current_dir()?,
+        },
+    )
+    .await?;
+    let mut chat_preset = (presets.default_name().to_string(), presets.default_preset());
+    
+    // A session template replaces the default chat pane and seeds the context
+    let mut template_panes = Vec::new();
+    if let Some(name) = &opts.template {
+        let root = match &config.workspace.root_dir {
+            Some(root) => root.clone(),
+            None => std::env::current_dir()?,
+        };
+        let session = SessionTemplate::load(&root, name).await?.instantiate(&root, &config).await?;
+        println!(
+            "{} Session template '{}' (layout: {})",
+            tier.symbol(StatusSymbol::Success),
+            name,
+            session.layout.as_deref().unwrap_or(&opts.layout)
+        );
+        for template_pane in &session.panes {
+            println!("  - {} {:?}", template_pane.title, template_pane.pane_type);
+        }
+        if let Some(chat) = session.chat_pane() {
+            pane = chat.clone();
+        }
+        if let Some(text) = pane.system_prompt_override() {
+            system_prompt.set_layer(picode_core::PromptLayerKind::Pane, format!("template {}", name), text);
+        }
+        conversation.pinned.extend(session.pinned);
+        if let Some(name) = session.preset {
+            chat_preset = (name.clone(), presets.get(&name)?.clone());
+        }
+        if let Some(prompt) = session.starter_prompt {
+            println!("> {}", prompt);
+            conversation.push(picode_core::ConversationMessage::new("user", prompt));
+        }
+        template_panes = session.panes;
+        println!();
+    }
+    pane.activate();
+    
+    // Typed lines go to the chat pane unless `/broadcast` sends them to the
+    // template's terminal panes
+    let mut panes = picode_core::PaneManager::new();
+    panes.add(pane.clone());
+    for template_pane in template_panes.into_iter().filter(|template_pane| template_pane.id != pane.id) {
+        panes.add(template_pane);
+    }
+    let events = picode_core::EventBus::new(64, 256);
+    if let Some(webhooks) = crate::webhooks::WebhookDispatcher::from_config(&config.webhooks)? {
+        events.register_handler(Box::new(webhooks)).await;
+    }
+    let mut catalog = ModelCatalog::new();
+    let mut attachments = PendingAttachments::new();
+    
+    // Pinned files are watched on disk; changes are announced and, once
+    // refreshed, sent as a delta with the next prompt
+    let context_watcher = picode_core::ContextWatcher::new(
+        match &config.workspace.root_dir {
+            Some(root) => root.clone(),
+            None => std::env::current_dir()?,
+        },
+        picode_core::io::default_file_system(),
+        events.clone(),
+        session_id.clone(),
+        config.workspace.watch.clone(),
+    );
+    context_watcher.spawn_poller();
+    if let picode_core::WatchStatus::Polling { reason, .. } = context_watcher.status() {
+        if config.workspace.watch.mode != picode_core::WatchMode::Poll {
+            println!("{}", tr!("interactive-watch-fallback", reason = reason));
+        }
+    }
+    events.register_handler(Box::new(StaleContextNotice)).await;
+    let mut pending_context: Option<picode_core::ContextUpdate> = None;
     
     loop {
-        // Simple prompt for now
-        print!("picode> ");
+        // Simple prompt for now, marked while pinned files are stale
+        context_watcher.watch(&conversation.pinned).await;
+        let stale = context_watcher.stale();
+        if !stale.is_empty() {
+            print!("{}", tr!("interactive-context-stale", count = stale.len()));
+        }
+        if let picode_core::WatchStatus::Polling { interval, .. } = context_watcher.status() {
+            if !conversation.pinned.is_empty() {
+                print!("{}", tr!("interactive-watch-polling", seconds = interval.as_secs().max(1)));
+            }
+        }
+        if panes.is_broadcasting() {
+            print!("{}", tr!("interactive-broadcast", panes = broadcast_titles(&panes)));
+        }
+        print!("{}", tr!("interactive-prompt"));
         std::io::Write::flush(&mut std::io::stdout()).unwrap();
         
-        let mut input = String::new();
-        match std::io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let input = input.trim();
+        // Follow-ups queued during the last reply go out before new input
+        let queued = if send_queued { conversation.composer.next_queued() } else { None };
+        send_queued = queued.is_some();
+        let line = if let Some(queued) = queued {
+            println!("{}", queued);
+            Ok(queued)
+        } else if line_editor {
+            crate::chat_input::read_line(&mentions, &registry, conversation.composer.take_draft())
+        } else {
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input).map(|_| input).map_err(Into::into)
+        };
+        match line {
+            Ok(input) => {
+                let mut input = input.trim().to_string();
+                
+                // Line mode delivers Ctrl-P as a control character
+                if input == "\u{10}" || input == "/palette" {
+                    match crate::palette::run(&registry, &recent) {
+                        Ok(Some(choice)) => input = choice,
+                        Ok(None) => continue,
+                        Err(err) => {
+                            println!("{}", tr!("interactive-error", what = "Palette", error = err));
+                            continue;
+                        }
+                    }
+                }
+                // Ctrl-R refreshes stale pinned files
+                if input == "\u{12}" {
+                    input = "/context refresh".to_string();
+                }
+                if input.split_whitespace().next().and_then(|name| registry.get(name)).is_some() {
+                    recent.record(&input);
+                }
+                let input = input.as_str();
                 
                 match input {
-                    "/help" => {
-                        println!("PiCode Help:");
-                        println!("  Interactive terminal workspace with AI assistance");
-                        println!("  Use slash commands to interact with the system");
+                    cmd if cmd.starts_with("/help") => {
+                        let topic = cmd.trim_start_matches("/help").trim();
+                        if topic.is_empty() {
+                            println!("{}", tr!("interactive-help-title"));
+                            println!("  {}", tr!("interactive-help-tagline"));
+                            for line in registry.help_lines() {
+                                println!("{}", line);
+                            }
+                        } else {
+                            match registry.help_for(topic) {
+                                Some(help) => println!("{}", help),
+                                None => println!("{}", tr!("interactive-unknown-command", command = topic)),
+                            }
+                        }
                     },
                     "/analyze" => {
-                        println!("Analyzing project structure...");
-                        println!("Workspace: {:?}", config.workspace);
+                        println!("{}", tr!("interactive-analyzing"));
+                        let stats = match crate::stats::workspace_root(&config) {
+                            Ok(root) => crate::stats::workspace_stats(&root).await,
+                            Err(err) => Err(err),
+                        };
+                        match stats {
+                            Ok(stats) => print!("{}", stats.render()),
+                            Err(err) => println!("{}", tr!("interactive-error", what = "Analyze", error = err)),
+                        }
                     },
-                    "/edit" => {
-                        println!("AI-powered editing not yet implemented");
+                    "/raw" => {
+                        ansi_policy = if ansi_policy == AnsiPolicy::Raw {
+                            config.ui.ansi_policy
+                        } else {
+                            AnsiPolicy::Raw
+                        };
+                        println!("{}", tr!("interactive-escape-handling", policy = format!("{:?}", ansi_policy)));
                     },
                     "/exit" => {
-                        println!("Goodbye!");
+                        if opts.session.is_some() || recorder.is_saved() {
+                            if let Err(err) = recorder.save(&mut conversation).await {
+                                println!("{}", tr!("interactive-error", what = "Session", error = err));
+                            }
+                        }
+                        println!("{}", tr!("interactive-goodbye"));
                         break;
                     },
                     "" => continue,
+                    cmd if cmd.starts_with("/system") => {
+                        let args = cmd.trim_start_matches("/system").trim();
+                        if let Err(err) = handle_system_command(args, &mut system_prompt) {
+                            println!("{}", tr!("interactive-error", what = "System prompt", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/edit") => {
+                        let target = cmd.trim_start_matches("/edit").trim();
+                        let (linked, target) = match target.strip_prefix("--chat") {
+                            Some(rest) => (true, rest.trim()),
+                            None => (false, target),
+                        };
+                        let result = match crate::editor::resolve_path(config.workspace.root_dir.as_deref(), target) {
+                            Ok(path) if linked => {
+                                handle_linked_edit(&path, &config, &journal, &pane, &chat_preset.1, &system_prompt, &mut conversation).await
+                            }
+                            Ok(path) => crate::editor::run(&path, &config, &journal).await,
+                            Err(err) => Err(err),
+                        };
+                        if let Err(err) = result {
+                            println!("{}", tr!("interactive-error", what = "Editor", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/timeline") => {
+                        let args = cmd.trim_start_matches("/timeline").trim();
+                        if let Err(err) = handle_timeline_command(args, &config, &journal).await {
+                            println!("{}", tr!("interactive-error", what = "Timeline", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/run") => {
+                        let args = cmd.trim_start_matches("/run").trim();
+                        if let Err(err) = handle_run_command(args, &config, &session_id, ansi_policy, &mut attachments, &events).await {
+                            println!("{}", tr!("interactive-error", what = "Run", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/open") => {
+                        let target = cmd.trim_start_matches("/open").trim();
+                        if let Err(err) = handle_open_command(target, &config, &session_id, &pane, &mut conversation, &events).await {
+                            println!("{}", tr!("interactive-error", what = "Open", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/model") => {
+                        let args = cmd.trim_start_matches("/model").trim();
+                        if let Err(err) = handle_model_command(args, &config, &mut catalog, &mut pane, &mut conversation).await {
+                            println!("{}", tr!("interactive-error", what = "Model", error = err));
+                            let provider = pane.llm_model().map(|(provider, _)| provider.to_string()).unwrap_or_default();
+                            let event = picode_core::Event::LLMError {
+                                session_id: session_id.clone(),
+                                pane_id: pane.id.clone(),
+                                provider,
+                                error: err.to_string(),
+                            };
+                            if let Err(err) = events.publish(event, "interactive".to_string()).await {
+                                error!("Failed to publish event: {}", err);
+                            }
+                        }
+                    },
+                    cmd if cmd.starts_with("/health") => {
+                        match cmd.trim_start_matches("/health").trim() {
+                            "" => {},
+                            "check" => crate::health::check_all(&config).await,
+                            other => {
+                                println!("{}", tr!("interactive-unknown-command", command = format!("/health {}", other)));
+                                continue;
+                            }
+                        }
+                        let rows = crate::health::collect(&config, crate::metrics::Metrics::global());
+                        let (_, dashboard) = crate::health::render(&rows, chrono::Utc::now());
+                        print!("{}", dashboard);
+                    },
+                    "/context refresh" => {
+                        let update = context_watcher.refresh(&mut conversation).await;
+                        if update.is_empty() {
+                            println!("{}", tr!("interactive-context-current"));
+                        } else {
+                            println!("{}", tr!("interactive-context-refreshed", count = update.deltas.len()));
+                            let pending = pending_context.get_or_insert_with(|| picode_core::ContextUpdate {
+                                turn: update.turn,
+                                ..Default::default()
+                            });
+                            pending.deltas.extend(update.deltas);
+                            pending.unchanged = update.unchanged;
+                        }
+                    },
+                    cmd if cmd.starts_with("/context") => {
+                        let args = cmd.trim_start_matches("/context").trim();
+                        if let Err(err) = handle_context_command(
+                            args,
+                            &config,
+                            &mut catalog,
+                            &pane,
+                            &mut system_prompt,
+                            &mut conversation,
+                        )
+                        .await
+                        {
+                            println!("{}", tr!("interactive-error", what = "Context", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/pack") => {
+                        let args = cmd.trim_start_matches("/pack").trim();
+                        if let Err(err) = handle_pack_command(args, &config, &mut conversation).await {
+                            println!("{}", tr!("interactive-error", what = "Pack", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/tag") || cmd.starts_with("/note") => {
+                        if let Err(err) = handle_annotation_command(cmd, &mut conversation, &mut recorder).await {
+                            println!("{}", tr!("interactive-error", what = "Annotation", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/bookmark") => {
+                        let args = cmd.trim_start_matches("/bookmark").trim();
+                        if let Err(err) = handle_bookmark_command(args).await {
+                            println!("{}", tr!("interactive-error", what = "Bookmark", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/files") => {
+                        let dir = cmd.trim_start_matches("/files").trim();
+                        if let Err(err) = handle_files_command(dir, &config).await {
+                            println!("{}", tr!("interactive-error", what = "Files", error = err));
+                        }
+                    },
+                    "/retry" => {
+                        let chat = ChatSettings {
+                            config: &config,
+                            pane: &pane,
+                            preset: &chat_preset.1,
+                            system_prompt: &system_prompt,
+                            renderer: &renderer,
+                        };
+                        if let Err(err) = handle_retry_command(&chat, &mut conversation, capabilities.colors).await
+                        {
+                            println!("{}", tr!("interactive-error", what = "Retry", error = err));
+                        }
+                        if config.session.auto_save {
+                            if let Err(err) = recorder.save(&mut conversation).await {
+                                println!("{}", tr!("interactive-error", what = "Session", error = err));
+                            }
+                        }
+                    },
+                    cmd if cmd.starts_with("/image") => {
+                        let path = cmd.trim_start_matches("/image").trim();
+                        if let Err(err) = handle_image_command(path, &renderer).await {
+                            println!("{}", tr!("interactive-error", what = "Image", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/todos") => {
+                        let args = cmd.trim_start_matches("/todos").trim();
+                        if let Err(err) = handle_todos_command(args, &config).await {
+                            println!("{}", tr!("interactive-error", what = "TODOs", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/preset") => {
+                        let args = cmd.trim_start_matches("/preset").trim();
+                        if let Err(err) = handle_preset_command(args, &presets, &mut chat_preset) {
+                            println!("{}", tr!("interactive-error", what = "Preset", error = err));
+                        }
+                    },
+                    cmd if cmd.starts_with("/queue") => {
+                        let args = cmd.trim_start_matches("/queue").trim();
+                        match handle_queue_command(args, &mut conversation.composer) {
+                            Ok(send) => send_queued |= send,
+                            Err(err) => println!("{}", tr!("interactive-error", what = "Queue", error = err)),
+                        }
+                    },
+                    cmd if cmd.starts_with("/broadcast") => {
+                        let args = cmd.trim_start_matches("/broadcast").trim();
+                        if let Err(err) = handle_broadcast_command(args, &mut panes) {
+                            println!("{}", tr!("interactive-error", what = "Broadcast", error = err));
+                        }
+                    },
+                    line if !line.starts_with('/') && panes.is_broadcasting() => {
+                        handle_broadcast_input(line, &mut panes, ansi_policy, &session_id, &events).await;
+                    },
+                    prompt if !prompt.starts_with('/') => {
+                        let prompt = attachments.take_prompt(prompt);
+                        let prompt = mentions.expand(&prompt).await;
+                        let context = pending_context.take();
+                        let chat = ChatSettings {
+                            config: &config,
+                            pane: &pane,
+                            preset: &chat_preset.1,
+                            system_prompt: &system_prompt,
+                            renderer: &renderer,
+                        };
+                        if let Err(err) = handle_chat_prompt(&prompt, context, &chat, &mut conversation).await
+                        {
+                            println!("{}", tr!("interactive-error", what = "Chat", error = err));
+                        }
+                        send_queued = true;
+                        // Coalesced by the recorder, so this is cheap per message
+                        let unsent = !conversation.composer.is_empty() && recorder.is_saved();
+                        if config.session.auto_save || unsent {
+                            if let Err(err) = recorder.save(&mut conversation).await {
+                                println!("{}", tr!("interactive-error", what = "Session", error = err));
+                            }
+                        }
+                    },
                     _ => {
-                        println!("Unknown command: {}. Type /help for available commands.", input);
+                        println!("{}", tr!("interactive-unknown-command", command = input));
                     }
                 }
             },
@@ -94,6 +562,754 @@ pub async fn run(opts: InteractiveOptions, config: Config) -> Result<()> {
         }
     }
     
+    if let Err(err) = recorder.flush().await {
+        println!("{}", tr!("interactive-error", what = "Session", error = err));
+    }
     info!("Interactive mode ended");
     Ok(())
-}
\ No newline at end of file
+}
+/// Handle `/system` subcommands
+fn handle_system_command(args: &str, prompt: &mut picode_core::SystemPrompt) -> Result<()> {
+    use picode_core::PromptLayerKind;
+
+    let (subcommand, rest) = args.split_once(' ').unwrap_or((args, ""));
+    match (subcommand, rest.trim()) {
+        ("" | "show", _) => print!("{}", prompt.render_inspection()),
+        ("pane", "clear") => {
+            prompt.remove_layer(PromptLayerKind::Pane);
+            println!("Pane override cleared");
+        },
+        ("pane", text) if !text.is_empty() => {
+            prompt.set_layer(PromptLayerKind::Pane, "pane override", text);
+            println!("Pane override set (~{} tokens total)", prompt.total_tokens());
+        },
+        (other, _) => {
+            return Err(crate::error::PiCodeError::InvalidCommand(format!("/system {}", other)));
+        },
+    }
+
+    Ok(())
+}
+
+/// What chat turns are sent with
+struct ChatSettings<'a> {
+    config: &'a Config,
+    pane: &'a picode_core::Pane,
+    preset: &'a GenerationPreset,
+    system_prompt: &'a picode_core::SystemPrompt,
+    renderer: &'a InlineRenderer,
+}
+
+/// Send a chat prompt, with any attached command output and refreshed
+/// context, to the pane's model
+async fn handle_chat_prompt(
+    prompt: &str,
+    context: Option<picode_core::ContextUpdate>,
+    chat: &ChatSettings<'_>,
+    conversation: &mut picode_core::ConversationLog,
+) -> Result<()> {
+    use picode_core::ConversationMessage;
+    let ChatSettings { config, pane, preset, system_prompt, renderer } = *chat;
+
+    let (provider, model) = pane
+        .llm_model()
+        .ok_or_else(|| crate::error::PiCodeError::NotFound("chat model for this pane".to_string()))?;
+    let policy = crate::policy::ResponsePolicy::load(config, &std::env::current_dir()?).await?;
+    let assistant = crate::assistant::Assistant::for_provider(config, provider)?
+        .with_model(model)
+        .with_policy(policy)
+        .with_preset(preset.clone())
+        .with_tag(picode_llm::attribution::SESSION_TAG, conversation.session_id.to_string());
+    // Pinned files go first, compressed against the prompt when enabled
+    let compressed = crate::compress::compress_pinned(config, &conversation.pinned, prompt).await;
+    let mut request = crate::compress::render_pinned(&conversation.pinned, &compressed);
+    // Pinned files refreshed since the last turn, as what changed in them
+    if let Some(update) = context {
+        let rendered = update.render();
+        request.push_str(&rendered);
+        request.push('\n');
+        conversation.push(ConversationMessage::new("user", rendered));
+    }
+    // Recorded so `picode session rerun` can send the turn again as it was
+    let system = system_prompt.effective();
+    conversation.push_prompt(prompt, &system, &request, provider, model);
+    request.push_str(prompt);
+    // Keys typed meanwhile become follow-ups instead of reaching the next prompt
+    let mut capture = crate::composer::FollowUpCapture::start(&mut conversation.composer);
+    let reply = assistant
+        .ask_streaming(&system, &request, None, |chunk| capture.print(chunk))
+        .await;
+    println!();
+    capture.finish();
+    let reply = reply?;
+    // Diagrams in the reply are drawn below it
+    for diagram in renderer.render_reply(&reply).await {
+        print!("{}", diagram);
+    }
+    conversation.push(ConversationMessage::new("assistant", reply));
+    Ok(())
+}
+
+/// Handle `/retry`: ask the last prompt again and show a word-level diff of
+/// the new response against the one it replaces
+async fn handle_retry_command(
+    chat: &ChatSettings<'_>,
+    conversation: &mut picode_core::ConversationLog,
+    colors: bool,
+) -> Result<()> {
+    let ChatSettings { config, pane, preset, system_prompt, renderer } = *chat;
+    let (prompt, previous) = match conversation.last_exchange() {
+        Some((prompt, reply)) => (prompt.to_string(), reply.content.clone()),
+        None => return Err(crate::error::PiCodeError::NotFound("response to regenerate".to_string())),
+    };
+    let (provider, model) = pane
+        .llm_model()
+        .ok_or_else(|| crate::error::PiCodeError::NotFound("chat model for this pane".to_string()))?;
+    let policy = crate::policy::ResponsePolicy::load(config, &std::env::current_dir()?).await?;
+    let assistant = crate::assistant::Assistant::for_provider(config, provider)?
+        .with_model(model)
+        .with_policy(policy)
+        .with_preset(preset.clone())
+        .with_tag(picode_llm::attribution::SESSION_TAG, conversation.session_id.to_string());
+    let compressed = crate::compress::compress_pinned(config, &conversation.pinned, &prompt).await;
+    let request = format!("{}{}", crate::compress::render_pinned(&conversation.pinned, &compressed), prompt);
+    let reply = assistant
+        .ask_streaming(&system_prompt.effective(), &request, None, |chunk| {
+            print!("{}", chunk);
+            std::io::Write::flush(&mut std::io::stdout()).ok();
+        })
+        .await?;
+    println!();
+    for diagram in renderer.render_reply(&reply).await {
+        print!("{}", diagram);
+    }
+
+    let diff = picode_core::WordDiff::new(&previous, &reply);
+    if diff.is_unchanged() {
+        println!("The regenerated response is identical to the previous one");
+    } else {
+        println!();
+        println!("Changes from the previous response (~{:.0}% of words):", diff.change_ratio() * 100.0);
+        let rendered = if colors { diff.render_ansi() } else { diff.render_markdown() };
+        println!("{}", rendered);
+    }
+    conversation.regenerate_last(reply);
+    Ok(())
+}
+
+/// Handle `/edit --chat`: the editor linked to a chat with the pane's model
+async fn handle_linked_edit(
+    path: &std::path::Path,
+    config: &Config,
+    journal: &picode_core::editor::EditJournal,
+    pane: &picode_core::Pane,
+    preset: &GenerationPreset,
+    system_prompt: &picode_core::SystemPrompt,
+    conversation: &mut picode_core::ConversationLog,
+) -> Result<()> {
+    let (provider, model) = pane
+        .llm_model()
+        .ok_or_else(|| crate::error::PiCodeError::NotFound("chat model for this pane".to_string()))?;
+    let policy = crate::policy::ResponsePolicy::load(config, &std::env::current_dir()?).await?;
+    let assistant = crate::assistant::Assistant::for_provider(config, provider)?
+        .with_model(model)
+        .with_policy(policy)
+        .with_preset(preset.clone())
+        .with_tag(picode_llm::attribution::SESSION_TAG, conversation.session_id.to_string());
+    crate::editor::run_linked(path, config, journal, pane, assistant, &system_prompt.effective(), conversation).await
+}
+
+/// Handle `/run`: run a command in an output pane and attach its summarized
+/// output to the next prompt; `/run clear` drops pending attachments
+async fn handle_run_command(
+    args: &str,
+    config: &Config,
+    session_id: &picode_core::SessionId,
+    ansi_policy: AnsiPolicy,
+    attachments: &mut PendingAttachments,
+    events: &picode_core::EventBus,
+) -> Result<()> {
+    use picode_core::Event;
+
+    if args == "clear" {
+        println!("Dropped {} pending attachment(s)", attachments.clear());
+        return Ok(());
+    }
+    let request = RunRequest::parse(args)?;
+    let root = match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    let (pane, result) = crate::attachments::run(&request.command, &root).await?;
+    print!("{}", crate::attachments::render_output(&pane, &result, ansi_policy));
+
+    let pane_created = Event::PaneCreated {
+        session_id: session_id.clone(),
+        pane_id: pane.id.clone(),
+        pane_type: pane.pane_type.clone(),
+    };
+    let completed = Event::CommandCompleted {
+        session_id: session_id.clone(),
+        pane_id: pane.id.clone(),
+        command_id: result.command_id.clone(),
+        status: result.status.clone(),
+        duration: result.duration,
+    };
+    for event in [pane_created, completed] {
+        if let Err(err) = events.publish(event, "interactive".to_string()).await {
+            error!("Failed to publish event: {}", err);
+        }
+    }
+
+    if request.attach {
+        let attachment = attachments.attach(&request.command, &result);
+        println!(
+            "Attached to your next prompt (~{} tokens); /run clear drops it",
+            picode_core::system_prompt::estimate_tokens(&attachment.summary)
+        );
+    }
+    Ok(())
+}
+
+/// Handle `/queue [send | clear]`: list the follow-ups queued during a
+/// reply, send them now or drop them; returns whether to send them
+fn handle_queue_command(args: &str, composer: &mut picode_core::Composer) -> Result<bool> {
+    match args {
+        "" => {
+            if composer.queued.is_empty() {
+                println!("No queued follow-ups");
+            }
+            for (i, message) in composer.queued.iter().enumerate() {
+                println!("{}. {}", i + 1, message);
+            }
+            Ok(false)
+        }
+        "send" => Ok(!composer.queued.is_empty()),
+        "clear" => {
+            println!("Dropped {} queued follow-up(s)", composer.clear_queue());
+            Ok(false)
+        }
+        _ => Err(crate::error::PiCodeError::InvalidCommand("usage: /queue [send | clear]".to_string())),
+    }
+}
+
+/// Handle `/todos [all | done <id> [note]]`: rescan and list the
+/// workspace's TODO items, or mark one done
+async fn handle_todos_command(args: &str, config: &Config) -> Result<()> {
+    use picode_core::todos::TodoStatus;
+
+    let root = crate::stats::workspace_root(config)?;
+    let (mut list, changes) = crate::todos::refresh(&root).await?;
+    for item in &changes.resolved {
+        println!("✅ Resolved {} {} {}", item.id, item.kind, item.location());
+    }
+    if let Some(rest) = args.strip_prefix("done") {
+        let (id, note) = rest.trim().split_once(' ').unwrap_or((rest.trim(), "marked done"));
+        if id.is_empty() {
+            return Err(crate::error::PiCodeError::InvalidCommand("usage: /todos done <id> [note]".to_string()));
+        }
+        let item = list.mark_done(id, note.trim()).map_err(picode_core::CoreError::from)?;
+        println!("✅ {} {} {} done", item.id, item.kind, item.location());
+        list.save(&picode_core::NativeFileSystem).await.map_err(picode_core::CoreError::from)?;
+        return Ok(());
+    }
+
+    let all = args == "all";
+    let mut shown = 0;
+    for item in list.items.iter().filter(|item| all || item.status == TodoStatus::Open) {
+        let owner = item.owner.as_deref().map(|owner| format!(" ({})", owner)).unwrap_or_default();
+        let done = if item.status == TodoStatus::Done { " [done]" } else { "" };
+        println!("{} {} {}{} {}{}", item.id, item.kind, item.location(), owner, item.text, done);
+        shown += 1;
+    }
+    if shown == 0 {
+        println!("No open TODO items");
+    }
+    Ok(())
+}
+
+/// Handle `/preset [list | <name>]`: show, list or switch the chat's
+/// generation preset
+fn handle_preset_command(args: &str, presets: &Presets, current: &mut (String, GenerationPreset)) -> Result<()> {
+    match args {
+        "" => println!("Preset: {} ({})", current.0, current.1.summary()),
+        "list" => {
+            for (name, preset) in presets.iter() {
+                let marker = if *name == current.0 { "*" } else { " " };
+                match &preset.description {
+                    Some(description) => println!("{} {} - {} ({})", marker, name, description, preset.summary()),
+                    None => println!("{} {} ({})", marker, name, preset.summary()),
+                }
+            }
+        }
+        name => {
+            *current = (name.to_string(), presets.get(name)?.clone());
+            println!("Preset: {} ({})", name, current.1.summary());
+        }
+    }
+    Ok(())
+}
+
+/// Handle `/broadcast`: toggle sending typed lines to every terminal pane,
+/// or select the panes by title
+fn handle_broadcast_command(args: &str, panes: &mut picode_core::PaneManager) -> Result<()> {
+    use picode_core::CoreError;
+
+    match args {
+        "off" => panes.stop_broadcast(),
+        "" if panes.is_broadcasting() => panes.stop_broadcast(),
+        "" | "all" => {
+            if panes.broadcast_all() == 0 {
+                return Err(crate::error::PiCodeError::InvalidCommand(
+                    "no terminal panes to broadcast to; add them to a session template".to_string(),
+                ));
+            }
+        }
+        titles => {
+            let ids = titles
+                .split_whitespace()
+                .map(|title| {
+                    panes
+                        .find(title)
+                        .map(|pane| pane.id.clone())
+                        .ok_or_else(|| crate::error::PiCodeError::NotFound(format!("pane '{}'", title)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            panes.broadcast_to(&ids).map_err(CoreError::from)?;
+        }
+    }
+    if panes.is_broadcasting() {
+        println!("{}", tr!("interactive-broadcast-on", panes = broadcast_titles(panes)));
+    } else {
+        println!("{}", tr!("interactive-broadcast-off"));
+    }
+    Ok(())
+}
+
+/// Titles of the panes broadcast to, for the prompt marker
+fn broadcast_titles(panes: &picode_core::PaneManager) -> String {
+    panes.broadcast_panes().iter().map(|pane| pane.title.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+/// Run a line typed while broadcasting in every selected terminal pane at
+/// once, showing each pane's output in turn
+async fn handle_broadcast_input(
+    command: &str,
+    panes: &mut picode_core::PaneManager,
+    ansi_policy: AnsiPolicy,
+    session_id: &picode_core::SessionId,
+    events: &picode_core::EventBus,
+) {
+    use picode_core::Event;
+
+    let targets = panes.route_input();
+    let targets: Vec<picode_core::Pane> = targets.iter().filter_map(|id| panes.get(id).cloned()).collect();
+    let runs = targets.iter().map(|pane| crate::attachments::run_in_pane(pane, command));
+    for (pane, result) in targets.iter().zip(futures::future::join_all(runs).await) {
+        match result {
+            Ok(result) => {
+                print!("{}", crate::attachments::render_output(pane, &result, ansi_policy));
+                let completed = Event::CommandCompleted {
+                    session_id: session_id.clone(),
+                    pane_id: pane.id.clone(),
+                    command_id: result.command_id.clone(),
+                    status: result.status.clone(),
+                    duration: result.duration,
+                };
+                if let Err(err) = events.publish(completed, "interactive".to_string()).await {
+                    error!("Failed to publish event: {}", err);
+                }
+            }
+            Err(err) => println!("{}", tr!("interactive-error", what = pane.title, error = err)),
+        }
+    }
+}
+
+/// Handle `/tag [rm] <tag>...` and `/note <text>`; the conversation is
+/// saved with its session right away
+async fn handle_annotation_command(
+    cmd: &str,
+    conversation: &mut picode_core::ConversationLog,
+    recorder: &mut SessionRecorder,
+) -> Result<()> {
+    use picode_core::CoreError;
+
+    if let Some(text) = cmd.strip_prefix("/note") {
+        let text = text.trim().trim_matches('"');
+        if text.is_empty() {
+            return Err(crate::error::PiCodeError::InvalidCommand("/note requires text".to_string()));
+        }
+        let note = conversation.note(text).map_err(CoreError::from)?;
+        println!("Noted on message {}", note.message + 1);
+    } else {
+        let args = cmd.trim_start_matches("/tag").trim();
+        let (remove, tags) = match args.strip_prefix("rm ") {
+            Some(tags) => (true, tags),
+            None => (false, args),
+        };
+        for tag in tags.split_whitespace() {
+            if remove {
+                conversation.annotations.untag(tag).map_err(CoreError::from)?;
+            } else {
+                conversation.annotations.tag(tag).map_err(CoreError::from)?;
+            }
+        }
+        let tags: Vec<String> = conversation.annotations.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        println!("Tags: {}", if tags.is_empty() { "(none)".to_string() } else { tags.join(" ") });
+    }
+    recorder.save(conversation).await?;
+    println!("Saved to session '{}'", recorder.name());
+    Ok(())
+}
+
+/// Handle `/timeline`: list, diff and restore versions of files edited in
+/// the session
+async fn handle_timeline_command(args: &str, config: &Config, journal: &picode_core::editor::EditJournal) -> Result<()> {
+    use crate::timeline::TimelineCommand;
+
+    let root = match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    print!("{}", crate::timeline::run(TimelineCommand::parse(args)?, journal, &root).await?);
+    Ok(())
+}
+
+/// Handle `/open`: edit a file in the external editor and bring the saved
+/// change into the conversation
+async fn handle_open_command(
+    target: &str,
+    config: &Config,
+    session_id: &picode_core::SessionId,
+    pane: &picode_core::Pane,
+    conversation: &mut picode_core::ConversationLog,
+    events: &picode_core::EventBus,
+) -> Result<()> {
+    use crate::open::{ExternalEditor, OpenTarget};
+
+    let target = OpenTarget::parse(target)?.resolve(config.workspace.root_dir.as_deref());
+    let edit = crate::open::edit(&ExternalEditor::from_config(config), &target).await?;
+    println!("{}", crate::open::describe(&edit));
+    if let Some(event) = crate::open::reingest(&edit, conversation, session_id, &pane.id) {
+        if let Err(err) = events.publish(event, "interactive".to_string()).await {
+            error!("Failed to publish event: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Handle `/model`: list the configured providers' models or switch the
+/// active pane to one of them
+async fn handle_model_command(
+    args: &str,
+    config: &Config,
+    catalog: &mut ModelCatalog,
+    pane: &mut picode_core::Pane,
+    conversation: &mut picode_core::ConversationLog,
+) -> Result<()> {
+    use picode_core::ConversationMessage;
+
+    if args == "refresh" {
+        catalog.refresh();
+    }
+    let choices = catalog.choices(config).await;
+    let active = pane
+        .llm_model()
+        .map(|(provider, model)| format!("{}/{}", provider, model))
+        .unwrap_or_default();
+
+    match args {
+        "" | "list" | "refresh" => {
+            if choices.is_empty() {
+                println!("No providers available. Configure one under [llm.providers]");
+            }
+            for (i, choice) in choices.iter().enumerate() {
+                let marker = if choice.label() == active { "*" } else { " " };
+                println!("{} {:>2}. {}", marker, i + 1, choice.describe());
+            }
+            println!("Active: {}. Switch with /model <n|provider/model>", active);
+        },
+        selector => {
+            let choice = ModelCatalog::resolve(&choices, selector)
+                .ok_or_else(|| crate::error::PiCodeError::NotFound(format!("model '{}'", selector)))?;
+            let (provider, model) = pane
+                .switch_model(choice.provider.clone(), choice.model.id.clone())
+                .map_err(picode_core::CoreError::from)?;
+            let note = format!("model switched: {}/{} -> {}", provider, model, choice.label());
+            conversation.push(ConversationMessage::annotation(note.clone()));
+            println!("{}", note);
+        },
+    }
+
+    Ok(())
+}
+
+/// Handle `/pack`: list context packs, attach one or drop it
+async fn handle_pack_command(args: &str, config: &Config, conversation: &mut picode_core::ConversationLog) -> Result<()> {
+    let root = crate::stats::workspace_root(config)?;
+    let packs = crate::packs::ContextPacks::load(config, &root).await?;
+    let (subcommand, rest) = args.split_once(' ').unwrap_or((args, ""));
+    match (subcommand, rest.trim()) {
+        ("" | "list", _) => {
+            let attached = crate::packs::attached(conversation);
+            if packs.iter().next().is_none() {
+                println!("No context packs; define them in [packs.<name>] of {}", crate::tasks::WORKSPACE_CONFIG_FILE);
+            }
+            for (name, pack) in packs.iter() {
+                let status = match attached.iter().find(|(attached, _)| attached == name) {
+                    Some((_, version)) if *version == pack.version => " (attached)".to_string(),
+                    Some((_, version)) => format!(" (v{} attached)", version),
+                    None => String::new(),
+                };
+                println!(
+                    "  {} v{}{} - {}",
+                    name,
+                    pack.version,
+                    status,
+                    pack.description.as_deref().unwrap_or("(no description)")
+                );
+            }
+        },
+        ("drop", name) if !name.is_empty() => {
+            let removed = packs.detach(name, &root, conversation).await?;
+            println!("Dropped pack {} ({} item(s))", name, removed);
+        },
+        (name, "") => {
+            let attached = packs.attach(name, &root, conversation, config.workspace.max_file_size).await?;
+            println!("Attached pack {}: {} file(s), ~{} tokens", name, attached.files, attached.tokens);
+            if !attached.skipped.is_empty() {
+                println!("Skipped {} file(s): {}", attached.skipped.len(), attached.skipped.join(", "));
+            }
+        },
+        (other, _) => {
+            return Err(crate::error::PiCodeError::InvalidCommand(format!("/pack {}", other)));
+        },
+    }
+
+    Ok(())
+}
+
+/// What `/context pin` pins of a file
+#[derive(Debug, Clone, PartialEq)]
+enum PinPart {
+    Whole,
+    Lines(picode_core::LineRange),
+    Symbol(String),
+}
+
+/// Split `path`, `path:start-end` or `path#symbol`
+fn pin_target(spec: &str) -> Result<(&str, PinPart)> {
+    if let Some((path, symbol)) = spec.rsplit_once('#').filter(|(path, symbol)| !path.is_empty() && !symbol.is_empty()) {
+        return Ok((path, PinPart::Symbol(symbol.to_string())));
+    }
+    // Only a trailing line range splits, so Windows drive letters stay
+    match spec.rsplit_once(':') {
+        Some((path, range)) if !path.is_empty() && range.starts_with(|c: char| c.is_ascii_digit()) => {
+            let range = range.parse().map_err(|e: picode_core::large_file::InvalidRange| {
+                crate::error::PiCodeError::InvalidCommand(e.to_string())
+            })?;
+            Ok((path, PinPart::Lines(range)))
+        }
+        _ => Ok((spec, PinPart::Whole)),
+    }
+}
+
+/// Handle `/context`: show what the next request is made of, pin files and
+/// prune items
+async fn handle_context_command(
+    args: &str,
+    config: &Config,
+    catalog: &mut ModelCatalog,
+    pane: &picode_core::Pane,
+    system_prompt: &mut picode_core::SystemPrompt,
+    conversation: &mut picode_core::ConversationLog,
+) -> Result<()> {
+    use picode_core::context_inspector::ContextItemSource;
+    use picode_core::conversation::PinnedItem;
+    use picode_core::io::FileSystem;
+    use picode_core::{ContextBreakdown, NativeFileSystem};
+
+    let (subcommand, rest) = args.split_once(' ').unwrap_or((args, ""));
+    match subcommand {
+        "" | "show" => {
+            let active = pane.llm_model().map(|(provider, model)| format!("{}/{}", provider, model));
+            let window = catalog
+                .choices(config)
+                .await
+                .into_iter()
+                .find(|choice| Some(choice.label()) == active)
+                .and_then(|choice| choice.model.context_window)
+                .map(|tokens| tokens as usize);
+            let mut breakdown = ContextBreakdown::build(system_prompt, conversation);
+            if config.compression.level != picode_core::CompressionLevel::Off {
+                let query = conversation
+                    .messages
+                    .iter()
+                    .rev()
+                    .find(|m| m.role == "user" && !m.is_context_update())
+                    .map(|m| m.content.as_str())
+                    .unwrap_or_default();
+                let compressed = crate::compress::compress_pinned(config, &conversation.pinned, query).await;
+                for (index, compressed) in compressed.iter().enumerate() {
+                    breakdown.set_compressed(ContextItemSource::Pinned(index), compressed.tokens);
+                }
+            }
+            print!("{}", breakdown.render(window));
+        },
+        "pin" => {
+            let (spec, part) = pin_target(rest.trim())?;
+            let path = crate::editor::resolve_path(config.workspace.root_dir.as_deref(), spec)?;
+            let large_files = &config.workspace.large_files;
+            let size = tokio::fs::metadata(&path).await?.len();
+            let pinned = match part {
+                PinPart::Whole if large_files.is_large(size) => {
+                    return Err(crate::error::PiCodeError::InvalidCommand(format!(
+                        "{} is too large to pin whole ({} bytes); pin lines with /context pin {}:1-{} or a definition with /context pin {}#<symbol>",
+                        spec, size, spec, large_files.window_lines, spec
+                    )));
+                }
+                PinPart::Whole => PinnedItem::new(spec, tokio::fs::read_to_string(&path).await?),
+                PinPart::Lines(range) => {
+                    let window = NativeFileSystem.read_window(&path, range).await?;
+                    PinnedItem::new(format!("{}:{}", spec, window.range), window.text)
+                }
+                PinPart::Symbol(symbol) => {
+                    let (mapped_path, name, max_lines) = (path.clone(), symbol.clone(), large_files.window_lines);
+                    let range = tokio::task::spawn_blocking(move || {
+                        picode_core::large_file::MappedFile::open(&mapped_path).map(|mapped| mapped.find_symbol(&name, max_lines))
+                    })
+                    .await
+                    .map_err(|e| crate::error::PiCodeError::Internal(e.to_string()))??
+                    .ok_or_else(|| crate::error::PiCodeError::NotFound(format!("definition of '{}' in {}", symbol, spec)))?;
+                    let window = NativeFileSystem.read_window(&path, range).await?;
+                    PinnedItem::new(format!("{}#{} (lines {})", spec, symbol, window.range), window.text)
+                }
+            };
+            println!("Pinned {} (~{} tokens)", pinned.label, picode_core::system_prompt::estimate_tokens(&pinned.content));
+            conversation.pinned.push(pinned);
+        },
+        "drop" => {
+            let numbers = rest
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|part| !part.is_empty())
+                .map(|part| {
+                    part.parse::<usize>()
+                        .map_err(|_| crate::error::PiCodeError::InvalidCommand(format!("/context drop {}", part)))
+                })
+                .collect::<Result<Vec<usize>>>()?;
+            let breakdown = ContextBreakdown::build(system_prompt, conversation);
+            let removed = breakdown.prune(&numbers, system_prompt, conversation);
+            println!(
+                "Removed {} item(s); context is now ~{} tokens",
+                removed,
+                ContextBreakdown::build(system_prompt, conversation).total_tokens()
+            );
+        },
+        other => {
+            return Err(crate::error::PiCodeError::InvalidCommand(format!("/context {}", other)));
+        },
+    }
+
+    Ok(())
+}
+
+/// Handle `/image <path>`: show an image inline, or describe it
+async fn handle_image_command(path: &str, renderer: &InlineRenderer) -> Result<()> {
+    if path.is_empty() {
+        return Err(crate::error::PiCodeError::InvalidCommand("/image requires a path".to_string()));
+    }
+    print!("{}", renderer.render_image(std::path::Path::new(path)).await?);
+    Ok(())
+}
+
+/// Handle `/files [dir]`: the workspace file tree, with the files other
+/// sessions and agent runs hold locks on
+async fn handle_files_command(dir: &str, config: &Config) -> Result<()> {
+    use picode_core::file_locks::render_tree;
+
+    let root = crate::stats::workspace_root(config)?;
+    let mut workspace = picode_core::Workspace::new(picode_core::WorkspaceConfig {
+        root_path: root.clone(),
+        git_enabled: false,
+        ..picode_core::WorkspaceConfig::default()
+    });
+    workspace.scan().await.map_err(picode_core::CoreError::from)?;
+    let locks = picode_core::FileLockService::new(
+        root,
+        std::sync::Arc::new(picode_core::NativeFileSystem),
+        "interactive",
+        config.agent.locks,
+    )
+    .list()
+    .await
+    .map_err(picode_core::CoreError::from)?;
+
+    let prefix = dir.trim_matches('/');
+    let mut paths: Vec<String> = workspace
+        .files
+        .iter()
+        .map(|file| picode_core::paths::to_slash(&file.relative_path))
+        .chain(locks.iter().map(|lock| lock.path.clone()))
+        .filter(|path| prefix.is_empty() || path.starts_with(&format!("{}/", prefix)))
+        .collect();
+    paths.sort();
+    paths.dedup();
+    if paths.is_empty() {
+        println!("No files under {}", if prefix.is_empty() { "the workspace" } else { prefix });
+        return Ok(());
+    }
+    print!("{}", render_tree(&paths, &locks, chrono::Utc::now()));
+    if !locks.is_empty() {
+        println!("🔒 {} file(s) locked for editing", locks.len());
+    }
+    Ok(())
+}
+
+/// Handle `/bookmark` subcommands for the current workspace
+async fn handle_bookmark_command(args: &str) -> Result<()> {
+    use picode_core::{Bookmark, BookmarkStore};
+
+    let workspace_root = std::env::current_dir()?;
+    let mut store = BookmarkStore::load(&workspace_root)
+        .await
+        .map_err(picode_core::CoreError::from)?;
+
+    let (subcommand, rest) = args.split_once(' ').unwrap_or((args, ""));
+    match subcommand {
+        "add" => {
+            let (location, label) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+            let (path, line) = Bookmark::parse_location(location).map_err(picode_core::CoreError::from)?;
+            let label = label.trim().trim_matches('"').to_string();
+            let bookmark = Bookmark::new(path, line, label);
+            println!("Bookmarked {}", bookmark.location());
+            store.add(bookmark);
+            store.save().await.map_err(picode_core::CoreError::from)?;
+        },
+        "" | "list" => {
+            if store.list().is_empty() {
+                println!("No bookmarks yet. Use /bookmark add <path[:line]> [label]");
+            }
+            for (i, bookmark) in store.list().iter().enumerate() {
+                println!("  {}. {} {}", i + 1, bookmark.location(), bookmark.label);
+            }
+        },
+        "find" => {
+            for bookmark in store.search(rest) {
+                println!("  {} {}", bookmark.location(), bookmark.label);
+            }
+        },
+        "rm" | "remove" => {
+            let removed = store.remove(rest.trim()).map_err(picode_core::CoreError::from)?;
+            store.save().await.map_err(picode_core::CoreError::from)?;
+            println!("Removed bookmark {}", removed.location());
+        },
+        "context" => {
+            print!("{}", store.to_context());
+        },
+        other => {
+            return Err(crate::error::PiCodeError::InvalidCommand(format!("/bookmark {}", other)));
+        },
+    }
+
+    Ok(())
+}
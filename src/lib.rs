@@ -1,19 +1,96 @@
 //! PiCode - A terminal workspace with AI capabilities
 //! 
 //! Claude Code compatible with OpenAPI LLMs, built on Rust and inspired by Zellij architecture.
+//!
+//! To embed PiCode in another application, use [`Engine`] and build without
+//! the default features (`features = ["native"]`): the `cli` and `tui`
+//! features only carry the binary's argument parsing and terminal UI.
 
 // Re-export main modules for easy access
+#[cfg(feature = "cli")]
 pub mod cli;
 pub mod config;
 pub mod error;
+pub mod i18n;
 pub mod logging;
+pub mod telemetry;
+pub mod terminal;
+pub mod images;
 
 // Interactive and execution modules
+#[cfg(feature = "tui")]
 pub mod interactive;
 pub mod execute;
+pub mod print;
+#[cfg(feature = "tui")]
+pub mod editor;
+pub mod slash;
+#[cfg(feature = "tui")]
+pub mod palette;
+#[cfg(feature = "tui")]
+pub mod chat_input;
+#[cfg(feature = "tui")]
+pub mod composer;
+pub mod mentions;
+pub mod assistant;
+pub mod agent;
+pub mod attachments;
+pub mod engine;
+#[cfg(feature = "cli")]
+pub mod explain;
+pub mod open;
+pub mod diff;
+pub mod daemon;
+pub mod serve;
+pub mod mcp;
+pub mod users;
+pub mod metrics;
+pub mod health;
+pub mod history;
+pub mod rerun;
+pub mod publish;
+pub mod models;
+pub mod bench;
+pub mod eval;
+pub mod webhooks;
+#[cfg(feature = "cli")]
+pub mod git;
+#[cfg(feature = "cli")]
+pub mod bisect;
+#[cfg(feature = "cli")]
+pub mod split_commit;
+#[cfg(feature = "cli")]
+pub mod resolve;
+#[cfg(feature = "cli")]
+pub mod release;
+#[cfg(feature = "cli")]
+pub mod bench_core;
+#[cfg(feature = "tui")]
+pub mod hook_guard;
+pub mod tasks;
+pub mod policy;
+pub mod presets;
+pub mod profile_spend;
+pub mod packs;
+pub mod session_template;
+pub mod timeline;
+pub mod bundle;
+pub mod sync;
+pub mod stats;
+pub mod todos;
+#[cfg(feature = "cli")]
+pub mod review;
+pub mod update;
+pub mod trust;
+pub mod compress;
+pub mod forge;
+pub mod audit;
+#[cfg(feature = "tui")]
+pub mod work_on;
 
 // Re-export workspace crates
 pub use picode_core as core;
+#[cfg(feature = "cli")]
 pub use picode_cli as cli_utils;
 pub use picode_llm as llm;
 pub use picode_hooks as hooks;
@@ -21,6 +98,8 @@ pub use picode_hooks as hooks;
 #[cfg(feature = "wasm")]
 pub use picode_wasm as wasm;
 
+pub use engine::{Engine, EngineBuilder, Reply, Tool};
+
 /// PiCode version information
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -31,6 +110,9 @@ pub mod defaults {
     pub const DEFAULT_SESSION_NAME: &str = "picode-session";
     pub const CONFIG_DIR: &str = ".picode";
     pub const HOOKS_DIR: &str = "hooks";
+    pub const SESSIONS_DIR: &str = "sessions";
+    pub const SERVE_SESSIONS_DIR: &str = "serve-sessions";
+    pub const WEBHOOK_DEAD_LETTER_FILE: &str = "webhooks-dead-letter.jsonl";
 }
 
 /// Common result type for PiCode operations
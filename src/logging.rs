@@ -4,6 +4,8 @@ use tracing::{Level, metadata::LevelFilter};
 use tracing_subscriber::{
     EnvFilter,
     FmtSubscriber,
+    Layer,
+    Registry,
     layer::SubscriberExt,
     util::SubscriberInitExt,
     fmt::format::{Format, Full},
@@ -12,11 +14,16 @@ use tracing_subscriber::{
 
 /// Configure logging for PiCode
 pub fn configure_logger() {
+    configure_logger_with_layer(None);
+}
+
+/// Configure logging with an extra layer, such as trace export, next to
+/// the log written to stderr
+pub fn configure_logger_with_layer(extra: Option<Box<dyn Layer<Registry> + Send + Sync>>) {
     // Create a custom time format
     let timer = UtcTime::rfc_3339();
     
-    // Build the subscriber
-    let subscriber = FmtSubscriber::builder()
+    let fmt = tracing_subscriber::fmt::layer()
         .with_timer(timer)
         .with_target(true)
         .with_thread_ids(false)
@@ -24,14 +31,18 @@ pub fn configure_logger() {
         .with_file(false)
         .with_line_number(false)
         .with_level(true)
-        .with_env_filter(
+        // stdout carries program output, e.g. `picode mcp` protocol messages
+        .with_writer(std::io::stderr);
+    
+    // Initialize the subscriber
+    let _ = tracing_subscriber::registry()
+        .with(extra)
+        .with(
             EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| EnvFilter::new("picode=info,picode_core=info"))
         )
-        .finish();
-    
-    // Initialize the subscriber
-    let _ = tracing::subscriber::set_global_default(subscriber);
+        .with(fmt)
+        .try_init();
 }
 
 /// Configure logger with custom level
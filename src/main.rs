@@ -2,20 +2,45 @@ use clap::Parser;
 use picode::cli::CliArgs;
 use picode::config::Config;
 use picode::error::Result;
-use picode::logging::configure_logger;
 use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging and configuration
-    configure_logger();
-    info!("Starting PiCode v{}", env!("CARGO_PKG_VERSION"));
-
     // Parse command line arguments
     let args = CliArgs::parse();
     
-    // Load configuration
+    // Load configuration, then logging, which may export traces
     let config = Config::try_from(&args).await?;
+    let _telemetry = picode::telemetry::init(&config.telemetry);
+    info!("Starting PiCode v{}", env!("CARGO_PKG_VERSION"));
+    picode::i18n::init(&config);
+
+    // Undo (or finish) edits a crash interrupted before anything reads the workspace
+    let root = match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    let edits = picode_core::editor::EditJournal::for_workspace(&root)
+        .recover(
+            &picode_core::NativeFileSystem,
+            &picode_core::ContentCache::global(),
+            config.ui.editor.crash_recovery,
+        )
+        .await?;
+    eprint!("{}", picode_core::RecoveryReport { edits, ..Default::default() });
+    
+    // Ask about unfamiliar workspaces before anything reads their hooks, tasks or PICODE.md
+    if matches!(
+        args.command,
+        picode_cli::Commands::Workspace { .. }
+            | picode_cli::Commands::Agent { .. }
+            | picode_cli::Commands::Task { .. }
+            | picode_cli::Commands::Execute { .. }
+            | picode_cli::Commands::WorkOn { .. }
+    ) && !picode::trust::ensure(&root)?
+    {
+        eprintln!("🔒 Workspace not trusted: hooks, picode.toml tasks and PICODE.md are disabled (`picode trust allow` to change)");
+    }
     
     // Execute command based on CLI input
     match args.command {
@@ -29,12 +54,15 @@ async fn main() -> Result<()> {
             println!("✅ Workspace initialized successfully");
             Ok(())
         },
-        picode_cli::Commands::Workspace { ai, provider, endpoint: _, session } => {
+        picode_cli::Commands::Workspace { ai, provider, endpoint: _, session, template } => {
             info!("Starting workspace mode");
             let opts = picode::interactive::InteractiveOptions {
                 debug: args.debug,
                 layout: "default".to_string(),
                 provider: provider.map(|p| format!("{:?}", p).to_lowercase()),
+                no_color: args.no_color,
+                template,
+                session: session.clone(),
             };
             
             if ai {
@@ -46,26 +74,40 @@ async fn main() -> Result<()> {
             
             picode::interactive::run(opts, config).await
         },
-        picode_cli::Commands::Execute { command, args: cmd_args, suggest: _, dry_run: _ } => {
+        picode_cli::Commands::Execute { command, args: cmd_args, suggest, dry_run: _ } => {
             info!("Executing command: {:?}", command);
             let full_command = if cmd_args.is_empty() {
                 command
             } else {
                 format!("{} {}", command, cmd_args.join(" "))
             };
-            picode::execute::run_command(full_command, None, config).await
+            let stdin = picode::print::PipedInput::read().await?;
+            picode::execute::run_command(full_command, None, stdin, suggest, config).await
+        },
+        picode_cli::Commands::Print { prompt, raw_stdin, output_file, stream } => {
+            info!("Print mode");
+            picode::print::run(&prompt, raw_stdin, output_file.as_deref(), stream, config).await
         },
         picode_cli::Commands::Config { action } => {
             info!("Configuration management");
-            println!("⚙️ Configuration: {:?}", action);
-            println!("Configuration management not fully implemented yet");
-            Ok(())
+            match action {
+                picode_cli::ConfigAction::Profile { action } => {
+                    let mut config = config;
+                    picode::config::handle_profile_action(&mut config, action).await
+                },
+                picode_cli::ConfigAction::Bundle { action } => {
+                    picode::bundle::handle_action(action, &config).await
+                },
+                action => {
+                    println!("⚙️ Configuration: {:?}", action);
+                    println!("Configuration management not fully implemented yet");
+                    Ok(())
+                },
+            }
         },
         picode_cli::Commands::Git { action } => {
             info!("Git integration");
-            println!("📝 Git action: {:?}", action);
-            println!("Git integration not implemented yet");
-            Ok(())
+            picode::git::handle_action(action, &config).await
         },
         picode_cli::Commands::Llm { action } => {
             info!("LLM provider management");
@@ -79,11 +121,374 @@ async fn main() -> Result<()> {
             println!("Plugin management not implemented yet");
             Ok(())
         },
+        picode_cli::Commands::Dev { action: picode_cli::DevAction::Update { pre_release, auto, insecure_checksum_only } } => {
+            info!("Checking for updates");
+            picode::update::run(&config, pre_release, auto, insecure_checksum_only).await
+        },
+        picode_cli::Commands::Dev { action: picode_cli::DevAction::WhyIgnored { path } } => {
+            let root = match &config.workspace.root_dir {
+                Some(root) => root.clone(),
+                None => std::env::current_dir()?,
+            };
+            let workspace = picode_core::Workspace::new(picode_core::WorkspaceConfig {
+                root_path: root.clone(),
+                ..picode_core::WorkspaceConfig::default()
+            });
+            let rules = workspace.ignore_rules().map_err(picode_core::CoreError::from)?;
+            match rules.explain(&path, root.join(&path).is_dir()) {
+                Some(decision) => println!("{}: {}", path.display(), decision),
+                None => println!("{}: not ignored", path.display()),
+            }
+            Ok(())
+        },
         picode_cli::Commands::Dev { action } => {
             info!("Development utilities");
             println!("🛠️ Dev action: {:?}", action);
             println!("Development utilities not implemented yet");
             Ok(())
         },
+        picode_cli::Commands::Hooks { action } => {
+            info!("Hook management");
+            let index_url = |index: Option<String>| {
+                index
+                    .or_else(|| config.hooks.index_url.clone())
+                    .unwrap_or_else(|| picode_hooks::DEFAULT_INDEX_URL.to_string())
+            };
+            let hooks_dir = config.hooks.dir();
+            let command = match action {
+                picode_cli::HooksAction::List => picode_hooks::HooksCommand::List,
+                picode_cli::HooksAction::Search { term, index } => {
+                    picode_hooks::HooksCommand::Search { term, index_url: index_url(index) }
+                },
+                picode_cli::HooksAction::Install { name, index, yes } => {
+                    picode_hooks::HooksCommand::Install { name, index_url: index_url(index), hooks_dir, yes }
+                },
+                picode_cli::HooksAction::Remove { name } => picode_hooks::HooksCommand::Remove { name },
+                picode_cli::HooksAction::Run { name, args } => picode_hooks::HooksCommand::Run { name, args },
+                picode_cli::HooksAction::Jobs { running } => picode_hooks::HooksCommand::Jobs {
+                    jobs_file: hooks_dir.join(picode_hooks::JOBS_FILE),
+                    running_only: running,
+                },
+            };
+            picode_hooks::handle_command(command)
+                .await
+                .map_err(|e| picode::error::PiCodeError::Hook(e.to_string()))
+        },
+        picode_cli::Commands::Agent { action } => {
+            info!("Agent runs");
+            match action {
+                picode_cli::AgentAction::Run { task, budget, max_turns, permissions, review, verify } => {
+                    let root = match &config.workspace.root_dir {
+                        Some(root) => root.clone(),
+                        None => std::env::current_dir()?,
+                    };
+                    let profile_name = permissions
+                        .or_else(|| config.agent.permissions.clone())
+                        .unwrap_or_else(|| picode_core::agent::DEFAULT_PROFILE.to_string());
+                    let profile = picode_core::agent::PermissionProfile::resolve(&profile_name, &config.agent.permission_profiles)
+                        .map_err(picode_core::CoreError::from)?;
+                    let context = picode_core::agent::ToolContext {
+                        root: root.clone(),
+                        fs: std::sync::Arc::new(picode_core::NativeFileSystem),
+                        processes: std::sync::Arc::new(picode_core::ScopedProcessRunner::new(
+                            config.workspace.panes.environment(std::env::vars()),
+                        )),
+                    };
+                    let locks = picode_core::FileLockService::new(
+                        root.clone(),
+                        context.fs.clone(),
+                        format!("agent run (pid {})", std::process::id()),
+                        config.agent.locks,
+                    );
+                    let mut tools = picode_core::agent::ToolRegistry::new(context, profile_name, profile)
+                        .with_builtin_tools()
+                        .with_large_files(config.workspace.large_files.clone())
+                        .with_locks(std::sync::Arc::new(locks));
+                    if review {
+                        tools = tools.with_review();
+                    }
+                    if config.agent.lockfile_only {
+                        tools = tools.with_lockfile_only();
+                    }
+                    let settings = config.agent.project_tools;
+                    if settings.format || settings.lint {
+                        let tooling = picode_core::agent::ProjectTooling::detect(&picode_core::NativeFileSystem, &root).await;
+                        if !tooling.is_empty() {
+                            info!("Project tools: {}", tooling.names().join(", "));
+                            tools = tools.with_project_tooling(tooling, settings);
+                        }
+                    }
+                    let assistant = picode::assistant::Assistant::from_config(&config)?;
+                    let mut system = config.system_prompt(&root).await?.effective();
+                    // Planning can pick up the workspace TODOs; edits that remove one settle it
+                    let (todos, _) = picode::todos::refresh(&root).await?;
+                    let open = todos.open().count();
+                    if open > 0 {
+                        system.push_str(&format!(
+                            "\n\nThe workspace has {} open TODO/FIXME/HACK item(s). Call list_todos to see them, \
+                             refer to them by id in your plan, and call update_todo when your change settles one.",
+                            open
+                        ));
+                    }
+                    let prices = picode::agent::prices(&config, assistant.provider_name());
+                    let verification = picode::agent::Verification::from_config(&config, &assistant, verify)?;
+                    let outcome = picode::agent::run(
+                        &assistant,
+                        &system,
+                        &task,
+                        budget.unwrap_or_default(),
+                        prices,
+                        max_turns,
+                        &tools,
+                        picode::agent::RunGuardrails::new(config.agent.limits, &picode_core::agent::TerminalConfirmation),
+                        verification.as_ref(),
+                    )
+                    .await;
+                    if let Err(e) = tools.release_locks().await {
+                        tracing::warn!("Releasing file locks failed: {}", e);
+                    }
+                    let outcome = outcome?;
+                    println!("{}\n", outcome.reply.trim_end());
+                    let path = outcome
+                        .report
+                        .save(&root.join(picode_core::agent::RUNS_DIR))
+                        .await
+                        .map_err(picode_core::CoreError::from)?;
+                    println!(
+                        "🤖 Run {} {} after {} turn(s); {} tokens, ${:.4}. Report: {}",
+                        outcome.report.run_id,
+                        outcome.stop,
+                        outcome.turns,
+                        outcome.report.usage.prompt_tokens + outcome.report.usage.completion_tokens,
+                        outcome.report.usage.cost_usd,
+                        path.display()
+                    );
+                    let (_, changes) = picode::todos::refresh(&root).await?;
+                    for item in &changes.resolved {
+                        println!("✅ Resolved {} {} {} {}", item.id, item.kind, item.location(), item.text);
+                    }
+                    if review {
+                        let queue = picode_core::editor::ReviewQueue::load(&picode_core::NativeFileSystem, &root)
+                            .await
+                            .map_err(picode_core::CoreError::from)?;
+                        println!("🔍 {} edit(s) pending review: picode review export --patch review.patch", queue.edits.len());
+                    }
+                    Ok(())
+                },
+                picode_cli::AgentAction::Report { id, json } => {
+                    let runs_dir = std::env::current_dir()?.join(picode_core::agent::RUNS_DIR);
+                    let report = picode_core::agent::AgentRunReport::load(&runs_dir, &id)
+                        .await
+                        .map_err(picode_core::CoreError::from)?;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    } else {
+                        println!("{}", report.to_markdown());
+                    }
+                    Ok(())
+                },
+                picode_cli::AgentAction::Trash { action } => {
+                    let fs = picode_core::NativeFileSystem;
+                    let trash_dir = std::env::current_dir()?.join(picode_core::agent::TRASH_DIR);
+                    let trash = picode_core::agent::Trash::new(trash_dir).with_mode(config.agent.trash);
+                    match action {
+                        picode_cli::TrashAction::List => {
+                            let entries = trash.list(&fs).await.map_err(picode_core::CoreError::from)?;
+                            if entries.is_empty() {
+                                println!("🗑️  Trash is empty");
+                            }
+                            let ids: Vec<_> = entries.iter().map(|entry| entry.id.clone()).collect();
+                            for (entry, short) in entries.iter().zip(picode_core::short_forms(&ids)) {
+                                println!(
+                                    "{}  {}  {} bytes  {}",
+                                    short,
+                                    entry.deleted_at.format("%Y-%m-%d %H:%M"),
+                                    entry.size,
+                                    entry.original_path.display()
+                                );
+                            }
+                        },
+                        picode_cli::TrashAction::Restore { id } => {
+                            let entry = trash.restore(&fs, &id).await.map_err(picode_core::CoreError::from)?;
+                            println!("✅ Restored {}", entry.original_path.display());
+                        },
+                        picode_cli::TrashAction::Empty => {
+                            let entries = trash.list(&fs).await.map_err(picode_core::CoreError::from)?;
+                            let paths: Vec<_> = entries.iter().map(|entry| &entry.original_path).collect();
+                            let payload = serde_json::json!({ "paths": paths });
+                            picode::hook_guard::check(&config, picode_hooks::HookEvent::FileDelete, payload, false).await?;
+                            let removed = trash.empty(&fs).await.map_err(picode_core::CoreError::from)?;
+                            println!("🗑️  Permanently deleted {} file(s)", removed);
+                        },
+                    }
+                    Ok(())
+                },
+            }
+        },
+        picode_cli::Commands::Daemon { action } => {
+            info!("Daemon management");
+            picode::daemon::handle_action(action).await
+        },
+        picode_cli::Commands::Session { action } => {
+            info!("Session management");
+            match action {
+                picode_cli::SessionAction::Redact { name, pattern, secrets } => {
+                    let mut redactor = picode_core::Redactor::new();
+                    for pattern in &pattern {
+                        redactor = redactor.with_pattern(pattern).map_err(picode_core::CoreError::from)?;
+                    }
+                    if secrets {
+                        redactor = redactor.with_secrets();
+                    }
+                    
+                    let session_dir = std::path::PathBuf::from(picode::defaults::CONFIG_DIR)
+                        .join(picode::defaults::SESSIONS_DIR);
+                    let manager = picode_core::SessionManager::new(session_dir).with_archive(config.session.archive);
+                    let recovery = manager.load_sessions().await.map_err(picode_core::CoreError::from)?;
+                    eprint!("{}", recovery);
+                    let report = manager
+                        .redact_session(&name, &redactor)
+                        .await
+                        .map_err(picode_core::CoreError::from)?;
+                    
+                    if report.replacements == 0 {
+                        println!("✅ Nothing to redact in session '{}'", name);
+                    } else {
+                        println!(
+                            "✅ Redacted {} occurrence(s) in {} message(s) of session '{}'",
+                            report.replacements, report.messages_changed, name
+                        );
+                    }
+                    Ok(())
+                },
+                picode_cli::SessionAction::Rerun { name, provider, model, output } => {
+                    info!("Rerunning session {} with {}", name, provider);
+                    picode::rerun::run(&config, &name, &provider, model, output).await
+                },
+                action @ (picode_cli::SessionAction::History { .. }
+                | picode_cli::SessionAction::Export { .. }
+                | picode_cli::SessionAction::Publish { .. }
+                | picode_cli::SessionAction::Compact) => {
+                    picode::history::handle_action(action, &config).await
+                },
+            }
+        },
+        picode_cli::Commands::Explain { target, depth } => {
+            info!("Explaining {}", target);
+            picode::explain::run(&target, depth, config).await
+        },
+        picode_cli::Commands::Open { target } => {
+            info!("Opening {} externally", target);
+            picode::open::run(&target, config).await
+        },
+        picode_cli::Commands::Diff { action } => {
+            match action {
+                picode_cli::DiffAction::Explain { file, range, pr } => {
+                    let source = match (file, range) {
+                        (Some(file), _) => picode::diff::DiffSource::File(file),
+                        (None, Some(range)) => picode::diff::DiffSource::GitRange(range),
+                        (None, None) => picode::diff::DiffSource::Stdin,
+                    };
+                    picode::diff::explain(source, pr, config).await
+                },
+            }
+        },
+        picode_cli::Commands::Serve { bind, users_file, action } => {
+            let users_file = users_file.unwrap_or_else(picode::users::default_users_file);
+            match action {
+                None => {
+                    info!("Starting serve mode on {}", bind);
+                    let sessions_dir = picode::serve::default_sessions_dir();
+                    picode::serve::run(picode::serve::ServeOptions { bind, users_file, sessions_dir }, config).await
+                },
+                Some(action) => picode::users::handle_action(action, &users_file).await,
+            }
+        },
+        picode_cli::Commands::Mcp { socket, isolation } => {
+            info!("Starting MCP server");
+            let isolation = match isolation {
+                picode_cli::McpIsolation::Shared => picode::mcp::Isolation::Shared,
+                picode_cli::McpIsolation::PerClient => picode::mcp::Isolation::PerClient,
+            };
+            picode::mcp::run(picode::mcp::McpOptions { socket, isolation }, config).await
+        },
+        picode_cli::Commands::Bench { action } => {
+            match action {
+                picode_cli::BenchAction::Llm { providers, suite, iterations, judge, json } => {
+                    info!("Benchmarking providers: {:?}", providers);
+                    let options = picode::bench::BenchOptions { providers, suite, iterations, judge, json };
+                    picode::bench::run(options, config).await
+                },
+                picode_cli::BenchAction::Core { only, baseline, save_baseline, threshold, quick } => {
+                    let benches = only
+                        .into_iter()
+                        .map(|kind| match kind {
+                            picode_cli::CoreBenchKind::WorkspaceScan => picode::bench_core::CoreBench::WorkspaceScan,
+                            picode_cli::CoreBenchKind::EventPublish => picode::bench_core::CoreBench::EventPublish,
+                            picode_cli::CoreBenchKind::ContextBuild => picode::bench_core::CoreBench::ContextBuild,
+                            picode_cli::CoreBenchKind::SessionPersist => picode::bench_core::CoreBench::SessionPersist,
+                        })
+                        .collect();
+                    let options = picode::bench_core::CoreBenchOptions { benches, baseline, save_baseline, threshold, quick };
+                    picode::bench_core::run(options, config).await
+                },
+            }
+        },
+        picode_cli::Commands::Task { action } => {
+            info!("Workspace task: {:?}", action);
+            picode::tasks::handle_action(action, &config).await
+        },
+        picode_cli::Commands::Eval { suite, providers, filter, junit } => {
+            info!("Eval suite: {}", suite.display());
+            let options = picode::eval::EvalOptions { suite, providers, filter, junit };
+            picode::eval::run(options, config).await
+        },
+        picode_cli::Commands::Sync { dry_run } => {
+            info!("Syncing workspace data");
+            picode::sync::run(&config, dry_run).await
+        },
+        picode_cli::Commands::Review { action } => {
+            info!("Review: {:?}", action);
+            picode::review::handle_action(action, &config).await
+        },
+        picode_cli::Commands::Stats { action } => {
+            info!("Project statistics: {:?}", action);
+            picode::stats::handle_action(action, &config).await
+        },
+        picode_cli::Commands::Todos { json, all } => {
+            info!("Workspace TODOs");
+            picode::todos::handle(&config, json, all).await
+        },
+        picode_cli::Commands::Trust { action } => {
+            info!("Workspace trust: {:?}", action);
+            picode::trust::handle_action(action, &config).await
+        },
+        picode_cli::Commands::Release { action } => {
+            match action {
+                picode_cli::ReleaseAction::Notes { since, until, version, changelog, announcement } => {
+                    info!("Release notes since {}", since);
+                    let options = picode::release::NotesOptions { since, until, version, changelog, announcement };
+                    picode::release::notes(&config, options).await
+                },
+            }
+        },
+        picode_cli::Commands::Audit { action } => {
+            match action {
+                picode_cli::AuditAction::Deps { json, output, plan, fail_on } => {
+                    info!("Auditing workspace dependencies");
+                    let fail_on = fail_on.map(|severity| match severity {
+                        picode_cli::AuditSeverity::Low => picode::audit::Severity::Low,
+                        picode_cli::AuditSeverity::Medium => picode::audit::Severity::Medium,
+                        picode_cli::AuditSeverity::High => picode::audit::Severity::High,
+                        picode_cli::AuditSeverity::Critical => picode::audit::Severity::Critical,
+                    });
+                    picode::audit::deps(&config, picode::audit::DepsOptions { json, output, plan, fail_on }).await
+                },
+            }
+        },
+        picode_cli::Commands::WorkOn { issue, template, base } => {
+            info!("Working on issue {}", issue);
+            picode::work_on::run(config, &issue, template, base).await
+        },
     }
 }
\ No newline at end of file
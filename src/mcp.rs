@@ -0,0 +1,512 @@
+//! MCP server mode
+//!
+//! `picode mcp` serves PiCode over the Model Context Protocol: JSON-RPC 2.0,
+//! one message per line, on stdin/stdout. With `--socket <path>` it listens
+//! on a Unix socket (a named pipe on Windows) instead and bridges every
+//! client that connects, e.g. two editors, to one PiCode session. When
+//! started through systemd socket activation the inherited socket is used.
+//!
+//! Each client negotiates its own protocol version and capabilities in
+//! `initialize`. Clients that declare the experimental
+//! `picode/sessionUpdates` capability are notified when another client adds
+//! to the shared conversation. With [`Isolation::PerClient`] every client
+//! gets its own conversation instead.
+//!
+//! Tools:
+//! - `picode_chat` - send `{"message": ...}` to the model in the session
+//! - `picode_history` - the session's conversation so far
+
+use crate::config::Config;
+use crate::engine::Engine;
+use crate::error::{PiCodeError, Result};
+use picode_core::SessionId;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, info, warn};
+
+/// Protocol version answered to clients asking for one we do not know
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Protocol versions this server speaks
+const SUPPORTED_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26"];
+
+/// Experimental client capability for shared-session notifications
+pub const SESSION_UPDATES_CAPABILITY: &str = "picode/sessionUpdates";
+
+/// Method of the notification sent for [`SESSION_UPDATES_CAPABILITY`]
+const SESSION_UPDATED_METHOD: &str = "notifications/picode/sessionUpdated";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const NOT_INITIALIZED: i64 = -32002;
+
+/// How bridged clients share conversations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Isolation {
+    /// Every client talks in the same conversation
+    #[default]
+    Shared,
+    /// Every client gets a conversation of its own
+    PerClient,
+}
+
+/// Options for MCP server mode
+#[derive(Debug, Clone, Default)]
+pub struct McpOptions {
+    /// Listen here instead of serving stdio
+    pub socket: Option<PathBuf>,
+    pub isolation: Isolation,
+}
+
+/// What a client declared in `initialize`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientInfo {
+    pub name: String,
+    pub protocol_version: String,
+    pub session_updates: bool,
+}
+
+struct Client {
+    info: Option<ClientInfo>,
+    session: SessionId,
+    notify: mpsc::UnboundedSender<String>,
+}
+
+/// State shared by every connection: the engine, the shared session and
+/// the connected clients
+pub struct McpHub {
+    engine: Engine,
+    shared: SessionId,
+    isolation: Isolation,
+    clients: Mutex<HashMap<u64, Client>>,
+    next_client: AtomicU64,
+}
+
+impl McpHub {
+    pub async fn new(engine: Engine, isolation: Isolation) -> Result<Self> {
+        let shared = engine.create_session("mcp").await?;
+        Ok(Self {
+            engine,
+            shared,
+            isolation,
+            clients: Mutex::new(HashMap::new()),
+            next_client: AtomicU64::new(1),
+        })
+    }
+
+    /// Register a connection; notifications for it arrive on the receiver
+    pub async fn connect(&self) -> Result<(u64, mpsc::UnboundedReceiver<String>)> {
+        let id = self.next_client.fetch_add(1, Ordering::Relaxed);
+        let session = match self.isolation {
+            Isolation::Shared => self.shared.clone(),
+            Isolation::PerClient => self.engine.create_session(format!("mcp-client-{}", id)).await?,
+        };
+        let (notify, receiver) = mpsc::unbounded_channel();
+        self.clients.lock().await.insert(id, Client { info: None, session, notify });
+        Ok((id, receiver))
+    }
+
+    pub async fn disconnect(&self, client: u64) {
+        let removed = self.clients.lock().await.remove(&client);
+        if let Some(removed) = removed {
+            if removed.session != self.shared {
+                let _ = self.engine.close_session(&removed.session).await;
+            }
+        }
+    }
+
+    /// Client info of a connection, once it has initialized
+    pub async fn client_info(&self, client: u64) -> Option<ClientInfo> {
+        self.clients.lock().await.get(&client).and_then(|c| c.info.clone())
+    }
+
+    /// Handle one JSON-RPC message; returns the response line, if any
+    pub async fn handle(&self, client: u64, line: &str) -> Option<String> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return id.map(|id| error_response(id, INVALID_REQUEST, "missing method"));
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        // Notifications (no id) get no response
+        let id = match id {
+            Some(id) => id,
+            None => {
+                debug!("MCP client {} notification: {}", client, method);
+                return None;
+            }
+        };
+
+        let result = match method {
+            "initialize" => Ok(self.initialize(client, &params).await),
+            "ping" => Ok(json!({})),
+            _ if self.client_info(client).await.is_none() => {
+                Err((NOT_INITIALIZED, "initialize must be called first".to_string()))
+            }
+            "tools/list" => Ok(tools()),
+            "tools/call" => self.call_tool(client, &params).await,
+            other => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", other))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    async fn initialize(&self, client: u64, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str).unwrap_or(PROTOCOL_VERSION);
+        let protocol_version = if SUPPORTED_VERSIONS.contains(&requested) { requested } else { PROTOCOL_VERSION };
+        let session_updates = self.isolation == Isolation::Shared
+            && params.pointer("/capabilities/experimental").and_then(|e| e.get(SESSION_UPDATES_CAPABILITY)).is_some();
+        let info = ClientInfo {
+            name: params.pointer("/clientInfo/name").and_then(Value::as_str).unwrap_or("unknown").to_string(),
+            protocol_version: protocol_version.to_string(),
+            session_updates,
+        };
+        info!("MCP client {} initialized: {} ({})", client, info.name, info.protocol_version);
+        if let Some(state) = self.clients.lock().await.get_mut(&client) {
+            state.info = Some(info);
+        }
+
+        let mut capabilities = json!({ "tools": { "listChanged": false } });
+        if session_updates {
+            capabilities["experimental"] = json!({ SESSION_UPDATES_CAPABILITY: {} });
+        }
+        json!({
+            "protocolVersion": protocol_version,
+            "capabilities": capabilities,
+            "serverInfo": { "name": "picode", "version": crate::VERSION },
+        })
+    }
+
+    async fn call_tool(&self, client: u64, params: &Value) -> std::result::Result<Value, (i64, String)> {
+        let session = match self.clients.lock().await.get(&client) {
+            Some(state) => state.session.clone(),
+            None => return Err((INVALID_REQUEST, "unknown client".to_string())),
+        };
+        let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+        let outcome = match params.get("name").and_then(Value::as_str) {
+            Some("picode_chat") => {
+                let Some(message) = arguments.get("message").and_then(Value::as_str) else {
+                    return Err((INVALID_PARAMS, "picode_chat needs a 'message' string".to_string()));
+                };
+                let outcome = self.engine.send_message(&session, message).await.map(|reply| reply.text);
+                if outcome.is_ok() {
+                    self.notify_others(client, &session).await;
+                }
+                outcome
+            }
+            Some("picode_history") => match self.engine.conversation(&session).await {
+                Some(log) => Ok(log
+                    .messages
+                    .iter()
+                    .filter(|m| !m.is_annotation())
+                    .map(|m| format!("{}: {}", m.role, m.content))
+                    .collect::<Vec<_>>()
+                    .join("\n\n")),
+                None => Err(PiCodeError::NotFound("session".to_string())),
+            },
+            Some(other) => return Err((INVALID_PARAMS, format!("unknown tool '{}'", other))),
+            None => return Err((INVALID_PARAMS, "missing tool name".to_string())),
+        };
+        // Tool failures are results the model can see, not protocol errors
+        Ok(match outcome {
+            Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+            Err(err) => json!({ "content": [{ "type": "text", "text": err.to_string() }], "isError": true }),
+        })
+    }
+
+    /// Tell the other clients of `session` that asked for it that the
+    /// conversation changed
+    async fn notify_others(&self, from: u64, session: &SessionId) {
+        let clients = self.clients.lock().await;
+        let from_name = clients.get(&from).and_then(|c| c.info.as_ref()).map(|i| i.name.clone());
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": SESSION_UPDATED_METHOD,
+            "params": { "from": from_name },
+        })
+        .to_string();
+        for (id, client) in clients.iter() {
+            let wants = client.info.as_ref().is_some_and(|info| info.session_updates);
+            if *id != from && wants && client.session == *session {
+                let _ = client.notify.send(notification.clone());
+            }
+        }
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }).to_string()
+}
+
+fn tools() -> Value {
+    json!({
+        "tools": [
+            {
+                "name": "picode_chat",
+                "description": "Send a message to the model in the PiCode session and return its answer",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": { "message": { "type": "string" } },
+                    "required": ["message"],
+                },
+            },
+            {
+                "name": "picode_history",
+                "description": "The PiCode session's conversation so far",
+                "inputSchema": { "type": "object", "properties": {} },
+            },
+        ]
+    })
+}
+
+/// Serve one client connection until it closes
+pub async fn serve_connection<R, W>(hub: Arc<McpHub>, reader: R, mut writer: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (client, mut notifications) = hub.connect().await?;
+    let mut lines = BufReader::new(reader).lines();
+    let result: Result<()> = async {
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => match line? {
+                    Some(line) if line.trim().is_empty() => continue,
+                    Some(line) => hub.handle(client, &line).await,
+                    None => return Ok(()),
+                },
+                Some(notification) = notifications.recv() => Some(notification),
+            };
+            if let Some(mut line) = line {
+                line.push('\n');
+                writer.write_all(line.as_bytes()).await?;
+                writer.flush().await?;
+            }
+        }
+    }
+    .await;
+    hub.disconnect(client).await;
+    result
+}
+
+/// The listening socket systemd passed us, if started by socket activation
+#[cfg(unix)]
+fn activated_listener() -> Result<Option<tokio::net::UnixListener>> {
+    use std::os::unix::io::FromRawFd;
+
+    // sd_listen_fds(3): inherited sockets start at fd 3
+    const SD_LISTEN_FDS_START: i32 = 3;
+    let for_us = std::env::var("LISTEN_PID").ok().and_then(|pid| pid.parse::<u32>().ok()) == Some(std::process::id());
+    let count = std::env::var("LISTEN_FDS").ok().and_then(|n| n.parse::<i32>().ok()).unwrap_or(0);
+    if !for_us || count < 1 {
+        return Ok(None);
+    }
+    // SAFETY: systemd hands over ownership of the descriptor and nothing
+    // else in the process uses it
+    let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    Ok(Some(tokio::net::UnixListener::from_std(listener)?))
+}
+
+#[cfg(unix)]
+async fn serve_listener(hub: Arc<McpHub>, listener: tokio::net::UnixListener) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let hub = hub.clone();
+        tokio::spawn(async move {
+            let (reader, writer) = stream.into_split();
+            if let Err(err) = serve_connection(hub, reader, writer).await {
+                warn!("MCP connection ended with an error: {}", err);
+            }
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn serve_socket(hub: Arc<McpHub>, path: PathBuf) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let _ = tokio::fs::remove_file(&path).await;
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    eprintln!("🔌 PiCode MCP server listening on {}", path.display());
+    serve_listener(hub, listener).await
+}
+
+#[cfg(windows)]
+async fn serve_socket(hub: Arc<McpHub>, path: PathBuf) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new().first_pipe_instance(true).create(&path)?;
+    eprintln!("🔌 PiCode MCP server listening on {}", path.display());
+    loop {
+        server.connect().await?;
+        // Create the next instance before handing this one off, so a client
+        // can always connect
+        let connected = std::mem::replace(&mut server, ServerOptions::new().create(&path)?);
+        let hub = hub.clone();
+        tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(connected);
+            if let Err(err) = serve_connection(hub, reader, writer).await {
+                warn!("MCP connection ended with an error: {}", err);
+            }
+        });
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn serve_socket(_hub: Arc<McpHub>, _path: PathBuf) -> Result<()> {
+    Err(PiCodeError::Internal("MCP socket mode requires Unix sockets or named pipes".to_string()))
+}
+
+/// Run the MCP server until stdin closes (stdio) or forever (socket)
+pub async fn run(opts: McpOptions, config: Config) -> Result<()> {
+    let engine = Engine::builder(config).build()?;
+    let hub = Arc::new(McpHub::new(engine, opts.isolation).await?);
+
+    #[cfg(unix)]
+    if let Some(listener) = activated_listener()? {
+        info!("MCP server using the socket-activated listener");
+        return serve_listener(hub, listener).await;
+    }
+    match opts.socket {
+        Some(path) => serve_socket(hub, path).await,
+        None => serve_connection(hub, tokio::io::stdin(), tokio::io::stdout()).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assistant::Assistant;
+    use picode_llm::{ChatChoice, ChatMessage, ChatRequest, ChatResponse, LlmProvider, ModelInfo, TokenUsage};
+
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for Echo {
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+
+        async fn health_check(&self) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn complete(&self, _request: picode_llm::CompletionRequest) -> anyhow::Result<picode_llm::CompletionResponse> {
+            anyhow::bail!("unsupported")
+        }
+
+        async fn chat(&self, request: ChatRequest) -> anyhow::Result<ChatResponse> {
+            let prompt = request.messages.last().unwrap().content.clone();
+            Ok(ChatResponse {
+                choices: vec![ChatChoice {
+                    message: ChatMessage { role: "assistant".to_string(), content: format!("echo: {}", prompt.lines().last().unwrap_or("")) },
+                    finish_reason: "stop".to_string(),
+                }],
+                usage: TokenUsage { prompt_tokens: 1, completion_tokens: 1, total_tokens: 2 },
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn get_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn hub(isolation: Isolation) -> Arc<McpHub> {
+        let engine = Engine::builder(Config::default())
+            .with_assistant(Assistant::with_provider("echo", Box::new(Echo), "echo-1"))
+            .with_workspace(std::env::temp_dir())
+            .build()
+            .unwrap();
+        Arc::new(McpHub::new(engine, isolation).await.unwrap())
+    }
+
+    async fn initialize(hub: &McpHub, client: u64, name: &str, updates: bool) -> Value {
+        let experimental = if updates { json!({ SESSION_UPDATES_CAPABILITY: {} }) } else { json!({}) };
+        let request = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "clientInfo": { "name": name },
+                        "capabilities": { "experimental": experimental } },
+        });
+        serde_json::from_str(&hub.handle(client, &request.to_string()).await.unwrap()).unwrap()
+    }
+
+    fn chat(message: &str) -> String {
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": { "name": "picode_chat", "arguments": { "message": message } } })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn clients_negotiate_and_share_a_session() {
+        let hub = hub(Isolation::Shared).await;
+        let (vim, mut vim_updates) = hub.connect().await.unwrap();
+        let (code, mut code_updates) = hub.connect().await.unwrap();
+
+        let early: Value = serde_json::from_str(&hub.handle(vim, &chat("hi")).await.unwrap()).unwrap();
+        assert_eq!(early["error"]["code"], NOT_INITIALIZED);
+
+        let answer = initialize(&hub, vim, "vim", true).await;
+        assert!(answer["result"]["capabilities"]["experimental"][SESSION_UPDATES_CAPABILITY].is_object());
+        let answer = initialize(&hub, code, "vscode", false).await;
+        assert!(answer["result"]["capabilities"].get("experimental").is_none());
+        assert!(hub.handle(code, r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).await.is_none());
+
+        let reply: Value = serde_json::from_str(&hub.handle(code, &chat("hello")).await.unwrap()).unwrap();
+        assert_eq!(reply["result"]["content"][0]["text"], "echo: hello");
+        let notification: Value = serde_json::from_str(&vim_updates.try_recv().unwrap()).unwrap();
+        assert_eq!(notification["params"]["from"], "vscode");
+        assert!(code_updates.try_recv().is_err());
+
+        // vim sees what vscode said
+        let history = json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": { "name": "picode_history" } });
+        let history: Value = serde_json::from_str(&hub.handle(vim, &history.to_string()).await.unwrap()).unwrap();
+        assert!(history["result"]["content"][0]["text"].as_str().unwrap().contains("user: hello"));
+    }
+
+    #[tokio::test]
+    async fn per_client_isolation_and_stream_bridging() {
+        let hub = hub(Isolation::PerClient).await;
+        let (client, input) = tokio::io::duplex(4096);
+        let (server_read, server_write) = tokio::io::split(input);
+        let server = tokio::spawn(serve_connection(hub.clone(), server_read, server_write));
+
+        let (read, mut write) = tokio::io::split(client);
+        let mut lines = BufReader::new(read).lines();
+        let init = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                           "params": { "capabilities": { "experimental": { SESSION_UPDATES_CAPABILITY: {} } } } });
+        write.write_all(format!("{}\n{}\n", init, chat("one")).as_bytes()).await.unwrap();
+        let init: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        // Nobody shares the conversation, so there are no updates to offer
+        assert!(init["result"]["capabilities"].get("experimental").is_none());
+        let reply: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(reply["result"]["content"][0]["text"], "echo: one");
+
+        write.write_all(b"not json\n").await.unwrap();
+        let error: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(error["error"]["code"], PARSE_ERROR);
+
+        write.shutdown().await.unwrap();
+        drop(lines);
+        server.await.unwrap().unwrap();
+        assert!(hub.clients.lock().await.is_empty());
+        // The shared session is untouched
+        assert!(hub.engine.conversation(&hub.shared).await.unwrap().messages.is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+//! `@file` and `#symbol` references in chat prompts
+//!
+//! The chat input completes `@` with workspace paths, `#` with indexed
+//! symbols and a leading `/` with slash commands. The references stay in
+//! the prompt as typed; before the prompt is sent, [`MentionIndex::expand`]
+//! puts the referenced content in front of it: the file, or the lines
+//! around each definition of the symbol.
+
+use crate::error::Result;
+use crate::slash::{fuzzy_score, SlashCommandRegistry};
+use picode_core::workspace::{Workspace, WorkspaceConfig};
+use picode_core::io::FileSystem;
+use picode_core::{NativeFileSystem, SymbolIndex};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Completions offered at once
+pub const MAX_COMPLETIONS: usize = 8;
+
+/// Files larger than this are neither indexed nor inlined whole
+const MAX_MENTIONED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Lines of a mentioned file sent to the model
+const MAX_FILE_LINES: usize = 400;
+
+/// Lines shown from each definition of a mentioned symbol
+const SYMBOL_CONTEXT_LINES: usize = 30;
+
+/// Definitions of one symbol included at most
+const MAX_SYMBOL_DEFINITIONS: usize = 3;
+
+/// A reference in a prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mention {
+    File(String),
+    Symbol(String),
+}
+
+/// What a completion inserts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    File,
+    Symbol,
+    Command,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    /// Replacement for the token being completed, sigil included
+    pub text: String,
+    /// Shown next to it, e.g. a symbol's signature
+    pub detail: String,
+}
+
+/// References in `prompt`: words starting with `@` or with `#` followed
+/// by an identifier, without trailing punctuation
+pub fn mentions(prompt: &str) -> Vec<Mention> {
+    let mut found = Vec::new();
+    for word in prompt.split_whitespace() {
+        let word = word.trim_end_matches([',', '.', ';', ':', ')', '!', '?', '`']);
+        let mention = if let Some(path) = word.strip_prefix('@') {
+            (!path.is_empty()).then(|| Mention::File(path.to_string()))
+        } else if let Some(name) = word.strip_prefix('#') {
+            name.starts_with(|c: char| c.is_alphabetic() || c == '_').then(|| Mention::Symbol(name.to_string()))
+        } else {
+            None
+        };
+        if let Some(mention) = mention.filter(|mention| !found.contains(mention)) {
+            found.push(mention);
+        }
+    }
+    found
+}
+
+/// The workspace's files and symbols, for completing and resolving mentions
+#[derive(Debug, Clone, Default)]
+pub struct MentionIndex {
+    root: PathBuf,
+    /// Workspace-relative paths with forward slashes
+    files: Vec<String>,
+    symbols: SymbolIndex,
+}
+
+impl MentionIndex {
+    /// Scan `root`, honoring its ignore rules
+    pub async fn build(root: &Path) -> Result<Self> {
+        let mut workspace = Workspace::new(WorkspaceConfig {
+            root_path: root.to_path_buf(),
+            git_enabled: false,
+            ..WorkspaceConfig::default()
+        });
+        workspace.scan().await.map_err(picode_core::CoreError::from)?;
+
+        let mut index = Self { root: root.to_path_buf(), ..Self::default() };
+        for file in workspace.files.iter().filter(|file| !file.is_binary) {
+            index.files.push(picode_core::paths::to_slash(&file.relative_path));
+            if file.language.is_some() && file.size <= MAX_MENTIONED_FILE_SIZE {
+                if let Ok(content) = tokio::fs::read_to_string(&file.path).await {
+                    index.symbols.index_file(&file.relative_path, &content);
+                }
+            }
+        }
+        index.files.sort();
+        Ok(index)
+    }
+
+    /// Completions for the word before the cursor; `line_start` tells
+    /// whether it is the first word, where `/` starts a command
+    pub fn complete(&self, word: &str, line_start: bool, commands: &SlashCommandRegistry) -> Vec<Completion> {
+        let (kind, query, candidates): (CompletionKind, &str, Vec<(String, String)>) =
+            if let Some(query) = word.strip_prefix('@') {
+                let files = self.files.iter().map(|file| (format!("@{}", file), String::new())).collect();
+                (CompletionKind::File, query, files)
+            } else if let Some(query) = word.strip_prefix('#').filter(|query| !query.is_empty()) {
+                let symbols = self
+                    .symbols
+                    .names()
+                    .map(|name| {
+                        let detail = self.symbols.lookup(name).first().map(|l| l.signature.clone()).unwrap_or_default();
+                        (format!("#{}", name), detail)
+                    })
+                    .collect();
+                (CompletionKind::Symbol, query, symbols)
+            } else if let Some(query) = word.strip_prefix('/').filter(|_| line_start) {
+                let names = commands
+                    .commands()
+                    .iter()
+                    .map(|command| (format!("/{}", command.name), command.summary.to_string()))
+                    .collect();
+                (CompletionKind::Command, query, names)
+            } else {
+                return Vec::new();
+            };
+
+        let mut scored: Vec<(i64, String, String)> = candidates
+            .into_iter()
+            .filter_map(|(text, detail)| fuzzy_score(query, &text[1..]).map(|score| (score, text, detail)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        scored
+            .into_iter()
+            .take(MAX_COMPLETIONS)
+            .map(|(_, text, detail)| Completion { kind, text, detail })
+            .collect()
+    }
+
+    /// `prompt` with the content of its known mentions in front of it;
+    /// unknown ones are left as plain text
+    pub async fn expand(&self, prompt: &str) -> String {
+        let mut context = String::new();
+        for mention in mentions(prompt) {
+            match mention {
+                Mention::File(path) if self.files.contains(&path) => {
+                    // Only the lines sent are read, however large the file
+                    let lines = picode_core::LineRange::new(1, MAX_FILE_LINES);
+                    match NativeFileSystem.read_window(&self.root.join(&path), lines).await {
+                        Ok(window) => {
+                            let more = if window.is_complete() { "" } else { "…\n" };
+                            let _ = writeln!(context, "Referenced file @{}:\n```\n{}{}```", path, window.text, more);
+                        }
+                        Err(e) => tracing::debug!("Mention @{} not read: {}", path, e),
+                    }
+                }
+                Mention::Symbol(name) => {
+                    for location in self.symbols.lookup(&name).iter().take(MAX_SYMBOL_DEFINITIONS) {
+                        let Ok(content) = tokio::fs::read_to_string(self.root.join(&location.path)).await else {
+                            continue;
+                        };
+                        let _ = writeln!(
+                            context,
+                            "Referenced symbol #{} ({}:{}):\n```\n{}```",
+                            name,
+                            picode_core::paths::to_slash(&location.path),
+                            location.line,
+                            excerpt(&content, location.line, SYMBOL_CONTEXT_LINES)
+                        );
+                    }
+                }
+                Mention::File(_) => {}
+            }
+        }
+        if context.is_empty() {
+            prompt.to_string()
+        } else {
+            format!("{}\n{}", context, prompt)
+        }
+    }
+}
+
+/// Up to `count` lines of `content` from the 1-based line `start`, each
+/// ending with a newline, with a marker when lines were left out
+fn excerpt(content: &str, start: usize, count: usize) -> String {
+    let mut out = String::new();
+    let mut lines = content.lines().skip(start.saturating_sub(1));
+    for line in lines.by_ref().take(count) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    if lines.next().is_some() {
+        out.push_str("…\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> MentionIndex {
+        let mut symbols = SymbolIndex::new();
+        symbols.index_file(Path::new("src/config.rs"), "pub struct Config;\npub fn load_config() {}\n");
+        MentionIndex {
+            root: PathBuf::from("/repo"),
+            files: vec!["README.md".to_string(), "src/config.rs".to_string(), "src/main.rs".to_string()],
+            symbols,
+        }
+    }
+
+    #[test]
+    fn mentions_are_parsed_from_prompts() {
+        assert_eq!(
+            mentions("why does #load_config fail in @src/config.rs? see # notes, #1 and @src/config.rs"),
+            vec![Mention::Symbol("load_config".to_string()), Mention::File("src/config.rs".to_string())]
+        );
+    }
+
+    #[test]
+    fn completions_match_files_symbols_and_commands() {
+        let index = index();
+        let registry = SlashCommandRegistry::builtin();
+
+        let files = index.complete("@cfg", false, &registry);
+        assert_eq!(files[0].text, "@src/config.rs");
+        assert_eq!(files[0].kind, CompletionKind::File);
+
+        let symbols = index.complete("#ldcf", false, &registry);
+        assert_eq!((symbols[0].text.as_str(), symbols[0].detail.as_str()), ("#load_config", "pub fn load_config() {}"));
+
+        assert_eq!(index.complete("/bookm", true, &registry)[0].text, "/bookmark");
+        assert!(index.complete("/bookm", false, &registry).is_empty());
+        assert!(index.complete("plain", false, &registry).is_empty());
+        assert_eq!(excerpt("a\nb\nc\n", 2, 1), "b\n…\n");
+    }
+}
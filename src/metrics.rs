@@ -0,0 +1,260 @@
+//! Runtime metrics for PiCode deployments
+//!
+//! Collects per-provider request counts, token usage, latencies and error
+//! rates plus active session gauges, rendered in the Prometheus text
+//! exposition format for the serve mode `/metrics` endpoint. Rate limits,
+//! health checks and today's token usage feed the provider health pane.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 9] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// A Prometheus counter: its name, help text and value per provider
+type Counter = (&'static str, &'static str, fn(&ProviderMetrics) -> u64);
+
+/// Metrics recorded for a single provider
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderMetrics {
+    pub requests: u64,
+    pub errors: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub latency_sum_seconds: f64,
+    /// Cumulative counts per entry of `LATENCY_BUCKETS`
+    pub latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    /// Requests rejected with HTTP 429
+    pub rate_limits: u64,
+    /// When the provider's last rate limit lifts
+    pub rate_limited_until: Option<DateTime<Utc>>,
+    pub last_health_check: Option<HealthCheck>,
+    pub today: DailyUsage,
+}
+
+impl ProviderMetrics {
+    /// Share of requests that failed, 0.0 to 1.0
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.requests > 0).then(|| Duration::from_secs_f64(self.latency_sum_seconds / self.requests as f64))
+    }
+
+    /// Whether a rate limit is in force at `now`
+    pub fn is_rate_limited(&self, now: DateTime<Utc>) -> bool {
+        self.rate_limited_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Outcome of a provider health check
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthCheck {
+    pub at: DateTime<Utc>,
+    pub healthy: bool,
+    pub latency: Duration,
+    pub error: Option<String>,
+}
+
+/// Tokens used on one (UTC) day
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DailyUsage {
+    pub day: Option<NaiveDate>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl DailyUsage {
+    fn add(&mut self, day: NaiveDate, prompt_tokens: u32, completion_tokens: u32) {
+        if self.day != Some(day) {
+            *self = Self { day: Some(day), ..Self::default() };
+        }
+        self.prompt_tokens += prompt_tokens as u64;
+        self.completion_tokens += completion_tokens as u64;
+    }
+
+    /// Usage on `day`; zero when the recorded usage is from another day
+    pub fn on(&self, day: NaiveDate) -> Self {
+        if self.day == Some(day) {
+            *self
+        } else {
+            Self { day: Some(day), ..Self::default() }
+        }
+    }
+}
+
+/// Process-wide metrics registry
+#[derive(Debug, Default)]
+pub struct Metrics {
+    providers: Mutex<BTreeMap<String, ProviderMetrics>>,
+    active_sessions: AtomicI64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared registry used by the running process
+    pub fn global() -> &'static Metrics {
+        static GLOBAL: OnceLock<Metrics> = OnceLock::new();
+        GLOBAL.get_or_init(Metrics::new)
+    }
+
+    /// Record a completed LLM request
+    pub fn record_request(
+        &self,
+        provider: &str,
+        latency: Duration,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+        success: bool,
+    ) {
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let metrics = providers.entry(provider.to_string()).or_default();
+
+        metrics.requests += 1;
+        if !success {
+            metrics.errors += 1;
+        }
+        metrics.prompt_tokens += prompt_tokens as u64;
+        metrics.completion_tokens += completion_tokens as u64;
+        metrics.today.add(Utc::now().date_naive(), prompt_tokens, completion_tokens);
+
+        let seconds = latency.as_secs_f64();
+        metrics.latency_sum_seconds += seconds;
+        for (bucket, bound) in metrics.latency_buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+    }
+
+    /// Record a request rejected for rate limiting, lifting after `retry_after`
+    pub fn record_rate_limit(&self, provider: &str, retry_after: Duration) {
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let metrics = providers.entry(provider.to_string()).or_default();
+        metrics.rate_limits += 1;
+        metrics.rate_limited_until = chrono::Duration::from_std(retry_after).ok().map(|retry| Utc::now() + retry);
+    }
+
+    pub fn record_health_check(&self, provider: &str, check: HealthCheck) {
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        providers.entry(provider.to_string()).or_default().last_health_check = Some(check);
+    }
+
+    pub fn session_started(&self) {
+        self.active_sessions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn session_ended(&self) {
+        self.active_sessions.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn active_sessions(&self) -> i64 {
+        self.active_sessions.load(Ordering::Relaxed)
+    }
+
+    /// Snapshot of the metrics for one provider
+    pub fn provider(&self, provider: &str) -> Option<ProviderMetrics> {
+        let providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        providers.get(provider).cloned()
+    }
+
+    /// Render all metrics in Prometheus text format
+    pub fn render_prometheus(&self) -> String {
+        let providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+
+        let counters: [Counter; 5] = [
+            ("picode_llm_requests_total", "Total LLM requests", |m| m.requests),
+            ("picode_llm_errors_total", "Failed LLM requests", |m| m.errors),
+            ("picode_llm_rate_limits_total", "LLM requests rejected by rate limits", |m| m.rate_limits),
+            ("picode_llm_prompt_tokens_total", "Prompt tokens sent", |m| m.prompt_tokens),
+            ("picode_llm_completion_tokens_total", "Completion tokens received", |m| m.completion_tokens),
+        ];
+
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            for (provider, metrics) in providers.iter() {
+                let _ = writeln!(out, "{}{{provider=\"{}\"}} {}", name, escape_label(provider), value(metrics));
+            }
+        }
+
+        let histogram = "picode_llm_request_duration_seconds";
+        let _ = writeln!(out, "# HELP {} LLM request latency", histogram);
+        let _ = writeln!(out, "# TYPE {} histogram", histogram);
+        for (provider, metrics) in providers.iter() {
+            let provider = escape_label(provider);
+            for (count, bound) in metrics.latency_buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(out, "{}_bucket{{provider=\"{}\",le=\"{}\"}} {}", histogram, provider, bound, count);
+            }
+            let _ = writeln!(out, "{}_bucket{{provider=\"{}\",le=\"+Inf\"}} {}", histogram, provider, metrics.requests);
+            let _ = writeln!(out, "{}_sum{{provider=\"{}\"}} {}", histogram, provider, metrics.latency_sum_seconds);
+            let _ = writeln!(out, "{}_count{{provider=\"{}\"}} {}", histogram, provider, metrics.requests);
+        }
+
+        let _ = writeln!(out, "# HELP picode_active_sessions Currently active sessions");
+        let _ = writeln!(out, "# TYPE picode_active_sessions gauge");
+        let _ = writeln!(out, "picode_active_sessions {}", self.active_sessions());
+
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_requests_per_provider() {
+        let metrics = Metrics::new();
+        metrics.record_request("openai", Duration::from_millis(200), 100, 20, true);
+        metrics.record_request("openai", Duration::from_secs(3), 50, 0, false);
+
+        let openai = metrics.provider("openai").unwrap();
+        assert_eq!(openai.requests, 2);
+        assert_eq!(openai.errors, 1);
+        assert_eq!(openai.prompt_tokens, 150);
+        assert_eq!(openai.latency_buckets[0], 0);
+        assert_eq!(openai.latency_buckets[1], 1);
+        assert_eq!(openai.latency_buckets[5], 2);
+        assert_eq!(openai.error_rate(), 0.5);
+        assert!((openai.average_latency().unwrap().as_secs_f64() - 1.6).abs() < 1e-6);
+        assert_eq!(openai.today.on(Utc::now().date_naive()).prompt_tokens, 150);
+        assert!(metrics.provider("anthropic").is_none());
+
+        metrics.record_rate_limit("openai", Duration::from_secs(30));
+        let openai = metrics.provider("openai").unwrap();
+        assert_eq!(openai.rate_limits, 1);
+        assert!(openai.is_rate_limited(Utc::now()));
+        assert!(!openai.is_rate_limited(Utc::now() + chrono::Duration::seconds(31)));
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        let metrics = Metrics::new();
+        metrics.record_request("anthropic", Duration::from_millis(50), 10, 5, true);
+        metrics.session_started();
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("# TYPE picode_llm_requests_total counter"));
+        assert!(text.contains("picode_llm_requests_total{provider=\"anthropic\"} 1"));
+        assert!(text.contains("picode_llm_request_duration_seconds_bucket{provider=\"anthropic\",le=\"+Inf\"} 1"));
+        assert!(text.contains("picode_active_sessions 1"));
+    }
+}
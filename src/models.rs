@@ -0,0 +1,201 @@
+//! Model catalog for the interactive `/model` picker
+//!
+//! Lists every configured provider with the models it reports through
+//! `get_models`. Provider answers are cached for a few minutes so opening the
+//! picker repeatedly does not hit every API; providers that cannot be reached
+//! fall back to their configured default model.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use picode_llm::ModelInfo;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// How long a provider's model list is reused
+const CATALOG_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// A provider/model pair offered by the picker
+#[derive(Debug, Clone)]
+pub struct ModelChoice {
+    pub provider: String,
+    pub model: ModelInfo,
+    /// USD per million prompt and completion tokens, when configured
+    pub price: Option<(f64, f64)>,
+}
+
+impl ModelChoice {
+    /// `provider/model` label, also accepted by [`ModelCatalog::resolve`]
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.provider, self.model.id)
+    }
+
+    /// One line for the picker listing
+    pub fn describe(&self) -> String {
+        let context = self
+            .model
+            .context_window
+            .map(|tokens| format!("{}k ctx", tokens / 1000))
+            .unwrap_or_else(|| "ctx ?".to_string());
+        let price = self
+            .price
+            .map(|(prompt, completion)| format!("${:.2}/${:.2} per Mtok", prompt, completion))
+            .unwrap_or_else(|| "price ?".to_string());
+        format!("{:<48} {:>10}  {}", self.label(), context, price)
+    }
+}
+
+struct CachedModels {
+    models: Vec<ModelInfo>,
+    fetched_at: Instant,
+}
+
+/// Per-session cache of the models each configured provider offers
+pub struct ModelCatalog {
+    ttl: Duration,
+    cache: HashMap<String, CachedModels>,
+}
+
+impl ModelCatalog {
+    pub fn new() -> Self {
+        Self {
+            ttl: CATALOG_TTL,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Forget cached model lists so the next listing asks the providers again
+    pub fn refresh(&mut self) {
+        self.cache.clear();
+    }
+
+    /// All configured providers and their models, providers sorted by name
+    pub async fn choices(&mut self, config: &Config) -> Vec<ModelChoice> {
+        let mut providers: Vec<&String> = config.llm.providers.keys().collect();
+        if !config.llm.providers.contains_key(&config.llm.default_provider) {
+            providers.push(&config.llm.default_provider);
+        }
+        providers.sort();
+
+        let mut choices = Vec::new();
+        for provider in providers {
+            let price = config.llm.providers.get(provider).and_then(|p| {
+                Some((p.prompt_price_per_million?, p.completion_price_per_million?))
+            });
+            for model in self.models(config, provider).await {
+                choices.push(ModelChoice {
+                    provider: provider.clone(),
+                    model,
+                    price,
+                });
+            }
+        }
+        choices
+    }
+
+    /// Models of one provider, from the cache when fresh
+    async fn models(&mut self, config: &Config, provider: &str) -> Vec<ModelInfo> {
+        if let Some(cached) = self.cache.get(provider) {
+            if cached.fetched_at.elapsed() < self.ttl {
+                return cached.models.clone();
+            }
+        }
+
+        let models = match Assistant::for_provider(config, provider) {
+            Ok(assistant) => match assistant.models().await {
+                Ok(models) if !models.is_empty() => models,
+                Ok(_) => vec![fallback_model(assistant.model())],
+                Err(e) => {
+                    debug!("Listing models of {} failed: {}", provider, e);
+                    vec![fallback_model(assistant.model())]
+                }
+            },
+            Err(e) => {
+                debug!("Provider {} unavailable: {}", provider, e);
+                Vec::new()
+            }
+        };
+
+        self.cache.insert(
+            provider.to_string(),
+            CachedModels {
+                models: models.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+        models
+    }
+
+    /// Find a choice by 1-based index, `provider/model` label or model id
+    pub fn resolve<'a>(choices: &'a [ModelChoice], selector: &str) -> Option<&'a ModelChoice> {
+        if let Ok(index) = selector.parse::<usize>() {
+            return index.checked_sub(1).and_then(|i| choices.get(i));
+        }
+        choices
+            .iter()
+            .find(|choice| choice.label() == selector)
+            .or_else(|| choices.iter().find(|choice| choice.model.id == selector))
+    }
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Entry for a provider that could not list its models
+fn fallback_model(id: &str) -> ModelInfo {
+    ModelInfo {
+        id: id.to_string(),
+        name: id.to_string(),
+        description: Some("configured default".to_string()),
+        context_window: None,
+        max_output_tokens: None,
+        capabilities: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choice(provider: &str, id: &str) -> ModelChoice {
+        ModelChoice {
+            provider: provider.to_string(),
+            model: fallback_model(id),
+            price: None,
+        }
+    }
+
+    #[test]
+    fn resolve_by_index_label_or_id() {
+        let choices = vec![choice("anthropic", "claude-3-opus"), choice("openai", "gpt-4o")];
+
+        assert_eq!(ModelCatalog::resolve(&choices, "2").unwrap().model.id, "gpt-4o");
+        assert_eq!(ModelCatalog::resolve(&choices, "openai/gpt-4o").unwrap().provider, "openai");
+        assert_eq!(ModelCatalog::resolve(&choices, "claude-3-opus").unwrap().provider, "anthropic");
+        assert!(ModelCatalog::resolve(&choices, "0").is_none());
+        assert!(ModelCatalog::resolve(&choices, "gpt-5").is_none());
+    }
+
+    #[test]
+    fn describe_shows_context_and_price() {
+        let mut choice = choice("openai", "gpt-4o");
+        choice.model.context_window = Some(128_000);
+        choice.price = Some((2.5, 10.0));
+        let line = choice.describe();
+        assert!(line.contains("128k ctx"));
+        assert!(line.contains("$2.50/$10.00 per Mtok"));
+    }
+
+    #[tokio::test]
+    async fn unreachable_providers_are_cached() {
+        let mut config = Config::default();
+        config.llm.default_provider = "picode-test-missing".to_string();
+        let mut catalog = ModelCatalog::new();
+
+        assert!(catalog.choices(&config).await.is_empty());
+        assert!(catalog.cache.contains_key("picode-test-missing"));
+    }
+}
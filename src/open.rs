@@ -0,0 +1,360 @@
+//! External editor integration
+//!
+//! `picode open <file:line[:col]>` and `/open` launch the user's editor
+//! (`[editor] external`, else `$VISUAL`, else `$EDITOR`) at a location,
+//! watch the file until the editor saves it and re-ingest the new content
+//! into the session: pinned copies are refreshed, the model is told about
+//! the edit and a `FileModified` event is published.
+//!
+//! Terminal editors own the terminal until they exit, so their edits are
+//! picked up when they close. GUI editors are watched while they run and the
+//! first save is taken.
+
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_core::{ConversationLog, ConversationMessage, Event, PaneId, SessionId};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::info;
+
+/// Editor used when neither the config nor the environment names one
+pub const FALLBACK_EDITOR: &str = "vi";
+
+/// How often the file is checked for a save
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a GUI editor is watched before giving up
+const WATCH_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// File and optional 1-based line and column to open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenTarget {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl OpenTarget {
+    /// Parse `path`, `path:LINE` or `path:LINE:COL`
+    pub fn parse(target: &str) -> Result<Self> {
+        if target.is_empty() {
+            return Err(PiCodeError::InvalidCommand("open requires a file path".to_string()));
+        }
+        let numeric = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        let position = |part: &str| match part.parse() {
+            Ok(0) | Err(_) => Err(PiCodeError::Parse(format!("invalid position '{}'", part))),
+            Ok(value) => Ok(value),
+        };
+
+        let mut path = target;
+        let mut numbers = Vec::new();
+        while numbers.len() < 2 {
+            match path.rsplit_once(':') {
+                Some((rest, part)) if numeric(part) && !rest.is_empty() => {
+                    numbers.insert(0, position(part)?);
+                    path = rest;
+                }
+                _ => break,
+            }
+        }
+        Ok(Self {
+            path: PathBuf::from(path),
+            line: numbers.first().copied(),
+            column: numbers.get(1).copied(),
+        })
+    }
+
+    /// Resolve a relative path against the workspace root
+    pub fn resolve(mut self, workspace: Option<&Path>) -> Self {
+        if let Some(root) = workspace.filter(|_| self.path.is_relative()) {
+            self.path = root.join(&self.path);
+        }
+        self
+    }
+
+    fn location(&self) -> String {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", self.path.display(), line, column),
+            (Some(line), None) => format!("{}:{}", self.path.display(), line),
+            _ => self.path.display().to_string(),
+        }
+    }
+}
+
+/// Editors whose command line for jumping to a location is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorFamily {
+    VsCode,
+    Sublime,
+    Vim,
+    Helix,
+    Emacs,
+    Nano,
+    Other,
+}
+
+impl EditorFamily {
+    /// Recognize an editor by its program name
+    pub fn detect(program: &str) -> Self {
+        let name = Path::new(program)
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .unwrap_or(program);
+        match name {
+            "code" | "code-insiders" | "codium" | "cursor" => Self::VsCode,
+            "subl" | "sublime_text" => Self::Sublime,
+            "vi" | "vim" | "nvim" | "gvim" | "mvim" => Self::Vim,
+            "hx" | "helix" => Self::Helix,
+            "emacs" | "emacsclient" => Self::Emacs,
+            "nano" => Self::Nano,
+            _ => Self::Other,
+        }
+    }
+
+    /// Whether the editor runs in, and blocks, the terminal
+    pub fn is_terminal(self) -> bool {
+        !matches!(self, Self::VsCode | Self::Sublime)
+    }
+
+    /// Arguments opening `target` at its location
+    pub fn arguments(self, target: &OpenTarget) -> Vec<String> {
+        let path = target.path.display().to_string();
+        let line = target.line.unwrap_or(1);
+        let column = target.column.unwrap_or(1);
+        if target.line.is_none() && self.is_terminal() {
+            return vec![path];
+        }
+        match self {
+            // GUI editors are asked to block until the file is closed
+            Self::VsCode => vec!["--wait".to_string(), "--goto".to_string(), target.location()],
+            Self::Sublime => vec!["--wait".to_string(), target.location()],
+            Self::Helix => vec![target.location()],
+            Self::Vim => vec![format!("+call cursor({}, {})", line, column), path],
+            Self::Emacs => vec![format!("+{}:{}", line, column), path],
+            Self::Nano => vec![format!("+{},{}", line, column), path],
+            Self::Other => vec![path],
+        }
+    }
+}
+
+/// The editor to launch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalEditor {
+    pub program: String,
+    /// Arguments given with the editor command, before the location
+    pub args: Vec<String>,
+    pub family: EditorFamily,
+}
+
+impl ExternalEditor {
+    /// Parse an editor command such as `code -n`
+    pub fn parse(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words.next()?;
+        Some(Self {
+            family: EditorFamily::detect(&program),
+            program,
+            args: words.collect(),
+        })
+    }
+
+    /// The configured editor, else `$VISUAL`, else `$EDITOR`, else `vi`
+    pub fn from_config(config: &Config) -> Self {
+        let from_env = |name| std::env::var(name).ok();
+        config
+            .ui
+            .editor
+            .external
+            .clone()
+            .or_else(|| from_env("VISUAL"))
+            .or_else(|| from_env("EDITOR"))
+            .and_then(|command| Self::parse(&command))
+            .unwrap_or_else(|| Self::parse(FALLBACK_EDITOR).expect("fallback editor is not empty"))
+    }
+
+    /// Full argument list for opening `target`
+    pub fn command_line(&self, target: &OpenTarget) -> Vec<String> {
+        let mut args = self.args.clone();
+        args.extend(self.family.arguments(target));
+        args
+    }
+}
+
+/// What happened to a file while it was open in the external editor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalEdit {
+    Saved { path: PathBuf, content: String },
+    Unchanged { path: PathBuf },
+}
+
+impl ExternalEdit {
+    pub fn path(&self) -> &Path {
+        match self {
+            ExternalEdit::Saved { path, .. } | ExternalEdit::Unchanged { path } => path,
+        }
+    }
+}
+
+/// Modification time, size and content of a file, to tell saves apart
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    modified: Option<SystemTime>,
+    len: u64,
+    content: Option<String>,
+}
+
+async fn snapshot(path: &Path) -> Snapshot {
+    match tokio::fs::metadata(path).await {
+        Ok(metadata) => Snapshot {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            content: tokio::fs::read_to_string(path).await.ok(),
+        },
+        Err(_) => Snapshot { modified: None, len: 0, content: None },
+    }
+}
+
+/// The edit, if the file now differs from `before`. A save that leaves
+/// the content as it was (e.g. `:w` without changes) is not an edit.
+async fn saved(path: &Path, before: &Snapshot) -> Option<ExternalEdit> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    if metadata.modified().ok() == before.modified && metadata.len() == before.len {
+        return None;
+    }
+    let now = snapshot(path).await;
+    match now.content {
+        Some(content) if Some(&content) != before.content.as_ref() => Some(ExternalEdit::Saved {
+            path: path.to_path_buf(),
+            content,
+        }),
+        _ => None,
+    }
+}
+
+/// Open `target` in `editor` and wait for the edit
+pub async fn edit(editor: &ExternalEditor, target: &OpenTarget) -> Result<ExternalEdit> {
+    let before = snapshot(&target.path).await;
+    let args = editor.command_line(target);
+    info!("Launching {} {}", editor.program, args.join(" "));
+    let mut child = tokio::process::Command::new(&editor.program)
+        .args(&args)
+        .spawn()
+        .map_err(|e| PiCodeError::Internal(format!("failed to launch editor '{}': {}", editor.program, e)))?;
+
+    let unchanged = || ExternalEdit::Unchanged { path: target.path.clone() };
+    if editor.family.is_terminal() {
+        child.wait().await?;
+        return Ok(saved(&target.path, &before).await.unwrap_or_else(unchanged));
+    }
+
+    let started = Instant::now();
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        if let Some(edit) = saved(&target.path, &before).await {
+            return Ok(edit);
+        }
+        if child.try_wait()?.is_some() || started.elapsed() > WATCH_TIMEOUT {
+            return Ok(unchanged());
+        }
+    }
+}
+
+/// Bring an external edit into the conversation: refresh the pinned copy of
+/// the file, tell the model what changed and describe the modification as
+/// an event for the session's bus. `None` when nothing changed.
+pub fn reingest(
+    edit: &ExternalEdit,
+    conversation: &mut ConversationLog,
+    session_id: &SessionId,
+    pane_id: &PaneId,
+) -> Option<Event> {
+    let ExternalEdit::Saved { path, content } = edit else {
+        return None;
+    };
+    let label = path.display().to_string();
+    let pinned = conversation.pinned.iter_mut().find(|item| Path::new(&item.label) == path.as_path());
+    let message = match pinned {
+        Some(item) => {
+            item.content = content.clone();
+            format!("The user edited {} outside PiCode; the pinned copy is updated.", label)
+        }
+        None => format!(
+            "The user edited {} outside PiCode. Its content is now:\n```\n{}\n```",
+            label,
+            content.trim_end()
+        ),
+    };
+    conversation.push(ConversationMessage::new("user", message));
+    Some(Event::FileModified {
+        session_id: session_id.clone(),
+        pane_id: pane_id.clone(),
+        file_path: path.clone(),
+    })
+}
+
+/// Summary line for an external edit
+pub fn describe(edit: &ExternalEdit) -> String {
+    match edit {
+        ExternalEdit::Saved { path, content } => {
+            format!("Saved {} ({} lines)", path.display(), content.lines().count())
+        }
+        ExternalEdit::Unchanged { path } => format!("{} was not changed", path.display()),
+    }
+}
+
+/// Run `picode open`
+pub async fn run(target: &str, config: Config) -> Result<()> {
+    let target = OpenTarget::parse(target)?.resolve(config.workspace.root_dir.as_deref());
+    let editor = ExternalEditor::from_config(&config);
+    println!("Opening {} in {}", target.location(), editor.program);
+    let edit = edit(&editor, &target).await?;
+    println!("{}", describe(&edit));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_targets_and_builds_editor_command_lines() {
+        let target = OpenTarget::parse("src/main.rs:42:7").unwrap();
+        assert_eq!(target.path, PathBuf::from("src/main.rs"));
+        assert_eq!((target.line, target.column), (Some(42), Some(7)));
+        assert_eq!(OpenTarget::parse("README.md").unwrap().line, None);
+        assert!(OpenTarget::parse("a.rs:0").is_err());
+
+        let code = ExternalEditor::parse("code -n").unwrap();
+        assert_eq!(code.family, EditorFamily::VsCode);
+        assert_eq!(code.command_line(&target), vec!["-n", "--wait", "--goto", "src/main.rs:42:7"]);
+        let vim = ExternalEditor::parse("/usr/bin/nvim").unwrap();
+        assert!(vim.family.is_terminal());
+        assert_eq!(vim.command_line(&target), vec!["+call cursor(42, 7)", "src/main.rs"]);
+        let nano = ExternalEditor::parse("nano").unwrap();
+        assert_eq!(nano.command_line(&OpenTarget::parse("a.rs").unwrap()), vec!["a.rs"]);
+    }
+
+    #[tokio::test]
+    async fn saved_edit_is_reingested() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        tokio::fs::write(&path, "draft\n").await.unwrap();
+
+        // `sh -c` stands in for an editor that appends a line and saves
+        let editor = ExternalEditor {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), "echo done >> \"$0\"".to_string()],
+            family: EditorFamily::Other,
+        };
+        let edit = edit(&editor, &OpenTarget::parse(path.to_str().unwrap()).unwrap()).await.unwrap();
+        assert_eq!(edit, ExternalEdit::Saved { path: path.clone(), content: "draft\ndone\n".to_string() });
+
+        let session_id = SessionId::new();
+        let mut conversation = ConversationLog::new(session_id.clone());
+        conversation.pinned.push(picode_core::conversation::PinnedItem::new(path.display().to_string(), "draft\n"));
+        let event = reingest(&edit, &mut conversation, &session_id, &PaneId::new()).unwrap();
+        assert_eq!(event.event_type(), "file_modified");
+        assert_eq!(conversation.pinned[0].content, "draft\ndone\n");
+        assert!(reingest(&ExternalEdit::Unchanged { path }, &mut conversation, &session_id, &PaneId::new()).is_none());
+    }
+}
@@ -0,0 +1,299 @@
+//! Context packs
+//!
+//! A context pack is a named bundle of files and notes for a recurring
+//! deep-dive, so the same files do not have to be pinned one by one every
+//! time. Packs are declared in `[packs.<name>]` of the configuration and,
+//! to share them through version control, in a trusted workspace's
+//! `picode.toml`, which overrides the configuration by name:
+//!
+//! ```toml
+//! [packs.auth-subsystem]
+//! description = "Login, sessions and token refresh"
+//! version = 3
+//! files = ["src/auth/**/*.rs", "docs/auth.md", "!src/auth/generated/**"]
+//! notes = "Tokens are rotated by refresh_token(); sessions live in Redis."
+//! ```
+//!
+//! `files` are gitignore-style globs relative to the workspace root; a
+//! leading `!` excludes. `/pack <name>` pins the matching files into the
+//! conversation together with an item naming the pack, its version and its
+//! notes, which is saved with the session so it shows what was attached.
+//! Attaching a pack again refreshes it; `/pack drop <name>` removes it.
+
+use crate::config::{Config, ConfigError};
+use crate::error::{PiCodeError, Result};
+use crate::tasks::WORKSPACE_CONFIG_FILE;
+use picode_core::conversation::PinnedItem;
+use picode_core::ConversationLog;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Files pinned from one pack at most
+pub const MAX_PACK_FILES: usize = 100;
+
+/// Label prefix of the item recording an attached pack
+const PACK_LABEL: &str = "pack:";
+
+/// A named bundle of files and notes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextPack {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Bumped by the pack's authors when its contents change meaning
+    #[serde(default = "default_version")]
+    pub version: u32,
+    /// Globs relative to the workspace root; `!` excludes
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Background given to the model along with the files
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+impl ContextPack {
+    /// The paths among `candidates` (relative to `root`) the pack's globs
+    /// select, sorted
+    pub fn select(&self, root: &Path, candidates: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let mut builder = ignore::overrides::OverrideBuilder::new(root);
+        for glob in &self.files {
+            builder
+                .add(glob)
+                .map_err(|e| PiCodeError::InvalidCommand(format!("invalid pack glob '{}': {}", glob, e)))?;
+        }
+        let matcher = builder
+            .build()
+            .map_err(|e| PiCodeError::InvalidCommand(format!("invalid pack globs: {}", e)))?;
+        let mut selected: Vec<PathBuf> = candidates
+            .iter()
+            .filter(|path| matcher.matched(root.join(path), false).is_whitelist())
+            .cloned()
+            .collect();
+        selected.sort();
+        Ok(selected)
+    }
+
+    /// The workspace files the pack selects
+    pub async fn resolve(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        let mut workspace = picode_core::Workspace::new(picode_core::WorkspaceConfig {
+            root_path: root.to_path_buf(),
+            git_enabled: false,
+            ..picode_core::WorkspaceConfig::default()
+        });
+        workspace.scan().await.map_err(picode_core::CoreError::from)?;
+        let files: Vec<PathBuf> = workspace.files.iter().map(|file| file.relative_path.clone()).collect();
+        self.select(root, &files)
+    }
+
+    /// Text of the item recording the pack in a conversation
+    fn summary(&self, name: &str, files: &[PathBuf]) -> String {
+        let mut out = format!("Context pack {} (version {})", name, self.version);
+        if let Some(description) = &self.description {
+            let _ = write!(out, ": {}", description);
+        }
+        out.push('\n');
+        if let Some(notes) = &self.notes {
+            let _ = writeln!(out, "\n{}", notes.trim_end());
+        }
+        let files: Vec<String> = files.iter().map(|path| picode_core::paths::to_slash(path)).collect();
+        let _ = writeln!(out, "\nFiles: {}", files.join(", "));
+        out
+    }
+}
+
+/// What attaching a pack did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attached {
+    pub files: usize,
+    pub tokens: usize,
+    /// Selected files left out: unreadable, too large or over the limit
+    pub skipped: Vec<String>,
+}
+
+/// Only the part of `picode.toml` this module reads
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceFile {
+    #[serde(default)]
+    packs: BTreeMap<String, ContextPack>,
+}
+
+/// Packs available in a workspace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextPacks {
+    packs: BTreeMap<String, ContextPack>,
+}
+
+impl ContextPacks {
+    pub fn from_config(config: &Config) -> Self {
+        Self { packs: config.packs.clone() }
+    }
+
+    /// Packs of the configuration overlaid with the workspace's
+    /// `picode.toml`, which is ignored unless the workspace is trusted
+    pub async fn load(config: &Config, workspace_root: &Path) -> Result<Self> {
+        let mut packs = Self::from_config(config);
+        let path = workspace_root.join(WORKSPACE_CONFIG_FILE);
+        if tokio::fs::try_exists(&path).await? && crate::trust::is_trusted(workspace_root) {
+            let content = tokio::fs::read_to_string(&path).await?;
+            packs.packs.extend(Self::parse(&content)?);
+        }
+        Ok(packs)
+    }
+
+    /// Read the `[packs]` section of a `picode.toml` document
+    pub fn parse(content: &str) -> Result<BTreeMap<String, ContextPack>> {
+        let file: WorkspaceFile = ::config::Config::builder()
+            .add_source(::config::File::from_str(content, ::config::FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| ConfigError::InvalidConfig(format!("{}: {}", WORKSPACE_CONFIG_FILE, e)))?;
+        Ok(file.packs)
+    }
+
+    pub fn get(&self, name: &str) -> Result<&ContextPack> {
+        self.packs.get(name).ok_or_else(|| {
+            let available: Vec<&str> = self.packs.keys().map(String::as_str).collect();
+            PiCodeError::NotFound(if available.is_empty() {
+                format!("context pack '{}' (none defined; add [packs.{}] to {})", name, name, WORKSPACE_CONFIG_FILE)
+            } else {
+                format!("context pack '{}' (available: {})", name, available.join(", "))
+            })
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ContextPack)> {
+        self.packs.iter()
+    }
+
+    /// Pin the files of pack `name` into `log`, replacing what an earlier
+    /// attach of it pinned; files over `max_file_size` bytes are skipped
+    pub async fn attach(&self, name: &str, root: &Path, log: &mut ConversationLog, max_file_size: u64) -> Result<Attached> {
+        let pack = self.get(name)?;
+        let files = pack.resolve(root).await?;
+        if files.is_empty() {
+            return Err(PiCodeError::NotFound(format!("files matching context pack '{}'", name)));
+        }
+        detach_files(log, name, &files);
+
+        let mut attached = Attached::default();
+        let mut pinned = Vec::new();
+        for (index, path) in files.iter().enumerate() {
+            let label = picode_core::paths::to_slash(path);
+            if index >= MAX_PACK_FILES {
+                attached.skipped.push(label);
+                continue;
+            }
+            let absolute = root.join(path);
+            match tokio::fs::metadata(&absolute).await {
+                Ok(metadata) if metadata.len() <= max_file_size => {}
+                _ => {
+                    attached.skipped.push(label);
+                    continue;
+                }
+            }
+            match tokio::fs::read_to_string(&absolute).await {
+                Ok(content) => {
+                    attached.tokens += picode_core::system_prompt::estimate_tokens(&content);
+                    log.pinned.push(PinnedItem::new(label, content));
+                    pinned.push(path.clone());
+                }
+                Err(_) => attached.skipped.push(label),
+            }
+        }
+        attached.files = pinned.len();
+        let summary = PinnedItem::new(format!("{}{}@{}", PACK_LABEL, name, pack.version), pack.summary(name, &pinned));
+        attached.tokens += picode_core::system_prompt::estimate_tokens(&summary.content);
+        log.pinned.push(summary);
+        Ok(attached)
+    }
+
+    /// Remove pack `name` from `log`; returns the number of items removed
+    pub async fn detach(&self, name: &str, root: &Path, log: &mut ConversationLog) -> Result<usize> {
+        let files = self.get(name)?.resolve(root).await?;
+        Ok(detach_files(log, name, &files))
+    }
+}
+
+/// Names and versions of the packs attached to `log`
+pub fn attached(log: &ConversationLog) -> Vec<(String, u32)> {
+    log.pinned
+        .iter()
+        .filter_map(|item| item.label.strip_prefix(PACK_LABEL)?.rsplit_once('@'))
+        .filter_map(|(name, version)| Some((name.to_string(), version.parse().ok()?)))
+        .collect()
+}
+
+/// Remove the pack's record and the pinned `files` from `log`
+fn detach_files(log: &mut ConversationLog, name: &str, files: &[PathBuf]) -> usize {
+    let record = format!("{}{}@", PACK_LABEL, name);
+    let labels: Vec<String> = files.iter().map(|path| picode_core::paths::to_slash(path)).collect();
+    let before = log.pinned.len();
+    log.pinned.retain(|item| !item.label.starts_with(&record) && !labels.contains(&item.label));
+    before - log.pinned.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKSPACE: &str = r#"
+[packs.auth-subsystem]
+description = "Login, sessions and token refresh"
+version = 3
+files = ["src/auth/**/*.rs", "docs/auth.md", "!src/auth/generated/**"]
+notes = "Tokens are rotated by refresh_token()."
+"#;
+
+    #[test]
+    fn pack_globs_select_files() {
+        let packs = ContextPacks::parse(WORKSPACE).unwrap();
+        let pack = &packs["auth-subsystem"];
+        assert_eq!(pack.version, 3);
+
+        let root = Path::new("/ws");
+        let candidates: Vec<PathBuf> = [
+            "src/auth/session.rs",
+            "src/auth/tokens/refresh.rs",
+            "src/auth/generated/schema.rs",
+            "src/auth/README.md",
+            "src/main.rs",
+            "docs/auth.md",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        let selected = pack.select(root, &candidates).unwrap();
+        assert_eq!(
+            selected,
+            vec![PathBuf::from("docs/auth.md"), PathBuf::from("src/auth/session.rs"), PathBuf::from("src/auth/tokens/refresh.rs")]
+        );
+        assert!(ContextPacks::default().get("auth").unwrap_err().to_string().contains("[packs.auth]"));
+    }
+
+    #[tokio::test]
+    async fn attaching_again_replaces_the_pack() {
+        let root = tempfile::tempdir().unwrap();
+        tokio::fs::create_dir_all(root.path().join("src/auth")).await.unwrap();
+        tokio::fs::write(root.path().join("src/auth/session.rs"), "pub struct Session;").await.unwrap();
+        tokio::fs::write(root.path().join("docs.md"), "unrelated").await.unwrap();
+        let packs = ContextPacks { packs: ContextPacks::parse(WORKSPACE).unwrap() };
+
+        let mut log = ConversationLog::new(picode_core::SessionId::new());
+        log.pinned.push(PinnedItem::new("docs.md", "unrelated"));
+        let first = packs.attach("auth-subsystem", root.path(), &mut log, 1024).await.unwrap();
+        assert_eq!((first.files, first.skipped.len()), (1, 0));
+        packs.attach("auth-subsystem", root.path(), &mut log, 1024).await.unwrap();
+        let labels: Vec<&str> = log.pinned.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, vec!["docs.md", "src/auth/session.rs", "pack:auth-subsystem@3"]);
+        assert!(log.pinned[2].content.contains("Tokens are rotated") && log.pinned[2].content.contains("Files: src/auth/session.rs"));
+        assert_eq!(attached(&log), vec![("auth-subsystem".to_string(), 3)]);
+
+        assert_eq!(packs.detach("auth-subsystem", root.path(), &mut log).await.unwrap(), 2);
+        assert_eq!(log.pinned.len(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+//! Command palette for interactive mode
+//!
+//! Ctrl-P (or `/palette`) opens a full-screen list of slash commands, key
+//! bindings and recently used actions. Typing narrows the list with fuzzy
+//! matching, the highlighted entry's inline help is shown below the list,
+//! and Enter hands the chosen command back to the interactive loop.
+
+use crate::error::Result;
+use crate::slash::{fuzzy_score, SlashCommandRegistry, KEYBINDINGS};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::io;
+
+/// Number of recently used actions remembered for the palette
+pub const RECENT_LIMIT: usize = 10;
+
+/// What a palette entry refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteItemKind {
+    Recent,
+    Command,
+    Keybinding,
+}
+
+/// One row of the palette
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteItem {
+    pub kind: PaletteItemKind,
+    pub label: String,
+    pub summary: String,
+    pub help: String,
+    /// Input submitted when the entry is chosen; key bindings have none
+    pub action: Option<String>,
+}
+
+/// Result of feeding a key to the palette
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteOutcome {
+    Continue,
+    Close,
+    Run(String),
+}
+
+/// Recently used slash command lines, most recent first
+#[derive(Debug, Clone, Default)]
+pub struct RecentActions {
+    actions: VecDeque<String>,
+}
+
+impl RecentActions {
+    pub fn record(&mut self, input: &str) {
+        self.actions.retain(|action| action != input);
+        self.actions.push_front(input.to_string());
+        self.actions.truncate(RECENT_LIMIT);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.actions.iter()
+    }
+}
+
+/// Palette state: all entries, the query and the selection in the filtered list
+#[derive(Debug, Clone)]
+pub struct CommandPalette {
+    items: Vec<PaletteItem>,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new(registry: &SlashCommandRegistry, recent: &RecentActions) -> Self {
+        let mut items: Vec<PaletteItem> = recent
+            .iter()
+            .map(|action| {
+                let command = action.split_whitespace().next().and_then(|name| registry.get(name));
+                PaletteItem {
+                    kind: PaletteItemKind::Recent,
+                    label: action.clone(),
+                    summary: command.map(|c| c.summary.to_string()).unwrap_or_default(),
+                    help: command.map(|c| c.help.to_string()).unwrap_or_default(),
+                    action: Some(action.clone()),
+                }
+            })
+            .collect();
+        items.extend(registry.commands().iter().map(|command| PaletteItem {
+            kind: PaletteItemKind::Command,
+            label: command.synopsis(),
+            summary: command.summary.to_string(),
+            help: command.help.to_string(),
+            action: Some(command.invocation()),
+        }));
+        items.extend(KEYBINDINGS.iter().map(|binding| PaletteItem {
+            kind: PaletteItemKind::Keybinding,
+            label: binding.keys.to_string(),
+            summary: binding.description.to_string(),
+            help: String::new(),
+            action: None,
+        }));
+        Self {
+            items,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    /// Entries matching the query, best match first; with an empty query
+    /// the original order (recent, commands, key bindings) is kept
+    pub fn filtered(&self) -> Vec<&PaletteItem> {
+        let mut matches: Vec<(i64, usize, &PaletteItem)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                let label = fuzzy_score(&self.query, &item.label);
+                let summary = fuzzy_score(&self.query, &item.summary).map(|score| score / 2);
+                label.max(summary).map(|score| (score, index, item))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        matches.into_iter().map(|(_, _, item)| item).collect()
+    }
+
+    pub fn selected(&self) -> Option<&PaletteItem> {
+        self.filtered().get(self.selected).copied()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PaletteOutcome {
+        if key.kind != KeyEventKind::Press {
+            return PaletteOutcome::Continue;
+        }
+        let count = self.filtered().len();
+        match key.code {
+            KeyCode::Esc => return PaletteOutcome::Close,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return PaletteOutcome::Close,
+            KeyCode::Enter => {
+                return match self.selected().and_then(|item| item.action.clone()) {
+                    Some(action) => PaletteOutcome::Run(action),
+                    None => PaletteOutcome::Continue,
+                };
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.selected = (self.selected + 1).min(count.saturating_sub(1))
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        PaletteOutcome::Continue
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1), Constraint::Length(5)])
+            .split(frame.size());
+
+        let prompt = Paragraph::new(format!("> {}", self.query))
+            .block(Block::default().borders(Borders::ALL).title(crate::tr!("palette-title")));
+        frame.render_widget(prompt, chunks[0]);
+        frame.set_cursor(chunks[0].x + 3 + self.query.chars().count() as u16, chunks[0].y + 1);
+
+        let filtered = self.filtered();
+        let height = chunks[1].height.saturating_sub(2) as usize;
+        let offset = self.selected.saturating_sub(height.saturating_sub(1));
+        let lines: Vec<Line> = filtered
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(height)
+            .map(|(index, item)| {
+                let (tag, color) = match item.kind {
+                    PaletteItemKind::Recent => (crate::tr!("palette-tag-recent"), Color::Magenta),
+                    PaletteItemKind::Command => (crate::tr!("palette-tag-command"), Color::Cyan),
+                    PaletteItemKind::Keybinding => (crate::tr!("palette-tag-key"), Color::Yellow),
+                };
+                let mut style = Style::default();
+                if index == self.selected {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                Line::from(vec![
+                    Span::styled(format!(" {:<7} ", tag), style.fg(color)),
+                    Span::styled(format!("{:<28}", item.label), style.add_modifier(Modifier::BOLD)),
+                    Span::styled(format!(" {}", item.summary), style),
+                ])
+            })
+            .collect();
+        let title = crate::tr!("palette-count", shown = filtered.len(), total = self.items.len());
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+            chunks[1],
+        );
+
+        let help = self
+            .selected()
+            .map(|item| if item.help.is_empty() { item.summary.clone() } else { item.help.clone() })
+            .unwrap_or_default();
+        frame.render_widget(
+            Paragraph::new(help)
+                .wrap(Wrap { trim: true })
+                .block(Block::default().borders(Borders::ALL).title(crate::tr!("palette-help"))),
+            chunks[2],
+        );
+    }
+}
+
+/// Show the palette until the user picks an entry or closes it; returns the
+/// chosen input line
+pub fn run(registry: &SlashCommandRegistry, recent: &RecentActions) -> Result<Option<String>> {
+    let mut palette = CommandPalette::new(registry, recent);
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = event_loop(&mut palette);
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+fn event_loop(palette: &mut CommandPalette) -> Result<Option<String>> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    loop {
+        terminal.draw(|frame| palette.draw(frame))?;
+        match event::read()? {
+            Event::Key(key) => match palette.handle_key(key) {
+                PaletteOutcome::Continue => {}
+                PaletteOutcome::Close => return Ok(None),
+                PaletteOutcome::Run(action) => return Ok(Some(action)),
+            },
+            Event::Resize(..) => terminal.autoresize()?,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn fuzzy_filter_selects_and_runs() {
+        let mut recent = RecentActions::default();
+        recent.record("/model list");
+        recent.record("/raw");
+        recent.record("/model list");
+        let mut palette = CommandPalette::new(&SlashCommandRegistry::builtin(), &recent);
+        assert_eq!(palette.filtered()[0].label, "/model list");
+        assert_eq!(palette.filtered()[1].label, "/raw");
+
+        for c in "bkmk".chars() {
+            palette.handle_key(press(KeyCode::Char(c)));
+        }
+        let selected = palette.selected().unwrap();
+        assert_eq!(selected.kind, PaletteItemKind::Command);
+        assert!(selected.help.contains("Bookmarks"));
+        assert_eq!(palette.handle_key(press(KeyCode::Enter)), PaletteOutcome::Run("/bookmark".to_string()));
+
+        assert_eq!(palette.handle_key(press(KeyCode::Esc)), PaletteOutcome::Close);
+    }
+}
@@ -0,0 +1,241 @@
+//! Response policies
+//!
+//! A `[policy]` section sets the answer language, verbosity, code-comment
+//! style and phrases replies must not contain. The policy is merged into the
+//! system prompt as its own layer, and replies are checked against it after
+//! the fact: a violation is logged, or with `on_violation = "regenerate"`
+//! the reply is requested again with the violations pointed out. A
+//! trusted workspace's `picode.toml` `[policy]` section replaces the user's
+//! one.
+
+use crate::config::{Config, ConfigError};
+use crate::error::Result;
+use crate::tasks::WORKSPACE_CONFIG_FILE;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use std::path::Path;
+
+/// How much detail replies should go into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Terse,
+    #[default]
+    Normal,
+    Detailed,
+}
+
+/// What to do with a reply that breaks the policy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViolationAction {
+    /// Keep the reply and log the violations
+    #[default]
+    Warn,
+    /// Ask again, up to `max_regenerations` times
+    Regenerate,
+}
+
+/// Response policy for a user or workspace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponsePolicy {
+    /// Language replies are written in (e.g. "English", "German")
+    #[serde(default)]
+    pub language: Option<String>,
+
+    #[serde(default)]
+    pub verbosity: Verbosity,
+
+    /// Longest acceptable reply in words
+    #[serde(default)]
+    pub max_words: Option<usize>,
+
+    /// How code in replies is commented (e.g. "doc comments on public items only")
+    #[serde(default)]
+    pub code_comments: Option<String>,
+
+    /// Phrases replies must not contain (case-insensitive)
+    #[serde(default)]
+    pub forbidden_phrases: Vec<String>,
+
+    #[serde(default)]
+    pub on_violation: ViolationAction,
+
+    /// Regenerations before the last reply is kept anyway
+    #[serde(default = "default_max_regenerations")]
+    pub max_regenerations: u32,
+}
+
+fn default_max_regenerations() -> u32 {
+    2
+}
+
+impl Default for ResponsePolicy {
+    fn default() -> Self {
+        Self {
+            language: None,
+            verbosity: Verbosity::default(),
+            max_words: None,
+            code_comments: None,
+            forbidden_phrases: Vec::new(),
+            on_violation: ViolationAction::default(),
+            max_regenerations: default_max_regenerations(),
+        }
+    }
+}
+
+/// Only the part of `picode.toml` this module reads
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceFile {
+    #[serde(default)]
+    policy: Option<ResponsePolicy>,
+}
+
+/// A way a reply broke the policy
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyViolation {
+    ForbiddenPhrase(String),
+    TooLong { words: usize, max: usize },
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyViolation::ForbiddenPhrase(phrase) => write!(f, "used the forbidden phrase \"{}\"", phrase),
+            PolicyViolation::TooLong { words, max } => write!(f, "was {} words long (limit {})", words, max),
+        }
+    }
+}
+
+impl ResponsePolicy {
+    /// The user's policy, replaced by the workspace's `picode.toml` one if
+    /// it has a `[policy]` section and the workspace is trusted
+    pub async fn load(config: &Config, workspace_root: &Path) -> Result<Self> {
+        let path = workspace_root.join(WORKSPACE_CONFIG_FILE);
+        if tokio::fs::try_exists(&path).await? && crate::trust::is_trusted(workspace_root) {
+            let content = tokio::fs::read_to_string(&path).await?;
+            if let Some(policy) = Self::parse(&content)? {
+                return Ok(policy);
+            }
+        }
+        Ok(config.policy.clone())
+    }
+
+    /// Read the `[policy]` section of a `picode.toml` document
+    pub fn parse(content: &str) -> Result<Option<Self>> {
+        let file: WorkspaceFile = ::config::Config::builder()
+            .add_source(::config::File::from_str(content, ::config::FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| ConfigError::InvalidConfig(format!("{}: {}", WORKSPACE_CONFIG_FILE, e)))?;
+        Ok(file.policy)
+    }
+
+    /// Whether the policy asks for nothing beyond the defaults
+    pub fn is_empty(&self) -> bool {
+        self.prompt_layer().is_none()
+    }
+
+    /// Instructions for the system prompt's policy layer
+    pub fn prompt_layer(&self) -> Option<String> {
+        let mut out = String::new();
+        if let Some(language) = &self.language {
+            let _ = writeln!(out, "- Write every reply in {}, whatever language the question is in.", language);
+        }
+        match self.verbosity {
+            Verbosity::Terse => out.push_str("- Be terse: answer directly, skip background and recaps.\n"),
+            Verbosity::Normal => {}
+            Verbosity::Detailed => out.push_str("- Be thorough: explain your reasoning and the trade-offs involved.\n"),
+        }
+        if let Some(max) = self.max_words {
+            let _ = writeln!(out, "- Keep replies under {} words.", max);
+        }
+        if let Some(comments) = &self.code_comments {
+            let _ = writeln!(out, "- Comments in code you write: {}.", comments);
+        }
+        if !self.forbidden_phrases.is_empty() {
+            let phrases: Vec<String> = self.forbidden_phrases.iter().map(|p| format!("\"{}\"", p)).collect();
+            let _ = writeln!(out, "- Never use these phrases: {}.", phrases.join(", "));
+        }
+        (!out.is_empty()).then(|| format!("Follow this response policy:\n{}", out))
+    }
+
+    /// The ways `reply` breaks the checkable parts of the policy
+    pub fn check(&self, reply: &str) -> Vec<PolicyViolation> {
+        let lowered = reply.to_lowercase();
+        let mut violations: Vec<PolicyViolation> = self
+            .forbidden_phrases
+            .iter()
+            .filter(|phrase| !phrase.trim().is_empty() && lowered.contains(&phrase.to_lowercase()))
+            .map(|phrase| PolicyViolation::ForbiddenPhrase(phrase.clone()))
+            .collect();
+        if let Some(max) = self.max_words {
+            let words = reply.split_whitespace().count();
+            if words > max {
+                violations.push(PolicyViolation::TooLong { words, max });
+            }
+        }
+        violations
+    }
+
+    /// Prompt asking for a new reply that avoids `violations`
+    pub fn correction(&self, prompt: &str, violations: &[PolicyViolation]) -> String {
+        let reasons: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        format!(
+            "{}\n\n(Your previous reply {}, which breaks the response policy. Answer again following it.)",
+            prompt,
+            reasons.join(" and ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_policy_becomes_a_prompt_layer() {
+        let policy = ResponsePolicy::parse(
+            r#"
+[policy]
+language = "German"
+verbosity = "terse"
+code_comments = "none"
+forbidden_phrases = ["As an AI"]
+on_violation = "regenerate"
+"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(policy.on_violation, ViolationAction::Regenerate);
+        assert_eq!(policy.max_regenerations, 2);
+
+        let layer = policy.prompt_layer().unwrap();
+        assert!(layer.contains("Write every reply in German"));
+        assert!(layer.contains("Be terse"));
+        assert!(layer.contains("\"As an AI\""));
+        assert!(ResponsePolicy::default().is_empty());
+        assert_eq!(ResponsePolicy::parse("[tasks.test]\ncommand = \"cargo test\"\n").unwrap(), None);
+    }
+
+    #[test]
+    fn checks_forbidden_phrases_and_length() {
+        let policy = ResponsePolicy {
+            forbidden_phrases: vec!["as an ai".to_string()],
+            max_words: Some(5),
+            ..ResponsePolicy::default()
+        };
+        assert!(policy.check("Use a HashMap here.").is_empty());
+
+        let violations = policy.check("As an AI language model, I would use a HashMap.");
+        assert_eq!(
+            violations,
+            vec![
+                PolicyViolation::ForbiddenPhrase("as an ai".to_string()),
+                PolicyViolation::TooLong { words: 10, max: 5 },
+            ]
+        );
+        let correction = policy.correction("Which map?", &violations);
+        assert!(correction.starts_with("Which map?\n\n(Your previous reply used the forbidden phrase \"as an ai\" and was 10 words long"));
+    }
+}
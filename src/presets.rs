@@ -0,0 +1,210 @@
+//! Named generation presets
+//!
+//! A preset bundles the sampling parameters of a request (temperature,
+//! top_p, seed and a default reply length) under a name, so commands and
+//! templates say `precise` or `creative` instead of repeating numbers.
+//! Three are built in: `precise` (temperature 0.2, the default),
+//! `creative` (temperature 0.9, top_p 0.95) and `deterministic`
+//! (temperature 0 with a fixed seed, for providers that honor one).
+//!
+//! `[presets.<name>]` in the configuration adds presets or overrides the
+//! built-ins, and a trusted workspace's `picode.toml` overrides both by
+//! name:
+//!
+//! ```toml
+//! [presets.review]
+//! description = "Terse, repeatable reviews"
+//! temperature = 0.1
+//! max_tokens = 2048
+//! ```
+//!
+//! `[llm] preset` picks the default, `/preset <name>` switches it for the
+//! chat and a session template names one with `preset:`.
+
+use crate::config::{Config, ConfigError};
+use crate::error::{PiCodeError, Result};
+use crate::tasks::WORKSPACE_CONFIG_FILE;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Preset used when none is configured
+pub const DEFAULT_PRESET: &str = "precise";
+
+/// Names of the built-in presets
+pub const BUILTIN_PRESETS: &[&str] = &["precise", "creative", "deterministic"];
+
+/// Sampling parameters of a request; unset ones are left to the provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationPreset {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Reply length for requests that do not set their own
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+}
+
+/// The `precise` preset
+impl Default for GenerationPreset {
+    fn default() -> Self {
+        Self {
+            description: Some("Focused answers for code and facts".to_string()),
+            temperature: Some(0.2),
+            top_p: None,
+            seed: None,
+            max_tokens: None,
+        }
+    }
+}
+
+impl GenerationPreset {
+    /// A built-in preset by name
+    pub fn builtin(name: &str) -> Option<Self> {
+        let preset = match name {
+            "precise" => Self::default(),
+            "creative" => Self {
+                description: Some("Varied wording for brainstorming and prose".to_string()),
+                temperature: Some(0.9),
+                top_p: Some(0.95),
+                seed: None,
+                max_tokens: None,
+            },
+            "deterministic" => Self {
+                description: Some("Repeatable output where the provider honors a seed".to_string()),
+                temperature: Some(0.0),
+                top_p: None,
+                seed: Some(0),
+                max_tokens: None,
+            },
+            _ => return None,
+        };
+        Some(preset)
+    }
+
+    /// The parameters that are set, e.g. `temperature 0.2, seed 7`
+    pub fn summary(&self) -> String {
+        let parameters: Vec<String> = [
+            self.temperature.map(|value| format!("temperature {}", value)),
+            self.top_p.map(|value| format!("top_p {}", value)),
+            self.seed.map(|value| format!("seed {}", value)),
+            self.max_tokens.map(|value| format!("max_tokens {}", value)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if parameters.is_empty() {
+            "provider defaults".to_string()
+        } else {
+            parameters.join(", ")
+        }
+    }
+}
+
+/// Only the part of `picode.toml` this module reads
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceFile {
+    #[serde(default)]
+    presets: BTreeMap<String, GenerationPreset>,
+}
+
+/// Presets available in a workspace, with the default one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Presets {
+    presets: BTreeMap<String, GenerationPreset>,
+    default: String,
+}
+
+impl Presets {
+    /// The built-ins overlaid with the user configuration
+    pub fn from_config(config: &Config) -> Self {
+        let mut presets: BTreeMap<String, GenerationPreset> = BUILTIN_PRESETS
+            .iter()
+            .filter_map(|name| GenerationPreset::builtin(name).map(|preset| (name.to_string(), preset)))
+            .collect();
+        presets.extend(config.presets.clone());
+        Self {
+            presets,
+            default: config.llm.preset.clone().unwrap_or_else(|| DEFAULT_PRESET.to_string()),
+        }
+    }
+
+    /// Presets of [`Presets::from_config`] overlaid with the workspace's
+    /// `picode.toml`, which is ignored unless the workspace is trusted
+    pub async fn load(config: &Config, workspace_root: &Path) -> Result<Self> {
+        let mut presets = Self::from_config(config);
+        let path = workspace_root.join(WORKSPACE_CONFIG_FILE);
+        if tokio::fs::try_exists(&path).await? && crate::trust::is_trusted(workspace_root) {
+            let content = tokio::fs::read_to_string(&path).await?;
+            presets.presets.extend(Self::parse(&content)?);
+        }
+        Ok(presets)
+    }
+
+    /// Read the `[presets]` section of a `picode.toml` document
+    pub fn parse(content: &str) -> Result<BTreeMap<String, GenerationPreset>> {
+        let file: WorkspaceFile = ::config::Config::builder()
+            .add_source(::config::File::from_str(content, ::config::FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| ConfigError::InvalidConfig(format!("{}: {}", WORKSPACE_CONFIG_FILE, e)))?;
+        Ok(file.presets)
+    }
+
+    pub fn get(&self, name: &str) -> Result<&GenerationPreset> {
+        self.presets.get(name).ok_or_else(|| {
+            let available: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+            PiCodeError::NotFound(format!("preset '{}' (available: {})", name, available.join(", ")))
+        })
+    }
+
+    /// Name of the configured default preset
+    pub fn default_name(&self) -> &str {
+        &self.default
+    }
+
+    /// The default preset; `precise` when the configured one is unknown
+    pub fn default_preset(&self) -> GenerationPreset {
+        match self.get(&self.default) {
+            Ok(preset) => preset.clone(),
+            Err(e) => {
+                tracing::warn!("Using the {} preset: {}", DEFAULT_PRESET, e);
+                GenerationPreset::default()
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &GenerationPreset)> {
+        self.presets.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_and_workspace_presets_override_builtins() {
+        let mut config = Config::default();
+        config.presets.insert(
+            "creative".to_string(),
+            GenerationPreset { temperature: Some(1.2), ..GenerationPreset::builtin("creative").unwrap() },
+        );
+        config.llm.preset = Some("deterministic".to_string());
+        let mut presets = Presets::from_config(&config);
+        assert_eq!(presets.get("creative").unwrap().temperature, Some(1.2));
+        assert_eq!(presets.default_preset().seed, Some(0));
+        assert_eq!(presets.get("precise").unwrap().summary(), "temperature 0.2");
+        assert!(presets.get("wild").is_err());
+
+        let workspace = Presets::parse("[presets.review]\ntemperature = 0.1\nmax_tokens = 2048\n").unwrap();
+        presets.presets.extend(workspace);
+        assert_eq!(presets.get("review").unwrap().summary(), "temperature 0.1, max_tokens 2048");
+        assert_eq!(presets.iter().count(), 4);
+    }
+}
@@ -0,0 +1,317 @@
+//! Print mode and piped stdin
+//!
+//! `cat error.log | picode -p "why is this failing?"` answers a single prompt
+//! and prints the reply, which makes PiCode usable inside shell pipelines.
+//! When stdin is not a terminal its content is read, reduced with the
+//! command output summarizer, and either substituted for `{{stdin}}` in the
+//! prompt or attached after it as context.
+//!
+//! `--output-file out.md` writes the reply to a file instead; with
+//! `--stream` the chunks go straight to disk as they arrive, so generations
+//! of several megabytes are never held in memory, while stdout shows the
+//! progress.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_core::{CommandOutputSummarizer, OutputSummary};
+use std::collections::HashMap;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tracing::info;
+
+/// Placeholder replaced with the piped input
+pub const STDIN_PLACEHOLDER: &str = "stdin";
+
+const PRINT_SYSTEM_PROMPT: &str = "You are PiCode, a coding assistant used from the command line. \
+Answer concisely in plain text suitable for a terminal; the user may have piped command output \
+or files in as context.";
+
+/// Content piped into PiCode
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipedInput {
+    pub raw: String,
+    pub summary: OutputSummary,
+}
+
+impl PipedInput {
+    pub fn new(raw: String) -> Self {
+        let summary = CommandOutputSummarizer::new().summarize(&raw);
+        Self { raw, summary }
+    }
+
+    /// Read stdin when it is piped or redirected; `None` for a terminal or
+    /// empty input
+    pub async fn read() -> Result<Option<Self>> {
+        if std::io::stdin().is_terminal() {
+            return Ok(None);
+        }
+
+        let mut raw = String::new();
+        tokio::io::stdin().read_to_string(&mut raw).await?;
+        if raw.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(raw)))
+    }
+
+    /// Text handed to the model: the summary, or the input verbatim
+    pub fn context(&self, raw: bool) -> &str {
+        if raw {
+            &self.raw
+        } else {
+            &self.summary.text
+        }
+    }
+
+    /// Short description, e.g. for status lines
+    pub fn describe(&self) -> String {
+        if self.summary.truncated {
+            format!(
+                "{} lines piped in (summarized, {} error line(s) kept)",
+                self.summary.original_lines, self.summary.extracted_errors
+            )
+        } else {
+            format!("{} lines piped in", self.summary.original_lines)
+        }
+    }
+}
+
+/// Replace `{{name}}` placeholders (whitespace inside the braces allowed);
+/// unknown placeholders are left untouched
+pub fn render_template(template: &str, vars: &HashMap<&str, &str>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match vars.get(name) {
+                    Some(value) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn has_placeholder(template: &str, name: &str) -> bool {
+    template
+        .split("{{")
+        .skip(1)
+        .any(|part| part.split_once("}}").is_some_and(|(inner, _)| inner.trim() == name))
+}
+
+/// Build the user prompt from the template and optional piped input
+pub fn build_prompt(template: &str, stdin: Option<&PipedInput>, raw: bool) -> String {
+    let context = stdin.map_or("", |input| input.context(raw));
+    let mut vars = HashMap::new();
+    vars.insert(STDIN_PLACEHOLDER, context);
+    let prompt = render_template(template, &vars);
+
+    match stdin {
+        Some(_) if !has_placeholder(template, STDIN_PLACEHOLDER) => {
+            format!("{}\n\nPiped input:\n```\n{}\n```", prompt, context)
+        }
+        _ => prompt,
+    }
+}
+
+/// A reply being streamed to disk. Chunks are written to `<file>.part`,
+/// which replaces the file once the reply is complete, so an interrupted
+/// generation never leaves a truncated file in its place.
+pub struct StreamingFile {
+    path: PathBuf,
+    part: PathBuf,
+    writer: BufWriter<std::fs::File>,
+    bytes: u64,
+}
+
+impl StreamingFile {
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut part = path.as_os_str().to_owned();
+        part.push(".part");
+        let part = PathBuf::from(part);
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(std::fs::File::create(&part)?),
+            part,
+            bytes: 0,
+        })
+    }
+
+    pub fn write(&mut self, chunk: &str) -> Result<()> {
+        self.writer.write_all(chunk.as_bytes())?;
+        self.bytes += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Bytes written so far
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Where chunks are written until [`StreamingFile::finish`]
+    pub fn part_path(&self) -> &Path {
+        &self.part
+    }
+
+    /// Flush the reply and move it into place; returns its size
+    pub fn finish(self) -> Result<u64> {
+        let file = self.writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        std::fs::rename(&self.part, &self.path)?;
+        Ok(self.bytes)
+    }
+}
+
+/// Run `picode -p <prompt>`, printing the reply or writing it to
+/// `output_file`; with `stream` the reply goes to the file as it arrives
+pub async fn run(prompt: &str, raw_stdin: bool, output_file: Option<&Path>, stream: bool, config: Config) -> Result<()> {
+    let stdin = PipedInput::read().await?;
+    if let Some(input) = &stdin {
+        info!("Attaching stdin: {}", input.describe());
+    }
+    if prompt.trim().is_empty() && stdin.is_none() {
+        return Err(PiCodeError::InvalidCommand("empty prompt".to_string()));
+    }
+
+    let prompt = build_prompt(prompt, stdin.as_ref(), raw_stdin);
+    let assistant = Assistant::from_config(&config)?;
+    match output_file {
+        Some(path) if stream => return stream_to_file(&assistant, &prompt, path).await,
+        Some(path) => {
+            let reply = assistant.ask(PRINT_SYSTEM_PROMPT, &prompt, None).await?;
+            tokio::fs::write(path, &reply).await?;
+            println!("✅ Wrote {} bytes to {}", reply.len(), path.display());
+            return Ok(());
+        }
+        None => {}
+    }
+    let policy = config.ui.ansi_policy;
+
+    let mut stdout = std::io::stdout();
+    assistant
+        .ask_streaming(PRINT_SYSTEM_PROMPT, &prompt, None, |chunk| {
+            let _ = write!(stdout, "{}", picode_core::ansi::sanitize(chunk, policy));
+            let _ = stdout.flush();
+        })
+        .await?;
+    println!();
+    Ok(())
+}
+
+/// Stream the reply into `path`, showing progress at most every 200ms
+async fn stream_to_file(assistant: &Assistant, prompt: &str, path: &Path) -> Result<()> {
+    let mut file = StreamingFile::create(path)?;
+    let mut stdout = std::io::stdout();
+    let started = Instant::now();
+    let mut last_progress = started;
+    let result = assistant
+        .stream_unbuffered(PRINT_SYSTEM_PROMPT, prompt, None, |chunk| {
+            file.write(chunk)?;
+            if last_progress.elapsed() >= Duration::from_millis(200) {
+                last_progress = Instant::now();
+                let _ = write!(stdout, "\r⏳ {} to {}", format_bytes(file.bytes()), path.display());
+                let _ = stdout.flush();
+            }
+            Ok(())
+        })
+        .await;
+    let tokens = match result {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            println!();
+            return Err(PiCodeError::Llm(format!(
+                "generation stopped after {}; the partial reply is in {}: {}",
+                format_bytes(file.bytes()),
+                file.part_path().display(),
+                e
+            )));
+        }
+    };
+    let bytes = file.finish()?;
+    println!(
+        "\r✅ Wrote {} (~{} tokens) to {} in {:.1}s",
+        format_bytes(bytes),
+        tokens,
+        path.display(),
+        started.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_rendered() {
+        let mut vars = HashMap::new();
+        vars.insert("stdin", "boom");
+        assert_eq!(render_template("why {{ stdin }}? {{other}} {{", &vars), "why boom? {{other}} {{");
+    }
+
+    #[test]
+    fn piped_input_is_substituted_or_attached() {
+        let input = PipedInput::new("error: linker failed\n".to_string());
+
+        let inline = build_prompt("Explain: {{stdin}}", Some(&input), false);
+        assert_eq!(inline, "Explain: error: linker failed");
+
+        let attached = build_prompt("why is this failing?", Some(&input), false);
+        assert!(attached.starts_with("why is this failing?\n\nPiped input:\n```\nerror: linker failed"));
+
+        assert_eq!(build_prompt("hello", None, false), "hello");
+    }
+
+    #[test]
+    fn streamed_files_replace_the_target_when_complete() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("docs/out.md");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "old").unwrap();
+
+        let mut file = StreamingFile::create(&path).unwrap();
+        file.write("# Title\n").unwrap();
+        file.write("body\n").unwrap();
+        assert!(file.part_path().exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(file.finish().unwrap(), 13);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Title\nbody\n");
+        assert!(!dir.path().join("docs/out.md.part").exists());
+        assert_eq!(format_bytes(3 * 1_048_576), "3.0 MB");
+    }
+
+    #[test]
+    fn long_input_is_summarized() {
+        let log: String = (0..2000).map(|i| format!("line {}\n", i)).collect();
+        let input = PipedInput::new(log.clone());
+        assert!(input.summary.truncated);
+        assert!(input.describe().starts_with("2000 lines piped in (summarized"));
+        assert_eq!(input.context(true), log);
+    }
+}
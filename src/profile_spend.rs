@@ -0,0 +1,126 @@
+//! Spend limits of the active config profile
+//!
+//! A profile's `spend_limits` cap what requests cost while it is active:
+//! per request, per day and per month. Assistants built under the profile
+//! check each request against the profile's ledger in
+//! `~/.picode/profile-spend.json` before sending it, then record what it
+//! cost. Costs come from the provider's configured prices, so a provider
+//! without prices cannot be held to a limit; that is logged once per
+//! assistant rather than refused.
+
+use crate::config::{Config, SpendLimits};
+use crate::error::Result;
+use crate::users::SpendLedger;
+use chrono::Utc;
+use picode_llm::TokenUsage;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File holding the profiles' ledgers inside `~/.picode`
+pub const PROFILE_SPEND_FILE: &str = "profile-spend.json";
+
+/// Default location of the profiles' ledgers
+pub fn default_ledger_file() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(crate::defaults::CONFIG_DIR)
+        .join(PROFILE_SPEND_FILE)
+}
+
+/// The spend limits a profile puts on one provider's requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileSpend {
+    profile: String,
+    limits: SpendLimits,
+    /// USD per million prompt and completion tokens
+    prices: Option<(f64, f64)>,
+    ledger_file: PathBuf,
+}
+
+impl ProfileSpend {
+    /// The limits of the active profile for `provider`, or `None` without an
+    /// active profile or when it sets no limit
+    pub fn from_config(config: &Config, provider: &str) -> Option<Self> {
+        let profile = config.active_profile.clone()?;
+        let limits = config.current_profile()?.spend_limits.clone();
+        if limits == SpendLimits::default() {
+            return None;
+        }
+        let spend = Self::new(profile, limits, crate::agent::prices(config, provider), default_ledger_file());
+        if spend.prices.is_none() {
+            tracing::warn!(
+                "Profile '{}' has spend limits, but provider '{}' has no prices; its requests are not counted",
+                spend.profile,
+                provider
+            );
+        }
+        Some(spend)
+    }
+
+    pub fn new(profile: impl Into<String>, limits: SpendLimits, prices: Option<(f64, f64)>, ledger_file: PathBuf) -> Self {
+        Self { profile: profile.into(), limits, prices, ledger_file }
+    }
+
+    /// What a request of `prompt_tokens` asking for up to `max_tokens`
+    /// would cost at most
+    pub fn estimate(&self, prompt_tokens: usize, max_tokens: Option<u32>) -> f64 {
+        self.cost(prompt_tokens as u64, max_tokens.unwrap_or(0) as u64)
+    }
+
+    fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        self.prices.map_or(0.0, |(prompt, completion)| {
+            (prompt_tokens as f64 * prompt + completion_tokens as f64 * completion) / 1_000_000.0
+        })
+    }
+
+    /// Fail if a request of the estimated cost would go past a limit
+    pub async fn check(&self, estimate: f64) -> Result<()> {
+        let mut ledgers = load(&self.ledger_file).await?;
+        ledgers.entry(self.profile.clone()).or_default().check(estimate, &self.limits, Utc::now())
+    }
+
+    /// Add the cost of a completed request to the profile's ledger
+    pub async fn record(&self, usage: &TokenUsage) -> Result<()> {
+        let cost = self.cost(usage.prompt_tokens as u64, usage.completion_tokens as u64);
+        if cost == 0.0 {
+            return Ok(());
+        }
+        let mut ledgers = load(&self.ledger_file).await?;
+        ledgers.entry(self.profile.clone()).or_default().record(cost, Utc::now());
+        if let Some(parent) = self.ledger_file.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&self.ledger_file, serde_json::to_string_pretty(&ledgers)?).await?;
+        Ok(())
+    }
+}
+
+async fn load(path: &Path) -> Result<BTreeMap<String, SpendLedger>> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_are_refused_past_the_profile_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = SpendLimits { per_request: Some(0.5), daily: Some(1.0), monthly: None };
+        let spend = ProfileSpend::new("work", limits, Some((1_000.0, 2_000.0)), dir.path().join(PROFILE_SPEND_FILE));
+
+        assert!((spend.estimate(100, Some(100)) - 0.3).abs() < 1e-9);
+        assert!(spend.check(spend.estimate(400, None)).await.is_ok());
+        assert!(spend.check(spend.estimate(600, None)).await.is_err());
+
+        let usage = TokenUsage { prompt_tokens: 400, completion_tokens: 200, total_tokens: 600 };
+        spend.record(&usage).await.unwrap();
+        assert!(spend.check(0.1).await.is_ok());
+        let err = spend.check(0.3).await.unwrap_err();
+        assert!(err.to_string().contains("daily spend limit of $1.00 reached"));
+    }
+}
@@ -0,0 +1,216 @@
+//! Read-only HTML view of a session
+//!
+//! `picode session publish <name>` renders a stored conversation, including
+//! the diffs applied to its context, as one self-contained HTML file: styles
+//! are inline and nothing is loaded from elsewhere, so the page can be
+//! attached to a design doc or review as is. Secrets are always redacted;
+//! with `--strip-contents` file contents are left out and only diffs and
+//! the conversation remain.
+
+use picode_core::context_delta::CONTEXT_UPDATE_TAG;
+use picode_core::{ConversationLog, Redactor, Session};
+use std::fmt::Write;
+
+const STYLE: &str = "\
+body{font-family:-apple-system,'Segoe UI',sans-serif;max-width:860px;margin:2rem auto;padding:0 1rem;color:#1f2328;line-height:1.5}\
+header{border-bottom:1px solid #d0d7de;margin-bottom:1.5rem}\
+.meta{color:#59636e;font-size:.9rem}\
+.tag{background:#ddf4ff;border-radius:1em;padding:0 .5em;margin-right:.3em}\
+.message{border:1px solid #d0d7de;border-radius:6px;margin:1rem 0;padding:.5rem 1rem}\
+.message.user{background:#f6f8fa}\
+.role{font-weight:600;text-transform:capitalize}\
+.text{white-space:pre-wrap;word-wrap:break-word}\
+pre{background:#f6f8fa;border-radius:6px;padding:.75rem;overflow-x:auto;font-size:.85rem}\
+.diff .add{background:#dafbe1}.diff .del{background:#ffebe9}.diff .hunk{color:#8250df}\
+.omitted{color:#59636e;font-style:italic}\
+details.context{margin:1rem 0;color:#59636e}\
+blockquote.note{border-left:3px solid #d4a72c;margin:.5rem 0;padding-left:.75rem}";
+
+/// How a session is published
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PublishOptions {
+    /// Leave out file contents, keeping diffs and the conversation
+    pub strip_contents: bool,
+}
+
+/// The session's conversation as a standalone HTML page
+pub fn render_html(session: &Session, log: &ConversationLog, options: PublishOptions) -> String {
+    let redactor = Redactor::new().with_secrets();
+    let redact = |text: &str| redactor.redact(text).0;
+
+    let mut out = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(out, "<title>{}</title>", escape(&session.name));
+    let _ = writeln!(out, "<style>{}</style>\n</head>\n<body>\n<header>", STYLE);
+    let _ = writeln!(out, "<h1>{}</h1>", escape(&session.name));
+    if !log.derived.title.is_empty() {
+        let _ = writeln!(out, "<p><em>{}</em></p>", escape(&redact(&log.derived.title)));
+    }
+    let _ = write!(
+        out,
+        "<p class=\"meta\">{} · {} · {}",
+        escape(&session.llm_provider),
+        escape(&session.model),
+        session.created_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    if options.strip_contents {
+        out.push_str(" · file contents omitted");
+    }
+    out.push_str("</p>\n");
+    if !log.annotations.tags.is_empty() {
+        out.push_str("<p>");
+        for tag in &log.annotations.tags {
+            let _ = write!(out, "<span class=\"tag\">#{}</span>", escape(tag));
+        }
+        out.push_str("</p>\n");
+    }
+    out.push_str("</header>\n<main>\n");
+
+    for (index, message) in log.messages.iter().enumerate() {
+        if message.is_annotation() {
+            continue;
+        }
+        let content = redact(&message.content);
+        if message.is_context_update() {
+            render_context_update(&mut out, &content, options);
+        } else {
+            let _ = writeln!(
+                out,
+                "<section class=\"message {}\">\n<div class=\"role\">{}</div>",
+                escape(&message.role),
+                escape(&message.role)
+            );
+            render_blocks(&mut out, &content, options);
+            for note in log.annotations.notes_for(index) {
+                let _ = writeln!(out, "<blockquote class=\"note\">📝 {}</blockquote>", escape(&redact(&note.text)));
+            }
+            out.push_str("</section>\n");
+        }
+    }
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+/// A `<context-update>` message as a collapsed block
+fn render_context_update(out: &mut String, content: &str, options: PublishOptions) {
+    let turn = content
+        .split_once("turn=\"")
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(turn, _)| turn)
+        .unwrap_or("?");
+    let body = content
+        .split_once('\n')
+        .map_or("", |(_, body)| body)
+        .trim_end()
+        .trim_end_matches(&format!("</{}>", CONTEXT_UPDATE_TAG));
+    let _ = writeln!(
+        out,
+        "<details class=\"context\">\n<summary>Context update (turn {})</summary>",
+        escape(turn)
+    );
+    render_blocks(out, body, options);
+    out.push_str("</details>\n");
+}
+
+/// Text with fenced code blocks; ```diff blocks get per-line highlighting
+fn render_blocks(out: &mut String, content: &str, options: PublishOptions) {
+    let mut text = String::new();
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let Some(lang) = line.trim_start().strip_prefix("```") else {
+            text.push_str(line);
+            text.push('\n');
+            continue;
+        };
+        flush_text(out, &mut text);
+        let code: Vec<&str> = lines.by_ref().take_while(|line| line.trim_start() != "```").collect();
+        if lang.trim() == "diff" {
+            out.push_str("<pre class=\"diff\"><code>");
+            for line in &code {
+                let class = match line.chars().next() {
+                    Some('+') => " class=\"add\"",
+                    Some('-') => " class=\"del\"",
+                    Some('@') => " class=\"hunk\"",
+                    _ => "",
+                };
+                let _ = writeln!(out, "<span{}>{}</span>", class, escape(line));
+            }
+            out.push_str("</code></pre>\n");
+        } else if options.strip_contents {
+            let _ = writeln!(out, "<p class=\"omitted\">[{} lines of file content omitted]</p>", code.len());
+        } else {
+            let _ = writeln!(out, "<pre><code>{}</code></pre>", escape(&code.join("\n")));
+        }
+    }
+    flush_text(out, &mut text);
+}
+
+fn flush_text(out: &mut String, text: &mut String) {
+    let trimmed = text.trim();
+    if !trimmed.is_empty() {
+        let _ = writeln!(out, "<div class=\"text\">{}</div>", escape(trimmed));
+    }
+    text.clear();
+}
+
+fn escape(text: &str) -> String {
+    text.chars().fold(String::with_capacity(text.len()), |mut out, c| {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picode_core::context_delta::{ContextUpdate, FileDelta};
+    use picode_core::{ConversationMessage, SessionId};
+    use std::path::PathBuf;
+
+    fn log() -> ConversationLog {
+        let update = ContextUpdate {
+            turn: 2,
+            deltas: vec![
+                FileDelta::Added { path: PathBuf::from("src/auth.rs"), content: "fn login() {}".to_string() },
+                FileDelta::Changed { path: PathBuf::from("src/lib.rs"), diff: "@@ -1 +1 @@\n-mod old;\n+mod auth;".to_string() },
+            ],
+            unchanged: Vec::new(),
+        };
+        let mut log = ConversationLog::new(SessionId::new());
+        log.push(ConversationMessage::new("user", "Why does <login> fail? key sk-ant-REDACTED"));
+        log.push(ConversationMessage::new("user", update.render()));
+        log.push(ConversationMessage::new("assistant", "Change it to:\n```rust\nfn login() -> bool { true }\n```"));
+        log
+    }
+
+    #[test]
+    fn renders_escaped_redacted_page_with_diffs() {
+        let session = Session::new("auth".to_string(), PathBuf::from("/repo"));
+        let html = render_html(&session, &log(), PublishOptions::default());
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("<script") && !html.contains("<link"));
+        assert!(html.contains("Why does &lt;login&gt; fail?"));
+        assert!(!html.contains("sk-ant-REDACTED"));
+        assert!(html.contains("<summary>Context update (turn 2)</summary>"));
+        assert!(html.contains("<span class=\"del\">-mod old;</span>\n<span class=\"add\">+mod auth;</span>"));
+        assert!(html.contains("<pre><code>fn login() -&gt; bool { true }</code></pre>"));
+    }
+
+    #[test]
+    fn strip_contents_keeps_diffs_only() {
+        let session = Session::new("auth".to_string(), PathBuf::from("/repo"));
+        let html = render_html(&session, &log(), PublishOptions { strip_contents: true });
+
+        assert!(!html.contains("fn login()"));
+        assert!(html.contains("[1 lines of file content omitted]"));
+        assert!(html.contains("+mod auth;"));
+        assert!(html.contains("file contents omitted"));
+    }
+}
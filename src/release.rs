@@ -0,0 +1,389 @@
+//! `picode release notes`
+//!
+//! The commits of a range (`--since v1.2.0`, up to `HEAD`) are read along
+//! the first parent, so a merged pull request shows up once, under its
+//! title. Conventional commit subjects (`feat(ui)!: ...`) are grouped by
+//! their type; the model classifies the others, which are listed under
+//! "Other Changes" without one. Pull request numbers of merge and squash
+//! subjects (`Merge pull request #12`, `Fix crash (#12)`) are kept with
+//! each entry.
+//!
+//! From the groups a CHANGELOG section is rendered and the model drafts a
+//! release announcement. Neither is written directly: both are staged in
+//! the review queue, for `picode review export` and `picode review apply`.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::Result;
+use crate::git::git;
+use chrono::NaiveDate;
+use picode_core::editor::review::read_text;
+use picode_core::editor::ReviewQueue;
+use picode_core::{CoreError, NativeFileSystem};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Separators of the `git log` format
+const FIELD: char = '\u{1f}';
+const RECORD: char = '\u{1e}';
+
+const CLASSIFY_SYSTEM: &str = "You sort commits for a changelog. For every numbered commit reply \
+    with one line `<number>: <type>`, where type is feat (new behaviour users see), fix (a bug \
+    fix), perf (faster or leaner), docs (documentation only), refactor (restructuring without \
+    behaviour change) or chore (tests, build, CI and housekeeping).";
+
+const ANNOUNCE_SYSTEM: &str = "You write release announcements for a software project. From the \
+    grouped changes, write a short markdown announcement for its users: a paragraph on what the \
+    release brings, the highlights with a sentence each, and upgrade notes when there are \
+    breaking changes. Mention nothing that is not in the list.";
+
+/// Changelog section of a change, in the order sections are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChangeKind {
+    Feature,
+    Fix,
+    Performance,
+    Docs,
+    Refactor,
+    Chore,
+    Other,
+}
+
+impl ChangeKind {
+    /// Kind of a conventional commit type; `None` for types it does not know
+    pub fn from_type(kind: &str) -> Option<Self> {
+        match kind.to_ascii_lowercase().as_str() {
+            "feat" | "feature" => Some(Self::Feature),
+            "fix" | "bugfix" => Some(Self::Fix),
+            "perf" => Some(Self::Performance),
+            "docs" | "doc" => Some(Self::Docs),
+            "refactor" => Some(Self::Refactor),
+            "chore" | "test" | "tests" | "build" | "ci" | "style" | "revert" => Some(Self::Chore),
+            _ => None,
+        }
+    }
+
+    pub fn heading(self) -> &'static str {
+        match self {
+            Self::Feature => "Features",
+            Self::Fix => "Bug Fixes",
+            Self::Performance => "Performance",
+            Self::Docs => "Documentation",
+            Self::Refactor => "Refactoring",
+            Self::Chore => "Maintenance",
+            Self::Other => "Other Changes",
+        }
+    }
+}
+
+/// A commit or merged pull request of the range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Abbreviated hash
+    pub commit: String,
+    pub author: String,
+    /// The subject without its conventional prefix and pull request number
+    pub summary: String,
+    pub scope: Option<String>,
+    /// `None` until classified when the subject is not conventional
+    pub kind: Option<ChangeKind>,
+    pub breaking: bool,
+    pub pull_request: Option<u64>,
+}
+
+fn merge_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^Merge pull request #(\d+) from \S+").unwrap())
+}
+
+fn squash_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\s*\(#(\d+)\)$").unwrap())
+}
+
+fn conventional_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^([A-Za-z]+)(?:\(([^)]*)\))?(!)?:\s*(.+)$").unwrap())
+}
+
+impl Change {
+    /// The change of one commit; `None` for merges of branches other than
+    /// pull requests, whose commits are listed on their own
+    pub fn parse(commit: &str, author: &str, subject: &str, body: &str) -> Option<Self> {
+        let subject = subject.trim();
+        let (mut summary, mut pull_request) = match merge_pattern().captures(subject) {
+            // The pull request's title is the first line of the merge body
+            Some(captures) => {
+                let title = body.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or(subject);
+                (title.to_string(), captures[1].parse().ok())
+            }
+            None if subject.starts_with("Merge ") => return None,
+            None => (subject.to_string(), None),
+        };
+        if let Some(captures) = squash_pattern().captures(&summary) {
+            pull_request = pull_request.or(captures[1].parse().ok());
+            summary.truncate(captures.get(0).map_or(summary.len(), |m| m.start()));
+        }
+
+        let mut change = Change {
+            commit: commit.trim().to_string(),
+            author: author.trim().to_string(),
+            summary: summary.clone(),
+            scope: None,
+            kind: None,
+            breaking: body.contains("BREAKING CHANGE:") || body.contains("BREAKING-CHANGE:"),
+            pull_request,
+        };
+        if let Some(captures) = conventional_pattern().captures(&summary) {
+            if let Some(kind) = ChangeKind::from_type(&captures[1]) {
+                change.kind = Some(kind);
+                change.scope = captures.get(2).map(|scope| scope.as_str().trim().to_string()).filter(|scope| !scope.is_empty());
+                change.breaking |= captures.get(3).is_some();
+                change.summary = captures[4].trim().to_string();
+            }
+        }
+        Some(change)
+    }
+
+    /// The changelog line, without its bullet
+    fn entry(&self) -> String {
+        let scope = self.scope.as_deref().map(|scope| format!("**{}:** ", scope)).unwrap_or_default();
+        let reference = match self.pull_request {
+            Some(number) => format!("#{}", number),
+            None => self.commit.clone(),
+        };
+        format!("{}{} ({})", scope, self.summary, reference)
+    }
+}
+
+/// Changes of `git log --format=%h%x1f%an%x1f%s%x1f%b%x1e`, newest first
+pub fn parse_log(log: &str) -> Vec<Change> {
+    log.split(RECORD)
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(4, FIELD);
+            let (commit, author, subject) = (fields.next()?, fields.next()?, fields.next()?);
+            Change::parse(commit, author, subject, fields.next().unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Numbered commits for the model to classify
+fn classify_prompt(changes: &[Change], indexes: &[usize]) -> String {
+    let mut prompt = String::new();
+    for &index in indexes {
+        let _ = writeln!(prompt, "{}. {}", index + 1, changes[index].summary);
+    }
+    prompt
+}
+
+/// The model's `<number>: <type>` lines
+fn parse_kinds(reply: &str) -> BTreeMap<usize, ChangeKind> {
+    reply
+        .lines()
+        .filter_map(|line| {
+            let (number, kind) = line.trim().trim_start_matches(['-', '*', ' ']).split_once([':', '.'])?;
+            let number: usize = number.trim().trim_start_matches('#').parse().ok()?;
+            let kind = ChangeKind::from_type(kind.trim().trim_matches('`'))?;
+            Some((number.checked_sub(1)?, kind))
+        })
+        .collect()
+}
+
+/// Classify the changes without a conventional type, with the model when
+/// there is one; what stays unclassified is `Other`
+async fn classify(changes: &mut [Change], assistant: Option<&Assistant>) {
+    let unclassified: Vec<usize> = (0..changes.len()).filter(|&index| changes[index].kind.is_none()).collect();
+    if let Some(assistant) = assistant.filter(|_| !unclassified.is_empty()) {
+        match assistant.ask(CLASSIFY_SYSTEM, &classify_prompt(changes, &unclassified), Some(800)).await {
+            Ok(reply) => {
+                for (index, kind) in parse_kinds(&reply) {
+                    if let Some(change) = changes.get_mut(index).filter(|change| change.kind.is_none()) {
+                        change.kind = Some(kind);
+                    }
+                }
+            }
+            Err(e) => warn!("Classifying commits with the model failed: {}", e),
+        }
+    }
+    for change in changes.iter_mut().filter(|change| change.kind.is_none()) {
+        change.kind = Some(ChangeKind::Other);
+    }
+}
+
+/// The changes of a release, grouped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseNotes {
+    /// `None` for an unreleased section
+    pub version: Option<String>,
+    pub date: NaiveDate,
+    pub changes: Vec<Change>,
+}
+
+impl ReleaseNotes {
+    fn groups(&self) -> BTreeMap<ChangeKind, Vec<&Change>> {
+        let mut groups: BTreeMap<ChangeKind, Vec<&Change>> = BTreeMap::new();
+        for change in &self.changes {
+            groups.entry(change.kind.unwrap_or(ChangeKind::Other)).or_default().push(change);
+        }
+        groups
+    }
+
+    /// The CHANGELOG section, in the Keep a Changelog layout
+    pub fn changelog_section(&self) -> String {
+        let mut out = match &self.version {
+            Some(version) => format!("## [{}] - {}\n", version.trim_start_matches('v'), self.date),
+            None => "## [Unreleased]\n".to_string(),
+        };
+        let breaking: Vec<&Change> = self.changes.iter().filter(|change| change.breaking).collect();
+        if !breaking.is_empty() {
+            out.push_str("\n### Breaking Changes\n\n");
+            for change in breaking {
+                let _ = writeln!(out, "- {}", change.entry());
+            }
+        }
+        for (kind, changes) in self.groups() {
+            let _ = write!(out, "\n### {}\n\n", kind.heading());
+            for change in changes {
+                let _ = writeln!(out, "- {}", change.entry());
+            }
+        }
+        out
+    }
+
+    /// `changelog` with this release's section above the previous ones
+    pub fn insert_into(&self, changelog: Option<&str>) -> String {
+        let section = self.changelog_section();
+        let Some(changelog) = changelog.filter(|changelog| !changelog.trim().is_empty()) else {
+            return format!("# Changelog\n\n{}", section);
+        };
+        match changelog.find("\n## ") {
+            Some(at) => format!("{}\n{}\n{}", &changelog[..at], section, &changelog[at + 1..]),
+            None => format!("{}\n\n{}", changelog.trim_end(), section),
+        }
+    }
+
+    /// The request for the announcement
+    fn announcement_prompt(&self) -> String {
+        let mut prompt = match &self.version {
+            Some(version) => format!("Release {}\n", version),
+            None => "Upcoming release\n".to_string(),
+        };
+        let contributors: std::collections::BTreeSet<&str> = self.changes.iter().map(|change| change.author.as_str()).collect();
+        let _ = writeln!(prompt, "Contributors: {}", contributors.into_iter().collect::<Vec<_>>().join(", "));
+        let _ = write!(prompt, "\n{}", self.changelog_section());
+        prompt
+    }
+}
+
+/// Options of `picode release notes`
+#[derive(Debug, Clone)]
+pub struct NotesOptions {
+    pub since: String,
+    pub until: String,
+    pub version: Option<String>,
+    pub changelog: PathBuf,
+    pub announcement: PathBuf,
+}
+
+/// Run `picode release notes`
+pub async fn notes(config: &Config, options: NotesOptions) -> Result<()> {
+    let root = crate::stats::workspace_root(config)?;
+    let range = format!("{}..{}", options.since, options.until);
+    let log = git(&root, &["log", "--first-parent", "--format=%h%x1f%an%x1f%s%x1f%b%x1e", &range]).await?;
+    let mut changes = parse_log(&log);
+    if changes.is_empty() {
+        println!("✅ No changes in {}", range);
+        return Ok(());
+    }
+
+    let assistant = match Assistant::from_config(config) {
+        Ok(assistant) => Some(assistant),
+        Err(e) => {
+            warn!("No model for classifying commits and drafting the announcement: {}", e);
+            None
+        }
+    };
+    classify(&mut changes, assistant.as_ref()).await;
+    let notes = ReleaseNotes { version: options.version, date: chrono::Local::now().date_naive(), changes };
+    println!("📝 {} change(s) in {}\n", notes.changes.len(), range);
+    print!("{}", notes.changelog_section());
+
+    let fs = NativeFileSystem;
+    let mut queue = ReviewQueue::load(&fs, &root).await.map_err(CoreError::from)?;
+    let base = read_text(&fs, &root.join(&options.changelog)).await.map_err(CoreError::from)?;
+    queue.stage(&options.changelog, base.clone(), notes.insert_into(base.as_deref()));
+    let mut staged = vec![options.changelog.display().to_string()];
+    if let Some(assistant) = &assistant {
+        match assistant.ask(ANNOUNCE_SYSTEM, &notes.announcement_prompt(), Some(1500)).await {
+            Ok(announcement) => {
+                let base = read_text(&fs, &root.join(&options.announcement)).await.map_err(CoreError::from)?;
+                queue.stage(&options.announcement, base, format!("{}\n", announcement.trim()));
+                staged.push(options.announcement.display().to_string());
+            }
+            Err(e) => warn!("Drafting the announcement failed: {}", e),
+        }
+    }
+    queue.save(&fs, &root).await.map_err(CoreError::from)?;
+    println!(
+        "\n🔍 Staged {} for review: picode review export --patch release.patch, then picode review apply",
+        staged.join(" and ")
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(commit: &str, subject: &str, body: &str) -> String {
+        format!("{}{FIELD}Ada{FIELD}{}{FIELD}{}{RECORD}\n", commit, subject, body)
+    }
+
+    #[test]
+    fn groups_conventional_commits_and_pull_requests() {
+        let log = [
+            record("a1b2c3d", "Merge pull request #42 from ada/themes", "feat(ui): add dark theme\n"),
+            record("b2c3d4e", "fix!: reject empty session names (#40)", "BREAKING CHANGE: names are required\n"),
+            record("c3d4e5f", "Merge branch 'main' into themes", ""),
+            record("d4e5f6a", "Speed up workspace scans", ""),
+            record("e5f6a7b", "docs: describe [audit] settings", ""),
+        ]
+        .concat();
+        let mut changes = parse_log(&log);
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[0].summary, "add dark theme");
+        assert_eq!((changes[0].scope.as_deref(), changes[0].pull_request), (Some("ui"), Some(42)));
+        assert!(changes[1].breaking && changes[1].kind == Some(ChangeKind::Fix));
+        assert_eq!(changes[2].kind, None);
+
+        assert_eq!(classify_prompt(&changes, &[2]), "3. Speed up workspace scans\n");
+        let kinds = parse_kinds("3: perf\n4: nonsense\n- 1. fix");
+        assert_eq!(kinds, BTreeMap::from([(0, ChangeKind::Fix), (2, ChangeKind::Performance)]));
+        changes[2].kind = kinds.get(&2).copied();
+
+        let notes = ReleaseNotes {
+            version: Some("v1.3.0".to_string()),
+            date: NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+            changes,
+        };
+        assert_eq!(
+            notes.changelog_section(),
+            "## [1.3.0] - 2026-10-16\n\n\
+             ### Breaking Changes\n\n- reject empty session names (#40)\n\n\
+             ### Features\n\n- **ui:** add dark theme (#42)\n\n\
+             ### Bug Fixes\n\n- reject empty session names (#40)\n\n\
+             ### Performance\n\n- Speed up workspace scans (d4e5f6a)\n\n\
+             ### Documentation\n\n- describe [audit] settings (e5f6a7b)\n"
+        );
+
+        let changelog = "# Changelog\n\nAll notable changes.\n\n## [1.2.0] - 2026-09-01\n\n- older\n";
+        let updated = notes.insert_into(Some(changelog));
+        assert!(updated.starts_with("# Changelog\n\nAll notable changes.\n\n## [1.3.0] - 2026-10-16\n"));
+        assert!(updated.ends_with("(e5f6a7b)\n\n## [1.2.0] - 2026-09-01\n\n- older\n"));
+        assert!(notes.insert_into(None).starts_with("# Changelog\n\n## [1.3.0]"));
+    }
+}
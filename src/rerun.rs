@@ -0,0 +1,274 @@
+//! `picode session rerun` - replaying a conversation against another model
+//!
+//! Prompts sent in chat record the model, system prompt and file context
+//! they were sent with (see [`picode_core::TurnContext`]). A rerun sends the
+//! user turns of a stored conversation again, each with exactly that
+//! system prompt and context, to another provider or model, and reports how
+//! the new replies compare with the recorded ones: a way to check a
+//! provider migration or a model upgrade on real conversations. Chat turns
+//! are independent requests, so a new reply does not change what the next
+//! turn is sent. Requests use temperature 0 and a fixed seed, so a rerun
+//! can itself be repeated where the provider honours them.
+//!
+//! Turns recorded before contexts were kept are sent with the context
+//! update that preceded them only, and are marked as such in the report.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::Result;
+use crate::presets::Presets;
+use picode_core::{ConversationLog, CoreError, WordDiff};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Seed of every rerun request
+pub const RERUN_SEED: u64 = 42;
+
+/// Characters of a prompt shown in the summary table
+const PROMPT_PREVIEW: usize = 48;
+
+/// A recorded user turn, ready to be sent again
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayTurn {
+    /// Position among the conversation's user turns, from 1
+    pub number: usize,
+    pub prompt: String,
+    pub system: String,
+    /// The full request text: file context, then the prompt
+    pub request: String,
+    /// `provider/model` the turn was sent to, when recorded
+    pub recorded_with: Option<String>,
+    /// The reply recorded for the turn
+    pub recorded: Option<String>,
+    /// Whether the system prompt and context were recorded
+    pub exact: bool,
+}
+
+/// The user turns of `log`, in order
+pub fn turns(log: &ConversationLog) -> Vec<ReplayTurn> {
+    let mut turns = Vec::new();
+    for (index, message) in log.messages.iter().enumerate() {
+        if message.role != "user" || message.is_annotation() || message.is_context_update() {
+            continue;
+        }
+        let (system, context, exact) = match log.sent_context(message) {
+            Some((system, context)) => (system.to_string(), context.to_string(), true),
+            None => {
+                let update = index
+                    .checked_sub(1)
+                    .map(|previous| &log.messages[previous])
+                    .filter(|previous| previous.is_context_update())
+                    .map(|previous| format!("{}\n", previous.content))
+                    .unwrap_or_default();
+                (String::new(), update, false)
+            }
+        };
+        let recorded = log.messages[index + 1..]
+            .iter()
+            .take_while(|m| m.role != "user" || m.is_context_update())
+            .find(|m| m.role == "assistant")
+            .map(|m| m.content.clone());
+        turns.push(ReplayTurn {
+            number: turns.len() + 1,
+            prompt: message.content.clone(),
+            system,
+            request: format!("{}{}", context, message.content),
+            recorded_with: message.sent_with.as_ref().map(|sent| format!("{}/{}", sent.provider, sent.model)),
+            recorded,
+            exact,
+        });
+    }
+    turns
+}
+
+/// The new reply to one turn
+#[derive(Debug, Clone, PartialEq)]
+pub struct RerunResult {
+    pub turn: ReplayTurn,
+    /// The reply, or why the request failed
+    pub reply: std::result::Result<String, String>,
+    pub completion_tokens: u32,
+    pub elapsed: Duration,
+}
+
+impl RerunResult {
+    /// Share of words that differ from the recorded reply
+    pub fn change_ratio(&self) -> Option<f64> {
+        match (&self.turn.recorded, &self.reply) {
+            (Some(recorded), Ok(reply)) => Some(WordDiff::new(recorded, reply).change_ratio()),
+            _ => None,
+        }
+    }
+}
+
+/// Markdown comparison of the recorded replies of session `name` and those
+/// of `target` (`provider/model`)
+pub fn render_report(name: &str, target: &str, results: &[RerunResult]) -> String {
+    let mut out = format!("# Rerun of {}\n\n", name);
+    let mut recorded_with: Vec<&str> = results.iter().filter_map(|r| r.turn.recorded_with.as_deref()).collect();
+    recorded_with.sort_unstable();
+    recorded_with.dedup();
+    let _ = writeln!(
+        out,
+        "Recorded with {} · rerun with {} · {} turn(s)\n",
+        if recorded_with.is_empty() { "unknown models".to_string() } else { recorded_with.join(", ") },
+        target,
+        results.len()
+    );
+
+    let ratios: Vec<f64> = results.iter().filter_map(RerunResult::change_ratio).collect();
+    let identical = ratios.iter().filter(|ratio| **ratio == 0.0).count();
+    let failed = results.iter().filter(|r| r.reply.is_err()).count();
+    let inexact = results.iter().filter(|r| !r.turn.exact).count();
+    if !ratios.is_empty() {
+        let mean = ratios.iter().sum::<f64>() / ratios.len() as f64;
+        let _ = writeln!(out, "Words changed: {:.0}% on average; identical replies: {}", mean * 100.0, identical);
+    }
+    if failed > 0 {
+        let _ = writeln!(out, "Failed: {} turn(s)", failed);
+    }
+    if inexact > 0 {
+        let _ = writeln!(out, "Sent without their recorded context (*): {} turn(s), recorded before contexts were kept", inexact);
+    }
+
+    out.push_str("\n| Turn | Prompt | Recorded | Rerun | Words changed | Time |\n|---:|---|---:|---:|---:|---:|\n");
+    for result in results {
+        let turn = &result.turn;
+        let prompt = turn.prompt.lines().next().unwrap_or_default();
+        let preview: String = prompt.chars().take(PROMPT_PREVIEW).collect();
+        let preview = if preview.len() < prompt.len() { format!("{}…", preview) } else { preview };
+        let recorded = turn
+            .recorded
+            .as_deref()
+            .map(|reply| format!("~{} tok", picode_core::system_prompt::estimate_tokens(reply)))
+            .unwrap_or_else(|| "-".to_string());
+        let rerun = match &result.reply {
+            Ok(_) => format!("{} tok", result.completion_tokens),
+            Err(_) => "failed".to_string(),
+        };
+        let changed = result.change_ratio().map(|ratio| format!("{:.0}%", ratio * 100.0)).unwrap_or_else(|| "-".to_string());
+        let _ = writeln!(
+            out,
+            "| {}{} | {} | {} | {} | {} | {:.1}s |",
+            turn.number,
+            if turn.exact { "" } else { "*" },
+            preview.replace('|', "\\|"),
+            recorded,
+            rerun,
+            changed,
+            result.elapsed.as_secs_f64()
+        );
+    }
+
+    for result in results {
+        let turn = &result.turn;
+        let _ = writeln!(out, "\n## Turn {}\n\n{}\n", turn.number, turn.prompt.trim_end());
+        let _ = writeln!(
+            out,
+            "### Recorded ({})\n\n{}\n",
+            turn.recorded_with.as_deref().unwrap_or("unknown model"),
+            turn.recorded.as_deref().unwrap_or("_No reply was recorded._").trim_end()
+        );
+        match &result.reply {
+            Ok(reply) => {
+                let _ = writeln!(out, "### Rerun ({})\n\n{}", target, reply.trim_end());
+                if let Some(recorded) = &turn.recorded {
+                    let diff = WordDiff::new(recorded, reply);
+                    if !diff.is_unchanged() {
+                        let _ = writeln!(out, "\n### Changes\n\n{}", diff.render_markdown());
+                    }
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(out, "### Rerun ({})\n\n_Failed: {}_", target, e);
+            }
+        }
+    }
+    out
+}
+
+/// Send the user turns of session `name` to `provider` (and `model`, or its
+/// default) and report how the replies compare
+pub async fn run(config: &Config, name: &str, provider: &str, model: Option<String>, output: Option<PathBuf>) -> Result<()> {
+    let manager = crate::history::load_manager(config).await?;
+    let session = manager.get_session_by_name(name).await.map_err(CoreError::from)?;
+    let log = manager.load_conversation(&session.id).await.map_err(CoreError::from)?;
+    let turns = turns(&log);
+    if turns.is_empty() {
+        return Err(crate::error::PiCodeError::NotFound(format!("user turns in session '{}'", name)));
+    }
+
+    let mut preset = Presets::from_config(config).default_preset();
+    preset.temperature = Some(0.0);
+    preset.seed = Some(RERUN_SEED);
+    let mut assistant = Assistant::for_provider(config, provider)?.with_preset(preset);
+    if let Some(model) = model {
+        assistant = assistant.with_model(model);
+    }
+    let target = format!("{}/{}", assistant.provider_name(), assistant.model());
+
+    let total = turns.len();
+    let mut results = Vec::new();
+    for turn in turns {
+        eprintln!("Turn {}/{}…", turn.number, total);
+        let started = Instant::now();
+        let (reply, completion_tokens) = match assistant.ask_with_usage(&turn.system, &turn.request, None).await {
+            Ok((reply, usage)) => (Ok(reply), usage.completion_tokens),
+            Err(e) => (Err(e.to_string()), 0),
+        };
+        results.push(RerunResult { turn, reply, completion_tokens, elapsed: started.elapsed() });
+    }
+
+    let report = render_report(name, &target, &results);
+    match output {
+        Some(path) => {
+            tokio::fs::write(&path, report).await?;
+            println!("✅ Wrote the rerun of session '{}' with {} to {}", name, target, path.display());
+        }
+        None => print!("{}", report),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picode_core::{ConversationMessage, SessionId};
+
+    #[test]
+    fn turns_carry_their_recorded_context() {
+        let mut log = ConversationLog::new(SessionId::new());
+        log.push(ConversationMessage::new("user", "Legacy question"));
+        log.push(ConversationMessage::new("assistant", "Legacy answer"));
+        log.push_context([(PathBuf::from("src/lib.rs"), "pub fn a() {}\n".to_string())]);
+        let update = format!("{}\n", log.messages[2].content);
+        log.push_prompt("Explain a", "Be brief", &update, "openai", "gpt-4o");
+        log.push(ConversationMessage::annotation("Switched model"));
+        log.push(ConversationMessage::new("assistant", "a does nothing"));
+
+        let turns = turns(&log);
+        assert_eq!(turns.len(), 2);
+        assert!(!turns[0].exact && turns[0].system.is_empty());
+        assert_eq!(turns[0].recorded.as_deref(), Some("Legacy answer"));
+        assert!(turns[1].exact);
+        assert_eq!(turns[1].request, format!("{}Explain a", update));
+        assert_eq!((turns[1].system.as_str(), turns[1].recorded.as_deref()), ("Be brief", Some("a does nothing")));
+        assert_eq!(turns[1].recorded_with.as_deref(), Some("openai/gpt-4o"));
+
+        let results = vec![
+            RerunResult { turn: turns[0].clone(), reply: Err("timeout".to_string()), completion_tokens: 0, elapsed: Duration::ZERO },
+            RerunResult {
+                turn: turns[1].clone(),
+                reply: Ok("a does nothing".to_string()),
+                completion_tokens: 4,
+                elapsed: Duration::from_millis(1500),
+            },
+        ];
+        let report = render_report("auth", "anthropic/claude", &results);
+        assert!(report.contains("Recorded with openai/gpt-4o · rerun with anthropic/claude · 2 turn(s)"));
+        assert!(report.contains("| 2 | Explain a | ~4 tok | 4 tok | 0% | 1.5s |"));
+        assert!(report.contains("| 1* | Legacy question |"));
+        assert!(report.contains("_Failed: timeout_"));
+    }
+}
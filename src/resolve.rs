@@ -0,0 +1,569 @@
+//! `picode git resolve`
+//!
+//! Walks through the merge conflicts of the unmerged files (or the files
+//! given) one at a time. Each conflict is shown with both sides, the common
+//! ancestor when the markers carry it (`merge.conflictStyle = diff3`) and
+//! the commits that last touched each side, found by blaming the side's
+//! lines in `HEAD` and in the commit being merged, cherry-picked, reverted
+//! or rebased. Unless `--no-ai`, the model suggests a resolution with its
+//! rationale. Every conflict is then accepted, taken from one side, edited
+//! in the external editor or skipped; a file whose conflicts are all
+//! resolved is written and staged, one with skipped conflicts is written
+//! with their markers left in place.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use crate::git::git;
+use crate::open::{ExternalEditor, OpenTarget};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Lines of the file shown around a conflict, to the user and the model
+const CONTEXT_LINES: usize = 10;
+
+/// Commits listed per side
+const BLAME_COMMITS: usize = 3;
+
+/// Refs naming the commit brought in, by the operation that conflicted
+const OTHER_HEADS: &[&str] = &["MERGE_HEAD", "CHERRY_PICK_HEAD", "REVERT_HEAD", "REBASE_HEAD"];
+
+const RESOLVE_SYSTEM: &str = "You resolve git merge conflicts. You are shown both sides of one \
+    conflict, the common ancestor when known, the surrounding lines and the commits behind each \
+    side. Reply with a line `RATIONALE: <one or two sentences>` followed by the resolved lines in a \
+    single fenced code block, without conflict markers. Keep the intent of both sides when they \
+    are compatible.";
+
+/// One conflict between `<<<<<<<` and `>>>>>>>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// 1-based line of the `<<<<<<<` marker
+    pub line: usize,
+    pub ours_label: String,
+    pub ours: String,
+    /// The common ancestor, with diff3-style markers
+    pub base: Option<String>,
+    pub theirs_label: String,
+    pub theirs: String,
+    /// The conflict as it appears in the file, markers included
+    raw: String,
+}
+
+/// Part of a conflicted file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Text(String),
+    Conflict(Conflict),
+}
+
+/// A file with conflict markers, cut into text and conflicts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictedFile {
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Ours,
+    Base,
+    Separator,
+    Theirs,
+}
+
+/// The marker `line` is, and its label
+fn marker(line: &str) -> Option<(Marker, &str)> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let labelled = |prefix: &str| {
+        let rest = line.strip_prefix(prefix)?;
+        (rest.is_empty() || rest.starts_with(' ')).then(|| rest.trim())
+    };
+    if line == "=======" {
+        return Some((Marker::Separator, ""));
+    }
+    [("<<<<<<<", Marker::Ours), ("|||||||", Marker::Base), (">>>>>>>", Marker::Theirs)]
+        .into_iter()
+        .find_map(|(prefix, kind)| labelled(prefix).map(|label| (kind, label)))
+}
+
+impl ConflictedFile {
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut open: Option<(Conflict, Marker)> = None;
+        for (index, line) in content.split_inclusive('\n').enumerate() {
+            let Some((conflict, section)) = &mut open else {
+                match marker(line) {
+                    Some((Marker::Ours, label)) => {
+                        if !text.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut text)));
+                        }
+                        let conflict = Conflict {
+                            line: index + 1,
+                            ours_label: label.to_string(),
+                            ours: String::new(),
+                            base: None,
+                            theirs_label: String::new(),
+                            theirs: String::new(),
+                            raw: line.to_string(),
+                        };
+                        open = Some((conflict, Marker::Ours));
+                    }
+                    _ => text.push_str(line),
+                }
+                continue;
+            };
+            conflict.raw.push_str(line);
+            match (marker(line), *section) {
+                (Some((Marker::Base, _)), Marker::Ours) => {
+                    conflict.base = Some(String::new());
+                    *section = Marker::Base;
+                }
+                (Some((Marker::Separator, _)), Marker::Ours | Marker::Base) => *section = Marker::Separator,
+                (Some((Marker::Theirs, label)), Marker::Separator) => {
+                    conflict.theirs_label = label.to_string();
+                    if let Some((conflict, _)) = open.take() {
+                        segments.push(Segment::Conflict(conflict));
+                    }
+                }
+                (Some((Marker::Ours, _)), _) => {
+                    return Err(PiCodeError::Parse(format!("conflict at line {} is not closed", conflict.line)));
+                }
+                (_, Marker::Ours) => conflict.ours.push_str(line),
+                (_, Marker::Base) => conflict.base.get_or_insert_with(String::new).push_str(line),
+                (_, _) => conflict.theirs.push_str(line),
+            }
+        }
+        if let Some((conflict, _)) = open {
+            return Err(PiCodeError::Parse(format!("conflict at line {} is not closed", conflict.line)));
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn conflicts(&self) -> Vec<&Conflict> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Conflict(conflict) => Some(conflict),
+                Segment::Text(_) => None,
+            })
+            .collect()
+    }
+
+    /// Up to `lines` lines of text before and after the `index`th conflict
+    pub fn context(&self, index: usize, lines: usize) -> (String, String) {
+        let Some(at) = self
+            .segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| matches!(segment, Segment::Conflict(_)))
+            .nth(index)
+            .map(|(at, _)| at)
+        else {
+            return Default::default();
+        };
+        let text = |at: Option<usize>| match at.and_then(|at| self.segments.get(at)) {
+            Some(Segment::Text(text)) => text.as_str(),
+            _ => "",
+        };
+        let before: Vec<&str> = text(at.checked_sub(1)).split_inclusive('\n').collect();
+        let after = text(Some(at + 1)).split_inclusive('\n').take(lines).collect();
+        (before[before.len().saturating_sub(lines)..].concat(), after)
+    }
+
+    /// The file with the `i`th conflict replaced by `resolutions[i]`, or
+    /// left with its markers where that is `None`
+    pub fn render(&self, resolutions: &[Option<String>]) -> String {
+        let mut out = String::new();
+        let mut index = 0;
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Conflict(conflict) => {
+                    match resolutions.get(index).and_then(Option::as_ref) {
+                        Some(resolution) => out.push_str(resolution),
+                        None => out.push_str(&conflict.raw),
+                    }
+                    index += 1;
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A commit that last touched some lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameCommit {
+    pub sha: String,
+    pub author: String,
+    pub summary: String,
+}
+
+impl std::fmt::Display for BlameCommit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: {}", &self.sha[..self.sha.len().min(8)], self.author, self.summary)
+    }
+}
+
+/// Commits of `git blame --porcelain` output, in order of first appearance;
+/// uncommitted lines are left out
+pub fn parse_blame(porcelain: &str) -> Vec<BlameCommit> {
+    let mut commits: Vec<BlameCommit> = Vec::new();
+    let mut current: Option<usize> = None;
+    for line in porcelain.lines() {
+        if line.starts_with('\t') {
+            continue;
+        }
+        let first = line.split(' ').next().unwrap_or_default();
+        if first.len() == 40 && first.bytes().all(|b| b.is_ascii_hexdigit()) {
+            current = commits.iter().position(|commit| commit.sha == first);
+            if current.is_none() && first.bytes().any(|b| b != b'0') {
+                commits.push(BlameCommit { sha: first.to_string(), author: String::new(), summary: String::new() });
+                current = Some(commits.len() - 1);
+            }
+        } else if let Some(commit) = current.map(|at| &mut commits[at]) {
+            if let Some(author) = line.strip_prefix("author ") {
+                commit.author = author.to_string();
+            } else if let Some(summary) = line.strip_prefix("summary ") {
+                commit.summary = summary.to_string();
+            }
+        }
+    }
+    commits
+}
+
+/// 1-based line where the lines of `block` start in `content`
+fn locate(content: &str, block: &str) -> Option<usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let block: Vec<&str> = block.lines().collect();
+    if block.is_empty() {
+        return None;
+    }
+    lines.windows(block.len()).position(|window| window == block.as_slice()).map(|at| at + 1)
+}
+
+/// Commits behind one side of a conflict: blame of its lines in `rev`, or
+/// the last commits of the file there when the lines cannot be found
+async fn blame(root: &Path, rev: &str, path: &str, side: &str) -> Vec<BlameCommit> {
+    let located = match git(root, &["show", &format!("{}:{}", rev, path)]).await {
+        Ok(content) => locate(&content, side),
+        Err(_) => return Vec::new(),
+    };
+    let output = match located {
+        Some(start) => {
+            let range = format!("{},+{}", start, side.lines().count());
+            git(root, &["blame", "--porcelain", "-L", &range, rev, "--", path]).await
+        }
+        None => {
+            let format = "--format=%H%nauthor %an%nsummary %s";
+            git(root, &["log", "-n", &BLAME_COMMITS.to_string(), format, rev, "--", path]).await
+        }
+    };
+    match output {
+        Ok(output) => parse_blame(&output).into_iter().take(BLAME_COMMITS).collect(),
+        Err(e) => {
+            warn!("Could not blame {} at {}: {}", path, rev, e);
+            Vec::new()
+        }
+    }
+}
+
+/// A resolution proposed by the model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub rationale: String,
+    pub text: String,
+}
+
+/// The suggestion in the model's reply; `None` without a code block
+pub fn parse_suggestion(reply: &str) -> Option<Suggestion> {
+    let rationale = reply
+        .lines()
+        .find_map(|line| {
+            let line = line.trim().trim_start_matches(['-', '*', ' ']);
+            line.strip_prefix("RATIONALE:").map(|rest| rest.trim().trim_start_matches("**").trim())
+        })
+        .unwrap_or_default()
+        .to_string();
+    let (_, rest) = reply.split_once("```")?;
+    let (_, code) = rest.split_once('\n')?;
+    let code = match code.rfind("```") {
+        Some(end) => &code[..end],
+        None => code,
+    };
+    let mut text = code.to_string();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Some(Suggestion { rationale, text })
+}
+
+/// The model's request for one conflict
+fn resolve_prompt(path: &str, conflict: &Conflict, context: &(String, String), blames: &[Vec<BlameCommit>; 2]) -> String {
+    let mut prompt = format!("File: {}\n\nLines before the conflict:\n```\n{}```\n\n", path, context.0);
+    for (name, label, text, commits) in [
+        ("Ours", &conflict.ours_label, &conflict.ours, &blames[0]),
+        ("Theirs", &conflict.theirs_label, &conflict.theirs, &blames[1]),
+    ] {
+        let _ = writeln!(prompt, "{} ({}):\n```\n{}```", name, label, text);
+        for commit in commits {
+            let _ = writeln!(prompt, "- {}", commit);
+        }
+        prompt.push('\n');
+    }
+    if let Some(base) = &conflict.base {
+        let _ = write!(prompt, "Common ancestor:\n```\n{}```\n\n", base);
+    }
+    let _ = write!(prompt, "Lines after the conflict:\n```\n{}```\n", context.1);
+    prompt
+}
+
+/// Files git lists as unmerged, relative to the repository root
+async fn unmerged(root: &Path) -> Result<Vec<String>> {
+    let output = git(root, &["diff", "--name-only", "--diff-filter=U"]).await?;
+    let mut paths: Vec<String> = output.lines().map(str::to_string).collect();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// The commit on the other side of the conflicts, if any operation is in
+/// progress
+async fn other_head(root: &Path) -> Option<&'static str> {
+    for head in OTHER_HEADS {
+        if git(root, &["rev-parse", "--verify", "--quiet", head]).await.is_ok() {
+            return Some(head);
+        }
+    }
+    None
+}
+
+fn print_side(name: &str, label: &str, text: &str, commits: &[BlameCommit]) {
+    println!("{} {}", name, label);
+    for commit in commits {
+        println!("    {}", commit);
+    }
+    for line in text.lines() {
+        println!("  │ {}", line);
+    }
+}
+
+/// Let the user rewrite `initial` in the external editor
+async fn edit(config: &Config, path: &str, initial: &str) -> Result<String> {
+    let name = Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or("conflict");
+    let scratch = std::env::temp_dir().join(format!("picode-resolve-{}-{}", std::process::id(), name));
+    tokio::fs::write(&scratch, initial).await?;
+    let target = OpenTarget { path: scratch.clone(), line: None, column: None };
+    let edited = crate::open::edit(&ExternalEditor::from_config(config), &target).await;
+    let content = tokio::fs::read_to_string(&scratch).await;
+    let _ = tokio::fs::remove_file(&scratch).await;
+    edited?;
+    Ok(content?)
+}
+
+/// Run `picode git resolve`
+pub async fn run(config: &Config, paths: Vec<PathBuf>, no_ai: bool) -> Result<()> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        return Err(PiCodeError::InvalidCommand("resolve asks about each conflict and needs a terminal".to_string()));
+    }
+    let workspace = crate::stats::workspace_root(config)?;
+    let root = PathBuf::from(git(&workspace, &["rev-parse", "--show-toplevel"]).await?.trim());
+    let files = if paths.is_empty() {
+        unmerged(&root).await?
+    } else {
+        let cwd = std::env::current_dir()?;
+        let root = picode_core::paths::canonicalize(&root).unwrap_or_else(|_| root.clone());
+        paths
+            .iter()
+            .map(|path| {
+                let absolute = picode_core::paths::canonicalize(cwd.join(path)).unwrap_or_else(|_| cwd.join(path));
+                match absolute.strip_prefix(&root) {
+                    Ok(relative) => Ok(relative.to_string_lossy().replace('\\', "/")),
+                    Err(_) => Err(PiCodeError::InvalidCommand(format!("{} is outside the repository", path.display()))),
+                }
+            })
+            .collect::<Result<_>>()?
+    };
+    if files.is_empty() {
+        println!("✅ No conflicts to resolve");
+        return Ok(());
+    }
+    let assistant = match Assistant::from_config(config) {
+        _ if no_ai => None,
+        Ok(assistant) => Some(assistant),
+        Err(e) => {
+            warn!("No model for suggestions: {}", e);
+            None
+        }
+    };
+    let other = other_head(&root).await;
+
+    let (mut staged, mut left) = (0, 0);
+    'files: for path in &files {
+        let content = tokio::fs::read_to_string(root.join(path)).await?;
+        let file = ConflictedFile::parse(&content)?;
+        let conflicts = file.conflicts();
+        if conflicts.is_empty() {
+            println!("⚠️  {} has no conflict markers (binary or delete/modify conflict); resolve it with git", path);
+            left += 1;
+            continue;
+        }
+        let mut resolutions: Vec<Option<String>> = vec![None; conflicts.len()];
+        let mut quit = false;
+        for (index, conflict) in conflicts.iter().enumerate() {
+            println!("\n── {}: conflict {}/{} at line {} ──", path, index + 1, conflicts.len(), conflict.line);
+            let context = file.context(index, CONTEXT_LINES);
+            let blames = [
+                blame(&root, "HEAD", path, &conflict.ours).await,
+                match other {
+                    Some(head) => blame(&root, head, path, &conflict.theirs).await,
+                    None => Vec::new(),
+                },
+            ];
+            print_side("<<<<<<< ours", &conflict.ours_label, &conflict.ours, &blames[0]);
+            if let Some(base) = &conflict.base {
+                print_side("||||||| base", "", base, &[]);
+            }
+            print_side(">>>>>>> theirs", &conflict.theirs_label, &conflict.theirs, &blames[1]);
+
+            let suggestion = match &assistant {
+                Some(assistant) => {
+                    let prompt = resolve_prompt(path, conflict, &context, &blames);
+                    match assistant.ask(RESOLVE_SYSTEM, &prompt, Some(2048)).await {
+                        Ok(reply) => parse_suggestion(&reply),
+                        Err(e) => {
+                            warn!("No suggestion: {}", e);
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+            if let Some(suggestion) = &suggestion {
+                println!("💡 {}", if suggestion.rationale.is_empty() { "Suggested resolution" } else { &suggestion.rationale });
+                for line in suggestion.text.lines() {
+                    println!("  │ {}", line);
+                }
+            }
+
+            let mut items = vec!["take ours", "take theirs", "take both (ours first)", "edit", "skip", "quit"];
+            if suggestion.is_some() {
+                items.insert(0, "accept suggestion");
+            }
+            let choice = dialoguer::Select::new()
+                .with_prompt(format!("Conflict {}/{}", index + 1, conflicts.len()))
+                .items(&items)
+                .default(0)
+                .interact()?;
+            resolutions[index] = match items[choice] {
+                "accept suggestion" => suggestion.map(|suggestion| suggestion.text),
+                "take ours" => Some(conflict.ours.clone()),
+                "take theirs" => Some(conflict.theirs.clone()),
+                "take both (ours first)" => Some(format!("{}{}", conflict.ours, conflict.theirs)),
+                "edit" => {
+                    let initial = suggestion.map_or_else(|| format!("{}{}", conflict.ours, conflict.theirs), |s| s.text);
+                    Some(edit(config, path, &initial).await?)
+                }
+                "skip" => None,
+                _ => {
+                    quit = true;
+                    break;
+                }
+            };
+        }
+
+        let resolved = file.render(&resolutions);
+        if resolved != content {
+            tokio::fs::write(root.join(path), &resolved).await?;
+        }
+        let remaining = ConflictedFile::parse(&resolved).map_or(1, |file| file.conflicts().len());
+        if remaining == 0 {
+            git(&root, &["add", "--", path]).await?;
+            println!("✅ Staged {}", path);
+            staged += 1;
+        } else {
+            println!("⏸️  {} conflict(s) left in {}", remaining, path);
+            left += 1;
+        }
+        if quit {
+            left = files.len() - staged;
+            break 'files;
+        }
+    }
+    println!("\n{} file(s) staged; {} file(s) still conflicted", staged, left);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MERGED: &str = "\
+fn main() {
+<<<<<<< HEAD
+    let port = 8080;
+||||||| base
+    let port = 80;
+=======
+    let port = env_port();
+>>>>>>> feature/env
+    serve(port);
+<<<<<<< HEAD
+=======
+    shutdown();
+>>>>>>> feature/env
+}
+";
+
+    #[test]
+    fn conflicts_parse_and_render_back() {
+        let file = ConflictedFile::parse(MERGED).unwrap();
+        let conflicts = file.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].line, 2);
+        assert_eq!((conflicts[0].ours_label.as_str(), conflicts[0].theirs_label.as_str()), ("HEAD", "feature/env"));
+        assert_eq!(conflicts[0].ours, "    let port = 8080;\n");
+        assert_eq!(conflicts[0].base.as_deref(), Some("    let port = 80;\n"));
+        assert_eq!(conflicts[1].ours, "");
+        assert_eq!(conflicts[1].theirs, "    shutdown();\n");
+        assert_eq!(file.context(1, 1), ("    serve(port);\n".to_string(), "}\n".to_string()));
+
+        assert_eq!(file.render(&[]), MERGED);
+        let partly = file.render(&[Some("    let port = env_port().unwrap_or(8080);\n".to_string()), None]);
+        assert!(partly.starts_with("fn main() {\n    let port = env_port().unwrap_or(8080);\n    serve(port);\n<<<<<<< HEAD"));
+        assert_eq!(ConflictedFile::parse(&partly).unwrap().conflicts().len(), 1);
+        assert!(ConflictedFile::parse("<<<<<<< HEAD\na\n=======\n").is_err());
+        assert!(ConflictedFile::parse("<<<<<<<<<< not a marker\n").unwrap().conflicts().is_empty());
+    }
+
+    #[test]
+    fn blame_and_suggestions_are_parsed() {
+        let porcelain = "\
+4b825dc642cb6eb9a060e54bf8d69288fbee4904 3 3 2
+author Ada
+summary Read the port from the environment
+\t    let port = env_port();
+4b825dc642cb6eb9a060e54bf8d69288fbee4904 4 4
+\t    serve(port);
+0000000000000000000000000000000000000000 5 5 1
+author Not Committed Yet
+summary Version of a.rs from a.rs
+\t}
+";
+        let commits = parse_blame(porcelain);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].to_string(), "4b825dc6 Ada: Read the port from the environment");
+        assert_eq!(locate("a\nb\nc\n", "b\nc\n"), Some(2));
+
+        let reply = "**RATIONALE:** Keep the environment lookup but fall back to 8080.\n\n```rust\n    let port = env_port().unwrap_or(8080);\n```";
+        let suggestion = parse_suggestion(reply).unwrap();
+        assert_eq!(suggestion.rationale, "Keep the environment lookup but fall back to 8080.");
+        assert_eq!(suggestion.text, "    let port = env_port().unwrap_or(8080);\n");
+        assert_eq!(parse_suggestion("Take theirs."), None);
+    }
+}
@@ -0,0 +1,57 @@
+//! `picode review` subcommands
+//!
+//! Edits staged by `picode agent run --review` wait in the workspace's
+//! review queue. They can be exported as a git patch for review in other
+//! tools, imported back after editing, and applied.
+
+use crate::config::Config;
+use crate::error::Result;
+use picode_core::editor::{EditJournal, ReviewQueue};
+use picode_core::{ContentCache, CoreError, NativeFileSystem};
+
+/// Handle `picode review` subcommands
+pub async fn handle_action(action: picode_cli::ReviewAction, config: &Config) -> Result<()> {
+    let root = crate::stats::workspace_root(config)?;
+    let fs = NativeFileSystem;
+    let mut queue = ReviewQueue::load(&fs, &root).await.map_err(CoreError::from)?;
+    match action {
+        picode_cli::ReviewAction::List => {
+            if queue.edits.is_empty() {
+                println!("No edits pending review");
+            }
+            for edit in &queue.edits {
+                let state = if edit.base.is_some() { "modified" } else { "new" };
+                println!("{:<9} {}", state, edit.path.display());
+            }
+        }
+        picode_cli::ReviewAction::Export { patch } => {
+            let text = queue.to_patch();
+            match patch {
+                Some(path) => {
+                    tokio::fs::write(&path, &text).await?;
+                    println!("✅ Wrote {} pending edit(s) to {}", queue.edits.len(), path.display());
+                }
+                None => print!("{}", text),
+            }
+        }
+        picode_cli::ReviewAction::Import { patch } => {
+            let text = tokio::fs::read_to_string(&patch).await?;
+            let import = queue.import_patch(&text, &fs, &root).await.map_err(CoreError::from)?;
+            queue.save(&fs, &root).await.map_err(CoreError::from)?;
+            println!("✅ {} file(s) from {}", import.updated.len(), patch.display());
+            for path in &import.dropped {
+                println!("   dropped {}", path.display());
+            }
+        }
+        picode_cli::ReviewAction::Apply => {
+            let journal = EditJournal::for_workspace(&root);
+            let written = queue
+                .apply(&root, &journal, &fs, &ContentCache::global())
+                .await
+                .map_err(CoreError::from)?;
+            queue.save(&fs, &root).await.map_err(CoreError::from)?;
+            println!("✅ Applied {} file(s)", written.len());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,675 @@
+//! Serve mode
+//!
+//! Runs PiCode as a long-lived HTTP server. Operators can scrape `/metrics`
+//! (Prometheus text format) and probe `/health` to monitor deployments.
+//!
+//! Teams can share one server through user accounts (see [`crate::users`]).
+//! Every account gets its own session namespace and spend limits, and
+//! authenticates with `Authorization: Bearer <token>`:
+//!
+//! - `GET /me` - the caller's role, limits and spend
+//! - `GET|POST /sessions` - list or create sessions in the caller's namespace
+//! - `GET /sessions/<name>/messages` - a session's conversation
+//! - `POST /sessions/<name>/ask` - send `{"prompt": ...}` to the model
+//! - `GET /admin/sessions`, `GET /admin/users` and
+//!   `POST /admin/users/<name>/revoke` - admin API
+//!
+//! Once any account exists, `/metrics` is restricted to admins.
+
+use crate::assistant::Assistant;
+use crate::config::{Config, ServeConfig};
+use crate::error::{PiCodeError, Result};
+use crate::metrics::Metrics;
+use crate::users::{ServeUser, UserStore};
+use picode_core::conversation::ConversationMessage;
+use picode_core::session::{SessionError, SessionId};
+use picode_core::SessionManager;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Largest accepted request head plus body
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+/// Completion tokens allowed, and reserved for, when a request sets no limit
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// Time a client gets to send its whole request
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options for serve mode
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Address to listen on
+    pub bind: SocketAddr,
+    /// Account store
+    pub users_file: PathBuf,
+    /// Root of the per-user session namespaces
+    pub sessions_dir: PathBuf,
+}
+
+/// Default root of the per-user session namespaces, in `~/.picode`
+pub fn default_sessions_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(crate::defaults::CONFIG_DIR)
+        .join(crate::defaults::SERVE_SESSIONS_DIR)
+}
+
+/// Minimal HTTP response
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl HttpResponse {
+    fn new(status: u16, content_type: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn json(status: u16, body: serde_json::Value) -> Self {
+        Self::new(status, "application/json", body.to_string())
+    }
+
+    fn error(status: u16, message: impl std::fmt::Display) -> Self {
+        Self::json(status, json!({ "error": message.to_string() }))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+
+    /// Serialize the response for the wire
+    pub fn to_bytes(&self) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            self.reason(),
+            self.content_type,
+            self.body.len(),
+            self.body
+        )
+        .into_bytes()
+    }
+}
+
+impl From<PiCodeError> for HttpResponse {
+    fn from(error: PiCodeError) -> Self {
+        let status = match &error {
+            PiCodeError::Parse(_) | PiCodeError::JsonSerialization(_) => 400,
+            PiCodeError::Auth(_) => 401,
+            PiCodeError::Permission(_) => 403,
+            PiCodeError::NotFound(_) => 404,
+            PiCodeError::AlreadyExists(_) => 409,
+            PiCodeError::Llm(_) => 502,
+            _ => 500,
+        };
+        Self::error(status, error)
+    }
+}
+
+/// Parsed HTTP request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercased
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl HttpRequest {
+    /// Parse the request line and headers
+    pub fn parse_head(head: &str) -> Option<Self> {
+        let mut lines = head.lines();
+        let mut request_line = lines.next()?.split_whitespace();
+        let method = request_line.next()?.to_string();
+        let path = request_line.next().unwrap_or("/").to_string();
+
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        Some(Self {
+            method,
+            path,
+            headers,
+            body: String::new(),
+        })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    pub fn bearer_token(&self) -> Option<&str> {
+        self.header("authorization")?.strip_prefix("Bearer ").map(str::trim)
+    }
+
+    fn content_length(&self) -> usize {
+        self.header("content-length")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0)
+    }
+
+    fn json<T: for<'de> Deserialize<'de>>(&self) -> Result<T> {
+        serde_json::from_str(&self.body).map_err(|e| PiCodeError::Parse(format!("invalid request body: {}", e)))
+    }
+}
+
+/// Route a request to its handler
+pub fn route(method: &str, path: &str, metrics: &Metrics) -> HttpResponse {
+    let path = path.split('?').next().unwrap_or(path);
+
+    match (method, path) {
+        ("GET", "/metrics") => HttpResponse::new(
+            200,
+            "text/plain; version=0.0.4; charset=utf-8",
+            metrics.render_prometheus(),
+        ),
+        ("GET", "/health") => HttpResponse::new(200, "application/json", r#"{"status":"ok"}"#),
+        (_, "/metrics") | (_, "/health") => HttpResponse::new(405, "text/plain", "method not allowed\n"),
+        _ => HttpResponse::new(404, "text/plain", "not found\n"),
+    }
+}
+
+/// Recent activity on a user's session
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveSession {
+    pub last_active: chrono::DateTime<chrono::Utc>,
+    pub requests: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateSession {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AskRequest {
+    prompt: String,
+    #[serde(default)]
+    max_tokens: Option<u32>,
+}
+
+/// Shared state of a running server
+pub struct ServeState {
+    metrics: &'static Metrics,
+    users: Mutex<UserStore>,
+    sessions_dir: PathBuf,
+    namespaces: Mutex<HashMap<String, Arc<SessionManager>>>,
+    active: Mutex<BTreeMap<(String, String), ActiveSession>>,
+    /// Held while a session's conversation is loaded, extended and saved
+    asking: Mutex<HashMap<SessionId, Arc<Mutex<()>>>>,
+    assistant: std::result::Result<Assistant, String>,
+    system_prompt: String,
+    pricing: ServeConfig,
+}
+
+impl ServeState {
+    pub fn new(users: UserStore, sessions_dir: PathBuf, config: &Config) -> Self {
+        Self {
+            metrics: Metrics::global(),
+            users: Mutex::new(users),
+            sessions_dir,
+            namespaces: Mutex::new(HashMap::new()),
+            active: Mutex::new(BTreeMap::new()),
+            asking: Mutex::new(HashMap::new()),
+            assistant: Assistant::from_config(config).map_err(|e| e.to_string()),
+            system_prompt: picode_core::SystemPrompt::new().effective(),
+            pricing: config.serve.clone(),
+        }
+    }
+
+    /// Handle a request, authenticating it when required
+    pub async fn handle(&self, request: &HttpRequest) -> HttpResponse {
+        let path = request.path.split('?').next().unwrap_or(&request.path).to_string();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let multi_user = !self.users.lock().await.is_empty();
+
+        match segments.as_slice() {
+            ["health"] => return route(&request.method, &path, self.metrics),
+            ["metrics"] if !multi_user => return route(&request.method, &path, self.metrics),
+            _ => {}
+        }
+
+        let user = match self.authenticate(request).await {
+            Ok(user) => user,
+            Err(e) => return e.into(),
+        };
+
+        let result = match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["metrics"]) => {
+                return match self.require_admin(&user) {
+                    Ok(()) => route("GET", "/metrics", self.metrics),
+                    Err(e) => e.into(),
+                }
+            }
+            ("GET", ["me"]) => Ok((200, json!({
+                "name": user.name,
+                "role": user.role,
+                "limits": user.limits,
+                "spend": user.spend,
+            }))),
+            ("GET", ["sessions"]) => self.list_sessions(&user).await,
+            ("POST", ["sessions"]) => self.create_session(&user, request).await,
+            ("GET", ["sessions", name, "messages"]) => self.messages(&user, name).await,
+            ("POST", ["sessions", name, "ask"]) => self.ask(&user, name, request).await,
+            ("GET", ["admin", "sessions"]) => self.admin_sessions(&user).await,
+            ("GET", ["admin", "users"]) => self.admin_users(&user).await,
+            ("POST", ["admin", "users", name, "revoke"]) => self.admin_revoke(&user, name).await,
+            _ => return HttpResponse::error(404, "not found"),
+        };
+
+        match result {
+            Ok((status, body)) => HttpResponse::json(status, body),
+            Err(e) => e.into(),
+        }
+    }
+
+    async fn authenticate(&self, request: &HttpRequest) -> Result<ServeUser> {
+        let users = self.users.lock().await;
+        if users.is_empty() {
+            return Err(PiCodeError::Auth(
+                "no accounts configured; create one with `picode serve add-user <name>`".to_string(),
+            ));
+        }
+        let token = request
+            .bearer_token()
+            .ok_or_else(|| PiCodeError::Auth("missing bearer token".to_string()))?;
+        users
+            .authenticate(token)
+            .cloned()
+            .ok_or_else(|| PiCodeError::Auth("invalid or revoked token".to_string()))
+    }
+
+    fn require_admin(&self, user: &ServeUser) -> Result<()> {
+        if user.is_admin() {
+            Ok(())
+        } else {
+            Err(PiCodeError::Permission("admin role required".to_string()))
+        }
+    }
+
+    /// Session manager for a user's namespace, loaded on first use
+    async fn namespace(&self, user: &ServeUser) -> Result<Arc<SessionManager>> {
+        let mut namespaces = self.namespaces.lock().await;
+        if let Some(manager) = namespaces.get(&user.name) {
+            return Ok(manager.clone());
+        }
+
+        let manager = Arc::new(SessionManager::new(self.sessions_dir.join(&user.name)));
+        let recovery = manager.load_sessions().await.map_err(picode_core::CoreError::from)?;
+        if !recovery.is_empty() {
+            warn!("Recovered sessions for {}:\n{}", user.name, recovery);
+        }
+        namespaces.insert(user.name.clone(), manager.clone());
+        Ok(manager)
+    }
+
+    async fn touch(&self, user: &ServeUser, session: &str) {
+        let mut active = self.active.lock().await;
+        let entry = active
+            .entry((user.name.clone(), session.to_string()))
+            .or_insert_with(|| {
+                self.metrics.session_started();
+                ActiveSession {
+                    last_active: chrono::Utc::now(),
+                    requests: 0,
+                }
+            });
+        entry.last_active = chrono::Utc::now();
+        entry.requests += 1;
+    }
+
+    async fn list_sessions(&self, user: &ServeUser) -> Result<(u16, serde_json::Value)> {
+        let mut sessions = self.namespace(user).await?.list_sessions().await;
+        sessions.sort_by(|a, b| a.name.cmp(&b.name));
+        let sessions: Vec<_> = sessions
+            .iter()
+            .map(|s| json!({ "id": s.id.to_string(), "name": s.name, "last_active": s.last_active }))
+            .collect();
+        Ok((200, json!({ "sessions": sessions })))
+    }
+
+    async fn create_session(&self, user: &ServeUser, request: &HttpRequest) -> Result<(u16, serde_json::Value)> {
+        let body: CreateSession = request.json()?;
+        let manager = self.namespace(user).await?;
+        let id = manager
+            .create_session(body.name.clone(), PathBuf::from("."))
+            .await
+            .map_err(session_error)?;
+        Ok((201, json!({ "id": id.to_string(), "name": body.name })))
+    }
+
+    async fn messages(&self, user: &ServeUser, name: &str) -> Result<(u16, serde_json::Value)> {
+        let manager = self.namespace(user).await?;
+        let session = manager.get_session_by_name(name).await.map_err(session_error)?;
+        let log = manager.load_conversation(&session.id).await.map_err(session_error)?;
+        Ok((200, json!({ "session": name, "messages": log.messages })))
+    }
+
+    async fn ask(&self, user: &ServeUser, name: &str, request: &HttpRequest) -> Result<(u16, serde_json::Value)> {
+        let body: AskRequest = request.json()?;
+        let manager = self.namespace(user).await?;
+        let session = manager.get_session_by_name(name).await.map_err(session_error)?;
+
+        // Concurrent asks to one session would each save only their own turn
+        let lock = self.asking.lock().await.entry(session.id.clone()).or_default().clone();
+        let result = {
+            let _asking = lock.lock().await;
+            self.ask_session(user, name, &manager, &session.id, body).await
+        };
+        let mut asking = self.asking.lock().await;
+        if Arc::strong_count(&lock) == 2 {
+            asking.remove(&session.id);
+        }
+        result
+    }
+
+    async fn ask_session(
+        &self,
+        user: &ServeUser,
+        name: &str,
+        manager: &SessionManager,
+        session_id: &SessionId,
+        body: AskRequest,
+    ) -> Result<(u16, serde_json::Value)> {
+        let mut log = manager.load_conversation(session_id).await.map_err(session_error)?;
+
+        let mut prompt = String::new();
+        for message in &log.messages {
+            prompt.push_str(&format!("{}: {}\n\n", message.role, message.content));
+        }
+        prompt.push_str(&format!("user: {}", body.prompt));
+
+        // Reserve the worst case up front; it is rejected when it would
+        // exceed the user's limits and settled once the reply is in
+        let max_tokens = body.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        let estimate = self.pricing.cost(
+            picode_core::system_prompt::estimate_tokens(&format!("{}{}", self.system_prompt, prompt)) as u32,
+            max_tokens,
+        );
+        let assistant = self
+            .assistant
+            .as_ref()
+            .map_err(|e| PiCodeError::Llm(format!("no model available: {}", e)))?;
+        self.users.lock().await.reserve_spend(&user.name, estimate)?;
+
+        self.touch(user, name).await;
+        let result = assistant
+            .ask_with_usage(&self.system_prompt, &prompt, Some(max_tokens))
+            .await;
+
+        let cost = match &result {
+            Ok((_, usage)) => self.pricing.cost(usage.prompt_tokens, usage.completion_tokens),
+            Err(_) => 0.0,
+        };
+        {
+            let mut users = self.users.lock().await;
+            users.settle_spend(&user.name, estimate, cost)?;
+            users.save().await?;
+        }
+        let (reply, usage) = result?;
+
+        log.push(ConversationMessage::new("user", body.prompt));
+        log.push(ConversationMessage::new("assistant", reply.clone()));
+        manager.save_conversation(&mut log).await.map_err(session_error)?;
+        manager
+            .update_session(session_id, |s| s.touch())
+            .await
+            .map_err(session_error)?;
+
+        Ok((200, json!({
+            "reply": reply,
+            "usage": usage,
+            "cost_usd": cost,
+        })))
+    }
+
+    async fn admin_sessions(&self, user: &ServeUser) -> Result<(u16, serde_json::Value)> {
+        self.require_admin(user)?;
+        let active = self.active.lock().await;
+        let sessions: Vec<_> = active
+            .iter()
+            .map(|((user, session), activity)| {
+                json!({
+                    "user": user,
+                    "session": session,
+                    "last_active": activity.last_active,
+                    "requests": activity.requests,
+                })
+            })
+            .collect();
+        Ok((200, json!({ "sessions": sessions })))
+    }
+
+    async fn admin_users(&self, user: &ServeUser) -> Result<(u16, serde_json::Value)> {
+        self.require_admin(user)?;
+        let users = self.users.lock().await;
+        let users: Vec<_> = users
+            .users()
+            .map(|u| {
+                json!({
+                    "name": u.name,
+                    "role": u.role,
+                    "active_tokens": u.active_tokens(),
+                    "limits": u.limits,
+                    "spend": u.spend,
+                })
+            })
+            .collect();
+        Ok((200, json!({ "users": users })))
+    }
+
+    async fn admin_revoke(&self, user: &ServeUser, name: &str) -> Result<(u16, serde_json::Value)> {
+        self.require_admin(user)?;
+        let mut users = self.users.lock().await;
+        let revoked = users.revoke_tokens(name)?;
+        users.save().await?;
+        info!("Admin {} revoked {} token(s) of {}", user.name, revoked, name);
+        Ok((200, json!({ "user": name, "revoked": revoked })))
+    }
+}
+
+fn session_error(error: SessionError) -> PiCodeError {
+    match error {
+        SessionError::NotFound(name) => PiCodeError::NotFound(format!("session '{}'", name)),
+        SessionError::AlreadyExists(name) => PiCodeError::AlreadyExists(format!("session '{}'", name)),
+        other => picode_core::CoreError::from(other).into(),
+    }
+}
+
+/// Run the HTTP server until the process is stopped
+pub async fn run(opts: ServeOptions, config: Config) -> Result<()> {
+    let users = UserStore::load(&opts.users_file).await?;
+    let multi_user = !users.is_empty();
+    let state = Arc::new(ServeState::new(users, opts.sessions_dir.clone(), &config));
+
+    let listener = TcpListener::bind(opts.bind).await?;
+    info!("Serve mode listening on http://{}", opts.bind);
+    println!("🌐 PiCode serving on http://{} (metrics at /metrics)", opts.bind);
+    if multi_user {
+        println!("🔐 Multi-user mode: accounts from {}", opts.users_file.display());
+    }
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        debug!("Accepted connection from {}", peer);
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &state).await {
+                warn!("Connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: &ServeState) -> std::io::Result<()> {
+    let response = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(Some(request))) => state.handle(&request).await,
+        Ok(Ok(None)) => HttpResponse::error(413, "request too large"),
+        Ok(Err(e)) => return Err(e),
+        Err(_) => HttpResponse::error(408, "request not received in time"),
+    };
+    stream.write_all(&response.to_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Read a request head and its body; `None` if it exceeds the size limit
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<HttpRequest>> {
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 8192];
+
+    let head_end = loop {
+        if let Some(position) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break position + 4;
+        }
+        if data.len() > MAX_REQUEST_SIZE {
+            return Ok(None);
+        }
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break data.len();
+        }
+        data.extend_from_slice(&buffer[..read]);
+    };
+
+    let Some(mut request) = HttpRequest::parse_head(&String::from_utf8_lossy(&data[..head_end])) else {
+        return Ok(Some(HttpRequest::default()));
+    };
+    let body_len = request.content_length();
+    if head_end + body_len > MAX_REQUEST_SIZE {
+        return Ok(None);
+    }
+    while data.len() < head_end + body_len {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..read]);
+    }
+
+    let body_end = data.len().min(head_end + body_len);
+    request.body = String::from_utf8_lossy(&data[head_end..body_end]).into_owned();
+    Ok(Some(request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpendLimits;
+    use crate::users::UserRole;
+
+    #[test]
+    fn metrics_route_returns_prometheus_text() {
+        let metrics = Metrics::new();
+        metrics.record_request("openai", Duration::from_millis(120), 10, 10, true);
+
+        let response = route("GET", "/metrics?format=text", &metrics);
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains("picode_llm_requests_total{provider=\"openai\"} 1"));
+
+        assert_eq!(route("POST", "/metrics", &metrics).status, 405);
+        assert_eq!(route("GET", "/unknown", &metrics).status, 404);
+    }
+
+    #[test]
+    fn response_serialization() {
+        let bytes = route("GET", "/health", &Metrics::new()).to_bytes();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(text.ends_with(r#"{"status":"ok"}"#));
+    }
+
+    #[test]
+    fn parses_request_head() {
+        let request = HttpRequest::parse_head(
+            "POST /sessions HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer pct_abc\r\nContent-Length: 16\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/sessions"));
+        assert_eq!(request.bearer_token(), Some("pct_abc"));
+        assert_eq!(request.content_length(), 16);
+    }
+
+    fn request(method: &str, path: &str, token: &str, body: &str) -> HttpRequest {
+        let mut request = HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.to_string(),
+            ..Default::default()
+        };
+        request.headers.insert("authorization".to_string(), format!("Bearer {}", token));
+        request
+    }
+
+    #[tokio::test]
+    async fn namespaces_limits_and_admin_api() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut users = UserStore::new();
+        let admin = users.add_user("root", UserRole::Admin, SpendLimits::default()).unwrap();
+        let limits = SpendLimits {
+            per_request: Some(0.0),
+            ..Default::default()
+        };
+        let alice = users.add_user("alice", UserRole::Member, limits).unwrap();
+        let state = ServeState::new(users, dir.path().to_path_buf(), &Config::default());
+
+        // Authentication is required once accounts exist
+        assert_eq!(state.handle(&request("GET", "/metrics", "", "")).await.status, 401);
+        assert_eq!(state.handle(&request("GET", "/metrics", &alice, "")).await.status, 403);
+        assert_eq!(state.handle(&request("GET", "/metrics", &admin, "")).await.status, 200);
+        assert_eq!(state.handle(&request("GET", "/health", "", "")).await.status, 200);
+
+        // Sessions are namespaced per user
+        let created = state.handle(&request("POST", "/sessions", &alice, r#"{"name":"fix-ci"}"#)).await;
+        assert_eq!(created.status, 201);
+        assert!(dir.path().join("alice").is_dir());
+        let listed = state.handle(&request("GET", "/sessions", &admin, "")).await;
+        assert!(!listed.body.contains("fix-ci"));
+
+        // Spend limits are checked before the model is called
+        let ask = state
+            .handle(&request("POST", "/sessions/fix-ci/ask", &alice, r#"{"prompt":"why?"}"#))
+            .await;
+        assert_eq!(ask.status, 403);
+        assert!(ask.body.contains("per-request spend limit"));
+
+        // Admins revoke tokens
+        assert_eq!(state.handle(&request("GET", "/admin/users", &alice, "")).await.status, 403);
+        let revoked = state.handle(&request("POST", "/admin/users/alice/revoke", &admin, "")).await;
+        assert!(revoked.body.contains(r#""revoked":1"#));
+        assert_eq!(state.handle(&request("GET", "/me", &alice, "")).await.status, 401);
+    }
+}
@@ -0,0 +1,316 @@
+//! Session templates
+//!
+//! A template under `.picode/sessions/templates/<name>.yaml` describes a
+//! ready-made workspace for a recurring workflow: the pane layout, the model
+//! of each chat pane, files pinned into the context and a starter prompt.
+//!
+//! ```yaml
+//! description: Review the current branch
+//! layout: vertical
+//! panes:
+//!   - type: chat
+//!     title: reviewer
+//!     model: claude-3-5-sonnet
+//!     system_prompt: You are a strict code reviewer.
+//!   - type: terminal
+//!     title: shell
+//!     cwd: active_pane
+//!     env: [PATH, HOME, LANG]
+//!     set_env:
+//!       RUST_LOG: debug
+//! pinned:
+//!   - CONTRIBUTING.md
+//! preset: precise
+//! starter_prompt: Review the diff against main.
+//! ```
+//!
+//! Terminal panes follow `[workspace.panes]` for their directory and the
+//! parent environment variables they see, unless the template overrides
+//! them: `cwd` is `workspace_root`, `active_pane` (the pane created just
+//! before) or a path relative to the workspace root, and `env` replaces
+//! the allowlist.
+//!
+//! `picode workspace --template <name>` boots interactive mode from it.
+
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use picode_core::conversation::PinnedItem;
+use picode_core::{CwdRule, InheritRules, Pane};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Directory, under the sessions directory, holding templates
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Template file extensions, in lookup order
+const EXTENSIONS: &[&str] = &["yaml", "yml"];
+
+/// Directory holding the templates of a workspace
+pub fn templates_dir(workspace_root: &Path) -> PathBuf {
+    workspace_root
+        .join(crate::defaults::CONFIG_DIR)
+        .join(crate::defaults::SESSIONS_DIR)
+        .join(TEMPLATES_DIR)
+}
+
+/// One pane a template creates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaneTemplate {
+    /// LLM chat; provider and model default to the configured ones
+    Chat {
+        title: Option<String>,
+        provider: Option<String>,
+        model: Option<String>,
+        system_prompt: Option<String>,
+    },
+    /// Shell; directory and environment default to `[workspace.panes]`
+    Terminal {
+        title: Option<String>,
+        shell: Option<String>,
+        cwd: Option<CwdRule>,
+        /// Parent environment variables passed on, replacing the allowlist
+        env: Option<Vec<String>>,
+        #[serde(default)]
+        set_env: BTreeMap<String, String>,
+    },
+    /// File opened in the editor
+    Editor { title: Option<String>, file: PathBuf },
+    /// Output display
+    Output { title: Option<String>, content_type: String },
+}
+
+/// A session template file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionTemplate {
+    /// File stem the template was loaded from
+    #[serde(skip)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Layout preference handed to interactive mode
+    #[serde(default)]
+    pub layout: Option<String>,
+    #[serde(default)]
+    pub panes: Vec<PaneTemplate>,
+    /// Files, relative to the workspace root, pinned into every request
+    #[serde(default)]
+    pub pinned: Vec<PathBuf>,
+    /// Generation preset of the chat (see [`crate::presets`])
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// First message of the conversation
+    #[serde(default)]
+    pub starter_prompt: Option<String>,
+}
+
+/// A template turned into panes and context
+#[derive(Debug, Clone)]
+pub struct TemplateSession {
+    pub layout: Option<String>,
+    /// The panes in template order; the first chat pane is the active one
+    pub panes: Vec<Pane>,
+    pub pinned: Vec<PinnedItem>,
+    pub preset: Option<String>,
+    pub starter_prompt: Option<String>,
+}
+
+impl TemplateSession {
+    /// The pane conversations go to
+    pub fn chat_pane(&self) -> Option<&Pane> {
+        self.panes.iter().find(|pane| pane.llm_model().is_some())
+    }
+}
+
+impl SessionTemplate {
+    pub fn parse(name: &str, content: &str) -> Result<Self> {
+        let mut template: Self = serde_yaml::from_str(content)?;
+        template.name = name.to_string();
+        Ok(template)
+    }
+
+    /// Load `<name>.yaml` (or `.yml`) from the workspace's templates
+    pub async fn load(workspace_root: &Path, name: &str) -> Result<Self> {
+        let dir = templates_dir(workspace_root);
+        for extension in EXTENSIONS {
+            let path = dir.join(format!("{}.{}", name, extension));
+            if tokio::fs::try_exists(&path).await? {
+                let content = tokio::fs::read_to_string(&path).await?;
+                return Self::parse(name, &content);
+            }
+        }
+        let available = list(workspace_root).await?;
+        Err(PiCodeError::NotFound(if available.is_empty() {
+            format!("session template '{}' (no templates in {})", name, dir.display())
+        } else {
+            format!("session template '{}' (available: {})", name, available.join(", "))
+        }))
+    }
+
+    /// Create the panes and read the pinned files. A template without a
+    /// chat pane gets one with the configured model, so there is always
+    /// somewhere to talk to.
+    pub async fn instantiate(&self, workspace_root: &Path, config: &Config) -> Result<TemplateSession> {
+        let mut panes: Vec<Pane> = Vec::with_capacity(self.panes.len() + 1);
+        for (index, pane) in self.panes.iter().enumerate() {
+            let pane = build_pane(pane, index, workspace_root, panes.last(), config);
+            panes.push(pane);
+        }
+        if !panes.iter().any(|pane| pane.llm_model().is_some()) {
+            let chat = PaneTemplate::Chat { title: None, provider: None, model: None, system_prompt: None };
+            panes.insert(0, build_pane(&chat, 0, workspace_root, None, config));
+        }
+
+        let mut pinned = Vec::with_capacity(self.pinned.len());
+        for path in &self.pinned {
+            let content = tokio::fs::read_to_string(workspace_root.join(path))
+                .await
+                .map_err(|e| PiCodeError::NotFound(format!("pinned file {}: {}", path.display(), e)))?;
+            pinned.push(PinnedItem::new(path.display().to_string(), content));
+        }
+
+        Ok(TemplateSession {
+            layout: self.layout.clone(),
+            panes,
+            pinned,
+            preset: self.preset.clone(),
+            starter_prompt: self.starter_prompt.clone(),
+        })
+    }
+}
+
+/// `active` is the pane created before, which `cwd: active_pane` follows
+fn build_pane(template: &PaneTemplate, index: usize, workspace_root: &Path, active: Option<&Pane>, config: &Config) -> Pane {
+    let title = |title: &Option<String>, kind: &str| title.clone().unwrap_or_else(|| format!("{}-{}", kind, index + 1));
+    match template {
+        PaneTemplate::Chat { title: t, provider, model, system_prompt } => {
+            let provider = provider.clone().unwrap_or_else(|| config.llm.default_provider.clone());
+            let model = model.clone().unwrap_or_else(|| {
+                config
+                    .llm
+                    .providers
+                    .get(&provider)
+                    .and_then(|p| p.default_model.clone())
+                    .unwrap_or_else(|| config.llm.default_model.clone())
+            });
+            let mut pane = Pane::new_llm_chat(provider, model, title(t, "chat"));
+            if let picode_core::PaneType::LLMChat { system_prompt: slot, .. } = &mut pane.pane_type {
+                *slot = system_prompt.clone();
+            }
+            pane
+        }
+        PaneTemplate::Terminal { title: t, shell, cwd, env, set_env } => {
+            let shell = shell
+                .clone()
+                .or_else(|| std::env::var("SHELL").ok())
+                .unwrap_or_else(|| "sh".to_string());
+            let mut rules: InheritRules = config.workspace.panes.clone();
+            if let Some(cwd) = cwd {
+                rules.cwd = cwd.clone();
+            }
+            if let Some(env) = env {
+                rules.env = env.clone();
+            }
+            rules.set_env.extend(set_env.iter().map(|(name, value)| (name.clone(), value.clone())));
+            Pane::new_terminal(shell, rules.working_dir(workspace_root, active), title(t, "terminal"))
+                .with_env(rules.environment(std::env::vars()))
+        }
+        PaneTemplate::Editor { title: t, file } => Pane::new_editor(workspace_root.join(file), title(t, "editor")),
+        PaneTemplate::Output { title: t, content_type } => Pane::new_output(content_type.clone(), title(t, "output")),
+    }
+}
+
+/// Names of the workspace's templates, sorted
+pub async fn list(workspace_root: &Path) -> Result<Vec<String>> {
+    let dir = templates_dir(workspace_root);
+    let mut names = Vec::new();
+    if !tokio::fs::try_exists(&dir).await? {
+        return Ok(names);
+    }
+    let mut entries = tokio::fs::read_dir(&dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_template = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| EXTENSIONS.contains(&extension));
+        if let (true, Some(stem)) = (is_template, path.file_stem().and_then(|stem| stem.to_str())) {
+            names.push(stem.to_string());
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REVIEW: &str = r#"
+description: Review the current branch
+layout: vertical
+panes:
+  - type: terminal
+    title: shell
+    cwd: src
+  - type: terminal
+    title: logs
+    cwd: active_pane
+    env: [PATH]
+    set_env:
+      RUST_LOG: debug
+  - type: chat
+    title: reviewer
+    provider: anthropic
+    model: claude-3-5-sonnet
+    system_prompt: You are a strict code reviewer.
+pinned:
+  - CONTRIBUTING.md
+starter_prompt: Review the diff against main.
+"#;
+
+    #[tokio::test]
+    async fn template_loads_and_instantiates() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = templates_dir(root.path());
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(dir.join("review-mode.yaml"), REVIEW).await.unwrap();
+        tokio::fs::write(root.path().join("CONTRIBUTING.md"), "Keep commits small.").await.unwrap();
+
+        assert_eq!(list(root.path()).await.unwrap(), vec!["review-mode"]);
+        let template = SessionTemplate::load(root.path(), "review-mode").await.unwrap();
+        assert_eq!(template.name, "review-mode");
+
+        let session = template.instantiate(root.path(), &Config::default()).await.unwrap();
+        assert_eq!(session.layout.as_deref(), Some("vertical"));
+        assert_eq!(session.panes.len(), 3);
+        assert_eq!(session.panes[0].get_working_dir(), Some(root.path().join("src")));
+        assert_eq!(session.panes[1].get_working_dir(), Some(root.path().join("src")));
+        let env = session.panes[1].env.as_ref().unwrap();
+        assert_eq!(env.get("RUST_LOG").map(String::as_str), Some("debug"));
+        assert!(env.keys().all(|name| name == "PATH" || name == "RUST_LOG"));
+        let chat = session.chat_pane().unwrap();
+        assert_eq!(chat.title, "reviewer");
+        assert_eq!(chat.llm_model(), Some(("anthropic", "claude-3-5-sonnet")));
+        assert_eq!(chat.system_prompt_override(), Some("You are a strict code reviewer."));
+        assert_eq!(session.pinned[0].content, "Keep commits small.");
+        assert_eq!(session.starter_prompt.as_deref(), Some("Review the diff against main."));
+
+        let err = SessionTemplate::load(root.path(), "missing").await.unwrap_err();
+        assert!(err.to_string().contains("available: review-mode"));
+    }
+
+    #[tokio::test]
+    async fn template_without_chat_pane_gets_default_one() {
+        let root = tempfile::tempdir().unwrap();
+        let template = SessionTemplate::parse("shell", "panes:\n  - type: terminal\n").unwrap();
+        let session = template.instantiate(root.path(), &Config::default()).await.unwrap();
+
+        let config = Config::default();
+        assert_eq!(session.panes.len(), 2);
+        assert_eq!(session.chat_pane().unwrap().llm_model().unwrap().0, config.llm.default_provider);
+        assert!(SessionTemplate::parse("bad", "panes:\n  - type: window\n").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Slash command metadata
+//!
+//! The [`SlashCommandRegistry`] describes every slash command of interactive
+//! mode: its usage line, a one-line summary and longer inline help. The
+//! startup banner, `/help` and the command palette all render from it, so a
+//! command documented here is discoverable everywhere. Summaries and help
+//! text are localized messages (see [`crate::i18n`]).
+
+use crate::i18n::Localizer;
+
+/// Description of one slash command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlashCommand {
+    /// Name without the leading slash
+    pub name: &'static str,
+    /// Arguments shown after the name, empty if there are none
+    pub usage: &'static str,
+    pub summary: String,
+    /// Longer help shown by `/help <name>` and in the palette
+    pub help: String,
+}
+
+impl SlashCommand {
+    /// Text the user types to invoke the command
+    pub fn invocation(&self) -> String {
+        format!("/{}", self.name)
+    }
+
+    /// `/name usage`
+    pub fn synopsis(&self) -> String {
+        if self.usage.is_empty() {
+            self.invocation()
+        } else {
+            format!("/{} {}", self.name, self.usage)
+        }
+    }
+}
+
+/// Name and usage of every built-in slash command, in help order
+const BUILTIN: &[(&str, &str)] = &[
+    ("help", "[command]"),
+    ("palette", ""),
+    ("analyze", ""),
+    ("todos", "[all | done <id> [note]]"),
+    ("edit", "[--chat] <path>"),
+    ("open", "<path[:line[:col]]>"),
+    ("timeline", "[<file> [diff <a> [<b>] | restore <n>]]"),
+    ("run", "[--no-attach] <cmd> | clear"),
+    ("queue", "[send | clear]"),
+    ("broadcast", "[all | off | <pane>...]"),
+    ("raw", ""),
+    ("context", "show | pin <path>[:<start>-<end> | #<symbol>] | drop <n>[,<n>...] | refresh"),
+    ("pack", "[list | <name> | drop <name>]"),
+    ("tag", "<tag>... | rm <tag>..."),
+    ("note", "<text>"),
+    ("retry", ""),
+    ("preset", "[list | <name>]"),
+    ("image", "<path>"),
+    ("bookmark", "add <path[:line]> [label] | list | find <query> | rm <location>"),
+    ("files", "[dir]"),
+    ("model", "list | refresh | <n|provider/model>"),
+    ("health", "[check]"),
+    ("system", "show | pane <text> | pane clear"),
+    ("exit", ""),
+];
+
+/// A key binding of interactive mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keybinding {
+    pub keys: &'static str,
+    pub description: &'static str,
+}
+
+/// Key bindings listed in the command palette
+pub const KEYBINDINGS: &[Keybinding] = &[
+    Keybinding { keys: "Ctrl-P", description: "Open the command palette" },
+    Keybinding { keys: "Up/Down", description: "Move the palette selection" },
+    Keybinding { keys: "Enter", description: "Run the selected palette entry" },
+    Keybinding { keys: "Esc", description: "Close the palette" },
+    Keybinding { keys: "Ctrl-R", description: "Refresh pinned files that changed on disk" },
+    Keybinding { keys: "i / Esc", description: "Editor: enter insert mode / back to normal mode" },
+    Keybinding { keys: ":w / :q", description: "Editor: save / quit" },
+    Keybinding { keys: "Ctrl-W", description: "Linked editor: switch between editor and chat" },
+    Keybinding { keys: "Ctrl-A", description: "Linked editor: apply the next edit proposed in chat" },
+    Keybinding { keys: "Ctrl-T", description: "Linked editor: send the whole file or only visible lines" },
+];
+
+/// All slash commands of interactive mode
+#[derive(Debug, Clone)]
+pub struct SlashCommandRegistry {
+    commands: Vec<SlashCommand>,
+}
+
+impl Default for SlashCommandRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl SlashCommandRegistry {
+    /// The commands interactive mode understands, described in the
+    /// process-wide locale
+    pub fn builtin() -> Self {
+        Self::localized(crate::i18n::localizer())
+    }
+
+    /// The built-in commands with summaries and help from `localizer`
+    /// (`slash-<name>-summary` and `slash-<name>-help`)
+    pub fn localized(localizer: &Localizer) -> Self {
+        let commands = BUILTIN
+            .iter()
+            .map(|&(name, usage)| SlashCommand {
+                name,
+                usage,
+                summary: localizer.message(&format!("slash-{}-summary", name), &[]),
+                help: localizer.message(&format!("slash-{}-help", name), &[]),
+            })
+            .collect();
+        Self { commands }
+    }
+
+    pub fn commands(&self) -> &[SlashCommand] {
+        &self.commands
+    }
+
+    /// Look up a command by name, with or without the leading slash
+    pub fn get(&self, name: &str) -> Option<&SlashCommand> {
+        let name = name.trim_start_matches('/');
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    /// One line per command: synopsis and summary
+    pub fn help_lines(&self) -> Vec<String> {
+        let width = self.commands.iter().map(|c| c.name.len() + 1).max().unwrap_or(0);
+        self.commands
+            .iter()
+            .map(|command| format!("  {:<width$} - {}", command.invocation(), command.summary, width = width))
+            .collect()
+    }
+
+    /// Full help for one command
+    pub fn help_for(&self, name: &str) -> Option<String> {
+        self.get(name)
+            .map(|command| format!("{}\n  {}\n\n  {}", command.synopsis(), command.summary, command.help))
+    }
+}
+
+/// Score how well `query` fuzzy-matches `candidate`: every query character
+/// must appear in order (case-insensitively). Higher is better; consecutive
+/// matches and matches at word starts score extra. `None` means no match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        let index = (position..candidate.len()).find(|&i| candidate[i].to_lowercase().eq(std::iter::once(wanted)))?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(index);
+        position = index + 1;
+    }
+    // Prefer shorter candidates when the match is otherwise equal
+    Some(score * 100 - candidate.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_lookup_and_help() {
+        let registry = SlashCommandRegistry::builtin();
+        assert_eq!(registry.get("/edit").unwrap().usage, "[--chat] <path>");
+        assert!(registry.get("missing").is_none());
+        assert_eq!(registry.help_lines().len(), registry.commands().len());
+        assert!(registry.help_for("model").unwrap().starts_with("/model list | refresh"));
+
+        let german = Localizer::english().with_messages("slash-exit-summary = Beenden").unwrap();
+        let registry = SlashCommandRegistry::localized(&german);
+        assert_eq!(registry.get("exit").unwrap().summary, "Beenden");
+        assert_eq!(registry.get("raw").unwrap().summary, "Toggle raw view of escape sequences in output");
+    }
+
+    #[test]
+    fn fuzzy_score_ranks_prefix_and_consecutive_matches() {
+        assert!(fuzzy_score("xyz", "/model").is_none());
+        assert!(fuzzy_score("mdl", "/model").is_some());
+        assert!(fuzzy_score("mod", "/model").unwrap() > fuzzy_score("mdl", "/model").unwrap());
+        assert!(fuzzy_score("bm", "/bookmark").is_some());
+        assert!(fuzzy_score("ed", "/edit").unwrap() > fuzzy_score("ed", "Enter runs the selected entry").unwrap());
+    }
+}
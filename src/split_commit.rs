@@ -0,0 +1,603 @@
+//! `picode git split-commit`
+//!
+//! Splits everything changed since `HEAD`, untracked files included, into a
+//! series of logical commits. The diff is cut into hunks, and related hunks
+//! are clustered: hunks close together in one file or in the same function,
+//! hunks that use a symbol another hunk defines, and lockfiles with their
+//! manifest. With `--judge` the model groups the hunks instead, using the
+//! clusters as a hint. Each proposed commit gets a message from the model,
+//! or a generic one without a model.
+//!
+//! Proposed commits are then made one by one, each confirmed, edited or
+//! skipped: the index is reset and each commit's hunks are staged with
+//! `git apply --cached` before committing, so skipped hunks stay in the
+//! working tree. New, deleted, renamed and binary files always go into a
+//! single commit.
+
+use crate::assistant::Assistant;
+use crate::config::Config;
+use crate::diff::ParsedDiff;
+use crate::error::{PiCodeError, Result};
+use crate::git::{git, git_with_input};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Hunks of one file at most this many lines apart belong together
+const PROXIMITY_LINES: usize = 20;
+
+/// Hunk text shown to the model per hunk, and for the whole prompt
+const HUNK_CHARS: usize = 1500;
+const PROMPT_CHARS: usize = 40_000;
+
+/// Lockfiles and the manifest they are generated from
+const LOCKFILES: &[(&str, &str)] = &[
+    ("Cargo.lock", "Cargo.toml"),
+    ("package-lock.json", "package.json"),
+    ("yarn.lock", "package.json"),
+    ("pnpm-lock.yaml", "package.json"),
+    ("poetry.lock", "pyproject.toml"),
+    ("go.sum", "go.mod"),
+];
+
+/// Symbol names too common to relate hunks by
+const COMMON_SYMBOLS: &[&str] = &["main", "test", "tests", "default", "from", "into", "init", "setup"];
+
+const GROUP_SYSTEM: &str = "You split a working tree diff into a series of logical commits. Each \
+    commit should hold one coherent change and build on the previous ones. For every commit reply \
+    with a line `COMMIT: <message>` (imperative mood, at most 72 characters) followed by a line \
+    `HUNKS: <comma-separated hunk numbers>`. Use every hunk exactly once.";
+
+const MESSAGE_SYSTEM: &str = "You write git commit messages. For every numbered group of hunks, \
+    reply with one line `<number>: <message>`: a summary in imperative mood of at most 72 \
+    characters describing what the change does.";
+
+/// One hunk of the diff, or a whole file change without hunks (binary
+/// files, pure renames and mode changes)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    pub path: String,
+    /// The file's `diff --git` line and extended headers
+    pub header: String,
+    /// `@@` line and body; empty for changes without hunks
+    pub text: String,
+    pub old_start: usize,
+    pub old_len: usize,
+    /// Function or section named in the `@@` line
+    pub heading: Option<String>,
+}
+
+impl Hunk {
+    /// Whether the file's hunks can only be committed together
+    fn whole_file(&self) -> bool {
+        self.text.is_empty()
+            || ["new file mode", "deleted file mode", "rename from", "copy from", "Binary files"]
+                .iter()
+                .any(|marker| self.header.contains(marker))
+    }
+
+    fn changed_lines(&self) -> impl Iterator<Item = &str> {
+        self.text
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.strip_prefix('+').or_else(|| line.strip_prefix('-')))
+    }
+
+    /// Added and removed line counts
+    fn stats(&self) -> (usize, usize) {
+        let added = self.text.lines().skip(1).filter(|line| line.starts_with('+')).count();
+        let removed = self.text.lines().skip(1).filter(|line| line.starts_with('-')).count();
+        (added, removed)
+    }
+
+    /// Symbols declared or removed by the changed lines
+    fn definitions(&self) -> BTreeSet<String> {
+        self.changed_lines()
+            .flat_map(|line| definition_pattern().captures_iter(line))
+            .map(|captures| captures[2].to_string())
+            .filter(|name| name.len() >= 4 && !COMMON_SYMBOLS.contains(&name.as_str()))
+            .collect()
+    }
+
+    fn identifiers(&self) -> BTreeSet<&str> {
+        self.changed_lines()
+            .flat_map(|line| line.split(|c: char| !(c.is_alphanumeric() || c == '_')))
+            .filter(|word| word.len() >= 4)
+            .collect()
+    }
+
+    fn label(&self) -> String {
+        let (added, removed) = self.stats();
+        match &self.heading {
+            Some(heading) => format!("{} {} (+{} -{})", self.path, heading, added, removed),
+            None if self.text.is_empty() => format!("{} (file change)", self.path),
+            None => format!("{} (+{} -{})", self.path, added, removed),
+        }
+    }
+}
+
+/// `fn name`, `class Name` and similar declarations
+fn definition_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\b(fn|struct|enum|trait|type|impl|mod|def|class|function|func|interface)\s+([A-Za-z_][A-Za-z0-9_]*)")
+            .expect("built-in definition pattern is valid")
+    })
+}
+
+/// Old start, old length and heading of a `@@ -a,b +c,d @@ heading` line
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, Option<String>)> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (range, rest) = rest.split_once(' ')?;
+    let (start, len) = match range.split_once(',') {
+        Some((start, len)) => (start.parse().ok()?, len.parse().ok()?),
+        None => (range.parse().ok()?, 1),
+    };
+    let heading = rest
+        .split_once("@@")
+        .map(|(_, heading)| heading.trim())
+        .filter(|heading| !heading.is_empty())
+        .map(|heading| match definition_pattern().captures(heading) {
+            Some(captures) => captures[2].to_string(),
+            None => heading.to_string(),
+        });
+    Some((start, len, heading))
+}
+
+/// The hunks of a diff, file by file
+pub fn hunks_of(diff: &ParsedDiff) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    for file in &diff.files {
+        if file.hunks.is_empty() {
+            hunks.push(Hunk {
+                path: file.path.clone(),
+                header: file.header.clone(),
+                text: String::new(),
+                old_start: 0,
+                old_len: 0,
+                heading: None,
+            });
+        }
+        for text in &file.hunks {
+            let (old_start, old_len, heading) =
+                parse_hunk_header(text.lines().next().unwrap_or_default()).unwrap_or((0, 0, None));
+            hunks.push(Hunk {
+                path: file.path.clone(),
+                header: file.header.clone(),
+                text: text.clone(),
+                old_start,
+                old_len,
+                heading,
+            });
+        }
+    }
+    hunks
+}
+
+fn near(a: &Hunk, b: &Hunk) -> bool {
+    let (first, second) = if a.old_start <= b.old_start { (a, b) } else { (b, a) };
+    second.old_start.saturating_sub(first.old_start + first.old_len) <= PROXIMITY_LINES
+}
+
+fn is_lockfile_of(lockfile: &str, manifest: &str) -> bool {
+    let lockfile = Path::new(lockfile);
+    LOCKFILES.iter().any(|(lock, manifest_name)| {
+        lockfile.file_name().is_some_and(|name| name == *lock) && lockfile.with_file_name(manifest_name) == Path::new(manifest)
+    })
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Cluster related hunks heuristically; groups are hunk indices, ordered by
+/// their first hunk
+pub fn cluster(hunks: &[Hunk]) -> Vec<Vec<usize>> {
+    let definitions: Vec<BTreeSet<String>> = hunks.iter().map(Hunk::definitions).collect();
+    let identifiers: Vec<BTreeSet<&str>> = hunks.iter().map(Hunk::identifiers).collect();
+    let uses = |a: usize, b: usize| definitions[a].iter().any(|name| identifiers[b].contains(name.as_str()));
+
+    let mut parent: Vec<usize> = (0..hunks.len()).collect();
+    for (a, x) in hunks.iter().enumerate() {
+        for (b, y) in hunks.iter().enumerate().skip(a + 1) {
+            let same_file = x.path == y.path
+                && (x.whole_file() || near(x, y) || (x.heading.is_some() && x.heading == y.heading));
+            let related = same_file
+                || uses(a, b)
+                || uses(b, a)
+                || is_lockfile_of(&x.path, &y.path)
+                || is_lockfile_of(&y.path, &x.path);
+            if related {
+                let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..hunks.len() {
+        groups.entry(find(&mut parent, i)).or_default().push(i);
+    }
+    groups.into_values().collect()
+}
+
+/// A commit of the plan
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProposedCommit {
+    pub message: String,
+    /// Indices into the hunks, in diff order
+    pub hunks: Vec<usize>,
+}
+
+/// Generic message for a group of hunks
+fn default_message(hunks: &[Hunk], ids: &[usize]) -> String {
+    let paths: BTreeSet<&str> = ids.iter().map(|&i| hunks[i].path.as_str()).collect();
+    if let [path] = paths.iter().collect::<Vec<_>>()[..] {
+        let headings: BTreeSet<&str> = ids.iter().filter_map(|&i| hunks[i].heading.as_deref()).collect();
+        return match headings.iter().collect::<Vec<_>>()[..] {
+            [heading] => format!("Update {} in {}", heading, path),
+            _ => format!("Update {}", path),
+        };
+    }
+    let mut common: Option<&Path> = None;
+    for path in &paths {
+        let parent = Path::new(path).parent().unwrap_or(Path::new(""));
+        common = Some(match common {
+            None => parent,
+            Some(common) => common.ancestors().find(|ancestor| parent.starts_with(ancestor)).unwrap_or(Path::new("")),
+        });
+    }
+    match common.filter(|common| !common.as_os_str().is_empty()) {
+        Some(dir) => format!("Update {} files in {}", paths.len(), picode_core::paths::to_slash(dir)),
+        None => format!("Update {} files", paths.len()),
+    }
+}
+
+/// Move every hunk of a file that must be committed whole into the commit
+/// holding its first hunk, dropping commits left empty
+fn keep_files_whole(commits: &mut Vec<ProposedCommit>, hunks: &[Hunk]) {
+    let mut owner: BTreeMap<&str, usize> = BTreeMap::new();
+    for (index, commit) in commits.iter().enumerate() {
+        for &hunk in commit.hunks.iter().filter(|&&hunk| hunks[hunk].whole_file()) {
+            owner.entry(hunks[hunk].path.as_str()).or_insert(index);
+        }
+    }
+    let mut moved = Vec::new();
+    for (index, commit) in commits.iter_mut().enumerate() {
+        commit.hunks.retain(|&hunk| match owner.get(hunks[hunk].path.as_str()) {
+            Some(&target) if target != index => {
+                moved.push((target, hunk));
+                false
+            }
+            _ => true,
+        });
+    }
+    for (target, hunk) in moved {
+        commits[target].hunks.push(hunk);
+    }
+    for commit in commits.iter_mut() {
+        commit.hunks.sort_unstable();
+    }
+    commits.retain(|commit| !commit.hunks.is_empty());
+}
+
+fn strip_label<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+    line.get(..label.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(label))
+        .map(|_| line[label.len()..].trim())
+}
+
+/// Parse `COMMIT:` / `HUNKS:` pairs; hunk numbers are 1-based and each
+/// hunk is only taken once
+fn parse_plan(reply: &str, count: usize) -> Vec<ProposedCommit> {
+    let mut commits = Vec::new();
+    let mut taken = BTreeSet::new();
+    let mut message: Option<String> = None;
+    for line in reply.lines() {
+        let line = line.trim().trim_start_matches(|c: char| c == '-' || c == '*' || c.is_whitespace());
+        if let Some(text) = strip_label(line, "COMMIT:") {
+            message = Some(text.trim_matches(|c: char| c == '`' || c == '*' || c.is_whitespace()).to_string());
+        } else if let Some(list) = strip_label(line, "HUNKS:") {
+            let ids: Vec<usize> = list
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter_map(|id| id.trim_matches(|c: char| !c.is_ascii_digit()).parse::<usize>().ok())
+                .filter(|&id| (1..=count).contains(&id) && taken.insert(id))
+                .map(|id| id - 1)
+                .collect();
+            if let Some(message) = message.take().filter(|_| !ids.is_empty()) {
+                commits.push(ProposedCommit { message, hunks: ids });
+            }
+        }
+    }
+    commits
+}
+
+/// Parse `<number>: <message>` lines
+fn parse_messages(reply: &str) -> BTreeMap<usize, String> {
+    reply
+        .lines()
+        .filter_map(|line| {
+            let (number, message) = line.trim().split_once([':', '.'])?;
+            let number = number.trim().trim_start_matches('#').parse().ok()?;
+            let message = message.trim().trim_matches('`').trim();
+            (!message.is_empty()).then(|| (number, message.to_string()))
+        })
+        .collect()
+}
+
+/// Hunks numbered for the model, within the prompt budget
+fn describe(hunks: &[Hunk], ids: &[usize], budget: &mut usize) -> String {
+    let mut out = String::new();
+    for &id in ids {
+        let _ = writeln!(out, "[{}] {}", id + 1, hunks[id].label());
+        let shown = HUNK_CHARS.min(*budget);
+        if shown > 0 {
+            let mut end = hunks[id].text.len().min(shown);
+            while !hunks[id].text.is_char_boundary(end) {
+                end -= 1;
+            }
+            let _ = writeln!(out, "{}", &hunks[id].text[..end]);
+            *budget -= end;
+        }
+    }
+    out
+}
+
+/// Propose commits for `hunks`, letting the model group them with `judge`
+/// and name them whenever it is available
+async fn propose(hunks: &[Hunk], assistant: Option<&Assistant>, judge: bool) -> Vec<ProposedCommit> {
+    let clusters = cluster(hunks);
+    let mut commits = Vec::new();
+    if let Some(assistant) = assistant.filter(|_| judge) {
+        let mut budget = PROMPT_CHARS;
+        let all: Vec<usize> = (0..hunks.len()).collect();
+        let mut prompt = format!("Hunks:\n{}\nSuggested grouping by proximity and symbols:\n", describe(hunks, &all, &mut budget));
+        for cluster in &clusters {
+            let ids: Vec<String> = cluster.iter().map(|id| (id + 1).to_string()).collect();
+            let _ = writeln!(prompt, "- {}", ids.join(", "));
+        }
+        match assistant.ask(GROUP_SYSTEM, &prompt, Some(1500)).await {
+            Ok(reply) => commits = parse_plan(&reply, hunks.len()),
+            Err(e) => warn!("Grouping by the model failed, using heuristics: {}", e),
+        }
+    }
+
+    // Hunks the model left out keep their heuristic clusters
+    let planned: BTreeSet<usize> = commits.iter().flat_map(|commit| commit.hunks.iter().copied()).collect();
+    let unnamed_from = commits.len();
+    for cluster in clusters {
+        let rest: Vec<usize> = cluster.into_iter().filter(|id| !planned.contains(id)).collect();
+        if !rest.is_empty() {
+            commits.push(ProposedCommit { message: default_message(hunks, &rest), hunks: rest });
+        }
+    }
+
+    if let Some(assistant) = assistant.filter(|_| commits.len() > unnamed_from) {
+        let mut budget = PROMPT_CHARS;
+        let mut prompt = String::new();
+        for (number, commit) in commits.iter().enumerate().skip(unnamed_from) {
+            let _ = writeln!(prompt, "Group {}:\n{}", number + 1, describe(hunks, &commit.hunks, &mut budget));
+        }
+        match assistant.ask(MESSAGE_SYSTEM, &prompt, Some(800)).await {
+            Ok(reply) => {
+                let messages = parse_messages(&reply);
+                for (number, commit) in commits.iter_mut().enumerate().skip(unnamed_from) {
+                    if let Some(message) = messages.get(&(number + 1)) {
+                        commit.message = message.clone();
+                    }
+                }
+            }
+            Err(e) => warn!("Commit messages from the model failed: {}", e),
+        }
+    }
+    keep_files_whole(&mut commits, hunks);
+    commits
+}
+
+/// Patch holding only the given hunks
+fn patch_for(hunks: &[Hunk], ids: &[usize]) -> String {
+    let mut patch = String::new();
+    let mut last_path: Option<&str> = None;
+    for &id in ids {
+        let hunk = &hunks[id];
+        if last_path != Some(hunk.path.as_str()) {
+            patch.push_str(&hunk.header);
+            last_path = Some(&hunk.path);
+        }
+        patch.push_str(&hunk.text);
+    }
+    patch
+}
+
+/// Run `picode git split-commit`
+pub async fn run(config: &Config, judge: bool, dry_run: bool, yes: bool) -> Result<()> {
+    use std::io::IsTerminal;
+
+    if !dry_run && !yes && !std::io::stdin().is_terminal() {
+        return Err(PiCodeError::InvalidCommand(
+            "split-commit asks before each commit; pass --yes or --dry-run when not on a terminal".to_string(),
+        ));
+    }
+    let root = crate::stats::workspace_root(config)?;
+    // Intent-to-add makes untracked files part of the diff
+    let untracked: Vec<String> = git(&root, &["ls-files", "--others", "--exclude-standard"])
+        .await?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if !untracked.is_empty() {
+        let mut args = vec!["add", "--intent-to-add", "--"];
+        args.extend(untracked.iter().map(String::as_str));
+        git(&root, &args).await?;
+    }
+    let outcome = split(config, &root, judge, dry_run, yes).await;
+    if !untracked.is_empty() {
+        let mut args = vec!["reset", "--quiet", "--"];
+        args.extend(untracked.iter().map(String::as_str));
+        if let Err(e) = git(&root, &args).await {
+            warn!("Could not unstage untracked files: {}", e);
+        }
+    }
+    outcome
+}
+
+async fn split(config: &Config, root: &Path, judge: bool, dry_run: bool, yes: bool) -> Result<()> {
+    let diff = ParsedDiff::parse(&git(root, &["diff", "--no-color", "--no-ext-diff", "--binary", "HEAD"]).await?);
+    let hunks = hunks_of(&diff);
+    if hunks.is_empty() {
+        println!("✅ Nothing to split");
+        return Ok(());
+    }
+    let assistant = match Assistant::from_config(config) {
+        Ok(assistant) => Some(assistant),
+        Err(e) if judge => return Err(e),
+        Err(e) => {
+            warn!("No model for commit messages: {}", e);
+            None
+        }
+    };
+    let commits = propose(&hunks, assistant.as_ref(), judge).await;
+
+    println!("📦 {} hunk(s) in {} file(s) → {} commit(s)", hunks.len(), diff.files.len(), commits.len());
+    for (number, commit) in commits.iter().enumerate() {
+        println!("\n{}. {}", number + 1, commit.message);
+        for &id in &commit.hunks {
+            println!("   {}", hunks[id].label());
+        }
+    }
+    if dry_run {
+        return Ok(());
+    }
+    println!();
+
+    // Staging starts from a clean index; the working tree is left alone
+    git(root, &["reset", "--quiet"]).await?;
+    let (mut made, mut left) = (0, 0);
+    for (number, commit) in commits.iter().enumerate() {
+        let mut message = commit.message.clone();
+        if !yes {
+            let choice = dialoguer::Select::new()
+                .with_prompt(format!("Commit {}/{}: {}", number + 1, commits.len(), message))
+                .items(&["commit", "edit message", "skip", "quit"])
+                .default(0)
+                .interact()?;
+            match choice {
+                1 => {
+                    message = dialoguer::Input::<String>::new()
+                        .with_prompt("Message")
+                        .with_initial_text(message)
+                        .interact_text()?;
+                }
+                2 => {
+                    left += commit.hunks.len();
+                    continue;
+                }
+                3 => {
+                    left += commits[number..].iter().map(|commit| commit.hunks.len()).sum::<usize>();
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let files: BTreeSet<&str> = commit.hunks.iter().map(|&id| hunks[id].path.as_str()).collect();
+        let payload = serde_json::json!({ "repo": "", "files": files, "message": message });
+        if let Err(e) = crate::hook_guard::check(config, picode_hooks::HookEvent::PreCommit, payload, false).await {
+            println!("⏭️  Skipped: {}", e);
+            left += commit.hunks.len();
+            continue;
+        }
+        git_with_input(root, &["apply", "--cached", "--whitespace=nowarn", "-"], Some(&patch_for(&hunks, &commit.hunks)))
+            .await?;
+        git(root, &["commit", "--quiet", "--message", &message]).await?;
+        let sha = git(root, &["rev-parse", "--short", "HEAD"]).await?;
+        println!("✅ {} {}", sha.trim(), message.lines().next().unwrap_or_default());
+        made += 1;
+    }
+    println!("\n{} commit(s) made; {} hunk(s) left in the working tree", made, left);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/auth.rs b/src/auth.rs
+index 1111111..2222222 100644
+--- a/src/auth.rs
++++ b/src/auth.rs
+@@ -10,3 +10,7 @@ impl Session {
+     pub fn token(&self) -> &str {
+         &self.token
+     }
++
++    pub fn refresh_token(&mut self) {
++        self.token = issue();
++    }
+@@ -200,2 +204,2 @@ fn log_level() -> Level {
+-    Level::Info
++    Level::Debug
+diff --git a/src/client.rs b/src/client.rs
+index 3333333..4444444 100644
+--- a/src/client.rs
++++ b/src/client.rs
+@@ -40,2 +40,3 @@ fn send(session: &mut Session) {
+     let response = http::post(session.token());
++    session.refresh_token();
+diff --git a/docs/auth.md b/docs/auth.md
+new file mode 100644
+index 0000000..5555555
+--- /dev/null
++++ b/docs/auth.md
+@@ -0,0 +1 @@
++# Tokens
+";
+
+    #[test]
+    fn clusters_hunks_by_symbol_and_proximity() {
+        let hunks = hunks_of(&ParsedDiff::parse(DIFF));
+        assert_eq!(hunks.len(), 4);
+        assert_eq!(hunks[0].heading.as_deref(), Some("Session"));
+        assert_eq!((hunks[1].old_start, hunks[1].old_len), (200, 2));
+        assert!(hunks[3].whole_file());
+
+        // refresh_token is defined in one hunk and used in another file
+        assert_eq!(cluster(&hunks), vec![vec![0, 2], vec![1], vec![3]]);
+        assert_eq!(default_message(&hunks, &[1]), "Update log_level in src/auth.rs");
+        assert_eq!(default_message(&hunks, &[0, 2]), "Update 2 files in src");
+
+        let patch = patch_for(&hunks, &[0, 2]);
+        assert_eq!(patch.matches("diff --git").count(), 2);
+        assert!(patch.contains("+    pub fn refresh_token") && !patch.contains("Level::Debug"));
+    }
+
+    #[test]
+    fn model_plans_are_validated() {
+        let hunks = hunks_of(&ParsedDiff::parse(DIFF));
+        let reply = "COMMIT: Add token refresh\nHUNKS: 1, 3, 9\n\n- **COMMIT:** Document tokens\nHUNKS: 4, 4, 1\nCOMMIT: Empty\nHUNKS: 7";
+        let mut commits = parse_plan(reply, hunks.len());
+        assert_eq!(
+            commits,
+            vec![
+                ProposedCommit { message: "Add token refresh".to_string(), hunks: vec![0, 2] },
+                ProposedCommit { message: "Document tokens".to_string(), hunks: vec![3] },
+            ]
+        );
+
+        // Commits left empty are dropped
+        commits.push(ProposedCommit { message: "Split".to_string(), hunks: vec![1, 3] });
+        commits[1].hunks = vec![];
+        keep_files_whole(&mut commits, &hunks);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[1].hunks, vec![1, 3]);
+
+        let messages = parse_messages("1: Add token refresh\n#2. `Lower the log level`\nnothing");
+        assert_eq!(messages[&2], "Lower the log level");
+    }
+}
@@ -0,0 +1,47 @@
+//! `picode stats` subcommands
+//!
+//! The workspace fingerprint (lines per language, test/source ratio,
+//! frameworks found in manifests and imports) is computed by scanning the
+//! workspace; the same report backs `/analyze` and the project layer of the
+//! system prompt.
+
+use crate::config::Config;
+use crate::error::Result;
+use picode_core::workspace::{Workspace, WorkspaceConfig};
+use picode_core::WorkspaceStats;
+use std::path::{Path, PathBuf};
+
+/// Handle `picode stats` subcommands
+#[cfg(feature = "cli")]
+pub async fn handle_action(action: picode_cli::StatsAction, config: &Config) -> Result<()> {
+    match action {
+        picode_cli::StatsAction::Workspace { json } => {
+            let stats = workspace_stats(&workspace_root(config)?).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                print!("{}", stats.render());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Scan `root` for its fingerprint; git status is not needed for it
+pub async fn workspace_stats(root: &Path) -> Result<WorkspaceStats> {
+    let mut workspace = Workspace::new(WorkspaceConfig {
+        root_path: root.to_path_buf(),
+        git_enabled: false,
+        ..WorkspaceConfig::default()
+    });
+    workspace.scan().await.map_err(picode_core::CoreError::from)?;
+    Ok(workspace.stats)
+}
+
+/// The configured workspace root, or the current directory
+pub fn workspace_root(config: &Config) -> Result<PathBuf> {
+    Ok(match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    })
+}
@@ -0,0 +1,766 @@
+//! Encrypted sync of sessions, memory and prompt templates
+//!
+//! `picode sync` keeps a workspace's stored sessions and conversations,
+//! bookmarks and prompt templates in step across machines through a
+//! user-provided S3-compatible bucket or WebDAV share. Everything is
+//! encrypted on the client with ChaCha20-Poly1305 under a key derived from
+//! a passphrase (PBKDF2-HMAC-SHA256); the backend only sees a salt, an
+//! encrypted manifest and encrypted objects named by a keyed hash.
+//!
+//! Each side is compared with the state recorded at the last sync, so a file
+//! changed on one machine simply travels to the other. When both changed it,
+//! the local copy wins and the remote one is kept next to it as
+//! `<file>.conflict-<device>` for review. The salt and the first manifest
+//! are only created where none exists yet (`If-None-Match: *`) and the
+//! manifest is replaced with `If-Match`, so two machines syncing at once
+//! cannot lose each other's changes: the second reads what the first stored
+//! and syncs against it.
+
+use crate::config::{Config, ConfigError};
+use crate::error::{PiCodeError, Result};
+use async_trait::async_trait;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use picode_core::bookmark::BOOKMARKS_FILE;
+use picode_llm::{RequestSigner, SigningConfig};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Sync bookkeeping kept under `.picode`
+pub const SYNC_STATE_FILE: &str = "sync-state.json";
+
+/// What is synced, relative to `.picode`
+const SYNCED: &[&str] = &[crate::defaults::SESSIONS_DIR, "prompts", BOOKMARKS_FILE];
+
+/// Marker in the names of conflict copies, which are never synced
+const CONFLICT_MARKER: &str = ".conflict-";
+
+const SALT_KEY: &str = "salt";
+const MANIFEST_KEY: &str = "manifest";
+const OBJECTS_PREFIX: &str = "objects/";
+
+/// Syncs tried when another machine keeps replacing the manifest first
+const SYNC_ATTEMPTS: usize = 3;
+
+/// Leading bytes of every encrypted blob, for format upgrades
+const MAGIC: &[u8] = b"PCS1";
+const NONCE_LEN: usize = 12;
+
+/// PBKDF2 rounds for deriving the key from the passphrase
+pub const KDF_ROUNDS: u32 = 600_000;
+
+/// Where synced data is stored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SyncRemote {
+    /// S3-compatible bucket, path style: `https://host/bucket/prefix`
+    S3 {
+        url: String,
+        /// Region; defaults to `AWS_REGION` / `AWS_DEFAULT_REGION`
+        #[serde(default)]
+        region: Option<String>,
+        /// Profile in the shared credentials file; the environment is used when unset
+        #[serde(default)]
+        profile: Option<String>,
+    },
+    #[serde(rename = "webdav")]
+    WebDav {
+        url: String,
+        #[serde(default)]
+        username_env: Option<String>,
+        #[serde(default)]
+        password_env: Option<String>,
+    },
+}
+
+/// `[sync]` section of the configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub remote: Option<SyncRemote>,
+
+    /// Environment variable holding the encryption passphrase
+    #[serde(default = "default_passphrase_env")]
+    pub passphrase_env: String,
+
+    /// Name of this machine in conflict copies; the host name by default
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+fn default_passphrase_env() -> String {
+    "PICODE_SYNC_PASSPHRASE".to_string()
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            remote: None,
+            passphrase_env: default_passphrase_env(),
+            device: None,
+        }
+    }
+}
+
+impl SyncConfig {
+    pub fn device(&self) -> String {
+        self.device
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .or_else(|| std::env::var("COMPUTERNAME").ok())
+            .unwrap_or_else(|| "device".to_string())
+    }
+}
+
+/// Client-side encryption of everything sent to the backend
+#[derive(Clone)]
+pub struct SyncCipher {
+    key: [u8; 32],
+}
+
+impl fmt::Debug for SyncCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncCipher").finish_non_exhaustive()
+    }
+}
+
+impl SyncCipher {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    pub fn from_passphrase(passphrase: &str, salt: &[u8], rounds: u32) -> Self {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, rounds, &mut key);
+        Self::new(key)
+    }
+
+    /// `MAGIC || nonce || ciphertext`
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let cipher = ChaCha20Poly1305::new((&self.key).into());
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| PiCodeError::Internal("encryption failed".to_string()))?;
+        let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let body = data
+            .strip_prefix(MAGIC)
+            .filter(|body| body.len() >= NONCE_LEN)
+            .ok_or_else(|| PiCodeError::Parse("not a PiCode sync object".to_string()))?;
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        ChaCha20Poly1305::new((&self.key).into())
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| PiCodeError::Auth("cannot decrypt sync data; is the passphrase right?".to_string()))
+    }
+
+    /// Keyed content hash naming an object, so the backend cannot confirm
+    /// guesses about what a file contains
+    pub fn object_id(&self, content: &[u8]) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+        mac.update(content);
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+/// A file as recorded in the remote manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Object holding the content (see [`SyncCipher::object_id`])
+    pub id: String,
+    /// Machine that last changed the file
+    pub device: String,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Every synced file, keyed by its `/`-separated path under `.picode`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    fn live_id(&self, path: &str) -> Option<&String> {
+        self.files.get(path).filter(|entry| !entry.deleted).map(|entry| &entry.id)
+    }
+}
+
+/// Object ids both sides agreed on at the last sync
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncState {
+    pub files: BTreeMap<String, String>,
+}
+
+/// What to do with one path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncAction {
+    Upload(String),
+    Download(String),
+    DeleteLocal(String),
+    DeleteRemote(String),
+    /// Changed on both sides since the last sync
+    Conflict(String),
+}
+
+/// Compare local files and the remote manifest with the last synced state.
+/// A change on one side wins over an unchanged other side; an edit wins
+/// over a deletion.
+pub fn plan(local: &BTreeMap<String, String>, remote: &Manifest, base: &SyncState) -> Vec<SyncAction> {
+    let paths: BTreeSet<&String> = local.keys().chain(remote.files.keys()).chain(base.files.keys()).collect();
+    let mut actions = Vec::new();
+    for path in paths {
+        let (l, r, b) = (local.get(path), remote.live_id(path), base.files.get(path));
+        if l == r {
+            continue;
+        }
+        let action = match (l == b, r == b) {
+            // Only the remote side changed
+            (true, _) if r.is_some() => SyncAction::Download(path.clone()),
+            (true, _) => SyncAction::DeleteLocal(path.clone()),
+            // Only the local side changed
+            (_, true) if l.is_some() => SyncAction::Upload(path.clone()),
+            (_, true) => SyncAction::DeleteRemote(path.clone()),
+            // Both changed
+            _ => match (l, r) {
+                (Some(_), Some(_)) => SyncAction::Conflict(path.clone()),
+                (None, _) => SyncAction::Download(path.clone()),
+                (_, None) => SyncAction::Upload(path.clone()),
+            },
+        };
+        actions.push(action);
+    }
+    actions
+}
+
+/// Outcome of a sync
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    pub actions: Vec<SyncAction>,
+    /// Conflict copies written next to the local files
+    pub conflict_copies: Vec<PathBuf>,
+    pub dry_run: bool,
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.actions.is_empty() {
+            return writeln!(f, "Already in sync");
+        }
+        let verb = if self.dry_run { "Would" } else { "" };
+        for action in &self.actions {
+            let (what, path) = match action {
+                SyncAction::Upload(path) => ("upload", path),
+                SyncAction::Download(path) => ("download", path),
+                SyncAction::DeleteLocal(path) => ("delete locally", path),
+                SyncAction::DeleteRemote(path) => ("delete remotely", path),
+                SyncAction::Conflict(path) => ("resolve conflict in", path),
+            };
+            if verb.is_empty() {
+                writeln!(f, "  {} {}", what, path)?;
+            } else {
+                writeln!(f, "  {} {} {}", verb, what, path)?;
+            }
+        }
+        for copy in &self.conflict_copies {
+            writeln!(f, "⚠️  Changed on both machines; the other version is in {}", copy.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// An object read from the backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteObject {
+    pub bytes: Vec<u8>,
+    pub etag: Option<String>,
+}
+
+/// When a write to the backend may replace what is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition<'a> {
+    Always,
+    /// Only when nothing is stored at the key yet (`If-None-Match: *`)
+    Absent,
+    /// Only when the object still has this ETag (`If-Match`)
+    Matches(&'a str),
+}
+
+/// Storage for encrypted sync objects
+#[async_trait]
+pub trait SyncBackend: Send + Sync {
+    /// The object at `key`, `None` if there is none
+    async fn get(&self, key: &str) -> Result<Option<RemoteObject>>;
+
+    /// Store `bytes` at `key` if `condition` holds; fails with
+    /// [`PiCodeError::Cancelled`] otherwise
+    async fn put(&self, key: &str, bytes: Vec<u8>, condition: Precondition<'_>) -> Result<()>;
+}
+
+enum HttpAuth {
+    Signed(Arc<dyn RequestSigner>),
+    Basic { username: String, password: Option<String> },
+    None,
+}
+
+/// S3 and WebDAV over plain HTTP requests
+pub struct HttpBackend {
+    client: reqwest::Client,
+    base: String,
+    auth: HttpAuth,
+    /// WebDAV needs the objects collection created before the first upload
+    webdav: bool,
+    collection_created: AtomicBool,
+}
+
+impl HttpBackend {
+    pub fn from_remote(remote: &SyncRemote) -> Result<Self> {
+        let (url, auth, webdav) = match remote {
+            SyncRemote::S3 { url, region, profile } => {
+                let signing = SigningConfig::SigV4 {
+                    service: "s3".to_string(),
+                    region: region.clone(),
+                    profile: profile.clone(),
+                };
+                let signer = signing.signer().map_err(|e| PiCodeError::Auth(e.to_string()))?;
+                (url, HttpAuth::Signed(signer), false)
+            }
+            SyncRemote::WebDav { url, username_env, password_env } => {
+                let env = |name: &Option<String>| name.as_ref().and_then(|name| std::env::var(name).ok());
+                let auth = match env(username_env) {
+                    Some(username) => HttpAuth::Basic { username, password: env(password_env) },
+                    None => HttpAuth::None,
+                };
+                (url, auth, true)
+            }
+        };
+        Ok(Self {
+            client: reqwest::Client::new(),
+            base: url.trim_end_matches('/').to_string(),
+            auth,
+            webdav,
+            collection_created: AtomicBool::new(false),
+        })
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+        condition: Precondition<'_>,
+    ) -> Result<reqwest::Response> {
+        let url = reqwest::Url::parse(&format!("{}/{}", self.base, key))
+            .map_err(|e| ConfigError::InvalidConfig(format!("sync url: {}", e)))?;
+        let mut headers = HashMap::new();
+        match condition {
+            Precondition::Always => {}
+            Precondition::Absent => {
+                headers.insert("If-None-Match".to_string(), "*".to_string());
+            }
+            Precondition::Matches(etag) => {
+                headers.insert("If-Match".to_string(), etag.to_string());
+            }
+        }
+        let mut request = self.client.request(method.clone(), url.clone());
+        match &self.auth {
+            HttpAuth::Signed(signer) => {
+                headers.insert("x-amz-content-sha256".to_string(), hex::encode(Sha256::digest(&body)));
+                let signable = picode_llm::signing::SignableRequest {
+                    method: method.as_str(),
+                    url: &url,
+                    headers: &headers,
+                    body: &body,
+                };
+                let signed = signer.sign(&signable, Utc::now()).map_err(|e| PiCodeError::Auth(e.to_string()))?;
+                for (name, value) in signed {
+                    request = request.header(name, value);
+                }
+            }
+            HttpAuth::Basic { username, password } => request = request.basic_auth(username, password.as_ref()),
+            HttpAuth::None => {}
+        }
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        Ok(request.body(body).send().await?)
+    }
+}
+
+#[async_trait]
+impl SyncBackend for HttpBackend {
+    async fn get(&self, key: &str) -> Result<Option<RemoteObject>> {
+        let response = self.send(reqwest::Method::GET, key, Vec::new(), Precondition::Always).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(Some(RemoteObject { bytes: response.bytes().await?.to_vec(), etag }))
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>, condition: Precondition<'_>) -> Result<()> {
+        if self.webdav && key.starts_with(OBJECTS_PREFIX) && !self.collection_created.swap(true, Ordering::Relaxed) {
+            let mkcol = reqwest::Method::from_bytes(b"MKCOL").expect("MKCOL is a valid method");
+            // 405 means the collection already exists
+            let response = self.send(mkcol, OBJECTS_PREFIX, Vec::new(), Precondition::Always).await?;
+            if !response.status().is_success() && response.status() != reqwest::StatusCode::METHOD_NOT_ALLOWED {
+                response.error_for_status()?;
+            }
+        }
+        let response = self.send(reqwest::Method::PUT, key, bytes, condition).await?;
+        // S3 answers a conditional write racing another with 409
+        if matches!(response.status(), reqwest::StatusCode::PRECONDITION_FAILED | reqwest::StatusCode::CONFLICT) {
+            return Err(PiCodeError::Cancelled(
+                "another machine synced at the same time; run `picode sync` again".to_string(),
+            ));
+        }
+        response.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Files under `dir` that are synced, as `/`-separated relative paths
+fn local_files(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    for root in SYNCED {
+        let root = dir.join(root);
+        if !root.exists() {
+            continue;
+        }
+        for entry in walkdir::WalkDir::new(&root) {
+            let entry = entry.map_err(|e| PiCodeError::Internal(e.to_string()))?;
+            if !entry.file_type().is_file() || entry.file_name().to_string_lossy().contains(CONFLICT_MARKER) {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+            let key: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+            files.insert(key.join("/"), std::fs::read(entry.path())?);
+        }
+    }
+    Ok(files)
+}
+
+/// A manifest path as a local path under `dir`, refusing anything that
+/// would escape it
+fn local_path(dir: &Path, path: &str) -> Result<PathBuf> {
+    let relative = Path::new(path);
+    let safe = relative.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+    if !safe || !SYNCED.iter().any(|root| path == *root || path.starts_with(&format!("{}/", root))) {
+        return Err(PiCodeError::Permission(format!("refusing to sync '{}'", path)));
+    }
+    Ok(dir.join(relative))
+}
+
+async fn download(backend: &dyn SyncBackend, cipher: &SyncCipher, entry: &ManifestEntry) -> Result<Vec<u8>> {
+    let object = backend
+        .get(&format!("{}{}", OBJECTS_PREFIX, entry.id))
+        .await?
+        .ok_or_else(|| PiCodeError::NotFound(format!("sync object {}", entry.id)))?;
+    cipher.decrypt(&object.bytes)
+}
+
+async fn write_local(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, content).await?;
+    Ok(())
+}
+
+/// Sync `dir` (a workspace's `.picode` directory) with the backend; when
+/// another machine stores a manifest meanwhile, sync again against it
+pub async fn sync_dir(
+    backend: &dyn SyncBackend,
+    cipher: &SyncCipher,
+    dir: &Path,
+    device: &str,
+    dry_run: bool,
+) -> Result<SyncReport> {
+    let mut attempts = 1;
+    loop {
+        match sync_once(backend, cipher, dir, device, dry_run).await {
+            Err(PiCodeError::Cancelled(_)) if attempts < SYNC_ATTEMPTS => attempts += 1,
+            result => return result,
+        }
+    }
+}
+
+async fn sync_once(
+    backend: &dyn SyncBackend,
+    cipher: &SyncCipher,
+    dir: &Path,
+    device: &str,
+    dry_run: bool,
+) -> Result<SyncReport> {
+    let (mut manifest, etag) = match backend.get(MANIFEST_KEY).await? {
+        Some(object) => (serde_json::from_slice(&cipher.decrypt(&object.bytes)?)?, object.etag),
+        None => (Manifest::default(), None),
+    };
+    let state_path = dir.join(SYNC_STATE_FILE);
+    let base: SyncState = match tokio::fs::read(&state_path).await {
+        Ok(content) => serde_json::from_slice(&content)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => SyncState::default(),
+        Err(e) => return Err(e.into()),
+    };
+    let files = local_files(dir)?;
+    let local: BTreeMap<String, String> = files.iter().map(|(path, content)| (path.clone(), cipher.object_id(content))).collect();
+
+    let mut report = SyncReport {
+        actions: plan(&local, &manifest, &base),
+        dry_run,
+        ..SyncReport::default()
+    };
+    if dry_run || report.actions.is_empty() {
+        return Ok(report);
+    }
+
+    let now = Utc::now();
+    let upload = |path: &String| -> Result<(String, Vec<u8>)> {
+        Ok((local[path].clone(), cipher.encrypt(&files[path])?))
+    };
+    for action in &report.actions {
+        match action {
+            SyncAction::Upload(path) | SyncAction::Conflict(path) => {
+                if let SyncAction::Conflict(_) = action {
+                    let entry = &manifest.files[path];
+                    let copy = local_path(dir, &format!("{}{}{}", path, CONFLICT_MARKER, entry.device))?;
+                    write_local(&copy, &download(backend, cipher, entry).await?).await?;
+                    report.conflict_copies.push(copy);
+                }
+                let (id, encrypted) = upload(path)?;
+                backend.put(&format!("{}{}", OBJECTS_PREFIX, id), encrypted, Precondition::Always).await?;
+                let entry = ManifestEntry { id, device: device.to_string(), updated_at: now, deleted: false };
+                manifest.files.insert(path.clone(), entry);
+            }
+            SyncAction::Download(path) => {
+                let content = download(backend, cipher, &manifest.files[path]).await?;
+                write_local(&local_path(dir, path)?, &content).await?;
+            }
+            SyncAction::DeleteLocal(path) => match tokio::fs::remove_file(local_path(dir, path)?).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            },
+            SyncAction::DeleteRemote(path) => {
+                if let Some(entry) = manifest.files.get_mut(path) {
+                    entry.deleted = true;
+                    entry.device = device.to_string();
+                    entry.updated_at = now;
+                }
+            }
+        }
+    }
+
+    let encrypted = cipher.encrypt(&serde_json::to_vec(&manifest)?)?;
+    let condition = etag.as_deref().map_or(Precondition::Absent, Precondition::Matches);
+    backend.put(MANIFEST_KEY, encrypted, condition).await?;
+    let state = SyncState {
+        files: manifest.files.iter().filter(|(_, e)| !e.deleted).map(|(p, e)| (p.clone(), e.id.clone())).collect(),
+    };
+    tokio::fs::write(&state_path, serde_json::to_vec_pretty(&state)?).await?;
+    Ok(report)
+}
+
+/// The key derivation salt, created on the first sync; when another
+/// machine creates it at the same time, theirs is used
+async fn load_salt(backend: &dyn SyncBackend, dry_run: bool) -> Result<Vec<u8>> {
+    if let Some(object) = backend.get(SALT_KEY).await? {
+        return Ok(object.bytes);
+    }
+    if dry_run {
+        return Err(PiCodeError::NotFound("nothing synced yet".to_string()));
+    }
+    let salt = uuid::Uuid::new_v4().as_bytes().to_vec();
+    match backend.put(SALT_KEY, salt.clone(), Precondition::Absent).await {
+        Ok(()) => Ok(salt),
+        Err(PiCodeError::Cancelled(_)) => backend
+            .get(SALT_KEY)
+            .await?
+            .map(|object| object.bytes)
+            .ok_or_else(|| PiCodeError::NotFound("sync salt".to_string())),
+        Err(e) => Err(e),
+    }
+}
+
+/// Run `picode sync` for the current workspace
+pub async fn run(config: &Config, dry_run: bool) -> Result<()> {
+    let remote = config.sync.remote.as_ref().ok_or_else(|| {
+        ConfigError::InvalidConfig("no [sync] remote configured (see `picode sync --help`)".to_string())
+    })?;
+    let passphrase = std::env::var(&config.sync.passphrase_env)
+        .map_err(|_| PiCodeError::Auth(format!("set {} to the sync passphrase", config.sync.passphrase_env)))?;
+    let backend = HttpBackend::from_remote(remote)?;
+    let cipher = SyncCipher::from_passphrase(&passphrase, &load_salt(&backend, dry_run).await?, KDF_ROUNDS);
+
+    let root = match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    let dir = root.join(crate::defaults::CONFIG_DIR);
+    let report = sync_dir(&backend, &cipher, &dir, &config.sync.device(), dry_run).await?;
+    print!("{}", report);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryBackend {
+        objects: Mutex<HashMap<String, (Vec<u8>, u32)>>,
+    }
+
+    #[async_trait]
+    impl SyncBackend for MemoryBackend {
+        async fn get(&self, key: &str) -> Result<Option<RemoteObject>> {
+            let objects = self.objects.lock().unwrap();
+            Ok(objects.get(key).map(|(bytes, version)| RemoteObject {
+                bytes: bytes.clone(),
+                etag: Some(version.to_string()),
+            }))
+        }
+
+        async fn put(&self, key: &str, bytes: Vec<u8>, condition: Precondition<'_>) -> Result<()> {
+            let mut objects = self.objects.lock().unwrap();
+            let version = objects.get(key).map(|(_, version)| *version);
+            let holds = match condition {
+                Precondition::Always => true,
+                Precondition::Absent => version.is_none(),
+                Precondition::Matches(etag) => version.is_some_and(|version| etag == version.to_string()),
+            };
+            if !holds {
+                return Err(PiCodeError::Cancelled("changed".to_string()));
+            }
+            let version = version.unwrap_or(0);
+            objects.insert(key.to_string(), (bytes, version + 1));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn encrypts_with_authentication() {
+        let cipher = SyncCipher::new([7; 32]);
+        let sealed = cipher.encrypt(b"conversation").unwrap();
+        assert!(!sealed.windows(12).any(|w| w == b"conversation"));
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), b"conversation");
+
+        assert!(matches!(SyncCipher::new([8; 32]).decrypt(&sealed), Err(PiCodeError::Auth(_))));
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher.decrypt(&tampered).is_err());
+        assert_ne!(cipher.object_id(b"a"), SyncCipher::new([8; 32]).object_id(b"a"));
+    }
+
+    #[test]
+    fn plans_against_the_last_synced_state() {
+        let entry = |id: &str| ManifestEntry { id: id.to_string(), device: "desk".to_string(), updated_at: Utc::now(), deleted: false };
+        let map = |pairs: &[(&str, &str)]| pairs.iter().map(|(p, i)| (p.to_string(), i.to_string())).collect::<BTreeMap<_, _>>();
+        let base = SyncState { files: map(&[("same", "1"), ("mine", "1"), ("theirs", "1"), ("both", "1"), ("gone", "1")]) };
+        let local = map(&[("same", "1"), ("mine", "2"), ("theirs", "1"), ("both", "2"), ("new", "1")]);
+        let mut remote = Manifest::default();
+        for (path, id) in [("same", "1"), ("mine", "1"), ("theirs", "3"), ("both", "3"), ("gone", "1")] {
+            remote.files.insert(path.to_string(), entry(id));
+        }
+
+        assert_eq!(
+            plan(&local, &remote, &base),
+            vec![
+                SyncAction::Conflict("both".to_string()),
+                SyncAction::DeleteRemote("gone".to_string()),
+                SyncAction::Upload("mine".to_string()),
+                SyncAction::Upload("new".to_string()),
+                SyncAction::Download("theirs".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn two_machines_converge_and_keep_conflict_copies() {
+        let backend = MemoryBackend::default();
+        let cipher = SyncCipher::new([1; 32]);
+        let (desk, laptop) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let file = "sessions/conversations/a.json";
+        write_local(&desk.path().join(file), b"v1").await.unwrap();
+        write_local(&desk.path().join("other/notes.txt"), b"not synced").await.unwrap();
+
+        sync_dir(&backend, &cipher, desk.path(), "desk", false).await.unwrap();
+        let report = sync_dir(&backend, &cipher, laptop.path(), "laptop", false).await.unwrap();
+        assert_eq!(report.actions, vec![SyncAction::Download(file.to_string())]);
+        assert_eq!(std::fs::read(laptop.path().join(file)).unwrap(), b"v1");
+        assert!(!laptop.path().join("other").exists());
+
+        // Both edit before syncing again: the laptop syncs last and keeps its version
+        std::fs::write(desk.path().join(file), b"desk edit").unwrap();
+        std::fs::write(laptop.path().join(file), b"laptop edit").unwrap();
+        sync_dir(&backend, &cipher, desk.path(), "desk", false).await.unwrap();
+        let report = sync_dir(&backend, &cipher, laptop.path(), "laptop", false).await.unwrap();
+        assert_eq!(report.conflict_copies, vec![laptop.path().join(format!("{}.conflict-desk", file))]);
+        assert_eq!(std::fs::read(&report.conflict_copies[0]).unwrap(), b"desk edit");
+
+        sync_dir(&backend, &cipher, desk.path(), "desk", false).await.unwrap();
+        assert_eq!(std::fs::read(desk.path().join(file)).unwrap(), b"laptop edit");
+        assert!(sync_dir(&backend, &cipher, desk.path(), "desk", true).await.unwrap().actions.is_empty());
+    }
+
+    /// Misses `key` on its first read, as if another machine stored it
+    /// just after
+    struct Stale<'a> {
+        inner: &'a MemoryBackend,
+        key: &'static str,
+        missed: AtomicBool,
+    }
+
+    #[async_trait]
+    impl SyncBackend for Stale<'_> {
+        async fn get(&self, key: &str) -> Result<Option<RemoteObject>> {
+            if key == self.key && !self.missed.swap(true, Ordering::SeqCst) {
+                return Ok(None);
+            }
+            self.inner.get(key).await
+        }
+
+        async fn put(&self, key: &str, bytes: Vec<u8>, condition: Precondition<'_>) -> Result<()> {
+            self.inner.put(key, bytes, condition).await
+        }
+    }
+
+    #[tokio::test]
+    async fn racing_machines_keep_the_first_salt_and_manifest() {
+        let backend = MemoryBackend::default();
+        let salt = load_salt(&backend, false).await.unwrap();
+        let racing = Stale { inner: &backend, key: SALT_KEY, missed: AtomicBool::new(false) };
+        assert_eq!(load_salt(&racing, false).await.unwrap(), salt);
+
+        let cipher = SyncCipher::new([1; 32]);
+        let (desk, laptop) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        write_local(&desk.path().join("prompts/review.md"), b"desk").await.unwrap();
+        write_local(&laptop.path().join("prompts/plan.md"), b"laptop").await.unwrap();
+        sync_dir(&backend, &cipher, desk.path(), "desk", false).await.unwrap();
+        let racing = Stale { inner: &backend, key: MANIFEST_KEY, missed: AtomicBool::new(false) };
+        let report = sync_dir(&racing, &cipher, laptop.path(), "laptop", false).await.unwrap();
+        assert!(report.actions.contains(&SyncAction::Download("prompts/review.md".to_string())));
+
+        sync_dir(&backend, &cipher, desk.path(), "desk", false).await.unwrap();
+        assert_eq!(std::fs::read(desk.path().join("prompts/plan.md")).unwrap(), b"laptop");
+    }
+}
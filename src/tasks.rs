@@ -0,0 +1,231 @@
+//! Workspace task definitions
+//!
+//! A workspace declares its canonical commands in a `[tasks]` section of
+//! `picode.toml` at its root:
+//!
+//! ```toml
+//! [tasks.test]
+//! command = "cargo test --workspace"
+//! description = "Run the test suite"
+//! ```
+//!
+//! Tasks run with `picode task run <name>`, are listed to the model in the
+//! system prompt as the preferred way to build, test and lint, and are
+//! suggested first by `picode execute --suggest`. Tasks from `picode.toml`
+//! are only loaded once the workspace is trusted (see [`crate::trust`]).
+
+use crate::config::{Config, ConfigError, TaskDefinition};
+use crate::error::{PiCodeError, Result};
+use crate::slash::fuzzy_score;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Workspace configuration file holding the `[tasks]` section
+pub const WORKSPACE_CONFIG_FILE: &str = "picode.toml";
+
+/// Only the part of `picode.toml` this module reads
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceFile {
+    #[serde(default)]
+    tasks: BTreeMap<String, TaskDefinition>,
+}
+
+/// Tasks available in a workspace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkspaceTasks {
+    tasks: BTreeMap<String, TaskDefinition>,
+}
+
+impl WorkspaceTasks {
+    pub fn new(tasks: BTreeMap<String, TaskDefinition>) -> Self {
+        Self { tasks }
+    }
+
+    /// Tasks from the user configuration overlaid with the workspace's
+    /// `picode.toml`, which is ignored unless the workspace is trusted
+    pub async fn load(config: &Config, workspace_root: &Path) -> Result<Self> {
+        let mut tasks = config.tasks.clone();
+        let path = workspace_root.join(WORKSPACE_CONFIG_FILE);
+        if tokio::fs::try_exists(&path).await? && crate::trust::is_trusted(workspace_root) {
+            let content = tokio::fs::read_to_string(&path).await?;
+            tasks.extend(Self::parse(&content)?.tasks);
+        }
+        Ok(Self { tasks })
+    }
+
+    /// Read the `[tasks]` section of a `picode.toml` document
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: WorkspaceFile = ::config::Config::builder()
+            .add_source(::config::File::from_str(content, ::config::FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| ConfigError::InvalidConfig(format!("{}: {}", WORKSPACE_CONFIG_FILE, e)))?;
+        Ok(Self { tasks: file.tasks })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TaskDefinition> {
+        self.tasks.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &TaskDefinition)> {
+        self.tasks.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Instructions listing the tasks, for the system prompt's tasks layer
+    pub fn prompt_layer(&self) -> Option<String> {
+        if self.tasks.is_empty() {
+            return None;
+        }
+        let mut out = String::from(
+            "This workspace defines the tasks below. Prefer them over ad-hoc shell commands for \
+             building, testing and linting; run one with `picode task run <name>`.\n",
+        );
+        for (name, task) in &self.tasks {
+            let _ = match &task.description {
+                Some(description) => writeln!(out, "- {}: {} (`{}`)", name, description, task.command),
+                None => writeln!(out, "- {}: `{}`", name, task.command),
+            };
+        }
+        Some(out)
+    }
+
+    /// Tasks relevant to a requested command line, best match first
+    pub fn suggest(&self, query: &str) -> Vec<(&str, &TaskDefinition)> {
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let mut ranked: Vec<(i64, &str, &TaskDefinition)> = self
+            .tasks
+            .iter()
+            .filter_map(|(name, task)| {
+                let by_name = words
+                    .iter()
+                    .filter_map(|word| fuzzy_score(word, name).max(fuzzy_score(name, word)))
+                    .max();
+                let by_command = fuzzy_score(query, &task.command);
+                let by_description = task
+                    .description
+                    .as_deref()
+                    .and_then(|description| fuzzy_score(query, description))
+                    .map(|score| score / 2);
+                [by_name, by_command, by_description]
+                    .into_iter()
+                    .flatten()
+                    .max()
+                    .map(|score| (score, name.as_str(), task))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        ranked.into_iter().map(|(_, name, task)| (name, task)).collect()
+    }
+
+    /// Run a task through the shell with inherited stdio; extra arguments
+    /// are appended to its command line
+    pub async fn run(&self, name: &str, args: &[String], workspace_root: &Path) -> Result<()> {
+        let task = self
+            .get(name)
+            .ok_or_else(|| PiCodeError::NotFound(format!("task '{}'", name)))?;
+        let mut line = task.command.clone();
+        for arg in args {
+            line.push(' ');
+            line.push_str(&shell_quote(arg));
+        }
+        let cwd = match &task.cwd {
+            Some(cwd) => workspace_root.join(cwd),
+            None => workspace_root.to_path_buf(),
+        };
+
+        let mut command = if cfg!(windows) {
+            let mut command = tokio::process::Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = tokio::process::Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        let status = command.arg(&line).current_dir(&cwd).status().await?;
+        if !status.success() {
+            return Err(PiCodeError::Internal(format!("task '{}' failed ({})", name, status)));
+        }
+        Ok(())
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    if cfg!(windows) || (!arg.is_empty() && arg.chars().all(|c| c.is_alphanumeric() || "-_./=:,@".contains(c))) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Handle `picode task` subcommands in the current directory
+#[cfg(feature = "cli")]
+pub async fn handle_action(action: picode_cli::TaskAction, config: &Config) -> Result<()> {
+    let root = match &config.workspace.root_dir {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    let tasks = WorkspaceTasks::load(config, &root).await?;
+    match action {
+        picode_cli::TaskAction::List => {
+            if tasks.is_empty() {
+                println!("No tasks defined. Add a [tasks] section to {}", WORKSPACE_CONFIG_FILE);
+            }
+            for (name, task) in tasks.iter() {
+                println!("  {:<12} {}", name, task.description.as_deref().unwrap_or(&task.command));
+            }
+            Ok(())
+        }
+        picode_cli::TaskAction::Run { name, args } => {
+            println!("🧰 Running task '{}'", name);
+            tasks.run(&name, &args, &root).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKSPACE: &str = r#"
+[tasks.test]
+command = "cargo test --workspace"
+description = "Run the test suite"
+
+[tasks.lint]
+command = "cargo clippy --all-targets -- -D warnings"
+description = "Lint with clippy"
+
+[tasks.build]
+command = "cargo build --release"
+"#;
+
+    #[test]
+    fn parses_tasks_and_renders_prompt_layer() {
+        let tasks = WorkspaceTasks::parse(WORKSPACE).unwrap();
+        assert_eq!(tasks.get("test").unwrap().command, "cargo test --workspace");
+        assert!(tasks.get("build").unwrap().description.is_none());
+
+        let layer = tasks.prompt_layer().unwrap();
+        assert!(layer.contains("- lint: Lint with clippy (`cargo clippy --all-targets -- -D warnings`)"));
+        assert!(layer.contains("- build: `cargo build --release`"));
+        assert!(WorkspaceTasks::default().prompt_layer().is_none());
+        assert!(WorkspaceTasks::parse("[tasks.broken]\ndescription = \"no command\"").is_err());
+    }
+
+    #[test]
+    fn suggestions_prefer_matching_tasks() {
+        let tasks = WorkspaceTasks::parse(WORKSPACE).unwrap();
+        assert_eq!(tasks.suggest("run the tests")[0].0, "test");
+        assert_eq!(tasks.suggest("cargo clippy")[0].0, "lint");
+        assert!(tasks.suggest("zzz").is_empty());
+        assert_eq!(shell_quote("--nocapture"), "--nocapture");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+}
@@ -0,0 +1,164 @@
+//! OpenTelemetry export of tracing spans
+//!
+//! LLM requests (`llm.request`), agent tool calls (`agent.tool`) and command
+//! runs (`command.run`) are recorded as tracing spans. In builds with the
+//! `otel` feature, `[telemetry] enabled = true` exports them over OTLP to a
+//! collector such as Jaeger or Tempo, next to the usual stderr log. Traces
+//! are sampled by trace ID ratio, following the parent's decision when a
+//! span continues a remote trace.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing_subscriber::{Layer, Registry};
+
+/// Transport used to reach the collector
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtlpProtocol {
+    #[default]
+    Grpc,
+    Http,
+}
+
+/// The `[telemetry]` configuration section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// Collector endpoint; the protocol's standard local one when unset
+    pub endpoint: Option<String>,
+    pub protocol: OtlpProtocol,
+    /// Share of traces exported, from 0.0 to 1.0
+    pub sample_ratio: f64,
+    /// `service.name` the traces are reported under
+    pub service_name: String,
+    /// Extra request headers, e.g. collector credentials (HTTP only)
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: None,
+            protocol: OtlpProtocol::default(),
+            sample_ratio: 1.0,
+            service_name: "picode".to_string(),
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+impl TelemetryConfig {
+    pub fn endpoint(&self) -> String {
+        match (&self.endpoint, self.protocol) {
+            (Some(endpoint), _) => endpoint.clone(),
+            (None, OtlpProtocol::Grpc) => "http://localhost:4317".to_string(),
+            (None, OtlpProtocol::Http) => "http://localhost:4318/v1/traces".to_string(),
+        }
+    }
+}
+
+/// Flushes spans not exported yet when dropped; keep it until exit
+#[must_use = "spans are flushed when the guard is dropped"]
+pub struct TelemetryGuard {
+    exporting: bool,
+}
+
+impl TelemetryGuard {
+    pub fn is_exporting(&self) -> bool {
+        self.exporting
+    }
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if self.exporting {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+    }
+}
+
+/// Install the global subscriber: the stderr log, plus OTLP export when
+/// `config` enables it. Needs a Tokio runtime.
+pub fn init(config: &TelemetryConfig) -> TelemetryGuard {
+    if !config.enabled {
+        crate::logging::configure_logger();
+        return TelemetryGuard { exporting: false };
+    }
+    match export_layer(config) {
+        Ok(layer) => {
+            crate::logging::configure_logger_with_layer(Some(layer));
+            tracing::info!(
+                "Exporting traces to {} over {:?}, sampling {}",
+                config.endpoint(),
+                config.protocol,
+                config.sample_ratio
+            );
+            TelemetryGuard { exporting: true }
+        }
+        Err(e) => {
+            crate::logging::configure_logger();
+            tracing::warn!("Trace export is disabled: {}", e);
+            TelemetryGuard { exporting: false }
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+fn export_layer(config: &TelemetryConfig) -> Result<Box<dyn Layer<Registry> + Send + Sync>, String> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::trace::{Config, Sampler};
+
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_ratio.clamp(0.0, 1.0))));
+    let trace_config = Config::default()
+        .with_sampler(sampler)
+        .with_resource(opentelemetry_sdk::Resource::new([KeyValue::new(
+            "service.name",
+            config.service_name.clone(),
+        )]));
+    let pipeline = opentelemetry_otlp::new_pipeline().tracing().with_trace_config(trace_config);
+    let provider = match config.protocol {
+        OtlpProtocol::Grpc => pipeline
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(config.endpoint()))
+            .install_batch(opentelemetry_sdk::runtime::Tokio),
+        OtlpProtocol::Http => pipeline
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .http()
+                    .with_endpoint(config.endpoint())
+                    .with_headers(config.headers.clone().into_iter().collect()),
+            )
+            .install_batch(opentelemetry_sdk::runtime::Tokio),
+    }
+    .map_err(|e| e.to_string())?;
+
+    let tracer = provider.tracer("picode");
+    opentelemetry::global::set_tracer_provider(provider);
+    Ok(Box::new(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+#[cfg(not(feature = "otel"))]
+fn export_layer(_config: &TelemetryConfig) -> Result<Box<dyn Layer<Registry> + Send + Sync>, String> {
+    Err("PiCode was built without the `otel` feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_parses_with_defaults() {
+        let config: TelemetryConfig = toml::from_str("enabled = true\nprotocol = \"http\"\nsample_ratio = 0.25\n").unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.endpoint(), "http://localhost:4318/v1/traces");
+        assert_eq!(config.service_name, "picode");
+
+        let config: TelemetryConfig = toml::from_str("endpoint = \"http://tempo:4317\"").unwrap();
+        assert_eq!((config.endpoint(), config.protocol), ("http://tempo:4317".to_string(), OtlpProtocol::Grpc));
+        assert!(!config.enabled);
+    }
+}
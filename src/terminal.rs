@@ -0,0 +1,199 @@
+//! Terminal capability detection
+//!
+//! Detects what the attached terminal supports at startup (color depth,
+//! unicode, mouse, inline image protocols) and selects a rendering tier, so
+//! PiCode degrades gracefully in CI logs and dumb terminals instead of
+//! assuming a modern emulator.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+/// Inline image protocols supported by some terminal emulators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageProtocol {
+    Kitty,
+    ITerm2,
+    Sixel,
+}
+
+/// Rendering tier selected from the detected capabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RenderTier {
+    /// Pure ASCII, no colors, no cursor movement (CI logs, dumb terminals)
+    Minimal,
+    /// 16 colors, ASCII borders
+    Basic,
+    /// 256 colors, unicode borders and symbols
+    Standard,
+    /// Truecolor, unicode, mouse and inline images where available
+    Full,
+}
+
+/// Capabilities of the attached terminal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TerminalCapabilities {
+    pub is_tty: bool,
+    pub colors: bool,
+    pub color_256: bool,
+    pub truecolor: bool,
+    pub unicode: bool,
+    pub mouse: bool,
+    pub image_protocol: Option<ImageProtocol>,
+}
+
+impl TerminalCapabilities {
+    /// Detect capabilities of the current process's terminal
+    pub fn detect() -> Self {
+        let env: HashMap<String, String> = std::env::vars().collect();
+        Self::from_env(&env, std::io::stdout().is_terminal())
+    }
+
+    /// Detect capabilities from an environment snapshot
+    pub fn from_env(env: &HashMap<String, String>, is_tty: bool) -> Self {
+        let get = |key: &str| env.get(key).map(|v| v.as_str()).unwrap_or("");
+        let term = get("TERM").to_lowercase();
+        let term_program = get("TERM_PROGRAM");
+        let colorterm = get("COLORTERM").to_lowercase();
+
+        // Windows consoles set no TERM; Windows Terminal identifies itself with WT_SESSION
+        let windows = get("OS") == "Windows_NT";
+        let windows_terminal = windows && env.contains_key("WT_SESSION");
+
+        let dumb = (term.is_empty() && !windows) || term == "dumb";
+        let ci = env.contains_key("CI");
+        let interactive = is_tty && !dumb && !ci;
+
+        let colors = interactive && !env.contains_key("NO_COLOR");
+        let truecolor = colors && (colorterm == "truecolor" || colorterm == "24bit" || windows_terminal);
+        let color_256 = colors && (truecolor || term.contains("256color"));
+
+        let locale = [get("LC_ALL"), get("LC_CTYPE"), get("LANG")]
+            .into_iter()
+            .find(|v| !v.is_empty())
+            .unwrap_or("")
+            .to_lowercase();
+        let unicode = interactive && (locale.contains("utf-8") || locale.contains("utf8") || windows_terminal);
+
+        let image_protocol = if !interactive {
+            None
+        } else if env.contains_key("KITTY_WINDOW_ID") || term.contains("kitty") {
+            Some(ImageProtocol::Kitty)
+        } else if term_program == "iTerm.app" || term_program == "WezTerm" {
+            Some(ImageProtocol::ITerm2)
+        } else if term.contains("sixel") {
+            Some(ImageProtocol::Sixel)
+        } else {
+            None
+        };
+
+        Self {
+            is_tty,
+            colors,
+            color_256,
+            truecolor,
+            unicode,
+            mouse: interactive,
+            image_protocol,
+        }
+    }
+
+    /// Disable colors, e.g. for `--no-color`
+    pub fn without_color(mut self) -> Self {
+        self.colors = false;
+        self.color_256 = false;
+        self.truecolor = false;
+        self
+    }
+
+    /// Select the richest rendering tier the terminal supports
+    pub fn render_tier(&self) -> RenderTier {
+        if !self.is_tty || (!self.colors && !self.unicode) {
+            RenderTier::Minimal
+        } else if self.truecolor && self.unicode {
+            RenderTier::Full
+        } else if self.color_256 && self.unicode {
+            RenderTier::Standard
+        } else {
+            RenderTier::Basic
+        }
+    }
+}
+
+impl RenderTier {
+    /// Symbol for a status marker, falling back to ASCII on limited terminals
+    pub fn symbol(&self, kind: StatusSymbol) -> &'static str {
+        let ascii = matches!(self, RenderTier::Minimal | RenderTier::Basic);
+        match (kind, ascii) {
+            (StatusSymbol::Success, false) => "✅",
+            (StatusSymbol::Success, true) => "[ok]",
+            (StatusSymbol::Failure, false) => "❌",
+            (StatusSymbol::Failure, true) => "[error]",
+            (StatusSymbol::Warning, false) => "⚠️",
+            (StatusSymbol::Warning, true) => "[warn]",
+            (StatusSymbol::Info, false) => "🎯",
+            (StatusSymbol::Info, true) => "*",
+        }
+    }
+}
+
+/// Status markers printed by the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusSymbol {
+    Success,
+    Failure,
+    Warning,
+    Info,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn modern_terminal_gets_full_tier() {
+        let caps = TerminalCapabilities::from_env(
+            &env(&[
+                ("TERM", "xterm-kitty"),
+                ("COLORTERM", "truecolor"),
+                ("LANG", "en_US.UTF-8"),
+            ]),
+            true,
+        );
+
+        assert!(caps.truecolor);
+        assert_eq!(caps.image_protocol, Some(ImageProtocol::Kitty));
+        assert_eq!(caps.render_tier(), RenderTier::Full);
+        assert_eq!(caps.without_color().render_tier(), RenderTier::Basic);
+    }
+
+    #[test]
+    fn ci_and_dumb_terminals_are_minimal() {
+        let ci = TerminalCapabilities::from_env(&env(&[("TERM", "xterm-256color"), ("CI", "true")]), true);
+        assert_eq!(ci.render_tier(), RenderTier::Minimal);
+
+        let dumb = TerminalCapabilities::from_env(&env(&[("TERM", "dumb")]), true);
+        assert_eq!(dumb.render_tier(), RenderTier::Minimal);
+        assert_eq!(dumb.render_tier().symbol(StatusSymbol::Success), "[ok]");
+
+        let piped = TerminalCapabilities::from_env(&env(&[("TERM", "xterm-256color")]), false);
+        assert!(!piped.mouse);
+        assert_eq!(piped.render_tier(), RenderTier::Minimal);
+    }
+
+    #[test]
+    fn windows_consoles_without_term_get_colors() {
+        let conhost = TerminalCapabilities::from_env(&env(&[("OS", "Windows_NT")]), true);
+        assert!(conhost.colors);
+        assert!(!conhost.truecolor);
+        assert!(!conhost.unicode);
+
+        let windows_terminal = TerminalCapabilities::from_env(&env(&[("OS", "Windows_NT"), ("WT_SESSION", "1")]), true);
+        assert!(windows_terminal.truecolor);
+        assert!(windows_terminal.unicode);
+    }
+}
@@ -0,0 +1,181 @@
+//! `/timeline`: a file's versions across the session
+//!
+//! Lists every version of a file edited in the session (its initial content,
+//! each journaled edit and the current content when it changed since), shows
+//! the diff between any two of them and restores an earlier version. The
+//! versions come from the session's [`EditJournal`].
+
+use crate::error::{PiCodeError, Result};
+use picode_core::editor::{diff_versions, restore_version, EditJournal, FileVersion};
+use picode_core::{ContentCache, CoreError, NativeFileSystem};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// A parsed `/timeline` invocation
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineCommand {
+    /// Files with versions
+    Files,
+    Show(String),
+    /// Diff between two versions; `to` defaults to the newest one
+    Diff { file: String, from: usize, to: Option<usize> },
+    Restore { file: String, version: usize },
+}
+
+impl TimelineCommand {
+    /// Parse `[<file> [diff <a> [<b>] | restore <n>]]`
+    pub fn parse(args: &str) -> Result<Self> {
+        let words: Vec<&str> = args.split_whitespace().collect();
+        let version = |word: &str| {
+            word.trim_start_matches('v')
+                .parse::<usize>()
+                .map_err(|_| PiCodeError::InvalidCommand(format!("'{}' is not a version number", word)))
+        };
+        match words.as_slice() {
+            [] => Ok(TimelineCommand::Files),
+            [file] => Ok(TimelineCommand::Show(file.to_string())),
+            [file, "diff", from] => Ok(TimelineCommand::Diff { file: file.to_string(), from: version(from)?, to: None }),
+            [file, "diff", from, to] => Ok(TimelineCommand::Diff {
+                file: file.to_string(),
+                from: version(from)?,
+                to: Some(version(to)?),
+            }),
+            [file, "restore", number] => Ok(TimelineCommand::Restore { file: file.to_string(), version: version(number)? }),
+            _ => Err(PiCodeError::InvalidCommand(
+                "usage: /timeline [<file> [diff <a> [<b>] | restore <n>]]".to_string(),
+            )),
+        }
+    }
+}
+
+/// One line per version
+pub fn render_versions(path: &Path, versions: &[FileVersion]) -> String {
+    let mut out = format!("── {} ──\n", path.display());
+    for version in versions {
+        let size = match &version.content {
+            Some(_) => format!("{} lines", version.lines()),
+            None => "absent".to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "v{:<3} {:<8} {:<18} {}",
+            version.number,
+            version.at.with_timezone(&chrono::Local).format("%H:%M:%S"),
+            version.source,
+            size
+        );
+    }
+    out.push_str("── /timeline <file> diff <a> [<b>] | restore <n> ──\n");
+    out
+}
+
+fn resolve(root: &Path, file: &str) -> PathBuf {
+    let path = PathBuf::from(file);
+    if path.is_relative() {
+        root.join(path)
+    } else {
+        path
+    }
+}
+
+/// The file's versions including its current content
+async fn versions(journal: &EditJournal, path: &Path) -> Result<Vec<FileVersion>> {
+    let current = match tokio::fs::read_to_string(path).await {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let versions = journal.timeline().view(path, current);
+    if versions.is_empty() {
+        return Err(PiCodeError::NotFound(format!("{} was not edited in this session", path.display())));
+    }
+    Ok(versions)
+}
+
+/// Run a `/timeline` command and return what to print
+pub async fn run(command: TimelineCommand, journal: &EditJournal, root: &Path) -> Result<String> {
+    match command {
+        TimelineCommand::Files => {
+            let timeline = journal.timeline();
+            let mut out = String::new();
+            for file in timeline.files() {
+                let shown = file.strip_prefix(root).unwrap_or(file);
+                let _ = writeln!(out, "{} ({} versions)", shown.display(), timeline.versions(file).len());
+            }
+            if out.is_empty() {
+                out.push_str("No files edited in this session\n");
+            }
+            Ok(out)
+        }
+        TimelineCommand::Show(file) => {
+            let path = resolve(root, &file);
+            Ok(render_versions(&path, &versions(journal, &path).await?))
+        }
+        TimelineCommand::Diff { file, from, to } => {
+            let path = resolve(root, &file);
+            let versions = versions(journal, &path).await?;
+            let to = to.unwrap_or(versions.len() - 1);
+            let version = |number: usize| {
+                versions
+                    .get(number)
+                    .ok_or_else(|| PiCodeError::NotFound(format!("version {} of {}", number, path.display())))
+            };
+            let diff = diff_versions(&path, version(from)?, version(to)?);
+            Ok(if diff.is_empty() { format!("v{} and v{} are identical\n", from, to) } else { diff })
+        }
+        TimelineCommand::Restore { file, version } => {
+            let path = resolve(root, &file);
+            restore_version(journal, &path, version, &NativeFileSystem, &ContentCache::global())
+                .await
+                .map_err(CoreError::from)?;
+            Ok(format!("✅ Restored {} to v{}\n", path.display(), version))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picode_core::FileEdit;
+
+    #[test]
+    fn parses_subcommands() {
+        assert_eq!(TimelineCommand::parse("").unwrap(), TimelineCommand::Files);
+        assert_eq!(
+            TimelineCommand::parse("src/lib.rs diff v1").unwrap(),
+            TimelineCommand::Diff { file: "src/lib.rs".to_string(), from: 1, to: None }
+        );
+        assert_eq!(
+            TimelineCommand::parse("src/lib.rs restore 0").unwrap(),
+            TimelineCommand::Restore { file: "src/lib.rs".to_string(), version: 0 }
+        );
+        assert!(TimelineCommand::parse("src/lib.rs diff latest").is_err());
+        assert!(TimelineCommand::parse("src/lib.rs undo").is_err());
+    }
+
+    #[tokio::test]
+    async fn shows_diffs_and_restores_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        let journal = EditJournal::for_workspace(dir.path());
+        let cache = ContentCache::new();
+        FileEdit::new(&path, "draft\n").with_journal(journal.clone()).apply(&NativeFileSystem, &cache).await.unwrap();
+        tokio::fs::write(&path, "final\n").await.unwrap();
+
+        let shown = run(TimelineCommand::Show("notes.md".to_string()), &journal, dir.path()).await.unwrap();
+        assert!(shown.contains("initial") && shown.contains("absent"));
+        assert!(shown.contains("current"));
+
+        let diff = run(TimelineCommand::Diff { file: "notes.md".to_string(), from: 1, to: None }, &journal, dir.path())
+            .await
+            .unwrap();
+        assert!(diff.contains("-draft\n+final\n"));
+
+        run(TimelineCommand::Restore { file: "notes.md".to_string(), version: 1 }, &journal, dir.path())
+            .await
+            .unwrap();
+        assert_eq!(tokio::fs::read_to_string(&path).await.unwrap(), "draft\n");
+        let files = run(TimelineCommand::Files, &journal, dir.path()).await.unwrap();
+        assert_eq!(files, "notes.md (4 versions)\n");
+    }
+}
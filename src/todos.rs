@@ -0,0 +1,63 @@
+//! `picode todos` and the workspace scan behind it
+//!
+//! Every scan reconciles `.picode/todos.json` with the comments in the code
+//! (see [`picode_core::todos`]), so items whose comment an edit removed are
+//! marked done. Agent runs refresh the list before they start, for the
+//! `list_todos` tool, and after they finish, to report what they settled.
+
+use crate::config::Config;
+use crate::error::Result;
+use picode_core::todos::{extract, TodoChanges, TodoItem, TodoList, TodoStatus};
+use picode_core::workspace::{Workspace, WorkspaceConfig};
+use picode_core::NativeFileSystem;
+use std::path::Path;
+
+/// Handle `picode todos`
+pub async fn handle(config: &Config, json: bool, all: bool) -> Result<()> {
+    let root = crate::stats::workspace_root(config)?;
+    let (list, _) = refresh(&root).await?;
+    let items: Vec<&TodoItem> = list.items.iter().filter(|item| all || item.status == TodoStatus::Open).collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&items)?);
+    } else if items.is_empty() {
+        println!("No open TODO items");
+    } else {
+        for item in items {
+            let owner = item.owner.as_deref().map(|owner| format!(" ({})", owner)).unwrap_or_default();
+            let done = if item.status == TodoStatus::Done { " [done]" } else { "" };
+            println!("{} {} {}{} {}{}", item.id, item.kind, item.location(), owner, item.text, done);
+        }
+    }
+    Ok(())
+}
+
+/// Marker comments of every text file in `root`, honoring ignore rules
+pub async fn scan(root: &Path) -> Result<Vec<TodoItem>> {
+    let mut workspace = Workspace::new(WorkspaceConfig {
+        root_path: root.to_path_buf(),
+        git_enabled: false,
+        ..WorkspaceConfig::default()
+    });
+    workspace.scan().await.map_err(picode_core::CoreError::from)?;
+
+    let mut items = Vec::new();
+    for file in workspace.files.iter().filter(|file| !file.is_binary) {
+        if file.relative_path.starts_with(".picode") {
+            continue;
+        }
+        // Files that are not UTF-8 have no comments worth tracking
+        if let Ok(content) = tokio::fs::read_to_string(&file.path).await {
+            items.extend(extract(&file.relative_path, &content));
+        }
+    }
+    Ok(items)
+}
+
+/// Rescan `root` and save the reconciled list
+pub async fn refresh(root: &Path) -> Result<(TodoList, TodoChanges)> {
+    let scanned = scan(root).await?;
+    let mut list = TodoList::load(&NativeFileSystem, root).await.map_err(picode_core::CoreError::from)?;
+    let changes = list.reconcile(scanned);
+    list.save(&NativeFileSystem).await.map_err(picode_core::CoreError::from)?;
+    Ok((list, changes))
+}
@@ -0,0 +1,222 @@
+//! Workspace trust
+//!
+//! A cloned repository can ship hooks under `.picode/hooks`, commands in the
+//! `[tasks]` section of `picode.toml`, a `[policy]` for replies and
+//! instructions in `PICODE.md`. None of them are used until the workspace is
+//! trusted. The first time PiCode
+//! opens an unfamiliar workspace it asks; the answer is kept in a global
+//! allowlist keyed by canonical path, and a decision for a directory also
+//! covers everything below it.
+
+use crate::config::ConfigError;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Trust decisions, stored in the user's configuration directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// Canonical workspace path to whether it is trusted
+    #[serde(default)]
+    pub workspaces: BTreeMap<PathBuf, bool>,
+}
+
+impl TrustStore {
+    /// Default location of the allowlist
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("picode")
+            .join("trusted.json")
+    }
+
+    /// Load the allowlist from its default location
+    pub fn load() -> Result<Self> {
+        Self::load_from(Self::default_path())
+    }
+
+    /// Load an allowlist; a missing file holds no decisions
+    pub fn load_from(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut store = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| ConfigError::Serialization(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        store.path = path;
+        Ok(store)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The decision for `root`, from the nearest directory that has one
+    pub fn decision(&self, root: &Path) -> Option<bool> {
+        canonical(root)
+            .ancestors()
+            .find_map(|dir| self.workspaces.get(dir).copied())
+    }
+
+    /// Whether `root` is trusted; undecided workspaces are not
+    pub fn is_trusted(&self, root: &Path) -> bool {
+        self.decision(root).unwrap_or(false)
+    }
+
+    pub fn set(&mut self, root: &Path, trusted: bool) {
+        self.workspaces.insert(canonical(root), trusted);
+    }
+
+    /// Drop the decision recorded for exactly `root`
+    pub fn forget(&mut self, root: &Path) -> bool {
+        self.workspaces.remove(&canonical(root)).is_some()
+    }
+}
+
+fn canonical(root: &Path) -> PathBuf {
+    picode_core::paths::canonicalize(root).unwrap_or_else(|_| root.to_path_buf())
+}
+
+/// Whether the workspace at `root` may run its hooks and tasks and
+/// contribute its instructions; an unreadable allowlist trusts nothing
+pub fn is_trusted(root: &Path) -> bool {
+    match TrustStore::load() {
+        Ok(store) => store.is_trusted(root),
+        Err(e) => {
+            debug!("Trust allowlist not loaded: {}", e);
+            false
+        }
+    }
+}
+
+/// Whether `path`, taken relative to the current directory, lies inside the
+/// workspace at `root` once symlinks are resolved, so using it takes trust
+pub fn is_inside(path: &Path, root: &Path) -> bool {
+    let resolve = |path: &Path| picode_core::paths::canonicalize_existing(path).unwrap_or_else(|_| path.to_path_buf());
+    let root = resolve(root);
+    picode_core::paths::strip_root(&resolve(path), &root).is_some()
+}
+
+/// Ask whether to trust `root` if no decision covers it yet. Nothing is
+/// asked (or recorded) when stdin is not a terminal.
+#[cfg(feature = "tui")]
+pub fn ensure(root: &Path) -> Result<bool> {
+    use std::io::IsTerminal;
+
+    let mut store = TrustStore::load()?;
+    if let Some(trusted) = store.decision(root) {
+        return Ok(trusted);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    println!("🔒 {} has not been opened before.", canonical(root).display());
+    println!("   Trusting it lets its hooks, picode.toml tasks and PICODE.md instructions run.");
+    let trusted = dialoguer::Confirm::new()
+        .with_prompt("Trust this workspace?")
+        .default(false)
+        .interact()?;
+    store.set(root, trusted);
+    store.save()?;
+    Ok(trusted)
+}
+
+/// Handle `picode trust` subcommands
+#[cfg(feature = "cli")]
+pub async fn handle_action(action: picode_cli::TrustAction, config: &crate::config::Config) -> Result<()> {
+    let mut store = TrustStore::load()?;
+    let target = |path: Option<PathBuf>| match path {
+        Some(path) => Ok(path),
+        None => crate::stats::workspace_root(config),
+    };
+    match action {
+        picode_cli::TrustAction::Allow { path } => {
+            let root = target(path)?;
+            store.set(&root, true);
+            store.save()?;
+            println!("✅ Trusted {}", canonical(&root).display());
+        }
+        picode_cli::TrustAction::Deny { path } => {
+            let root = target(path)?;
+            store.set(&root, false);
+            store.save()?;
+            println!("🔒 Untrusted {}", canonical(&root).display());
+        }
+        picode_cli::TrustAction::Forget { path } => {
+            let root = target(path)?;
+            if store.forget(&root) {
+                store.save()?;
+                println!("Forgot {}", canonical(&root).display());
+            } else {
+                println!("No decision recorded for {}", canonical(&root).display());
+            }
+        }
+        picode_cli::TrustAction::List => {
+            if store.workspaces.is_empty() {
+                println!("No trust decisions recorded");
+            }
+            for (path, trusted) in &store.workspaces {
+                println!("{:<9} {}", if *trusted { "trusted" } else { "untrusted" }, path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decisions_cover_subdirectories_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work");
+        let nested = work.join("clone");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(work.join("other")).unwrap();
+        let file = dir.path().join("config").join("trusted.json");
+
+        let mut store = TrustStore::load_from(&file).unwrap();
+        assert_eq!(store.decision(&nested), None);
+        assert!(!store.is_trusted(&nested));
+
+        store.set(&work, true);
+        store.set(&nested, false);
+        store.save().unwrap();
+
+        let mut store = TrustStore::load_from(&file).unwrap();
+        assert!(store.is_trusted(&work));
+        assert!(store.is_trusted(&work.join("other")));
+        assert_eq!(store.decision(&nested.join(".")), Some(false));
+
+        assert!(store.forget(&nested));
+        assert!(!store.forget(&nested));
+        assert!(store.is_trusted(&nested));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_through_symlinks_are_inside_their_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work");
+        let elsewhere = dir.path().join("elsewhere");
+        std::fs::create_dir_all(work.join(".picode")).unwrap();
+        std::fs::create_dir_all(&elsewhere).unwrap();
+        std::os::unix::fs::symlink(work.join(".picode"), elsewhere.join("hooks")).unwrap();
+
+        assert!(is_inside(&work.join(".picode/hooks"), &work));
+        assert!(is_inside(&elsewhere.join("hooks"), &work));
+        assert!(!is_inside(&elsewhere, &work));
+        assert!(!is_inside(&work.join("../elsewhere"), &work));
+    }
+}
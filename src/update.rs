@@ -0,0 +1,385 @@
+//! `picode dev update`: self-update from GitHub releases
+//!
+//! Releases carry one binary per platform (`picode-linux-x64`,
+//! `picode-macos-arm64`, `picode-windows-x64.exe`, ...) next to a
+//! `<asset>.sha256` checksum and a `<asset>.sig` detached Ed25519 signature
+//! of the binary. A download is installed only when its checksum matches
+//! and its signature verifies under the release key, which release builds
+//! embed as the default `update.public_key`; installing on the checksum
+//! alone takes `--insecure-checksum-only`. The running
+//! executable is swapped by renames inside its own directory; if any step
+//! fails, or the new binary does not start, the previous one is put back.
+
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use base64::Engine as _;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// `[update]` section of the configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    /// GitHub repository releases are taken from
+    #[serde(default = "default_repository")]
+    pub repository: String,
+
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+
+    /// Base64 Ed25519 public key release binaries are signed with; the
+    /// embedded release key unless configured
+    #[serde(default = "default_public_key")]
+    pub public_key: Option<String>,
+
+    /// Proxy for update requests (`http://`, `https://` or `socks5://`);
+    /// `HTTPS_PROXY` is honoured when unset
+    #[serde(default)]
+    pub proxy: Option<String>,
+}
+
+fn default_repository() -> String {
+    "pnocera/PiCode".to_string()
+}
+
+fn default_api_url() -> String {
+    "https://api.github.com".to_string()
+}
+
+/// The release signing key, set with `PICODE_RELEASE_PUBLIC_KEY` when
+/// release binaries are built
+fn default_public_key() -> Option<String> {
+    option_env!("PICODE_RELEASE_PUBLIC_KEY").map(str::to_string)
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            repository: default_repository(),
+            api_url: default_api_url(),
+            public_key: default_public_key(),
+            proxy: None,
+        }
+    }
+}
+
+/// A GitHub release, as far as updating needs it
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// `major.minor.patch` with an optional pre-release part; a release sorts
+/// after its pre-releases
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    numbers: [u64; 3],
+    pre: Option<String>,
+}
+
+impl Version {
+    /// Parse `1.2.3`, `v1.2.3` or `1.2.3-beta.1`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().trim_start_matches('v');
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (text, None),
+        };
+        let mut numbers = [0u64; 3];
+        let mut parts = core.split('.');
+        for number in &mut numbers {
+            *number = parts.next()?.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self { numbers, pre })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numbers.cmp(&other.numbers).then_with(|| match (&self.pre, &other.pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Release asset name of the binary for this platform
+pub fn platform_asset() -> Option<String> {
+    let os = match std::env::consts::OS {
+        "linux" => "linux",
+        "macos" => "macos",
+        "windows" => "windows",
+        _ => return None,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        _ => return None,
+    };
+    let suffix = if os == "windows" { ".exe" } else { "" };
+    Some(format!("picode-{}-{}{}", os, arch, suffix))
+}
+
+/// The newest release above `current`, skipping drafts and, unless
+/// `pre_release`, pre-releases
+pub fn newest_release<'a>(releases: &'a [Release], current: &Version, pre_release: bool) -> Option<(&'a Release, Version)> {
+    releases
+        .iter()
+        .filter(|release| !release.draft && (pre_release || !release.prerelease))
+        .filter_map(|release| Version::parse(&release.tag_name).map(|version| (release, version)))
+        .filter(|(_, version)| version > current)
+        .max_by(|a, b| a.1.cmp(&b.1))
+}
+
+/// Check the binary against its `.sha256` file (`<hex>  <name>` or bare hex)
+/// and, unless `public_key` is `None` for a checksum-only install, its
+/// base64 or raw `.sig` signature
+pub fn verify(binary: &[u8], checksum: &str, signature: Option<&[u8]>, public_key: Option<&str>) -> Result<()> {
+    let expected = checksum.split_whitespace().next().unwrap_or_default().to_lowercase();
+    let actual = hex::encode(Sha256::digest(binary));
+    if expected != actual {
+        return Err(PiCodeError::Permission(format!("checksum mismatch: expected {}, got {}", expected, actual)));
+    }
+
+    let Some(public_key) = public_key else {
+        return Ok(());
+    };
+    let signature = signature.ok_or_else(|| PiCodeError::Permission("release has no signature".to_string()))?;
+    let key = base64::engine::general_purpose::STANDARD
+        .decode(public_key.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+        .ok_or_else(|| crate::config::ConfigError::InvalidConfig("update.public_key is not a base64 Ed25519 key".to_string()))?;
+    let signature = match <[u8; 64]>::try_from(signature) {
+        Ok(raw) => raw,
+        Err(_) => base64::engine::general_purpose::STANDARD
+            .decode(String::from_utf8_lossy(signature).trim())
+            .ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .ok_or_else(|| PiCodeError::Permission("malformed release signature".to_string()))?,
+    };
+    key.verify(binary, &Signature::from_bytes(&signature))
+        .map_err(|_| PiCodeError::Permission("release signature does not verify".to_string()))
+}
+
+/// Replace `exe` with `binary`. The old executable is kept as `<exe>.old`
+/// until `check` accepts the new one; any failure restores it.
+pub fn swap_executable(exe: &Path, binary: &[u8], check: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let staged = sibling(exe, "new");
+    let backup = sibling(exe, "old");
+    std::fs::write(&staged, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(exe).map(|m| m.permissions().mode()).unwrap_or(0o755);
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode | 0o111))?;
+    }
+
+    let _ = std::fs::remove_file(&backup);
+    if let Err(e) = std::fs::rename(exe, &backup) {
+        let _ = std::fs::remove_file(&staged);
+        return Err(e.into());
+    }
+    let installed = std::fs::rename(&staged, exe).map_err(PiCodeError::from).and_then(|()| check(exe));
+    match installed {
+        Ok(()) => {
+            // A running executable cannot be deleted on Windows; it is
+            // replaced on the next update instead
+            let _ = std::fs::remove_file(&backup);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(exe);
+            let _ = std::fs::remove_file(&staged);
+            std::fs::rename(&backup, exe)?;
+            Err(e)
+        }
+    }
+}
+
+fn sibling(exe: &Path, extension: &str) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", extension));
+    exe.with_file_name(name)
+}
+
+/// The new binary must at least start and print its version
+fn check_starts(exe: &Path) -> Result<()> {
+    let output = std::process::Command::new(exe).arg("--version").output()?;
+    if !output.status.success() {
+        return Err(PiCodeError::Internal(format!("the new binary failed to start: {}", String::from_utf8_lossy(&output.stderr))));
+    }
+    Ok(())
+}
+
+fn client(config: &UpdateConfig) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(concat!("picode/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(300));
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    Ok(builder.build()?)
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    Ok(client.get(url).send().await?.error_for_status()?.bytes().await?.to_vec())
+}
+
+/// Check for a newer release and, with `install`, install it; the release
+/// signature must verify unless `checksum_only`
+pub async fn run(config: &Config, pre_release: bool, install: bool, checksum_only: bool) -> Result<()> {
+    let settings = &config.update;
+    let public_key = match (settings.public_key.as_deref(), checksum_only) {
+        (_, true) => None,
+        (Some(key), false) => Some(key),
+        (None, false) if install => {
+            return Err(PiCodeError::Permission(
+                "no release key to verify the signature with; set update.public_key, or pass --insecure-checksum-only"
+                    .to_string(),
+            ))
+        }
+        (None, false) => None,
+    };
+    let client = client(settings)?;
+    let url = format!("{}/repos/{}/releases?per_page=30", settings.api_url.trim_end_matches('/'), settings.repository);
+    let releases: Vec<Release> = client
+        .get(&url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))
+        .ok_or_else(|| PiCodeError::Internal("unparseable package version".to_string()))?;
+    let Some((release, _)) = newest_release(&releases, &current, pre_release) else {
+        println!("✅ PiCode {} is up to date", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    };
+    println!("⬆️  PiCode {} is available (installed: {})", release.tag_name, env!("CARGO_PKG_VERSION"));
+    if !install {
+        println!("Run `picode dev update --auto` to install it");
+        return Ok(());
+    }
+
+    let name = platform_asset()
+        .ok_or_else(|| PiCodeError::NotFound(format!("a release binary for {}/{}", std::env::consts::OS, std::env::consts::ARCH)))?;
+    let missing = |asset: &str| PiCodeError::NotFound(format!("{} in release {}", asset, release.tag_name));
+    let binary = release.asset(&name).ok_or_else(|| missing(&name))?;
+    let checksum_name = format!("{}.sha256", name);
+    let checksum = release.asset(&checksum_name).ok_or_else(|| missing(&checksum_name))?;
+
+    let binary = download(&client, &binary.browser_download_url).await?;
+    let checksum = String::from_utf8_lossy(&download(&client, &checksum.browser_download_url).await?).into_owned();
+    let signature = match release.asset(&format!("{}.sig", name)) {
+        Some(asset) => Some(download(&client, &asset.browser_download_url).await?),
+        None => None,
+    };
+    verify(&binary, &checksum, signature.as_deref(), public_key)?;
+    if public_key.is_none() {
+        println!("⚠️  Only the checksum was verified (--insecure-checksum-only)");
+    }
+
+    let exe = std::env::current_exe()?;
+    swap_executable(&exe, &binary, check_starts)?;
+    println!("✅ Updated {} to {}", exe.display(), release.tag_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn release(tag: &str, prerelease: bool) -> Release {
+        Release { tag_name: tag.to_string(), prerelease, draft: false, assets: Vec::new() }
+    }
+
+    #[test]
+    fn picks_the_newest_eligible_release() {
+        let current = Version::parse("0.1.0").unwrap();
+        let releases = [release("v0.1.0", false), release("v0.2.0", false), release("v0.3.0-beta.1", true), release("nightly", false)];
+        assert_eq!(newest_release(&releases, &current, false).unwrap().0.tag_name, "v0.2.0");
+        assert_eq!(newest_release(&releases, &current, true).unwrap().0.tag_name, "v0.3.0-beta.1");
+        assert!(Version::parse("1.0.0-rc.1").unwrap() < Version::parse("1.0.0").unwrap());
+        assert!(newest_release(&releases[..1], &current, true).is_none());
+    }
+
+    #[test]
+    fn verifies_checksums_and_signatures() {
+        let binary = b"new picode";
+        let checksum = format!("{}  picode-linux-x64\n", hex::encode(Sha256::digest(binary)));
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let public = base64::engine::general_purpose::STANDARD.encode(key.verifying_key().to_bytes());
+        let signature = base64::engine::general_purpose::STANDARD.encode(key.sign(binary).to_bytes());
+
+        verify(binary, &checksum, None, None).unwrap();
+        verify(binary, &checksum, Some(signature.as_bytes()), Some(&public)).unwrap();
+        assert!(verify(b"tampered", &checksum, Some(signature.as_bytes()), Some(&public)).is_err());
+        assert!(verify(binary, &checksum, None, Some(&public)).is_err());
+        let other = SigningKey::from_bytes(&[8; 32]).sign(binary).to_bytes();
+        assert!(verify(binary, &checksum, Some(&other[..]), Some(&public)).is_err());
+    }
+
+    #[tokio::test]
+    async fn installs_need_a_release_key_or_an_explicit_opt_out() {
+        let mut config = Config::default();
+        config.update.public_key = None;
+        config.update.api_url = "http://127.0.0.1:9".to_string();
+        let err = run(&config, false, true, false).await.unwrap_err();
+        assert!(matches!(err, PiCodeError::Permission(_)), "{}", err);
+        // With the opt-out it gets as far as asking for releases
+        assert!(!matches!(run(&config, false, true, true).await, Err(PiCodeError::Permission(_))));
+    }
+
+    #[test]
+    fn failed_swaps_restore_the_previous_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("picode");
+        std::fs::write(&exe, b"old").unwrap();
+
+        let failed = swap_executable(&exe, b"broken", |_| Err(PiCodeError::Internal("does not start".to_string())));
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old");
+        assert!(!sibling(&exe, "new").exists());
+
+        swap_executable(&exe, b"new", |_| Ok(())).unwrap();
+        assert_eq!(std::fs::read(&exe).unwrap(), b"new");
+        assert!(!sibling(&exe, "old").exists());
+    }
+}
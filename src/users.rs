@@ -0,0 +1,428 @@
+//! Accounts for multi-user serve mode
+//!
+//! Each user has a role, one or more bearer tokens (only their SHA-256
+//! hashes are stored), optional spend limits and a running spend ledger.
+//! The store lives in `~/.picode/serve-users.json`; when it contains at
+//! least one user, serve mode requires a token on every request.
+
+use crate::config::SpendLimits;
+use crate::error::{PiCodeError, Result};
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File holding serve-mode accounts inside `~/.picode`
+pub const USERS_FILE: &str = "serve-users.json";
+
+/// Prefix of issued tokens, making them easy to spot in logs and secret scanners
+const TOKEN_PREFIX: &str = "pct_";
+
+/// Default location of the user store
+pub fn default_users_file() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(crate::defaults::CONFIG_DIR)
+        .join(USERS_FILE)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    Member,
+    /// May use the admin API
+    Admin,
+}
+
+/// An issued token; the secret itself is never stored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenRecord {
+    /// Short public identifier (first characters of the token)
+    pub id: String,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl TokenRecord {
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+    }
+}
+
+/// Spend in USD for the current day and month
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpendLedger {
+    /// Day the daily total belongs to (`YYYY-MM-DD`)
+    pub day: String,
+    pub daily_usd: f64,
+    /// Month the monthly total belongs to (`YYYY-MM`)
+    pub month: String,
+    pub monthly_usd: f64,
+    pub total_usd: f64,
+}
+
+impl SpendLedger {
+    /// Reset totals whose period has ended
+    fn roll_over(&mut self, now: DateTime<Utc>) {
+        let day = now.format("%Y-%m-%d").to_string();
+        let month = format!("{:04}-{:02}", now.year(), now.month());
+        if self.day != day {
+            self.day = day;
+            self.daily_usd = 0.0;
+        }
+        if self.month != month {
+            self.month = month;
+            self.monthly_usd = 0.0;
+        }
+    }
+
+    pub fn record(&mut self, cost: f64, now: DateTime<Utc>) {
+        self.roll_over(now);
+        self.daily_usd += cost;
+        self.monthly_usd += cost;
+        self.total_usd += cost;
+    }
+
+    /// Take back part of a reservation that was recorded up front; a
+    /// period that ended meanwhile is not changed
+    pub fn release(&mut self, amount: f64, now: DateTime<Utc>) {
+        let (day, month) = (self.day.clone(), self.month.clone());
+        self.roll_over(now);
+        if self.day == day {
+            self.daily_usd = (self.daily_usd - amount).max(0.0);
+        }
+        if self.month == month {
+            self.monthly_usd = (self.monthly_usd - amount).max(0.0);
+        }
+        self.total_usd = (self.total_usd - amount).max(0.0);
+    }
+
+    /// Check whether a request of the estimated cost fits the limits
+    pub fn check(&mut self, estimate: f64, limits: &SpendLimits, now: DateTime<Utc>) -> Result<()> {
+        self.roll_over(now);
+        let exceeded = |period: &str, limit: f64| {
+            Err(PiCodeError::Permission(format!("{} spend limit of ${:.2} reached", period, limit)))
+        };
+
+        if let Some(limit) = limits.per_request.filter(|limit| estimate > *limit) {
+            return exceeded("per-request", limit);
+        }
+        if let Some(limit) = limits.daily.filter(|limit| self.daily_usd + estimate > *limit) {
+            return exceeded("daily", limit);
+        }
+        if let Some(limit) = limits.monthly.filter(|limit| self.monthly_usd + estimate > *limit) {
+            return exceeded("monthly", limit);
+        }
+        Ok(())
+    }
+}
+
+/// A serve-mode account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServeUser {
+    pub name: String,
+    #[serde(default)]
+    pub role: UserRole,
+    #[serde(default)]
+    pub tokens: Vec<TokenRecord>,
+    #[serde(default)]
+    pub limits: SpendLimits,
+    #[serde(default)]
+    pub spend: SpendLedger,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ServeUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == UserRole::Admin
+    }
+
+    pub fn active_tokens(&self) -> usize {
+        self.tokens.iter().filter(|t| t.is_active()).count()
+    }
+}
+
+/// Persistent set of serve-mode accounts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserStore {
+    users: BTreeMap<String, ServeUser>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl UserStore {
+    /// In-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the store from a file (empty if the file does not exist yet)
+    pub async fn load(path: &Path) -> Result<Self> {
+        let mut store: Self = match tokio::fs::read_to_string(path).await {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        store.path = Some(path.to_path_buf());
+        Ok(store)
+    }
+
+    /// Write the store back to the file it was loaded from
+    pub async fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_string_pretty(self)?).await?;
+        restrict_permissions(path).await
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    pub fn users(&self) -> impl Iterator<Item = &ServeUser> {
+        self.users.values()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ServeUser> {
+        self.users.get(name)
+    }
+
+    /// Create a user and return its first token
+    pub fn add_user(&mut self, name: &str, role: UserRole, limits: SpendLimits) -> Result<String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(PiCodeError::Parse(format!(
+                "invalid user name '{}' (use letters, digits, '-' and '_')",
+                name
+            )));
+        }
+        if self.users.contains_key(name) {
+            return Err(PiCodeError::AlreadyExists(format!("user '{}'", name)));
+        }
+
+        self.users.insert(
+            name.to_string(),
+            ServeUser {
+                name: name.to_string(),
+                role,
+                tokens: Vec::new(),
+                limits,
+                spend: SpendLedger::default(),
+                created_at: Utc::now(),
+            },
+        );
+        self.issue_token(name)
+    }
+
+    /// Issue an additional token for a user
+    pub fn issue_token(&mut self, name: &str) -> Result<String> {
+        let user = self.user_mut(name)?;
+        let token = format!("{}{}", TOKEN_PREFIX, uuid::Uuid::new_v4().simple());
+        user.tokens.push(TokenRecord {
+            id: token[..TOKEN_PREFIX.len() + 8].to_string(),
+            hash: hash_token(&token),
+            created_at: Utc::now(),
+            revoked_at: None,
+        });
+        Ok(token)
+    }
+
+    /// Revoke every active token of a user; returns how many were revoked
+    pub fn revoke_tokens(&mut self, name: &str) -> Result<usize> {
+        let user = self.user_mut(name)?;
+        let now = Utc::now();
+        let mut revoked = 0;
+        for token in user.tokens.iter_mut().filter(|t| t.is_active()) {
+            token.revoked_at = Some(now);
+            revoked += 1;
+        }
+        Ok(revoked)
+    }
+
+    /// Find the user owning an active token
+    pub fn authenticate(&self, token: &str) -> Option<&ServeUser> {
+        let hash = hash_token(token);
+        self.users
+            .values()
+            .find(|user| user.tokens.iter().any(|t| t.is_active() && t.hash == hash))
+    }
+
+    /// Fail if a request of the estimated cost would exceed the user's limits
+    pub fn check_spend(&mut self, name: &str, estimate: f64) -> Result<()> {
+        let user = self.user_mut(name)?;
+        let limits = user.limits.clone();
+        user.spend.check(estimate, &limits, Utc::now())
+    }
+
+    pub fn record_spend(&mut self, name: &str, cost: f64) -> Result<()> {
+        self.user_mut(name)?.spend.record(cost, Utc::now());
+        Ok(())
+    }
+
+    /// Check the estimated cost of a request against the user's limits and
+    /// count it right away, so concurrent requests cannot all pass the
+    /// check before any is recorded
+    pub fn reserve_spend(&mut self, name: &str, estimate: f64) -> Result<()> {
+        self.check_spend(name, estimate)?;
+        self.record_spend(name, estimate)
+    }
+
+    /// Replace a reservation with what the request actually cost (nothing
+    /// when it failed)
+    pub fn settle_spend(&mut self, name: &str, reserved: f64, cost: f64) -> Result<()> {
+        let spend = &mut self.user_mut(name)?.spend;
+        let now = Utc::now();
+        spend.release(reserved, now);
+        spend.record(cost, now);
+        Ok(())
+    }
+
+    fn user_mut(&mut self, name: &str) -> Result<&mut ServeUser> {
+        self.users
+            .get_mut(name)
+            .ok_or_else(|| PiCodeError::NotFound(format!("user '{}'", name)))
+    }
+}
+
+/// Run a `picode serve` account management command
+#[cfg(feature = "cli")]
+pub async fn handle_action(action: picode_cli::ServeAction, users_file: &Path) -> Result<()> {
+    let mut store = UserStore::load(users_file).await?;
+
+    match action {
+        picode_cli::ServeAction::AddUser {
+            name,
+            admin,
+            per_request_limit,
+            daily_limit,
+            monthly_limit,
+        } => {
+            let role = if admin { UserRole::Admin } else { UserRole::Member };
+            let limits = SpendLimits {
+                per_request: per_request_limit,
+                daily: daily_limit,
+                monthly: monthly_limit,
+            };
+            let token = store.add_user(&name, role, limits)?;
+            store.save().await?;
+            println!("✅ Created user '{}'", name);
+            println!("🔑 Token (shown once): {}", token);
+        }
+        picode_cli::ServeAction::Token { name } => {
+            let token = store.issue_token(&name)?;
+            store.save().await?;
+            println!("🔑 New token for '{}' (shown once): {}", name, token);
+        }
+        picode_cli::ServeAction::Revoke { name } => {
+            let revoked = store.revoke_tokens(&name)?;
+            store.save().await?;
+            println!("✅ Revoked {} token(s) of '{}'", revoked, name);
+        }
+        picode_cli::ServeAction::Users => {
+            if store.is_empty() {
+                println!("No serve users (single-user mode)");
+            }
+            for user in store.users() {
+                println!(
+                    "  • {} ({:?}) - {} active token(s), ${:.2} today, ${:.2} this month",
+                    user.name,
+                    user.role,
+                    user.active_tokens(),
+                    user.spend.daily_usd,
+                    user.spend.monthly_usd
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Keep the token hashes readable by the owner only
+#[cfg(unix)]
+async fn restrict_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn restrict_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn tokens_authenticate_until_revoked() {
+        let mut store = UserStore::new();
+        let token = store.add_user("alice", UserRole::Member, SpendLimits::default()).unwrap();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert!(store.add_user("alice", UserRole::Admin, SpendLimits::default()).is_err());
+        assert!(store.add_user("bob smith", UserRole::Member, SpendLimits::default()).is_err());
+
+        assert_eq!(store.authenticate(&token).unwrap().name, "alice");
+        assert!(store.authenticate("pct_wrong").is_none());
+
+        let second = store.issue_token("alice").unwrap();
+        assert_eq!(store.revoke_tokens("alice").unwrap(), 2);
+        assert!(store.authenticate(&token).is_none());
+        assert!(store.authenticate(&second).is_none());
+        assert!(!serde_json::to_string(&store).unwrap().contains(&token));
+    }
+
+    #[test]
+    fn spend_limits_roll_over_by_period() {
+        let limits = SpendLimits {
+            per_request: Some(1.0),
+            daily: Some(2.0),
+            monthly: Some(3.0),
+        };
+        let mut ledger = SpendLedger::default();
+        let day1 = Utc.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+
+        assert!(ledger.check(1.5, &limits, day1).is_err());
+        ledger.record(1.8, day1);
+        assert!(ledger.check(0.5, &limits, day1).is_err());
+
+        // A new day resets the daily total but not the monthly one
+        assert!(ledger.check(0.5, &limits, day2).is_ok());
+        ledger.record(0.9, day2);
+        let err = ledger.check(0.5, &limits, day2).unwrap_err();
+        assert!(err.to_string().contains("monthly spend limit of $3.00"));
+    }
+
+    #[test]
+    fn reservations_count_until_settled() {
+        let mut store = UserStore::new();
+        let limits = SpendLimits { per_request: None, daily: Some(1.0), monthly: None };
+        store.add_user("dev", UserRole::Member, limits).unwrap();
+
+        store.reserve_spend("dev", 0.6).unwrap();
+        // A concurrent request sees the first one's reservation
+        assert!(store.reserve_spend("dev", 0.6).is_err());
+
+        store.settle_spend("dev", 0.6, 0.1).unwrap();
+        store.reserve_spend("dev", 0.6).unwrap();
+        store.settle_spend("dev", 0.6, 0.0).unwrap();
+        let spend = &store.users().next().unwrap().spend;
+        assert!((spend.daily_usd - 0.1).abs() < 1e-9 && (spend.total_usd - 0.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,355 @@
+//! Webhook delivery of workspace events
+//!
+//! [`WebhookDispatcher`] is an [`EventHandler`] that POSTs selected event bus
+//! events to configured URLs, so PiCode activity can be piped into chat or
+//! observability systems. Payloads are signed with HMAC-SHA256 when the
+//! endpoint has a secret, failed deliveries are retried with exponential
+//! backoff, and deliveries that still fail are appended to a dead-letter log.
+
+use crate::config::{ConfigError, WebhooksConfig};
+use crate::error::Result;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use picode_core::event::{EventEnvelope, EventError, EventHandler};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Event types that can be sent to webhooks; `hook_triggered` is not one,
+/// as it asks the hook runner to run a hook rather than reporting anything
+pub const SUPPORTED_EVENTS: &[&str] = &[
+    "command_completed",
+    "llm_error",
+    "hook_completed",
+    "system_error",
+    "context_stale",
+];
+
+/// Header carrying `sha256=<hex HMAC of the body>`
+pub const SIGNATURE_HEADER: &str = "X-PiCode-Signature";
+pub const EVENT_HEADER: &str = "X-PiCode-Event";
+/// Unique per event, stable across retries so receivers can deduplicate
+pub const DELIVERY_HEADER: &str = "X-PiCode-Delivery";
+
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON body POSTed for an event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub source: String,
+    pub event: picode_core::Event,
+}
+
+impl WebhookPayload {
+    pub fn from_envelope(envelope: &EventEnvelope) -> Self {
+        Self {
+            id: envelope.id.to_string(),
+            event_type: envelope.event.event_type().to_string(),
+            timestamp: envelope.timestamp,
+            source: envelope.source.clone(),
+            event: envelope.event.clone(),
+        }
+    }
+}
+
+/// A delivery that failed after all retries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub url: String,
+    pub payload: WebhookPayload,
+    pub error: String,
+    pub attempts: u32,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    events: Vec<&'static str>,
+    secret: Option<String>,
+}
+
+/// Sends events to webhook endpoints
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+    endpoints: Arc<Vec<Endpoint>>,
+    max_retries: u32,
+    retry_delay: Duration,
+    dead_letter: PathBuf,
+}
+
+impl WebhookDispatcher {
+    /// Dispatcher for the configured endpoints; `None` when there are none
+    pub fn from_config(config: &WebhooksConfig) -> Result<Option<Self>> {
+        if config.endpoints.is_empty() {
+            return Ok(None);
+        }
+
+        let mut endpoints = Vec::new();
+        for endpoint in &config.endpoints {
+            let events = endpoint
+                .events
+                .iter()
+                .map(|name| {
+                    SUPPORTED_EVENTS.iter().copied().find(|event| event == name).ok_or_else(|| {
+                        ConfigError::InvalidConfig(format!(
+                            "webhook event '{}' is not one of: {}",
+                            name,
+                            SUPPORTED_EVENTS.join(", ")
+                        ))
+                    })
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let secret = match &endpoint.secret_env {
+                Some(var) => Some(std::env::var(var).map_err(|_| {
+                    ConfigError::InvalidConfig(format!("webhook secret {} is not set", var))
+                })?),
+                None => None,
+            };
+            endpoints.push(Endpoint {
+                url: endpoint.url.clone(),
+                events,
+                secret,
+            });
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("PiCode-Webhooks/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        Ok(Some(Self {
+            client,
+            endpoints: Arc::new(endpoints),
+            max_retries: config.max_retries,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            dead_letter: config.dead_letter_path(),
+        }))
+    }
+
+    /// Base delay between retries (doubled after each attempt)
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Deliver an event to every endpoint subscribed to it; returns how many
+    /// deliveries failed and were dead-lettered
+    pub async fn dispatch(&self, envelope: &EventEnvelope) -> usize {
+        let event_type = envelope.event.event_type();
+        let payload = WebhookPayload::from_envelope(envelope);
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Cannot serialize {} for webhooks: {}", event_type, e);
+                return 0;
+            }
+        };
+
+        let mut failed = 0;
+        for endpoint in self.endpoints.iter().filter(|e| e.events.contains(&event_type)) {
+            let (attempts, result) = self.deliver(endpoint, &payload, &body).await;
+            if let Err(error) = result {
+                failed += 1;
+                warn!("Webhook delivery to {} failed after {} attempt(s): {}", endpoint.url, attempts, error);
+                self.dead_letter(DeadLetter {
+                    url: endpoint.url.clone(),
+                    payload: payload.clone(),
+                    error,
+                    attempts,
+                    failed_at: chrono::Utc::now(),
+                });
+            }
+        }
+        failed
+    }
+
+    /// POST with retries; returns the number of attempts made
+    async fn deliver(&self, endpoint: &Endpoint, payload: &WebhookPayload, body: &[u8]) -> (u32, std::result::Result<(), String>) {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let mut request = self
+                .client
+                .post(&endpoint.url)
+                .header("Content-Type", "application/json")
+                .header(EVENT_HEADER, &payload.event_type)
+                .header(DELIVERY_HEADER, &payload.id)
+                .body(body.to_vec());
+            if let Some(secret) = &endpoint.secret {
+                request = request.header(SIGNATURE_HEADER, signature(secret, body));
+            }
+
+            let (retryable, error) = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Delivered {} to {}", payload.event_type, endpoint.url);
+                    return (attempt, Ok(()));
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let retryable = status.is_server_error() || status.as_u16() == 408 || status.as_u16() == 429;
+                    (retryable, format!("HTTP {}", status))
+                }
+                Err(e) => (true, e.to_string()),
+            };
+
+            if !retryable || attempt > self.max_retries {
+                return (attempt, Err(error));
+            }
+            tokio::time::sleep(self.retry_delay * 2u32.saturating_pow(attempt - 1)).await;
+        }
+    }
+
+    fn dead_letter(&self, letter: DeadLetter) {
+        let append = || -> std::io::Result<()> {
+            if let Some(parent) = self.dead_letter.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.dead_letter)?;
+            writeln!(file, "{}", serde_json::to_string(&letter)?)
+        };
+        if let Err(e) = append() {
+            warn!("Cannot write webhook dead-letter log {}: {}", self.dead_letter.display(), e);
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for WebhookDispatcher {
+    /// Deliveries run in the background so publishers are never blocked
+    async fn handle(&self, envelope: &EventEnvelope) -> std::result::Result<(), EventError> {
+        let dispatcher = self.clone();
+        let envelope = envelope.clone();
+        tokio::spawn(async move {
+            dispatcher.dispatch(&envelope).await;
+        });
+        Ok(())
+    }
+
+    fn event_types(&self) -> Vec<&'static str> {
+        SUPPORTED_EVENTS
+            .iter()
+            .copied()
+            .filter(|event| self.endpoints.iter().any(|e| e.events.contains(event)))
+            .collect()
+    }
+
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+}
+
+/// `sha256=<hex>` HMAC-SHA256 of the body
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WebhookEndpoint;
+    use picode_core::{Event, SessionId};
+    use std::collections::HashMap;
+    use wiremock::matchers::{header_exists, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config(url: String, events: &[&str], dead_letter: PathBuf) -> WebhooksConfig {
+        WebhooksConfig {
+            endpoints: vec![WebhookEndpoint {
+                url,
+                events: events.iter().map(|e| e.to_string()).collect(),
+                secret_env: None,
+            }],
+            max_retries: 2,
+            dead_letter_file: Some(dead_letter),
+        }
+    }
+
+    fn llm_error() -> EventEnvelope {
+        EventEnvelope::new(
+            Event::LLMError {
+                session_id: SessionId::new(),
+                pane_id: picode_core::PaneId::new(),
+                provider: "openai".to_string(),
+                error: "rate limited".to_string(),
+            },
+            "test".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn delivers_subscribed_events() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header_exists(DELIVERY_HEADER))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dispatcher = WebhookDispatcher::from_config(&config(server.uri(), &["llm_error"], dir.path().join("dead.jsonl")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(dispatcher.event_types(), vec!["llm_error"]);
+
+        assert_eq!(dispatcher.dispatch(&llm_error()).await, 0);
+        let shutdown = EventEnvelope::new(Event::SystemError { error: "x".to_string(), context: HashMap::new() }, "test".to_string());
+        assert_eq!(dispatcher.dispatch(&shutdown).await, 0);
+
+        let requests = server.received_requests().await.unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(payload["type"], "llm_error");
+        assert_eq!(payload["event"]["data"]["error"], "rate limited");
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_are_retried_then_dead_lettered() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letter = dir.path().join("dead.jsonl");
+        let dispatcher = WebhookDispatcher::from_config(&config(server.uri(), &["llm_error"], dead_letter.clone()))
+            .unwrap()
+            .unwrap()
+            .with_retry_delay(Duration::from_millis(1));
+
+        assert_eq!(dispatcher.dispatch(&llm_error()).await, 1);
+        let log = std::fs::read_to_string(dead_letter).unwrap();
+        let letter: DeadLetter = serde_json::from_str(log.trim()).unwrap();
+        assert_eq!(letter.attempts, 3);
+        assert_eq!(letter.error, "HTTP 503 Service Unavailable");
+    }
+
+    #[test]
+    fn rejects_unknown_events_and_signs_bodies() {
+        for event in ["pane_resized", "hook_triggered"] {
+            let bad = config("http://localhost".to_string(), &[event], PathBuf::from("dead.jsonl"));
+            assert!(WebhookDispatcher::from_config(&bad).is_err());
+        }
+        assert!(WebhookDispatcher::from_config(&WebhooksConfig::default()).unwrap().is_none());
+
+        // RFC 4231 test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
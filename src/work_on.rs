@@ -0,0 +1,122 @@
+//! `picode work-on <issue>`
+//!
+//! One command to start on a ticket: the issue is fetched through
+//! [`crate::forge`], a branch named after it is created (or checked out when
+//! it exists), and interactive mode opens on a session named like the branch
+//! with the issue's description and acceptance criteria pinned into its
+//! context. The `[forge] template` session template is used when the
+//! workspace has it, so teams can preconfigure panes and a starter prompt.
+
+use crate::config::Config;
+use crate::error::{PiCodeError, Result};
+use crate::forge::Issue;
+use crate::history::SessionRecorder;
+use crate::interactive::InteractiveOptions;
+use picode_core::conversation::PinnedItem;
+use picode_core::ConversationLog;
+use std::path::Path;
+
+/// Run `picode work-on`
+pub async fn run(config: Config, issue: &str, template: Option<String>, base: Option<String>) -> Result<()> {
+    let root = crate::stats::workspace_root(&config)?;
+    let issue = crate::forge::fetch_issue(&config, &root, issue).await?;
+    println!("🎫 #{} {}", issue.number, issue.title);
+
+    let branch = issue.branch_name(&config.forge.branch_prefix);
+    if switch_branch(&root, &branch, base.as_deref()).await? {
+        println!("🌿 Created branch {}", branch);
+    } else {
+        println!("🌿 Switched to existing branch {}", branch);
+    }
+
+    seed_session(&config, &branch, &issue).await?;
+
+    let template = match template {
+        Some(template) => Some(template),
+        None => crate::session_template::list(&root)
+            .await?
+            .contains(&config.forge.template)
+            .then(|| config.forge.template.clone()),
+    };
+    let opts = InteractiveOptions {
+        template,
+        session: Some(branch),
+        ..InteractiveOptions::default()
+    };
+    crate::interactive::run(opts, config).await
+}
+
+/// Check out `branch`, creating it from `base` (or HEAD) when it does not
+/// exist; returns whether it was created
+async fn switch_branch(root: &Path, branch: &str, base: Option<&str>) -> Result<bool> {
+    let exists = git(root, &["rev-parse", "--verify", "--quiet", &format!("refs/heads/{}", branch)]).await.is_ok();
+    if exists {
+        git(root, &["switch", branch]).await?;
+        return Ok(false);
+    }
+    let mut args = vec!["switch", "--create", branch];
+    args.extend(base);
+    git(root, &args).await?;
+    Ok(true)
+}
+
+/// Pin the issue into the branch's session, replacing an older copy, so
+/// every request in the session carries it
+async fn seed_session(config: &Config, name: &str, issue: &Issue) -> Result<()> {
+    let mut recorder = SessionRecorder::open(config, Some(name.to_string())).await?;
+    let mut log = match recorder.load().await? {
+        Some(log) => log,
+        None => ConversationLog::new(picode_core::SessionId::new()),
+    };
+    pin_issue(&mut log, issue);
+    recorder.save(&mut log).await?;
+    recorder.flush().await
+}
+
+fn pin_issue(log: &mut ConversationLog, issue: &Issue) {
+    let label = format!("issue #{}", issue.number);
+    log.pinned.retain(|item| item.label != label);
+    log.pinned.insert(0, PinnedItem::new(label, issue.memory()));
+    log.annotations.tags.insert(format!("issue-{}", issue.number));
+}
+
+async fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(PiCodeError::Internal(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinning_replaces_the_previous_copy() {
+        let issue = Issue {
+            number: 9,
+            title: "Retry uploads".to_string(),
+            body: "## Acceptance criteria\n- retries three times\n".to_string(),
+            url: "https://github.com/o/r/issues/9".to_string(),
+            labels: Vec::new(),
+        };
+        let mut log = ConversationLog::new(picode_core::SessionId::new());
+        log.pinned.push(PinnedItem::new("src/upload.rs", "fn upload() {}"));
+        pin_issue(&mut log, &issue);
+        pin_issue(&mut log, &Issue { title: "Retry uploads with backoff".to_string(), ..issue });
+
+        assert_eq!(log.pinned.len(), 2);
+        assert_eq!(log.pinned[0].label, "issue #9");
+        assert!(log.pinned[0].content.starts_with("Issue #9: Retry uploads with backoff"));
+        assert!(log.annotations.tags.contains("issue-9"));
+    }
+}